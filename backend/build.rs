@@ -0,0 +1,34 @@
+//! Bakes the commit the binary is built from into `GIT_SHA`.
+//!
+//! A `GIT_SHA` set for the build, like the Docker build argument, wins;
+//! otherwise git is asked. Builds outside a checkout report `unknown`.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    // Rebuild after a commit or checkout
+    for path in ["../.git/HEAD", "../.git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    let sha = std::env::var("GIT_SHA")
+        .ok()
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())?;
+            String::from_utf8(output.stdout)
+                .ok()
+                .map(|sha| sha.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_SHA={sha}");
+}
@@ -0,0 +1,435 @@
+//! Daily content backups into a directory, without cron on the host.
+//!
+//! With `BACKUP_CONTENT_DIR` set, [`BackupJob`] writes the export bundle of
+//! [`crate::content_bundle`] as `content-YYYY-MM-DD.json` once a day, after
+//! `BACKUP_TIME` (UTC, default `03:00`). `BACKUP_DATABASE=true` adds a copy
+//! of the whole SQLite database as `database-YYYY-MM-DD.sqlite`, made with
+//! `VACUUM INTO`. Backups older than `BACKUP_RETENTION_DAYS` (default 14, `0`
+//! keeps them all) are deleted afterwards; other files in the directory are
+//! left alone.
+//!
+//! The job checks every few minutes whether today's backup is due, so a
+//! server that was down at the backup time catches up once it is back. A
+//! failed backup is logged and shows up in `/api/admin/jobs`; the next check
+//! tries again.
+
+use crate::{
+    content_bundle::{self, ExportOptions},
+    db::DbPool,
+    jobs::{Job, JobResult},
+    middleware::security::parse_bool,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often the job checks whether a backup is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// Time of day (UTC) after which the day's backup is written.
+    pub time: NaiveTime,
+    /// Days of backups to keep, `0` for all.
+    pub retention_days: u32,
+    /// Whether to copy the SQLite database as well.
+    pub database: bool,
+}
+
+impl BackupConfig {
+    /// `None` when `BACKUP_CONTENT_DIR` is unset, which turns backups off.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let value = |key: &str| lookup(key).filter(|raw| !raw.trim().is_empty());
+        let Some(dir) = value("BACKUP_CONTENT_DIR") else {
+            return Ok(None);
+        };
+
+        let time = match value("BACKUP_TIME") {
+            Some(raw) => NaiveTime::parse_from_str(raw.trim(), "%H:%M")
+                .map_err(|_| format!("BACKUP_TIME must be HH:MM, got '{}'", raw))?,
+            None => NaiveTime::from_hms_opt(3, 0, 0).expect("valid time"),
+        };
+        let retention_days = match value("BACKUP_RETENTION_DAYS") {
+            Some(raw) => raw.trim().parse::<u32>().map_err(|_| {
+                format!(
+                    "BACKUP_RETENTION_DAYS must be a number of days, got '{}'",
+                    raw
+                )
+            })?,
+            None => 14,
+        };
+        let database = match value("BACKUP_DATABASE") {
+            Some(raw) => parse_bool(&raw)
+                .ok_or_else(|| format!("BACKUP_DATABASE must be true or false, got '{}'", raw))?,
+            None => false,
+        };
+
+        Ok(Some(Self {
+            dir: PathBuf::from(dir.trim()),
+            time,
+            retention_days,
+            database,
+        }))
+    }
+}
+
+fn content_file_name(date: NaiveDate) -> String {
+    format!("content-{}.json", date.format("%Y-%m-%d"))
+}
+
+fn database_file_name(date: NaiveDate) -> String {
+    format!("database-{}.sqlite", date.format("%Y-%m-%d"))
+}
+
+/// The day of a file this job wrote, `None` for any other file.
+fn backup_date(file_name: &str) -> Option<NaiveDate> {
+    let date = file_name
+        .strip_prefix("content-")
+        .and_then(|rest| rest.strip_suffix(".json"))
+        .or_else(|| {
+            file_name
+                .strip_prefix("database-")
+                .and_then(|rest| rest.strip_suffix(".sqlite"))
+        })?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// What one check of the job did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BackupOutcome {
+    /// The files written, empty when no backup was due.
+    pub written: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+/// Writes today's backup if it is due and missing, then rotates.
+pub async fn run_backup(
+    pool: &DbPool,
+    config: &BackupConfig,
+    now: DateTime<Utc>,
+) -> Result<BackupOutcome> {
+    let today = now.date_naive();
+    let content_path = config.dir.join(content_file_name(today));
+    if now.time() < config.time || content_path.exists() {
+        return Ok(BackupOutcome::default());
+    }
+
+    tokio::fs::create_dir_all(&config.dir)
+        .await
+        .with_context(|| format!("Failed to create {}", config.dir.display()))?;
+    let mut written = Vec::new();
+
+    // The database copy first, so an existing content file always means the
+    // whole backup is done
+    if config.database {
+        let path = config.dir.join(database_file_name(today));
+        let partial = config
+            .dir
+            .join(format!(".{}.partial", database_file_name(today)));
+        remove_if_exists(&partial).await?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(partial.to_string_lossy().into_owned())
+            .execute(pool)
+            .await
+            .context("Failed to copy the database")?;
+        rename(&partial, &path).await?;
+        written.push(path);
+    }
+
+    let bundle = content_bundle::export_bundle(pool, &ExportOptions::default()).await?;
+    let partial = config
+        .dir
+        .join(format!(".{}.partial", content_file_name(today)));
+    tokio::fs::write(&partial, content_bundle::to_json(&bundle)?)
+        .await
+        .with_context(|| format!("Failed to write {}", partial.display()))?;
+    rename(&partial, &content_path).await?;
+    written.push(content_path);
+
+    let removed = rotate(&config.dir, today, config.retention_days).await?;
+    Ok(BackupOutcome { written, removed })
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn rename(from: &Path, to: &Path) -> Result<()> {
+    tokio::fs::rename(from, to)
+        .await
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+}
+
+/// Deletes the backups of days at least `retention_days` before `today`.
+async fn rotate(dir: &Path, today: NaiveDate, retention_days: u32) -> Result<Vec<PathBuf>> {
+    if retention_days == 0 {
+        return Ok(Vec::new());
+    }
+    let Some(oldest_kept) = today.checked_sub_days(Days::new(u64::from(retention_days) - 1)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to list {}", dir.display()))?;
+    let mut removed = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(date) = name.to_str().and_then(backup_date) else {
+            continue;
+        };
+        if date < oldest_kept && entry.file_type().await?.is_file() {
+            let path = entry.path();
+            tokio::fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed.push(path);
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// Runs [`run_backup`] on the scheduler.
+pub struct BackupJob {
+    config: BackupConfig,
+}
+
+impl BackupJob {
+    pub fn new(config: BackupConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Job for BackupJob {
+    fn name(&self) -> &'static str {
+        "content backup"
+    }
+
+    fn interval(&self) -> Duration {
+        CHECK_INTERVAL
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        let outcome = run_backup(pool, &self.config, Utc::now())
+            .await
+            .map_err(|err| format!("Content backup failed: {:#}", err))?;
+        for path in &outcome.written {
+            tracing::info!("Wrote backup {}", path.display());
+        }
+        if !outcome.removed.is_empty() {
+            tracing::info!("Removed {} old backup file(s)", outcome.removed.len());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config(dir: &Path, retention_days: u32, database: bool) -> BackupConfig {
+        BackupConfig {
+            dir: dir.to_path_buf(),
+            time: NaiveTime::from_hms_opt(3, 0, 0).unwrap(),
+            retention_days,
+            database,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_config_parses_and_skips_without_a_directory() {
+        let lookup = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(BackupConfig::parse(lookup(&[])), Ok(None));
+        assert_eq!(
+            BackupConfig::parse(lookup(&[("BACKUP_CONTENT_DIR", " "), ("BACKUP_TIME", "x")])),
+            Ok(None)
+        );
+        assert_eq!(
+            BackupConfig::parse(lookup(&[("BACKUP_CONTENT_DIR", "/var/backups/cms")])),
+            Ok(Some(config(Path::new("/var/backups/cms"), 14, false)))
+        );
+        let parsed = BackupConfig::parse(lookup(&[
+            ("BACKUP_CONTENT_DIR", "/var/backups/cms"),
+            ("BACKUP_TIME", "23:30"),
+            ("BACKUP_RETENTION_DAYS", "0"),
+            ("BACKUP_DATABASE", "true"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(parsed.time, NaiveTime::from_hms_opt(23, 30, 0).unwrap());
+        assert_eq!(parsed.retention_days, 0);
+        assert!(parsed.database);
+
+        assert!(BackupConfig::parse(lookup(&[
+            ("BACKUP_CONTENT_DIR", "/var/backups/cms"),
+            ("BACKUP_TIME", "3 Uhr"),
+        ]))
+        .is_err());
+        assert!(BackupConfig::parse(lookup(&[
+            ("BACKUP_CONTENT_DIR", "/var/backups/cms"),
+            ("BACKUP_RETENTION_DAYS", "-1"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_names_carry_the_date() {
+        assert_eq!(content_file_name(date(7)), "content-2026-03-07.json");
+        assert_eq!(database_file_name(date(7)), "database-2026-03-07.sqlite");
+        assert_eq!(backup_date("content-2026-03-07.json"), Some(date(7)));
+        assert_eq!(backup_date("database-2026-03-07.sqlite"), Some(date(7)));
+        assert_eq!(backup_date("content-2026-03-07.json.bak"), None);
+        assert_eq!(backup_date(".content-2026-03-07.json.partial"), None);
+        assert_eq!(backup_date("content-latest.json"), None);
+        assert_eq!(backup_date("notes.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_deletes_only_old_backups() {
+        let dir = std::env::temp_dir().join(format!("backups-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for day in [1, 2, 8, 9, 10] {
+            std::fs::write(dir.join(content_file_name(date(day))), "{}").unwrap();
+        }
+        std::fs::write(dir.join(database_file_name(date(2))), "").unwrap();
+        std::fs::write(dir.join("content-latest.json"), "{}").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        // A week up to and including the 10th starts on the 4th
+        let removed = rotate(&dir, date(10), 7).await.unwrap();
+        assert_eq!(
+            removed,
+            [
+                dir.join("content-2026-03-01.json"),
+                dir.join("content-2026-03-02.json"),
+                dir.join("database-2026-03-02.sqlite"),
+            ]
+        );
+        assert_eq!(
+            file_names(&dir),
+            [
+                "content-2026-03-08.json",
+                "content-2026-03-09.json",
+                "content-2026-03-10.json",
+                "content-latest.json",
+                "notes.txt",
+            ]
+        );
+
+        assert!(rotate(&dir, date(31), 0).await.unwrap().is_empty());
+        assert_eq!(file_names(&dir).len(), 5);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_runs_once_a_day_after_its_time() {
+        let dir = std::env::temp_dir().join(format!("backups-{}", uuid::Uuid::new_v4()));
+        // `VACUUM INTO` writes nothing from an in-memory database
+        let pool = crate::db::pool::create_pool_with_url(&format!(
+            "sqlite:{}",
+            dir.join("cms.db").display()
+        ))
+        .await
+        .unwrap();
+        let config = config(&dir.join("nested"), 2, true);
+        let at = |day, hour| Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap();
+
+        let outcome = run_backup(&pool, &config, at(9, 2)).await.unwrap();
+        assert_eq!(outcome, BackupOutcome::default());
+        assert!(!config.dir.exists());
+
+        let outcome = run_backup(&pool, &config, at(9, 4)).await.unwrap();
+        assert_eq!(
+            outcome.written,
+            [
+                config.dir.join("database-2026-03-09.sqlite"),
+                config.dir.join("content-2026-03-09.json"),
+            ]
+        );
+        let written = std::fs::read_to_string(&outcome.written[1]).unwrap();
+        let expected = content_bundle::export_bundle(&pool, &ExportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(written, content_bundle::to_json(&expected).unwrap());
+        assert!(std::fs::metadata(&outcome.written[0]).unwrap().len() > 0);
+
+        // Already done for the day
+        let outcome = run_backup(&pool, &config, at(9, 5)).await.unwrap();
+        assert_eq!(outcome, BackupOutcome::default());
+
+        run_backup(&pool, &config, at(10, 4)).await.unwrap();
+        let outcome = run_backup(&pool, &config, at(11, 4)).await.unwrap();
+        assert_eq!(
+            outcome.removed,
+            [
+                config.dir.join("content-2026-03-09.json"),
+                config.dir.join("database-2026-03-09.sqlite"),
+            ]
+        );
+        assert_eq!(
+            file_names(&config.dir),
+            [
+                "content-2026-03-10.json",
+                "content-2026-03-11.json",
+                "database-2026-03-10.sqlite",
+                "database-2026-03-11.sqlite",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_backups_are_job_errors() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let file = std::env::temp_dir().join(format!("backups-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, "").unwrap();
+        let job = BackupJob::new(BackupConfig {
+            time: NaiveTime::MIN,
+            ..config(&file, 14, false)
+        });
+
+        let err = job.run(&pool).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Content backup failed: Failed to create"),
+            "{err}"
+        );
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -8,12 +8,58 @@
  * Usage:
  * ```bash
  * cargo run --bin import_content -- input.json
+ * cargo run --bin import_content -- --dry-run input.json
+ * cargo run --bin import_content -- --diff input.json > report.json
+ * cargo run --bin import_content -- --skip-invalid input.json
+ * cargo run --bin import_content -- --only pages,posts --strategy skip-existing input.json
+ * cargo run --bin import_content -- --from-markdown ../content/markdown
+ * cargo run --bin import_content -- --sha256 <hex> https://ci.example/bundle.json
+ * cargo run --bin import_content -- --json-log --continue-on-error input.json > import.log
  * ```
  *
+ * Options:
+ * - `--dry-run`: Compare the bundle with the database and print what an import
+ *   would create, update or leave unchanged, without writing anything
+ * - `--diff`: Like `--dry-run`, but print the report as JSON
+ * - `--skip-invalid`: Leave out the entries that break a rule of the API and
+ *   import the rest, listing what was skipped
+ * - `--only <list>`: Import only these collections (comma-separated:
+ *   site_content, pages, posts, tutorials, tutorial_topics, users, comments)
+ *   and leave the others alone
+ * - `--strategy <name>`: What to do with entries that already exist:
+ *   `overwrite` (default) replaces them, `skip-existing` leaves them as they
+ *   are, `fail-on-conflict` imports nothing and lists the entries that would
+ *   change
+ * - `--from-markdown <dir>`: Import the posts of a Markdown directory instead
+ *   of a bundle, see below
+ * - `--sha256 <hex>`: Refuse a bundle whose SHA-256 checksum differs, checked
+ *   before it is parsed
+ * - `--continue-on-error`: Import the rows the database accepts and list the
+ *   ones it refuses, instead of importing nothing when a row fails
+ * - `--json-log`: Print what the import does as one JSON object per line:
+ *   `progress` every 100 rows of a collection, `row_failed` with the
+ *   collection, key and, where known, field of a refused row, `collection`
+ *   when one is written, and `finished` with the report (or `invalid` or
+ *   `error`)
+ *
+ * The input may be an `https://` URL instead of a file; the bundle is
+ * downloaded within `IMPORT_FETCH_TIMEOUT_SECONDS` and
+ * `IMPORT_FETCH_MAX_BYTES`, following at most three redirects, and never
+ * with credentials.
+ *
+ * A bundle exported by a newer release with a higher `format_version` is
+ * refused; fields this build does not know are ignored, with a warning
+ * naming each.
+ *
+ * Entries are checked with the same rules as the HTTP API. Unless skipping,
+ * a single violation imports nothing and every violation is reported with
+ * its collection and index, and the utility exits non-zero, in every mode.
+ *
  * Features:
  * - Imports site content (hero sections, headers, footers)
  * - Imports site pages with navigation and publication settings
  * - Imports blog posts with markdown content
+ * - Imports tutorials and their topic index
  * - Preserves original IDs and timestamps when available
  * - Validates content structure and data integrity
  * - Runs all operations in database transactions
@@ -24,6 +70,23 @@
  * - site_content: Array of content section objects
  * - pages: Array of page objects with hero/layout data
  * - posts: Array of blog post objects with markdown content
+ * - tutorials, tutorial_topics: Optional; a bundle with either replaces the
+ *   topics of the tutorials it names, except those `--strategy
+ *   skip-existing` leaves alone. Topics listed for a tutorial become its
+ *   `topics`
+ * - users, comments: Optional, written by `export_content --include-users
+ *   --include-comments`. Accounts keep their id and password hash; a username
+ *   that already exists is refused unless `--strategy overwrite`. Comments
+ *   whose tutorial or post does not exist are skipped and listed
+ *
+ * After writing, the topic index and search rows of every tutorial touched are
+ * rebuilt, and the word counts of the posts written are recomputed.
+ *
+ * Markdown Format:
+ * `<dir>/pages/<page-slug>/<post-slug>.md` files with YAML frontmatter
+ * (title, excerpt, published, published_at, order, tags). Posts are matched
+ * by page slug and post slug; missing pages are created unpublished and
+ * outside the navigation. Frontmatter errors name the file and line.
  *
  * Security:
  * - Validates file paths to prevent directory traversal
@@ -37,219 +100,323 @@
  * - Database connection or transaction errors
  * - Data validation failures
  */
-use std::{env, fs, path::Path};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Context, Result};
-use serde::Deserialize;
-use serde_json::Value;
-use sqlx::{Sqlite, Transaction};
 
+use rust_blog_backend::content_bundle::{
+    self, ChangeAction, Collection, ImportBundle, ImportEvent, ImportOptions, ImportReport,
+    PROGRESS_INTERVAL,
+};
+use rust_blog_backend::content_fetch;
+use rust_blog_backend::content_markdown::{self, MarkdownPost};
 use rust_blog_backend::db;
-
-#[derive(Debug, Deserialize)]
-struct SiteContentImport {
-    section: String,
-
-    content: Value,
-
-    #[serde(default)]
-    updated_at: Option<String>,
+use serde_json::json;
+use sqlx::SqliteConnection;
+
+#[derive(Debug, Default)]
+struct Args {
+    input: Option<String>,
+    dry_run: bool,
+    diff: bool,
+    from_markdown: Option<PathBuf>,
+    sha256: Option<String>,
+    json_log: bool,
+    options: ImportOptions,
 }
 
-#[derive(Debug, Deserialize)]
-struct SitePageImport {
-    id: String,
-
-    slug: String,
-
-    title: String,
-
-    description: String,
-
-    nav_label: Option<String>,
-
-    show_in_nav: bool,
-
-    order_index: i64,
-
-    is_published: bool,
-
-    hero: Value,
-
-    layout: Value,
-
-    #[serde(default)]
-    created_at: Option<String>,
-
-    #[serde(default)]
-    updated_at: Option<String>,
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => parsed.dry_run = true,
+            "--diff" => {
+                parsed.dry_run = true;
+                parsed.diff = true;
+            }
+            "--skip-invalid" => parsed.options.skip_invalid = true,
+            "--continue-on-error" => parsed.options.continue_on_error = true,
+            "--json-log" => parsed.json_log = true,
+            "--only" => {
+                let list = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--only expects a list of collections"))?;
+                parsed.options.only = Collection::parse_list(&list).map_err(|err| anyhow!(err))?;
+            }
+            "--from-markdown" => {
+                let dir = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--from-markdown expects a directory"))?;
+                parsed.from_markdown = Some(PathBuf::from(dir));
+            }
+            "--sha256" => {
+                let checksum = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--sha256 expects a checksum"))?;
+                parsed.sha256 = Some(content_fetch::parse_sha256(&checksum)?);
+            }
+            "--strategy" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--strategy expects a strategy"))?;
+                parsed.options.strategy = name.parse().map_err(|err: String| anyhow!(err))?;
+            }
+            flag if flag.starts_with('-') => return Err(anyhow!("Unknown argument '{}'", flag)),
+            _ if parsed.input.is_some() => return Err(anyhow!("Expected a single input file")),
+            _ => parsed.input = Some(arg),
+        }
+    }
+    if parsed.from_markdown.is_some() && parsed.input.is_some() {
+        return Err(anyhow!(
+            "Expected either an input file or --from-markdown, not both"
+        ));
+    }
+    if parsed.from_markdown.is_some() && parsed.sha256.is_some() {
+        return Err(anyhow!("--sha256 checks a bundle, not a Markdown directory"));
+    }
+    if parsed.json_log && parsed.dry_run {
+        return Err(anyhow!(
+            "--json-log logs an import; --diff prints a dry run as JSON"
+        ));
+    }
+    Ok(parsed)
 }
 
-#[derive(Debug, Deserialize)]
-struct SitePostImport {
-    id: String,
-
-    page_id: String,
-
-    title: String,
-
-    slug: String,
-
-    excerpt: String,
-
-    content_markdown: String,
-
-    is_published: bool,
-
-    published_at: Option<String>,
-
-    order_index: i64,
+/// What is imported: a bundle file, or the posts of a Markdown directory.
+enum Source {
+    Bundle(ImportBundle),
+    Markdown(Vec<MarkdownPost>),
+}
 
-    #[serde(default)]
-    created_at: Option<String>,
+impl Source {
+    async fn bundle(&self, conn: &mut SqliteConnection) -> Result<ImportBundle> {
+        match self {
+            Source::Bundle(bundle) => Ok(bundle.clone()),
+            Source::Markdown(posts) => content_markdown::to_bundle(conn, posts).await,
+        }
+    }
+}
 
-    #[serde(default)]
-    updated_at: Option<String>,
+/// Shows an event of the import: as a JSON line with `--json-log`, else
+/// progress through large collections and refused rows.
+fn log_event(json_log: bool, event: &ImportEvent) {
+    if json_log {
+        match serde_json::to_string(event) {
+            Ok(line) => println!("{}", line),
+            Err(err) => eprintln!("Failed to serialize import event: {}", err),
+        }
+        return;
+    }
+    match event {
+        ImportEvent::Progress {
+            collection,
+            processed,
+            total,
+        } if *total > PROGRESS_INTERVAL => {
+            eprintln!("  {}: {}/{}", collection.as_str(), processed, total)
+        }
+        ImportEvent::RowFailed(failure) => eprintln!("  failed: {}", failure),
+        _ => {}
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct ImportBundle {
-    site_content: Vec<SiteContentImport>,
+/// Keeps stdout to the JSON lines with `--json-log`.
+fn print_info(json_log: bool, message: &str) {
+    if json_log {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
 
-    pages: Vec<SitePageImport>,
+fn print_json(value: serde_json::Value) {
+    println!("{}", value);
+}
 
-    posts: Vec<SitePostImport>,
+fn print_report(report: &ImportReport) {
+    for collection in &report.collections {
+        println!(
+            "  {}: {} create, {} update, {} unchanged{}{}",
+            collection.collection.as_str(),
+            collection.create,
+            collection.update,
+            collection.unchanged,
+            if collection.skip > 0 {
+                format!(", {} skipped", collection.skip)
+            } else {
+                String::new()
+            },
+            if collection.delete > 0 {
+                format!(", {} delete", collection.delete)
+            } else {
+                String::new()
+            }
+        );
+        for change in &collection.changes {
+            match change.action {
+                ChangeAction::Create => println!("    + {}", change.key),
+                ChangeAction::Delete => println!("    - {}", change.key),
+                ChangeAction::Update => println!(
+                    "    ~ {} ({})",
+                    change.key,
+                    change.changed_fields.join(", ")
+                ),
+                ChangeAction::Skip => println!("    = {} (exists, skipped)", change.key),
+                ChangeAction::Unchanged => {}
+            }
+        }
+    }
+    if let Some(rebuilt) = &report.rebuilt {
+        println!(
+            "  rebuilt: {} tutorial(s) with {} topic(s), {} post(s)",
+            rebuilt.tutorials, rebuilt.topics, rebuilt.posts
+        );
+    }
+    for warning in &report.warnings {
+        eprintln!("  warning: {}", warning);
+    }
+    for skipped in &report.skipped {
+        eprintln!("  skipped: {}", skipped);
+    }
+    for failed in &report.failed {
+        eprintln!("  failed: {}", failed);
+    }
+    for error in &report.errors {
+        eprintln!("  error: {}", error);
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv::dotenv().ok();
 
-    let args: Vec<String> = env::args().collect();
-
-    let input_path = args
-        .get(1)
-        .map(String::as_str)
-        .unwrap_or("../content/site_content.json");
-    let path = Path::new(input_path);
-
-    if !path.exists() {
-        return Err(anyhow!("Input file '{}' does not exist", path.display()));
-    }
-
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read input file {}", path.display()))?;
-
-    let bundle: ImportBundle = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse JSON from {}", path.display()))?;
+    let args = parse_args(env::args().skip(1))?;
+
+    let mut options = args.options.clone();
+    let mut warnings = Vec::new();
+    let (source, path) = match &args.from_markdown {
+        Some(dir) => {
+            let posts = content_markdown::read_posts(dir)?;
+            let read = format!("Read {} post(s) from {}", posts.len(), dir.display());
+            print_info(args.json_log, &read);
+            let tagged = posts.iter().filter(|post| !post.tags.is_empty()).count();
+            if tagged > 0 {
+                eprintln!(
+                    "  note: the tags of {} post(s) are not imported, posts have no tags",
+                    tagged
+                );
+            }
+            options
+                .only
+                .retain(|collection| matches!(collection, Collection::Pages | Collection::Posts));
+            (Source::Markdown(posts), dir.clone())
+        }
+        None => {
+            let input_path = args
+                .input
+                .as_deref()
+                .unwrap_or("../content/site_content.json");
+            let path = Path::new(input_path);
+
+            let content = if content_fetch::is_url(input_path) {
+                let content = content_fetch::fetch_bundle(
+                    input_path,
+                    args.sha256.as_deref(),
+                    content_fetch::fetch_config(),
+                )
+                .await?;
+                let fetched = format!("Fetched {} bytes from {}", content.len(), input_path);
+                print_info(args.json_log, &fetched);
+                content
+            } else {
+                if !path.exists() {
+                    return Err(anyhow!("Input file '{}' does not exist", path.display()));
+                }
+                let content = fs::read(path)
+                    .with_context(|| format!("Failed to read input file {}", path.display()))?;
+                if let Some(expected) = &args.sha256 {
+                    content_fetch::verify_sha256(&content, expected)?;
+                }
+                content
+            };
+
+            let (bundle, unknown) = content_bundle::parse_bundle(&content)
+                .with_context(|| format!("Failed to read the bundle {}", path.display()))?;
+            warnings = unknown;
+            (Source::Bundle(bundle), path.to_path_buf())
+        }
+    };
 
     let pool = db::create_pool()
         .await
         .context("Failed to connect to database. Is DATABASE_URL set correctly?")?;
 
-    let mut tx = pool.begin().await.context("Failed to start transaction")?;
-
-    import_site_content(&mut tx, &bundle.site_content).await?;
-    import_site_pages(&mut tx, &bundle.pages).await?;
-    import_site_posts(&mut tx, &bundle.posts).await?;
-
-    tx.commit().await.context("Failed to commit transaction")?;
-
-    println!(
-        "Import completed:\n  site_content: {}\n  pages: {}\n  posts: {}\n  <- {}",
-        bundle.site_content.len(),
-        bundle.pages.len(),
-        bundle.posts.len(),
-        path.display()
-    );
-
-    Ok(())
-}
-
-async fn import_site_content(
-    tx: &mut Transaction<'_, Sqlite>,
-    items: &[SiteContentImport],
-) -> Result<()> {
-    for item in items {
-        let serialized = serde_json::to_string(&item.content)
-            .context("Failed to serialize site_content entry")?;
-
-        sqlx::query(
-            "INSERT INTO site_content (section, content_json, updated_at) VALUES (?, ?, COALESCE(?, CURRENT_TIMESTAMP)) \
-             ON CONFLICT(section) DO UPDATE SET content_json = excluded.content_json, updated_at = COALESCE(excluded.updated_at, CURRENT_TIMESTAMP)",
-        )
-        .bind(&item.section)
-        .bind(&serialized)
-        .bind(&item.updated_at)
-        .execute(&mut **tx)
-        .await
-        .with_context(|| format!("Failed to upsert site_content section '{}'", item.section))?;
+    if args.dry_run {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Failed to acquire a database connection")?;
+        let bundle = source.bundle(&mut conn).await?;
+        let mut report = content_bundle::plan_import(&mut conn, &bundle, &options)
+            .await?
+            .report;
+        report.warnings = warnings;
+        if args.diff {
+            let json = serde_json::to_string_pretty(&report)
+                .context("Failed to serialize import report")?;
+            println!("{}", json);
+        } else {
+            println!("Dry run, nothing written:");
+            print_report(&report);
+        }
+        if !report.is_valid() {
+            return Err(anyhow!(
+                "The bundle is invalid: {} error(s)",
+                report.errors.len()
+            ));
+        }
+        return Ok(());
     }
 
-    Ok(())
-}
-
-async fn import_site_pages(
-    tx: &mut Transaction<'_, Sqlite>,
-    items: &[SitePageImport],
-) -> Result<()> {
-    for item in items {
-        let hero_serialized =
-            serde_json::to_string(&item.hero).context("Failed to serialize page hero JSON")?;
-        let layout_serialized =
-            serde_json::to_string(&item.layout).context("Failed to serialize page layout JSON")?;
-
-        sqlx::query(
-            "INSERT INTO site_pages (id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at) \
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP)) \
-             ON CONFLICT(id) DO UPDATE SET slug = excluded.slug, title = excluded.title, description = excluded.description, nav_label = excluded.nav_label, show_in_nav = excluded.show_in_nav, order_index = excluded.order_index, is_published = excluded.is_published, hero_json = excluded.hero_json, layout_json = excluded.layout_json, updated_at = COALESCE(excluded.updated_at, CURRENT_TIMESTAMP)",
-        )
-        .bind(&item.id)
-        .bind(&item.slug)
-        .bind(&item.title)
-        .bind(&item.description)
-        .bind(&item.nav_label)
+    let mut tx = pool.begin().await.context("Failed to start transaction")?;
 
-        .bind(if item.show_in_nav { 1 } else { 0 })
-        .bind(item.order_index)
-        .bind(if item.is_published { 1 } else { 0 })
-        .bind(&hero_serialized)
-        .bind(&layout_serialized)
-        .bind(&item.created_at)
-        .bind(&item.updated_at)
-        .execute(&mut **tx)
-        .await
-        .with_context(|| format!("Failed to upsert site_page '{}'", item.slug))?;
+    let bundle = source.bundle(&mut tx).await?;
+    let mut plan = content_bundle::plan_import(&mut tx, &bundle, &options).await?;
+    plan.report.warnings = warnings;
+    if !plan.report.is_valid() {
+        if args.json_log {
+            print_json(json!({ "event": "invalid", "errors": plan.report.errors }));
+        }
+        return Err(anyhow!(
+            "The bundle is invalid:\n  {}",
+            plan.report.errors.join("\n  ")
+        ));
     }
+    let json_log = args.json_log;
+    let report =
+        match content_bundle::apply_plan_with(&mut tx, plan, &mut |event| log_event(json_log, event))
+            .await
+        {
+            Ok(report) => report,
+            Err(err) => {
+                if json_log {
+                    print_json(json!({ "event": "error", "error": format!("{:#}", err) }));
+                }
+                return Err(err);
+            }
+        };
 
-    Ok(())
-}
-
-async fn import_site_posts(
-    tx: &mut Transaction<'_, Sqlite>,
-    items: &[SitePostImport],
-) -> Result<()> {
-    for item in items {
-        sqlx::query(
-            "INSERT INTO site_posts (id, page_id, title, slug, excerpt, content_markdown, is_published, published_at, order_index, created_at, updated_at) \
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP)) \
-             ON CONFLICT(id) DO UPDATE SET page_id = excluded.page_id, title = excluded.title, slug = excluded.slug, excerpt = excluded.excerpt, content_markdown = excluded.content_markdown, is_published = excluded.is_published, published_at = excluded.published_at, order_index = excluded.order_index, updated_at = COALESCE(excluded.updated_at, CURRENT_TIMESTAMP)",
-        )
-        .bind(&item.id)
-        .bind(&item.page_id)
-        .bind(&item.title)
-        .bind(&item.slug)
-        .bind(&item.excerpt)
-        .bind(&item.content_markdown)
+    tx.commit().await.context("Failed to commit transaction")?;
 
-        .bind(if item.is_published { 1 } else { 0 })
-        .bind(&item.published_at)
-        .bind(item.order_index)
-        .bind(&item.created_at)
-        .bind(&item.updated_at)
-        .execute(&mut **tx)
-        .await
-        .with_context(|| format!("Failed to upsert site_post '{}'", item.slug))?;
+    if json_log {
+        print_json(json!({ "event": "finished", "report": report }));
+    } else {
+        println!("Import completed from {}:", path.display());
+        print_report(&report);
     }
 
     Ok(())
@@ -0,0 +1,3367 @@
+//! Content bundles: the site's content as one JSON document.
+//!
+//! `export_content` writes an [`ImportBundle`] from a live database and
+//! `import_content` reads one back. A bundle holds the `site_content`
+//! sections, the pages and posts, and the tutorials with their topics; each
+//! collection is ordered by section or id, so exports of the same content are
+//! byte-identical and diff cleanly. Derived data (post word counts, the
+//! search index) is left out; an import rebuilds it for what it writes, see
+//! [`crate::derived`].
+//!
+//! An import first plans: it compares every entry with the row of the same
+//! section or id and reports it as created, updated (with the fields that
+//! change) or unchanged, and checks that the bundle can be imported at all.
+//! [`plan_import`] stops there, for dry runs; [`import_bundle`] then upserts
+//! the created and updated entries, restoring the timestamps of the bundle,
+//! `created_at` included.
+//!
+//! Planning also holds every entry to the rules of the HTTP API (see
+//! [`crate::validation`]) and normalizes it as the API would store it. An
+//! entry breaking a rule keeps the whole bundle from being imported, unless
+//! [`ImportOptions::skip_invalid`] leaves it out, along with the posts and
+//! topics of a page or tutorial left out.
+//!
+//! A row the database still refuses, e.g. a page whose slug another page
+//! has, fails the import and nothing is written. With
+//! [`ImportOptions::continue_on_error`] the row is reported as failed instead
+//! and the others are written. [`apply_plan_with`] reports each failure and
+//! the progress through every collection as an [`ImportEvent`].
+//!
+//! [`ImportOptions`] narrow an import to some collections and pick what
+//! happens to entries that already exist ([`ImportStrategy`]). A bundle with
+//! tutorials or topics replaces the topics of the tutorials it names. Where
+//! it lists topics for a tutorial, they become the tutorial's `topics`;
+//! otherwise the tutorial's `topics` are indexed, so the two always agree.
+//!
+//! A bundle written before a field existed still imports: missing fields take
+//! the column's default. That includes `format_version`, which exports stamp
+//! with [`FORMAT_VERSION`]; bundles without it are version 0, whose missing
+//! `visibility` is `public`, `allow_comments` true, tutorial `version` 1 and
+//! other missing fields empty. [`parse_bundle`] refuses a bundle of a newer
+//! version and warns about fields it does not know.
+//!
+//! For moving a whole instance, a bundle can also carry the user accounts and
+//! the comments, which exports only include when asked to. Accounts keep their
+//! id and password hash (never a password); a username the database already
+//! has is only replaced by an `overwrite` import. A comment whose tutorial or
+//! post is missing is left out and reported as skipped.
+
+use crate::db::DbPool;
+use crate::derived::{self, RebuildReport, RebuildScope};
+use crate::repositories;
+use crate::utils::timestamps::parse_timestamp;
+use crate::validation::{self, pages, posts, tutorials, users};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, Sqlite, SqliteConnection, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Recorded as `updated_by` on imported content sections.
+pub const IMPORT_AUTHOR: &str = "import";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteContentImport {
+    pub section: String,
+    pub content: Value,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SitePageImport {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub nav_label: Option<String>,
+    pub show_in_nav: bool,
+    pub order_index: i64,
+    pub is_published: bool,
+    #[serde(default = "default_visibility")]
+    pub visibility: String,
+    pub hero: Value,
+    pub layout: Value,
+    #[serde(default)]
+    pub meta_title: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SitePostImport {
+    pub id: String,
+    pub page_id: String,
+    pub title: String,
+    pub slug: String,
+    pub excerpt: String,
+    #[serde(default)]
+    pub excerpt_auto: bool,
+    pub content_markdown: String,
+    pub is_published: bool,
+    #[serde(default = "default_allow_comments")]
+    pub allow_comments: bool,
+    pub published_at: Option<String>,
+    pub order_index: i64,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TutorialImport {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub icon: String,
+    pub color: String,
+    pub topics: Vec<String>,
+    pub content: String,
+    #[serde(default = "default_version")]
+    pub version: i64,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TutorialTopicImport {
+    pub tutorial_id: String,
+    pub topic: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserImport {
+    pub id: i64,
+    pub username: String,
+    /// The bcrypt hash, copied as is.
+    pub password_hash: String,
+    pub role: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommentImport {
+    pub id: String,
+    /// Set for a comment on a tutorial, `post_id` for one on a post.
+    pub tutorial_id: Option<String>,
+    pub post_id: Option<String>,
+    pub author: String,
+    /// HTML-escaped, as the API stores it.
+    pub content: String,
+    #[serde(default)]
+    pub votes: i64,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// The [`ImportBundle::format_version`] exports are stamped with.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportBundle {
+    /// Raised when the bundle changes in a way an older importer would
+    /// misread; 0 for bundles written before it was stamped. A new field
+    /// with a default does not raise it, an older importer warns about it.
+    #[serde(default)]
+    pub format_version: u32,
+    pub site_content: Vec<SiteContentImport>,
+    pub pages: Vec<SitePageImport>,
+    pub posts: Vec<SitePostImport>,
+    #[serde(default)]
+    pub tutorials: Vec<TutorialImport>,
+    #[serde(default)]
+    pub tutorial_topics: Vec<TutorialTopicImport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<UserImport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<CommentImport>,
+}
+
+fn default_visibility() -> String {
+    crate::models::PAGE_VISIBILITY_PUBLIC.to_string()
+}
+
+fn default_allow_comments() -> bool {
+    true
+}
+
+fn default_version() -> i64 {
+    1
+}
+
+/// Reads a bundle from JSON, with a warning for every field the bundle has
+/// but this build does not know, which the import ignores.
+///
+/// A bundle of a newer [`FORMAT_VERSION`] is refused before it is read, so
+/// an outdated importer says so rather than failing on a field it does not
+/// know the type of.
+pub fn parse_bundle(data: &[u8]) -> Result<(ImportBundle, Vec<String>)> {
+    #[derive(Deserialize)]
+    struct Header {
+        #[serde(default)]
+        format_version: u32,
+    }
+
+    let header: Header = serde_json::from_slice(data).context("Invalid bundle")?;
+    if header.format_version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "The bundle has format version {}, but this build reads up to version {}; \
+             import it with a newer release",
+            header.format_version,
+            FORMAT_VERSION
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_slice(data);
+    let bundle = serde_ignored::deserialize(&mut deserializer, |path| {
+        warnings.push(format!("{}: unknown field, ignored", field_path(&path)))
+    })
+    .context("Invalid bundle")?;
+    Ok((bundle, warnings))
+}
+
+/// Writes an ignored field as `pages[2].tags`, like the errors of a plan.
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", field_path(parent), index),
+        Path::Map { parent, key } => match field_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => field_path(parent),
+    }
+}
+
+/// A collection of the bundle, named as its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collection {
+    SiteContent,
+    Pages,
+    Posts,
+    Tutorials,
+    TutorialTopics,
+    Users,
+    Comments,
+}
+
+impl Collection {
+    pub const ALL: [Collection; 7] = [
+        Collection::SiteContent,
+        Collection::Pages,
+        Collection::Posts,
+        Collection::Tutorials,
+        Collection::TutorialTopics,
+        Collection::Users,
+        Collection::Comments,
+    ];
+
+    /// The collections an export includes unless asked for more.
+    pub const CONTENT: [Collection; 5] = [
+        Collection::SiteContent,
+        Collection::Pages,
+        Collection::Posts,
+        Collection::Tutorials,
+        Collection::TutorialTopics,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Collection::SiteContent => "site_content",
+            Collection::Pages => "pages",
+            Collection::Posts => "posts",
+            Collection::Tutorials => "tutorials",
+            Collection::TutorialTopics => "tutorial_topics",
+            Collection::Users => "users",
+            Collection::Comments => "comments",
+        }
+    }
+
+    /// A comma-separated list such as `pages,posts`.
+    pub fn parse_list(raw: &str) -> Result<Vec<Collection>, String> {
+        let mut collections = Vec::new();
+        for name in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let collection = name.parse()?;
+            if !collections.contains(&collection) {
+                collections.push(collection);
+            }
+        }
+        if collections.is_empty() {
+            return Err("Expected at least one collection".to_string());
+        }
+        Ok(collections)
+    }
+}
+
+impl FromStr for Collection {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Collection::ALL
+            .into_iter()
+            .find(|collection| collection.as_str() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Collection::ALL.iter().map(|c| c.as_str()).collect();
+                format!(
+                    "Unknown collection '{}', expected one of {}",
+                    name,
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// The collections to fill; the others are exported empty, or left out
+    /// for users and comments. [`Collection::CONTENT`] by default.
+    pub only: Vec<Collection>,
+    /// Leave out unpublished and deleted pages, posts that are unpublished
+    /// or belong to such a page, and the comments on those posts.
+    pub published_only: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            only: Collection::CONTENT.to_vec(),
+            published_only: false,
+        }
+    }
+}
+
+impl ExportOptions {
+    fn includes(&self, collection: Collection) -> bool {
+        self.only.contains(&collection)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct SiteContentRow {
+    section: String,
+    content_json: String,
+    updated_at: String,
+}
+
+#[derive(Debug, FromRow)]
+struct SitePageRow {
+    id: String,
+    slug: String,
+    title: String,
+    description: String,
+    nav_label: Option<String>,
+    show_in_nav: bool,
+    order_index: i64,
+    is_published: bool,
+    visibility: String,
+    hero_json: String,
+    layout_json: String,
+    meta_title: Option<String>,
+    meta_description: Option<String>,
+    og_image: Option<String>,
+    created_at: String,
+    updated_at: String,
+    deleted_at: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct SitePostRow {
+    id: String,
+    page_id: String,
+    title: String,
+    slug: String,
+    excerpt: Option<String>,
+    excerpt_auto: bool,
+    content_markdown: String,
+    is_published: bool,
+    allow_comments: bool,
+    published_at: Option<String>,
+    order_index: i64,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, FromRow)]
+struct TutorialRow {
+    id: String,
+    title: String,
+    description: String,
+    icon: String,
+    color: String,
+    topics: String,
+    content: String,
+    version: i64,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, FromRow)]
+struct TutorialTopicRow {
+    tutorial_id: String,
+    topic: String,
+}
+
+#[derive(Debug, FromRow)]
+struct UserRow {
+    id: i64,
+    username: String,
+    password_hash: String,
+    role: String,
+    created_at: String,
+}
+
+#[derive(Debug, FromRow)]
+struct CommentRow {
+    id: String,
+    tutorial_id: Option<String>,
+    post_id: Option<String>,
+    author: String,
+    content: String,
+    votes: i64,
+    is_admin: bool,
+    created_at: String,
+}
+
+/// Reads the bundle `options` ask for.
+pub async fn export_bundle(pool: &DbPool, options: &ExportOptions) -> Result<ImportBundle> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire a database connection")?;
+    read_bundle(&mut conn, options).await
+}
+
+pub(crate) async fn read_bundle(
+    conn: &mut SqliteConnection,
+    options: &ExportOptions,
+) -> Result<ImportBundle> {
+    let mut bundle = ImportBundle {
+        format_version: FORMAT_VERSION,
+        ..ImportBundle::default()
+    };
+
+    if options.includes(Collection::SiteContent) {
+        let rows = sqlx::query_as::<_, SiteContentRow>(
+            "SELECT section, content_json, updated_at FROM site_content ORDER BY section",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load site_content entries")?;
+        bundle.site_content = rows
+            .into_iter()
+            .map(|row| {
+                let content = serde_json::from_str(&row.content_json).with_context(|| {
+                    format!("Failed to parse JSON for section '{}'", row.section)
+                })?;
+                Ok(SiteContentImport {
+                    section: row.section,
+                    content,
+                    updated_at: Some(row.updated_at),
+                })
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    if options.includes(Collection::Pages) {
+        let filter = if options.published_only {
+            "WHERE is_published = 1 AND deleted_at IS NULL"
+        } else {
+            ""
+        };
+        let rows = sqlx::query_as::<_, SitePageRow>(&format!(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at \
+             FROM site_pages {filter} ORDER BY id"
+        ))
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load site_pages entries")?;
+        bundle.pages = rows
+            .into_iter()
+            .map(|row| {
+                let hero = serde_json::from_str(&row.hero_json).with_context(|| {
+                    format!("Failed to parse hero JSON for page '{}'", row.slug)
+                })?;
+                let layout = serde_json::from_str(&row.layout_json).with_context(|| {
+                    format!("Failed to parse layout JSON for page '{}'", row.slug)
+                })?;
+                Ok(SitePageImport {
+                    id: row.id,
+                    slug: row.slug,
+                    title: row.title,
+                    description: row.description,
+                    nav_label: row.nav_label,
+                    show_in_nav: row.show_in_nav,
+                    order_index: row.order_index,
+                    is_published: row.is_published,
+                    visibility: row.visibility,
+                    hero,
+                    layout,
+                    meta_title: row.meta_title,
+                    meta_description: row.meta_description,
+                    og_image: row.og_image,
+                    created_at: Some(row.created_at),
+                    updated_at: Some(row.updated_at),
+                    deleted_at: row.deleted_at,
+                })
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    if options.includes(Collection::Posts) {
+        let filter = if options.published_only {
+            "WHERE is_published = 1 AND page_id IN \
+             (SELECT id FROM site_pages WHERE is_published = 1 AND deleted_at IS NULL)"
+        } else {
+            ""
+        };
+        let rows = sqlx::query_as::<_, SitePostRow>(&format!(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at \
+             FROM site_posts {filter} ORDER BY id"
+        ))
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load site_posts entries")?;
+        bundle.posts = rows
+            .into_iter()
+            .map(|row| SitePostImport {
+                id: row.id,
+                page_id: row.page_id,
+                title: row.title,
+                slug: row.slug,
+                excerpt: row.excerpt.unwrap_or_default(),
+                excerpt_auto: row.excerpt_auto,
+                content_markdown: row.content_markdown,
+                is_published: row.is_published,
+                allow_comments: row.allow_comments,
+                published_at: row.published_at,
+                order_index: row.order_index,
+                created_at: Some(row.created_at),
+                updated_at: Some(row.updated_at),
+            })
+            .collect();
+    }
+
+    if options.includes(Collection::Tutorials) {
+        let rows = sqlx::query_as::<_, TutorialRow>(
+            "SELECT id, title, description, icon, color, topics, content, version, created_at, updated_at \
+             FROM tutorials ORDER BY id",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load tutorials entries")?;
+        bundle.tutorials = rows
+            .into_iter()
+            .map(|row| {
+                let topics = serde_json::from_str(&row.topics).with_context(|| {
+                    format!("Failed to parse topics JSON for tutorial '{}'", row.id)
+                })?;
+                Ok(TutorialImport {
+                    id: row.id,
+                    title: row.title,
+                    description: row.description,
+                    icon: row.icon,
+                    color: row.color,
+                    topics,
+                    content: row.content,
+                    version: row.version,
+                    created_at: Some(row.created_at),
+                    updated_at: Some(row.updated_at),
+                })
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    if options.includes(Collection::TutorialTopics) {
+        let rows = sqlx::query_as::<_, TutorialTopicRow>(
+            "SELECT tutorial_id, topic FROM tutorial_topics ORDER BY tutorial_id, topic",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load tutorial_topics entries")?;
+        bundle.tutorial_topics = rows
+            .into_iter()
+            .map(|row| TutorialTopicImport {
+                tutorial_id: row.tutorial_id,
+                topic: row.topic,
+            })
+            .collect();
+    }
+
+    if options.includes(Collection::Users) {
+        let rows = sqlx::query_as::<_, UserRow>(
+            "SELECT id, username, password_hash, role, created_at FROM users ORDER BY id",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load users entries")?;
+        bundle.users = rows
+            .into_iter()
+            .map(|row| UserImport {
+                id: row.id,
+                username: row.username,
+                password_hash: row.password_hash,
+                role: row.role,
+                created_at: Some(row.created_at),
+            })
+            .collect();
+    }
+
+    if options.includes(Collection::Comments) {
+        let filter = if options.published_only {
+            "WHERE post_id IS NULL OR post_id IN \
+             (SELECT id FROM site_posts WHERE is_published = 1 AND page_id IN \
+             (SELECT id FROM site_pages WHERE is_published = 1 AND deleted_at IS NULL))"
+        } else {
+            ""
+        };
+        let rows = sqlx::query_as::<_, CommentRow>(&format!(
+            "SELECT id, tutorial_id, post_id, author, content, votes, is_admin, created_at \
+             FROM comments {filter} ORDER BY id"
+        ))
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to load comments entries")?;
+        bundle.comments = rows
+            .into_iter()
+            .map(|row| CommentImport {
+                id: row.id,
+                tutorial_id: row.tutorial_id,
+                post_id: row.post_id,
+                author: row.author,
+                content: row.content,
+                votes: row.votes,
+                is_admin: row.is_admin,
+                created_at: Some(row.created_at),
+            })
+            .collect();
+    }
+
+    Ok(bundle)
+}
+
+/// The bundle as written to disk: pretty-printed, with a final newline.
+pub fn to_json(bundle: &ImportBundle) -> Result<String> {
+    let mut json =
+        serde_json::to_string_pretty(bundle).context("Failed to serialize export bundle")?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Identifies an entry of its collection: what an import upserts by.
+trait BundleEntry: Serialize {
+    fn key(&self) -> String;
+}
+
+impl BundleEntry for SiteContentImport {
+    fn key(&self) -> String {
+        self.section.clone()
+    }
+}
+
+impl BundleEntry for SitePageImport {
+    fn key(&self) -> String {
+        self.id.clone()
+    }
+}
+
+impl BundleEntry for SitePostImport {
+    fn key(&self) -> String {
+        self.id.clone()
+    }
+}
+
+impl BundleEntry for TutorialImport {
+    fn key(&self) -> String {
+        self.id.clone()
+    }
+}
+
+impl BundleEntry for TutorialTopicImport {
+    fn key(&self) -> String {
+        format!("{}/{}", self.tutorial_id, self.topic)
+    }
+}
+
+/// Accounts are matched by name, which is what logs in.
+impl BundleEntry for UserImport {
+    fn key(&self) -> String {
+        self.username.clone()
+    }
+}
+
+impl BundleEntry for CommentImport {
+    fn key(&self) -> String {
+        self.id.clone()
+    }
+}
+
+/// What an import does to one entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Unchanged,
+    /// Left alone by a `skip-existing` import, although it differs.
+    Skip,
+    /// Only topics are ever deleted, when the topics of a tutorial are
+    /// replaced.
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntityChange {
+    /// The section, the id, `tutorial_id/topic` for a topic, or the
+    /// username.
+    pub key: String,
+    pub action: ChangeAction,
+    /// The fields an update changes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectionReport {
+    pub collection: Collection,
+    pub create: usize,
+    pub update: usize,
+    pub unchanged: usize,
+    pub skip: usize,
+    pub delete: usize,
+    pub changes: Vec<EntityChange>,
+}
+
+impl CollectionReport {
+    fn new(collection: Collection, changes: Vec<EntityChange>) -> Self {
+        let count = |action| {
+            changes
+                .iter()
+                .filter(|change| change.action == action)
+                .count()
+        };
+        Self {
+            collection,
+            create: count(ChangeAction::Create),
+            update: count(ChangeAction::Update),
+            unchanged: count(ChangeAction::Unchanged),
+            skip: count(ChangeAction::Skip),
+            delete: count(ChangeAction::Delete),
+            changes,
+        }
+    }
+
+    /// The keys an import writes.
+    fn pending(&self) -> HashSet<&str> {
+        self.changes
+            .iter()
+            .filter(|change| matches!(change.action, ChangeAction::Create | ChangeAction::Update))
+            .map(|change| change.key.as_str())
+            .collect()
+    }
+
+    fn is_unchanged(&self) -> bool {
+        self.create == 0 && self.update == 0 && self.delete == 0
+    }
+}
+
+/// The outcome of comparing a bundle with the database, before or after
+/// applying it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    /// Problems that keep the bundle from being imported, by collection and
+    /// index.
+    pub errors: Vec<String>,
+    /// The same problems, when the entries having them are left out instead.
+    pub skipped: Vec<String>,
+    /// Fields of the bundle this build does not know, see [`parse_bundle`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    pub collections: Vec<CollectionReport>,
+    /// The derived data rebuilt after applying the import; absent from
+    /// plans.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rebuilt: Option<RebuildReport>,
+    /// The rows the database refused, with `continue_on_error`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<RowFailure>,
+}
+
+impl ImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn collection(&self, collection: Collection) -> Option<&CollectionReport> {
+        self.collections
+            .iter()
+            .find(|report| report.collection == collection)
+    }
+}
+
+/// The fields of `incoming` that differ from `current`. A timestamp the
+/// bundle leaves out is not a change.
+fn changed_fields<T: Serialize>(incoming: &T, current: &T) -> Result<Vec<String>> {
+    let incoming = serde_json::to_value(incoming).context("Failed to serialize bundle entry")?;
+    let current = serde_json::to_value(current).context("Failed to serialize database row")?;
+    let (Value::Object(incoming), Value::Object(current)) = (incoming, current) else {
+        return Ok(Vec::new());
+    };
+    Ok(incoming
+        .into_iter()
+        .filter(|(field, value)| {
+            !(value.is_null() && matches!(field.as_str(), "created_at" | "updated_at"))
+        })
+        .filter(|(field, value)| current.get(field) != Some(value))
+        .map(|(field, _)| field)
+        .collect())
+}
+
+fn compare<T: BundleEntry>(
+    collection: Collection,
+    incoming: &[T],
+    current: &[T],
+) -> Result<CollectionReport> {
+    let current: HashMap<String, &T> = current.iter().map(|row| (row.key(), row)).collect();
+    let changes = incoming
+        .iter()
+        .map(|item| {
+            let key = item.key();
+            let (action, changed_fields) = match current.get(&key) {
+                None => (ChangeAction::Create, Vec::new()),
+                Some(row) => {
+                    let fields = changed_fields(item, *row)?;
+                    if fields.is_empty() {
+                        (ChangeAction::Unchanged, fields)
+                    } else {
+                        (ChangeAction::Update, fields)
+                    }
+                }
+            };
+            Ok(EntityChange {
+                key,
+                action,
+                changed_fields,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(CollectionReport::new(collection, changes))
+}
+
+/// What an import does with an entry whose key the database already holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportStrategy {
+    /// Replace the row with the entry.
+    #[default]
+    Overwrite,
+    /// Leave the row as it is.
+    SkipExisting,
+    /// Import nothing if an entry would change a row.
+    FailOnConflict,
+}
+
+impl ImportStrategy {
+    pub const ALL: [ImportStrategy; 3] = [
+        ImportStrategy::Overwrite,
+        ImportStrategy::SkipExisting,
+        ImportStrategy::FailOnConflict,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportStrategy::Overwrite => "overwrite",
+            ImportStrategy::SkipExisting => "skip-existing",
+            ImportStrategy::FailOnConflict => "fail-on-conflict",
+        }
+    }
+}
+
+impl FromStr for ImportStrategy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ImportStrategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.as_str() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = ImportStrategy::ALL.iter().map(|s| s.as_str()).collect();
+                format!(
+                    "Unknown strategy '{}', expected one of {}",
+                    name,
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// The collections to import; the entries of the others are ignored and
+    /// their rows left alone.
+    pub only: Vec<Collection>,
+    pub strategy: ImportStrategy,
+    /// Leave out the entries that break a rule and import the rest, instead
+    /// of importing nothing.
+    pub skip_invalid: bool,
+    /// Record the rows the database refuses and write the rest, instead of
+    /// writing nothing.
+    pub continue_on_error: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            only: Collection::ALL.to_vec(),
+            strategy: ImportStrategy::default(),
+            skip_invalid: false,
+            continue_on_error: false,
+        }
+    }
+}
+
+impl ImportOptions {
+    fn includes(&self, collection: Collection) -> bool {
+        self.only.contains(&collection)
+    }
+}
+
+/// An entry that breaks a rule of the API, or that the import cannot write.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Violation {
+    collection: Collection,
+    index: usize,
+    key: String,
+    message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] '{}': {}",
+            self.collection.as_str(),
+            self.index,
+            self.key,
+            self.message
+        )
+    }
+}
+
+/// Replaces every entry with its normalized form, and records the entries
+/// `check` rejects and those sharing a key with an earlier one. Entries that
+/// differ from their row in `current` in nothing but the timestamps, which the
+/// API never writes, are not checked: rows seeded by migrations, such as the
+/// default tutorials without content, need not pass the rules of the API.
+/// Neither are entries a `skip-existing` import leaves alone.
+fn check_entries<T: BundleEntry>(
+    collection: Collection,
+    items: &mut [T],
+    current: &[T],
+    strategy: ImportStrategy,
+    check: impl Fn(&T) -> Result<T, String>,
+    violations: &mut Vec<Violation>,
+) -> Result<()> {
+    let current: HashMap<String, &T> = current.iter().map(|row| (row.key(), row)).collect();
+    let mut seen = HashSet::new();
+    for (index, item) in items.iter_mut().enumerate() {
+        let key = item.key();
+        if !seen.insert(key.clone()) {
+            violations.push(Violation {
+                collection,
+                index,
+                key,
+                message: "duplicate entry".to_string(),
+            });
+            continue;
+        }
+        if let Some(row) = current.get(&key) {
+            if strategy == ImportStrategy::SkipExisting {
+                continue;
+            }
+            let fields = changed_fields(item, *row)?;
+            if fields
+                .iter()
+                .all(|field| matches!(field.as_str(), "created_at" | "updated_at"))
+            {
+                continue;
+            }
+        }
+        match check(item) {
+            Ok(normalized) => *item = normalized,
+            Err(message) => violations.push(Violation {
+                collection,
+                index,
+                key,
+                message,
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// `items` without the entries that have a violation.
+fn without_violations<T>(
+    collection: Collection,
+    items: Vec<T>,
+    violations: &[Violation],
+) -> Vec<T> {
+    let rejected: HashSet<usize> = violations
+        .iter()
+        .filter(|violation| violation.collection == collection)
+        .map(|violation| violation.index)
+        .collect();
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !rejected.contains(index))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Timestamps are stored as given, so they must be ones the models can read
+/// back: RFC 3339 or SQLite's `YYYY-MM-DD HH:MM:SS`.
+fn check_timestamps(fields: &[(&str, &Option<String>)]) -> Result<(), String> {
+    for (field, value) in fields {
+        if let Some(value) = value {
+            if parse_timestamp(value).is_none() {
+                return Err(format!("{field} '{value}' is not a timestamp"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_site_content(
+    entry: &SiteContentImport,
+    custom_sections: &HashMap<String, Option<Value>>,
+) -> Result<SiteContentImport, String> {
+    check_timestamps(&[("updated_at", &entry.updated_at)])?;
+    validation::content::validate_content_size(&entry.content)?;
+    if validation::content::is_builtin_section(&entry.section) {
+        validation::content::validate_content_structure(&entry.section, &entry.content)?;
+    } else {
+        let schema = custom_sections
+            .get(&entry.section)
+            .ok_or_else(|| format!("Unknown content section '{}'", entry.section))?;
+        validation::content::validate_custom_content(
+            &entry.section,
+            schema.as_ref(),
+            &entry.content,
+        )?;
+    }
+    Ok(entry.clone())
+}
+
+fn check_page(page: &SitePageImport) -> Result<SitePageImport, String> {
+    check_timestamps(&[
+        ("created_at", &page.created_at),
+        ("updated_at", &page.updated_at),
+        ("deleted_at", &page.deleted_at),
+    ])?;
+    let slug = pages::normalize_slug(&page.slug)?;
+    validation::validate_slug(&slug)?;
+    pages::validate_hero(&page.hero)?;
+    pages::validate_layout(&page.layout)?;
+    Ok(SitePageImport {
+        slug,
+        title: pages::normalize_title(&page.title)?,
+        description: pages::normalize_description(&page.description)?,
+        nav_label: pages::normalize_nav_label(page.nav_label.clone())?,
+        visibility: pages::normalize_visibility(&page.visibility)?,
+        meta_title: pages::normalize_meta_title(page.meta_title.clone())?,
+        meta_description: pages::normalize_meta_description(page.meta_description.clone())?,
+        og_image: pages::normalize_og_image(page.og_image.clone())?,
+        ..page.clone()
+    })
+}
+
+fn check_post(post: &SitePostImport) -> Result<SitePostImport, String> {
+    check_timestamps(&[
+        ("published_at", &post.published_at),
+        ("created_at", &post.created_at),
+        ("updated_at", &post.updated_at),
+    ])?;
+    let slug = posts::sanitize_slug(&post.slug);
+    posts::validate_post_fields(
+        &post.title,
+        &slug,
+        Some(post.excerpt.trim()),
+        &post.content_markdown,
+    )?;
+    validation::validate_slug(&slug)?;
+    Ok(SitePostImport {
+        title: post.title.trim().to_string(),
+        slug,
+        excerpt: post.excerpt.trim().to_string(),
+        ..post.clone()
+    })
+}
+
+fn check_tutorial(tutorial: &TutorialImport) -> Result<TutorialImport, String> {
+    check_timestamps(&[
+        ("created_at", &tutorial.created_at),
+        ("updated_at", &tutorial.updated_at),
+    ])?;
+    tutorials::validate_tutorial_id(&tutorial.id)?;
+    tutorials::validate_tutorial_data(&tutorial.title, &tutorial.description, &tutorial.content)?;
+    tutorials::validate_icon(&tutorial.icon)?;
+    tutorials::validate_color(&tutorial.color)?;
+    Ok(TutorialImport {
+        title: tutorial.title.trim().to_string(),
+        description: tutorial.description.trim().to_string(),
+        topics: tutorials::sanitize_topics(&tutorial.topics)?,
+        ..tutorial.clone()
+    })
+}
+
+fn check_topic(topic: &TutorialTopicImport) -> Result<TutorialTopicImport, String> {
+    tutorials::validate_tutorial_id(&topic.tutorial_id)?;
+    if topic.topic.trim().is_empty() {
+        return Err("Topic cannot be empty".to_string());
+    }
+    let mut sanitized = tutorials::sanitize_topics(std::slice::from_ref(&topic.topic))?;
+    Ok(TutorialTopicImport {
+        topic: sanitized.remove(0),
+        ..topic.clone()
+    })
+}
+
+fn check_user(user: &UserImport) -> Result<UserImport, String> {
+    if user.id <= 0 {
+        return Err("User id must be positive".to_string());
+    }
+    check_timestamps(&[("created_at", &user.created_at)])?;
+    let username = user.username.trim().to_string();
+    users::validate_username(&username)?;
+    users::validate_role(&user.role)?;
+    users::validate_password_hash(&user.password_hash)?;
+    Ok(UserImport {
+        username,
+        ..user.clone()
+    })
+}
+
+fn check_comment(comment: &CommentImport) -> Result<CommentImport, String> {
+    check_timestamps(&[("created_at", &comment.created_at)])?;
+    match (&comment.tutorial_id, &comment.post_id) {
+        (Some(tutorial_id), None) => tutorials::validate_tutorial_id(tutorial_id)?,
+        (None, Some(post_id)) if !post_id.trim().is_empty() => {}
+        _ => return Err("A comment belongs to either a tutorial or a post".to_string()),
+    }
+    let author = comment.author.trim();
+    if author.is_empty() {
+        return Err("Comment author cannot be empty".to_string());
+    }
+    let content = comment.content.trim();
+    if content.is_empty() {
+        return Err("Comment content cannot be empty".to_string());
+    }
+    Ok(CommentImport {
+        author: author.to_string(),
+        content: content.to_string(),
+        ..comment.clone()
+    })
+}
+
+/// Records the users whose id belongs to another account, in the bundle or
+/// in the database.
+fn check_user_ids(items: &[UserImport], current: &[UserImport], violations: &mut Vec<Violation>) {
+    let mut owners: HashMap<i64, &str> = current
+        .iter()
+        .map(|user| (user.id, user.username.as_str()))
+        .collect();
+    // Accounts of the bundle replace the stored ones of the same name
+    let names: HashSet<&str> = items.iter().map(|user| user.username.as_str()).collect();
+    owners.retain(|_, owner| !names.contains(owner));
+    for (index, user) in items.iter().enumerate() {
+        match owners.get(&user.id) {
+            Some(owner) if *owner != user.username => violations.push(Violation {
+                collection: Collection::Users,
+                index,
+                key: user.key(),
+                message: format!("id {} belongs to user '{}'", user.id, owner),
+            }),
+            _ => {
+                owners.insert(user.id, &user.username);
+            }
+        }
+    }
+}
+
+/// The custom sections registered in `content_sections`, with their schema.
+async fn custom_sections(conn: &mut SqliteConnection) -> Result<HashMap<String, Option<Value>>> {
+    let rows: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT name, schema_json FROM content_sections")
+            .fetch_all(&mut *conn)
+            .await
+            .context("Failed to load content_sections")?;
+    rows.into_iter()
+        .map(|(name, schema)| {
+            let schema = schema
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()
+                .with_context(|| format!("Failed to parse the schema of section '{}'", name))?;
+            Ok((name, schema))
+        })
+        .collect()
+}
+
+/// A bundle ready to be written, and what writing it does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportPlan {
+    /// The entries to import, normalized as the API stores them; without
+    /// the rejected ones when skipping those.
+    pub bundle: ImportBundle,
+    pub report: ImportReport,
+    topic_scope: Option<TopicScope>,
+    continue_on_error: bool,
+}
+
+/// Checks `bundle` against the rules of the API and compares it with what
+/// the database holds, without writing: which entries an import creates,
+/// updates or leaves alone, and which topics it deletes. Posts and topics
+/// must belong to a page or tutorial of the bundle or the database; comments
+/// that do not are left out.
+pub async fn plan_import(
+    conn: &mut SqliteConnection,
+    bundle: &ImportBundle,
+    options: &ImportOptions,
+) -> Result<ImportPlan> {
+    let current = read_bundle(
+        conn,
+        &ExportOptions {
+            only: Collection::ALL.to_vec(),
+            published_only: false,
+        },
+    )
+    .await?;
+    let custom_sections = custom_sections(conn).await?;
+    // Users and comments are only reported when the bundle has some
+    let optional = |collection| match collection {
+        Collection::Users => !bundle.users.is_empty(),
+        Collection::Comments => !bundle.comments.is_empty(),
+        _ => true,
+    };
+
+    let mut bundle = bundle.clone();
+    if !options.includes(Collection::SiteContent) {
+        bundle.site_content.clear();
+    }
+    if !options.includes(Collection::Pages) {
+        bundle.pages.clear();
+    }
+    if !options.includes(Collection::Posts) {
+        bundle.posts.clear();
+    }
+    if !options.includes(Collection::Tutorials) {
+        bundle.tutorials.clear();
+    }
+    if !options.includes(Collection::TutorialTopics) {
+        bundle.tutorial_topics.clear();
+    }
+    if !options.includes(Collection::Users) {
+        bundle.users.clear();
+    }
+    if !options.includes(Collection::Comments) {
+        bundle.comments.clear();
+    }
+    let mut violations = Vec::new();
+    check_entries(
+        Collection::SiteContent,
+        &mut bundle.site_content,
+        &current.site_content,
+        options.strategy,
+        |entry| check_site_content(entry, &custom_sections),
+        &mut violations,
+    )?;
+    check_entries(
+        Collection::Pages,
+        &mut bundle.pages,
+        &current.pages,
+        options.strategy,
+        check_page,
+        &mut violations,
+    )?;
+    check_entries(
+        Collection::Posts,
+        &mut bundle.posts,
+        &current.posts,
+        options.strategy,
+        check_post,
+        &mut violations,
+    )?;
+    check_entries(
+        Collection::Tutorials,
+        &mut bundle.tutorials,
+        &current.tutorials,
+        options.strategy,
+        check_tutorial,
+        &mut violations,
+    )?;
+    check_entries(
+        Collection::TutorialTopics,
+        &mut bundle.tutorial_topics,
+        &current.tutorial_topics,
+        options.strategy,
+        check_topic,
+        &mut violations,
+    )?;
+    check_entries(
+        Collection::Users,
+        &mut bundle.users,
+        &current.users,
+        options.strategy,
+        check_user,
+        &mut violations,
+    )?;
+    check_user_ids(&bundle.users, &current.users, &mut violations);
+    check_entries(
+        Collection::Comments,
+        &mut bundle.comments,
+        &current.comments,
+        options.strategy,
+        check_comment,
+        &mut violations,
+    )?;
+
+    // A skipped page or tutorial takes its posts or topics along
+    let rejected = |collection: Collection, index: usize| {
+        options.skip_invalid
+            && violations
+                .iter()
+                .any(|violation| violation.collection == collection && violation.index == index)
+    };
+    let pages: HashSet<&str> = bundle
+        .pages
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !rejected(Collection::Pages, *index))
+        .map(|(_, page)| page.id.as_str())
+        .chain(current.pages.iter().map(|page| page.id.as_str()))
+        .collect();
+    let tutorial_ids: HashSet<&str> = bundle
+        .tutorials
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !rejected(Collection::Tutorials, *index))
+        .map(|(_, tutorial)| tutorial.id.as_str())
+        .chain(
+            current
+                .tutorials
+                .iter()
+                .map(|tutorial| tutorial.id.as_str()),
+        )
+        .collect();
+    let mut missing = Vec::new();
+    for (index, post) in bundle.posts.iter().enumerate() {
+        if !pages.contains(post.page_id.as_str()) {
+            missing.push(Violation {
+                collection: Collection::Posts,
+                index,
+                key: post.key(),
+                message: format!("page '{}' does not exist", post.page_id),
+            });
+        }
+    }
+    for (index, topic) in bundle.tutorial_topics.iter().enumerate() {
+        if !tutorial_ids.contains(topic.tutorial_id.as_str()) {
+            missing.push(Violation {
+                collection: Collection::TutorialTopics,
+                index,
+                key: topic.key(),
+                message: format!("tutorial '{}' does not exist", topic.tutorial_id),
+            });
+        }
+    }
+    // Comments on a missing tutorial or post are always left out
+    let post_ids: HashSet<&str> = bundle
+        .posts
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !rejected(Collection::Posts, *index))
+        .map(|(_, post)| post.id.as_str())
+        .chain(current.posts.iter().map(|post| post.id.as_str()))
+        .collect();
+    let mut orphans = Vec::new();
+    for (index, comment) in bundle.comments.iter().enumerate() {
+        let message = match (&comment.tutorial_id, &comment.post_id) {
+            (Some(id), _) if !tutorial_ids.contains(id.as_str()) => {
+                format!("tutorial '{}' does not exist", id)
+            }
+            (None, Some(id)) if !post_ids.contains(id.as_str()) => {
+                format!("post '{}' does not exist", id)
+            }
+            _ => continue,
+        };
+        orphans.push(Violation {
+            collection: Collection::Comments,
+            index,
+            key: comment.key(),
+            message,
+        });
+    }
+
+    violations.extend(missing);
+    violations.sort();
+
+    let (mut errors, skipped) = if options.skip_invalid {
+        let mut left_out = [violations.as_slice(), orphans.as_slice()].concat();
+        left_out.sort();
+        bundle = ImportBundle {
+            format_version: bundle.format_version,
+            site_content: without_violations(
+                Collection::SiteContent,
+                bundle.site_content,
+                &left_out,
+            ),
+            pages: without_violations(Collection::Pages, bundle.pages, &left_out),
+            posts: without_violations(Collection::Posts, bundle.posts, &left_out),
+            tutorials: without_violations(Collection::Tutorials, bundle.tutorials, &left_out),
+            tutorial_topics: without_violations(
+                Collection::TutorialTopics,
+                bundle.tutorial_topics,
+                &left_out,
+            ),
+            users: without_violations(Collection::Users, bundle.users, &left_out),
+            comments: without_violations(Collection::Comments, bundle.comments, &left_out),
+        };
+        (
+            Vec::new(),
+            left_out.iter().map(ToString::to_string).collect(),
+        )
+    } else {
+        bundle.comments = without_violations(Collection::Comments, bundle.comments, &orphans);
+        (
+            violations.iter().map(ToString::to_string).collect(),
+            orphans.iter().map(ToString::to_string).collect(),
+        )
+    };
+
+    let topic_scope = topic_scope(&bundle, &current, options);
+    if let Some(scope) = &topic_scope {
+        align_topics(&mut bundle, scope);
+    }
+    let mut collections = vec![
+        compare(
+            Collection::SiteContent,
+            &bundle.site_content,
+            &current.site_content,
+        )?,
+        compare(Collection::Pages, &bundle.pages, &current.pages)?,
+        compare(Collection::Posts, &bundle.posts, &current.posts)?,
+        compare(Collection::Tutorials, &bundle.tutorials, &current.tutorials)?,
+        compare_topics(&bundle, &current, topic_scope.as_ref())?,
+        compare(Collection::Users, &bundle.users, &current.users)?,
+        compare(Collection::Comments, &bundle.comments, &current.comments)?,
+    ];
+    collections.retain(|report| options.includes(report.collection) && optional(report.collection));
+
+    match options.strategy {
+        ImportStrategy::Overwrite => {}
+        ImportStrategy::SkipExisting => {
+            for report in &mut collections {
+                let changes = std::mem::take(&mut report.changes)
+                    .into_iter()
+                    .map(|change| match change.action {
+                        ChangeAction::Update => EntityChange {
+                            action: ChangeAction::Skip,
+                            ..change
+                        },
+                        _ => change,
+                    })
+                    .collect();
+                *report = CollectionReport::new(report.collection, changes);
+            }
+            // Accounts are too sensitive to keep the stored one silently
+            if let Some(users) = collections
+                .iter()
+                .find(|report| report.collection == Collection::Users)
+            {
+                errors.extend(
+                    users
+                        .changes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, change)| change.action == ChangeAction::Skip)
+                        .map(|(index, change)| {
+                            Violation {
+                                collection: Collection::Users,
+                                index,
+                                key: change.key.clone(),
+                                message: "username is taken; only an overwrite import replaces an account"
+                                    .to_string(),
+                            }
+                            .to_string()
+                        }),
+                );
+            }
+        }
+        ImportStrategy::FailOnConflict => {
+            // Entries and changes share their order, see `compare`
+            errors.extend(collections.iter().flat_map(|report| {
+                report
+                    .changes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, change)| change.action == ChangeAction::Update)
+                    .map(|(index, change)| {
+                        Violation {
+                            collection: report.collection,
+                            index,
+                            key: change.key.clone(),
+                            message: format!(
+                                "conflicts with the stored entry ({})",
+                                change.changed_fields.join(", ")
+                            ),
+                        }
+                        .to_string()
+                    })
+            }));
+        }
+    }
+
+    let report = ImportReport {
+        errors,
+        skipped,
+        warnings: Vec::new(),
+        collections,
+        rebuilt: None,
+        failed: Vec::new(),
+    };
+    Ok(ImportPlan {
+        bundle,
+        report,
+        topic_scope,
+        continue_on_error: options.continue_on_error,
+    })
+}
+
+/// The tutorials whose topics an import replaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TopicScope(HashSet<String>);
+
+impl TopicScope {
+    fn contains(&self, tutorial_id: &str) -> bool {
+        self.0.contains(tutorial_id)
+    }
+}
+
+/// A bundle with tutorials or topics replaces the topics of the tutorials it
+/// names, except those `skip-existing` leaves alone. The topics of other
+/// tutorials are kept, as they follow from their own `topics`.
+fn topic_scope(
+    bundle: &ImportBundle,
+    current: &ImportBundle,
+    options: &ImportOptions,
+) -> Option<TopicScope> {
+    if !options.includes(Collection::TutorialTopics)
+        || (bundle.tutorials.is_empty() && bundle.tutorial_topics.is_empty())
+    {
+        return None;
+    }
+    let existing: HashSet<&str> = current
+        .tutorials
+        .iter()
+        .map(|tutorial| tutorial.id.as_str())
+        .collect();
+    let ids = bundle
+        .tutorials
+        .iter()
+        .map(|tutorial| tutorial.id.as_str())
+        .chain(
+            bundle
+                .tutorial_topics
+                .iter()
+                .map(|topic| topic.tutorial_id.as_str()),
+        )
+        .filter(|id| options.strategy != ImportStrategy::SkipExisting || !existing.contains(id))
+        .map(str::to_string)
+        .collect();
+    Some(TopicScope(ids))
+}
+
+/// Makes the tutorials of the bundle agree with its topic index: a tutorial
+/// the index lists topics for takes those, in its own order where it had
+/// them, and the topics of any other tutorial are added to the index.
+fn align_topics(bundle: &mut ImportBundle, scope: &TopicScope) {
+    let mut listed: HashMap<String, Vec<String>> = HashMap::new();
+    for topic in &bundle.tutorial_topics {
+        listed
+            .entry(topic.tutorial_id.clone())
+            .or_default()
+            .push(topic.topic.clone());
+    }
+    for tutorial in bundle
+        .tutorials
+        .iter_mut()
+        .filter(|tutorial| scope.contains(&tutorial.id))
+    {
+        match listed.get(&tutorial.id) {
+            Some(topics) => tutorial.topics = follow_index(&tutorial.topics, topics),
+            None => bundle
+                .tutorial_topics
+                .extend(tutorial.topics.iter().map(|topic| TutorialTopicImport {
+                    tutorial_id: tutorial.id.clone(),
+                    topic: topic.clone(),
+                })),
+        }
+    }
+}
+
+/// The `topics` of a tutorial once its topic index is `listed`.
+fn follow_index(topics: &[String], listed: &[String]) -> Vec<String> {
+    topics
+        .iter()
+        .filter(|topic| listed.contains(topic))
+        .chain(listed.iter().filter(|topic| !topics.contains(topic)))
+        .cloned()
+        .collect()
+}
+
+/// Compares the topics like any collection; the stored topics of a replaced
+/// tutorial that the bundle leaves out are deleted, and the topics of the
+/// bundle for a tutorial left alone are skipped.
+fn compare_topics(
+    bundle: &ImportBundle,
+    current: &ImportBundle,
+    scope: Option<&TopicScope>,
+) -> Result<CollectionReport> {
+    let report = compare(
+        Collection::TutorialTopics,
+        &bundle.tutorial_topics,
+        &current.tutorial_topics,
+    )?;
+    let Some(scope) = scope else {
+        return Ok(report);
+    };
+
+    let mut changes: Vec<EntityChange> = report
+        .changes
+        .into_iter()
+        .zip(&bundle.tutorial_topics)
+        .map(|(change, topic)| {
+            if change.action == ChangeAction::Create && !scope.contains(&topic.tutorial_id) {
+                EntityChange {
+                    action: ChangeAction::Skip,
+                    ..change
+                }
+            } else {
+                change
+            }
+        })
+        .collect();
+    let kept: HashSet<String> = bundle
+        .tutorial_topics
+        .iter()
+        .map(BundleEntry::key)
+        .collect();
+    changes.extend(
+        current
+            .tutorial_topics
+            .iter()
+            .filter(|topic| scope.contains(&topic.tutorial_id))
+            .map(BundleEntry::key)
+            .filter(|key| !kept.contains(key))
+            .map(|key| EntityChange {
+                key,
+                action: ChangeAction::Delete,
+                changed_fields: Vec::new(),
+            }),
+    );
+    Ok(CollectionReport::new(Collection::TutorialTopics, changes))
+}
+
+/// Plans the import of `bundle` and, if nothing keeps it from being
+/// imported, applies the plan. Returns the report of the plan; a rejected
+/// bundle writes nothing.
+pub async fn import_bundle(
+    tx: &mut Transaction<'_, Sqlite>,
+    bundle: &ImportBundle,
+    options: &ImportOptions,
+) -> Result<ImportReport> {
+    let plan = plan_import(tx, bundle, options).await?;
+    if !plan.report.is_valid() {
+        return Err(anyhow!(
+            "The bundle is invalid:\n  {}",
+            plan.report.errors.join("\n  ")
+        ));
+    }
+    apply_plan(tx, plan).await
+}
+
+/// Writes the entries a valid plan creates or updates, pages before their
+/// posts and tutorials before their topics and comments, in the transaction
+/// it was planned in. Then rebuilds the derived data of the tutorials and
+/// posts written.
+pub async fn apply_plan(
+    tx: &mut Transaction<'_, Sqlite>,
+    plan: ImportPlan,
+) -> Result<ImportReport> {
+    apply_plan_with(tx, plan, &mut |_| {}).await
+}
+
+/// Like [`apply_plan`], telling `on_event` how far it got. A row the
+/// database refuses fails the import, unless the plan was made with
+/// [`ImportOptions::continue_on_error`]: then the failure is reported and
+/// the other rows are written.
+pub async fn apply_plan_with(
+    tx: &mut Transaction<'_, Sqlite>,
+    plan: ImportPlan,
+    on_event: &mut (dyn FnMut(&ImportEvent) + Send),
+) -> Result<ImportReport> {
+    let ImportPlan {
+        bundle,
+        mut report,
+        topic_scope,
+        continue_on_error,
+    } = plan;
+    if !report.is_valid() {
+        return Err(anyhow!("Refusing to apply an invalid plan"));
+    }
+
+    let pending = |collection| {
+        report
+            .collection(collection)
+            .map(CollectionReport::pending)
+            .unwrap_or_default()
+    };
+    let skipped = |collection| {
+        report
+            .collection(collection)
+            .map_or(0, |collection| collection.skip)
+    };
+    let mut writer = RowWriter::new(continue_on_error, on_event);
+    apply_site_content(
+        tx,
+        &bundle.site_content,
+        &pending(Collection::SiteContent),
+        &mut writer,
+    )
+    .await?;
+    writer.finish(skipped(Collection::SiteContent));
+    apply_site_pages(tx, &bundle.pages, &pending(Collection::Pages), &mut writer).await?;
+    writer.finish(skipped(Collection::Pages));
+    apply_site_posts(tx, &bundle.posts, &pending(Collection::Posts), &mut writer).await?;
+    writer.finish(skipped(Collection::Posts));
+    let mut tutorials: HashSet<String> = pending(Collection::Tutorials)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    apply_tutorials(
+        tx,
+        &bundle.tutorials,
+        &pending(Collection::Tutorials),
+        &mut writer,
+    )
+    .await?;
+    writer.finish(skipped(Collection::Tutorials));
+    if let Some(scope) = topic_scope.filter(|_| {
+        report
+            .collection(Collection::TutorialTopics)
+            .is_some_and(|topics| !topics.is_unchanged())
+    }) {
+        apply_tutorial_topics(tx, &bundle.tutorial_topics, &scope, &mut writer).await?;
+        writer.finish(skipped(Collection::TutorialTopics));
+        follow_topic_index(tx, &bundle, &scope).await?;
+        tutorials.extend(scope.0);
+    }
+    apply_users(tx, &bundle.users, &pending(Collection::Users), &mut writer).await?;
+    writer.finish(skipped(Collection::Users));
+    apply_comments(
+        tx,
+        &bundle.comments,
+        &pending(Collection::Comments),
+        &mut writer,
+    )
+    .await?;
+    writer.finish(skipped(Collection::Comments));
+
+    let posts = pending(Collection::Posts)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let rebuilt = derived::rebuild(tx, &RebuildScope::Only { tutorials, posts }).await?;
+    report.rebuilt = Some(rebuilt);
+    report.failed = writer.failures;
+    Ok(report)
+}
+
+/// Rows between two [`ImportEvent::Progress`] events of a collection.
+pub const PROGRESS_INTERVAL: usize = 100;
+
+/// A row the database refused to write.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowFailure {
+    pub collection: Collection,
+    /// The id of the entry, or the section or username it is keyed by.
+    pub key: String,
+    /// The column the database names, when it names one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub error: String,
+}
+
+impl RowFailure {
+    fn new(collection: Collection, key: &str, err: &sqlx::Error) -> Self {
+        let error = match err.as_database_error() {
+            Some(err) => err.message().to_string(),
+            None => err.to_string(),
+        };
+        let field = match error.split_once("constraint failed: ") {
+            Some((_, columns)) => Some(
+                columns
+                    .split(", ")
+                    .map(|column| column.rsplit('.').next().unwrap_or(column))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            // Foreign key errors do not say which key; these rows have one
+            None if error.starts_with("FOREIGN KEY") => match collection {
+                Collection::Posts => Some("page_id".to_string()),
+                Collection::TutorialTopics => Some("tutorial_id".to_string()),
+                _ => None,
+            },
+            None => None,
+        };
+        Self {
+            collection,
+            key: key.to_string(),
+            field,
+            error,
+        }
+    }
+}
+
+impl fmt::Display for RowFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} '{}': ", self.collection.as_str(), self.key)?;
+        if let Some(field) = &self.field {
+            write!(f, "{}: ", field)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+/// What an import reports while it writes, e.g. as one JSON object per
+/// line: `{"event":"progress","collection":"posts",...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ImportEvent {
+    /// Every [`PROGRESS_INTERVAL`] rows of a collection, and after its last.
+    Progress {
+        collection: Collection,
+        processed: usize,
+        total: usize,
+    },
+    RowFailed(RowFailure),
+    /// A collection is written.
+    Collection {
+        collection: Collection,
+        processed: usize,
+        upserted: usize,
+        skipped: usize,
+        failed: usize,
+    },
+}
+
+/// Counts the rows of the collection being written and reports them.
+struct RowWriter<'a> {
+    continue_on_error: bool,
+    on_event: &'a mut (dyn FnMut(&ImportEvent) + Send),
+    failures: Vec<RowFailure>,
+    collection: Option<Collection>,
+    total: usize,
+    processed: usize,
+    upserted: usize,
+    failed: usize,
+}
+
+impl<'a> RowWriter<'a> {
+    fn new(continue_on_error: bool, on_event: &'a mut (dyn FnMut(&ImportEvent) + Send)) -> Self {
+        Self {
+            continue_on_error,
+            on_event,
+            failures: Vec::new(),
+            collection: None,
+            total: 0,
+            processed: 0,
+            upserted: 0,
+            failed: 0,
+        }
+    }
+
+    fn start(&mut self, collection: Collection, total: usize) {
+        self.collection = Some(collection);
+        self.total = total;
+        self.processed = 0;
+        self.upserted = 0;
+        self.failed = 0;
+    }
+
+    /// Counts the outcome of writing the row `key`. A failure ends the
+    /// import, unless continuing on errors.
+    fn record(&mut self, key: &str, result: Result<(), sqlx::Error>) -> Result<()> {
+        let collection = self
+            .collection
+            .expect("RowWriter::start is called before a row is recorded");
+        self.processed += 1;
+        match result {
+            Ok(()) => self.upserted += 1,
+            Err(err) => {
+                let failure = RowFailure::new(collection, key, &err);
+                (self.on_event)(&ImportEvent::RowFailed(failure.clone()));
+                if !self.continue_on_error {
+                    return Err(anyhow!("Failed to import {}", failure));
+                }
+                self.failed += 1;
+                self.failures.push(failure);
+            }
+        }
+        if self.processed % PROGRESS_INTERVAL == 0 || self.processed == self.total {
+            (self.on_event)(&ImportEvent::Progress {
+                collection,
+                processed: self.processed,
+                total: self.total,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reports the collection being written, if it had rows to write.
+    fn finish(&mut self, skipped: usize) {
+        if let Some(collection) = self.collection.take() {
+            (self.on_event)(&ImportEvent::Collection {
+                collection,
+                processed: self.processed,
+                upserted: self.upserted,
+                skipped,
+                failed: self.failed,
+            });
+        }
+    }
+}
+
+async fn apply_site_content(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[SiteContentImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    writer.start(Collection::SiteContent, pending.len());
+    for item in items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+    {
+        // Archived like an edit in the admin UI, so an import can be rolled
+        // back section by section
+        let result = repositories::content::upsert_site_content_tx(
+            tx,
+            &item.section,
+            &item.content,
+            item.updated_at.as_deref(),
+            IMPORT_AUTHOR,
+            repositories::content::history_retention(),
+        )
+        .await;
+        writer.record(&item.section, result)?;
+    }
+
+    Ok(())
+}
+
+async fn apply_site_pages(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[SitePageImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    writer.start(Collection::Pages, pending.len());
+    for item in items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+    {
+        let hero_serialized =
+            serde_json::to_string(&item.hero).context("Failed to serialize page hero JSON")?;
+        let layout_serialized =
+            serde_json::to_string(&item.layout).context("Failed to serialize page layout JSON")?;
+
+        let result = sqlx::query(
+            "INSERT INTO site_pages (id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP), ?) \
+             ON CONFLICT(id) DO UPDATE SET slug = excluded.slug, title = excluded.title, description = excluded.description, nav_label = excluded.nav_label, show_in_nav = excluded.show_in_nav, order_index = excluded.order_index, is_published = excluded.is_published, visibility = excluded.visibility, hero_json = excluded.hero_json, layout_json = excluded.layout_json, meta_title = excluded.meta_title, meta_description = excluded.meta_description, og_image = excluded.og_image, created_at = COALESCE(?, site_pages.created_at), updated_at = COALESCE(excluded.updated_at, CURRENT_TIMESTAMP), deleted_at = excluded.deleted_at",
+        )
+        .bind(&item.id)
+        .bind(&item.slug)
+        .bind(&item.title)
+        .bind(&item.description)
+        .bind(&item.nav_label)
+        .bind(if item.show_in_nav { 1 } else { 0 })
+        .bind(item.order_index)
+        .bind(if item.is_published { 1 } else { 0 })
+        .bind(&item.visibility)
+        .bind(&hero_serialized)
+        .bind(&layout_serialized)
+        .bind(&item.meta_title)
+        .bind(&item.meta_description)
+        .bind(&item.og_image)
+        .bind(&item.created_at)
+        .bind(&item.updated_at)
+        .bind(&item.deleted_at)
+        .bind(&item.created_at)
+        .execute(&mut **tx)
+        .await;
+        writer.record(&item.id, result.map(|_| ()))?;
+    }
+
+    Ok(())
+}
+
+async fn apply_site_posts(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[SitePostImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    writer.start(Collection::Posts, pending.len());
+    for item in items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+    {
+        let result = sqlx::query(
+            "INSERT INTO site_posts (id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP)) \
+             ON CONFLICT(id) DO UPDATE SET page_id = excluded.page_id, title = excluded.title, slug = excluded.slug, excerpt = excluded.excerpt, excerpt_auto = excluded.excerpt_auto, content_markdown = excluded.content_markdown, is_published = excluded.is_published, allow_comments = excluded.allow_comments, published_at = excluded.published_at, order_index = excluded.order_index, created_at = COALESCE(?, site_posts.created_at), updated_at = COALESCE(excluded.updated_at, CURRENT_TIMESTAMP)",
+        )
+        .bind(&item.id)
+        .bind(&item.page_id)
+        .bind(&item.title)
+        .bind(&item.slug)
+        .bind(&item.excerpt)
+        .bind(if item.excerpt_auto { 1 } else { 0 })
+        .bind(&item.content_markdown)
+        .bind(if item.is_published { 1 } else { 0 })
+        .bind(item.allow_comments)
+        .bind(&item.published_at)
+        .bind(item.order_index)
+        .bind(&item.created_at)
+        .bind(&item.updated_at)
+        .bind(&item.created_at)
+        .execute(&mut **tx)
+        .await;
+        writer.record(&item.id, result.map(|_| ()))?;
+    }
+
+    Ok(())
+}
+
+async fn apply_tutorials(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[TutorialImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    writer.start(Collection::Tutorials, pending.len());
+    for item in items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+    {
+        let topics_serialized =
+            serde_json::to_string(&item.topics).context("Failed to serialize tutorial topics")?;
+
+        let result = sqlx::query(
+            "INSERT INTO tutorials (id, title, description, icon, color, topics, content, version, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')), COALESCE(?, datetime('now'))) \
+             ON CONFLICT(id) DO UPDATE SET title = excluded.title, description = excluded.description, icon = excluded.icon, color = excluded.color, topics = excluded.topics, content = excluded.content, version = excluded.version, created_at = COALESCE(?, tutorials.created_at), updated_at = COALESCE(excluded.updated_at, datetime('now'))",
+        )
+        .bind(&item.id)
+        .bind(&item.title)
+        .bind(&item.description)
+        .bind(&item.icon)
+        .bind(&item.color)
+        .bind(&topics_serialized)
+        .bind(&item.content)
+        .bind(item.version)
+        .bind(&item.created_at)
+        .bind(&item.updated_at)
+        .bind(&item.created_at)
+        .execute(&mut **tx)
+        .await;
+        writer.record(&item.id, result.map(|_| ()))?;
+    }
+
+    Ok(())
+}
+
+async fn apply_tutorial_topics(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[TutorialTopicImport],
+    scope: &TopicScope,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    for id in &scope.0 {
+        sqlx::query("DELETE FROM tutorial_topics WHERE tutorial_id = ?")
+            .bind(id)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to clear the topics of tutorial '{}'", id))?;
+    }
+
+    let items: Vec<&TutorialTopicImport> = items
+        .iter()
+        .filter(|item| scope.contains(&item.tutorial_id))
+        .collect();
+    writer.start(Collection::TutorialTopics, items.len());
+    for item in items {
+        let result = sqlx::query("INSERT INTO tutorial_topics (tutorial_id, topic) VALUES (?, ?)")
+            .bind(&item.tutorial_id)
+            .bind(&item.topic)
+            .execute(&mut **tx)
+            .await;
+        writer.record(&item.key(), result.map(|_| ()))?;
+    }
+
+    Ok(())
+}
+
+/// Gives the stored tutorials whose topic index the bundle replaced, without
+/// importing them, the topics of that index; see [`align_topics`] for those
+/// it imports.
+async fn follow_topic_index(
+    tx: &mut Transaction<'_, Sqlite>,
+    bundle: &ImportBundle,
+    scope: &TopicScope,
+) -> Result<()> {
+    let imported: HashSet<&str> = bundle
+        .tutorials
+        .iter()
+        .map(|tutorial| tutorial.id.as_str())
+        .collect();
+    for id in scope.0.iter().filter(|id| !imported.contains(id.as_str())) {
+        let stored: Option<String> =
+            sqlx::query_scalar("SELECT topics FROM tutorials WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&mut **tx)
+                .await
+                .with_context(|| format!("Failed to read the topics of tutorial '{}'", id))?;
+        let Some(stored) = stored else {
+            continue;
+        };
+        let topics: Vec<String> = serde_json::from_str(&stored)
+            .with_context(|| format!("Tutorial '{}' has invalid topics JSON", id))?;
+        let listed: Vec<String> = bundle
+            .tutorial_topics
+            .iter()
+            .filter(|topic| &topic.tutorial_id == id)
+            .map(|topic| topic.topic.clone())
+            .collect();
+        let followed = follow_index(&topics, &listed);
+        if followed != topics {
+            sqlx::query("UPDATE tutorials SET topics = ? WHERE id = ?")
+                .bind(
+                    serde_json::to_string(&followed)
+                        .context("Failed to serialize tutorial topics")?,
+                )
+                .bind(id)
+                .execute(&mut **tx)
+                .await
+                .with_context(|| format!("Failed to update the topics of tutorial '{}'", id))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn apply_users(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[UserImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    let items: Vec<&UserImport> = items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+        .collect();
+    // Stored accounts first take an id no other account has, so one giving up
+    // its id to another in the same import does not collide
+    let mut parked = HashMap::new();
+    for item in &items {
+        let stored: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+            .bind(&item.username)
+            .fetch_optional(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to read user '{}'", item.username))?;
+        if let Some(id) = stored {
+            parked.insert(item.username.as_str(), id);
+        }
+        sqlx::query("UPDATE users SET id = ? WHERE username = ?")
+            .bind(-item.id)
+            .bind(&item.username)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to renumber user '{}'", item.username))?;
+    }
+
+    writer.start(Collection::Users, items.len());
+    let mut failed = Vec::new();
+    for item in items {
+        let result = sqlx::query(
+            "INSERT INTO users (id, username, password_hash, role, created_at) VALUES (?, ?, ?, ?, COALESCE(?, datetime('now'))) \
+             ON CONFLICT(username) DO UPDATE SET id = excluded.id, password_hash = excluded.password_hash, role = excluded.role, created_at = COALESCE(?, users.created_at)",
+        )
+        .bind(item.id)
+        .bind(&item.username)
+        .bind(&item.password_hash)
+        .bind(&item.role)
+        .bind(&item.created_at)
+        .bind(&item.created_at)
+        .execute(&mut **tx)
+        .await;
+        if result.is_err() {
+            failed.push(item.username.as_str());
+        }
+        writer.record(&item.username, result.map(|_| ()))?;
+    }
+    // Accounts that could not be replaced get their id back
+    for username in failed {
+        let Some(id) = parked.get(username) else {
+            continue;
+        };
+        sqlx::query("UPDATE users SET id = ? WHERE username = ?")
+            .bind(id)
+            .bind(username)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to restore the id of user '{}'", username))?;
+    }
+
+    Ok(())
+}
+
+async fn apply_comments(
+    tx: &mut Transaction<'_, Sqlite>,
+    items: &[CommentImport],
+    pending: &HashSet<&str>,
+    writer: &mut RowWriter<'_>,
+) -> Result<()> {
+    writer.start(Collection::Comments, pending.len());
+    for item in items
+        .iter()
+        .filter(|item| pending.contains(item.key().as_str()))
+    {
+        let result = sqlx::query(
+            "INSERT INTO comments (id, tutorial_id, post_id, author, content, votes, is_admin, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now'))) \
+             ON CONFLICT(id) DO UPDATE SET tutorial_id = excluded.tutorial_id, post_id = excluded.post_id, author = excluded.author, content = excluded.content, votes = excluded.votes, is_admin = excluded.is_admin, created_at = COALESCE(?, comments.created_at)",
+        )
+        .bind(&item.id)
+        .bind(&item.tutorial_id)
+        .bind(&item.post_id)
+        .bind(&item.author)
+        .bind(&item.content)
+        .bind(item.votes)
+        .bind(item.is_admin)
+        .bind(&item.created_at)
+        .bind(&item.created_at)
+        .execute(&mut **tx)
+        .await;
+        writer.record(&item.id, result.map(|_| ()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::models::{CreateSitePageRequest, CreateSitePostRequest, PAGE_VISIBILITY_PUBLIC};
+    use crate::repositories;
+    use serde_json::json;
+
+    async fn create_page(pool: &DbPool, slug: &str, is_published: bool) -> String {
+        repositories::pages::create_site_page(
+            pool,
+            CreateSitePageRequest {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                description: Some(format!("Über {slug}")),
+                nav_label: None,
+                show_in_nav: true,
+                order_index: None,
+                is_published,
+                visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+                hero: json!({ "title": slug }),
+                layout: json!({ "sections": [] }),
+                meta_title: Some(format!("{slug} | Linux")),
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("create page")
+        .id
+    }
+
+    async fn fill(pool: &DbPool) {
+        let published = create_page(pool, "grundlagen", true).await;
+        let draft = create_page(pool, "entwurf", false).await;
+        for (page_id, slug, is_published, allow_comments) in [
+            (&published, "erste-schritte", true, true),
+            (&published, "rechte", false, false),
+            (&draft, "geheim", true, true),
+        ] {
+            repositories::posts::create_site_post(
+                pool,
+                page_id,
+                CreateSitePostRequest {
+                    title: slug.to_string(),
+                    slug: slug.to_string(),
+                    excerpt: None,
+                    content_markdown: format!("# {slug}\n\nText"),
+                    is_published,
+                    allow_comments,
+                    published_at: None,
+                    order_index: None,
+                },
+            )
+            .await
+            .expect("create post");
+        }
+        repositories::content::upsert_site_content(
+            pool,
+            "footer",
+            &json!({ "brand": { "title": "© Linux" }, "quickLinks": [] }),
+            "admin",
+            10,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_collections_parse() {
+        assert_eq!(
+            Collection::parse_list(" pages,posts ,pages").unwrap(),
+            [Collection::Pages, Collection::Posts]
+        );
+        assert_eq!(
+            Collection::parse_list("pages,uploads").unwrap_err(),
+            "Unknown collection 'uploads', expected one of site_content, pages, posts, tutorials, tutorial_topics, users, comments"
+        );
+        assert!(Collection::parse_list(" , ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip_is_byte_identical() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let exported = to_json(
+            &export_bundle(&source, &ExportOptions::default())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        let target = db::pool::create_test_pool().await;
+        // As if seeded at another time
+        sqlx::query("UPDATE tutorials SET created_at = '2000-01-01 00:00:00'")
+            .execute(&target)
+            .await
+            .unwrap();
+        let bundle: ImportBundle = serde_json::from_str(&exported).unwrap();
+        let mut tx = target.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let again = to_json(
+            &export_bundle(&target, &ExportOptions::default())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(again, exported);
+        assert_eq!(bundle.posts.len(), 3);
+        assert!(!bundle.tutorials.is_empty());
+        assert!(!bundle.tutorial_topics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_is_sorted_by_id() {
+        let pool = db::pool::create_test_pool().await;
+        fill(&pool).await;
+        let bundle = export_bundle(&pool, &ExportOptions::default())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = bundle.posts.iter().map(|post| post.id.as_str()).collect();
+        let mut sorted = ids.clone();
+        sorted.sort_unstable();
+        assert_eq!(ids, sorted);
+        let sections: Vec<&str> = bundle
+            .site_content
+            .iter()
+            .map(|entry| entry.section.as_str())
+            .collect();
+        let mut sorted = sections.clone();
+        sorted.sort_unstable();
+        assert_eq!(sections, sorted);
+    }
+
+    #[tokio::test]
+    async fn test_export_options_narrow_the_bundle() {
+        let pool = db::pool::create_test_pool().await;
+        fill(&pool).await;
+
+        let bundle = export_bundle(
+            &pool,
+            &ExportOptions {
+                only: vec![Collection::Pages, Collection::Posts],
+                published_only: true,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(bundle.site_content.is_empty());
+        assert!(bundle.tutorials.is_empty());
+        assert!(bundle.tutorial_topics.is_empty());
+        let pages: Vec<&str> = bundle.pages.iter().map(|page| page.slug.as_str()).collect();
+        assert_eq!(pages, ["grundlagen"]);
+        let posts: Vec<&str> = bundle.posts.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(posts, ["erste-schritte"]);
+    }
+
+    #[tokio::test]
+    async fn test_bundles_without_newer_fields_still_import() {
+        let bundle: ImportBundle = serde_json::from_value(json!({
+            "site_content": [],
+            "pages": [{
+                "id": "p1", "slug": "alt", "title": "Alt", "description": "",
+                "nav_label": null, "show_in_nav": false, "order_index": 0,
+                "is_published": true, "hero": {}, "layout": {}
+            }],
+            "posts": [{
+                "id": "s1", "page_id": "p1", "title": "Alt", "slug": "alt",
+                "excerpt": "", "content_markdown": "Text", "is_published": true,
+                "published_at": null, "order_index": 0
+            }]
+        }))
+        .unwrap();
+        assert_eq!(bundle.pages[0].visibility, PAGE_VISIBILITY_PUBLIC);
+        assert!(bundle.posts[0].allow_comments);
+
+        let pool = db::pool::create_test_pool().await;
+        let topics_before: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tutorial_topics")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let page = repositories::pages::get_site_page_by_slug(&pool, "alt")
+            .await
+            .unwrap()
+            .expect("imported page");
+        assert_eq!(page.id, "p1");
+        let topics_after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tutorial_topics")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(topics_after, topics_before);
+    }
+
+    async fn plan(pool: &DbPool, bundle: &ImportBundle, options: &ImportOptions) -> ImportPlan {
+        let mut conn = pool.acquire().await.unwrap();
+        plan_import(&mut conn, bundle, options).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_predicts_the_import() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let mut bundle = export_bundle(&source, &ExportOptions::default())
+            .await
+            .unwrap();
+        let target = db::pool::create_test_pool().await;
+        let mut tx = target.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let renamed = bundle.pages[0].id.clone();
+        bundle.pages[0].title = "Neu".to_string();
+        bundle.pages[0].hero = json!({ "title": "Neu" });
+        let mut post = bundle.posts[0].clone();
+        post.id = "neuer-beitrag".to_string();
+        post.slug = "neuer-beitrag".to_string();
+        post.created_at = None;
+        post.updated_at = None;
+        bundle.posts.push(post);
+        let dropped = bundle.tutorial_topics.pop().unwrap();
+
+        let predicted = plan(&target, &bundle, &ImportOptions::default())
+            .await
+            .report;
+        assert!(predicted.is_valid());
+        let pages = predicted.collection(Collection::Pages).unwrap();
+        assert_eq!((pages.create, pages.update), (0, 1));
+        let update = pages
+            .changes
+            .iter()
+            .find(|change| change.action == ChangeAction::Update)
+            .unwrap();
+        assert_eq!(update.key, renamed);
+        assert_eq!(update.changed_fields, ["hero", "title"]);
+        let posts = predicted.collection(Collection::Posts).unwrap();
+        assert_eq!((posts.create, posts.update, posts.unchanged), (1, 0, 3));
+        let topics = predicted.collection(Collection::TutorialTopics).unwrap();
+        assert_eq!((topics.create, topics.update, topics.delete), (0, 0, 1));
+        assert_eq!(
+            topics.changes.last().unwrap().key,
+            format!("{}/{}", dropped.tutorial_id, dropped.topic)
+        );
+        // The tutorial follows its topic index
+        let tutorials = predicted.collection(Collection::Tutorials).unwrap();
+        assert_eq!((tutorials.update, tutorials.unchanged), (1, 7));
+        let update = tutorials
+            .changes
+            .iter()
+            .find(|change| change.action == ChangeAction::Update)
+            .unwrap();
+        assert_eq!(update.key, dropped.tutorial_id);
+        assert_eq!(update.changed_fields, ["topics"]);
+        assert!(predicted
+            .collection(Collection::SiteContent)
+            .unwrap()
+            .is_unchanged());
+
+        // Planning wrote nothing
+        let page = repositories::pages::get_site_page_by_id(&target, &renamed)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(page.title, "Neu");
+
+        let mut tx = target.begin().await.unwrap();
+        let applied = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tutorial_topics")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(
+            applied.rebuilt,
+            Some(RebuildReport {
+                tutorials: bundle.tutorials.len(),
+                topics: indexed as usize,
+                posts: 1,
+            })
+        );
+        assert_eq!(
+            ImportReport {
+                rebuilt: None,
+                ..applied
+            },
+            predicted
+        );
+        assert!(!topics_of(&target, &dropped.tutorial_id)
+            .await
+            .contains(&dropped.topic));
+
+        let after = plan(&target, &bundle, &ImportOptions::default())
+            .await
+            .report;
+        assert!(after.collections.iter().all(|report| report.is_unchanged()));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bundles_are_reported_and_not_imported() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let mut bundle = export_bundle(&source, &ExportOptions::default())
+            .await
+            .unwrap();
+        bundle.posts[1].page_id = "fehlt".to_string();
+        let duplicate = bundle.pages[0].clone();
+        bundle.pages.push(duplicate);
+
+        let target = db::pool::create_test_pool().await;
+        let report = plan(&target, &bundle, &ImportOptions::default())
+            .await
+            .report;
+        assert_eq!(
+            report.errors,
+            [
+                format!("pages[2] '{}': duplicate entry", bundle.pages[0].id),
+                format!(
+                    "posts[1] '{}': page 'fehlt' does not exist",
+                    bundle.posts[1].id
+                ),
+            ]
+        );
+
+        let mut tx = target.begin().await.unwrap();
+        let err = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap_err();
+        tx.rollback().await.unwrap();
+        assert!(err.to_string().contains("duplicate entry"), "{err}");
+        let pages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM site_pages")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(pages, 0);
+    }
+
+    #[tokio::test]
+    async fn test_entries_breaking_api_rules_are_rejected_or_skipped() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let mut bundle = export_bundle(&source, &ExportOptions::default())
+            .await
+            .unwrap();
+        let index = |keys: Vec<&str>, key: &str| keys.iter().position(|k| *k == key).unwrap();
+        let footer = index(
+            bundle
+                .site_content
+                .iter()
+                .map(|c| c.section.as_str())
+                .collect(),
+            "footer",
+        );
+        bundle.site_content[footer].content = json!({ "quickLinks": "keine Liste" });
+        let draft = index(
+            bundle.pages.iter().map(|p| p.slug.as_str()).collect(),
+            "entwurf",
+        );
+        bundle.pages[draft].slug = "kein slug".to_string();
+        let rights = index(
+            bundle.posts.iter().map(|p| p.slug.as_str()).collect(),
+            "rechte",
+        );
+        bundle.posts[rights].title = "  ".to_string();
+        bundle.tutorials[0].content = "# Neu".to_string();
+        bundle.tutorials[0].icon = "Rocket".to_string();
+        bundle.tutorial_topics[0].topic = " ".to_string();
+        // Normalized like the API does, not rejected
+        bundle.pages[1 - draft].title = format!("  {}  ", bundle.pages[1 - draft].title);
+
+        let target = db::pool::create_test_pool().await;
+        let rejected = plan(&target, &bundle, &ImportOptions::default()).await;
+        let prefixes: Vec<String> = rejected
+            .report
+            .errors
+            .iter()
+            .map(|error| error.split(':').next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            prefixes,
+            [
+                format!("site_content[{footer}] 'footer'"),
+                format!("pages[{draft}] '{}'", bundle.pages[draft].id),
+                format!("posts[{rights}] '{}'", bundle.posts[rights].id),
+                format!("tutorials[0] '{}'", bundle.tutorials[0].id),
+                format!(
+                    "tutorial_topics[0] '{}/ '",
+                    bundle.tutorial_topics[0].tutorial_id
+                ),
+            ]
+        );
+        assert!(rejected.report.skipped.is_empty());
+
+        let mut tx = target.begin().await.unwrap();
+        let err = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap_err();
+        tx.rollback().await.unwrap();
+        assert!(err.to_string().contains("Topic cannot be empty"), "{err}");
+        let pages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM site_pages")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(pages, 0);
+
+        let options = ImportOptions {
+            skip_invalid: true,
+            ..ImportOptions::default()
+        };
+        let mut tx = target.begin().await.unwrap();
+        let report = import_bundle(&mut tx, &bundle, &options).await.unwrap();
+        tx.commit().await.unwrap();
+        assert!(report.errors.is_empty());
+        // The post on the skipped page goes too
+        assert_eq!(report.skipped.len(), 6, "{:?}", report.skipped);
+        let orphan = format!("page '{}' does not exist", bundle.pages[draft].id);
+        assert!(report
+            .skipped
+            .iter()
+            .any(|skipped| skipped.ends_with(&orphan)));
+
+        let stored = repositories::pages::list_site_pages(&target, false, 50, 0)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].title, "grundlagen");
+        let posts: Vec<String> = sqlx::query_scalar("SELECT slug FROM site_posts")
+            .fetch_all(&target)
+            .await
+            .unwrap();
+        assert_eq!(posts, ["erste-schritte"]);
+        let icon: String = sqlx::query_scalar("SELECT icon FROM tutorials WHERE id = ?")
+            .bind(&bundle.tutorials[0].id)
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_ne!(icon, "Rocket");
+    }
+
+    #[tokio::test]
+    async fn test_timestamps_must_be_readable() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let mut bundle = export_bundle(&source, &ExportOptions::default())
+            .await
+            .unwrap();
+        bundle.tutorials[0].created_at = Some("2024-05-01 10:00:00".to_string());
+        bundle.tutorials[0].updated_at = Some("2024-05-02T08:30:00+02:00".to_string());
+        bundle.posts[0].published_at = Some("letzten Dienstag".to_string());
+
+        let target = db::pool::create_test_pool().await;
+        let rejected = plan(&target, &bundle, &ImportOptions::default()).await;
+        assert_eq!(
+            rejected.report.errors.len(),
+            1,
+            "{:?}",
+            rejected.report.errors
+        );
+        assert!(rejected.report.errors[0].contains("published_at 'letzten Dienstag'"));
+
+        bundle.posts[0].published_at = None;
+        let mut tx = target.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let tutorial = repositories::tutorials::get_tutorial(&target, &bundle.tutorials[0].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            tutorial.created_at.to_rfc3339(),
+            "2024-05-01T10:00:00+00:00"
+        );
+        assert_eq!(
+            tutorial.updated_at.to_rfc3339(),
+            "2024-05-02T06:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_strategies_parse() {
+        assert_eq!(
+            "skip-existing".parse::<ImportStrategy>(),
+            Ok(ImportStrategy::SkipExisting)
+        );
+        assert_eq!(
+            "merge".parse::<ImportStrategy>().unwrap_err(),
+            "Unknown strategy 'merge', expected one of overwrite, skip-existing, fail-on-conflict"
+        );
+    }
+
+    /// A target holding the exported content of a source.
+    async fn imported(pool: &DbPool) -> (DbPool, ImportBundle) {
+        fill(pool).await;
+        let bundle = export_bundle(pool, &ExportOptions::default())
+            .await
+            .unwrap();
+        let target = db::pool::create_test_pool().await;
+        let mut tx = target.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        (target, bundle)
+    }
+
+    async fn topics_of(pool: &DbPool, tutorial_id: &str) -> Vec<String> {
+        sqlx::query_scalar("SELECT topic FROM tutorial_topics WHERE tutorial_id = ? ORDER BY topic")
+            .bind(tutorial_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    fn new_post(bundle: &ImportBundle, slug: &str) -> SitePostImport {
+        SitePostImport {
+            id: slug.to_string(),
+            slug: slug.to_string(),
+            created_at: None,
+            updated_at: None,
+            ..bundle.posts[0].clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skip_existing_only_creates() {
+        let source = db::pool::create_test_pool().await;
+        let (target, mut bundle) = imported(&source).await;
+        bundle.pages[0].title = "Geändert".to_string();
+        bundle.posts.push(new_post(&bundle, "neuer-beitrag"));
+        let kept = topics_of(&target, "1").await;
+        bundle
+            .tutorial_topics
+            .retain(|topic| topic.tutorial_id != "1");
+        let mut tutorial = bundle.tutorials[0].clone();
+        tutorial.id = "neu".to_string();
+        tutorial.content = "# Neu".to_string();
+        bundle.tutorials.push(tutorial);
+        bundle.tutorial_topics.push(TutorialTopicImport {
+            tutorial_id: "neu".to_string(),
+            topic: "Shell".to_string(),
+        });
+
+        let options = ImportOptions {
+            strategy: ImportStrategy::SkipExisting,
+            ..ImportOptions::default()
+        };
+        let mut tx = target.begin().await.unwrap();
+        let report = import_bundle(&mut tx, &bundle, &options).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let pages = report.collection(Collection::Pages).unwrap();
+        assert_eq!((pages.update, pages.skip), (0, 1));
+        let posts = report.collection(Collection::Posts).unwrap();
+        assert_eq!(posts.create, 1);
+        let page = repositories::pages::get_site_page_by_id(&target, &bundle.pages[0].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(page.title, "Geändert");
+        let created: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM site_posts WHERE id = ?")
+            .bind("neuer-beitrag")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(created, 1);
+        // Only the topics of the new tutorial are written
+        assert_eq!(topics_of(&target, "1").await, kept);
+        assert_eq!(topics_of(&target, "neu").await, ["Shell"]);
+        assert_eq!(
+            report
+                .collection(Collection::TutorialTopics)
+                .unwrap()
+                .delete,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_conflict_lists_the_conflicts() {
+        let source = db::pool::create_test_pool().await;
+        let (target, mut bundle) = imported(&source).await;
+        bundle.pages[1].title = "Geändert".to_string();
+        bundle.posts.push(new_post(&bundle, "neuer-beitrag"));
+
+        let options = ImportOptions {
+            strategy: ImportStrategy::FailOnConflict,
+            ..ImportOptions::default()
+        };
+        let report = plan(&target, &bundle, &options).await.report;
+        assert_eq!(
+            report.errors,
+            [format!(
+                "pages[1] '{}': conflicts with the stored entry (title)",
+                bundle.pages[1].id
+            )]
+        );
+
+        let mut tx = target.begin().await.unwrap();
+        let err = import_bundle(&mut tx, &bundle, &options).await.unwrap_err();
+        tx.rollback().await.unwrap();
+        assert!(err.to_string().contains("conflicts"), "{err}");
+        let created: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM site_posts WHERE id = ?")
+            .bind("neuer-beitrag")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(created, 0);
+
+        // Entries equal to the stored ones are no conflict
+        bundle.pages[1].title =
+            repositories::pages::get_site_page_by_id(&target, &bundle.pages[1].id)
+                .await
+                .unwrap()
+                .unwrap()
+                .title;
+        let mut tx = target.begin().await.unwrap();
+        import_bundle(&mut tx, &bundle, &options).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_only_touches_the_selected_collections() {
+        let source = db::pool::create_test_pool().await;
+        let (target, mut bundle) = imported(&source).await;
+        bundle.pages[0].title = "Geändert".to_string();
+        bundle.posts[0].title = "Auch geändert".to_string();
+        let others = topics_of(&target, "2").await;
+        assert!(!others.is_empty());
+        bundle
+            .tutorial_topics
+            .retain(|topic| topic.tutorial_id == "1");
+        let dropped = bundle.tutorial_topics.pop().unwrap();
+
+        let options = ImportOptions {
+            only: vec![Collection::Pages, Collection::TutorialTopics],
+            ..ImportOptions::default()
+        };
+        let mut tx = target.begin().await.unwrap();
+        let report = import_bundle(&mut tx, &bundle, &options).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let reported: Vec<Collection> = report.collections.iter().map(|r| r.collection).collect();
+        assert_eq!(reported, [Collection::Pages, Collection::TutorialTopics]);
+        let page = repositories::pages::get_site_page_by_id(&target, &bundle.pages[0].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(page.title, "Geändert");
+        let post_title: String = sqlx::query_scalar("SELECT title FROM site_posts WHERE id = ?")
+            .bind(&bundle.posts[0].id)
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_ne!(post_title, "Auch geändert");
+        // Without tutorials only the topics of the tutorials named are replaced
+        let topics = report.collection(Collection::TutorialTopics).unwrap();
+        assert_eq!(topics.delete, 1);
+        assert!(!topics_of(&target, "1").await.contains(&dropped.topic));
+        assert_eq!(topics_of(&target, "2").await, others);
+        // and the tutorial takes the topics left
+        let stored: String = sqlx::query_scalar("SELECT topics FROM tutorials WHERE id = '1'")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        let mut stored: Vec<String> = serde_json::from_str(&stored).unwrap();
+        stored.sort();
+        assert_eq!(stored, topics_of(&target, "1").await);
+    }
+
+    async fn add_user(pool: &DbPool, id: i64, username: &str, role: &str) {
+        sqlx::query("INSERT INTO users (id, username, password_hash, role) VALUES (?, ?, ?, ?)")
+            .bind(id)
+            .bind(username)
+            .bind(bcrypt::hash(username, 4).unwrap())
+            .bind(role)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn add_comment(
+        pool: &DbPool,
+        id: &str,
+        tutorial_id: Option<&str>,
+        post_id: Option<&str>,
+    ) {
+        sqlx::query(
+            "INSERT INTO comments (id, tutorial_id, post_id, author, content, votes, is_admin) \
+             VALUES (?, ?, ?, 'leser', 'Danke &amp; Gruß', 3, 0)",
+        )
+        .bind(id)
+        .bind(tutorial_id)
+        .bind(post_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn everything() -> ExportOptions {
+        ExportOptions {
+            only: Collection::ALL.to_vec(),
+            published_only: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_users_and_comments_round_trip() {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        add_user(&source, 7, "redaktion", "admin").await;
+        add_user(&source, 9, "leser", "user").await;
+        let post_id: String =
+            sqlx::query_scalar("SELECT id FROM site_posts WHERE slug = 'erste-schritte'")
+                .fetch_one(&source)
+                .await
+                .unwrap();
+        let tutorial_id: String = sqlx::query_scalar("SELECT id FROM tutorials ORDER BY id")
+            .fetch_one(&source)
+            .await
+            .unwrap();
+        add_comment(&source, "c-post", None, Some(&post_id)).await;
+        add_comment(&source, "c-tutorial", Some(&tutorial_id), None).await;
+
+        let content_only = to_json(
+            &export_bundle(&source, &ExportOptions::default())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!content_only.contains("\"users\""));
+        assert!(!content_only.contains("\"comments\""));
+
+        let exported = to_json(&export_bundle(&source, &everything()).await.unwrap()).unwrap();
+        let bundle: ImportBundle = serde_json::from_str(&exported).unwrap();
+        assert_eq!(
+            bundle
+                .users
+                .iter()
+                .map(|user| (user.id, user.username.as_str()))
+                .collect::<Vec<_>>(),
+            [(7, "redaktion"), (9, "leser")]
+        );
+        let comment = &bundle.comments[0];
+        assert_eq!(comment.id, "c-post");
+        assert_eq!(comment.post_id.as_deref(), Some(post_id.as_str()));
+        assert_eq!(
+            (comment.votes, comment.content.as_str()),
+            (3, "Danke &amp; Gruß")
+        );
+
+        let target = db::pool::create_test_pool().await;
+        sqlx::query("UPDATE tutorials SET created_at = '2000-01-01 00:00:00'")
+            .execute(&target)
+            .await
+            .unwrap();
+        let mut tx = target.begin().await.unwrap();
+        let report = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        assert_eq!(report.collection(Collection::Users).unwrap().create, 2);
+        assert_eq!(report.collection(Collection::Comments).unwrap().create, 2);
+
+        let again = to_json(&export_bundle(&target, &everything()).await.unwrap()).unwrap();
+        assert_eq!(again, exported);
+        let user = repositories::users::get_user_by_username(&target, "leser")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(bcrypt::verify("leser", &user.password_hash).unwrap());
+
+        // A content-only bundle says nothing about users and comments
+        let report = plan(
+            &target,
+            &serde_json::from_str(&content_only).unwrap(),
+            &ImportOptions::default(),
+        )
+        .await
+        .report;
+        assert!(report.collection(Collection::Users).is_none());
+        assert!(report.collection(Collection::Comments).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_user_and_comment_rules() {
+        let pool = db::pool::create_test_pool().await;
+        add_user(&pool, 3, "redaktion", "user").await;
+        let hash = bcrypt::hash("geheim", 4).unwrap();
+        let user = |id, username: &str, role: &str, password_hash: &str| UserImport {
+            id,
+            username: username.to_string(),
+            password_hash: password_hash.to_string(),
+            role: role.to_string(),
+            created_at: None,
+        };
+        let comment = |id: &str, post_id: &str| CommentImport {
+            id: id.to_string(),
+            tutorial_id: None,
+            post_id: Some(post_id.to_string()),
+            author: "leser".to_string(),
+            content: "Danke".to_string(),
+            votes: 0,
+            is_admin: false,
+            created_at: None,
+        };
+        let bundle = ImportBundle {
+            users: vec![
+                user(4, "root", "superuser", &hash),
+                user(5, "klartext", "user", "hunter2"),
+                user(3, "gast", "user", &hash),
+                user(6, "redaktion", "admin", &hash),
+            ],
+            comments: vec![comment("c1", "fehlt")],
+            ..ImportBundle::default()
+        };
+
+        // "gast" may take id 3, which "redaktion" gives up
+        let report = plan(&pool, &bundle, &ImportOptions::default()).await.report;
+        assert_eq!(
+            report.errors,
+            [
+                "users[0] 'root': Unknown role 'superuser', expected one of admin, user",
+                "users[1] 'klartext': Password hash must be a bcrypt hash",
+            ]
+        );
+        assert_eq!(
+            report.skipped,
+            ["comments[0] 'c1': post 'fehlt' does not exist"]
+        );
+
+        let bundle = ImportBundle {
+            users: bundle.users[2..].to_vec(),
+            comments: vec![comment("c1", "fehlt")],
+            ..ImportBundle::default()
+        };
+        let skip_existing = ImportOptions {
+            strategy: ImportStrategy::SkipExisting,
+            ..ImportOptions::default()
+        };
+        let report = plan(&pool, &bundle, &skip_existing).await.report;
+        assert_eq!(
+            report.errors,
+            ["users[1] 'redaktion': username is taken; only an overwrite import replaces an account"]
+        );
+        let report = plan(
+            &pool,
+            &ImportBundle {
+                users: vec![user(3, "gast", "user", &hash)],
+                ..ImportBundle::default()
+            },
+            &ImportOptions::default(),
+        )
+        .await
+        .report;
+        assert_eq!(
+            report.errors,
+            ["users[0] 'gast': id 3 belongs to user 'redaktion'"]
+        );
+
+        let mut tx = pool.begin().await.unwrap();
+        let report = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        assert_eq!(report.skipped.len(), 1);
+        let redaktion = repositories::users::get_user_by_username(&pool, "redaktion")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((redaktion.id, redaktion.role.as_str()), (6, "admin"));
+        let gast = repositories::users::get_user_by_username(&pool, "gast")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(gast.id, 3);
+        let comments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(comments, 0);
+    }
+
+    /// Pages and posts of a source, and a page reusing the first page's slug.
+    async fn clashing_bundle() -> ImportBundle {
+        let source = db::pool::create_test_pool().await;
+        fill(&source).await;
+        let mut bundle = export_bundle(
+            &source,
+            &ExportOptions {
+                only: vec![Collection::Pages, Collection::Posts],
+                ..ExportOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        bundle.pages.push(SitePageImport {
+            id: "doppelt".to_string(),
+            ..bundle.pages[0].clone()
+        });
+        bundle
+    }
+
+    async fn count(pool: &DbPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error_logs_and_skips_failed_rows() {
+        let bundle = clashing_bundle().await;
+        let pool = db::pool::create_test_pool().await;
+        let options = ImportOptions {
+            continue_on_error: true,
+            ..ImportOptions::default()
+        };
+
+        let mut log = Vec::new();
+        let mut tx = pool.begin().await.unwrap();
+        let plan = plan_import(&mut tx, &bundle, &options).await.unwrap();
+        let report = apply_plan_with(&mut tx, plan, &mut |event| {
+            log.push(serde_json::to_string(event).unwrap())
+        })
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        let events: Vec<Value> = log
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let failed: Vec<&Value> = events
+            .iter()
+            .filter(|event| event["event"] == "row_failed")
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0]["collection"], "pages");
+        assert_eq!(failed[0]["key"], "doppelt");
+        assert_eq!(failed[0]["field"], "slug");
+        let collection = |name: &str| {
+            events
+                .iter()
+                .find(|event| event["event"] == "collection" && event["collection"] == name)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            collection("pages"),
+            json!({
+                "event": "collection",
+                "collection": "pages",
+                "processed": 3,
+                "upserted": 2,
+                "skipped": 0,
+                "failed": 1,
+            })
+        );
+        assert_eq!(collection("posts")["upserted"], 3);
+        assert!(events.contains(&json!({
+            "event": "progress",
+            "collection": "posts",
+            "processed": 3,
+            "total": 3,
+        })));
+
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            report.failed[0].to_string(),
+            format!("pages 'doppelt': slug: {}", report.failed[0].error)
+        );
+        assert_eq!(count(&pool, "site_pages").await, 2);
+        assert_eq!(count(&pool, "site_posts").await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_rows_abort_the_import_by_default() {
+        let bundle = clashing_bundle().await;
+        let pool = db::pool::create_test_pool().await;
+
+        let mut tx = pool.begin().await.unwrap();
+        let err = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap_err();
+        drop(tx);
+
+        assert!(
+            err.to_string()
+                .starts_with("Failed to import pages 'doppelt': slug: "),
+            "{err}"
+        );
+        assert_eq!(count(&pool, "site_pages").await, 0);
+        assert_eq!(count(&pool, "site_posts").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_parse_bundle_checks_the_format_version() {
+        // Written before bundles were stamped: the defaults of the fields
+        // added since apply
+        let (old, warnings) = parse_bundle(
+            br#"{
+                "site_content": [],
+                "pages": [{ "id": "p1", "slug": "alt", "title": "Alt", "description": "",
+                            "nav_label": null, "show_in_nav": true, "order_index": 0,
+                            "is_published": true, "hero": {}, "layout": {} }],
+                "posts": [{ "id": "b1", "page_id": "p1", "title": "Alt", "slug": "alt",
+                            "excerpt": "", "content_markdown": "Text",
+                            "is_published": true, "published_at": null, "order_index": 0 }]
+            }"#,
+        )
+        .unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(old.format_version, 0);
+        assert_eq!(old.pages[0].visibility, PAGE_VISIBILITY_PUBLIC);
+        assert!(old.posts[0].allow_comments);
+        assert!(old.tutorials.is_empty());
+
+        let pool = db::pool::create_test_pool().await;
+        fill(&pool).await;
+        let current = export_bundle(&pool, &ExportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(current.format_version, FORMAT_VERSION);
+        let mut json = serde_json::to_value(&current).unwrap();
+        let (parsed, warnings) = parse_bundle(json.to_string().as_bytes()).unwrap();
+        assert_eq!(parsed, current);
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        json["media"] = json!([]);
+        json["posts"][1]["tags"] = json!(["shell"]);
+        json["pages"][0]["hero"] = json!({ "title": "bleibt" });
+        let (_, warnings) = parse_bundle(json.to_string().as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "media: unknown field, ignored",
+                "posts[1].tags: unknown field, ignored"
+            ]
+        );
+
+        json["format_version"] = json!(FORMAT_VERSION + 1);
+        json["posts"] = json!({ "by_page": {} });
+        let err = parse_bundle(json.to_string().as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "The bundle has format version {}, but this build reads up to version {}; \
+                 import it with a newer release",
+                FORMAT_VERSION + 1,
+                FORMAT_VERSION
+            )
+        );
+        let err = parse_bundle(b"{ \"format_version\": \"2.0\" }").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid bundle");
+    }
+}
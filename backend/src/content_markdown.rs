@@ -0,0 +1,749 @@
+//! Markdown directories as content bundles.
+//!
+//! Writers draft posts as `pages/<page-slug>/<post-slug>.md` files: YAML
+//! frontmatter between two `---` lines, then the Markdown of the post. The
+//! directory and file names are the slugs. [`read_posts`] reads such a
+//! directory and [`to_bundle`] turns the posts into an [`ImportBundle`] for
+//! the regular import, keyed by page slug and post slug: a post that exists
+//! is updated, a page that does not is created with the defaults of the API.
+//!
+//! The frontmatter is a flat map of the keys below; values are plain or
+//! quoted scalars, or lists written `[a, b]` or as `- item` lines:
+//!
+//! ```text
+//! ---
+//! title: Erste Schritte
+//! excerpt: "Die Shell in fünf Minuten"
+//! published: true
+//! published_at: 2024-03-01T09:00:00Z
+//! order: 1
+//! tags: [shell, einstieg]
+//! ---
+//! ```
+//!
+//! Only `title` is required. Without an excerpt one is derived from the
+//! Markdown; without an order the posts of a page follow their file names.
+
+use crate::content_bundle::{
+    self, Collection, ExportOptions, ImportBundle, SitePageImport, SitePostImport,
+};
+use crate::models::PAGE_VISIBILITY_PUBLIC;
+use crate::utils::markdown::derive_excerpt;
+use crate::validation::{self, posts};
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+use sqlx::SqliteConnection;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A post read from a Markdown file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownPost {
+    pub path: PathBuf,
+    pub page_slug: String,
+    pub slug: String,
+    pub title: String,
+    pub excerpt: Option<String>,
+    pub published: bool,
+    pub published_at: Option<String>,
+    pub order: Option<i64>,
+    /// Read, but not stored: posts have no tags.
+    pub tags: Vec<String>,
+    pub content: String,
+}
+
+/// A frontmatter problem, at a 1-based line of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontmatterError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FrontmatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for FrontmatterError {}
+
+fn error_at(line: usize, message: impl Into<String>) -> FrontmatterError {
+    FrontmatterError {
+        line,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FrontmatterValue {
+    Scalar { value: String, quoted: bool },
+    List(Vec<String>),
+}
+
+/// The frontmatter of a file, as written.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    pub published: Option<bool>,
+    pub published_at: Option<String>,
+    pub order: Option<i64>,
+    pub tags: Vec<String>,
+}
+
+/// Splits `source` into its frontmatter and the Markdown after it.
+pub fn parse_frontmatter(source: &str) -> Result<(Frontmatter, String), FrontmatterError> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line));
+    match lines.next() {
+        Some((_, line)) if line.trim_end() == "---" => {}
+        _ => return Err(error_at(1, "Expected frontmatter starting with '---'")),
+    }
+
+    let mut entries: Vec<(usize, String, FrontmatterValue)> = Vec::new();
+    let mut closed = false;
+    for (number, line) in lines.by_ref() {
+        if line.trim_end() == "---" {
+            closed = true;
+            break;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""))
+        {
+            let item = parse_scalar(item, number)?.0;
+            let Some((_, key, value)) = entries.last_mut() else {
+                return Err(error_at(number, "List item without a key"));
+            };
+            let starts_list = matches!(
+                value,
+                FrontmatterValue::Scalar { value, quoted: false } if value.is_empty()
+            );
+            match value {
+                FrontmatterValue::List(items) => items.push(item),
+                _ if starts_list => *value = FrontmatterValue::List(vec![item]),
+                _ => {
+                    return Err(error_at(
+                        number,
+                        format!("'{}' has a value and cannot also have list items", key),
+                    ))
+                }
+            }
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            return Err(error_at(number, "Unexpected indentation"));
+        }
+        let Some((key, raw)) = trimmed.split_once(':') else {
+            return Err(error_at(
+                number,
+                format!("Expected 'key: value', got '{}'", trimmed),
+            ));
+        };
+        let key = key.trim().to_string();
+        if entries.iter().any(|(_, existing, _)| *existing == key) {
+            return Err(error_at(number, format!("Duplicate key '{}'", key)));
+        }
+        let raw = raw.trim();
+        let value = if let Some(list) = raw.strip_prefix('[') {
+            let list = list
+                .strip_suffix(']')
+                .ok_or_else(|| error_at(number, "Unterminated list, expected ']'"))?;
+            FrontmatterValue::List(
+                split_flow_list(list, number)?
+                    .into_iter()
+                    .map(|item| parse_scalar(item, number).map(|(value, _)| value))
+                    .collect::<Result<_, _>>()?,
+            )
+        } else {
+            let (value, quoted) = parse_scalar(raw, number)?;
+            FrontmatterValue::Scalar { value, quoted }
+        };
+        entries.push((number, key, value));
+    }
+    if !closed {
+        return Err(error_at(
+            source.lines().count().max(1),
+            "Unterminated frontmatter, expected a closing '---'",
+        ));
+    }
+
+    let mut frontmatter = Frontmatter::default();
+    for (number, key, value) in entries {
+        match key.as_str() {
+            "title" => frontmatter.title = Some(expect_text(value, &key, number)?),
+            "excerpt" => frontmatter.excerpt = Some(expect_text(value, &key, number)?),
+            "published" => {
+                frontmatter.published = Some(match expect_plain(value, &key, number)?.as_str() {
+                    "true" | "yes" => true,
+                    "false" | "no" => false,
+                    other => {
+                        return Err(error_at(
+                            number,
+                            format!("'published' must be true or false, got '{}'", other),
+                        ))
+                    }
+                })
+            }
+            "published_at" => {
+                let text = expect_text(value, &key, number)?;
+                chrono::DateTime::parse_from_rfc3339(&text).map_err(|_| {
+                    error_at(
+                        number,
+                        format!("'published_at' must be an RFC 3339 timestamp, got '{}'", text),
+                    )
+                })?;
+                frontmatter.published_at = Some(text);
+            }
+            "order" => {
+                let text = expect_plain(value, &key, number)?;
+                frontmatter.order = Some(text.parse().map_err(|_| {
+                    error_at(number, format!("'order' must be an integer, got '{}'", text))
+                })?);
+            }
+            "tags" => {
+                frontmatter.tags = match value {
+                    FrontmatterValue::List(items) => items,
+                    FrontmatterValue::Scalar { value, .. } if value.is_empty() => Vec::new(),
+                    FrontmatterValue::Scalar { value, .. } => vec![value],
+                }
+            }
+            _ => {
+                return Err(error_at(
+                    number,
+                    format!(
+                        "Unknown key '{}', expected title, excerpt, published, published_at, order or tags",
+                        key
+                    ),
+                ))
+            }
+        }
+    }
+
+    let body: Vec<&str> = lines.map(|(_, line)| line).collect();
+    let mut content = body.join("\n").trim().to_string();
+    content.push('\n');
+    Ok((frontmatter, content))
+}
+
+/// A plain or quoted scalar; plain ones end at a ` #` comment.
+fn parse_scalar(raw: &str, line: usize) -> Result<(String, bool), FrontmatterError> {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    return match chars.as_str().trim() {
+                        "" => Ok((value, true)),
+                        tail if tail.starts_with('#') => Ok((value, true)),
+                        tail => Err(error_at(
+                            line,
+                            format!("Unexpected '{}' after quoted value", tail),
+                        )),
+                    }
+                }
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(escaped @ ('"' | '\\')) => value.push(escaped),
+                    _ => return Err(error_at(line, "Invalid escape in quoted value")),
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err(error_at(line, "Unterminated quoted value"));
+    }
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let mut value = String::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push('\'');
+                    continue;
+                }
+                let tail: String = chars.collect();
+                let tail = tail.trim();
+                if tail.is_empty() || tail.starts_with('#') {
+                    return Ok((value, true));
+                }
+                return Err(error_at(
+                    line,
+                    format!("Unexpected '{}' after quoted value", tail),
+                ));
+            }
+            value.push(c);
+        }
+        return Err(error_at(line, "Unterminated quoted value"));
+    }
+    let value = match raw.find(" #") {
+        Some(comment) => &raw[..comment],
+        None => raw,
+    };
+    Ok((value.trim().to_string(), false))
+}
+
+/// Splits the inside of `[a, "b, c"]` at the commas outside quotes.
+fn split_flow_list(list: &str, line: usize) -> Result<Vec<&str>, FrontmatterError> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    for (index, c) in list.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ',') => {
+                items.push(&list[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        return Err(error_at(line, "Unterminated quoted value in list"));
+    }
+    items.push(&list[start..]);
+    if items.len() == 1 && items[0].trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    if items.iter().any(|item| item.trim().is_empty()) {
+        return Err(error_at(line, "Empty list item"));
+    }
+    Ok(items)
+}
+
+fn expect_text(
+    value: FrontmatterValue,
+    key: &str,
+    line: usize,
+) -> Result<String, FrontmatterError> {
+    match value {
+        FrontmatterValue::Scalar { value, .. } => Ok(value),
+        FrontmatterValue::List(_) => {
+            Err(error_at(line, format!("'{}' must be a single value", key)))
+        }
+    }
+}
+
+/// An unquoted scalar, for booleans and numbers.
+fn expect_plain(
+    value: FrontmatterValue,
+    key: &str,
+    line: usize,
+) -> Result<String, FrontmatterError> {
+    match value {
+        FrontmatterValue::Scalar {
+            value,
+            quoted: false,
+        } => Ok(value.to_lowercase()),
+        _ => Err(error_at(
+            line,
+            format!("'{}' must be an unquoted value", key),
+        )),
+    }
+}
+
+/// Reads the posts under `<dir>/pages`, ordered by page slug and file name.
+/// Every other file is ignored.
+pub fn read_posts(dir: &Path) -> Result<Vec<MarkdownPost>> {
+    let pages_dir = dir.join("pages");
+    let mut page_dirs = sorted_entries(&pages_dir)?;
+    page_dirs.retain(|path| path.is_dir());
+
+    let mut posts = Vec::new();
+    for page_dir in page_dirs {
+        let page_slug = slug_of(&page_dir)?;
+        let mut files = sorted_entries(&page_dir)?;
+        files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"));
+        for path in files {
+            posts.push(read_post(&path, &page_slug)?);
+        }
+    }
+    Ok(posts)
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// The slug a directory or file name stands for.
+fn slug_of(path: &Path) -> Result<String> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("{}: the name is not valid UTF-8", path.display()))?;
+    let slug = posts::sanitize_slug(stem);
+    validation::validate_slug(&slug).map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+    Ok(slug)
+}
+
+fn read_post(path: &Path, page_slug: &str) -> Result<MarkdownPost> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (frontmatter, content) = parse_frontmatter(&source)
+        .map_err(|err| anyhow!("{}:{}: {}", path.display(), err.line, err.message))?;
+    let title = frontmatter
+        .title
+        .filter(|title| !title.trim().is_empty())
+        .ok_or_else(|| anyhow!("{}:1: the frontmatter has no 'title'", path.display()))?;
+    Ok(MarkdownPost {
+        path: path.to_path_buf(),
+        page_slug: page_slug.to_string(),
+        slug: slug_of(path)?,
+        title,
+        excerpt: frontmatter
+            .excerpt
+            .filter(|excerpt| !excerpt.trim().is_empty()),
+        published: frontmatter.published.unwrap_or(false),
+        published_at: frontmatter.published_at,
+        order: frontmatter.order,
+        tags: frontmatter.tags,
+        content,
+    })
+}
+
+/// `linux-grundlagen` becomes `Linux Grundlagen`.
+fn title_from_slug(slug: &str) -> String {
+    slug.split('-')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The bundle that writes `markdown_posts`: the pages they need that do not
+/// exist yet, and the posts, updating the stored post of the same page and
+/// slug. A post keeps what its file does not set, such as whether comments
+/// are allowed.
+pub async fn to_bundle(
+    conn: &mut SqliteConnection,
+    markdown_posts: &[MarkdownPost],
+) -> Result<ImportBundle> {
+    let current = content_bundle::read_bundle(
+        conn,
+        &ExportOptions {
+            only: vec![Collection::Pages, Collection::Posts],
+            published_only: false,
+        },
+    )
+    .await?;
+
+    let mut page_ids: HashMap<String, String> = current
+        .pages
+        .iter()
+        .map(|page| (page.slug.clone(), page.id.clone()))
+        .collect();
+    let stored_posts: HashMap<(&str, &str), &SitePostImport> = current
+        .posts
+        .iter()
+        .map(|post| ((post.page_id.as_str(), post.slug.as_str()), post))
+        .collect();
+    let mut next_order = current
+        .pages
+        .iter()
+        .map(|page| page.order_index + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut bundle = ImportBundle {
+        format_version: content_bundle::FORMAT_VERSION,
+        ..ImportBundle::default()
+    };
+    let mut position: HashMap<&str, i64> = HashMap::new();
+    for post in markdown_posts {
+        let page_id = match page_ids.get(&post.page_slug) {
+            Some(id) => id.clone(),
+            None => {
+                let id = uuid::Uuid::new_v4().to_string();
+                let title = title_from_slug(&post.page_slug);
+                bundle.pages.push(SitePageImport {
+                    id: id.clone(),
+                    slug: post.page_slug.clone(),
+                    hero: json!({ "title": title }),
+                    title,
+                    description: String::new(),
+                    nav_label: None,
+                    show_in_nav: false,
+                    order_index: next_order,
+                    is_published: false,
+                    visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+                    layout: json!({ "sections": [] }),
+                    meta_title: None,
+                    meta_description: None,
+                    og_image: None,
+                    created_at: None,
+                    updated_at: None,
+                    deleted_at: None,
+                });
+                next_order += 1;
+                page_ids.insert(post.page_slug.clone(), id.clone());
+                id
+            }
+        };
+
+        let index = position.entry(post.page_slug.as_str()).or_insert(0);
+        let order_index = post.order.unwrap_or(*index);
+        *index += 1;
+
+        let (excerpt, excerpt_auto) = match &post.excerpt {
+            Some(excerpt) => (excerpt.clone(), false),
+            None => (derive_excerpt(&post.content), true),
+        };
+        let title = post.title.clone();
+        let written = match stored_posts.get(&(page_id.as_str(), post.slug.as_str())) {
+            Some(stored) => SitePostImport {
+                title,
+                excerpt,
+                excerpt_auto,
+                content_markdown: post.content.clone(),
+                is_published: post.published,
+                published_at: post.published_at.clone(),
+                order_index,
+                updated_at: None,
+                ..(*stored).clone()
+            },
+            None => SitePostImport {
+                id: uuid::Uuid::new_v4().to_string(),
+                page_id,
+                title,
+                slug: post.slug.clone(),
+                excerpt,
+                excerpt_auto,
+                content_markdown: post.content.clone(),
+                is_published: post.published,
+                allow_comments: true,
+                published_at: post.published_at.clone(),
+                order_index,
+                created_at: None,
+                updated_at: None,
+            },
+        };
+        bundle.posts.push(written);
+    }
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content_bundle::{import_bundle, ImportOptions};
+    use crate::db;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/markdown")
+    }
+
+    #[test]
+    fn test_frontmatter_is_parsed() {
+        let (frontmatter, content) = parse_frontmatter(
+            "---\n\
+             title: \"Rechte: chmod & Co.\"\n\
+             excerpt: 'Wer darf''s?' # kurz\n\
+             published: yes\n\
+             order: 3\n\
+             tags:\n  - rechte\n  - \"chmod\"\n\
+             ---\n\n# Rechte\n",
+        )
+        .unwrap();
+        assert_eq!(
+            frontmatter,
+            Frontmatter {
+                title: Some("Rechte: chmod & Co.".to_string()),
+                excerpt: Some("Wer darf's?".to_string()),
+                published: Some(true),
+                published_at: None,
+                order: Some(3),
+                tags: vec!["rechte".to_string(), "chmod".to_string()],
+            }
+        );
+        assert_eq!(content, "# Rechte\n");
+
+        let (frontmatter, _) =
+            parse_frontmatter("---\ntitle: T\ntags: [a, \"b, c\"]\n---\n").unwrap();
+        assert_eq!(frontmatter.tags, ["a", "b, c"]);
+    }
+
+    #[test]
+    fn test_frontmatter_errors_name_the_line() {
+        let error = |source: &str| parse_frontmatter(source).unwrap_err().to_string();
+        assert_eq!(
+            error("# Kein Frontmatter\n"),
+            "line 1: Expected frontmatter starting with '---'"
+        );
+        assert_eq!(
+            error("---\ntitle: T\npublished: vielleicht\n---\n"),
+            "line 3: 'published' must be true or false, got 'vielleicht'"
+        );
+        assert_eq!(
+            error("---\ntitle: T\n\norder: erste\n---\n"),
+            "line 4: 'order' must be an integer, got 'erste'"
+        );
+        assert_eq!(
+            error("---\ntitle: \"offen\n---\n"),
+            "line 2: Unterminated quoted value"
+        );
+        assert_eq!(
+            error("---\ntitel: T\n---\n"),
+            "line 2: Unknown key 'titel', expected title, excerpt, published, published_at, order or tags"
+        );
+        assert_eq!(
+            error("---\ntitle: T\ntitle: U\n---\n"),
+            "line 3: Duplicate key 'title'"
+        );
+        assert_eq!(
+            error("---\ntitle: T\n"),
+            "line 2: Unterminated frontmatter, expected a closing '---'"
+        );
+        assert!(error("---\npublished_at: gestern\n---\n").starts_with("line 2: 'published_at'"));
+    }
+
+    #[test]
+    fn test_file_errors_name_the_file_and_line() {
+        let dir = std::env::temp_dir().join(format!("markdown-import-{}", uuid::Uuid::new_v4()));
+        let page = dir.join("pages/grundlagen");
+        fs::create_dir_all(&page).unwrap();
+        let file = page.join("kaputt.md");
+        fs::write(&file, "---\ntitle: T\norder: x\n---\n").unwrap();
+
+        let err = read_posts(&dir).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            format!("{}:3: 'order' must be an integer, got 'x'", file.display())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fixture_directory_imports_into_rows() {
+        let markdown_posts = read_posts(&fixture()).unwrap();
+        let slugs: Vec<(&str, &str)> = markdown_posts
+            .iter()
+            .map(|post| (post.page_slug.as_str(), post.slug.as_str()))
+            .collect();
+        assert_eq!(
+            slugs,
+            [
+                ("linux-grundlagen", "dateien"),
+                ("linux-grundlagen", "erste-schritte"),
+                ("netzwerk", "ssh"),
+            ]
+        );
+
+        let pool = db::pool::create_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let bundle = to_bundle(&mut tx, &markdown_posts).await.unwrap();
+        import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let pages: Vec<(String, String, bool)> =
+            sqlx::query_as("SELECT slug, title, is_published FROM site_pages ORDER BY slug")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            pages,
+            [
+                (
+                    "linux-grundlagen".to_string(),
+                    "Linux Grundlagen".to_string(),
+                    false
+                ),
+                ("netzwerk".to_string(), "Netzwerk".to_string(), false),
+            ]
+        );
+        // slug, title, excerpt, excerpt_auto, is_published, published_at, order_index
+        type PostRow = (String, String, String, bool, bool, Option<String>, i64);
+        let posts: Vec<PostRow> = sqlx::query_as(
+            "SELECT p.slug, p.title, p.excerpt, p.excerpt_auto, p.is_published, p.published_at, p.order_index \
+             FROM site_posts p JOIN site_pages g ON g.id = p.page_id ORDER BY g.slug, p.order_index",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            posts,
+            [
+                (
+                    "erste-schritte".to_string(),
+                    "Erste Schritte".to_string(),
+                    "Die Shell in fünf Minuten".to_string(),
+                    false,
+                    true,
+                    Some("2024-03-01T09:00:00Z".to_string()),
+                    0,
+                ),
+                (
+                    "dateien".to_string(),
+                    "Dateien und Verzeichnisse".to_string(),
+                    derive_excerpt(&markdown_posts[0].content),
+                    true,
+                    false,
+                    None,
+                    1,
+                ),
+                (
+                    "ssh".to_string(),
+                    "SSH".to_string(),
+                    derive_excerpt(&markdown_posts[2].content),
+                    true,
+                    true,
+                    None,
+                    0,
+                ),
+            ]
+        );
+        let content: String =
+            sqlx::query_scalar("SELECT content_markdown FROM site_posts WHERE slug = 'ssh'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(content, markdown_posts[2].content);
+
+        // A second run updates the same rows and keeps what the files do not set
+        sqlx::query("UPDATE site_posts SET allow_comments = 0, title = 'Alt' WHERE slug = 'ssh'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let bundle = to_bundle(&mut tx, &markdown_posts).await.unwrap();
+        assert!(bundle.pages.is_empty());
+        let report = import_bundle(&mut tx, &bundle, &ImportOptions::default())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let posts = report.collection(Collection::Posts).unwrap();
+        assert_eq!((posts.create, posts.update, posts.unchanged), (0, 1, 2));
+        let (title, allow_comments): (String, bool) =
+            sqlx::query_as("SELECT title, allow_comments FROM site_posts WHERE slug = 'ssh'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((title.as_str(), allow_comments), ("SSH", false));
+    }
+}
@@ -1,7 +1,12 @@
 use sqlx::{Sqlite, Transaction};
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
 use super::pool::DbPool;
 use super::seed::{seed_site_content_tx, insert_default_tutorials_tx};
+use crate::repositories;
+use crate::validation::users::validate_new_password;
+
+static MIGRATIONS_COMPLETE: AtomicBool = AtomicBool::new(false);
 
 /// Runs all database migrations and initial data seeding.
 ///
@@ -15,6 +20,7 @@ use super::seed::{seed_site_content_tx, insert_default_tutorials_tx};
 /// 4. **Default Content**: Seed default site content (hero, footer, etc.)
 /// 5. **Admin User**: Create admin account from environment variables
 /// 6. **Default Tutorials**: Optionally seed sample tutorials
+/// 7. **Token Blacklist**: Purge revoked tokens that have expired
 ///
 /// # Admin User Creation
 /// If `ADMIN_USERNAME` and `ADMIN_PASSWORD` are set:
@@ -38,7 +44,7 @@ use super::seed::{seed_site_content_tx, insert_default_tutorials_tx};
 ///
 /// # Errors
 /// - Schema creation failure
-/// - Admin password too weak (< 12 characters)
+/// - Admin password too weak (< 12 characters) or too long
 /// - bcrypt hashing failure
 /// - Transaction rollback on any error
 ///
@@ -57,6 +63,24 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
 
     tx.commit().await?;
 
+    // Apply user schema migrations (token_version)
+    {
+        let mut tx = pool.begin().await?;
+        if let Err(err) = apply_user_migrations(&mut tx).await {
+            tracing::error!("Failed to apply user migrations: {}", err);
+        }
+        tx.commit().await?;
+    }
+
+    // Apply tutorial schema migrations (word_count, reading_time_minutes)
+    {
+        let mut tx = pool.begin().await?;
+        if let Err(err) = apply_tutorial_migrations(&mut tx).await {
+            tracing::error!("Failed to apply tutorial migrations: {}", err);
+        }
+        tx.commit().await?;
+    }
+
     // Apply comment schema migrations (add post_id)
     {
         let mut tx = pool.begin().await?;
@@ -101,6 +125,33 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
         tx.commit().await?;
     }
 
+    // Apply site page schema migrations (trash, visibility and SEO columns)
+    {
+        let mut tx = pool.begin().await?;
+        if let Err(err) = apply_site_page_migrations(&mut tx).await {
+            tracing::error!("Failed to apply site page migrations: {}", err);
+        }
+        tx.commit().await?;
+    }
+
+    // Apply site content schema migrations (updated_by, revision)
+    {
+        let mut tx = pool.begin().await?;
+        if let Err(err) = apply_site_content_migrations(&mut tx).await {
+            tracing::error!("Failed to apply site content migrations: {}", err);
+        }
+        tx.commit().await?;
+    }
+
+    // Apply upload schema migrations (original_size_bytes, kind)
+    {
+        let mut tx = pool.begin().await?;
+        if let Err(err) = apply_upload_migrations(&mut tx).await {
+            tracing::error!("Failed to apply upload migrations: {}", err);
+        }
+        tx.commit().await?;
+    }
+
     // Seed default site content (hero, footer, etc.)
     {
         let mut tx = pool.begin().await?;
@@ -114,11 +165,9 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
 
     match (admin_username, admin_password) {
         (Some(username), Some(password)) if !username.is_empty() && !password.is_empty() => {
-            if password.len() < 12 {
-                tracing::error!(
-                    "ADMIN_PASSWORD must be at least 12 characters long (NIST recommendation)!"
-                );
-                return Err(sqlx::Error::Protocol("Admin password too weak".into()));
+            if let Err(err) = validate_new_password(&password) {
+                tracing::error!("Invalid ADMIN_PASSWORD: {}", err);
+                return Err(sqlx::Error::Protocol("Invalid admin password".into()));
             }
 
             let existing_user: Option<(i64, String)> =
@@ -206,9 +255,22 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
 
     tx.commit().await?;
 
+    // Tokens revoked before the restart that have expired since
+    if let Err(err) = repositories::token_blacklist::purge_expired(pool, chrono::Utc::now()).await
+    {
+        tracing::error!("Failed to purge expired blacklisted tokens: {}", err);
+    }
+
+    MIGRATIONS_COMPLETE.store(true, Ordering::Release);
     Ok(())
 }
 
+/// Whether [`run_migrations`] has finished in this process; the readiness
+/// probe reports not ready until it has.
+pub fn migrations_complete() -> bool {
+    MIGRATIONS_COMPLETE.load(Ordering::Acquire)
+}
+
 async fn apply_core_migrations(
     tx: &mut Transaction<'_, Sqlite>,
 ) -> Result<(), sqlx::Error> {
@@ -250,6 +312,58 @@ async fn apply_core_migrations(
     .execute(&mut **tx)
     .await?;
 
+    // Addresses or CIDR ranges refused before routing; `expires_at` NULL is permanent
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS ip_bans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ip TEXT NOT NULL UNIQUE,
+            reason TEXT,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TEXT
+        )
+        "#,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    // Admin mutations recorded by the audit middleware; bodies are never stored
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT,
+            method TEXT NOT NULL,
+            path TEXT NOT NULL,
+            route TEXT,
+            status INTEGER NOT NULL,
+            latency_ms INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    // Outcome of the latest run of each scheduled job, see `crate::jobs`
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS job_runs (
+            name TEXT PRIMARY KEY,
+            last_run_at TEXT NOT NULL,
+            last_success_at TEXT,
+            duration_ms INTEGER NOT NULL,
+            succeeded INTEGER NOT NULL,
+            error TEXT,
+            runs INTEGER NOT NULL DEFAULT 0,
+            failures INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(&mut **tx)
+    .await?;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS tutorials (
@@ -261,6 +375,8 @@ async fn apply_core_migrations(
             topics TEXT NOT NULL,
             content TEXT NOT NULL DEFAULT '',
             version INTEGER NOT NULL DEFAULT 1,
+            word_count INTEGER NOT NULL DEFAULT 0,
+            reading_time_minutes INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT (datetime('now')),
             updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )
@@ -308,6 +424,20 @@ async fn apply_core_migrations(
         .execute(&mut **tx)
         .await?;
 
+    // Kept apart from `tutorials` so counting a view never touches the row,
+    // its version or the search index
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tutorial_views (
+            tutorial_id TEXT PRIMARY KEY,
+            views INTEGER NOT NULL DEFAULT 0,
+            CONSTRAINT fk_tutorial_views_tutorial FOREIGN KEY (tutorial_id) REFERENCES tutorials(id) ON DELETE CASCADE ON UPDATE CASCADE
+        )
+        "#,
+    )
+    .execute(&mut **tx)
+    .await?;
+
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS comments (
@@ -406,12 +536,33 @@ async fn ensure_site_page_schema(pool: &DbPool) -> Result<(), sqlx::Error> {
         "CREATE TABLE IF NOT EXISTS site_content (
             section TEXT PRIMARY KEY,
             content_json TEXT NOT NULL,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_by TEXT,
+            revision INTEGER NOT NULL DEFAULT 0
         )",
     )
     .execute(&mut *tx)
     .await?;
 
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS site_content_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            section TEXT NOT NULL,
+            content_json TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            updated_by TEXT,
+            archived_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_site_content_history_section ON site_content_history(section, id)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS site_pages (
             id TEXT PRIMARY KEY,
@@ -422,10 +573,15 @@ async fn ensure_site_page_schema(pool: &DbPool) -> Result<(), sqlx::Error> {
             show_in_nav INTEGER NOT NULL DEFAULT 0,
             order_index INTEGER NOT NULL DEFAULT 0,
             is_published INTEGER NOT NULL DEFAULT 0,
+            visibility TEXT NOT NULL DEFAULT 'public',
             hero_json TEXT NOT NULL DEFAULT '{}',
             layout_json TEXT NOT NULL DEFAULT '{}',
+            meta_title TEXT,
+            meta_description TEXT,
+            og_image TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            deleted_at TEXT
         )",
     )
     .execute(&mut *tx)
@@ -444,7 +600,10 @@ async fn ensure_site_page_schema(pool: &DbPool) -> Result<(), sqlx::Error> {
             title TEXT NOT NULL,
             slug TEXT NOT NULL,
             excerpt TEXT DEFAULT '',
+            excerpt_auto INTEGER NOT NULL DEFAULT 0,
             content_markdown TEXT NOT NULL,
+            word_count INTEGER NOT NULL DEFAULT 0,
+            reading_time_minutes INTEGER NOT NULL DEFAULT 0,
             is_published INTEGER NOT NULL DEFAULT 0,
             allow_comments BOOLEAN NOT NULL DEFAULT 1,
             published_at TEXT,
@@ -469,6 +628,76 @@ async fn ensure_site_page_schema(pool: &DbPool) -> Result<(), sqlx::Error> {
     .execute(&mut *tx)
     .await?;
 
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_site_posts_published ON site_posts(is_published, published_at)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS layout_blocks (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            block_json TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS content_sections (
+            name TEXT PRIMARY KEY,
+            description TEXT NOT NULL DEFAULT '',
+            schema_json TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS uploads (
+            id TEXT PRIMARY KEY,
+            filename TEXT NOT NULL UNIQUE,
+            original_filename TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            width INTEGER,
+            height INTEGER,
+            uploaded_by TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_uploads_created_at ON uploads(created_at)")
+        .execute(&mut *tx)
+        .await?;
+
+    // Daily upload quota per user
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_uploads_uploaded_by_created_at ON uploads(uploaded_by, created_at)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS upload_sessions (
+            id TEXT PRIMARY KEY,
+            file_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            chunk_size INTEGER NOT NULL,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TEXT NOT NULL
+        )",
+    )
+    .execute(&mut *tx)
+    .await?;
+
     tx.commit().await?;
 
     Ok(())
@@ -497,6 +726,18 @@ async fn apply_comment_migrations(
             .await?;
     }
 
+    // Newest-first pages and their cursors, `(datetime(created_at), id) < (datetime(?), ?)`
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_comments_tutorial_created ON comments(tutorial_id, datetime(created_at), id)",
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_comments_post_created ON comments(post_id, datetime(created_at), id)",
+    )
+    .execute(&mut **tx)
+    .await?;
+
     Ok(())
 }
 
@@ -613,6 +854,16 @@ async fn fix_comment_schema(
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_comments_post ON comments(post_id)")
         .execute(&mut **tx)
         .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_comments_tutorial_created ON comments(tutorial_id, datetime(created_at), id)",
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_comments_post_created ON comments(post_id, datetime(created_at), id)",
+    )
+    .execute(&mut **tx)
+    .await?;
 
     // 6. Mark as fixed
     sqlx::query("INSERT INTO app_metadata (key, value) VALUES ('comment_schema_fixed_v1', 'true')")
@@ -622,6 +873,223 @@ async fn fix_comment_schema(
     Ok(())
 }
 
+async fn apply_user_migrations(tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
+    // Bumped on password change to revoke every older session
+    let has_token_version: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('users') WHERE name='token_version'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_token_version {
+        tracing::info!("Adding token_version column to users table");
+        sqlx::query("ALTER TABLE users ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn apply_tutorial_migrations(tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
+    // Check if word_count/reading_time_minutes columns exist
+    let has_text_stats: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('tutorials') WHERE name='word_count'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_text_stats {
+        tracing::info!("Adding word_count and reading_time_minutes columns to tutorials table");
+        sqlx::query("ALTER TABLE tutorials ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query(
+            "ALTER TABLE tutorials ADD COLUMN reading_time_minutes INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        // Backfill existing tutorials
+        let tutorials: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, content FROM tutorials")
+                .fetch_all(&mut **tx)
+                .await?;
+        for (id, content) in tutorials {
+            let stats = crate::utils::textstats::compute(&content);
+            sqlx::query(
+                "UPDATE tutorials SET word_count = ?, reading_time_minutes = ? WHERE id = ?",
+            )
+            .bind(stats.word_count)
+            .bind(stats.reading_time_minutes)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn apply_site_content_migrations(
+    tx: &mut Transaction<'_, Sqlite>,
+) -> Result<(), sqlx::Error> {
+    // Check if updated_by column exists
+    let has_updated_by: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_content') WHERE name='updated_by'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_updated_by {
+        tracing::info!("Adding updated_by column to site_content table");
+        sqlx::query("ALTER TABLE site_content ADD COLUMN updated_by TEXT")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Bumped by every write; updated_at only has a resolution of one second
+    let has_revision: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_content') WHERE name='revision'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_revision {
+        tracing::info!("Adding revision column to site_content table");
+        sqlx::query("ALTER TABLE site_content ADD COLUMN revision INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn apply_upload_migrations(tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
+    // Size before processing; NULL for files stored unchanged
+    let has_original_size: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('uploads') WHERE name='original_size_bytes'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_original_size {
+        tracing::info!("Adding original_size_bytes column to uploads table");
+        sqlx::query("ALTER TABLE uploads ADD COLUMN original_size_bytes INTEGER")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Images vs downloadable files
+    let has_kind: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('uploads') WHERE name='kind'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_kind {
+        tracing::info!("Adding kind column to uploads table");
+        sqlx::query("ALTER TABLE uploads ADD COLUMN kind TEXT NOT NULL DEFAULT 'image'")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Draft uploads are kept private until the content using them is published
+    let has_visibility: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('uploads') WHERE name='visibility'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_visibility {
+        tracing::info!("Adding visibility column to uploads table");
+        sqlx::query("ALTER TABLE uploads ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public'")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Accessibility metadata for embedded images
+    for column in ["alt_text", "caption"] {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('uploads') WHERE name = ?",
+        )
+        .bind(column)
+        .fetch_one(&mut **tx)
+        .await
+        .map(|count: i64| count > 0)?;
+
+        if !exists {
+            tracing::info!("Adding {} column to uploads table", column);
+            sqlx::query(&format!("ALTER TABLE uploads ADD COLUMN {column} TEXT"))
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn apply_site_page_migrations(
+    tx: &mut Transaction<'_, Sqlite>,
+) -> Result<(), sqlx::Error> {
+    // Check if deleted_at column exists
+    let has_deleted_at: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_pages') WHERE name='deleted_at'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_deleted_at {
+        tracing::info!("Adding deleted_at column to site_pages table");
+        sqlx::query("ALTER TABLE site_pages ADD COLUMN deleted_at TEXT")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Check if visibility column exists
+    let has_visibility: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_pages') WHERE name='visibility'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_visibility {
+        tracing::info!("Adding visibility column to site_pages table");
+        sqlx::query("ALTER TABLE site_pages ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public'")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Per-page SEO overrides for the frontend meta injection
+    for column in ["meta_title", "meta_description", "og_image"] {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('site_pages') WHERE name = ?",
+        )
+        .bind(column)
+        .fetch_one(&mut **tx)
+        .await
+        .map(|count: i64| count > 0)?;
+
+        if !exists {
+            tracing::info!("Adding {} column to site_pages table", column);
+            sqlx::query(&format!("ALTER TABLE site_pages ADD COLUMN {column} TEXT"))
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
 async fn apply_site_post_migrations(
     tx: &mut Transaction<'_, Sqlite>,
 ) -> Result<(), sqlx::Error> {
@@ -640,5 +1108,64 @@ async fn apply_site_post_migrations(
             .await?;
     }
 
+    // Check if excerpt_auto column exists
+    let has_excerpt_auto: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_posts') WHERE name='excerpt_auto'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_excerpt_auto {
+        tracing::info!("Adding excerpt_auto column to site_posts table");
+        sqlx::query("ALTER TABLE site_posts ADD COLUMN excerpt_auto INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    // Check if word_count/reading_time_minutes columns exist
+    let has_text_stats: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('site_posts') WHERE name='word_count'",
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map(|count: i64| count > 0)?;
+
+    if !has_text_stats {
+        tracing::info!("Adding word_count and reading_time_minutes columns to site_posts table");
+        sqlx::query("ALTER TABLE site_posts ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query(
+            "ALTER TABLE site_posts ADD COLUMN reading_time_minutes INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        // Backfill existing posts
+        let posts: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, content_markdown FROM site_posts")
+                .fetch_all(&mut **tx)
+                .await?;
+        for (id, content) in posts {
+            let stats = crate::utils::textstats::compute(&content);
+            sqlx::query(
+                "UPDATE site_posts SET word_count = ?, reading_time_minutes = ? WHERE id = ?",
+            )
+            .bind(stats.word_count)
+            .bind(stats.reading_time_minutes)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        }
+    }
+
+    // The public feed pages by this expression; see `repositories::posts`
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_site_posts_sorted_at ON site_posts(datetime(COALESCE(published_at, created_at)), id)",
+    )
+    .execute(&mut **tx)
+    .await?;
+
     Ok(())
 }
@@ -1,5 +1,6 @@
 pub mod migrations;
 pub mod pool;
 pub mod seed;
+pub mod timing;
 
 pub use pool::{create_pool, DbPool};
@@ -52,12 +52,16 @@ pub async fn create_pool() -> Result<DbPool, sqlx::Error> {
         tracing::warn!("DATABASE_URL not set, defaulting to sqlite:./database.db");
         "sqlite:./database.db".to_string()
     });
+    create_pool_with_url(&database_url).await
+}
 
+/// Opens and migrates the database at `database_url`, see [`create_pool`].
+pub async fn create_pool_with_url(database_url: &str) -> Result<DbPool, sqlx::Error> {
     // Ensure parent directory exists
-    ensure_sqlite_directory(&database_url)?;
+    ensure_sqlite_directory(database_url)?;
 
     // Configure SQLite connection options
-    let connect_options = SqliteConnectOptions::from_str(&database_url)?
+    let connect_options = SqliteConnectOptions::from_str(database_url)?
         .create_if_missing(true)
         .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
         .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
@@ -81,6 +85,26 @@ pub async fn create_pool() -> Result<DbPool, sqlx::Error> {
     Ok(pool)
 }
 
+/// Folds the write-ahead log back into the database file and closes the
+/// pool, so the next start has no WAL to recover. Called last on shutdown.
+pub async fn close_pool(pool: &DbPool) {
+    match sqlx::query_as::<_, (i64, i64, i64)>("PRAGMA wal_checkpoint(TRUNCATE)")
+        .fetch_one(pool)
+        .await
+    {
+        Ok((0, _, _)) => tracing::info!("Checkpointed and truncated the write-ahead log"),
+        Ok((_, log_frames, checkpointed)) => tracing::warn!(
+            "WAL checkpoint was blocked; {} of {} frame(s) checkpointed",
+            checkpointed,
+            log_frames
+        ),
+        Err(err) => tracing::error!("Failed to checkpoint the write-ahead log: {}", err),
+    }
+
+    pool.close().await;
+    tracing::info!("Database pool closed");
+}
+
 fn ensure_sqlite_directory(database_url: &str) -> Result<(), sqlx::Error> {
     if let Some(db_path) = sqlite_file_path(database_url) {
         if let Some(parent) = db_path.parent() {
@@ -128,3 +152,29 @@ fn sqlite_file_path(database_url: &str) -> Option<PathBuf> {
 
     Some(PathBuf::from(normalized))
 }
+
+/// Creates a migrated in-memory database for tests.
+///
+/// The pool is pinned to a single connection that never expires, since every
+/// new SQLite `:memory:` connection would otherwise see an empty database.
+#[cfg(test)]
+pub async fn create_test_pool() -> DbPool {
+    let connect_options = SqliteConnectOptions::from_str("sqlite::memory:")
+        .expect("valid in-memory sqlite url")
+        .foreign_keys(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(connect_options)
+        .await
+        .expect("Failed to create in-memory test pool");
+
+    run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations on test pool");
+
+    pool
+}
@@ -25,7 +25,9 @@ pub async fn seed_site_content_tx(
     Ok(())
 }
 
-fn default_site_content() -> Vec<(&'static str, serde_json::Value)> {
+/// Default content for a fresh database. Section names must be listed in
+/// [`crate::models::SITE_CONTENT_SECTIONS`] to be reachable through the API.
+pub(crate) fn default_site_content() -> Vec<(&'static str, serde_json::Value)> {
     vec![
         (
             "hero",
@@ -138,6 +140,28 @@ fn default_site_content() -> Vec<(&'static str, serde_json::Value)> {
                 }
             }),
         ),
+        (
+            "stats",
+            json!({
+                "items": [
+                    { "value": "10k+", "label": "Leser monatlich" },
+                    { "value": "500+", "label": "Artikel" },
+                    { "value": "20+", "label": "Themenbereiche" },
+                    { "value": "Active", "label": "Community" }
+                ]
+            }),
+        ),
+        (
+            "cta_section",
+            json!({
+                "title": "Wissen teilen & erweitern",
+                "description": "Bleib auf dem Laufenden mit den neuesten Entwicklungen in der IT-Welt.",
+                "primary": {
+                    "label": "Zum Blog",
+                    "target": { "type": "route", "value": "/blog" }
+                }
+            }),
+        ),
         (
             "grundlagen_page",
             json!({
@@ -310,7 +334,7 @@ pub async fn insert_default_tutorials_tx(
             continue;
         }
 
-        if let Err(err) = crate::handlers::tutorials::validate_icon(icon) {
+        if let Err(err) = crate::validation::tutorials::validate_icon(icon) {
             tracing::warn!(
                 "Skipping default tutorial '{}' due to invalid icon: {}",
                 id,
@@ -319,7 +343,7 @@ pub async fn insert_default_tutorials_tx(
             continue;
         }
 
-        if let Err(err) = crate::handlers::tutorials::validate_color(color) {
+        if let Err(err) = crate::validation::tutorials::validate_color(color) {
             tracing::warn!(
                 "Skipping default tutorial '{}' due to invalid color: {}",
                 id,
@@ -333,8 +357,7 @@ pub async fn insert_default_tutorials_tx(
                 format!(
                     "Failed to serialize topics for default tutorial '{}': {}",
                     id, e
-                )
-                .into(),
+                ),
             )
         })?;
 
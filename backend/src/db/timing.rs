@@ -0,0 +1,219 @@
+//! Execution time of named database queries.
+//!
+//! Wrap a query (or a whole transaction) in [`timed_query`] to record its
+//! duration in a per-name histogram and log a warning under the `slow_query`
+//! target when it takes at least `SLOW_QUERY_MS` milliseconds (default 250,
+//! `0` logs every timed query). The fast path takes a shared lock and bumps a
+//! few atomics.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+const DEFAULT_SLOW_QUERY_MS: u64 = 250;
+
+/// Upper bounds of the histogram buckets in milliseconds; slower queries
+/// only count towards the total.
+pub const BUCKET_BOUNDS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 5000];
+
+static SLOW_QUERY_THRESHOLD: OnceLock<Duration> = OnceLock::new();
+
+fn parse_threshold(value: Option<String>) -> Result<Duration, String> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(Duration::from_millis(DEFAULT_SLOW_QUERY_MS)),
+        Some(value) => value
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .map_err(|_| {
+                format!(
+                    "SLOW_QUERY_MS must be a whole number of milliseconds, got '{}'",
+                    value
+                )
+            }),
+    }
+}
+
+/// Validates `SLOW_QUERY_MS` and fixes the threshold for the process.
+pub fn init_slow_query_threshold() -> Result<(), String> {
+    SLOW_QUERY_THRESHOLD
+        .set(parse_threshold(std::env::var("SLOW_QUERY_MS").ok())?)
+        .map_err(|_| "Slow query threshold already initialized".to_string())
+}
+
+fn slow_query_threshold() -> Duration {
+    *SLOW_QUERY_THRESHOLD.get_or_init(|| {
+        parse_threshold(std::env::var("SLOW_QUERY_MS").ok()).unwrap_or_else(|err| {
+            tracing::error!("{}; using the default slow query threshold", err);
+            Duration::from_millis(DEFAULT_SLOW_QUERY_MS)
+        })
+    })
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKET_BOUNDS_MS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let millis = elapsed.as_millis();
+        if let Some(index) = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| millis <= u128::from(*bound))
+        {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(
+            u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+type Histograms = RwLock<HashMap<&'static str, Arc<Histogram>>>;
+
+fn histograms() -> &'static Histograms {
+    static HISTOGRAMS: OnceLock<Histograms> = OnceLock::new();
+    HISTOGRAMS.get_or_init(Default::default)
+}
+
+fn histogram(name: &'static str) -> Arc<Histogram> {
+    if let Some(histogram) = histograms()
+        .read()
+        .ok()
+        .and_then(|histograms| histograms.get(name).cloned())
+    {
+        return histogram;
+    }
+    match histograms().write() {
+        Ok(mut histograms) => histograms.entry(name).or_default().clone(),
+        // Poisoned: keep serving queries, just stop recording them.
+        Err(_) => Arc::default(),
+    }
+}
+
+/// Recorded timings of one query name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryStats {
+    pub name: &'static str,
+    pub count: u64,
+    pub sum_micros: u64,
+    /// Cumulative counts per entry of [`BUCKET_BOUNDS_MS`].
+    pub buckets: Vec<u64>,
+}
+
+/// Timings of every query name seen so far, sorted by name.
+pub fn query_stats() -> Vec<QueryStats> {
+    let Ok(histograms) = histograms().read() else {
+        return Vec::new();
+    };
+    let mut stats: Vec<_> = histograms
+        .iter()
+        .map(|(name, histogram)| QueryStats {
+            name,
+            count: histogram.count.load(Ordering::Relaxed),
+            sum_micros: histogram.sum_micros.load(Ordering::Relaxed),
+            buckets: histogram
+                .buckets
+                .iter()
+                .scan(0, |total, bucket| {
+                    *total += bucket.load(Ordering::Relaxed);
+                    Some(*total)
+                })
+                .collect(),
+        })
+        .collect();
+    stats.sort_by_key(|stats| stats.name);
+    stats
+}
+
+/// Runs `query`, recording its duration under `name`.
+pub async fn timed_query<F: Future>(name: &'static str, query: F) -> F::Output {
+    let started = Instant::now();
+    let output = query.await;
+    let elapsed = started.elapsed();
+
+    histogram(name).observe(elapsed);
+    let threshold = slow_query_threshold();
+    if elapsed >= threshold {
+        tracing::warn!(
+            target: "slow_query",
+            query = name,
+            duration_ms = elapsed.as_secs_f64() * 1000.0,
+            threshold_ms = threshold.as_millis() as u64,
+            "Slow database query"
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{tests::Captured, JsonLayer};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_threshold_parsing() {
+        assert_eq!(parse_threshold(None).unwrap(), Duration::from_millis(250));
+        assert_eq!(
+            parse_threshold(Some(" 40 ".to_string())).unwrap(),
+            Duration::from_millis(40)
+        );
+        assert_eq!(
+            parse_threshold(Some("0".to_string())).unwrap(),
+            Duration::ZERO
+        );
+        assert!(parse_threshold(Some("fast".to_string())).is_err());
+        assert!(parse_threshold(Some("-1".to_string())).is_err());
+    }
+
+    async fn sleepy_query(delay: Duration) -> Result<i64, sqlx::Error> {
+        tokio::time::sleep(delay).await;
+        Ok(1)
+    }
+
+    #[tokio::test]
+    async fn test_slow_queries_are_logged_and_recorded() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(JsonLayer::new(captured.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let fast = timed_query("test.fast", sleepy_query(Duration::ZERO)).await;
+        assert_eq!(fast.unwrap(), 1);
+        let slow = timed_query("test.slow", sleepy_query(Duration::from_millis(300))).await;
+        assert_eq!(slow.unwrap(), 1);
+
+        let warnings: Vec<_> = captured
+            .lines()
+            .into_iter()
+            .filter(|line| line["target"] == "slow_query")
+            .collect();
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0]["level"], "WARN");
+        assert_eq!(warnings[0]["query"], "test.slow");
+        assert!(warnings[0]["duration_ms"].as_f64().unwrap() >= 300.0);
+        assert_eq!(warnings[0]["threshold_ms"], 250);
+
+        let stats = query_stats();
+        let slow = stats
+            .iter()
+            .find(|stats| stats.name == "test.slow")
+            .unwrap();
+        assert_eq!(slow.count, 1);
+        assert!(slow.sum_micros >= 300_000);
+        // 300ms lands in the 500ms bucket
+        assert_eq!(slow.buckets[6], 0);
+        assert_eq!(slow.buckets[7], 1);
+        let fast = stats
+            .iter()
+            .find(|stats| stats.name == "test.fast")
+            .unwrap();
+        assert_eq!((fast.count, fast.buckets[0]), (1, 1));
+    }
+}
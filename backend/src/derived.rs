@@ -0,0 +1,266 @@
+//! Data the database derives from the content: the topic index
+//! (`tutorial_topics`), the search index (`tutorials_fts`) and the word
+//! counts and reading times of tutorials and posts.
+//!
+//! The API keeps these in step as it writes, and triggers keep the search
+//! index in step with `tutorials`. Writes that bypass the API, such as a
+//! content import, leave them behind; [`rebuild`] recomputes them for the
+//! tutorials and posts it is given, or for everything.
+//!
+//! A tutorial's `topics` column is the source of truth: the topic index and
+//! the `topics` column of the search index are recomputed from it, never the
+//! other way round.
+
+use crate::repositories::tutorials::replace_tutorial_topics_tx;
+use crate::utils::textstats;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::{Sqlite, Transaction};
+use std::collections::HashSet;
+
+/// What [`rebuild`] recomputes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildScope {
+    /// Every tutorial and post, dropping index rows of deleted tutorials.
+    Everything,
+    /// The tutorials and posts with these ids, e.g. those an import wrote.
+    Only {
+        tutorials: HashSet<String>,
+        posts: HashSet<String>,
+    },
+}
+
+impl RebuildScope {
+    fn includes_tutorial(&self, id: &str) -> bool {
+        match self {
+            RebuildScope::Everything => true,
+            RebuildScope::Only { tutorials, .. } => tutorials.contains(id),
+        }
+    }
+
+    fn includes_post(&self, id: &str) -> bool {
+        match self {
+            RebuildScope::Everything => true,
+            RebuildScope::Only { posts, .. } => posts.contains(id),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            RebuildScope::Everything => false,
+            RebuildScope::Only { tutorials, posts } => tutorials.is_empty() && posts.is_empty(),
+        }
+    }
+}
+
+/// How much [`rebuild`] recomputed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RebuildReport {
+    /// Tutorials whose topic index, search rows and word count were rebuilt.
+    pub tutorials: usize,
+    /// Rows written to the topic index.
+    pub topics: usize,
+    /// Posts whose word count and reading time were recomputed.
+    pub posts: usize,
+}
+
+/// Recomputes the derived data of the tutorials and posts in `scope`, in the
+/// transaction that wrote them.
+pub async fn rebuild(
+    tx: &mut Transaction<'_, Sqlite>,
+    scope: &RebuildScope,
+) -> Result<RebuildReport> {
+    let mut report = RebuildReport::default();
+    if scope.is_empty() {
+        return Ok(report);
+    }
+
+    let tutorials: Vec<(String, String, String)> =
+        sqlx::query_as("SELECT id, topics, content FROM tutorials ORDER BY id")
+            .fetch_all(&mut **tx)
+            .await
+            .context("Failed to read tutorials")?;
+    for (id, topics, content) in tutorials
+        .iter()
+        .filter(|(id, _, _)| scope.includes_tutorial(id))
+    {
+        let topics: Vec<String> = serde_json::from_str(topics)
+            .with_context(|| format!("Tutorial '{}' has invalid topics JSON", id))?;
+        replace_tutorial_topics_tx(tx, id, &topics)
+            .await
+            .with_context(|| format!("Failed to rebuild the topics of tutorial '{}'", id))?;
+        let stats = textstats::compute(content);
+        sqlx::query("UPDATE tutorials SET word_count = ?, reading_time_minutes = ? WHERE id = ?")
+            .bind(stats.word_count)
+            .bind(stats.reading_time_minutes)
+            .bind(id)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to update the word count of tutorial '{}'", id))?;
+        report.tutorials += 1;
+        report.topics += topics.len();
+    }
+
+    match scope {
+        RebuildScope::Everything => {
+            sqlx::query("DELETE FROM tutorials_fts")
+                .execute(&mut **tx)
+                .await
+                .context("Failed to clear the search index")?;
+            sqlx::query(
+                "INSERT INTO tutorials_fts(tutorial_id, title, description, content, topics) \
+                 SELECT id, title, description, content, topics FROM tutorials",
+            )
+            .execute(&mut **tx)
+            .await
+            .context("Failed to rebuild the search index")?;
+        }
+        RebuildScope::Only { tutorials, .. } => {
+            let mut ids: Vec<&String> = tutorials.iter().collect();
+            ids.sort();
+            for id in ids {
+                sqlx::query("DELETE FROM tutorials_fts WHERE tutorial_id = ?")
+                    .bind(id)
+                    .execute(&mut **tx)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to clear the search rows of tutorial '{}'", id)
+                    })?;
+                sqlx::query(
+                    "INSERT INTO tutorials_fts(tutorial_id, title, description, content, topics) \
+                     SELECT id, title, description, content, topics FROM tutorials WHERE id = ?",
+                )
+                .bind(id)
+                .execute(&mut **tx)
+                .await
+                .with_context(|| {
+                    format!("Failed to rebuild the search rows of tutorial '{}'", id)
+                })?;
+            }
+        }
+    }
+
+    let posts: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, content_markdown FROM site_posts ORDER BY id")
+            .fetch_all(&mut **tx)
+            .await
+            .context("Failed to read posts")?;
+    for (id, content) in posts.iter().filter(|(id, _)| scope.includes_post(id)) {
+        let stats = textstats::compute(content);
+        sqlx::query("UPDATE site_posts SET word_count = ?, reading_time_minutes = ? WHERE id = ?")
+            .bind(stats.word_count)
+            .bind(stats.reading_time_minutes)
+            .bind(id)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to update the word count of post '{}'", id))?;
+        report.posts += 1;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+
+    async fn topics_of(tx: &mut Transaction<'_, Sqlite>, id: &str) -> Vec<String> {
+        sqlx::query_scalar("SELECT topic FROM tutorial_topics WHERE tutorial_id = ? ORDER BY topic")
+            .bind(id)
+            .fetch_all(&mut **tx)
+            .await
+            .unwrap()
+    }
+
+    async fn search(tx: &mut Transaction<'_, Sqlite>, term: &str) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT tutorial_id FROM tutorials_fts WHERE tutorials_fts MATCH ? ORDER BY tutorial_id",
+        )
+        .bind(term)
+        .fetch_all(&mut **tx)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_restores_derived_data() {
+        let pool = create_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let id: String = sqlx::query_scalar("SELECT id FROM tutorials ORDER BY id LIMIT 1")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE tutorials SET topics = '[\"Zeroconf\",\"mDNS\"]', content = 'avahi-browse -a zeigt Dienste' WHERE id = ?",
+        )
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        sqlx::query("DELETE FROM tutorial_topics WHERE tutorial_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM tutorials_fts WHERE tutorial_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO site_pages (id, slug, title) VALUES ('page-1', 'notes', 'Notes')")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO site_posts (id, page_id, title, slug, content_markdown) \
+             VALUES ('post-1', 'page-1', 'Hello', 'hello', 'one two three four')",
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        assert!(search(&mut tx, "zeroconf").await.is_empty());
+
+        let scope = RebuildScope::Only {
+            tutorials: HashSet::from([id.clone()]),
+            posts: HashSet::from(["post-1".to_string()]),
+        };
+        let report = rebuild(&mut tx, &scope).await.unwrap();
+        assert_eq!(
+            report,
+            RebuildReport {
+                tutorials: 1,
+                topics: 2,
+                posts: 1,
+            }
+        );
+        assert_eq!(topics_of(&mut tx, &id).await, vec!["Zeroconf", "mDNS"]);
+        assert_eq!(search(&mut tx, "zeroconf").await, vec![id.clone()]);
+        let words: i64 =
+            sqlx::query_scalar("SELECT word_count FROM site_posts WHERE id = 'post-1'")
+                .fetch_one(&mut *tx)
+                .await
+                .unwrap();
+        assert_eq!(words, 4);
+        let words: i64 = sqlx::query_scalar("SELECT word_count FROM tutorials WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(words, 4);
+
+        let tutorials: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tutorials")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        let report = rebuild(&mut tx, &RebuildScope::Everything).await.unwrap();
+        assert_eq!(report.tutorials, tutorials as usize);
+        assert_eq!(report.posts, 1);
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tutorials_fts")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(indexed, tutorials);
+        assert_eq!(search(&mut tx, "zeroconf").await, vec![id]);
+    }
+}
@@ -0,0 +1,386 @@
+//! Handler errors with a machine-readable code.
+//!
+//! An [`AppError`] answers with the status of its kind and a JSON body that
+//! keeps the `error` message of [`ErrorResponse`] and adds a stable `code`
+//! clients can branch on, the offending `field` where one is known, and the
+//! request id:
+//!
+//! ```json
+//! { "error": "Tutorial not found", "code": "not_found", "request_id": "..." }
+//! ```
+//!
+//! A request body that breaks several rules lists each broken field under
+//! `errors`, each with its own `field` and `error`.
+//!
+//! Messages may change and be translated; codes do not. Handlers that still
+//! answer with `(StatusCode, Json<ErrorResponse>)` can convert an `AppError`
+//! into that pair, without the code.
+
+use crate::models::ErrorResponse;
+use crate::security::auth::Claims;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    NotFound(String),
+    /// A request the handler refuses as given, naming the field at fault
+    /// when it is one field, and every broken field in `errors`.
+    Validation {
+        field: Option<String>,
+        message: String,
+        errors: Vec<FieldError>,
+    },
+    /// A unique value already taken, or a write that lost a race.
+    Conflict(String),
+    Unauthorized(String),
+    Forbidden(String),
+    RateLimited(String),
+    PayloadTooLarge(String),
+    /// A server the request had to reach, such as a bundle URL, failed.
+    Upstream(String),
+    /// The message is shown to the client, so it names what failed and not
+    /// why; [`AppError::internal`] logs the why.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation {
+            field: None,
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        AppError::Validation {
+            field: Some(field.to_string()),
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// All rules a request broke, each with its field. One broken field reads
+    /// like [`AppError::invalid_field`]; several are joined into one message.
+    pub fn invalid_fields(errors: Vec<FieldError>) -> Self {
+        let (field, message) = match errors.as_slice() {
+            [only] => (Some(only.field.clone()), only.message.clone()),
+            _ => (
+                None,
+                errors
+                    .iter()
+                    .map(|err| err.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        };
+        AppError::Validation {
+            field,
+            message,
+            errors,
+        }
+    }
+
+    pub fn forbidden() -> Self {
+        AppError::Forbidden("Insufficient permissions".to_string())
+    }
+
+    /// Logs `err` and answers with `message` alone.
+    pub fn internal(message: &str, err: impl fmt::Display) -> Self {
+        tracing::error!("{}: {}", message, err);
+        AppError::Internal(message.to_string())
+    }
+
+    /// A database error, with a missing row reported as `what` not found:
+    /// unique violations are conflicts and malformed queries the client's
+    /// fault.
+    pub fn from_sqlx(err: sqlx::Error, what: &str) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => AppError::NotFound(format!("{what} not found")),
+            sqlx::Error::Protocol(message) => AppError::validation(message),
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => AppError::Conflict(
+                db_err
+                    .constraint()
+                    .map(|c| format!("Duplicate value violates unique constraint '{c}'"))
+                    .unwrap_or_else(|| "Duplicate value violates unique constraint".to_string()),
+            ),
+            sqlx::Error::Database(db_err) => AppError::internal("Database error", db_err),
+            other => {
+                tracing::error!("Unexpected database error: {}", other);
+                AppError::Internal(format!("Unexpected database error: {other}"))
+            }
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Validation { .. } => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The stable name of the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::Validation { .. } => "validation_failed",
+            AppError::Conflict(_) => "conflict",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::RateLimited(_) => "rate_limited",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::Upstream(_) => "upstream_failed",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Validation { message, .. }
+            | AppError::Conflict(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::RateLimited(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::Upstream(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            AppError::Validation { field, .. } => field.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Every broken field of a request body; empty unless the error came from
+    /// [`AppError::invalid_fields`].
+    pub fn field_errors(&self) -> &[FieldError] {
+        match self {
+            AppError::Validation { errors, .. } => errors,
+            _ => &[],
+        }
+    }
+}
+
+/// Refuses every role but `admin`.
+pub(crate) fn ensure_admin(claims: &Claims) -> Result<(), AppError> {
+    if claims.role != "admin" {
+        return Err(AppError::forbidden());
+    }
+    Ok(())
+}
+
+/// One rule a request field broke.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    #[serde(rename = "error")]
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        AppError::from_sqlx(err, "Record")
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [FieldError],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.message(),
+            code: self.code(),
+            field: self.field(),
+            errors: self.field_errors(),
+            request_id: crate::middleware::request_id::current(),
+        };
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+impl From<AppError> for (StatusCode, Json<ErrorResponse>) {
+    fn from(err: AppError) -> Self {
+        (
+            err.status(),
+            Json(ErrorResponse {
+                error: err.message().to_string(),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::middleware::request_id::{request_id, REQUEST_ID_HEADER};
+    use crate::security::auth;
+    use axum::{body::Body, extract::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn body(err: AppError) -> (StatusCode, serde_json::Value) {
+        let response = err.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_bodies_keep_the_message_and_add_the_code() {
+        let (status, json) = body(AppError::invalid_field("slug", "Slug cannot be empty")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "error": "Slug cannot be empty",
+                "code": "validation_failed",
+                "field": "slug",
+            })
+        );
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), _>(AppError::NotFound("Page not found".to_string())) }),
+            )
+            .layer(axum::middleware::from_fn(request_id));
+        let request = Request::builder()
+            .uri("/")
+            .header(REQUEST_ID_HEADER, "support-4711")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["code"], "not_found");
+        assert_eq!(json["request_id"], "support-4711");
+
+        let (status, json) = body(AppError::RateLimited("Später".to_string())).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(json["code"], "rate_limited");
+        assert!(json.get("field").is_none());
+
+        let (status, json) = body(AppError::Upstream("Bundle URL unreachable".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(json["code"], "upstream_failed");
+
+        let (status, Json(legacy)) = AppError::forbidden().into();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(legacy.error, "Insufficient permissions");
+    }
+
+    #[test]
+    fn test_only_admins_pass() {
+        assert_eq!(ensure_admin(&auth::test_admin_claims()), Ok(()));
+        assert_eq!(
+            ensure_admin(&auth::test_claims("leser", "user")),
+            Err(AppError::forbidden())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_broken_fields_are_listed() {
+        let one = AppError::invalid_fields(vec![FieldError::new("icon", "Invalid icon")]);
+        assert_eq!(one.field(), Some("icon"));
+        assert_eq!(one.message(), "Invalid icon");
+
+        let (status, json) = body(AppError::invalid_fields(vec![
+            FieldError::new("title", "Title cannot be empty"),
+            FieldError::new("slug", "Slug cannot be empty"),
+        ]))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "error": "Title cannot be empty; Slug cannot be empty",
+                "code": "validation_failed",
+                "errors": [
+                    { "field": "title", "error": "Title cannot be empty" },
+                    { "field": "slug", "error": "Slug cannot be empty" },
+                ],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_errors_are_mapped_by_kind() {
+        assert_eq!(
+            AppError::from_sqlx(sqlx::Error::RowNotFound, "Site page"),
+            AppError::NotFound("Site page not found".to_string())
+        );
+
+        let pool = create_test_pool().await;
+        sqlx::query("INSERT INTO site_pages (id, slug, title) VALUES ('a', 'doppelt', 'A')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let err =
+            sqlx::query("INSERT INTO site_pages (id, slug, title) VALUES ('b', 'doppelt', 'B')")
+                .execute(&pool)
+                .await
+                .unwrap_err();
+        let err = AppError::from(err);
+        assert_eq!(err.code(), "conflict");
+        assert!(err.message().starts_with("Duplicate value"), "{err}");
+
+        let err = sqlx::query("SELECT * FROM no_such_table")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            AppError::from(err),
+            AppError::Internal("Database error".to_string())
+        );
+    }
+}
@@ -16,21 +16,28 @@
 //! - POST /api/auth/login: Authenticate user and issue tokens
 //! - GET /api/auth/me: Get current user information
 //! - POST /api/auth/logout: Invalidate session
+//! - PUT /api/auth/password: Change the own password and rotate the session
 //!
 //! # Rate Limiting
 //! Failed login attempts trigger progressive lockout:
 //! - 3 failures: 10-second lockout
 //! - 5+ failures: 60-second lockout
 
-use crate::{security::{auth, csrf}, db::DbPool, models::*, repositories};
+use crate::{
+    db::DbPool,
+    error::AppError,
+    models::*,
+    repositories,
+    security::{auth, csrf},
+    validation::users::{validate_new_password, validate_username},
+};
 use axum::{
     extract::State,
     http::{HeaderMap, StatusCode},
     Json,
 };
-use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use chrono::{Duration as ChronoDuration, Utc};
 use sha2::{Digest, Sha256};
-use sqlx::{self, FromRow};
 use std::{env, sync::OnceLock, time::Duration};
 
 /// Global salt for hashing login attempt identifiers.
@@ -75,6 +82,12 @@ pub fn init_login_attempt_salt() -> Result<(), String> {
     Ok(())
 }
 
+/// Fixed salt for tests that log in.
+#[cfg(test)]
+pub(crate) fn init_test_login_attempt_salt() {
+    LOGIN_ATTEMPT_SALT.get_or_init(|| "login-attempt-test-salt-0123456789-ABCDEF".to_string());
+}
+
 /// Retrieves the initialized login attempt salt.
 ///
 /// # Panics
@@ -108,21 +121,6 @@ fn hash_login_identifier(username: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-/// Parses an optional RFC3339 timestamp string into a UTC DateTime.
-///
-/// # Arguments
-/// * `value` - Optional RFC3339 timestamp string
-///
-/// # Returns
-/// - `Some(DateTime<Utc>)` if parsing succeeds
-/// - `None` if value is None or parsing fails
-fn parse_rfc3339_opt(value: &Option<String>) -> Option<DateTime<Utc>> {
-    value
-        .as_ref()
-        .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok())
-        .map(|dt| dt.with_timezone(&Utc))
-}
-
 /// Returns a precomputed dummy bcrypt hash for timing-attack resistance.
 ///
 /// This hash is used during failed login attempts to ensure password
@@ -146,36 +144,6 @@ fn dummy_bcrypt_hash() -> &'static str {
     })
 }
 
-/// Validates a username meets security and format requirements.
-///
-/// # Arguments
-/// * `username` - The username to validate
-///
-/// # Returns
-/// - `Ok(())` if valid
-/// - `Err(String)` with error message if invalid
-///
-/// # Validation Rules
-/// - Not empty
-/// - Length ≤ 50 characters
-/// - Only alphanumeric, underscore, hyphen, and period allowed
-fn validate_username(username: &str) -> Result<(), String> {
-    if username.is_empty() {
-        return Err("Username cannot be empty".to_string());
-    }
-    if username.len() > 50 {
-        return Err("Username too long".to_string());
-    }
-
-    if !username
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
-    {
-        return Err("Username contains invalid characters".to_string());
-    }
-    Ok(())
-}
-
 /// Validates a password meets security and format requirements.
 ///
 /// # Arguments
@@ -243,15 +211,11 @@ fn validate_password(password: &str) -> Result<(), String> {
 pub async fn login(
     State(pool): State<DbPool>,
     Json(payload): Json<LoginRequest>,
-) -> Result<(HeaderMap, Json<LoginResponse>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(HeaderMap, Json<LoginResponse>), AppError> {
     let username = payload.username.trim().to_string();
 
-    if let Err(e) = validate_username(&username) {
-        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })));
-    }
-    if let Err(e) = validate_password(&payload.password) {
-        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })));
-    }
+    validate_username(&username).map_err(|e| AppError::invalid_field("username", e))?;
+    validate_password(&payload.password).map_err(|e| AppError::invalid_field("password", e))?;
 
     let attempt_key = hash_login_identifier(&username);
 
@@ -259,30 +223,20 @@ pub async fn login(
         .await
         .map_err(|e| {
             tracing::error!("Failed to load login attempts for {}: {}", username, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Internal server error".to_string(),
-                }),
-            )
+            AppError::Internal("Internal server error".to_string())
         })?;
 
     if let Some(record) = &attempt_record {
-        if let Some(blocked_until) = parse_rfc3339_opt(&record.blocked_until) {
+        if let Some(blocked_until) = record.blocked_until {
             let now = Utc::now();
             if blocked_until > now {
                 let remaining = (blocked_until - now).num_seconds().max(0);
                 // Do not sleep here to avoid holding connections (DoS prevention)
-                return Err((
-                    StatusCode::TOO_MANY_REQUESTS,
-                    Json(ErrorResponse {
-                        error: format!(
-                            "Zu viele fehlgeschlagene Versuche. Bitte warte {} Sekunde{}.",
-                            remaining,
-                            if remaining == 1 { "" } else { "n" }
-                        ),
-                    }),
-                ));
+                return Err(AppError::RateLimited(format!(
+                    "Zu viele fehlgeschlagene Versuche. Bitte warte {} Sekunde{}.",
+                    remaining,
+                    if remaining == 1 { "" } else { "n" }
+                )));
             }
         }
     }
@@ -291,12 +245,7 @@ pub async fn login(
         .await
         .map_err(|e| {
             tracing::error!("Database error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Internal server error".to_string(),
-                }),
-            )
+            AppError::Internal("Internal server error".to_string())
         })?;
 
     let hash_to_verify_owned = user.as_ref().map(|u| u.password_hash.clone());
@@ -321,26 +270,18 @@ pub async fn login(
 
     if !password_valid {
         let now = Utc::now();
-        let long_block = (now + ChronoDuration::seconds(60)).to_rfc3339();
-        let short_block = (now + ChronoDuration::seconds(10)).to_rfc3339();
+        let long_block = now + ChronoDuration::seconds(60);
+        let short_block = now + ChronoDuration::seconds(10);
 
-        repositories::users::record_failed_login(&pool, &attempt_key, &long_block, &short_block)
+        repositories::users::record_failed_login(&pool, &attempt_key, long_block, short_block)
             .await
             .map_err(|e| {
                 tracing::error!("Failed to record login attempt for hashed key: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "Internal server error".to_string(),
-                    }),
-                )
+                AppError::Internal("Internal server error".to_string())
             })?;
 
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ErrorResponse {
-                error: "Ungültige Anmeldedaten".to_string(),
-            }),
+        return Err(AppError::Unauthorized(
+            "Ungültige Anmeldedaten".to_string(),
         ));
     }
 
@@ -354,16 +295,15 @@ pub async fn login(
     }
 
     let user_record = user_record.expect("Successful login must have user record");
-    let token =
-        auth::create_jwt(user_record.username.clone(), user_record.role.clone()).map_err(|e| {
-            tracing::error!("JWT creation error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to create token".to_string(),
-                }),
-            )
-        })?;
+    let token = auth::create_jwt(
+        user_record.username.clone(),
+        user_record.role.clone(),
+        user_record.token_version,
+    )
+    .map_err(|e| {
+        tracing::error!("JWT creation error: {}", e);
+        AppError::Internal("Failed to create token".to_string())
+    })?;
 
     let mut headers = HeaderMap::new();
     auth::append_auth_cookie(&mut headers, auth::build_auth_cookie(&token));
@@ -375,12 +315,7 @@ pub async fn login(
             "Failed to issue CSRF token for user {}",
             user_record.username
         );
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to create token".to_string(),
-            }),
-        ));
+        return Err(AppError::Internal("Failed to create token".to_string()));
     }
 
     Ok((
@@ -425,7 +360,7 @@ pub async fn login(
 /// not from request parameters, preventing impersonation.
 pub async fn me(
     claims: auth::Claims,
-) -> Result<(HeaderMap, Json<UserResponse>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(HeaderMap, Json<UserResponse>), AppError> {
     let mut headers = HeaderMap::new();
 
     // Refresh CSRF token to ensure active sessions always have a valid one
@@ -448,8 +383,9 @@ pub async fn me(
 
 /// HTTP handler for user logout.
 ///
-/// Invalidates the user's session by removing auth and CSRF cookies.
-/// Requires CSRF token validation to prevent logout CSRF attacks.
+/// Invalidates the user's session: the JWT is blacklisted until it expires,
+/// so copies of it stop working too, and the auth and CSRF cookies are
+/// removed. Requires CSRF token validation to prevent logout CSRF attacks.
 ///
 /// # Endpoint
 /// POST /api/auth/logout
@@ -461,6 +397,7 @@ pub async fn me(
 ///
 /// # Response
 /// On success (204 No Content):
+/// - Blacklists the token until its `exp`
 /// - Sets auth cookie expiration to past (removes session)
 /// - Sets CSRF cookie expiration to past (removes token)
 /// - Empty response body
@@ -494,3 +431,133 @@ pub async fn logout(
     tracing::info!(user = %claims.sub, "User logged out");
     (StatusCode::NO_CONTENT, headers)
 }
+
+/// HTTP handler for changing the password of the logged-in user.
+///
+/// # Endpoint
+/// PUT /api/auth/password
+///
+/// # Request
+/// JSON body with ChangePasswordRequest:
+/// ```json
+/// {
+///   "current_password": "string",
+///   "new_password": "string"
+/// }
+/// ```
+///
+/// # Authentication
+/// Requires:
+/// - Valid JWT token (cookie or header)
+/// - Valid CSRF token (header and cookie must match)
+///
+/// # Response
+/// On success (200 OK), the session is rotated:
+/// - The user's token version is bumped, which revokes every token issued
+///   before: the one the request was made with and those of other sessions
+/// - A fresh auth cookie and CSRF cookie are set
+/// - The body is a LoginResponse carrying the new token
+///
+/// # Errors
+/// - 400 Bad Request: Wrong current password, or a new password that is
+///   shorter than 12 characters or the same as the current one
+/// - 401 Unauthorized: Missing, invalid or revoked JWT token
+/// - 403 Forbidden: Missing or invalid CSRF token
+/// - 429 Too Many Requests: Locked out by failed attempts
+///
+/// # Security
+/// A wrong current password counts as a failed login for the user, so the
+/// endpoint cannot be used to guess it faster than login allows.
+pub async fn change_password(
+    State(pool): State<DbPool>,
+    _csrf: csrf::CsrfGuard,
+    claims: auth::Claims,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<(HeaderMap, Json<LoginResponse>), AppError> {
+    validate_password(&payload.current_password)
+        .map_err(|e| AppError::invalid_field("current_password", e))?;
+    validate_new_password(&payload.new_password)
+        .map_err(|e| AppError::invalid_field("new_password", e))?;
+    if payload.new_password == payload.current_password {
+        return Err(AppError::invalid_field(
+            "new_password",
+            "New password must differ from the current one",
+        ));
+    }
+
+    let attempt_key = hash_login_identifier(&claims.sub);
+    let attempt_record = repositories::users::get_login_attempt(&pool, &attempt_key)
+        .await
+        .map_err(|e| AppError::internal("Failed to load login attempts", e))?;
+    if let Some(blocked_until) = attempt_record.as_ref().and_then(|r| r.blocked_until) {
+        let remaining = (blocked_until - Utc::now()).num_seconds();
+        if remaining > 0 {
+            return Err(AppError::RateLimited(format!(
+                "Zu viele fehlgeschlagene Versuche. Bitte warte {} Sekunde{}.",
+                remaining,
+                if remaining == 1 { "" } else { "n" }
+            )));
+        }
+    }
+
+    let user = repositories::users::get_user_by_username(&pool, &claims.sub)
+        .await
+        .map_err(|e| AppError::internal("Failed to load user", e))?
+        // Deleted since the token was issued
+        .ok_or_else(|| AppError::Unauthorized("Unknown user".to_string()))?;
+
+    match bcrypt::verify(&payload.current_password, &user.password_hash) {
+        Ok(true) => {}
+        Ok(false) => {
+            let now = Utc::now();
+            repositories::users::record_failed_login(
+                &pool,
+                &attempt_key,
+                now + ChronoDuration::seconds(60),
+                now + ChronoDuration::seconds(10),
+            )
+            .await
+            .map_err(|e| AppError::internal("Failed to record login attempt", e))?;
+            return Err(AppError::invalid_field(
+                "current_password",
+                "Current password is incorrect",
+            ));
+        }
+        Err(e) => return Err(AppError::internal("Password verification error", e)),
+    }
+
+    let password_hash = bcrypt::hash(&payload.new_password, bcrypt::DEFAULT_COST)
+        .map_err(|e| AppError::internal("Failed to hash password", e))?;
+    // Bumping the token version revokes every other session of the user
+    let token_version =
+        repositories::users::update_password_hash(&pool, &user.username, &password_hash)
+            .await
+            .map_err(|e| AppError::internal("Failed to update password", e))?
+            .ok_or_else(|| AppError::Unauthorized("Unknown user".to_string()))?;
+    if attempt_record.is_some() {
+        if let Err(e) = repositories::users::clear_login_attempts(&pool, &attempt_key).await {
+            tracing::warn!("Failed to clear login attempts after password change: {}", e);
+        }
+    }
+
+    let token = auth::create_jwt(user.username.clone(), user.role.clone(), token_version)
+        .map_err(|e| AppError::internal("Failed to create token", e))?;
+    let csrf_token = csrf::issue_csrf_token(&user.username)
+        .map_err(|e| AppError::internal("Failed to create token", e))?;
+
+    let mut headers = HeaderMap::new();
+    auth::append_auth_cookie(&mut headers, auth::build_auth_cookie(&token));
+    csrf::append_csrf_cookie(&mut headers, &csrf_token);
+    tracing::info!(user = %user.username, "User changed their password");
+
+    Ok((
+        headers,
+        Json(LoginResponse {
+            token,
+            user: UserResponse {
+                username: user.username,
+                role: user.role,
+            },
+        }),
+    ))
+}
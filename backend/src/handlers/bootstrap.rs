@@ -0,0 +1,222 @@
+//! First-paint bundle for the SPA.
+//!
+//! Combines the public site content, the navigation and the tutorial
+//! summaries into one response so the frontend does not pay four round
+//! trips before it can render. Each part is loaded by the same helper that
+//! serves its standalone endpoint, so visibility rules stay identical.
+//!
+//! The anonymous bundle is also inlined into every served page; see
+//! `frontend_proxy`.
+
+use crate::{
+    db,
+    handlers::{site_content, site_pages, tutorials},
+    models::{BootstrapResponse, ErrorResponse, DEFAULT_PAGE_LIMIT},
+    security::auth,
+    utils::conditional,
+};
+use axum::{
+    extract::State,
+    http::{header::VARY, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
+
+/// The bundle as a visitor sees it, with `generated_at` left empty.
+pub(crate) async fn load_bundle(
+    pool: &db::DbPool,
+    authenticated: bool,
+) -> Result<BootstrapResponse, (StatusCode, Json<ErrorResponse>)> {
+    let (records, navigation, tutorials) = tokio::join!(
+        site_content::fetch_records(pool),
+        site_pages::load_navigation(pool, authenticated),
+        tutorials::load_tutorial_summaries(pool, DEFAULT_PAGE_LIMIT, 0),
+    );
+
+    let mut content = serde_json::Map::new();
+    for record in records? {
+        let item = site_content::map_record(record)?;
+        content.insert(item.section, item.content);
+    }
+
+    Ok(BootstrapResponse {
+        content,
+        navigation: navigation?,
+        tutorials: tutorials?,
+        generated_at: String::new(),
+    })
+}
+
+pub async fn get_bootstrap(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
+    State(pool): State<db::DbPool>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let authenticated = site_pages::viewer_is_authenticated(&claims);
+    let mut bundle = load_bundle(&pool, authenticated).await?;
+
+    // The tag covers the assembled data, so any change to a section, a nav
+    // page or a tutorial (including its updated_at) invalidates the bundle.
+    // generated_at is filled in afterwards because it changes on every call.
+    let serialized = serde_json::to_string(&bundle).map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to serialize bootstrap bundle: {err}"),
+            }),
+        )
+    })?;
+    let etag = conditional::weak_etag([
+        if authenticated { "authenticated" } else { "anonymous" },
+        serialized.as_str(),
+    ]);
+    bundle.generated_at = chrono::Utc::now().to_rfc3339();
+
+    let cache_control = if authenticated {
+        conditional::PRIVATE_REVALIDATE
+    } else {
+        conditional::PUBLIC_SHORT_CACHE
+    };
+    let mut response = conditional::respond(&headers, &etag, cache_control, bundle);
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("Cookie, Authorization"));
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::models::{CreateSitePageRequest, PageParams, PAGE_VISIBILITY_AUTHENTICATED};
+    use crate::security::auth::test_admin_claims as admin;
+    use crate::validation::ValidatedJson;
+    use axum::body::to_bytes;
+    use axum::extract::Path;
+    use axum::http::header::{ETAG, IF_NONE_MATCH};
+    use serde_json::{json, Value};
+
+    fn anonymous() -> Result<auth::OptionalClaims, (StatusCode, String)> {
+        Ok(auth::OptionalClaims(None))
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    async fn add_nav_page(pool: &db::DbPool, slug: &str, visibility: &str) {
+        let _ = site_pages::create_site_page(
+            admin(),
+            State(pool.clone()),
+            ValidatedJson(CreateSitePageRequest {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                description: None,
+                nav_label: None,
+                show_in_nav: true,
+                order_index: None,
+                is_published: true,
+                visibility: visibility.to_string(),
+                hero: json!({}),
+                layout: json!({}),
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            }),
+        )
+        .await
+        .expect("create page");
+    }
+
+    #[tokio::test]
+    async fn test_bundle_matches_individual_endpoints() {
+        let pool = create_test_pool().await;
+        add_nav_page(&pool, "oeffentlich", "public").await;
+        add_nav_page(&pool, "intern", PAGE_VISIBILITY_AUTHENTICATED).await;
+
+        let bundle = body_json(
+            get_bootstrap(anonymous(), State(pool.clone()), HeaderMap::new())
+                .await
+                .expect("bootstrap"),
+        )
+        .await;
+
+        let content = body_json(
+            site_content::list_site_content(State(pool.clone()), HeaderMap::new())
+                .await
+                .expect("content"),
+        )
+        .await;
+        for item in content["items"].as_array().unwrap() {
+            let section = item["section"].as_str().unwrap();
+            assert_eq!(bundle["content"][section], item["content"]);
+        }
+        assert_eq!(
+            bundle["content"].as_object().unwrap().len(),
+            content["items"].as_array().unwrap().len()
+        );
+
+        let Json(navigation) = site_pages::get_navigation(anonymous(), State(pool.clone()))
+            .await
+            .expect("navigation");
+        assert_eq!(bundle["navigation"], serde_json::to_value(&navigation).unwrap());
+        let slugs: Vec<&str> = navigation.items.iter().map(|item| item.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["oeffentlich"]);
+
+        let Json(tutorials) =
+            tutorials::list_tutorials(State(pool.clone()), PageParams::default())
+                .await
+                .expect("tutorials");
+        assert_eq!(
+            bundle["tutorials"],
+            serde_json::to_value(&tutorials).unwrap()["items"]
+        );
+        assert!(bundle["tutorials"][0].get("content").is_none());
+
+        let signed_in = body_json(
+            get_bootstrap(
+                Ok(auth::OptionalClaims(Some(admin()))),
+                State(pool.clone()),
+                HeaderMap::new(),
+            )
+            .await
+            .expect("bootstrap"),
+        )
+        .await;
+        assert_eq!(signed_in["navigation"]["items"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_etag_changes_with_content() {
+        let pool = create_test_pool().await;
+        let first = get_bootstrap(anonymous(), State(pool.clone()), HeaderMap::new())
+            .await
+            .expect("bootstrap");
+        let etag = first.headers()[ETAG].clone();
+
+        let mut conditional_headers = HeaderMap::new();
+        conditional_headers.insert(IF_NONE_MATCH, etag.clone());
+        let cached = get_bootstrap(anonymous(), State(pool.clone()), conditional_headers.clone())
+            .await
+            .expect("bootstrap");
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+        let _ = site_content::update_site_content(
+            admin(),
+            State(pool.clone()),
+            Path("site_meta".to_string()),
+            Json(crate::models::UpdateSiteContentRequest {
+                content: json!({ "title": "Neu" }),
+            }),
+        )
+        .await
+        .expect("update");
+
+        let fresh = get_bootstrap(anonymous(), State(pool.clone()), conditional_headers)
+            .await
+            .expect("bootstrap");
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_ne!(fresh.headers()[ETAG], etag);
+    }
+}
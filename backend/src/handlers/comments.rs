@@ -9,7 +9,7 @@
 //! - DELETE /api/comments/{id}: Delete comment (admin only, CSRF protected)
 //!
 //! # Features
-//! - Pagination support (default 50 comments, configurable via query params)
+//! - Pagination envelope with totals (default 50 comments, see `models::pagination`)
 //! - Author attribution from JWT claims
 //! - Content length validation (1-2000 characters)
 //! - Foreign key cascade deletion (comments deleted with tutorial)
@@ -20,14 +20,14 @@
 //! - Content length limits prevent abuse
 //! - Tutorial ID validation prevents injection
 
-use crate::{security::auth, db::DbPool, handlers::tutorials::validate_tutorial_id, models::*, repositories};
+use crate::{security::auth, db::DbPool, error::AppError, middleware::security::ClientIp, models::*, repositories, validation::tutorials::validate_tutorial_id};
 use axum::{
-    extract::{ConnectInfo, Path, Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
 use html_escape;
 
 #[derive(Deserialize)]
@@ -38,20 +38,10 @@ pub struct CreateCommentRequest {
 
 #[derive(Deserialize)]
 pub struct CommentListQuery {
-    #[serde(default = "default_comment_limit")]
-    limit: i64,
-
-    #[serde(default)]
-    offset: i64,
-
     #[serde(default)]
     sort: Option<String>,
 }
 
-fn default_comment_limit() -> i64 {
-    50
-}
-
 #[derive(Serialize, sqlx::FromRow)]
 pub struct Comment {
     pub id: String,
@@ -59,7 +49,10 @@ pub struct Comment {
     pub post_id: Option<String>,
     pub author: String,
     pub content: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    #[sqlx(rename = "created_at")]
+    pub stored_created_at: String,
     pub votes: i64,
     pub is_admin: bool,
 }
@@ -90,11 +83,46 @@ fn sanitize_comment_content(raw: &str) -> Result<String, (StatusCode, Json<Error
     Ok(sanitized)
 }
 
+/// Cursors follow the newest-first order; `top` pages by offset only.
+fn ensure_cursor_order(
+    cursor: Option<&Cursor>,
+    sort: Option<&str>,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if cursor.is_some() && sort == Some("top") {
+        return Err(AppError::invalid_field(
+            "cursor",
+            "Cursors cannot be combined with sort=top; use offset instead",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// A page from up to `page.limit + 1` comments, with a cursor to the next
+/// page unless sorted by votes.
+fn comment_page(
+    comments: Vec<Comment>,
+    total: i64,
+    page: &PageParams,
+    sort: Option<&str>,
+) -> Paginated<Comment> {
+    let mut listing = Paginated::from_overfetch(comments, total, page.limit, page.offset, |c| {
+        Cursor::new(&c.stored_created_at, &c.id)
+    });
+    if sort == Some("top") {
+        listing.next_cursor = None;
+    }
+    listing
+}
+
 pub async fn list_comments(
     State(pool): State<DbPool>,
     Path(tutorial_id): Path<String>,
+    CursorPageParams { page, cursor }: CursorPageParams,
     Query(params): Query<CommentListQuery>,
-) -> Result<Json<Vec<Comment>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Listing<Comment>>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_cursor_order(cursor.as_ref(), params.sort.as_deref())?;
+
     if let Err(e) = validate_tutorial_id(&tutorial_id) {
         return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })));
     }
@@ -120,17 +148,17 @@ pub async fn list_comments(
         ));
     }
 
-    let limit = params.limit.clamp(1, 200);
-    let offset = params.offset.max(0);
-
-    let comments = repositories::comments::list_comments(
-        &pool,
-        &tutorial_id,
-        limit,
-        offset,
-        params.sort.as_deref(),
+    let (comments, total) = tokio::try_join!(
+        repositories::comments::list_comments(
+            &pool,
+            &tutorial_id,
+            page.limit + 1,
+            page.offset,
+            params.sort.as_deref(),
+            cursor.as_ref(),
+        ),
+        repositories::comments::count_comments(&pool, &tutorial_id),
     )
-    .await
     .map_err(|e| {
         tracing::error!("Database error: {}", e);
         (
@@ -150,17 +178,21 @@ pub async fn list_comments(
             author: c.author,
             content: c.content,
             created_at: c.created_at,
+            stored_created_at: c.stored_created_at,
             votes: c.votes,
             is_admin: c.is_admin,
         })
         .collect();
 
-    Ok(Json(response_comments))
+    Ok(Json(
+        comment_page(response_comments, total, &page, params.sort.as_deref())
+            .into_listing(&page),
+    ))
 }
 
 pub async fn create_comment(
     State(pool): State<DbPool>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ClientIp(client_ip): ClientIp,
     Path(tutorial_id): Path<String>,
     Json(payload): Json<CreateCommentRequest>,
 ) -> Result<Json<Comment>, (StatusCode, Json<ErrorResponse>)> {
@@ -190,14 +222,17 @@ pub async fn create_comment(
         ));
     }
 
-    create_comment_internal(pool, Some(tutorial_id), None, payload, None, addr.ip().to_string()).await
+    create_comment_internal(pool, Some(tutorial_id), None, payload, None, client_ip.to_string()).await
 }
 
 pub async fn list_post_comments(
     State(pool): State<DbPool>,
     Path(post_id): Path<String>,
+    CursorPageParams { page, cursor }: CursorPageParams,
     Query(params): Query<CommentListQuery>,
-) -> Result<Json<Vec<Comment>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Listing<Comment>>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_cursor_order(cursor.as_ref(), params.sort.as_deref())?;
+
     // Verify post exists
     let exists = repositories::posts::check_post_exists(&pool, &post_id)
         .await
@@ -220,17 +255,17 @@ pub async fn list_post_comments(
         ));
     }
 
-    let limit = params.limit.clamp(1, 200);
-    let offset = params.offset.max(0);
-
-    let comments = repositories::comments::list_post_comments(
-        &pool,
-        &post_id,
-        limit,
-        offset,
-        params.sort.as_deref(),
+    let (comments, total) = tokio::try_join!(
+        repositories::comments::list_post_comments(
+            &pool,
+            &post_id,
+            page.limit + 1,
+            page.offset,
+            params.sort.as_deref(),
+            cursor.as_ref(),
+        ),
+        repositories::comments::count_post_comments(&pool, &post_id),
     )
-    .await
     .map_err(|e| {
         tracing::error!("Database error: {}", e);
         (
@@ -250,17 +285,21 @@ pub async fn list_post_comments(
             author: c.author,
             content: c.content,
             created_at: c.created_at,
+            stored_created_at: c.stored_created_at,
             votes: c.votes,
             is_admin: c.is_admin,
         })
         .collect();
 
-    Ok(Json(response_comments))
+    Ok(Json(
+        comment_page(response_comments, total, &page, params.sort.as_deref())
+            .into_listing(&page),
+    ))
 }
 
 pub async fn create_post_comment(
     State(pool): State<DbPool>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ClientIp(client_ip): ClientIp,
     Path(post_id): Path<String>,
     auth::OptionalClaims(claims): auth::OptionalClaims,
     Json(payload): Json<CreateCommentRequest>,
@@ -287,7 +326,7 @@ pub async fn create_post_comment(
         ));
     }
 
-    create_comment_internal(pool, None, Some(post_id), payload, claims, addr.ip().to_string()).await
+    create_comment_internal(pool, None, Some(post_id), payload, claims, client_ip.to_string()).await
 }
 
 async fn create_comment_internal(
@@ -364,26 +403,23 @@ async fn create_comment_internal(
             )
         })?;
 
-    if let Some(created_at_str) = last_comment_time {
-        if let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&created_at_str) {
-            let now = chrono::Utc::now();
-            let diff = now.signed_duration_since(created_at);
-            if diff.num_seconds() < 60 {
-                return Err((
-                    StatusCode::TOO_MANY_REQUESTS,
-                    Json(ErrorResponse {
-                        error: format!(
-                            "Please wait {} seconds before posting another comment",
-                            60 - diff.num_seconds()
-                        ),
-                    }),
-                ));
-            }
+    if let Some(created_at) = last_comment_time {
+        let diff = Utc::now().signed_duration_since(created_at);
+        if diff.num_seconds() < 60 {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Please wait {} seconds before posting another comment",
+                        60 - diff.num_seconds()
+                    ),
+                }),
+            ));
         }
     }
 
     let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().to_rfc3339();
+    let now = Utc::now();
 
     // Determine if author is admin
     let is_admin = if let Some(ref c) = claims {
@@ -399,7 +435,7 @@ async fn create_comment_internal(
         post_id,
         &author,
         &comment_content,
-        &now,
+        now,
         is_admin,
     )
     .await
@@ -420,6 +456,7 @@ async fn create_comment_internal(
         author: comment.author,
         content: comment.content,
         created_at: comment.created_at,
+        stored_created_at: comment.stored_created_at,
         votes: comment.votes,
         is_admin: comment.is_admin,
     };
@@ -596,6 +633,7 @@ pub async fn vote_comment(
         author: comment.author,
         content: comment.content,
         created_at: comment.created_at,
+        stored_created_at: comment.stored_created_at,
         votes: comment.votes,
         is_admin: comment.is_admin,
     };
@@ -0,0 +1,273 @@
+//! Content bundles over HTTP, for hosts without shell access.
+//!
+//! The same export and import as the `export_content` and `import_content`
+//! utilities, see [`crate::content_bundle`]. An import plans and applies in
+//! one transaction, so a bundle is imported whole or not at all, unless
+//! `continue_on_error` reports the rows the database refuses and keeps the
+//! rest.
+//!
+//! The data derived from the content can also be rebuilt on its own, see
+//! [`crate::derived`].
+
+use crate::{
+    content_bundle::{self, Collection, ExportOptions, ImportBundle, ImportOptions, ImportReport},
+    content_fetch::{self, FetchError},
+    db,
+    derived::{self, RebuildReport, RebuildScope},
+    error::{ensure_admin, AppError},
+    middleware::body_limit::{body_limits, payload_too_large},
+    security::auth,
+    settings,
+};
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Multipart, Query, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+fn parse_only(only: Option<&str>, default: &[Collection]) -> Result<Vec<Collection>, AppError> {
+    match only {
+        Some(list) => {
+            Collection::parse_list(list).map_err(|message| AppError::invalid_field("only", message))
+        }
+        None => Ok(default.to_vec()),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BundleExportQuery {
+    /// Comma-separated collections; all but users and comments by default.
+    pub only: Option<String>,
+    #[serde(default)]
+    pub published_only: bool,
+}
+
+/// The site's content as one bundle, ready for `POST /api/admin/import`.
+pub async fn export_bundle(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<BundleExportQuery>,
+) -> Result<Json<ImportBundle>, AppError> {
+    ensure_admin(&claims)?;
+    let options = ExportOptions {
+        only: parse_only(query.only.as_deref(), &Collection::CONTENT)?,
+        published_only: query.published_only,
+    };
+
+    let bundle = content_bundle::export_bundle(&pool, &options)
+        .await
+        .map_err(|err| AppError::internal("Failed to export content", err))?;
+    Ok(Json(bundle))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BundleImportQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+    /// `overwrite` (default), `skip-existing` or `fail-on-conflict`.
+    pub strategy: Option<String>,
+    /// Comma-separated collections, all by default.
+    pub only: Option<String>,
+    #[serde(default)]
+    pub skip_invalid: bool,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl BundleImportQuery {
+    fn options(&self) -> Result<ImportOptions, AppError> {
+        Ok(ImportOptions {
+            only: parse_only(self.only.as_deref(), &Collection::ALL)?,
+            strategy: match &self.strategy {
+                Some(name) => name
+                    .parse()
+                    .map_err(|message: String| AppError::invalid_field("strategy", message))?,
+                None => Default::default(),
+            },
+            skip_invalid: self.skip_invalid,
+            continue_on_error: self.continue_on_error,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleImportResponse {
+    pub dry_run: bool,
+    pub strategy: &'static str,
+    #[serde(flatten)]
+    pub report: ImportReport,
+}
+
+/// A JSON body naming a bundle to download instead of holding one.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteBundle {
+    /// An `https` URL, see [`crate::content_fetch`].
+    pub url: String,
+    /// The expected SHA-256 of the bundle in hex.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+fn fetch_error(err: FetchError) -> AppError {
+    match err {
+        FetchError::InvalidUrl(_) => AppError::invalid_field("url", err.to_string()),
+        FetchError::InvalidChecksum(_) | FetchError::ChecksumMismatch { .. } => {
+            AppError::invalid_field("sha256", err.to_string())
+        }
+        FetchError::TooLarge { .. } | FetchError::Request(_) => AppError::Upstream(err.to_string()),
+    }
+}
+
+/// Reads the bundle from a JSON body, or from the `file` field of a
+/// multipart form, with the warnings of [`content_bundle::parse_bundle`]. A
+/// JSON body may instead name the bundle's URL.
+async fn read_bundle(request: Request) -> Result<(ImportBundle, Vec<String>), AppError> {
+    let too_large = || payload_too_large(body_limits().admin);
+    let is_multipart = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let data = if is_multipart {
+        let mut multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|rejection| AppError::validation(rejection.body_text()))?;
+        let mut data = None;
+        while let Some(field) = multipart.next_field().await.map_err(|err| {
+            if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                too_large()
+            } else {
+                AppError::validation(format!("Failed to process multipart field: {}", err))
+            }
+        })? {
+            if field.name() != Some("file") {
+                continue;
+            }
+            data = Some(field.bytes().await.map_err(|err| {
+                if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    too_large()
+                } else {
+                    AppError::invalid_field("file", format!("Failed to read the file: {}", err))
+                }
+            })?);
+            break;
+        }
+        data.ok_or_else(|| AppError::invalid_field("file", "No file found in request"))?
+    } else {
+        Bytes::from_request(request, &())
+            .await
+            .map_err(|rejection| {
+                if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    too_large()
+                } else {
+                    AppError::validation(rejection.body_text())
+                }
+            })?
+    };
+    let data = match serde_json::from_slice::<RemoteBundle>(&data) {
+        Ok(remote) => {
+            let data = content_fetch::fetch_bundle(
+                &remote.url,
+                remote.sha256.as_deref(),
+                content_fetch::fetch_config(),
+            )
+            .await
+            .map_err(fetch_error)?;
+            tracing::info!(
+                "Fetched a content bundle of {} bytes from {}",
+                data.len(),
+                remote.url
+            );
+            Bytes::from(data)
+        }
+        Err(_) => data,
+    };
+
+    content_bundle::parse_bundle(&data).map_err(|err| AppError::validation(format!("{:#}", err)))
+}
+
+/// Imports a bundle with the rules and options of `import_content`. Answers
+/// with the same report as a dry run; with `dry_run` nothing is written, and
+/// an invalid bundle is reported rather than rejected.
+pub async fn import_bundle(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<BundleImportQuery>,
+    request: Request,
+) -> Result<Json<BundleImportResponse>, AppError> {
+    ensure_admin(&claims)?;
+    let options = query.options()?;
+    let (bundle, warnings) = read_bundle(request).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|err| AppError::internal("Failed to start the import", err))?;
+    let mut plan = content_bundle::plan_import(&mut tx, &bundle, &options)
+        .await
+        .map_err(|err| AppError::internal("Failed to plan the import", err))?;
+    plan.report.warnings = warnings;
+    let respond = |report| {
+        Json(BundleImportResponse {
+            dry_run: query.dry_run,
+            strategy: options.strategy.as_str(),
+            report,
+        })
+    };
+    if query.dry_run {
+        return Ok(respond(plan.report));
+    }
+    if !plan.report.is_valid() {
+        return Err(AppError::validation(format!(
+            "The bundle is invalid: {}",
+            plan.report.errors.join("; ")
+        )));
+    }
+
+    let report = content_bundle::apply_plan(&mut tx, plan)
+        .await
+        .map_err(|err| AppError::internal("Failed to import content", err))?;
+    tx.commit()
+        .await
+        .map_err(|err| AppError::internal("Failed to import content", err))?;
+    // The bundle may have replaced the settings section
+    settings::invalidate();
+    tracing::info!(
+        "{} imported a content bundle ({})",
+        claims.sub,
+        options.strategy.as_str()
+    );
+    Ok(respond(report))
+}
+
+/// Rebuilds the topic index, the search index and the post word counts of
+/// all content, e.g. after editing the database by hand.
+pub async fn rebuild_derived(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<RebuildReport>, AppError> {
+    ensure_admin(&claims)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|err| AppError::internal("Failed to rebuild derived data", err))?;
+    let report = derived::rebuild(&mut tx, &RebuildScope::Everything)
+        .await
+        .map_err(|err| AppError::internal("Failed to rebuild derived data", err))?;
+    tx.commit()
+        .await
+        .map_err(|err| AppError::internal("Failed to rebuild derived data", err))?;
+    tracing::info!(
+        "{} rebuilt derived data: {} tutorial(s), {} topic(s), {} post(s)",
+        claims.sub,
+        report.tutorials,
+        report.topics,
+        report.posts
+    );
+    Ok(Json(report))
+}
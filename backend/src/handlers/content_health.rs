@@ -0,0 +1,600 @@
+//! Content Health
+//!
+//! `GET /api/admin/content/health` scans site content, page hero/layout JSON
+//! and share images, post markdown and tutorial content for internal
+//! references and reports the ones that no longer resolve: missing uploads,
+//! unknown home page anchors, unknown routes, and pages, posts or tutorials
+//! that were renamed, unpublished or deleted.
+//!
+//! Rows are read in keyset batches and each distinct reference is resolved
+//! once, so memory stays bounded by the batch size and the report cap rather
+//! than by the size of the site.
+
+use crate::{
+    db,
+    error::ensure_admin,
+    models::{
+        BrokenReference, BrokenReferenceKind, ContentHealthReport, ContentHealthScanned,
+        ErrorResponse, ReferenceSource,
+    },
+    repositories,
+    security::auth,
+    storage::{self, Storage},
+    utils::content_refs::{
+        classify_route, is_plain_upload_name, json_references, markdown_references, FoundReference,
+        ReferenceLocation, ReferenceTarget, RouteKind, HOME_SECTIONS, UPLOADS_PREFIX,
+    },
+};
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const SCAN_BATCH_SIZE: i64 = 100;
+
+/// Upper bound on listed references; the total is still counted.
+const MAX_REPORTED_REFERENCES: usize = 500;
+
+/// Upper bound on memoized lookups before the memo is reset.
+const MAX_MEMOIZED_TARGETS: usize = 10_000;
+
+type Breakage = Option<(BrokenReferenceKind, String)>;
+
+/// Resolves references against the database and upload storage,
+/// remembering every answer for the rest of the scan.
+struct Resolver<'a> {
+    pool: &'a db::DbPool,
+    storage: &'a dyn Storage,
+    memo: HashMap<String, Breakage>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(pool: &'a db::DbPool, storage: &'a dyn Storage) -> Self {
+        Self {
+            pool,
+            storage,
+            memo: HashMap::new(),
+        }
+    }
+
+    async fn check(&mut self, target: &ReferenceTarget) -> Result<Breakage, sqlx::Error> {
+        let key = match target {
+            ReferenceTarget::Section(name) => format!("section:{name}"),
+            ReferenceTarget::Route(path) => format!("route:{path}"),
+        };
+        if let Some(known) = self.memo.get(&key) {
+            return Ok(known.clone());
+        }
+
+        let result = match target {
+            ReferenceTarget::Section(name) => self.check_section(name),
+            ReferenceTarget::Route(path) => self.check_route(path).await?,
+        };
+
+        if self.memo.len() >= MAX_MEMOIZED_TARGETS {
+            self.memo.clear();
+        }
+        self.memo.insert(key, result.clone());
+        Ok(result)
+    }
+
+    fn check_section(&self, name: &str) -> Breakage {
+        let normalized = name.trim().to_ascii_lowercase();
+        if HOME_SECTIONS.contains(&normalized.as_str()) {
+            None
+        } else {
+            Some((
+                BrokenReferenceKind::Section,
+                "Unknown home page section".to_string(),
+            ))
+        }
+    }
+
+    async fn check_route(&self, path: &str) -> Result<Breakage, sqlx::Error> {
+        let broken = |kind, reason: &str| Ok(Some((kind, reason.to_string())));
+
+        match classify_route(path) {
+            RouteKind::Static | RouteKind::Api => Ok(None),
+            RouteKind::Unknown => broken(BrokenReferenceKind::Route, "Unknown route"),
+            RouteKind::Upload(file) => {
+                if !is_plain_upload_name(file) {
+                    return broken(BrokenReferenceKind::Upload, "Invalid upload path");
+                }
+                let exists = self.storage.exists(file).await.unwrap_or(false);
+                if exists {
+                    Ok(None)
+                } else {
+                    broken(BrokenReferenceKind::Upload, "Uploaded file does not exist")
+                }
+            }
+            RouteKind::Tutorial(id) => {
+                if repositories::tutorials::check_tutorial_exists(self.pool, id).await? {
+                    Ok(None)
+                } else {
+                    broken(BrokenReferenceKind::Tutorial, "Tutorial does not exist")
+                }
+            }
+            RouteKind::Page(slug) => {
+                match repositories::pages::get_site_page_by_slug(self.pool, slug).await? {
+                    Some(page) if page.is_published => Ok(None),
+                    Some(_) => broken(BrokenReferenceKind::Page, "Page is not published"),
+                    None => broken(BrokenReferenceKind::Page, "Page does not exist"),
+                }
+            }
+            RouteKind::Post { page, post } => {
+                let page = match repositories::pages::get_site_page_by_slug(self.pool, page).await? {
+                    Some(page) if page.is_published => page,
+                    Some(_) => return broken(BrokenReferenceKind::Page, "Page is not published"),
+                    None => return broken(BrokenReferenceKind::Page, "Page does not exist"),
+                };
+                let found =
+                    repositories::posts::get_published_post_by_slug(self.pool, &page.id, post)
+                        .await?;
+                if found.is_some() {
+                    Ok(None)
+                } else {
+                    broken(BrokenReferenceKind::Post, "Post does not exist or is not published")
+                }
+            }
+        }
+    }
+}
+
+fn add_broken(report: &mut ContentHealthReport, kind: BrokenReferenceKind, entry: BrokenReference) {
+    report.broken_count += 1;
+    let listed: usize = report.broken.values().map(Vec::len).sum();
+    if listed >= MAX_REPORTED_REFERENCES {
+        report.truncated = true;
+        return;
+    }
+    report.broken.entry(kind).or_default().push(entry);
+}
+
+async fn check_references(
+    resolver: &mut Resolver<'_>,
+    report: &mut ContentHealthReport,
+    source: ReferenceSource,
+    id: &str,
+    field: Option<&str>,
+    references: Vec<FoundReference>,
+) -> Result<(), sqlx::Error> {
+    for found in references {
+        let Some((kind, reason)) = resolver.check(&found.target).await? else {
+            continue;
+        };
+        let reference = match found.target {
+            ReferenceTarget::Section(name) => name,
+            ReferenceTarget::Route(path) => path,
+        };
+        let (pointer, line) = match found.location {
+            ReferenceLocation::Pointer(pointer) => (Some(pointer), None),
+            ReferenceLocation::Line(line) => (None, Some(line)),
+        };
+        add_broken(
+            report,
+            kind,
+            BrokenReference {
+                source,
+                id: id.to_string(),
+                field: field.map(str::to_string),
+                pointer,
+                line,
+                reference,
+                reason,
+            },
+        );
+    }
+    Ok(())
+}
+
+fn parse_json(raw: &str, what: &str) -> Option<Value> {
+    serde_json::from_str(raw)
+        .map_err(|err| tracing::warn!("Skipping unparsable JSON in {}: {}", what, err))
+        .ok()
+}
+
+/// References found in one stored document.
+pub(crate) struct ScannedDocument {
+    pub source: ReferenceSource,
+    pub id: String,
+    pub field: Option<&'static str>,
+    pub references: Vec<FoundReference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    SiteContent,
+    Pages,
+    Posts,
+    Tutorials,
+    Done,
+}
+
+/// Walks site content, pages, posts and tutorials in keyset batches and
+/// extracts the internal references of every stored document.
+pub(crate) struct ReferenceScanner<'a> {
+    pool: &'a db::DbPool,
+    stage: Stage,
+    after: String,
+    pub scanned: ContentHealthScanned,
+}
+
+impl<'a> ReferenceScanner<'a> {
+    pub(crate) fn new(pool: &'a db::DbPool) -> Self {
+        Self {
+            pool,
+            stage: Stage::SiteContent,
+            after: String::new(),
+            scanned: ContentHealthScanned::default(),
+        }
+    }
+
+    fn advance(&mut self, next: Stage) {
+        self.stage = next;
+        self.after.clear();
+    }
+
+    /// Returns the next batch of documents, or `None` once everything was read.
+    pub(crate) async fn next_batch(&mut self) -> Result<Option<Vec<ScannedDocument>>, sqlx::Error> {
+        loop {
+            let documents = match self.stage {
+                Stage::SiteContent => self.site_content_batch().await?,
+                Stage::Pages => self.page_batch().await?,
+                Stage::Posts => self.post_batch().await?,
+                Stage::Tutorials => self.tutorial_batch().await?,
+                Stage::Done => return Ok(None),
+            };
+            if let Some(documents) = documents {
+                return Ok(Some(documents));
+            }
+        }
+    }
+
+    async fn site_content_batch(&mut self) -> Result<Option<Vec<ScannedDocument>>, sqlx::Error> {
+        let batch =
+            repositories::content::list_site_content_after(self.pool, &self.after, SCAN_BATCH_SIZE)
+                .await?;
+        let Some(last) = batch.last() else {
+            self.advance(Stage::Pages);
+            return Ok(None);
+        };
+        self.after = last.section.clone();
+        self.scanned.site_content += batch.len();
+
+        Ok(Some(
+            batch
+                .into_iter()
+                .filter_map(|record| {
+                    let what = format!("site content '{}'", record.section);
+                    let content = parse_json(&record.content_json, &what)?;
+                    Some(ScannedDocument {
+                        source: ReferenceSource::SiteContent,
+                        references: json_references(&content),
+                        id: record.section,
+                        field: None,
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    async fn page_batch(&mut self) -> Result<Option<Vec<ScannedDocument>>, sqlx::Error> {
+        let batch =
+            repositories::pages::list_site_pages_after(self.pool, &self.after, SCAN_BATCH_SIZE)
+                .await?;
+        let Some(last) = batch.last() else {
+            self.advance(Stage::Posts);
+            return Ok(None);
+        };
+        self.after = last.id.clone();
+        self.scanned.pages += batch.len();
+
+        let mut documents = Vec::new();
+        for page in batch {
+            for (field, raw) in [("hero", &page.hero_json), ("layout", &page.layout_json)] {
+                let what = format!("{field} of page '{}'", page.id);
+                if let Some(content) = parse_json(raw, &what) {
+                    documents.push(ScannedDocument {
+                        source: ReferenceSource::Page,
+                        id: page.id.clone(),
+                        field: Some(field),
+                        references: json_references(&content),
+                    });
+                }
+            }
+            if let Some(image) = page.og_image.filter(|image| image.starts_with(UPLOADS_PREFIX)) {
+                documents.push(ScannedDocument {
+                    source: ReferenceSource::Page,
+                    id: page.id,
+                    field: Some("og_image"),
+                    references: vec![FoundReference {
+                        target: ReferenceTarget::Route(image),
+                        location: ReferenceLocation::Pointer(String::new()),
+                    }],
+                });
+            }
+        }
+        Ok(Some(documents))
+    }
+
+    async fn post_batch(&mut self) -> Result<Option<Vec<ScannedDocument>>, sqlx::Error> {
+        let batch =
+            repositories::posts::list_site_posts_after(self.pool, &self.after, SCAN_BATCH_SIZE)
+                .await?;
+        let Some(last) = batch.last() else {
+            self.advance(Stage::Tutorials);
+            return Ok(None);
+        };
+        self.after = last.id.clone();
+        self.scanned.posts += batch.len();
+
+        Ok(Some(
+            batch
+                .into_iter()
+                .map(|post| ScannedDocument {
+                    source: ReferenceSource::Post,
+                    references: markdown_references(&post.content_markdown),
+                    id: post.id,
+                    field: Some("content_markdown"),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn tutorial_batch(&mut self) -> Result<Option<Vec<ScannedDocument>>, sqlx::Error> {
+        let batch =
+            repositories::tutorials::list_tutorials_after(self.pool, &self.after, SCAN_BATCH_SIZE)
+                .await?;
+        let Some(last) = batch.last() else {
+            self.advance(Stage::Done);
+            return Ok(None);
+        };
+        self.after = last.id.clone();
+        self.scanned.tutorials += batch.len();
+
+        Ok(Some(
+            batch
+                .into_iter()
+                .map(|tutorial| ScannedDocument {
+                    source: ReferenceSource::Tutorial,
+                    references: markdown_references(&tutorial.content),
+                    id: tutorial.id,
+                    field: Some("content"),
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Walks all content and collects broken references.
+pub(crate) async fn scan(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+) -> Result<ContentHealthReport, sqlx::Error> {
+    let mut resolver = Resolver::new(pool, storage);
+    let mut scanner = ReferenceScanner::new(pool);
+    let mut report = ContentHealthReport::default();
+
+    while let Some(documents) = scanner.next_batch().await? {
+        for document in documents {
+            check_references(
+                &mut resolver,
+                &mut report,
+                document.source,
+                &document.id,
+                document.field,
+                document.references,
+            )
+            .await?;
+        }
+    }
+
+    report.scanned = scanner.scanned;
+    Ok(report)
+}
+
+pub async fn get_content_health(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<ContentHealthReport>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let report = scan(&pool, storage::get().as_ref()).await.map_err(|err| {
+        tracing::error!("Failed to scan content health: {}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to scan content".to_string(),
+            }),
+        )
+    })?;
+
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn insert_page(pool: &db::DbPool, id: &str, slug: &str, published: bool, hero: Value) {
+        sqlx::query(
+            "INSERT INTO site_pages (id, slug, title, is_published, hero_json) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(slug)
+        .bind(slug)
+        .bind(published)
+        .bind(hero.to_string())
+        .execute(pool)
+        .await
+        .expect("insert page");
+    }
+
+    async fn insert_post(pool: &db::DbPool, id: &str, page_id: &str, slug: &str, markdown: &str) {
+        sqlx::query(
+            "INSERT INTO site_posts (id, page_id, title, slug, content_markdown, is_published) VALUES (?, ?, ?, ?, ?, 1)",
+        )
+        .bind(id)
+        .bind(page_id)
+        .bind(slug)
+        .bind(slug)
+        .bind(markdown)
+        .execute(pool)
+        .await
+        .expect("insert post");
+    }
+
+    fn locations(
+        report: &ContentHealthReport,
+        kind: BrokenReferenceKind,
+    ) -> Vec<(String, Option<String>, Option<usize>, String)> {
+        report
+            .broken
+            .get(&kind)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| entry.id.starts_with("fixture"))
+                    .map(|entry| {
+                        (
+                            entry.id.clone(),
+                            entry.pointer.clone(),
+                            entry.line,
+                            entry.reference.clone(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_reports_each_breakage_class_with_location() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let upload_dir = std::env::temp_dir().join(format!("content-health-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&upload_dir).expect("upload dir");
+        std::fs::write(upload_dir.join("present.png"), b"png").expect("upload file");
+
+        repositories::content::upsert_site_content(
+            &pool,
+            "fixture_links",
+            &json!({
+                "logo": "/uploads/present.png",
+                "banner": "/uploads/missing.png",
+                "links": [
+                    { "target": { "type": "section", "value": "tutorials" } },
+                    { "target": { "type": "section", "value": "verschwunden" } },
+                    { "target": { "type": "route", "value": "/alt/pfad" } }
+                ]
+            }),
+            "admin",
+            0,
+        )
+        .await
+        .expect("fixture content");
+
+        insert_page(&pool, "fixture-live", "live", true, json!({})).await;
+        insert_page(&pool, "fixture-draft", "entwurf", false, json!({})).await;
+        insert_page(
+            &pool,
+            "fixture-page",
+            "referrer",
+            true,
+            json!({ "cta": { "target": { "type": "page", "slug": "entwurf" } } }),
+        )
+        .await;
+        insert_post(&pool, "fixture-post-ok", "fixture-live", "vorhanden", "").await;
+
+        sqlx::query(
+            "INSERT INTO tutorials (id, title, description, icon, color, topics) VALUES ('fixture-tut', 'T', 'D', 'Terminal', 'blue', '[]')",
+        )
+        .execute(&pool)
+        .await
+        .expect("insert tutorial");
+
+        insert_post(
+            &pool,
+            "fixture-post",
+            "fixture-live",
+            "links",
+            "[ok](/pages/live/posts/vorhanden) [tut](/tutorials/fixture-tut)\n\
+             [alt](/pages/live/posts/umbenannt)\n\
+             [weg](/tutorials/geloescht)",
+        )
+        .await;
+
+        let storage = crate::storage::LocalStorage::new(&upload_dir);
+        let report = scan(&pool, &storage).await.expect("scan");
+        let _ = std::fs::remove_dir_all(&upload_dir);
+
+        let pointer = |value: &str| Some(value.to_string());
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Upload),
+            vec![("fixture_links".into(), pointer("/banner"), None, "/uploads/missing.png".into())]
+        );
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Section),
+            vec![(
+                "fixture_links".into(),
+                pointer("/links/1/target/value"),
+                None,
+                "verschwunden".into()
+            )]
+        );
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Route),
+            vec![(
+                "fixture_links".into(),
+                pointer("/links/2/target/value"),
+                None,
+                "/alt/pfad".into()
+            )]
+        );
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Page),
+            vec![("fixture-page".into(), pointer("/cta/target/slug"), None, "/pages/entwurf".into())]
+        );
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Post),
+            vec![("fixture-post".into(), None, Some(2), "/pages/live/posts/umbenannt".into())]
+        );
+        assert_eq!(
+            locations(&report, BrokenReferenceKind::Tutorial),
+            vec![("fixture-post".into(), None, Some(3), "/tutorials/geloescht".into())]
+        );
+
+        let page_entry = report.broken[&BrokenReferenceKind::Page]
+            .iter()
+            .find(|entry| entry.id == "fixture-page")
+            .expect("page entry");
+        assert_eq!(page_entry.field.as_deref(), Some("hero"));
+        assert_eq!(page_entry.reason, "Page is not published");
+        assert_eq!(report.scanned.pages, 3);
+        assert_eq!(report.scanned.posts, 2);
+        assert!(report.scanned.tutorials >= 1);
+        assert!(!report.truncated);
+    }
+
+    #[test]
+    fn test_report_is_capped() {
+        let mut report = ContentHealthReport::default();
+        for index in 0..MAX_REPORTED_REFERENCES + 5 {
+            add_broken(
+                &mut report,
+                BrokenReferenceKind::Route,
+                BrokenReference {
+                    source: ReferenceSource::Post,
+                    id: index.to_string(),
+                    field: None,
+                    pointer: None,
+                    line: Some(1),
+                    reference: "/x".to_string(),
+                    reason: "Unknown route".to_string(),
+                },
+            );
+        }
+        assert_eq!(report.broken_count, MAX_REPORTED_REFERENCES + 5);
+        assert_eq!(report.broken[&BrokenReferenceKind::Route].len(), MAX_REPORTED_REFERENCES);
+        assert!(report.truncated);
+    }
+}
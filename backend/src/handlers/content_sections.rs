@@ -0,0 +1,383 @@
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    models::{
+        ContentSection, ContentSectionListResponse, ContentSectionResponse,
+        CreateContentSectionRequest, UpdateContentSectionRequest,
+        SITE_CONTENT_SECTIONS,
+    },
+    repositories,
+    security::auth,
+    utils::json_schema,
+    validation::content::is_builtin_section,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+const MAX_DESCRIPTION_LEN: usize = 500;
+const MAX_SCHEMA_BYTES: usize = 20_000;
+
+fn builtin_conflict(name: &str, action: &str) -> AppError {
+    AppError::Conflict(format!(
+        "'{name}' is a built-in content section and cannot be {action}"
+    ))
+}
+
+fn sanitize_description(description: &str) -> Result<String, AppError> {
+    let description = description.trim();
+    if description.chars().count() > MAX_DESCRIPTION_LEN {
+        return Err(AppError::invalid_field(
+            "description",
+            format!("Description exceeds maximum length of {MAX_DESCRIPTION_LEN} characters"),
+        ));
+    }
+    Ok(description.to_string())
+}
+
+fn validate_schema(schema: Option<&Value>) -> Result<(), AppError> {
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    let serialized = serde_json::to_string(schema)
+        .map_err(|err| AppError::invalid_field("schema", format!("Invalid schema JSON: {err}")))?;
+    if serialized.len() > MAX_SCHEMA_BYTES {
+        return Err(AppError::invalid_field(
+            "schema",
+            format!("Schema exceeds maximum size of {MAX_SCHEMA_BYTES} bytes"),
+        ));
+    }
+    json_schema::check_schema(schema)
+        .map_err(|err| AppError::invalid_field("schema", format!("Invalid schema: {err}")))
+}
+
+fn map_section(section: ContentSection) -> Result<ContentSectionResponse, AppError> {
+    let schema = section
+        .schema_json
+        .as_deref()
+        .map(serde_json::from_str::<Value>)
+        .transpose()
+        .map_err(|err| AppError::internal("Failed to parse stored section schema", err))?;
+
+    Ok(ContentSectionResponse {
+        name: section.name,
+        description: section.description,
+        schema,
+        builtin: false,
+        created_at: Some(section.created_at),
+    })
+}
+
+fn builtin_response(name: &str) -> ContentSectionResponse {
+    ContentSectionResponse {
+        name: name.to_string(),
+        description: String::new(),
+        schema: None,
+        builtin: true,
+        created_at: None,
+    }
+}
+
+/// Lists built-in sections followed by registered ones.
+pub async fn list_content_sections(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<ContentSectionListResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let records = repositories::content_sections::list_content_sections(&pool)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+
+    let mut items: Vec<ContentSectionResponse> = SITE_CONTENT_SECTIONS
+        .iter()
+        .map(|name| builtin_response(name))
+        .collect();
+    for record in records {
+        items.push(map_section(record)?);
+    }
+
+    Ok(Json(ContentSectionListResponse { items }))
+}
+
+pub async fn get_content_section(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(name): Path<String>,
+) -> Result<Json<ContentSectionResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    if is_builtin_section(&name) {
+        return Ok(Json(builtin_response(&name)));
+    }
+
+    let record = repositories::content_sections::get_content_section(&pool, &name)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Content section"))?
+        .ok_or_else(|| AppError::NotFound("Content section not found".to_string()))?;
+
+    Ok(Json(map_section(record)?))
+}
+
+pub async fn create_content_section(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Json(payload): Json<CreateContentSectionRequest>,
+) -> Result<Json<ContentSectionResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let name = payload.name.trim().to_lowercase();
+    if is_builtin_section(&name) {
+        return Err(builtin_conflict(&name, "registered again"));
+    }
+    let description = sanitize_description(&payload.description)?;
+    validate_schema(payload.schema.as_ref())?;
+
+    let record = repositories::content_sections::create_content_section(
+        &pool,
+        &name,
+        &description,
+        payload.schema.as_ref(),
+    )
+    .await
+    .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+
+    Ok(Json(map_section(record)?))
+}
+
+/// Replaces a section's description and schema. Existing content must still
+/// satisfy the new schema, so a schema change can't strand stored data.
+pub async fn update_content_section(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateContentSectionRequest>,
+) -> Result<Json<ContentSectionResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    if is_builtin_section(&name) {
+        return Err(builtin_conflict(&name, "modified"));
+    }
+    let description = sanitize_description(&payload.description)?;
+    validate_schema(payload.schema.as_ref())?;
+
+    if let Some(schema) = payload.schema.as_ref() {
+        let stored = repositories::content::fetch_site_content_by_section(&pool, &name)
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+        if let Some(stored) = stored {
+            let content: Value = serde_json::from_str(&stored.content_json)
+                .map_err(|err| AppError::internal("Failed to parse stored content JSON", err))?;
+            json_schema::validate(schema, &content).map_err(|err| {
+                AppError::Conflict(format!(
+                    "Stored content does not match the new schema: {err}"
+                ))
+            })?;
+        }
+    }
+
+    let record = repositories::content_sections::update_content_section(
+        &pool,
+        &name,
+        &description,
+        payload.schema.as_ref(),
+    )
+    .await
+    .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+
+    Ok(Json(map_section(record)?))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteContentSectionQuery {
+    /// Also delete the section's stored content and history.
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+pub async fn delete_content_section(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteContentSectionQuery>,
+) -> Result<StatusCode, AppError> {
+    ensure_admin(&claims)?;
+
+    if is_builtin_section(&name) {
+        return Err(builtin_conflict(&name, "deleted"));
+    }
+
+    if !query.cascade {
+        let stored = repositories::content::fetch_site_content_by_section(&pool, &name)
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+        if stored.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Content section '{name}' still has content; delete with cascade=true to remove it as well"
+            )));
+        }
+    }
+
+    repositories::content_sections::delete_content_section(&pool, &name, query.cascade)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Content section"))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::handlers::site_content;
+    use crate::models::{ErrorResponse, UpdateSiteContentRequest};
+    use crate::security::auth::test_admin_claims as admin;
+    use serde_json::json;
+
+    async fn register(pool: &db::DbPool, name: &str, schema: Option<Value>) {
+        let _ = create_content_section(
+            admin(),
+            State(pool.clone()),
+            Json(CreateContentSectionRequest {
+                name: name.to_string(),
+                description: "Banner über der Navigation".to_string(),
+                schema,
+            }),
+        )
+        .await
+        .expect("register section");
+    }
+
+    async fn save(
+        pool: &db::DbPool,
+        section: &str,
+        content: Value,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        site_content::update_site_content(
+            admin(),
+            State(pool.clone()),
+            Path(section.to_string()),
+            Json(UpdateSiteContentRequest { content }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    fn banner_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["text"],
+            "properties": { "text": { "type": "string", "minLength": 1 } }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_registered_section_accepts_content() {
+        let pool = create_test_pool().await;
+        let err = save(&pool, "banner", json!({ "text": "Hallo" })).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+
+        register(&pool, "banner", None).await;
+        save(&pool, "banner", json!({ "text": "Hallo" }))
+            .await
+            .expect("save registered section");
+
+        let Json(list) = list_content_sections(admin(), State(pool.clone()))
+            .await
+            .expect("list");
+        let banner = list.items.iter().find(|item| item.name == "banner").unwrap();
+        assert!(!banner.builtin);
+        assert!(list.items.iter().any(|item| item.name == "hero" && item.builtin));
+    }
+
+    #[tokio::test]
+    async fn test_schema_rejects_invalid_content() {
+        let pool = create_test_pool().await;
+        register(&pool, "banner", Some(banner_schema())).await;
+
+        let err = save(&pool, "banner", json!({ "text": "" })).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.1.error,
+            "Invalid structure for section 'banner': /text: must be at least 1 characters long"
+        );
+        save(&pool, "banner", json!({ "text": "Wartung" }))
+            .await
+            .expect("valid content");
+
+        // A schema the stored content no longer satisfies is refused.
+        let err = update_content_section(
+            admin(),
+            State(pool.clone()),
+            Path("banner".to_string()),
+            Json(UpdateContentSectionRequest {
+                description: String::new(),
+                schema: Some(json!({ "type": "object", "required": ["level"] })),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_registration_validates_name_and_schema() {
+        let pool = create_test_pool().await;
+        let create = |name: &str, schema: Option<Value>| {
+            create_content_section(
+                admin(),
+                State(pool.clone()),
+                Json(CreateContentSectionRequest {
+                    name: name.to_string(),
+                    description: String::new(),
+                    schema,
+                }),
+            )
+        };
+
+        assert_eq!(create("hero", None).await.unwrap_err().status(), StatusCode::CONFLICT);
+        assert_eq!(
+            create("Bad Name!", None).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+        let err = create("banner", Some(json!({ "anyOf": [] }))).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(err.message().contains("unsupported keyword 'anyOf'"));
+
+        register(&pool, "banner", None).await;
+        assert_eq!(create("banner", None).await.unwrap_err().status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_delete_is_blocked_while_content_exists() {
+        let pool = create_test_pool().await;
+        register(&pool, "banner", None).await;
+        save(&pool, "banner", json!({ "text": "Hallo" }))
+            .await
+            .expect("save");
+
+        let delete = |name: &str, cascade: bool| {
+            delete_content_section(
+                admin(),
+                State(pool.clone()),
+                Path(name.to_string()),
+                Query(DeleteContentSectionQuery { cascade }),
+            )
+        };
+
+        assert_eq!(delete("hero", true).await.unwrap_err().status(), StatusCode::CONFLICT);
+        assert_eq!(delete("banner", false).await.unwrap_err().status(), StatusCode::CONFLICT);
+        assert_eq!(
+            delete("banner", true).await.expect("cascade delete"),
+            StatusCode::NO_CONTENT
+        );
+
+        let err = save(&pool, "banner", json!({ "text": "Hallo" })).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+        assert_eq!(delete("banner", false).await.unwrap_err().status(), StatusCode::NOT_FOUND);
+    }
+}
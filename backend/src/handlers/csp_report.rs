@@ -0,0 +1,91 @@
+//! Collects Content-Security-Policy violation reports.
+//!
+//! Point `CSP_REPORT_URI` at `/api/csp-report` to have browsers post their
+//! reports here; each one is logged under the `csp` target. Both the legacy
+//! `report-uri` format and Reporting API batches are understood.
+
+use axum::{body::Bytes, http::StatusCode};
+use serde_json::Value;
+
+/// Largest report body accepted; real reports are a few hundred bytes.
+pub const CSP_REPORT_BODY_LIMIT: usize = 16 * 1024;
+
+/// Field of a report, under its legacy or Reporting API name.
+fn field<'a>(report: &'a Value, legacy: &str, reporting: &str) -> &'a str {
+    report
+        .get(legacy)
+        .or_else(|| report.get(reporting))
+        .and_then(Value::as_str)
+        .unwrap_or("")
+}
+
+fn log_violation(report: &Value) {
+    tracing::warn!(
+        target: "csp",
+        document_uri = field(report, "document-uri", "documentURL"),
+        directive = field(report, "effective-directive", "effectiveDirective"),
+        blocked_uri = field(report, "blocked-uri", "blockedURL"),
+        source_file = field(report, "source-file", "sourceFile"),
+        "CSP violation"
+    );
+}
+
+/// Individual reports in a request body.
+fn violations(body: &Value) -> Vec<&Value> {
+    match body {
+        // `application/reports+json`: a batch of typed reports
+        Value::Array(reports) => reports
+            .iter()
+            .filter(|report| report.get("type").and_then(Value::as_str) == Some("csp-violation"))
+            .filter_map(|report| report.get("body"))
+            .collect(),
+        // `application/csp-report`: one report per request
+        _ => body.get("csp-report").into_iter().collect(),
+    }
+}
+
+pub async fn csp_report(body: Bytes) -> StatusCode {
+    let Ok(body) = serde_json::from_slice::<Value>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    for report in violations(&body) {
+        log_violation(report);
+    }
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_both_report_formats_are_read() {
+        let legacy = json!({
+            "csp-report": {
+                "document-uri": "https://example.com/admin",
+                "effective-directive": "connect-src",
+                "blocked-uri": "https://api.example.net"
+            }
+        });
+        let reports = violations(&legacy);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(
+            field(reports[0], "effective-directive", "effectiveDirective"),
+            "connect-src"
+        );
+
+        let batch = json!([
+            {
+                "type": "csp-violation",
+                "body": { "documentURL": "https://example.com/", "blockedURL": "inline" }
+            },
+            { "type": "deprecation", "body": {} }
+        ]);
+        let reports = violations(&batch);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(field(reports[0], "blocked-uri", "blockedURL"), "inline");
+
+        assert!(violations(&json!({ "unrelated": true })).is_empty());
+    }
+}
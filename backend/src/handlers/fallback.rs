@@ -0,0 +1,17 @@
+//! Answers for requests no API route matches.
+//!
+//! Without it, an unknown `/api/...` path falls through to the frontend
+//! catch-all and returns the SPA's HTML with 200.
+
+use crate::models::ErrorResponse;
+use axum::{http::StatusCode, Json};
+
+/// Fallback for the `/api` prefix.
+pub async fn api_not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Not found".to_string(),
+        }),
+    )
+}
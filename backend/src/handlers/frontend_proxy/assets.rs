@@ -0,0 +1,147 @@
+//! The built frontend, served from `FRONTEND_STATIC_DIR`.
+//!
+//! Single-container deployments can do without the frontend container:
+//! pointed at the Vite build output, the backend serves its files, using the
+//! `.br` and `.gz` copies next to them when the client accepts them, and
+//! reads `index.html` from there ([`IndexCache::from_dir`]). Vite names what
+//! it puts below `/assets/` after the content's hash, so those files are
+//! cached for a year; everything else is revalidated.
+//!
+//! [`IndexCache::from_dir`]: super::IndexCache::from_dir
+
+use crate::middleware::uploads::IMMUTABLE_CACHE_CONTROL;
+use crate::utils::conditional::REVALIDATE;
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header::CACHE_CONTROL, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::path::{Path, PathBuf};
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+
+/// Checks the value of `FRONTEND_STATIC_DIR`; unset keeps proxying the
+/// frontend container.
+pub(crate) fn parse_static_dir(value: Option<String>) -> Result<Option<PathBuf>, String> {
+    let Some(raw) = value.map(|raw| raw.trim().to_string()) else {
+        return Ok(None);
+    };
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let dir = PathBuf::from(&raw);
+    if !dir.join("index.html").is_file() {
+        return Err(format!(
+            "FRONTEND_STATIC_DIR must be a frontend build containing index.html, got '{}'",
+            raw
+        ));
+    }
+    Ok(Some(dir))
+}
+
+/// Serves the file `request` asks for from `dir`; a missing one is a bare
+/// 404.
+pub(super) async fn serve_asset(dir: &Path, request: Request) -> Response {
+    let path = request.uri().path().to_string();
+    // Only ever served with the page's meta injected
+    if path == "/index.html" {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let files = ServeDir::new(dir)
+        .append_index_html_on_directories(false)
+        .precompressed_br()
+        .precompressed_gzip();
+    let mut response = match files.oneshot(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    };
+
+    let status = response.status();
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        let cache_control = if path.starts_with("/assets/") {
+            IMMUTABLE_CACHE_CONTROL
+        } else {
+            REVALIDATE
+        };
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+
+    /// A build output with a hashed script, its Brotli copy and a manifest.
+    fn build_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("frontend-build-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("index.html"), "<html></html>").unwrap();
+        std::fs::write(dir.join("assets/index-B2x7Kq_a.js"), "console.log(1)").unwrap();
+        std::fs::write(dir.join("assets/index-B2x7Kq_a.js.br"), "brotli").unwrap();
+        std::fs::write(dir.join("manifest.json"), "{}").unwrap();
+        dir
+    }
+
+    async fn get(dir: &Path, path: &str, encoding: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(path);
+        if let Some(encoding) = encoding {
+            request = request.header(ACCEPT_ENCODING, encoding);
+        }
+        serve_asset(dir, request.body(Body::empty()).unwrap()).await
+    }
+
+    #[test]
+    fn test_parse_static_dir() {
+        assert_eq!(parse_static_dir(None), Ok(None));
+        assert_eq!(parse_static_dir(Some(" ".to_string())), Ok(None));
+
+        let dir = build_dir();
+        assert_eq!(
+            parse_static_dir(Some(dir.display().to_string())),
+            Ok(Some(dir.clone()))
+        );
+        let assets = dir.join("assets").display().to_string();
+        assert!(parse_static_dir(Some(assets)).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hashed_assets_are_immutable_and_precompressed() {
+        let dir = build_dir();
+
+        let response = get(&dir, "/assets/index-B2x7Kq_a.js", Some("br, gzip")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_ENCODING], "br");
+        assert_eq!(response.headers()[CACHE_CONTROL], IMMUTABLE_CACHE_CONTROL);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"brotli");
+
+        let response = get(&dir, "/assets/index-B2x7Kq_a.js", None).await;
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_other_files_are_revalidated() {
+        let dir = build_dir();
+
+        let response = get(&dir, "/manifest.json", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], REVALIDATE);
+
+        for path in ["/missing.js", "/index.html", "/../index.html"] {
+            let response = get(&dir, path, None).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+            assert!(response.headers().get(CACHE_CONTROL).is_none());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
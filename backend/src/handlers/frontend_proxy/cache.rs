@@ -0,0 +1,590 @@
+//! In-memory copy of the frontend's `index.html`.
+//!
+//! Fetching the SPA shell from the frontend container and `site_meta` and
+//! the header navigation from the database on every page view doubles the
+//! latency of each view and takes the site down with the container. The
+//! [`IndexCache`] keeps them for `FRONTEND_CACHE_TTL_SECONDS` (default 60). Once that has passed, the next
+//! request still gets the stale copy while one refresh runs in the
+//! background; a failed refresh keeps the stale copy. Only a cold cache makes
+//! a request wait for the fetch, and concurrent cold requests share it.
+//!
+//! `POST /api/admin/cache/frontend/purge` drops the copy right away, e.g.
+//! after a frontend deploy. A TTL of `0` turns the cache off.
+//!
+//! Read from `FRONTEND_STATIC_DIR` instead, the copy is also reloaded as soon
+//! as the file's modification time changes, so a new build shows up without
+//! a purge.
+//!
+//! The `site_meta` of the other supported locales is loaded with it, already
+//! merged over the default one.
+//!
+//! The cache also holds the HTML generation that goes into every page's
+//! ETag. `POST /api/admin/cache/html/purge` bumps it, so CDNs and browsers
+//! holding a page revalidate and get it anew.
+
+use super::inline::{self, Preload};
+use super::locale;
+use super::paths;
+use super::upstream::FrontendClient;
+use crate::db::DbPool;
+use crate::utils::timestamps::format_timestamp;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexCacheConfig {
+    pub ttl: Duration,
+}
+
+impl Default for IndexCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+impl IndexCacheConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the settings through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let Some(raw) = lookup("FRONTEND_CACHE_TTL_SECONDS").filter(|raw| !raw.trim().is_empty())
+        else {
+            return Ok(Self::default());
+        };
+        let seconds = raw.trim().parse::<u64>().map_err(|_| {
+            format!(
+                "FRONTEND_CACHE_TTL_SECONDS must be a number of seconds, got '{}'",
+                raw
+            )
+        })?;
+        Ok(Self {
+            ttl: Duration::from_secs(seconds),
+        })
+    }
+}
+
+/// The fetched SPA shell with the `site_meta` it is rendered with.
+#[derive(Debug)]
+pub struct CachedIndex {
+    pub html: String,
+    pub site_meta: Value,
+    /// When `site_meta` was saved; empty while it never was.
+    pub site_meta_updated_at: String,
+    /// `site_meta` by locale, for the locales that have their own, with
+    /// when that was saved.
+    localized_meta: HashMap<String, (Value, String)>,
+    /// Local paths the header navigation links to.
+    pub nav_paths: Vec<String>,
+    /// The bundles the shell loads, as preload hints.
+    pub(super) preloads: Vec<Preload>,
+    fetched_at: Instant,
+    /// Modification time of `index.html` when read from disk.
+    modified: Option<SystemTime>,
+}
+
+impl CachedIndex {
+    /// The `site_meta` of `locale` and when its own section was saved,
+    /// empty for the default one.
+    pub fn site_meta_for(&self, locale: &str) -> (&Value, &str) {
+        match self.localized_meta.get(locale) {
+            Some((site_meta, updated_at)) => (site_meta, updated_at),
+            None => (&self.site_meta, ""),
+        }
+    }
+}
+
+/// How the last fetch of `index.html` went.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshResult {
+    pub at: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// What the cache holds, for the frontend status report.
+#[derive(Debug, Serialize)]
+pub struct CacheState {
+    /// `frontend` when proxying the container, `static` when reading
+    /// `FRONTEND_STATIC_DIR`.
+    pub source: &'static str,
+    pub static_dir: Option<String>,
+    pub ttl_seconds: u64,
+    pub cached: bool,
+    pub age_seconds: Option<u64>,
+    pub html_generation: u64,
+    pub last_refresh: Option<RefreshResult>,
+}
+
+/// Where `index.html` comes from.
+#[derive(Debug)]
+enum Source {
+    Frontend(FrontendClient),
+    Disk(PathBuf),
+}
+
+impl Source {
+    /// The modification time of `index.html` on disk; always `None` for the
+    /// frontend container.
+    async fn modified(&self) -> Option<SystemTime> {
+        match self {
+            Self::Frontend(_) => None,
+            Self::Disk(dir) => tokio::fs::metadata(dir.join("index.html"))
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+
+    async fn fetch(&self) -> Result<(String, Option<SystemTime>), String> {
+        match self {
+            Self::Frontend(client) => Ok((client.fetch_index().await?, None)),
+            Self::Disk(dir) => {
+                let path = dir.join("index.html");
+                let modified = self.modified().await;
+                let html = tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+                Ok((html, modified))
+            }
+        }
+    }
+}
+
+/// Shared by every listener; clones share the cached copy.
+#[derive(Debug, Clone)]
+pub struct IndexCache {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: IndexCacheConfig,
+    source: Source,
+    entry: RwLock<Option<Arc<CachedIndex>>>,
+    last_refresh: RwLock<Option<RefreshResult>>,
+    /// Bumped by every purge, so a fetch started before it is not stored.
+    generation: AtomicU64,
+    /// Part of every page's ETag; bumped by the HTML purge.
+    html_generation: AtomicU64,
+    refreshing: AtomicBool,
+    cold_start: tokio::sync::Mutex<()>,
+}
+
+impl Default for IndexCache {
+    fn default() -> Self {
+        Self::new(IndexCacheConfig::default(), FrontendClient::default())
+    }
+}
+
+impl IndexCache {
+    /// Fetches `index.html` from the frontend container.
+    pub fn new(config: IndexCacheConfig, client: FrontendClient) -> Self {
+        Self::with_source(config, Source::Frontend(client))
+    }
+
+    /// Reads `index.html` from the built frontend in `dir`.
+    pub fn from_dir(config: IndexCacheConfig, dir: PathBuf) -> Self {
+        Self::with_source(config, Source::Disk(dir))
+    }
+
+    fn with_source(config: IndexCacheConfig, source: Source) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                source,
+                entry: RwLock::new(None),
+                last_refresh: RwLock::new(None),
+                generation: AtomicU64::new(0),
+                html_generation: AtomicU64::new(0),
+                refreshing: AtomicBool::new(false),
+                cold_start: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    /// The cached copy, fetched first when there is none. A stale copy is
+    /// returned as is and refreshed in the background.
+    pub async fn get(&self, pool: &DbPool) -> Result<Arc<CachedIndex>, String> {
+        if self.inner.config.ttl.is_zero() {
+            return self.inner.load(pool).await.map(Arc::new);
+        }
+        if let Some(entry) = self.current_unchanged().await {
+            if entry.fetched_at.elapsed() >= self.inner.config.ttl {
+                self.refresh_in_background(pool.clone());
+            }
+            return Ok(entry);
+        }
+
+        let _cold_start = self.inner.cold_start.lock().await;
+        // Loaded while this request waited for the lock
+        if let Some(entry) = self.current_unchanged().await {
+            return Ok(entry);
+        }
+        let generation = self.inner.generation.load(Ordering::SeqCst);
+        let entry = Arc::new(self.inner.load(pool).await?);
+        self.inner.store(generation, entry.clone());
+        Ok(entry)
+    }
+
+    /// Drops the cached copy; the next request fetches a fresh one.
+    pub fn purge(&self) {
+        self.inner.generation.fetch_add(1, Ordering::SeqCst);
+        *self
+            .inner
+            .entry
+            .write()
+            .unwrap_or_else(|err| err.into_inner()) = None;
+        tracing::info!("Purged the cached frontend index");
+    }
+
+    /// Changes the ETag of every page, so caches holding one revalidate.
+    pub fn purge_html(&self) {
+        let generation = self.inner.html_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        tracing::info!(generation, "Purged cached HTML pages");
+    }
+
+    pub fn html_generation(&self) -> u64 {
+        self.inner.html_generation.load(Ordering::SeqCst)
+    }
+
+    /// The directory of the built frontend, when served from disk.
+    pub fn static_dir(&self) -> Option<&Path> {
+        match &self.inner.source {
+            Source::Disk(dir) => Some(dir),
+            Source::Frontend(_) => None,
+        }
+    }
+
+    /// The client fetching from the frontend container, unless served from
+    /// disk.
+    pub fn frontend_client(&self) -> Option<&FrontendClient> {
+        match &self.inner.source {
+            Source::Frontend(client) => Some(client),
+            Source::Disk(_) => None,
+        }
+    }
+
+    pub fn state(&self) -> CacheState {
+        let entry = self.current();
+        CacheState {
+            source: match self.inner.source {
+                Source::Frontend(_) => "frontend",
+                Source::Disk(_) => "static",
+            },
+            static_dir: self.static_dir().map(|dir| dir.display().to_string()),
+            ttl_seconds: self.inner.config.ttl.as_secs(),
+            cached: entry.is_some(),
+            age_seconds: entry.map(|entry| entry.fetched_at.elapsed().as_secs()),
+            html_generation: self.html_generation(),
+            last_refresh: self
+                .inner
+                .last_refresh
+                .read()
+                .unwrap_or_else(|err| err.into_inner())
+                .clone(),
+        }
+    }
+
+    fn current(&self) -> Option<Arc<CachedIndex>> {
+        self.inner
+            .entry
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// The cached copy, unless `index.html` changed on disk since.
+    async fn current_unchanged(&self) -> Option<Arc<CachedIndex>> {
+        let entry = self.current()?;
+        (entry.modified == self.inner.source.modified().await).then_some(entry)
+    }
+
+    fn refresh_in_background(&self, pool: DbPool) {
+        if self.inner.refreshing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let generation = inner.generation.load(Ordering::SeqCst);
+            match inner.load(&pool).await {
+                Ok(entry) => inner.store(generation, Arc::new(entry)),
+                Err(err) => tracing::warn!("Keeping the stale frontend index: {}", err),
+            }
+            inner.refreshing.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+impl Inner {
+    fn store(&self, generation: u64, entry: Arc<CachedIndex>) {
+        let mut slot = self.entry.write().unwrap_or_else(|err| err.into_inner());
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *slot = Some(entry);
+        }
+    }
+
+    async fn load(&self, pool: &DbPool) -> Result<CachedIndex, String> {
+        let fetched = self.source.fetch().await;
+        *self
+            .last_refresh
+            .write()
+            .unwrap_or_else(|err| err.into_inner()) = Some(RefreshResult {
+            at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            ok: fetched.is_ok(),
+            error: fetched.as_ref().err().cloned(),
+        });
+        let (html, modified) = fetched?;
+
+        let (site_meta, site_meta_updated_at) = section(pool, "site_meta").await;
+        let (header, _) = section(pool, "header").await;
+        let locales = locale::supported_locales();
+        let mut localized_meta = HashMap::new();
+        for supported in locales.supported() {
+            let Some(name) = locales.site_meta_section(supported) else {
+                continue;
+            };
+            let (localized, updated_at) = section(pool, &name).await;
+            if !localized.is_null() {
+                let merged = locale::merge_site_meta(&site_meta, &localized);
+                localized_meta.insert(supported.clone(), (merged, updated_at));
+            }
+        }
+
+        let preloads = inline::discover_preloads(&html);
+
+        Ok(CachedIndex {
+            html,
+            site_meta,
+            site_meta_updated_at,
+            localized_meta,
+            nav_paths: paths::nav_paths(&header),
+            preloads,
+            fetched_at: Instant::now(),
+            modified,
+        })
+    }
+}
+
+/// A site content section with the time it was saved, or `null` and an
+/// empty time when it is missing or unreadable.
+async fn section(pool: &DbPool, section: &str) -> (Value, String) {
+    match crate::repositories::content::fetch_site_content_by_section(pool, section).await {
+        Ok(Some(record)) => (
+            serde_json::from_str(&record.content_json).unwrap_or_else(|_| Value::default()),
+            format_timestamp(&record.updated_at),
+        ),
+        _ => (Value::default(), String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::frontend_proxy::upstream::FrontendClientConfig;
+    use axum::{extract::State, routing::get, Router};
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::Semaphore;
+
+    /// A frontend answering `v1` first; later fetches wait for `release`
+    /// and then answer `v2`.
+    #[derive(Clone)]
+    struct Upstream {
+        hits: Arc<AtomicUsize>,
+        gate: Arc<Semaphore>,
+    }
+
+    impl Upstream {
+        fn new() -> Self {
+            Self {
+                hits: Arc::new(AtomicUsize::new(0)),
+                gate: Arc::new(Semaphore::new(0)),
+            }
+        }
+
+        async fn start(&self) -> String {
+            let app = Router::new()
+                .route(
+                    "/index.html",
+                    get(|State(upstream): State<Upstream>| async move {
+                        if upstream.hits.fetch_add(1, Ordering::SeqCst) == 0 {
+                            return "v1";
+                        }
+                        let _permit = upstream.gate.acquire().await.unwrap();
+                        "v2"
+                    }),
+                )
+                .with_state(self.clone());
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            url
+        }
+
+        fn hits(&self) -> usize {
+            self.hits.load(Ordering::SeqCst)
+        }
+
+        fn release(&self) {
+            self.gate.add_permits(Semaphore::MAX_PERMITS / 2);
+        }
+    }
+
+    fn cache(url: String, ttl: Duration) -> IndexCache {
+        let client = FrontendClient::new(FrontendClientConfig {
+            url,
+            ..FrontendClientConfig::default()
+        });
+        IndexCache::new(IndexCacheConfig { ttl }, client)
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        assert_eq!(
+            IndexCacheConfig::parse(|_| None).unwrap(),
+            IndexCacheConfig::default()
+        );
+        let config = IndexCacheConfig::parse(|key| {
+            (key == "FRONTEND_CACHE_TTL_SECONDS").then(|| " 0 ".to_string())
+        })
+        .unwrap();
+        assert_eq!(config.ttl, Duration::ZERO);
+        assert!(IndexCacheConfig::parse(|_| Some("soon".to_string())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cold_start_fetches_once() {
+        let pool = crate::db::pool::create_test_pool().await;
+        crate::repositories::content::upsert_site_content(
+            &pool,
+            "site_meta",
+            &serde_json::json!({ "title": "Linux lernen" }),
+            "admin",
+            10,
+        )
+        .await
+        .unwrap();
+        let upstream = Upstream::new();
+        let cache = cache(upstream.start().await, Duration::from_secs(60));
+
+        let (first, second) = tokio::join!(cache.get(&pool), cache.get(&pool));
+        assert_eq!(first.unwrap().html, "v1");
+        assert_eq!(second.unwrap().html, "v1");
+        assert_eq!(upstream.hits(), 1);
+        assert_eq!(
+            cache.get(&pool).await.unwrap().site_meta["title"],
+            "Linux lernen"
+        );
+        assert_eq!(upstream.hits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cold_start_without_frontend_fails_and_caches_nothing() {
+        let pool = crate::db::pool::create_test_pool().await;
+        // A port nobody listens on any more
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let cache = cache(url, Duration::from_secs(60));
+
+        let err = cache.get(&pool).await.unwrap_err();
+        assert!(err.starts_with("Failed to fetch index.html"), "{err}");
+        assert!(cache.current().is_none());
+
+        let state = cache.state();
+        assert_eq!(state.source, "frontend");
+        assert!(!state.cached);
+        assert_eq!(state.age_seconds, None);
+        let last_refresh = state.last_refresh.expect("refresh recorded");
+        assert!(!last_refresh.ok);
+        assert_eq!(last_refresh.error, Some(err));
+    }
+
+    #[tokio::test]
+    async fn test_stale_copy_is_served_while_refreshing() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let upstream = Upstream::new();
+        let cache = cache(upstream.start().await, Duration::from_millis(20));
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v1");
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // The refresh hangs until released; requests keep getting v1 fast
+        // and do not start refreshes of their own
+        for _ in 0..3 {
+            let entry = tokio::time::timeout(Duration::from_millis(100), cache.get(&pool))
+                .await
+                .expect("stale copy served without waiting");
+            assert_eq!(entry.unwrap().html, "v1");
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(upstream.hits(), 2);
+
+        upstream.release();
+        for _ in 0..100 {
+            if cache.current().unwrap().html == "v2" {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("the refreshed copy was never stored");
+    }
+
+    #[tokio::test]
+    async fn test_index_from_disk_is_reloaded_when_modified() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = std::env::temp_dir().join(format!("index-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index = dir.join("index.html");
+        let write = |html: &str, modified: SystemTime| {
+            std::fs::write(&index, html).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&index)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        let built = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        write("v1", built);
+        let cache = IndexCache::from_dir(IndexCacheConfig::default(), dir.clone());
+        assert_eq!(cache.static_dir(), Some(dir.as_path()));
+
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v1");
+        // Within the TTL, an unchanged file is not read again
+        write("v2", built);
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v1");
+
+        write("v3", built + Duration::from_secs(60));
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v3");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(cache
+            .get(&pool)
+            .await
+            .unwrap_err()
+            .starts_with("Failed to read"));
+    }
+
+    #[tokio::test]
+    async fn test_purge_forces_a_fresh_fetch() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let upstream = Upstream::new();
+        upstream.release();
+        let cache = cache(upstream.start().await, Duration::from_secs(60));
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v1");
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v1");
+
+        cache.clone().purge();
+        assert_eq!(cache.get(&pool).await.unwrap().html, "v2");
+        assert_eq!(upstream.hits(), 2);
+    }
+}
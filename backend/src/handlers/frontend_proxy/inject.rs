@@ -0,0 +1,444 @@
+//! Writes the meta values into the SPA shell.
+//!
+//! An HTML rewriter finds the `<title>`, the description and the Open Graph
+//! and Twitter tags in `<head>` however the frontend build formats them, so
+//! a changed default or reordered attribute no longer leaves stale meta
+//! behind. Tags the shell lacks are added before `</head>`, except the
+//! Twitter ones: Twitter reads the Open Graph tags when its own are missing.
+//! The canonical link replaces the shell's, if any, and the JSON-LD script
+//! goes last.
+//!
+//! With a negotiated locale, `<html lang>` is set to it and the locale script
+//! goes before the JSON-LD. The `hreflang` alternates replace any the shell
+//! has.
+//!
+//! Preload hints open `<head>`; the bootstrap bundle closes `<body>`.
+
+use super::locale::locale_script;
+use super::meta::PageMeta;
+use lol_html::html_content::{ContentType, Element};
+use lol_html::{element, end_tag, errors::RewritingError, HtmlRewriter, Settings};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The tags seen in `<head>`, so the missing ones can be added.
+#[derive(Debug, Default)]
+struct Seen {
+    title: bool,
+    description: bool,
+    og_title: bool,
+    og_description: bool,
+    og_image: bool,
+    canonical: bool,
+}
+
+fn attribute(value: &str) -> String {
+    html_escape::encode_double_quoted_attribute(value).into_owned()
+}
+
+/// Sets `content` to `value`, which is escaped here.
+fn set_content(el: &mut Element, value: &str) -> lol_html::HandlerResult {
+    el.set_attribute("content", &attribute(value))?;
+    Ok(())
+}
+
+/// The tags for whatever `seen` lacks.
+fn missing_tags(seen: &Seen, meta: &PageMeta) -> String {
+    let mut tags = String::new();
+    if !seen.title {
+        tags.push_str(&format!(
+            "<title>{}</title>",
+            html_escape::encode_text(&meta.title)
+        ));
+    }
+    let mut tag = |present: bool, key: &str, name: &str, value: &str| {
+        if !present {
+            tags.push_str(&format!(
+                "<meta {}=\"{}\" content=\"{}\" />",
+                key,
+                name,
+                attribute(value)
+            ));
+        }
+    };
+    tag(seen.description, "name", "description", &meta.description);
+    tag(seen.og_title, "property", "og:title", &meta.title);
+    tag(
+        seen.og_description,
+        "property",
+        "og:description",
+        &meta.description,
+    );
+    if let Some(image) = meta.image.as_deref() {
+        tag(seen.og_image, "property", "og:image", image);
+    }
+    if let (false, Some(canonical)) = (seen.canonical, meta.canonical.as_deref()) {
+        tags.push_str(&format!(
+            "<link rel=\"canonical\" href=\"{}\" />",
+            attribute(canonical)
+        ));
+    }
+    for alternate in &meta.alternates {
+        tags.push_str(&format!(
+            "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\" />",
+            attribute(&alternate.hreflang),
+            attribute(&alternate.href)
+        ));
+    }
+    if let Some(lang) = meta.lang.as_deref() {
+        tags.push_str(&format!("<script>{}</script>", locale_script(lang)));
+    }
+    if let Some(data) = meta.structured_data.as_deref() {
+        tags.push_str(&format!(
+            "<script type=\"application/ld+json\">{}</script>",
+            data
+        ));
+    }
+    tags
+}
+
+/// Rewrites `html` chunk by chunk, handing the output to `sink`.
+pub(super) fn inject_meta_into(
+    html: &[u8],
+    meta: &PageMeta,
+    sink: impl FnMut(&[u8]),
+) -> Result<(), RewritingError> {
+    let seen = Rc::new(RefCell::new(Seen::default()));
+    let mut rewriter = HtmlRewriter::new(
+        Settings::new()
+            .append_element_content_handler(element!("html", |el| {
+                if let Some(lang) = meta.lang.as_deref() {
+                    el.set_attribute("lang", &attribute(lang))?;
+                }
+                Ok(())
+            }))
+            .append_element_content_handler(element!("head > title", |el| {
+                seen.borrow_mut().title = true;
+                el.set_inner_content(&meta.title, ContentType::Text);
+                Ok(())
+            }))
+            .append_element_content_handler(element!(r#"head > meta[name="description"]"#, |el| {
+                seen.borrow_mut().description = true;
+                set_content(el, &meta.description)
+            }))
+            .append_element_content_handler(element!(r#"head > meta[property^="og:"]"#, |el| {
+                let property = el.get_attribute("property").unwrap_or_default();
+                let mut seen = seen.borrow_mut();
+                match property.as_str() {
+                    "og:title" => {
+                        seen.og_title = true;
+                        set_content(el, &meta.title)
+                    }
+                    "og:description" => {
+                        seen.og_description = true;
+                        set_content(el, &meta.description)
+                    }
+                    "og:image" => {
+                        seen.og_image = true;
+                        match meta.image.as_deref() {
+                            Some(image) => set_content(el, image),
+                            None => Ok(()),
+                        }
+                    }
+                    _ => Ok(()),
+                }
+            }))
+            .append_element_content_handler(element!(r#"head > meta[name^="twitter:"]"#, |el| {
+                let name = el.get_attribute("name").unwrap_or_default();
+                match (name.as_str(), meta.image.as_deref()) {
+                    ("twitter:title", _) => set_content(el, &meta.title),
+                    ("twitter:description", _) => set_content(el, &meta.description),
+                    ("twitter:image", Some(image)) => set_content(el, image),
+                    _ => Ok(()),
+                }
+            }))
+            .append_element_content_handler(element!(r#"head > link[rel="canonical"]"#, |el| {
+                seen.borrow_mut().canonical = true;
+                if let Some(canonical) = meta.canonical.as_deref() {
+                    el.set_attribute("href", &attribute(canonical))?;
+                }
+                Ok(())
+            }))
+            .append_element_content_handler(element!(
+                r#"head > link[rel="alternate"][hreflang]"#,
+                |el| {
+                    if !meta.alternates.is_empty() {
+                        el.remove();
+                    }
+                    Ok(())
+                }
+            ))
+            .append_element_content_handler(element!("head", |el| {
+                let hints: String = meta.preloads.iter().map(|preload| preload.tag()).collect();
+                el.prepend(&hints, ContentType::Html);
+                let seen = seen.clone();
+                let meta = meta.clone();
+                el.on_end_tag(end_tag!(move |end| {
+                    end.before(&missing_tags(&seen.borrow(), &meta), ContentType::Html);
+                    Ok(())
+                }))
+            }))
+            .append_element_content_handler(element!("body", |el| {
+                if let Some(bootstrap) = meta.bootstrap.clone() {
+                    el.on_end_tag(end_tag!(move |end| {
+                        end.before(
+                            &format!(
+                                "<script id=\"__BOOTSTRAP__\" type=\"application/json\">{}</script>",
+                                bootstrap
+                            ),
+                            ContentType::Html,
+                        );
+                        Ok(())
+                    }))?;
+                }
+                Ok(())
+            })),
+        sink,
+    );
+    rewriter.write(html)?;
+    rewriter.end()
+}
+
+/// [`inject_meta_into`] for a whole document.
+pub(super) fn inject_meta(html: &str, meta: &PageMeta) -> Result<String, RewritingError> {
+    let mut output = Vec::with_capacity(html.len() + 512);
+    inject_meta_into(html.as_bytes(), meta, |chunk| {
+        output.extend_from_slice(chunk)
+    })?;
+    // The rewriter passes the UTF-8 input through and only adds UTF-8
+    Ok(String::from_utf8(output).expect("rewritten HTML is UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(image: Option<&str>) -> PageMeta {
+        PageMeta {
+            title: "Shell Grundlagen".to_string(),
+            description: "Alles zur Shell".to_string(),
+            image: image.map(str::to_string),
+            canonical: None,
+            structured_data: None,
+            lang: None,
+            alternates: Vec::new(),
+            preloads: Vec::new(),
+            bootstrap: None,
+        }
+    }
+
+    /// The raw `content` of every tag matching `selector`.
+    fn contents(html: &str, selector: &str) -> Vec<String> {
+        let found = RefCell::new(Vec::new());
+        lol_html::rewrite_str(
+            html,
+            lol_html::RewriteStrSettings::new().append_element_content_handler(element!(
+                selector,
+                |el| {
+                    found
+                        .borrow_mut()
+                        .push(el.get_attribute("content").unwrap_or_default());
+                    Ok(())
+                }
+            )),
+        )
+        .unwrap();
+        found.into_inner()
+    }
+
+    fn content_of(html: &str, name: &str) -> String {
+        let selector = if name.starts_with("og:") {
+            format!(r#"meta[property="{name}"]"#)
+        } else {
+            format!(r#"meta[name="{name}"]"#)
+        };
+        let mut found = contents(html, &selector);
+        assert_eq!(found.len(), 1, "one {name} in {html}");
+        found.remove(0)
+    }
+
+    #[test]
+    fn test_differently_formatted_shells_get_the_same_meta() {
+        let shells = [
+            // As built today
+            r#"<!doctype html><html lang="de"><head>
+    <title>Linux Tutorial - Lerne Linux Schritt für Schritt</title>
+    <meta name="description" content="Lerne Linux von Grund auf." />
+    <meta property="og:title" content="Linux Tutorial" />
+    <meta property="og:description" content="Lerne Linux." />
+    <meta property="og:image" content="/linux-icon.svg" />
+    <meta name="twitter:title" content="Linux Tutorial" />
+    <meta name="twitter:description" content="Lerne Linux." />
+  </head><body><div id="root"></div></body></html>"#,
+            // Minified, other defaults, attributes reordered
+            r#"<html><head><TITLE>Neu</TITLE><meta content='Alt' name=description><meta content="x" property="og:title"><meta content="y" property="og:description"><meta content="/a.png" property="og:image"><meta content="z" name="twitter:title"><meta content="w" name="twitter:description"></head><body></body></html>"#,
+        ];
+
+        for shell in shells {
+            let html = inject_meta(shell, &meta(Some("/uploads/share.png"))).unwrap();
+            assert_eq!(
+                html.to_lowercase()
+                    .matches("<title>shell grundlagen</title>")
+                    .count(),
+                1,
+                "{html}"
+            );
+            assert_eq!(content_of(&html, "description"), "Alles zur Shell");
+            assert_eq!(content_of(&html, "og:title"), "Shell Grundlagen");
+            assert_eq!(content_of(&html, "og:description"), "Alles zur Shell");
+            assert_eq!(content_of(&html, "og:image"), "/uploads/share.png");
+            assert_eq!(content_of(&html, "twitter:title"), "Shell Grundlagen");
+            assert_eq!(content_of(&html, "twitter:description"), "Alles zur Shell");
+        }
+    }
+
+    #[test]
+    fn test_missing_tags_are_added_to_head() {
+        let html = inject_meta(
+            "<html><head><meta charset=\"UTF-8\"></head><body><svg><title>Icon</title></svg></body></html>",
+            &meta(None),
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<html><head><meta charset=\"UTF-8\"><title>Shell Grundlagen</title>\
+             <meta name=\"description\" content=\"Alles zur Shell\" />\
+             <meta property=\"og:title\" content=\"Shell Grundlagen\" />\
+             <meta property=\"og:description\" content=\"Alles zur Shell\" />\
+             </head><body><svg><title>Icon</title></svg></body></html>"
+        );
+    }
+
+    #[test]
+    fn test_default_share_image_is_kept_without_a_configured_one() {
+        let html = inject_meta(
+            r#"<head><meta property="og:image" content="/linux-icon.svg"></head>"#,
+            &meta(None),
+        )
+        .unwrap();
+        assert_eq!(content_of(&html, "og:image"), "/linux-icon.svg");
+    }
+
+    #[test]
+    fn test_canonical_link_and_structured_data() {
+        let meta = PageMeta {
+            canonical: Some("https://linux.example/tutorials/bash".to_string()),
+            structured_data: Some(r#"{"@type":"TechArticle"}"#.to_string()),
+            ..meta(None)
+        };
+
+        let html = inject_meta("<head><title>Alt</title></head>", &meta).unwrap();
+        assert!(
+            html.ends_with(
+                "<link rel=\"canonical\" href=\"https://linux.example/tutorials/bash\" />\
+                 <script type=\"application/ld+json\">{\"@type\":\"TechArticle\"}</script></head>"
+            ),
+            "{html}"
+        );
+
+        let html = inject_meta(
+            r#"<head><link rel="canonical" href="https://old.example/"></head>"#,
+            &meta,
+        )
+        .unwrap();
+        assert_eq!(html.matches("rel=\"canonical\"").count(), 1, "{html}");
+        assert!(html.contains(r#"href="https://linux.example/tutorials/bash""#));
+    }
+
+    #[test]
+    fn test_locale_sets_lang_alternates_and_script() {
+        let href = "https://linux.example/tutorials/bash";
+        let meta = PageMeta {
+            lang: Some("en".to_string()),
+            alternates: crate::handlers::frontend_proxy::locale::Locales::default()
+                .alternates(href),
+            ..meta(None)
+        };
+        let shell = r#"<!doctype html><html lang="de"><head><title>Alt</title><link rel="alternate" hreflang="de" href="/old"></head><body></body></html>"#;
+
+        let html = inject_meta(shell, &meta).unwrap();
+        assert!(html.contains(r#"<html lang="en">"#), "{html}");
+        assert!(!html.contains("/old"), "{html}");
+        assert!(
+            html.ends_with(
+                "<link rel=\"alternate\" hreflang=\"de\" href=\"https://linux.example/tutorials/bash\" />\
+                 <link rel=\"alternate\" hreflang=\"en\" href=\"https://linux.example/tutorials/bash\" />\
+                 <link rel=\"alternate\" hreflang=\"x-default\" href=\"https://linux.example/tutorials/bash\" />\
+                 <script>window.__LOCALE__=\"en\";</script></head><body></body></html>"
+            ),
+            "{html}"
+        );
+
+        // Without alternates of its own, the shell's are kept
+        let html = inject_meta(
+            shell,
+            &PageMeta {
+                alternates: Vec::new(),
+                preloads: Vec::new(),
+                bootstrap: None,
+                ..meta
+            },
+        )
+        .unwrap();
+        assert!(html.contains("/old"));
+    }
+
+    #[test]
+    fn test_preload_hints_and_bootstrap_bundle() {
+        let shell = r#"<html><head><title>Alt</title><script type="module" src="/assets/app-C4fQ91xz.js"></script></head><body><div id="root"></div></body></html>"#;
+        let meta = PageMeta {
+            preloads: crate::handlers::frontend_proxy::inline::discover_preloads(shell),
+            bootstrap: Some(r#"{"content":{}}"#.to_string()),
+            ..meta(None)
+        };
+
+        let html = inject_meta(shell, &meta).unwrap();
+        assert!(
+            html.starts_with(
+                r#"<html><head><link rel="modulepreload" href="/assets/app-C4fQ91xz.js" /><title>"#
+            ),
+            "{html}"
+        );
+        assert!(
+            html.ends_with(
+                r#"<div id="root"></div><script id="__BOOTSTRAP__" type="application/json">{"content":{}}</script></body></html>"#
+            ),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_injected_values_are_escaped() {
+        let hostile = PageMeta {
+            title: r#""><script>alert(1)</script>"#.to_string(),
+            description: "Tom & Jerry's \"Shell\"".to_string(),
+            image: Some(r#"/x.png"><script>"#.to_string()),
+            canonical: None,
+            structured_data: None,
+            lang: None,
+            alternates: Vec::new(),
+            preloads: Vec::new(),
+            bootstrap: None,
+        };
+        let shell = r#"<head><title>Alt</title><meta name="description" content="Alt"><meta property="og:title" content="Alt"><meta property="og:image" content="/a.png"></head>"#;
+
+        let html = inject_meta(shell, &hostile).unwrap();
+        assert!(!html.contains("<script>"), "{html}");
+        assert!(
+            html.contains("<title>\"&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>"),
+            "{html}"
+        );
+        assert_eq!(
+            content_of(&html, "og:title"),
+            "&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(
+            content_of(&html, "description"),
+            "Tom &amp; Jerry's &quot;Shell&quot;"
+        );
+        assert_eq!(
+            content_of(&html, "og:image"),
+            "/x.png&quot;&gt;&lt;script&gt;"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Critical data inlined into the page shell.
+//!
+//! The SPA only asks for its data once its bundle has loaded, a full round
+//! trip before anything renders. So every page names the shell's module
+//! scripts and stylesheets as preload hints at the top of `<head>`, and
+//! carries the bootstrap bundle of `/api/public/bootstrap` as
+//! `<script id="__BOOTSTRAP__" type="application/json">` at the end of
+//! `<body>`. Pages are cached at the edge, so the bundle is always the one an
+//! anonymous visitor gets. A bundle larger than `INLINE_BOOTSTRAP_MAX_BYTES`
+//! (default 131072) is left out and fetched as before; `0` never inlines it.
+
+use super::structured::script_json;
+use crate::db::DbPool;
+use crate::handlers::bootstrap;
+use lol_html::{element, HtmlRewriter, Settings};
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineBootstrapConfig {
+    pub max_bytes: usize,
+}
+
+impl Default for InlineBootstrapConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 128 * 1024,
+        }
+    }
+}
+
+static INLINE_BOOTSTRAP: OnceLock<InlineBootstrapConfig> = OnceLock::new();
+
+impl InlineBootstrapConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the settings through `lookup`; unset values keep their default.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let Some(raw) = lookup("INLINE_BOOTSTRAP_MAX_BYTES").filter(|raw| !raw.trim().is_empty())
+        else {
+            return Ok(Self::default());
+        };
+        let max_bytes = raw.trim().parse::<usize>().map_err(|_| {
+            format!(
+                "INLINE_BOOTSTRAP_MAX_BYTES must be a number of bytes, got '{}'",
+                raw
+            )
+        })?;
+        Ok(Self { max_bytes })
+    }
+}
+
+pub fn init_inline_bootstrap() -> Result<(), String> {
+    INLINE_BOOTSTRAP
+        .set(InlineBootstrapConfig::from_env()?)
+        .map_err(|_| "Inline bootstrap already initialized".to_string())
+}
+
+pub(super) fn inline_bootstrap_config() -> &'static InlineBootstrapConfig {
+    INLINE_BOOTSTRAP.get_or_init(InlineBootstrapConfig::default)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreloadKind {
+    Module,
+    Style,
+}
+
+/// A bundle the shell loads, to be fetched before the parser gets to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Preload {
+    kind: PreloadKind,
+    href: String,
+    /// The bundle's own `crossorigin`, which the hint must repeat to be used.
+    crossorigin: Option<String>,
+}
+
+impl Preload {
+    pub(super) fn tag(&self) -> String {
+        let attribute =
+            |value: &str| html_escape::encode_double_quoted_attribute(value).into_owned();
+        let crossorigin = match self.crossorigin.as_deref() {
+            Some("") => " crossorigin".to_string(),
+            Some(value) => format!(" crossorigin=\"{}\"", attribute(value)),
+            None => String::new(),
+        };
+        match self.kind {
+            PreloadKind::Module => format!(
+                "<link rel=\"modulepreload\" href=\"{}\"{} />",
+                attribute(&self.href),
+                crossorigin
+            ),
+            PreloadKind::Style => format!(
+                "<link rel=\"preload\" as=\"style\" href=\"{}\"{} />",
+                attribute(&self.href),
+                crossorigin
+            ),
+        }
+    }
+}
+
+/// The shell's own module scripts and stylesheets that it does not hint
+/// already.
+pub(super) fn discover_preloads(html: &str) -> Vec<Preload> {
+    let found = RefCell::new(Vec::new());
+    let hinted = RefCell::new(Vec::new());
+    let bundle = |kind: PreloadKind, href: Option<String>, crossorigin: Option<String>| {
+        let href = href.unwrap_or_default();
+        // Only the app's own files, not e.g. a font stylesheet
+        if href.starts_with('/') && !href.starts_with("//") {
+            found.borrow_mut().push(Preload {
+                kind,
+                href,
+                crossorigin,
+            });
+        }
+    };
+    let hint = |href: Option<String>| hinted.borrow_mut().push(href.unwrap_or_default());
+    let mut rewriter = HtmlRewriter::new(
+        Settings::new()
+            .append_element_content_handler(element!(r#"script[type="module"][src]"#, |el| {
+                bundle(
+                    PreloadKind::Module,
+                    el.get_attribute("src"),
+                    el.get_attribute("crossorigin"),
+                );
+                Ok(())
+            }))
+            .append_element_content_handler(element!(r#"link[rel="stylesheet"][href]"#, |el| {
+                bundle(
+                    PreloadKind::Style,
+                    el.get_attribute("href"),
+                    el.get_attribute("crossorigin"),
+                );
+                Ok(())
+            }))
+            .append_element_content_handler(element!(r#"link[rel="modulepreload"][href]"#, |el| {
+                hint(el.get_attribute("href"));
+                Ok(())
+            }))
+            .append_element_content_handler(element!(r#"link[rel="preload"][href]"#, |el| {
+                hint(el.get_attribute("href"));
+                Ok(())
+            })),
+        |_: &[u8]| {},
+    );
+    if rewriter.write(html.as_bytes()).is_err() || rewriter.end().is_err() {
+        return Vec::new();
+    }
+
+    let hinted = hinted.into_inner();
+    let mut preloads: Vec<Preload> = Vec::new();
+    for preload in found.into_inner() {
+        if !hinted.contains(&preload.href) && !preloads.contains(&preload) {
+            preloads.push(preload);
+        }
+    }
+    preloads
+}
+
+/// The anonymous bootstrap bundle to inline, escaped for a `<script>`
+/// element, with a fingerprint of its content for the page's ETag. `None`
+/// when it is off, cannot be loaded or exceeds `max_bytes`.
+pub(super) async fn bootstrap_json(pool: &DbPool, max_bytes: usize) -> Option<(String, String)> {
+    if max_bytes == 0 {
+        return None;
+    }
+    let mut bundle = match bootstrap::load_bundle(pool, false).await {
+        Ok(bundle) => bundle,
+        Err((_, err)) => {
+            tracing::warn!("Not inlining the bootstrap bundle: {}", err.error);
+            return None;
+        }
+    };
+    // Changes on every call, so it stays out of the fingerprint
+    let fingerprint = serde_json::to_string(&bundle).ok()?;
+    bundle.generated_at = chrono::Utc::now().to_rfc3339();
+    let embedded = script_json(&serde_json::to_value(&bundle).ok()?);
+    if embedded.len() > max_bytes {
+        tracing::debug!(
+            bytes = embedded.len(),
+            max_bytes,
+            "Bootstrap bundle too large to inline"
+        );
+        return None;
+    }
+    Some((fingerprint, embedded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::models::{
+        CreateSitePageRequest, PAGE_VISIBILITY_AUTHENTICATED, PAGE_VISIBILITY_PUBLIC,
+    };
+    use crate::repositories;
+    use serde_json::{json, Value};
+
+    const VITE_SHELL: &str = r#"<!doctype html><html lang="de"><head>
+    <link rel="stylesheet" href="https://fonts.googleapis.com/css2?family=Inter" />
+    <script type="module" crossorigin src="/assets/index-B2x7Kq_a.js"></script>
+    <link rel="modulepreload" crossorigin href="/assets/vendor-D1s9Xa0f.js">
+    <script type="module" crossorigin src="/assets/vendor-D1s9Xa0f.js"></script>
+    <link rel="stylesheet" crossorigin href="/assets/index-C8kLm2Qz.css">
+    <script src="/legacy.js"></script>
+  </head><body><div id="root"></div></body></html>"#;
+
+    #[test]
+    fn test_config_from_env() {
+        assert_eq!(
+            InlineBootstrapConfig::parse(|_| None).unwrap(),
+            InlineBootstrapConfig::default()
+        );
+        assert_eq!(
+            InlineBootstrapConfig::parse(|_| Some(" 0 ".to_string())).unwrap(),
+            InlineBootstrapConfig { max_bytes: 0 }
+        );
+        assert!(InlineBootstrapConfig::parse(|_| Some("-1".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_preloads_name_the_main_bundles() {
+        let tags: Vec<String> = discover_preloads(VITE_SHELL)
+            .iter()
+            .map(Preload::tag)
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                r#"<link rel="modulepreload" href="/assets/index-B2x7Kq_a.js" crossorigin />"#,
+                r#"<link rel="preload" as="style" href="/assets/index-C8kLm2Qz.css" crossorigin />"#,
+            ]
+        );
+        assert!(discover_preloads("<html><head></head></html>").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_json_is_the_anonymous_bundle() {
+        let pool = db::pool::create_test_pool().await;
+        for (slug, is_published, visibility) in [
+            ("oeffentlich", true, PAGE_VISIBILITY_PUBLIC),
+            ("entwurf", false, PAGE_VISIBILITY_PUBLIC),
+            ("intern", true, PAGE_VISIBILITY_AUTHENTICATED),
+        ] {
+            repositories::pages::create_site_page(
+                &pool,
+                CreateSitePageRequest {
+                    slug: slug.to_string(),
+                    title: slug.to_string(),
+                    description: None,
+                    nav_label: None,
+                    show_in_nav: true,
+                    order_index: None,
+                    is_published,
+                    visibility: visibility.to_string(),
+                    hero: json!({}),
+                    layout: json!({}),
+                    meta_title: None,
+                    meta_description: None,
+                    og_image: None,
+                },
+            )
+            .await
+            .expect("create page");
+        }
+        repositories::content::upsert_site_content(
+            &pool,
+            "site_meta",
+            &json!({ "title": "</script><script>alert(1)</script>" }),
+            "admin",
+            10,
+        )
+        .await
+        .unwrap();
+
+        let (fingerprint, embedded) = bootstrap_json(&pool, 1024 * 1024).await.unwrap();
+        assert!(!embedded.contains('<'), "{embedded}");
+        let bundle: Value = serde_json::from_str(&embedded).unwrap();
+        assert_eq!(
+            bundle["content"]["site_meta"]["title"],
+            "</script><script>alert(1)</script>"
+        );
+        let slugs: Vec<&str> = bundle["navigation"]["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["slug"].as_str().unwrap())
+            .collect();
+        assert_eq!(slugs, ["oeffentlich"]);
+
+        // Stable between calls although generated_at is not
+        let (again, _) = bootstrap_json(&pool, 1024 * 1024).await.unwrap();
+        assert_eq!(again, fingerprint);
+
+        assert!(bootstrap_json(&pool, embedded.len() - 1).await.is_none());
+        assert!(bootstrap_json(&pool, 0).await.is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! The visitor's language, negotiated from `Accept-Language`.
+//!
+//! `SUPPORTED_LOCALES` (default `de,en`) lists the languages the site is
+//! written in; the first one is the default. A request gets the supported
+//! locale its `Accept-Language` prefers most, else the default. The page's
+//! `<html lang>` follows it, and so do title and description: `site_meta_en`,
+//! registered as a custom content section (`-` becomes `_`), overrides the
+//! `site_meta` fields it sets. All locales share their paths, so every page
+//! lists the same URL as its `hreflang` alternate for each of them, and the
+//! SPA reads the choice from `window.__LOCALE__`.
+
+use super::structured::script_json;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+const DEFAULT_LOCALES: [&str; 2] = ["de", "en"];
+
+/// An `hreflang` alternate of the requested page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Alternate {
+    pub hreflang: String,
+    pub href: String,
+}
+
+/// The supported locales, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locales {
+    /// Lowercased, the default first; never empty.
+    supported: Vec<String>,
+}
+
+impl Default for Locales {
+    fn default() -> Self {
+        Self {
+            supported: DEFAULT_LOCALES
+                .iter()
+                .map(|locale| locale.to_string())
+                .collect(),
+        }
+    }
+}
+
+static LOCALES: OnceLock<Locales> = OnceLock::new();
+
+impl Locales {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the locales through `lookup`; unset keeps the defaults.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let Some(raw) = lookup("SUPPORTED_LOCALES").filter(|raw| !raw.trim().is_empty()) else {
+            return Ok(Self::default());
+        };
+        let mut supported: Vec<String> = Vec::new();
+        for locale in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|locale| !locale.is_empty())
+        {
+            if !is_language_tag(locale) {
+                return Err(format!(
+                    "SUPPORTED_LOCALES entries must be language tags like 'de' or 'pt-br', got '{}'",
+                    locale
+                ));
+            }
+            let locale = locale.to_ascii_lowercase();
+            if !supported.contains(&locale) {
+                supported.push(locale);
+            }
+        }
+        Ok(Self { supported })
+    }
+
+    pub(super) fn default_locale(&self) -> &str {
+        &self.supported[0]
+    }
+
+    pub(super) fn supported(&self) -> &[String] {
+        &self.supported
+    }
+
+    /// The supported locale `accept_language` prefers most. A range matches
+    /// the locale itself, a locale it is a more specific form of (`en-us`
+    /// finds `en`) or one that is a more specific form of it (`pt` finds
+    /// `pt-br`).
+    pub(super) fn negotiate(&self, accept_language: Option<&str>) -> &str {
+        for range in accept_language
+            .map(parse_accept_language)
+            .unwrap_or_default()
+        {
+            if range == "*" {
+                break;
+            }
+            let mut prefix = range.as_str();
+            loop {
+                if let Some(locale) = self.supported.iter().find(|locale| *locale == prefix) {
+                    return locale;
+                }
+                match prefix.rsplit_once('-') {
+                    Some((shorter, _)) => prefix = shorter,
+                    None => break,
+                }
+            }
+            if let Some(locale) = self.supported.iter().find(|locale| {
+                locale
+                    .strip_prefix(range.as_str())
+                    .is_some_and(|rest| rest.starts_with('-'))
+            }) {
+                return locale;
+            }
+        }
+        self.default_locale()
+    }
+
+    /// The content section holding the `site_meta` of `locale`; `None` for
+    /// the default, whose is `site_meta` itself.
+    pub(super) fn site_meta_section(&self, locale: &str) -> Option<String> {
+        (locale != self.default_locale()).then(|| format!("site_meta_{}", locale.replace('-', "_")))
+    }
+
+    /// `href` as the alternate of every locale, plus `x-default`. A single
+    /// locale has no alternates.
+    pub(super) fn alternates(&self, href: &str) -> Vec<Alternate> {
+        if self.supported.len() < 2 {
+            return Vec::new();
+        }
+        self.supported
+            .iter()
+            .map(String::as_str)
+            .chain(["x-default"])
+            .map(|hreflang| Alternate {
+                hreflang: hreflang.to_string(),
+                href: href.to_string(),
+            })
+            .collect()
+    }
+}
+
+pub fn init_supported_locales() -> Result<(), String> {
+    LOCALES
+        .set(Locales::from_env()?)
+        .map_err(|_| "Supported locales already initialized".to_string())
+}
+
+pub(super) fn supported_locales() -> &'static Locales {
+    LOCALES.get_or_init(Locales::default)
+}
+
+/// Letters for the language, then letters or digits for each subtag.
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=8).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// The language ranges of an `Accept-Language` header, most preferred
+/// first and lowercased. Ranges with `q=0` or a malformed entry are left
+/// out; equal weights keep their order.
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut ranges: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';').map(str::trim);
+            let range = parts
+                .next()
+                .filter(|range| *range == "*" || is_language_tag(range))?;
+            let mut weight = 1.0;
+            for parameter in parts {
+                if let Some(value) = parameter
+                    .strip_prefix("q=")
+                    .or_else(|| parameter.strip_prefix("Q="))
+                {
+                    weight = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|weight| (0.0..=1.0).contains(weight))?;
+                }
+            }
+            (weight > 0.0).then(|| (range.to_ascii_lowercase(), weight))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges.into_iter().map(|(range, _)| range).collect()
+}
+
+/// The inline script telling the SPA the locale.
+pub(super) fn locale_script(locale: &str) -> String {
+    format!(
+        "window.__LOCALE__={};",
+        script_json(&Value::String(locale.to_string()))
+    )
+}
+
+/// `localized` over `site_meta`: the fields it sets win, blank ones fall
+/// back.
+pub(super) fn merge_site_meta(site_meta: &Value, localized: &Value) -> Value {
+    let Some(fields) = localized.as_object() else {
+        return site_meta.clone();
+    };
+    let mut merged = site_meta.as_object().cloned().unwrap_or_default();
+    for (key, value) in fields {
+        let blank = match value {
+            Value::Null => true,
+            Value::String(text) => text.trim().is_empty(),
+            _ => false,
+        };
+        if !blank {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Object(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn locales(raw: &str) -> Locales {
+        Locales::parse(|_| Some(raw.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_locales_from_env() {
+        assert_eq!(Locales::parse(|_| None).unwrap(), Locales::default());
+        let parsed = locales(" EN, de ,pt-BR, en ");
+        assert_eq!(parsed.supported(), ["en", "de", "pt-br"]);
+        assert_eq!(parsed.default_locale(), "en");
+        assert_eq!(
+            Locales::parse(|_| Some("de, english!".to_string())),
+            Err(
+                "SUPPORTED_LOCALES entries must be language tags like 'de' or 'pt-br', got 'english!'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_accept_language_is_ordered_by_weight() {
+        assert_eq!(
+            parse_accept_language("fr;q=0.5, en-US, de;q=0.9, *;q=0.1"),
+            ["en-us", "de", "fr", "*"]
+        );
+        assert_eq!(
+            parse_accept_language("en;q=0, de;q=abc, fr;Q=0.3, <script>, it;q=2"),
+            ["fr"]
+        );
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiation_picks_the_preferred_supported_locale() {
+        let locales = locales("de,en,pt-br");
+        for (header, expected) in [
+            (Some("en-US,en;q=0.9,de;q=0.8"), "en"),
+            (Some("fr-FR, fr;q=0.9, de;q=0.5"), "de"),
+            (Some("de;q=0.4, en;q=0.6"), "en"),
+            (Some("pt"), "pt-br"),
+            (Some("PT-br"), "pt-br"),
+            (Some("en;q=0, fr"), "de"),
+            (Some("*"), "de"),
+            (Some("ja"), "de"),
+            (None, "de"),
+        ] {
+            assert_eq!(locales.negotiate(header), expected, "{header:?}");
+        }
+    }
+
+    #[test]
+    fn test_localized_site_meta_falls_back_field_by_field() {
+        let locales = Locales::default();
+        assert_eq!(locales.site_meta_section("de"), None);
+        assert_eq!(
+            locales.site_meta_section("en").as_deref(),
+            Some("site_meta_en")
+        );
+        assert_eq!(
+            locales.site_meta_section("pt-br").as_deref(),
+            Some("site_meta_pt_br")
+        );
+
+        let merged = merge_site_meta(
+            &json!({ "title": "Linux lernen", "description": "Schritt für Schritt", "image": "/share.png" }),
+            &json!({ "title": "Learn Linux", "description": " " }),
+        );
+        assert_eq!(
+            merged,
+            json!({ "title": "Learn Linux", "description": "Schritt für Schritt", "image": "/share.png" })
+        );
+    }
+
+    #[test]
+    fn test_every_locale_gets_the_same_alternate() {
+        let href = "https://linux.example/tutorials/bash";
+        let alternates = Locales::default().alternates(href);
+        let hreflangs: Vec<&str> = alternates
+            .iter()
+            .map(|alternate| alternate.hreflang.as_str())
+            .collect();
+        assert_eq!(hreflangs, ["de", "en", "x-default"]);
+        assert!(alternates.iter().all(|alternate| alternate.href == href));
+
+        assert!(locales("de").alternates(href).is_empty());
+    }
+
+    #[test]
+    fn test_locale_script() {
+        assert_eq!(locale_script("pt-br"), r#"window.__LOCALE__="pt-br";"#);
+    }
+}
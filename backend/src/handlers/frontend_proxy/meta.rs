@@ -0,0 +1,456 @@
+//! Meta values for the route a page view asks for.
+//!
+//! The SPA's content routes are mapped to what they show: `/pages/{slug}` to
+//! a page, `/pages/{slug}/posts/{post}` to a post and `/tutorials/{id}` to a
+//! tutorial. Their title, description and share image replace the global
+//! `site_meta` values; every other route, and any entity visitors cannot
+//! see, keeps the globals.
+
+use super::inline::Preload;
+use super::locale::Alternate;
+use crate::db;
+use crate::models::{SitePage, PAGE_VISIBILITY_PUBLIC};
+use crate::repositories;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+const DEFAULT_TITLE: &str = "Linux Tutorial - Lerne Linux Schritt für Schritt";
+const DEFAULT_DESCRIPTION: &str = "Lerne Linux von Grund auf - Interaktiv, modern und praxisnah.";
+
+/// The content a request path points at.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum MetaRoute {
+    Page {
+        slug: String,
+    },
+    Post {
+        page_slug: String,
+        post_slug: String,
+    },
+    Tutorial {
+        id: String,
+    },
+    Other,
+}
+
+impl MetaRoute {
+    pub(super) fn of(path: &str) -> Self {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').map(str::trim).collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Self::Other;
+        }
+        match segments.as_slice() {
+            ["pages", slug] => Self::Page {
+                slug: slug.to_lowercase(),
+            },
+            ["pages", page_slug, "posts", post_slug] => Self::Post {
+                page_slug: page_slug.to_lowercase(),
+                post_slug: post_slug.to_lowercase(),
+            },
+            ["tutorials", id] => Self::Tutorial { id: id.to_string() },
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The schema.org type of an article route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ArticleKind {
+    Article,
+    TechArticle,
+}
+
+/// Dates of a post or tutorial, for its structured data.
+#[derive(Debug, PartialEq)]
+pub(super) struct ArticleMeta {
+    pub kind: ArticleKind,
+    pub published: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+}
+
+/// SEO fields of one page, post or tutorial. Blank values fall back to the
+/// globals.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct EntityMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub article: Option<ArticleMeta>,
+    /// When anything shown here last changed, for the page's ETag.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<&SitePage> for EntityMeta {
+    fn from(page: &SitePage) -> Self {
+        Self {
+            title: page.meta_title.clone(),
+            description: page.meta_description.clone(),
+            image: page.og_image.clone(),
+            article: None,
+            updated_at: Some(page.updated_at),
+        }
+    }
+}
+
+/// Meta values injected into `index.html` for a single request.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct PageMeta {
+    pub title: String,
+    pub description: String,
+    pub image: Option<String>,
+    /// Absolute canonical URL, only known with `PUBLIC_BASE_URL`.
+    pub canonical: Option<String>,
+    /// JSON-LD, already escaped for a `<script>` element.
+    pub structured_data: Option<String>,
+    /// The negotiated locale, for `<html lang>` and the SPA.
+    pub lang: Option<String>,
+    pub alternates: Vec<Alternate>,
+    pub preloads: Vec<Preload>,
+    /// The bootstrap bundle, already escaped for a `<script>` element.
+    pub bootstrap: Option<String>,
+}
+
+fn non_blank(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|text| !text.is_empty())
+}
+
+/// Combines the global `site_meta` section with an entity's own SEO fields.
+/// Entity values win whenever they are set; blank ones fall back to the
+/// globals.
+pub(super) fn resolve_meta(site_meta: &Value, entity: Option<&EntityMeta>) -> PageMeta {
+    let global = |key: &str| non_blank(site_meta.get(key).and_then(Value::as_str));
+
+    let title = entity
+        .and_then(|entity| non_blank(entity.title.as_deref()))
+        .or_else(|| global("title"))
+        .unwrap_or(DEFAULT_TITLE);
+    let description = entity
+        .and_then(|entity| non_blank(entity.description.as_deref()))
+        .or_else(|| global("description"))
+        .unwrap_or(DEFAULT_DESCRIPTION);
+    let image = entity
+        .and_then(|entity| non_blank(entity.image.as_deref()))
+        .or_else(|| global("image"));
+
+    PageMeta {
+        title: title.to_string(),
+        description: description.to_string(),
+        image: image.map(str::to_string),
+        canonical: None,
+        structured_data: None,
+        lang: None,
+        alternates: Vec::new(),
+        preloads: Vec::new(),
+        bootstrap: None,
+    }
+}
+
+/// The name of the site: the global title.
+pub(super) fn site_name(site_meta: &Value) -> String {
+    resolve_meta(site_meta, None).title
+}
+
+/// Loads the page with `slug`. Drafts, trashed and members-only pages are
+/// ignored so their metadata never leaks.
+async fn load_public_page(pool: &db::DbPool, slug: &str) -> Result<Option<SitePage>, sqlx::Error> {
+    Ok(repositories::pages::get_site_page_by_slug(pool, slug)
+        .await?
+        .filter(|page| page.is_published && page.visibility == PAGE_VISIBILITY_PUBLIC))
+}
+
+async fn lookup(pool: &db::DbPool, route: &MetaRoute) -> Result<Option<EntityMeta>, sqlx::Error> {
+    match route {
+        MetaRoute::Page { slug } => Ok(load_public_page(pool, slug)
+            .await?
+            .as_ref()
+            .map(EntityMeta::from)),
+        MetaRoute::Post {
+            page_slug,
+            post_slug,
+        } => {
+            let Some(page) = load_public_page(pool, page_slug).await? else {
+                return Ok(None);
+            };
+            // Only published posts are found; the page's share image stands
+            // in for the post's
+            let post =
+                repositories::posts::get_published_post_by_slug(pool, &page.id, post_slug).await?;
+            Ok(post.map(|post| EntityMeta {
+                updated_at: Some(page.updated_at.max(post.updated_at)),
+                article: Some(ArticleMeta {
+                    kind: ArticleKind::Article,
+                    published: post.published_at.unwrap_or(post.created_at),
+                    modified: post.updated_at,
+                }),
+                title: Some(post.title),
+                description: Some(post.excerpt),
+                image: page.og_image,
+            }))
+        }
+        MetaRoute::Tutorial { id } => {
+            let tutorial = repositories::tutorials::get_tutorial(pool, id).await?;
+            Ok(tutorial.map(|tutorial| EntityMeta {
+                title: Some(tutorial.title),
+                description: Some(tutorial.description),
+                image: None,
+                updated_at: Some(tutorial.updated_at),
+                article: Some(ArticleMeta {
+                    kind: ArticleKind::TechArticle,
+                    published: tutorial.created_at,
+                    modified: tutorial.updated_at,
+                }),
+            }))
+        }
+        MetaRoute::Other => Ok(None),
+    }
+}
+
+/// The meta values of what `route` shows, if visitors may see it.
+pub(super) async fn load_entity_meta(pool: &db::DbPool, route: &MetaRoute) -> Option<EntityMeta> {
+    match lookup(pool, route).await {
+        Ok(entity) => entity,
+        Err(e) => {
+            tracing::warn!("Failed to load {:?} for meta injection: {}", route, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateSitePageRequest, CreateSitePostRequest};
+    use serde_json::json;
+
+    fn page(
+        meta_title: Option<&str>,
+        meta_description: Option<&str>,
+        og_image: Option<&str>,
+    ) -> SitePage {
+        SitePage {
+            id: "page-1".to_string(),
+            slug: "grundlagen".to_string(),
+            title: "Grundlagen".to_string(),
+            description: String::new(),
+            nav_label: None,
+            show_in_nav: true,
+            order_index: 0,
+            is_published: true,
+            visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+            hero_json: "{}".to_string(),
+            layout_json: "{}".to_string(),
+            meta_title: meta_title.map(str::to_string),
+            meta_description: meta_description.map(str::to_string),
+            og_image: og_image.map(str::to_string),
+            created_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            updated_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            deleted_at: None,
+        }
+    }
+
+    async fn seed_page(pool: &db::DbPool, slug: &str, is_published: bool) -> String {
+        repositories::pages::create_site_page(
+            pool,
+            CreateSitePageRequest {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                description: None,
+                nav_label: None,
+                show_in_nav: false,
+                order_index: None,
+                is_published,
+                visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+                hero: json!({}),
+                layout: json!({}),
+                meta_title: Some(format!("{slug} meta")),
+                meta_description: None,
+                og_image: Some("/uploads/share.png".to_string()),
+            },
+        )
+        .await
+        .expect("create page")
+        .id
+    }
+
+    async fn seed_post(pool: &db::DbPool, page_id: &str, slug: &str, is_published: bool) {
+        repositories::posts::create_site_post(
+            pool,
+            page_id,
+            CreateSitePostRequest {
+                title: format!("Post {slug}"),
+                slug: slug.to_string(),
+                excerpt: Some(format!("About {slug}")),
+                content_markdown: "Body".to_string(),
+                is_published,
+                allow_comments: true,
+                published_at: None,
+                order_index: None,
+            },
+        )
+        .await
+        .expect("create post");
+    }
+
+    fn route(path: &str) -> MetaRoute {
+        MetaRoute::of(path)
+    }
+
+    #[test]
+    fn test_routes_from_paths() {
+        assert_eq!(
+            route("/pages/Grundlagen"),
+            MetaRoute::Page {
+                slug: "grundlagen".to_string()
+            }
+        );
+        assert_eq!(
+            route("/pages/grundlagen/posts/Erste-Schritte/"),
+            MetaRoute::Post {
+                page_slug: "grundlagen".to_string(),
+                post_slug: "erste-schritte".to_string(),
+            }
+        );
+        assert_eq!(
+            route("/tutorials/bash-basics"),
+            MetaRoute::Tutorial {
+                id: "bash-basics".to_string()
+            }
+        );
+        for path in [
+            "/",
+            "/pages/",
+            "/pages//posts/x",
+            "/pages/a/b",
+            "/tutorials",
+            "/admin",
+        ] {
+            assert_eq!(route(path), MetaRoute::Other, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_entity_meta_overrides_globals() {
+        let globals = json!({ "title": "Global", "description": "Global description" });
+        let page = page(
+            Some("Shell Grundlagen"),
+            Some("Alles zur Shell"),
+            Some("/uploads/share.png"),
+        );
+
+        assert_eq!(
+            resolve_meta(&globals, Some(&EntityMeta::from(&page))),
+            PageMeta {
+                title: "Shell Grundlagen".to_string(),
+                description: "Alles zur Shell".to_string(),
+                image: Some("/uploads/share.png".to_string()),
+                canonical: None,
+                structured_data: None,
+                lang: None,
+                alternates: Vec::new(),
+                preloads: Vec::new(),
+                bootstrap: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_blank_entity_meta_falls_back_to_globals() {
+        let globals = json!({ "title": "Global", "description": "Global description" });
+        let page = page(Some("  "), None, Some(""));
+
+        assert_eq!(
+            resolve_meta(&globals, Some(&EntityMeta::from(&page))),
+            PageMeta {
+                title: "Global".to_string(),
+                description: "Global description".to_string(),
+                image: None,
+                canonical: None,
+                structured_data: None,
+                lang: None,
+                alternates: Vec::new(),
+                preloads: Vec::new(),
+                bootstrap: None,
+            }
+        );
+        assert_eq!(site_name(&json!({})), DEFAULT_TITLE);
+    }
+
+    #[tokio::test]
+    async fn test_pages_and_posts_visitors_can_see() {
+        let pool = db::pool::create_test_pool().await;
+        let page_id = seed_page(&pool, "grundlagen", true).await;
+        seed_post(&pool, &page_id, "mein-post", true).await;
+        seed_post(&pool, &page_id, "entwurf", false).await;
+        let draft_id = seed_page(&pool, "geheim", false).await;
+        seed_post(&pool, &draft_id, "versteckt", true).await;
+
+        let page = load_entity_meta(&pool, &route("/pages/grundlagen"))
+            .await
+            .unwrap();
+        assert_eq!(page.title.as_deref(), Some("grundlagen meta"));
+
+        let mut post = load_entity_meta(&pool, &route("/pages/grundlagen/posts/mein-post"))
+            .await
+            .unwrap();
+        let article = post.article.take().unwrap();
+        assert_eq!(article.kind, ArticleKind::Article);
+        assert!(article.published <= article.modified);
+        assert!(post.updated_at.take().is_some());
+        assert_eq!(
+            post,
+            EntityMeta {
+                title: Some("Post mein-post".to_string()),
+                description: Some("About mein-post".to_string()),
+                image: Some("/uploads/share.png".to_string()),
+                article: None,
+                updated_at: None,
+            }
+        );
+
+        // Drafts, and published posts of a draft page, keep their titles
+        for path in [
+            "/pages/grundlagen/posts/entwurf",
+            "/pages/geheim",
+            "/pages/geheim/posts/versteckt",
+            "/pages/grundlagen/posts/unbekannt",
+        ] {
+            assert_eq!(load_entity_meta(&pool, &route(path)).await, None, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tutorials_by_id() {
+        let pool = db::pool::create_test_pool().await;
+        repositories::tutorials::create_tutorial(
+            &pool,
+            "bash-basics",
+            "Bash Grundlagen",
+            "Eine Einführung in die Shell",
+            "Inhalt",
+            "Terminal",
+            "from-green-500 to-emerald-600",
+            "[\"shell\"]",
+            &["shell".to_string()],
+        )
+        .await
+        .expect("create tutorial");
+
+        let tutorial = load_entity_meta(&pool, &route("/tutorials/bash-basics"))
+            .await
+            .unwrap();
+        assert_eq!(tutorial.title.as_deref(), Some("Bash Grundlagen"));
+        assert_eq!(
+            tutorial.description.as_deref(),
+            Some("Eine Einführung in die Shell")
+        );
+        assert_eq!(
+            tutorial.article.map(|article| article.kind),
+            Some(ArticleKind::TechArticle)
+        );
+        assert_eq!(
+            load_entity_meta(&pool, &route("/tutorials/unknown")).await,
+            None
+        );
+        assert_eq!(load_entity_meta(&pool, &route("/admin")).await, None);
+    }
+}
@@ -0,0 +1,444 @@
+//! Serves the SPA's `index.html` with the page's meta tags injected.
+//!
+//! The shell comes from the frontend container through the [`IndexCache`],
+//! which fetches it with the shared [`FrontendClient`]. With
+//! `FRONTEND_STATIC_DIR` set, the shell and the rest of the build are read
+//! from disk instead; see [`assets`]. [`status`] reports on both.
+//!
+//! Every page is rendered in the visitor's language; see [`locale`]. It
+//! carries what the SPA needs for its first paint; see [`inline`].
+
+mod assets;
+mod cache;
+mod inject;
+mod inline;
+mod locale;
+mod meta;
+mod paths;
+mod status;
+mod structured;
+mod upstream;
+
+pub(crate) use assets::parse_static_dir;
+pub(crate) use paths::is_asset;
+pub use cache::{IndexCache, IndexCacheConfig};
+pub use inline::{init_inline_bootstrap, InlineBootstrapConfig};
+pub use locale::{init_supported_locales, Locales};
+pub use paths::{init_app_routes, AppRoutes};
+pub use status::frontend_status;
+pub use upstream::{FrontendClient, FrontendClientConfig};
+
+use crate::db;
+use crate::middleware::security::SecurityHeaderOverrides;
+use crate::models::ErrorResponse;
+use crate::security::auth;
+use crate::utils::{conditional, public_url, timestamps::format_timestamp};
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::{
+        header::{ACCEPT_LANGUAGE, VARY},
+        HeaderValue, StatusCode,
+    },
+    response::{Html, IntoResponse, Response},
+    Extension, Json,
+};
+use meta::MetaRoute;
+
+pub async fn serve_index(
+    State(pool): State<db::DbPool>,
+    Extension(cache): Extension<IndexCache>,
+    OriginalUri(uri): OriginalUri,
+    request: Request,
+) -> impl IntoResponse {
+    // A file of the build when served from disk; a missing script or image
+    // otherwise, not a page
+    if paths::is_asset(uri.path()) {
+        return match cache.static_dir() {
+            Some(dir) => assets::serve_asset(dir, request).await,
+            None => StatusCode::NOT_FOUND.into_response(),
+        };
+    }
+
+    let index = match cache.get(&pool).await {
+        Ok(index) => index,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Html("<h1>Bad Gateway</h1><p>Failed to load application.</p>"),
+            )
+                .into_response();
+        }
+    };
+
+    let route = MetaRoute::of(uri.path());
+    let entity = meta::load_entity_meta(&pool, &route).await;
+    let found = paths::shows_page(uri.path(), &route, entity.is_some(), &index.nav_paths);
+    let locales = locale::supported_locales();
+    let lang = locales.negotiate(
+        request
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let (site_meta, localized_updated_at) = index.site_meta_for(lang);
+    let mut meta = meta::resolve_meta(site_meta, entity.as_ref());
+    meta.lang = Some(lang.to_string());
+    meta.preloads = index.preloads.clone();
+    let bootstrap =
+        inline::bootstrap_json(&pool, inline::inline_bootstrap_config().max_bytes).await;
+    let bootstrap_fingerprint = match bootstrap {
+        Some((fingerprint, embedded)) => {
+            meta.bootstrap = Some(embedded);
+            fingerprint
+        }
+        None => String::new(),
+    };
+    if found {
+        let base_url = public_url::public_base_url();
+        structured::annotate(
+            &mut meta,
+            &route,
+            entity.as_ref(),
+            uri.path(),
+            &meta::site_name(site_meta),
+            base_url,
+        );
+        meta.alternates = locales.alternates(&format!(
+            "{}{}",
+            base_url.unwrap_or_default(),
+            structured::canonical_path(&route, uri.path())
+        ));
+    }
+
+    let injected_html = match inject::inject_meta(&index.html, &meta) {
+        Ok(html) => html,
+        Err(err) => {
+            tracing::error!("Failed to inject meta tags into index.html: {}", err);
+            index.html.clone()
+        }
+    };
+
+    // The SPA renders its not-found view; crawlers see the status
+    if !found {
+        return localized(
+            (StatusCode::NOT_FOUND, Html(injected_html)).into_response(),
+            lang,
+        );
+    }
+
+    // Changes with the shell, what the page shows, the inlined bundle and
+    // the HTML purge
+    let html_generation = cache.html_generation().to_string();
+    let entity_updated_at = entity
+        .as_ref()
+        .and_then(|entity| entity.updated_at.as_ref())
+        .map(format_timestamp)
+        .unwrap_or_default();
+    let etag = conditional::weak_etag([
+        index.html.as_str(),
+        entity_updated_at.as_str(),
+        index.site_meta_updated_at.as_str(),
+        lang,
+        localized_updated_at,
+        bootstrap_fingerprint.as_str(),
+        html_generation.as_str(),
+    ]);
+    // Anonymous views may come from a CDN; signed-in ones are never stored
+    let cache_control = if auth::extract_token(request.headers()).is_some() {
+        conditional::PRIVATE_NO_STORE
+    } else {
+        conditional::EDGE_CACHE
+    };
+    localized(
+        conditional::respond_html(request.headers(), &etag, cache_control, injected_html),
+        lang,
+    )
+}
+
+/// Marks `response` as depending on the visitor's language and lets the
+/// CSP run its locale script.
+fn localized(mut response: Response, lang: &str) -> Response {
+    if locale::supported_locales().supported().len() > 1 {
+        response
+            .headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-language"));
+    }
+    response.extensions_mut().insert(
+        SecurityHeaderOverrides::new().allow_inline_script(&locale::locale_script(lang)),
+    );
+    response
+}
+
+/// Drops the cached `index.html`, e.g. right after a frontend deploy.
+pub async fn purge_cache(
+    claims: auth::Claims,
+    Extension(cache): Extension<IndexCache>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    if claims.role != "admin" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Insufficient permissions".to_string(),
+            }),
+        ));
+    }
+
+    cache.purge();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Changes the ETag of every page, so CDNs and browsers revalidate them.
+pub async fn purge_html_cache(
+    claims: auth::Claims,
+    Extension(cache): Extension<IndexCache>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    if claims.role != "admin" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Insufficient permissions".to_string(),
+            }),
+        ));
+    }
+
+    cache.purge_html();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::{CACHE_CONTROL, COOKIE, ETAG, IF_NONE_MATCH};
+    use axum::http::{HeaderName, Uri};
+    use axum::response::Response;
+    use std::path::PathBuf;
+
+    /// A frontend build with one script, served from disk.
+    fn dist() -> (PathBuf, IndexCache) {
+        let dir = std::env::temp_dir().join(format!("frontend-dist-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(
+            dir.join("index.html"),
+            "<html><head><title>Vite</title></head><body></body></html>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("assets/app-C4fQ91xz.js"), "app()").unwrap();
+        let cache = IndexCache::from_dir(IndexCacheConfig::default(), dir.clone());
+        (dir, cache)
+    }
+
+    async fn request(
+        pool: &db::DbPool,
+        cache: &IndexCache,
+        path: &str,
+        headers: &[(HeaderName, &str)],
+    ) -> Response {
+        let uri: Uri = path.parse().unwrap();
+        let mut request = Request::builder().uri(uri.clone());
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        serve_index(
+            State(pool.clone()),
+            Extension(cache.clone()),
+            OriginalUri(uri),
+            request.body(Body::empty()).unwrap(),
+        )
+        .await
+        .into_response()
+    }
+
+    async fn get(pool: &db::DbPool, cache: &IndexCache, path: &str) -> (StatusCode, String) {
+        let response = request(pool, cache, path, &[]).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_build_served_from_disk() {
+        let pool = db::pool::create_test_pool().await;
+        crate::repositories::content::upsert_site_content(
+            &pool,
+            "site_meta",
+            &serde_json::json!({ "title": "Linux lernen" }),
+            "admin",
+            10,
+        )
+        .await
+        .unwrap();
+        let (dir, cache) = dist();
+
+        // The same injection as for the proxied shell
+        let (status, html) = get(&pool, &cache, "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(html.contains("<title>Linux lernen</title>"), "{html}");
+        assert!(html.contains(r#"<meta property="og:title" content="Linux lernen" />"#));
+
+        assert_eq!(
+            get(&pool, &cache, "/assets/app-C4fQ91xz.js").await,
+            (StatusCode::OK, "app()".to_string())
+        );
+        assert_eq!(
+            get(&pool, &cache, "/assets/gone-A1b2C3d4.js").await,
+            (StatusCode::NOT_FOUND, String::new())
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pages_follow_accept_language() {
+        let pool = db::pool::create_test_pool().await;
+        for (section, title) in [("site_meta", "Linux lernen"), ("site_meta_en", "Learn Linux")] {
+            crate::repositories::content::upsert_site_content(
+                &pool,
+                section,
+                &serde_json::json!({ "title": title, "description": "Schritt für Schritt" }),
+                "admin",
+                10,
+            )
+            .await
+            .unwrap();
+        }
+        let (dir, cache) = dist();
+
+        let english = request(&pool, &cache, "/blog", &[(ACCEPT_LANGUAGE, "en-GB,en")]).await;
+        assert!(english
+            .headers()
+            .get_all(VARY)
+            .iter()
+            .any(|value| value == "accept-language"));
+        let english_etag = english.headers()[ETAG].clone();
+        let body = axum::body::to_bytes(english.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"<html lang="en">"#), "{html}");
+        assert!(html.contains("<title>Learn Linux</title>"), "{html}");
+        assert!(html.contains(r#"<meta name="description" content="Schritt für Schritt" />"#));
+        assert!(html.contains(r#"<link rel="alternate" hreflang="de" href="/blog" />"#));
+        assert!(html.contains(r#"<script>window.__LOCALE__="en";</script>"#));
+
+        let german = request(&pool, &cache, "/blog", &[(ACCEPT_LANGUAGE, "fr, de")]).await;
+        assert_ne!(german.headers()[ETAG], english_etag);
+        let (_, html) = get(&pool, &cache, "/blog").await;
+        assert!(html.contains(r#"<html lang="de">"#), "{html}");
+        assert!(html.contains("<title>Linux lernen</title>"), "{html}");
+
+        // Missing pages name no alternates
+        let (status, html) = get(&pool, &cache, "/nirgendwo").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!html.contains("hreflang"), "{html}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pages_carry_the_bootstrap_bundle() {
+        let pool = db::pool::create_test_pool().await;
+        let (dir, cache) = dist();
+        let (_, html) = get(&pool, &cache, "/blog").await;
+        let etag = request(&pool, &cache, "/blog", &[]).await.headers()[ETAG].clone();
+
+        let start = r#"<script id="__BOOTSTRAP__" type="application/json">"#;
+        let json = html
+            .split_once(start)
+            .and_then(|(_, rest)| rest.split_once("</script>"))
+            .map(|(json, _)| json)
+            .expect("inlined bundle");
+        let bundle: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert!(bundle["content"].is_object());
+        assert!(bundle["navigation"]["items"].is_array());
+
+        // A content change reaches cached pages
+        crate::repositories::content::upsert_site_content(
+            &pool,
+            "footer",
+            &serde_json::json!({ "text": "</script><script>alert(1)</script>" }),
+            "admin",
+            10,
+        )
+        .await
+        .unwrap();
+        let if_none_match = [(IF_NONE_MATCH, etag.to_str().unwrap())];
+        let response = request(&pool, &cache, "/blog", &if_none_match).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let (_, html) = get(&pool, &cache, "/blog").await;
+        assert_eq!(html.matches("</script>").count(), 2, "{html}");
+        assert!(!html.contains("alert(1)</script>"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_pages_are_edge_cached_and_revalidated() {
+        let pool = db::pool::create_test_pool().await;
+        let (dir, cache) = dist();
+
+        let response = request(&pool, &cache, "/blog", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], conditional::EDGE_CACHE);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+
+        let response = request(&pool, &cache, "/blog", &[(IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+
+        // After the purge every edge gets the page anew
+        cache.purge_html();
+        let response = request(&pool, &cache, "/blog", &[(IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[ETAG], etag.as_str());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_signed_in_views_are_never_stored() {
+        let pool = db::pool::create_test_pool().await;
+        let (dir, cache) = dist();
+
+        let cookie = format!("theme=dark; {}=token", auth::AUTH_COOKIE_NAME);
+        let response = request(&pool, &cache, "/", &[(COOKIE, &cookie)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CACHE_CONTROL],
+            conditional::PRIVATE_NO_STORE
+        );
+
+        let response = request(&pool, &cache, "/", &[(COOKIE, "theme=dark")]).await;
+        assert_eq!(response.headers()[CACHE_CONTROL], conditional::EDGE_CACHE);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purge_requires_admin() {
+        let claims = |role: &str| auth::test_claims("someone", role);
+        let cache = IndexCache::default();
+
+        let (status, _) = purge_cache(claims("user"), Extension(cache.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            purge_cache(claims("admin"), Extension(cache.clone()))
+                .await
+                .unwrap(),
+            StatusCode::NO_CONTENT
+        );
+
+        let (status, _) = purge_html_cache(claims("user"), Extension(cache.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(cache.html_generation(), 0);
+        assert_eq!(
+            purge_html_cache(claims("admin"), Extension(cache.clone()))
+                .await
+                .unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(cache.html_generation(), 1);
+    }
+}
@@ -0,0 +1,228 @@
+//! Which paths get the SPA with `200` and which a `404`.
+//!
+//! The app's own routes, `SPA_APP_ROUTES` (default `/`, `/blog`, `/login`
+//! and `/admin`), and every path the header navigation links to are pages.
+//! A page, post or tutorial route is one while visitors can see what it
+//! shows. Every other path still gets the SPA, so its not-found view renders,
+//! but with a `404`; a path ending in a file extension is a missing asset and
+//! gets a bare `404` without HTML.
+//!
+//! An entry of `SPA_APP_ROUTES` ending in `/*` also matches everything below
+//! it, e.g. `/admin/*`.
+
+use super::meta::MetaRoute;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+const DEFAULT_APP_ROUTES: [&str; 4] = ["/", "/blog", "/login", "/admin"];
+
+/// The SPA's own routes, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppRoutes {
+    patterns: Vec<String>,
+}
+
+impl Default for AppRoutes {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_APP_ROUTES
+                .iter()
+                .map(|route| route.to_string())
+                .collect(),
+        }
+    }
+}
+
+static APP_ROUTES: OnceLock<AppRoutes> = OnceLock::new();
+
+impl AppRoutes {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the routes through `lookup`; unset keeps the defaults.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let Some(raw) = lookup("SPA_APP_ROUTES").filter(|raw| !raw.trim().is_empty()) else {
+            return Ok(Self::default());
+        };
+        let mut patterns = Vec::new();
+        for route in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|route| !route.is_empty())
+        {
+            if !route.starts_with('/') {
+                return Err(format!(
+                    "SPA_APP_ROUTES entries must start with '/', got '{}'",
+                    route
+                ));
+            }
+            match route.strip_suffix("/*") {
+                Some(prefix) => patterns.push(format!("{}/*", normalize(prefix))),
+                None => patterns.push(normalize(route)),
+            }
+        }
+        Ok(Self { patterns })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some("/") => true,
+                Some(prefix) => {
+                    path == prefix
+                        || path
+                            .strip_prefix(prefix)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                }
+                None => path == pattern,
+            })
+    }
+}
+
+pub fn init_app_routes() -> Result<(), String> {
+    APP_ROUTES
+        .set(AppRoutes::from_env()?)
+        .map_err(|_| "SPA app routes already initialized".to_string())
+}
+
+fn app_routes() -> &'static AppRoutes {
+    APP_ROUTES.get_or_init(AppRoutes::default)
+}
+
+/// `path` without empty segments and trailing slash, lowercased like the
+/// SPA's router matches it.
+fn normalize(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/")).to_lowercase()
+}
+
+/// Whether the last segment of `path` ends in a file extension.
+pub(crate) fn is_asset(path: &str) -> bool {
+    let last = path.rsplit('/').next().unwrap_or_default();
+    last.rsplit_once('.').is_some_and(|(_, extension)| {
+        (1..=16).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// The local paths the header navigation links to.
+pub(super) fn nav_paths(header: &Value) -> Vec<String> {
+    header
+        .get("navItems")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.get("path").and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|path| path.starts_with('/'))
+        .map(normalize)
+        .collect()
+}
+
+/// Whether `path` shows a page rather than the not-found view.
+/// `entity_found` tells whether the content `route` points at is visible.
+pub(super) fn shows_page(
+    path: &str,
+    route: &MetaRoute,
+    entity_found: bool,
+    nav_paths: &[String],
+) -> bool {
+    if *route != MetaRoute::Other {
+        return entity_found;
+    }
+    let path = normalize(path);
+    app_routes().matches(&path) || nav_paths.contains(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shows(path: &str, entity_found: bool) -> bool {
+        let nav = nav_paths(&json!({
+            "navItems": [
+                { "id": "home", "label": "Home", "type": "section" },
+                { "id": "grundlagen", "label": "Grundlagen", "type": "route", "path": "/Grundlagen/" },
+                { "id": "extern", "label": "Extern", "href": "https://example.com" },
+            ]
+        }));
+        shows_page(path, &MetaRoute::of(path), entity_found, &nav)
+    }
+
+    #[test]
+    fn test_app_routes_show_a_page() {
+        for path in ["/", "/blog", "/blog/", "/login", "/Admin"] {
+            assert!(shows(path, false), "{path}");
+        }
+        assert!(!shows("/admin/users", false));
+    }
+
+    #[test]
+    fn test_nav_paths_show_a_page() {
+        assert!(shows("/grundlagen", false));
+        assert!(!shows("/grundlagen/mehr", false));
+    }
+
+    #[test]
+    fn test_content_routes_need_visible_content() {
+        for path in [
+            "/pages/grundlagen",
+            "/pages/grundlagen/posts/shell",
+            "/tutorials/bash-basics",
+        ] {
+            assert!(shows(path, true), "{path}");
+            assert!(!shows(path, false), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_paths_are_missing() {
+        for path in [
+            "/asdfghjk",
+            "/grundlagen-alt",
+            "/pages",
+            "/blog/2024/eintrag",
+        ] {
+            assert!(!shows(path, false), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_assets_are_recognized_by_extension() {
+        for path in [
+            "/favicon.ico",
+            "/assets/index-3f2a1b.js",
+            "/.env",
+            "/robots.TXT",
+            "/site.webmanifest",
+        ] {
+            assert!(is_asset(path), "{path}");
+        }
+        for path in ["/", "/pages/grundlagen", "/tutorials/v1.2-notes", "/blog."] {
+            assert!(!is_asset(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_app_routes_from_env() {
+        assert_eq!(AppRoutes::parse(|_| None).unwrap(), AppRoutes::default());
+
+        let routes = AppRoutes::parse(|key| {
+            (key == "SPA_APP_ROUTES").then(|| " /, /Hilfe/ , /admin/* ".to_string())
+        })
+        .unwrap();
+        for path in ["/", "/hilfe", "/admin", "/admin/users"] {
+            assert!(routes.matches(path), "{path}");
+        }
+        for path in ["/blog", "/administrator", "/hilfe/faq"] {
+            assert!(!routes.matches(path), "{path}");
+        }
+
+        assert_eq!(
+            AppRoutes::parse(|_| Some("/, blog".to_string())),
+            Err("SPA_APP_ROUTES entries must start with '/', got 'blog'".to_string())
+        );
+    }
+}
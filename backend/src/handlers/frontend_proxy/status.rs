@@ -0,0 +1,125 @@
+//! `GET /api/admin/frontend/status`: is the frontend up, and which build?
+//!
+//! Probes the frontend container once, outside the circuit, and reports
+//! whether it answered, how fast, and a hash of its `index.html`, next to
+//! what the [`IndexCache`] holds and the version and commit of the backend.
+//! Served from `FRONTEND_STATIC_DIR` there is no container to probe.
+
+use super::cache::{CacheState, IndexCache};
+use super::upstream::FrontendProbe;
+use crate::models::ErrorResponse;
+use crate::security::auth;
+use axum::{http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// The commit the binary was built from; see `build.rs`.
+    pub git_sha: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrontendStatus {
+    pub backend: BuildInfo,
+    /// `None` when the frontend is served from disk.
+    pub frontend: Option<FrontendProbe>,
+    pub circuit_open: bool,
+    pub cache: CacheState,
+}
+
+pub async fn frontend_status(
+    claims: auth::Claims,
+    Extension(cache): Extension<IndexCache>,
+) -> Result<Json<FrontendStatus>, (StatusCode, Json<ErrorResponse>)> {
+    if claims.role != "admin" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Insufficient permissions".to_string(),
+            }),
+        ));
+    }
+
+    let client = cache.frontend_client();
+    let frontend = match client {
+        Some(client) => Some(client.probe().await),
+        None => None,
+    };
+    Ok(Json(FrontendStatus {
+        backend: BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: env!("GIT_SHA"),
+        },
+        frontend,
+        circuit_open: client.is_some_and(|client| client.circuit_open()),
+        cache: cache.state(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::frontend_proxy::{FrontendClient, FrontendClientConfig, IndexCacheConfig};
+    use crate::security::auth::test_claims;
+    use axum::{routing::get, Router};
+
+    fn cache(url: String) -> IndexCache {
+        let client = FrontendClient::new(FrontendClientConfig {
+            url,
+            ..FrontendClientConfig::default()
+        });
+        IndexCache::new(IndexCacheConfig::default(), client)
+    }
+
+    #[tokio::test]
+    async fn test_reports_a_reachable_frontend_and_the_cache() {
+        let app = Router::new().route("/index.html", get(|| async { "<html></html>" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let cache = cache(url);
+        let pool = crate::db::pool::create_test_pool().await;
+        cache.get(&pool).await.unwrap();
+
+        let Json(status) = frontend_status(test_claims("someone", "admin"), Extension(cache))
+            .await
+            .unwrap();
+        assert_eq!(status.backend.version, env!("CARGO_PKG_VERSION"));
+        assert!(!status.backend.git_sha.is_empty());
+        let probe = status.frontend.expect("frontend probed");
+        assert!(probe.reachable);
+        assert_eq!(probe.status, Some(200));
+        assert!(!status.circuit_open);
+        assert!(status.cache.cached);
+        assert_eq!(status.cache.age_seconds, Some(0));
+        assert!(status.cache.last_refresh.unwrap().ok);
+    }
+
+    #[tokio::test]
+    async fn test_reports_an_unreachable_frontend() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let Json(status) = frontend_status(test_claims("someone", "admin"), Extension(cache(url)))
+            .await
+            .unwrap();
+        let probe = status.frontend.expect("frontend probed");
+        assert!(!probe.reachable);
+        assert!(probe.error.is_some());
+        assert!(!status.cache.cached);
+        assert!(status.cache.last_refresh.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requires_admin() {
+        let (status, _) = frontend_status(
+            test_claims("someone", "user"),
+            Extension(IndexCache::default()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,347 @@
+//! Canonical links and schema.org JSON-LD for the page shell.
+//!
+//! With `PUBLIC_BASE_URL` set, every route gets a canonical URL: the content
+//! routes in their normalized form, any other path without query string,
+//! empty segments or trailing slash. Posts and tutorials describe themselves
+//! as an `Article` or `TechArticle`; every other route describes the site as
+//! a `WebSite` whose `SearchAction` points at the tutorial search. Content
+//! visitors cannot see, like drafts, gets neither.
+
+use super::meta::{ArticleKind, ArticleMeta, EntityMeta, MetaRoute, PageMeta};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+/// The normalized path of `route`, which `path` requested.
+pub(super) fn canonical_path(route: &MetaRoute, path: &str) -> String {
+    match route {
+        MetaRoute::Page { slug } => format!("/pages/{}", slug),
+        MetaRoute::Post {
+            page_slug,
+            post_slug,
+        } => format!("/pages/{}/posts/{}", page_slug, post_slug),
+        MetaRoute::Tutorial { id } => format!("/tutorials/{}", id),
+        MetaRoute::Other => {
+            let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            format!("/{}", segments.join("/"))
+        }
+    }
+}
+
+/// ISO 8601 to the second, in UTC.
+fn iso_date(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn article(
+    article: &ArticleMeta,
+    meta: &PageMeta,
+    site_name: &str,
+    base_url: Option<&str>,
+) -> Value {
+    let kind = match article.kind {
+        ArticleKind::Article => "Article",
+        ArticleKind::TechArticle => "TechArticle",
+    };
+    let mut data = json!({
+        "@context": "https://schema.org",
+        "@type": kind,
+        "headline": meta.title,
+        "description": meta.description,
+        "datePublished": iso_date(&article.published),
+        "dateModified": iso_date(&article.modified),
+        // Posts and tutorials have no author of their own
+        "author": { "@type": "Organization", "name": site_name },
+    });
+    if let Some(canonical) = meta.canonical.as_deref() {
+        data["url"] = json!(canonical);
+        data["mainEntityOfPage"] = json!(canonical);
+    }
+    if let Some(image) = meta.image.as_deref() {
+        data["image"] = match (image.starts_with('/'), base_url) {
+            (true, Some(base_url)) => json!(format!("{}{}", base_url, image)),
+            _ => json!(image),
+        };
+    }
+    data
+}
+
+fn website(site_name: &str, base_url: &str) -> Value {
+    json!({
+        "@context": "https://schema.org",
+        "@type": "WebSite",
+        "name": site_name,
+        "url": format!("{}/", base_url),
+        "potentialAction": {
+            "@type": "SearchAction",
+            "target": format!("{}/api/search/tutorials?q={{search_term_string}}", base_url),
+            "query-input": "required name=search_term_string",
+        },
+    })
+}
+
+/// `value` as JSON that cannot end the `<script>` element it is embedded
+/// in: `<`, `>` and `&` become `\u` escapes, which JSON parsers read back as
+/// the same characters.
+pub(super) fn script_json(value: &Value) -> String {
+    let mut escaped = String::new();
+    for c in value.to_string().chars() {
+        match c {
+            '<' => escaped.push_str("\\u003c"),
+            '>' => escaped.push_str("\\u003e"),
+            '&' => escaped.push_str("\\u0026"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fills in the canonical URL and structured data of `meta`.
+pub(super) fn annotate(
+    meta: &mut PageMeta,
+    route: &MetaRoute,
+    entity: Option<&EntityMeta>,
+    path: &str,
+    site_name: &str,
+    base_url: Option<&str>,
+) {
+    // A draft or unknown page, post or tutorial
+    if *route != MetaRoute::Other && entity.is_none() {
+        return;
+    }
+
+    meta.canonical =
+        base_url.map(|base_url| format!("{}{}", base_url, canonical_path(route, path)));
+    let data = match entity.and_then(|entity| entity.article.as_ref()) {
+        Some(details) => Some(article(details, meta, site_name, base_url)),
+        None => base_url.map(|base_url| website(site_name, base_url)),
+    };
+    meta.structured_data = data.as_ref().map(script_json);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::handlers::frontend_proxy::meta::load_entity_meta;
+    use crate::models::{CreateSitePageRequest, CreateSitePostRequest, PAGE_VISIBILITY_PUBLIC};
+    use crate::repositories;
+    use crate::utils::timestamps::parse_timestamp;
+
+    const BASE: Option<&str> = Some("https://linux.example");
+
+    fn page_meta(title: &str) -> PageMeta {
+        PageMeta {
+            title: title.to_string(),
+            description: "Alles zur Shell".to_string(),
+            image: Some("/uploads/share.png".to_string()),
+            canonical: None,
+            structured_data: None,
+            lang: None,
+            alternates: Vec::new(),
+            preloads: Vec::new(),
+            bootstrap: None,
+        }
+    }
+
+    fn post() -> EntityMeta {
+        EntityMeta {
+            article: Some(ArticleMeta {
+                kind: ArticleKind::Article,
+                published: parse_timestamp("2024-03-01 08:30:00").unwrap(),
+                modified: parse_timestamp("2024-03-02T10:00:00+01:00").unwrap(),
+            }),
+            ..EntityMeta::default()
+        }
+    }
+
+    fn parsed(meta: &PageMeta) -> Value {
+        serde_json::from_str(meta.structured_data.as_deref().expect("structured data"))
+            .expect("valid JSON")
+    }
+
+    #[test]
+    fn test_canonical_paths_are_normalized() {
+        let canonical = |path: &str| canonical_path(&MetaRoute::of(path), path);
+        assert_eq!(canonical("/pages/Grundlagen/"), "/pages/grundlagen");
+        assert_eq!(
+            canonical("/pages/grundlagen/posts/Erste-Schritte"),
+            "/pages/grundlagen/posts/erste-schritte"
+        );
+        assert_eq!(
+            canonical("/tutorials/bash-basics/"),
+            "/tutorials/bash-basics"
+        );
+        assert_eq!(canonical("//blog//"), "/blog");
+        assert_eq!(canonical("/"), "/");
+    }
+
+    #[test]
+    fn test_posts_describe_an_article() {
+        let route = MetaRoute::of("/pages/grundlagen/posts/shell/");
+        let mut meta = page_meta("Shell Grundlagen");
+        annotate(
+            &mut meta,
+            &route,
+            Some(&post()),
+            "/pages/grundlagen/posts/shell/",
+            "Linux lernen",
+            BASE,
+        );
+
+        let url = "https://linux.example/pages/grundlagen/posts/shell";
+        assert_eq!(meta.canonical.as_deref(), Some(url));
+        assert_eq!(
+            parsed(&meta),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "Article",
+                "headline": "Shell Grundlagen",
+                "description": "Alles zur Shell",
+                "datePublished": "2024-03-01T08:30:00Z",
+                "dateModified": "2024-03-02T09:00:00Z",
+                "author": { "@type": "Organization", "name": "Linux lernen" },
+                "url": url,
+                "mainEntityOfPage": url,
+                "image": "https://linux.example/uploads/share.png",
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_routes_describe_the_website() {
+        let mut meta = page_meta("Linux lernen");
+        annotate(
+            &mut meta,
+            &MetaRoute::Other,
+            None,
+            "/blog/",
+            "Linux lernen",
+            BASE,
+        );
+
+        assert_eq!(
+            meta.canonical.as_deref(),
+            Some("https://linux.example/blog")
+        );
+        let data = parsed(&meta);
+        assert_eq!(data["@type"], "WebSite");
+        assert_eq!(data["url"], "https://linux.example/");
+        assert_eq!(
+            data["potentialAction"]["target"],
+            "https://linux.example/api/search/tutorials?q={search_term_string}"
+        );
+    }
+
+    #[test]
+    fn test_without_base_url_only_articles_are_described() {
+        let mut meta = page_meta("Linux lernen");
+        annotate(
+            &mut meta,
+            &MetaRoute::Other,
+            None,
+            "/",
+            "Linux lernen",
+            None,
+        );
+        assert_eq!(meta, page_meta("Linux lernen"));
+
+        let route = MetaRoute::of("/tutorials/bash");
+        annotate(
+            &mut meta,
+            &route,
+            Some(&post()),
+            "/tutorials/bash",
+            "Linux lernen",
+            None,
+        );
+        assert_eq!(meta.canonical, None);
+        let data = parsed(&meta);
+        assert_eq!(data["image"], "/uploads/share.png");
+        assert!(data.get("url").is_none());
+    }
+
+    #[test]
+    fn test_script_breaking_values_are_escaped() {
+        let title = "</script><script>alert(1)</script> & <!--";
+        let mut meta = page_meta(title);
+        annotate(
+            &mut meta,
+            &MetaRoute::of("/tutorials/x"),
+            Some(&post()),
+            "/tutorials/x",
+            "Linux lernen",
+            BASE,
+        );
+
+        let embedded = meta.structured_data.clone().unwrap();
+        assert!(
+            !embedded.contains('<') && !embedded.contains('>'),
+            "{embedded}"
+        );
+        assert!(!embedded.to_lowercase().contains("</script"));
+        assert_eq!(parsed(&meta)["headline"], title);
+    }
+
+    #[tokio::test]
+    async fn test_drafts_never_get_structured_data() {
+        let pool = db::pool::create_test_pool().await;
+        let page = repositories::pages::create_site_page(
+            &pool,
+            CreateSitePageRequest {
+                slug: "grundlagen".to_string(),
+                title: "Grundlagen".to_string(),
+                description: None,
+                nav_label: None,
+                show_in_nav: false,
+                order_index: None,
+                is_published: true,
+                visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+                hero: json!({}),
+                layout: json!({}),
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("create page");
+        for (slug, is_published) in [("fertig", true), ("entwurf", false)] {
+            repositories::posts::create_site_post(
+                &pool,
+                &page.id,
+                CreateSitePostRequest {
+                    title: format!("Post {slug}"),
+                    slug: slug.to_string(),
+                    excerpt: None,
+                    content_markdown: "Body".to_string(),
+                    is_published,
+                    allow_comments: true,
+                    published_at: None,
+                    order_index: None,
+                },
+            )
+            .await
+            .expect("create post");
+        }
+
+        for (path, described) in [
+            ("/pages/grundlagen/posts/fertig", true),
+            ("/pages/grundlagen/posts/entwurf", false),
+            ("/tutorials/unbekannt", false),
+        ] {
+            let route = MetaRoute::of(path);
+            let entity = load_entity_meta(&pool, &route).await;
+            let mut meta = page_meta("Titel");
+            annotate(
+                &mut meta,
+                &route,
+                entity.as_ref(),
+                path,
+                "Linux lernen",
+                BASE,
+            );
+            assert_eq!(meta.structured_data.is_some(), described, "{path}");
+            assert_eq!(meta.canonical.is_some(), described, "{path}");
+        }
+    }
+}
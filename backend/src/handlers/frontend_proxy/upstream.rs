@@ -0,0 +1,495 @@
+//! The HTTP client for the frontend container.
+//!
+//! One [`FrontendClient`] is shared by all requests. A fetch gives up after
+//! `FRONTEND_CONNECT_TIMEOUT_MS` (default 2000) to connect and
+//! `FRONTEND_READ_TIMEOUT_MS` (default 3000) without data, and a timeout,
+//! refused connection or `5xx` is retried once after a short pause.
+//!
+//! After `FRONTEND_CIRCUIT_FAILURES` (default 5) failed fetches in a row the
+//! circuit opens: for `FRONTEND_CIRCUIT_COOLDOWN_SECONDS` (default 30) every
+//! fetch fails right away, so page views get the cached copy or the error
+//! page without waiting. The first fetch after the cooldown is a trial; its
+//! success closes the circuit and its failure opens it again.
+//!
+//! [`FrontendClient::probe`] checks the frontend for the status report. It
+//! ignores the circuit, so an open one cannot hide that the frontend is back.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Default frontend URL (internal Docker network)
+const DEFAULT_FRONTEND_URL: &str = "http://frontend";
+
+/// Pause before the single retry of a transient failure.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Most of `index.html` a probe reads and hashes.
+const PROBE_BYTE_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontendClientConfig {
+    pub url: String,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub circuit_failures: u32,
+    pub circuit_cooldown: Duration,
+}
+
+impl Default for FrontendClientConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_FRONTEND_URL.to_string(),
+            connect_timeout: Duration::from_secs(2),
+            read_timeout: Duration::from_secs(3),
+            circuit_failures: 5,
+            circuit_cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl FrontendClientConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// An HTTP client with the configured timeouts.
+    pub fn http_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .build()
+            .expect("Failed to build the frontend HTTP client")
+    }
+
+    /// Reads the settings through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let value = |key: &str| {
+            lookup(key)
+                .map(|raw| raw.trim().to_string())
+                .filter(|raw| !raw.is_empty())
+        };
+        let positive = |key: &str, default: u64| -> Result<u64, String> {
+            let Some(raw) = value(key) else {
+                return Ok(default);
+            };
+            match raw.parse::<u64>() {
+                Ok(number) if number > 0 => Ok(number),
+                _ => Err(format!("{} must be a positive integer, got '{}'", key, raw)),
+            }
+        };
+
+        let defaults = Self::default();
+        let circuit_failures = positive(
+            "FRONTEND_CIRCUIT_FAILURES",
+            u64::from(defaults.circuit_failures),
+        )?;
+        Ok(Self {
+            url: value("FRONTEND_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(defaults.url),
+            connect_timeout: Duration::from_millis(positive(
+                "FRONTEND_CONNECT_TIMEOUT_MS",
+                defaults.connect_timeout.as_millis() as u64,
+            )?),
+            read_timeout: Duration::from_millis(positive(
+                "FRONTEND_READ_TIMEOUT_MS",
+                defaults.read_timeout.as_millis() as u64,
+            )?),
+            circuit_failures: u32::try_from(circuit_failures).map_err(|_| {
+                format!("FRONTEND_CIRCUIT_FAILURES is too large, got '{circuit_failures}'")
+            })?,
+            circuit_cooldown: Duration::from_secs(positive(
+                "FRONTEND_CIRCUIT_COOLDOWN_SECONDS",
+                defaults.circuit_cooldown.as_secs(),
+            )?),
+        })
+    }
+}
+
+/// What one probe of the frontend found.
+#[derive(Debug, Serialize)]
+pub struct FrontendProbe {
+    pub url: String,
+    /// Whether the frontend answered at all, whatever the status.
+    pub reachable: bool,
+    pub latency_ms: u64,
+    pub status: Option<u16>,
+    /// The `Content-Length` the frontend announced.
+    pub content_length: Option<u64>,
+    pub bytes_read: u64,
+    /// Whether reading stopped at the probe's byte limit.
+    pub truncated: bool,
+    /// SHA-256 of the bytes read, telling one frontend build from another.
+    pub sha256: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Circuit {
+    Closed { failures: u32 },
+    Open { until: Instant },
+}
+
+#[derive(Debug)]
+pub struct FrontendClient {
+    config: FrontendClientConfig,
+    client: reqwest::Client,
+    circuit: Mutex<Circuit>,
+}
+
+impl Default for FrontendClient {
+    fn default() -> Self {
+        Self::new(FrontendClientConfig::default())
+    }
+}
+
+impl FrontendClient {
+    pub fn new(config: FrontendClientConfig) -> Self {
+        let client = config.http_client();
+        Self::with_client(config, client)
+    }
+
+    /// Sends its requests through `client`, which should come from
+    /// [`FrontendClientConfig::http_client`] so the timeouts apply.
+    pub fn with_client(config: FrontendClientConfig, client: reqwest::Client) -> Self {
+        Self {
+            config,
+            client,
+            circuit: Mutex::new(Circuit::Closed { failures: 0 }),
+        }
+    }
+
+    /// Fetches `index.html`, unless the circuit is open.
+    pub async fn fetch_index(&self) -> Result<String, String> {
+        let index_url = format!("{}/index.html", self.config.url);
+        if !self.try_acquire() {
+            return Err(format!(
+                "Not fetching index.html from {} while the circuit is open",
+                index_url
+            ));
+        }
+
+        let mut result = self.fetch(&index_url).await;
+        if matches!(&result, Err(err) if is_transient(err)) {
+            tokio::time::sleep(RETRY_BACKOFF).await;
+            result = self.fetch(&index_url).await;
+        }
+        self.record(result.is_ok());
+        result.map_err(|err| format!("Failed to fetch index.html from {}: {}", index_url, err))
+    }
+
+    async fn fetch(&self, url: &str) -> Result<String, reqwest::Error> {
+        self.client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+
+    /// Fetches `index.html` once, bypassing the circuit, and reports what
+    /// came back.
+    pub async fn probe(&self) -> FrontendProbe {
+        let url = format!("{}/index.html", self.config.url);
+        let started = Instant::now();
+        let mut probe = FrontendProbe {
+            url: url.clone(),
+            reachable: false,
+            latency_ms: 0,
+            status: None,
+            content_length: None,
+            bytes_read: 0,
+            truncated: false,
+            sha256: None,
+            error: None,
+        };
+
+        match self.client.get(&url).send().await {
+            Ok(mut response) => {
+                probe.reachable = true;
+                probe.status = Some(response.status().as_u16());
+                probe.content_length = response.content_length();
+                let mut hasher = Sha256::new();
+                loop {
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => {
+                            let room = PROBE_BYTE_LIMIT - probe.bytes_read as usize;
+                            let take = chunk.len().min(room);
+                            hasher.update(&chunk[..take]);
+                            probe.bytes_read += take as u64;
+                            if take < chunk.len() {
+                                probe.truncated = true;
+                                break;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(err) => {
+                            probe.error = Some(err.to_string());
+                            break;
+                        }
+                    }
+                }
+                probe.sha256 = Some(format!("{:x}", hasher.finalize()));
+            }
+            Err(err) => probe.error = Some(err.to_string()),
+        }
+        probe.latency_ms = started.elapsed().as_millis() as u64;
+        probe
+    }
+
+    /// Whether fetches currently fail fast.
+    pub fn circuit_open(&self) -> bool {
+        matches!(*self.lock(), Circuit::Open { .. })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Whether a fetch may go out now.
+    fn try_acquire(&self) -> bool {
+        let mut circuit = self.lock();
+        match *circuit {
+            Circuit::Closed { .. } => true,
+            Circuit::Open { until } if Instant::now() >= until => {
+                tracing::info!("Frontend circuit half-open, trying a fetch");
+                // Everyone else keeps failing fast during the trial. Should
+                // the trial never finish, the next one follows a cooldown.
+                *circuit = Circuit::Open {
+                    until: Instant::now() + self.config.circuit_cooldown,
+                };
+                true
+            }
+            Circuit::Open { .. } => false,
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut circuit = self.lock();
+        let next = match (*circuit, success) {
+            (Circuit::Closed { failures }, true) => {
+                if failures > 0 {
+                    tracing::debug!(failures, "Frontend fetch succeeded again");
+                }
+                Circuit::Closed { failures: 0 }
+            }
+            (Circuit::Open { .. }, true) => {
+                tracing::info!("Frontend circuit closed");
+                Circuit::Closed { failures: 0 }
+            }
+            (Circuit::Closed { failures }, false)
+                if failures + 1 < self.config.circuit_failures =>
+            {
+                Circuit::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, false) => {
+                tracing::warn!(
+                    cooldown_seconds = self.config.circuit_cooldown.as_secs(),
+                    "Frontend circuit open after repeated failures"
+                );
+                Circuit::Open {
+                    until: Instant::now() + self.config.circuit_cooldown,
+                }
+            }
+        };
+        *circuit = next;
+    }
+}
+
+/// Failures worth a second attempt: the frontend was slow, not up yet or
+/// briefly overloaded.
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout()
+        || err.is_connect()
+        || err.status().is_some_and(|status| status.is_server_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::get, Router};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A frontend that hangs while `hanging` is set.
+    #[derive(Clone, Default)]
+    struct Upstream {
+        hits: Arc<AtomicUsize>,
+        hanging: Arc<AtomicBool>,
+    }
+
+    impl Upstream {
+        async fn start(&self) -> String {
+            let app = Router::new()
+                .route(
+                    "/index.html",
+                    get(|State(upstream): State<Upstream>| async move {
+                        upstream.hits.fetch_add(1, Ordering::SeqCst);
+                        if upstream.hanging.load(Ordering::SeqCst) {
+                            tokio::time::sleep(Duration::from_secs(60)).await;
+                        }
+                        "<html></html>"
+                    }),
+                )
+                .route(
+                    "/large/index.html",
+                    get(|| async { "x".repeat(PROBE_BYTE_LIMIT + 10) }),
+                )
+                .route(
+                    "/missing/index.html",
+                    get(|State(upstream): State<Upstream>| async move {
+                        upstream.hits.fetch_add(1, Ordering::SeqCst);
+                        StatusCode::NOT_FOUND
+                    }),
+                )
+                .with_state(self.clone());
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            url
+        }
+
+        fn hits(&self) -> usize {
+            self.hits.load(Ordering::SeqCst)
+        }
+    }
+
+    fn client(url: String) -> FrontendClient {
+        FrontendClient::new(FrontendClientConfig {
+            url,
+            connect_timeout: Duration::from_millis(100),
+            read_timeout: Duration::from_millis(100),
+            circuit_failures: 2,
+            circuit_cooldown: Duration::from_secs(1),
+        })
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        assert_eq!(
+            FrontendClientConfig::parse(|_| None).unwrap(),
+            FrontendClientConfig::default()
+        );
+        let config = FrontendClientConfig::parse(|key| match key {
+            "FRONTEND_URL" => Some("http://web:8080/".to_string()),
+            "FRONTEND_READ_TIMEOUT_MS" => Some(" 500 ".to_string()),
+            "FRONTEND_CIRCUIT_FAILURES" => Some("3".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.url, "http://web:8080");
+        assert_eq!(config.connect_timeout, Duration::from_secs(2));
+        assert_eq!(config.read_timeout, Duration::from_millis(500));
+        assert_eq!(config.circuit_failures, 3);
+        assert_eq!(
+            FrontendClientConfig::parse(|key| {
+                (key == "FRONTEND_CIRCUIT_COOLDOWN_SECONDS").then(|| "0".to_string())
+            }),
+            Err(
+                "FRONTEND_CIRCUIT_COOLDOWN_SECONDS must be a positive integer, got '0'".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hanging_frontend_opens_the_circuit_until_it_recovers() {
+        let upstream = Upstream::default();
+        upstream.hanging.store(true, Ordering::SeqCst);
+        let client = client(upstream.start().await);
+
+        // Each fetch times out twice: the attempt and its retry
+        for fetches in 1..=2 {
+            let started = Instant::now();
+            let err = client.fetch_index().await.unwrap_err();
+            assert!(err.starts_with("Failed to fetch index.html"), "{err}");
+            assert!(started.elapsed() < Duration::from_secs(2));
+            assert_eq!(upstream.hits(), fetches * 2);
+        }
+
+        // Open: no request reaches the frontend
+        let started = Instant::now();
+        let err = client.fetch_index().await.unwrap_err();
+        assert!(err.contains("circuit is open"), "{err}");
+        assert!(started.elapsed() < Duration::from_millis(50));
+        assert_eq!(upstream.hits(), 4);
+
+        // The trial after the cooldown succeeds and closes the circuit
+        upstream.hanging.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(client.fetch_index().await.unwrap(), "<html></html>");
+        assert_eq!(*client.lock(), Circuit::Closed { failures: 0 });
+        assert_eq!(client.fetch_index().await.unwrap(), "<html></html>");
+        assert_eq!(upstream.hits(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_probe_of_a_reachable_frontend() {
+        let upstream = Upstream::default();
+        let url = upstream.start().await;
+
+        let probe = client(url.clone()).probe().await;
+        assert!(probe.reachable);
+        assert_eq!(probe.url, format!("{url}/index.html"));
+        assert_eq!(probe.status, Some(200));
+        assert_eq!(probe.content_length, Some(13));
+        assert_eq!(probe.bytes_read, 13);
+        assert!(!probe.truncated);
+        assert_eq!(
+            probe.sha256,
+            Some(format!("{:x}", Sha256::digest(b"<html></html>")))
+        );
+        assert_eq!(probe.error, None);
+
+        let probe = client(format!("{url}/large")).probe().await;
+        assert_eq!(probe.bytes_read, PROBE_BYTE_LIMIT as u64);
+        assert!(probe.truncated);
+
+        // A 404 still means the frontend is up
+        let probe = client(format!("{url}/missing")).probe().await;
+        assert!(probe.reachable);
+        assert_eq!(probe.status, Some(404));
+    }
+
+    #[tokio::test]
+    async fn test_probe_of_an_unreachable_frontend_ignores_the_circuit() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = client(url);
+        *client.lock() = Circuit::Open {
+            until: Instant::now() + Duration::from_secs(60),
+        };
+
+        let probe = client.probe().await;
+        assert!(!probe.reachable);
+        assert_eq!(probe.status, None);
+        assert_eq!(probe.sha256, None);
+        assert!(probe.error.is_some());
+        assert!(client.circuit_open());
+    }
+
+    #[tokio::test]
+    async fn test_failed_trial_reopens_the_circuit() {
+        let upstream = Upstream::default();
+        let client = client(format!("{}/missing", upstream.start().await));
+
+        // A 404 is not retried, but still counts towards the circuit
+        for _ in 0..2 {
+            assert!(client.fetch_index().await.is_err());
+        }
+        assert_eq!(upstream.hits(), 2);
+        assert!(matches!(*client.lock(), Circuit::Open { .. }));
+
+        *client.lock() = Circuit::Open {
+            until: Instant::now(),
+        };
+        assert!(client.fetch_index().await.is_err());
+        assert_eq!(upstream.hits(), 3);
+        assert!(matches!(*client.lock(), Circuit::Open { until } if until > Instant::now()));
+    }
+}
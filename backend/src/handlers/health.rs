@@ -0,0 +1,70 @@
+//! Liveness and readiness probes.
+//!
+//! `/api/health` only says the process is serving. `/api/health/ready` also
+//! requires the migrations to have finished, the database to answer and
+//! every critical background task to be running. The listener is bound
+//! after migrations, so in practice readiness fails when the database goes
+//! away or a task dies.
+
+use crate::{db, tasks::TaskHealth, tasks::TaskRegistry};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long the database gets to answer the readiness query.
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    /// Why the service is not ready.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub tasks: Vec<TaskHealth>,
+}
+
+pub async fn liveness() -> &'static str {
+    "OK"
+}
+
+async fn not_ready_reason(pool: &db::DbPool, tasks: &[TaskHealth]) -> Option<String> {
+    if !db::migrations::migrations_complete() {
+        return Some("Database migrations have not finished".to_string());
+    }
+
+    let ping = sqlx::query_scalar::<_, i64>("SELECT 1").fetch_one(pool);
+    match tokio::time::timeout(DATABASE_CHECK_TIMEOUT, ping).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(err)) => {
+            tracing::warn!("Readiness database check failed: {}", err);
+            return Some("Database is unavailable".to_string());
+        }
+        Err(_) => return Some("Database did not answer in time".to_string()),
+    }
+
+    tasks
+        .iter()
+        .find(|task| task.critical && !task.running)
+        .map(|task| format!("Background task '{}' is not running", task.name))
+}
+
+pub async fn readiness(
+    State(pool): State<db::DbPool>,
+    Extension(registry): Extension<TaskRegistry>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let tasks = registry.tasks();
+    let reason = not_ready_reason(&pool, &tasks).await;
+    let status = if reason.is_some() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (
+        status,
+        Json(ReadinessResponse {
+            ready: reason.is_none(),
+            reason,
+            tasks,
+        }),
+    )
+}
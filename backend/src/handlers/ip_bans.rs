@@ -0,0 +1,133 @@
+//! Runtime IP ban list.
+//!
+//! See [`crate::middleware::ip_ban`] for how bans are enforced.
+
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    middleware::{
+        ip_ban,
+        security::{ClientIp, IpRange},
+    },
+    models::{CreateIpBanRequest, DeleteIpBanQuery, IpBan},
+    repositories,
+    security::auth,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+const MAX_REASON_LENGTH: usize = 500;
+
+fn parse_range(ip: &str) -> Result<IpRange, AppError> {
+    ip.parse::<IpRange>()
+        .map_err(|message| AppError::invalid_field("ip", message))
+}
+
+pub async fn list_ip_bans(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<Vec<IpBan>>, AppError> {
+    ensure_admin(&claims)?;
+    repositories::ip_bans::list_bans(&pool)
+        .await
+        .map(Json)
+        .map_err(|err| AppError::internal("Failed to access IP bans", err))
+}
+
+/// Bans an address or CIDR range, permanently unless `expires_at` is given.
+/// Ranges covering the requesting admin are refused so nobody locks
+/// themselves out.
+pub async fn create_ip_ban(
+    claims: auth::Claims,
+    ClientIp(client_ip): ClientIp,
+    State(pool): State<db::DbPool>,
+    Json(payload): Json<CreateIpBanRequest>,
+) -> Result<(StatusCode, Json<IpBan>), AppError> {
+    ensure_admin(&claims)?;
+
+    let range = parse_range(&payload.ip)?;
+    if range.contains(client_ip) {
+        return Err(AppError::invalid_field(
+            "ip",
+            format!("{} includes your own address", range),
+        ));
+    }
+
+    let reason = payload
+        .reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if reason
+        .as_ref()
+        .is_some_and(|reason| reason.chars().count() > MAX_REASON_LENGTH)
+    {
+        return Err(AppError::invalid_field(
+            "reason",
+            format!("Reason must be at most {} characters", MAX_REASON_LENGTH),
+        ));
+    }
+
+    let expires_at = match payload.expires_at.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => {
+            let at = DateTime::parse_from_rfc3339(value)
+                .map_err(|_| {
+                    AppError::invalid_field(
+                        "expires_at",
+                        "expires_at must be an RFC 3339 timestamp",
+                    )
+                })?
+                .with_timezone(&Utc);
+            if at <= Utc::now() {
+                return Err(AppError::invalid_field(
+                    "expires_at",
+                    "expires_at must be in the future",
+                ));
+            }
+            Some(at.to_rfc3339_opts(SecondsFormat::Millis, true))
+        }
+    };
+
+    let ban = repositories::ip_bans::create_ban(
+        &pool,
+        &range.to_string(),
+        reason.as_deref(),
+        &claims.sub,
+        expires_at.as_deref(),
+    )
+    .await
+    .map_err(|err| AppError::internal("Failed to access IP bans", err))?
+    .ok_or_else(|| AppError::Conflict(format!("{} is already banned", range)))?;
+    ip_ban::invalidate();
+    tracing::info!(
+        user = %claims.sub,
+        ip = %ban.ip,
+        expires_at = ban.expires_at.as_deref().unwrap_or("never"),
+        "IP ban added"
+    );
+    Ok((StatusCode::CREATED, Json(ban)))
+}
+
+/// Lifts the ban on `?ip=`, given as listed or in any equivalent notation.
+pub async fn delete_ip_ban(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<DeleteIpBanQuery>,
+) -> Result<StatusCode, AppError> {
+    ensure_admin(&claims)?;
+
+    let range = parse_range(&query.ip)?;
+    let deleted = repositories::ip_bans::delete_ban(&pool, &range.to_string())
+        .await
+        .map_err(|err| AppError::internal("Failed to access IP bans", err))?;
+    if !deleted {
+        return Err(AppError::NotFound(format!("{} is not banned", range)));
+    }
+    ip_ban::invalidate();
+    tracing::info!(user = %claims.sub, ip = %range, "IP ban lifted");
+    Ok(StatusCode::NO_CONTENT)
+}
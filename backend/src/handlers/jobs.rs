@@ -0,0 +1,62 @@
+//! Status of the scheduled background jobs.
+//!
+//! See [`crate::jobs`] for how jobs are run and recorded.
+
+use crate::{
+    db,
+    models::{ErrorResponse, JobRun},
+    repositories,
+    security::auth,
+    tasks::{TaskHealth, TaskRegistry},
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct JobStatus {
+    #[serde(flatten)]
+    pub task: TaskHealth,
+    /// Latest recorded run; `None` until the job first ran.
+    pub last_run: Option<JobRun>,
+}
+
+/// Every job of this process with its latest run.
+pub async fn list_jobs(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Extension(registry): Extension<TaskRegistry>,
+) -> Result<Json<Vec<JobStatus>>, (StatusCode, Json<ErrorResponse>)> {
+    if claims.role != "admin" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Insufficient permissions".to_string(),
+            }),
+        ));
+    }
+
+    let mut runs = repositories::job_runs::list_runs(&pool)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to load job runs: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to load job status".to_string(),
+                }),
+            )
+        })?;
+
+    let jobs = registry
+        .tasks()
+        .into_iter()
+        .map(|task| {
+            let last_run = runs
+                .iter()
+                .position(|run| run.name == task.name)
+                .map(|index| runs.swap_remove(index));
+            JobStatus { task, last_run }
+        })
+        .collect();
+    Ok(Json(jobs))
+}
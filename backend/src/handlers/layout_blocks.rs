@@ -0,0 +1,456 @@
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    models::{
+        CreateLayoutBlockRequest, LayoutBlock, LayoutBlockInUseResponse, LayoutBlockListResponse,
+        LayoutBlockResponse, UpdateLayoutBlockRequest,
+    },
+    repositories::{self, blocks::BlockReferences},
+    security::auth,
+    utils::layout_blocks::resolve_block,
+    validation::page_schema,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::Value;
+
+const MAX_BLOCK_JSON_BYTES: usize = 50_000;
+
+fn sanitize_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "Name cannot be empty"));
+    }
+    Ok(name)
+}
+
+fn validate_block(block: &Value) -> Result<(), AppError> {
+    let serialized = serde_json::to_string(block)
+        .map_err(|err| AppError::invalid_field("block", format!("Invalid block JSON: {err}")))?;
+    if serialized.len() > MAX_BLOCK_JSON_BYTES {
+        return Err(AppError::invalid_field(
+            "block",
+            format!("Block JSON exceeds maximum size of {MAX_BLOCK_JSON_BYTES} bytes"),
+        ));
+    }
+
+    page_schema::validate_layout_block(block).map_err(|violation| {
+        AppError::invalid_field("block", format!("Invalid block at {violation}"))
+    })
+}
+
+/// Makes sure a block that is itself a `ref` points at an existing block and
+/// would not close a reference cycle once saved under `id` / `name`.
+async fn ensure_block_resolves(
+    pool: &db::DbPool,
+    id: &str,
+    name: &str,
+    block: &Value,
+) -> Result<(), AppError> {
+    let mut library = repositories::blocks::load_block_library(pool, Some(id))
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+    library.insert(id, name, block.clone());
+
+    resolve_block(block, &library, "/")
+        .map(|_| ())
+        .map_err(|err| AppError::invalid_field("block", format!("Invalid block at {err}")))
+}
+
+fn describe_references(references: &BlockReferences) -> String {
+    let mut parts = Vec::new();
+    if !references.pages.is_empty() {
+        parts.push(format!("pages: {}", references.pages.join(", ")));
+    }
+    if !references.blocks.is_empty() {
+        parts.push(format!("blocks: {}", references.blocks.join(", ")));
+    }
+    parts.join("; ")
+}
+
+/// A `409 Conflict` naming the referencing pages and blocks, both in the
+/// message and as lists the admin UI can link to.
+fn in_use(error: String, references: BlockReferences) -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(LayoutBlockInUseResponse {
+            error,
+            pages: references.pages,
+            blocks: references.blocks,
+        }),
+    )
+        .into_response()
+}
+
+fn map_block(block: LayoutBlock) -> Result<LayoutBlockResponse, AppError> {
+    let value = serde_json::from_str::<Value>(&block.block_json)
+        .map_err(|err| AppError::internal("Failed to parse stored block JSON", err))?;
+
+    Ok(LayoutBlockResponse {
+        id: block.id,
+        name: block.name,
+        block: value,
+        updated_at: block.updated_at,
+    })
+}
+
+async fn load_block(pool: &db::DbPool, id: &str) -> Result<LayoutBlock, AppError> {
+    repositories::blocks::get_layout_block(pool, id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))?
+        .ok_or_else(|| AppError::NotFound("Layout block not found".to_string()))
+}
+
+pub async fn list_layout_blocks(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<LayoutBlockListResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let records = repositories::blocks::list_layout_blocks(&pool)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+
+    let mut items = Vec::with_capacity(records.len());
+    for record in records {
+        items.push(map_block(record)?);
+    }
+
+    Ok(Json(LayoutBlockListResponse { items }))
+}
+
+pub async fn get_layout_block(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+) -> Result<Json<LayoutBlockResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let record = load_block(&pool, &id).await?;
+    Ok(Json(map_block(record)?))
+}
+
+pub async fn create_layout_block(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Json(payload): Json<CreateLayoutBlockRequest>,
+) -> Result<Json<LayoutBlockResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let name = sanitize_name(&payload.name)?;
+    validate_block(&payload.block)?;
+    ensure_block_resolves(&pool, "", &name, &payload.block).await?;
+
+    let record = repositories::blocks::create_layout_block(&pool, &name, &payload.block)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+
+    Ok(Json(map_block(record)?))
+}
+
+pub async fn update_layout_block(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateLayoutBlockRequest>,
+) -> Result<Response, AppError> {
+    ensure_admin(&claims)?;
+
+    let existing = load_block(&pool, &id).await?;
+    let name = payload.name.as_deref().map(sanitize_name).transpose()?;
+
+    // Renaming would silently break every reference that uses the old name.
+    if let Some(new_name) = name
+        .as_deref()
+        .filter(|new_name| *new_name != existing.name)
+    {
+        let references = repositories::blocks::find_block_references(&pool, &[&existing.name])
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+        if !references.is_empty() {
+            let error = format!(
+                "Cannot rename layout block '{}' to '{new_name}': it is referenced by name from {}",
+                existing.name,
+                describe_references(&references)
+            );
+            return Ok(in_use(error, references));
+        }
+    }
+
+    if let Some(block) = payload.block.as_ref() {
+        validate_block(block)?;
+        let effective_name = name.as_deref().unwrap_or(&existing.name);
+        ensure_block_resolves(&pool, &id, effective_name, block).await?;
+    }
+
+    let record = repositories::blocks::update_layout_block(
+        &pool,
+        &id,
+        name.as_deref(),
+        payload.block.as_ref(),
+    )
+    .await
+    .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+
+    Ok(Json(map_block(record)?).into_response())
+}
+
+pub async fn delete_layout_block(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    ensure_admin(&claims)?;
+
+    let existing = load_block(&pool, &id).await?;
+    let references =
+        repositories::blocks::find_block_references(&pool, &[&existing.id, &existing.name])
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+
+    if !references.is_empty() {
+        let error = format!(
+            "Layout block '{}' is still referenced by {}",
+            existing.name,
+            describe_references(&references)
+        );
+        return Ok(in_use(error, references));
+    }
+
+    repositories::blocks::delete_layout_block(&pool, &id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::handlers::site_pages;
+    use crate::models::{CreateSitePageRequest, PAGE_VISIBILITY_PUBLIC};
+    use crate::security::auth::test_admin_claims as admin;
+    use crate::validation::ValidatedJson;
+    use axum::extract::Query;
+    use serde_json::json;
+
+    async fn json_body(response: Response) -> (StatusCode, Value) {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn create_block(pool: &db::DbPool, name: &str, block: Value) -> LayoutBlockResponse {
+        let Json(block) = create_layout_block(
+            admin(),
+            State(pool.clone()),
+            Json(CreateLayoutBlockRequest {
+                name: name.to_string(),
+                block,
+            }),
+        )
+        .await
+        .expect("create block");
+        block
+    }
+
+    fn page_request(slug: &str, layout: Value) -> CreateSitePageRequest {
+        CreateSitePageRequest {
+            slug: slug.to_string(),
+            title: slug.to_string(),
+            description: None,
+            nav_label: None,
+            show_in_nav: false,
+            order_index: None,
+            is_published: true,
+            visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+            hero: Value::Null,
+            layout,
+            meta_title: None,
+            meta_description: None,
+            og_image: None,
+        }
+    }
+
+    async fn public_blocks(pool: &db::DbPool, slug: &str) -> Value {
+        let Json(response) = site_pages::get_published_page_by_slug(
+            Ok(auth::OptionalClaims(None)),
+            State(pool.clone()),
+            Path(slug.to_string()),
+            Query(site_pages::PublicPostListQuery::default()),
+        )
+        .await
+        .expect("public page");
+        response.page.layout["blocks"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_public_page_resolves_refs_and_admin_keeps_them() {
+        let pool = create_test_pool().await;
+        let block = create_block(
+            &pool,
+            "Shell-Hint",
+            json!({ "type": "infobox", "title": "Tipp", "content": "Nutze Tab" }),
+        )
+        .await;
+        assert_eq!(block.name, "shell-hint");
+
+        let Json(page) = site_pages::create_site_page(
+            admin(),
+            State(pool.clone()),
+            ValidatedJson(page_request(
+                "grundlagen",
+                json!({ "blocks": [
+                    { "type": "text", "content": "Intro" },
+                    { "type": "ref", "block": "shell-hint" },
+                    { "type": "ref", "block": block.id }
+                ] }),
+            )),
+        )
+        .await
+        .expect("create page");
+        assert_eq!(page.layout["blocks"][1]["type"], "ref");
+
+        let blocks = public_blocks(&pool, "grundlagen").await;
+        assert_eq!(blocks.as_array().map(Vec::len), Some(3));
+        assert_eq!(blocks[1]["title"], "Tipp");
+        assert_eq!(blocks[2]["type"], "infobox");
+    }
+
+    #[tokio::test]
+    async fn test_missing_refs_are_rejected_for_admins_and_skipped_publicly() {
+        let pool = create_test_pool().await;
+
+        let err = site_pages::create_site_page(
+            admin(),
+            State(pool.clone()),
+            ValidatedJson(page_request(
+                "kaputt",
+                json!({ "blocks": [{ "type": "ref", "block": "gone" }] }),
+            )),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.field(), Some("layout"));
+        assert_eq!(
+            err.message(),
+            "Invalid layout at /blocks/0: references unknown block 'gone'"
+        );
+
+        // A reference that broke outside the API is dropped from the public page.
+        repositories::pages::create_site_page(
+            &pool,
+            page_request(
+                "kaputt",
+                json!({ "blocks": [
+                    { "type": "ref", "block": "gone" },
+                    { "type": "text", "content": "Bleibt" }
+                ] }),
+            ),
+        )
+        .await
+        .expect("seed page");
+        assert_eq!(
+            public_blocks(&pool, "kaputt").await,
+            json!([{ "type": "text", "content": "Bleibt" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_updates_cannot_create_cycles() {
+        let pool = create_test_pool().await;
+        let a = create_block(&pool, "a", json!({ "type": "text", "content": "a" })).await;
+        create_block(&pool, "b", json!({ "type": "ref", "block": "a" })).await;
+
+        let err = update_layout_block(
+            admin(),
+            State(pool.clone()),
+            Path(a.id.clone()),
+            Json(UpdateLayoutBlockRequest {
+                name: None,
+                block: Some(json!({ "type": "ref", "block": "b" })),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(err.message().contains("cycle"), "{err}");
+
+        let err = create_layout_block(
+            admin(),
+            State(pool.clone()),
+            Json(CreateLayoutBlockRequest {
+                name: "c".to_string(),
+                block: json!({ "type": "ref", "block": "missing" }),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_rename_are_guarded_while_referenced() {
+        let pool = create_test_pool().await;
+        let block = create_block(&pool, "banner", json!({ "type": "text", "content": "x" })).await;
+
+        for slug in ["praxis", "grundlagen"] {
+            let _ = site_pages::create_site_page(
+                admin(),
+                State(pool.clone()),
+                ValidatedJson(page_request(
+                    slug,
+                    json!({ "blocks": [{ "type": "ref", "block": "banner" }] }),
+                )),
+            )
+            .await
+            .expect("create page");
+        }
+
+        let response = delete_layout_block(admin(), State(pool.clone()), Path(block.id.clone()))
+            .await
+            .expect("delete");
+        let (status, body) = json_body(response).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body,
+            json!({
+                "error": "Layout block 'banner' is still referenced by pages: grundlagen, praxis",
+                "pages": ["grundlagen", "praxis"],
+                "blocks": [],
+            })
+        );
+
+        let response = update_layout_block(
+            admin(),
+            State(pool.clone()),
+            Path(block.id.clone()),
+            Json(UpdateLayoutBlockRequest {
+                name: Some("promo".to_string()),
+                block: None,
+            }),
+        )
+        .await
+        .expect("rename");
+        let (status, body) = json_body(response).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["pages"], json!(["grundlagen", "praxis"]));
+
+        let unused = create_block(&pool, "unused", json!({ "type": "text", "content": "y" })).await;
+        assert_eq!(
+            delete_layout_block(admin(), State(pool.clone()), Path(unused.id))
+                .await
+                .expect("delete unused")
+                .status(),
+            StatusCode::NO_CONTENT
+        );
+    }
+}
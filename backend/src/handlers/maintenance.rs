@@ -0,0 +1,66 @@
+//! Maintenance mode toggle.
+//!
+//! See [`crate::middleware::maintenance`] for what the flag blocks.
+
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    middleware::maintenance,
+    models::{MaintenanceStatus, UpdateMaintenanceRequest},
+    security::auth,
+};
+use axum::{extract::State, Json};
+
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+pub async fn get_maintenance(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<MaintenanceStatus>, AppError> {
+    ensure_admin(&claims)?;
+    maintenance::load(&pool)
+        .await
+        .map(Json)
+        .map_err(|err| AppError::internal("Failed to access maintenance mode", err))
+}
+
+/// Turns maintenance mode on or off. A blank message falls back to the
+/// settings section's `maintenanceMessage`.
+pub async fn update_maintenance(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Json(payload): Json<UpdateMaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>, AppError> {
+    ensure_admin(&claims)?;
+
+    let message = payload
+        .message
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty());
+    if message
+        .as_ref()
+        .is_some_and(|message| message.chars().count() > MAX_MESSAGE_LENGTH)
+    {
+        return Err(AppError::invalid_field(
+            "message",
+            format!(
+                "Maintenance message must be at most {} characters",
+                MAX_MESSAGE_LENGTH
+            ),
+        ));
+    }
+
+    let status = MaintenanceStatus {
+        enabled: payload.enabled,
+        message,
+    };
+    maintenance::set(&pool, &status)
+        .await
+        .map_err(|err| AppError::internal("Failed to access maintenance mode", err))?;
+    tracing::info!(
+        user = %claims.sub,
+        enabled = status.enabled,
+        "Maintenance mode updated"
+    );
+    Ok(Json(status))
+}
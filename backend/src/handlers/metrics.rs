@@ -0,0 +1,33 @@
+//! Runtime counters of this process.
+
+use crate::{
+    middleware::load_shed::{LoadBudgetStats, LoadBudgets},
+    models::ErrorResponse,
+    security::auth,
+};
+use axum::{http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    /// Concurrency budgets with the requests they turned away.
+    pub load_shedding: Vec<LoadBudgetStats>,
+}
+
+pub async fn get_metrics(
+    claims: auth::Claims,
+    Extension(budgets): Extension<LoadBudgets>,
+) -> Result<Json<Metrics>, (StatusCode, Json<ErrorResponse>)> {
+    if claims.role != "admin" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Insufficient permissions".to_string(),
+            }),
+        ));
+    }
+
+    Ok(Json(Metrics {
+        load_shedding: budgets.stats(),
+    }))
+}
@@ -44,6 +44,21 @@
  * - `GET /api/content` - List all content sections
  * - `GET /api/content/{section}` - Get specific section content
  * - `PUT /api/content/{section}` - Update section content (admin)
+ * - `PATCH /api/content/{section}` - Apply a JSON Merge Patch to a section (admin)
+ * - `GET /api/content/{section}/history` - List archived versions (admin)
+ * - `GET /api/content/{section}/history/{version}` - Get an archived version (admin)
+ * - `POST /api/content/{section}/rollback/{version}` - Restore an archived version (admin)
+ * - `POST /api/admin/content/{section}/diff` - Preview changes without saving (admin)
+ * - `GET /api/admin/content-sections` - List built-in and registered sections (admin)
+ * - `POST /api/admin/content-sections` - Register a custom section (admin)
+ * - `GET|PUT|DELETE /api/admin/content-sections/{name}` - Manage a custom section (admin)
+ * - `GET /api/admin/content/export` - Export all sections as one document (admin)
+ * - `POST /api/admin/content/import` - Import an exported document, optionally as a dry run (admin)
+ * - `GET /api/admin/content/health` - Report broken internal links, uploads and anchors (admin)
+ *
+ * ### [`bootstrap`](mod@bootstrap)
+ * **First-Paint Bundle**
+ * - `GET /api/public/bootstrap` - Site content, navigation and tutorial summaries in one response
  *
  * ### [`site_pages`](mod@site_pages)
  * **Static Page Management**
@@ -51,23 +66,88 @@
  * - `GET /api/pages/{id}` - Get specific page (admin)
  * - `POST /api/pages` - Create new page (admin)
  * - `PUT /api/pages/{id}` - Update page (admin)
- * - `DELETE /api/pages/{id}` - Delete page (admin)
+ * - `DELETE /api/pages/{id}` - Move page to the trash (admin)
+ * - `POST /api/pages/{id}/restore` - Restore a trashed page (admin)
+ * - `DELETE /api/pages/{id}/purge` - Permanently delete a trashed page (admin)
+ *
+ * ### [`layout_blocks`](mod@layout_blocks)
+ * **Reusable Layout Blocks**
+ * - `GET /api/admin/blocks` - List library blocks (admin)
+ * - `GET /api/admin/blocks/{id}` - Get specific block (admin)
+ * - `POST /api/admin/blocks` - Create block (admin)
+ * - `PUT /api/admin/blocks/{id}` - Update block (admin)
+ * - `DELETE /api/admin/blocks/{id}` - Delete an unreferenced block (admin)
  *
  * ### [`site_posts`](mod@site_posts)
  * **Blog Post Management**
  * - `GET /api/pages/{page_id}/posts` - List posts for page (admin)
  * - `GET /api/posts/{id}` - Get specific post (admin)
  * - `POST /api/pages/{page_id}/posts` - Create post (admin)
+ * - `POST /api/pages/{page_id}/posts/bulk-publish` - Publish/unpublish posts in bulk (admin)
  * - `PUT /api/posts/{id}` - Update post (admin)
  * - `DELETE /api/posts/{id}` - Delete post (admin)
+ * - `POST /api/posts/{id}/move` - Move post to another page (admin)
+ *
+ * ### [`upload`](mod@upload)
+ * **Media Uploads**
+ * - `POST /api/upload` - Upload one or more images, with per-file results (admin)
+ * - `POST /api/upload/file` - Upload a downloadable PDF, archive or text file (admin)
+ * - `GET /api/admin/uploads` - Paginated media library with name filter (admin)
+ * - `PUT /api/admin/uploads/{id}` - Set alt text and caption (admin)
+ * - `DELETE /api/admin/uploads/{id}` - Delete an unreferenced upload, or any with `force=true` (admin)
+ * - `GET /api/admin/uploads/orphans` - Report uploads no content links to (admin)
+ * - `POST /api/admin/uploads/orphans?delete=true` - Delete orphans older than the grace period (admin)
+ * - `GET /api/admin/uploads/quota` - Today's upload usage against the daily quota (admin)
+ *
+ * ### [`upload_sessions`](mod@upload_sessions)
+ * **Resumable Uploads**
+ * - `POST /api/upload/sessions` - Open a session for a file of known size and type (admin)
+ * - `GET /api/upload/sessions/{id}` - List the chunks received so far (admin)
+ * - `PUT /api/upload/sessions/{id}/chunks/{index}` - Store one chunk; resending replaces it (admin)
+ * - `POST /api/upload/sessions/{id}/complete` - Verify the SHA-256 and store the assembled file (admin)
+ *
+ * ### [`content_bundles`](mod@content_bundles)
+ * **Content Bundles**
+ * - `GET /api/admin/export` - Pages, posts, tutorials and site content as one bundle, users and comments on request (admin)
+ * - `POST /api/admin/import` - Validate and import a bundle in one transaction, `dry_run=true` to preview, `continue_on_error=true` to keep the rows the database accepts, or `{ "url", "sha256" }` to fetch it (admin)
+ * - `POST /api/admin/rebuild-derived` - Rebuild the topic index, search index and post word counts from the content (admin)
+ *
+ * ### [`maintenance`](mod@maintenance)
+ * **Maintenance Mode**
+ * - `GET /api/admin/maintenance` - Current flag and message (admin)
+ * - `POST /api/admin/maintenance` - Turn maintenance mode on or off, with an optional message (admin)
+ *
+ * ### [`ip_bans`](mod@ip_bans)
+ * **IP Ban List**
+ * - `GET /api/admin/bans/ip` - List banned addresses and ranges (admin)
+ * - `POST /api/admin/bans/ip` - Ban an address or CIDR range, optionally until `expires_at` (admin)
+ * - `DELETE /api/admin/bans/ip?ip=` - Lift a ban (admin)
+ *
+ * ### [`jobs`](mod@jobs)
+ * **Scheduled Jobs**
+ * - `GET /api/admin/jobs` - Background jobs with their latest run, duration and error (admin)
+ *
+ * ### [`metrics`](mod@metrics)
+ * **Runtime Counters**
+ * - `GET /api/admin/metrics` - Concurrency budgets and how many requests each shed (admin)
+ *
+ * ### [`health`](mod@health)
+ * **Probes**
+ * - `GET /api/health` - Liveness: the process is serving
+ * - `GET /api/health/ready` - Readiness: migrations done, database answering, critical tasks running
  *
  * ## Public Endpoints
  *
  * These are automatically accessible without authentication:
  * - `GET /api/public/pages/{slug}` - Get published page by slug
+ * - `GET /api/public/pages/{slug}/archive` - Monthly post counts for a page
  * - `GET /api/public/pages/{slug}/posts/{post_slug}` - Get published post
+ * - `GET /api/public/posts` - Latest published posts across all pages
  * - `GET /api/public/navigation` - Get site navigation structure
  * - `GET /api/public/published-pages` - List published page slugs
+ * - `GET /api/public/uploads/{id}/meta` - Alt text and caption of an upload, by id or file name
+ * - `POST /api/csp-report` - Log Content-Security-Policy violation reports
+ * - `GET /robots.txt` - Crawler rules, following the `indexingEnabled` setting
  *
  * # Security Features
  *
@@ -109,26 +189,62 @@
  * ```json
  * {
  *   "error": "Human-readable error message",
- *   "details": "Additional error context (optional)"
+ *   "code": "validation_failed",  // Stable error kind
+ *   "field": "slug",              // Field at fault (optional)
+ *   "request_id": "..."           // Id to quote when reporting (optional)
  * }
  * ```
  *
+ * The tutorial, page and authentication endpoints answer with a `code`, one
+ * of `not_found`, `validation_failed`, `conflict`, `unauthorized`,
+ * `forbidden`, `rate_limited` and `internal_error` (see [`crate::error`]).
+ * Clients should branch on it rather than on the message.
+ *
  * ## List Responses
  * ```json
  * {
  *   "items": [ ... ],     // Array of items
- *   "total": 42,          // Total count (when paginated)
- *   "hasMore": true       // Pagination indicator
+ *   "total": 42,          // Rows matching the filters, across all pages
+ *   "limit": 50,          // Page size after clamping
+ *   "offset": 0,
+ *   "has_more": true,     // Whether a later page exists
+ *   "next_cursor": "..."  // Where the next page starts (cursor lists only)
  * }
  * ```
  *
+ * Tutorials, tutorial search, comments, admin pages, the posts of a page and
+ * `GET /api/public/posts` take `limit` (1-100, default 50) and `offset` (see
+ * [`crate::models::pagination`]). The tutorial, search and comment lists
+ * still answer with a bare array for `?envelope=false`, until the next
+ * release. Comments and `GET /api/public/posts` also take the `next_cursor`
+ * of a page as `?cursor=`, which keeps newest-first pages stable while rows
+ * are added; the other lists refuse a cursor.
+ *
+ * # Timestamps
+ *
+ * `created_at`, `updated_at`, `published_at` and `deleted_at` of tutorials,
+ * pages, posts, comments, content sections and users are RFC 3339 in UTC,
+ * e.g. `2024-05-01T10:00:00Z`, whichever form the row was stored in.
+ *
  * # Rate Limiting
  *
- * Different endpoints have different rate limits:
- * - Authentication endpoints: 5 requests per second
- * - Content creation endpoints: 3 requests per second
- * - Read-only endpoints: No rate limiting
- * - Public search endpoints: 10 requests per second
+ * Per-IP limits, configurable through `RATE_LIMIT_{LOGIN,ADMIN,PUBLIC}_{PER_SECOND,BURST}`
+ * (see [`crate::middleware::rate_limit`]):
+ * - Authentication endpoints: 1 request per second, burst 5
+ * - Admin endpoints: 1 request per second, burst 3
+ * - Public read and search endpoints: 10 requests per second, burst 30
+ * - Uploaded files: No rate limiting
+ *
+ * Rate limited responses are 429 with `Retry-After` and `X-RateLimit-Reset`.
+ *
+ * # Timeouts
+ *
+ * Requests running longer than `REQUEST_TIMEOUT_SECONDS` (default 30) are
+ * answered with 503 and the usual error body. Uploads get
+ * `UPLOAD_REQUEST_TIMEOUT_SECONDS` (default 300), bundle imports and exports
+ * `IMPORT_REQUEST_TIMEOUT_SECONDS` (default 300) and frontend pages
+ * `FRONTEND_PROXY_TIMEOUT_SECONDS` (default 60); see
+ * [`crate::middleware::timeout`].
  *
  * # Performance Optimizations
  *
@@ -139,19 +255,34 @@
  * - Async/await for high concurrency
  */
 // HTTP Handler Modules - Organized by Domain
-
 // Core System Handlers
 pub mod auth; // Authentication and authorization
+pub mod bootstrap; // First-paint bundle for the SPA
 pub mod search; // Full-text search functionality
 
 // Content Management Handlers
 pub mod tutorials;
 pub mod upload;
+pub mod upload_orphans; // Unreferenced upload report
+pub mod upload_quota; // Daily upload quota per user
+pub mod upload_sessions; // Resumable chunked uploads
 // Tutorial CRUD operations
 pub mod comments; // Comment system management
 
 // Site Content Handlers
+pub mod content_bundles; // Whole-site content import and export
+pub mod content_health; // Broken internal reference report
+pub mod content_sections; // Registry of custom content sections
+pub mod csp_report; // CSP violation report sink
+pub mod fallback; // JSON 404 for unknown API paths
 pub mod frontend_proxy;
+pub mod health; // Liveness and readiness probes
+pub mod ip_bans; // Runtime IP ban list
+pub mod jobs; // Scheduled job status
+pub mod layout_blocks; // Reusable page layout blocks
+pub mod maintenance; // Maintenance mode toggle
+pub mod metrics; // Runtime counters
+pub mod robots; // robots.txt from the site settings
 pub mod site_content; // Dynamic site content sections
 pub mod site_pages; // Static page management
 pub mod site_posts; // Blog post management // Frontend proxy for server-side injection
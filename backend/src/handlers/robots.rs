@@ -0,0 +1,92 @@
+//! `robots.txt` from the site settings.
+//!
+//! With `indexingEnabled` (the default) crawlers may index everything but the
+//! API, the admin area and private uploads, and are pointed at the sitemap
+//! when `PUBLIC_BASE_URL` is set. Turning it off, e.g. on staging, disallows
+//! everything. The settings cache is dropped whenever the section is saved,
+//! so a change shows up once the response's own short cache has expired.
+
+use crate::db;
+use crate::settings;
+use crate::utils::conditional::PUBLIC_SHORT_CACHE;
+use crate::utils::public_url;
+use axum::{
+    extract::State,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    response::IntoResponse,
+};
+
+/// Never meant for crawlers, whatever the setting.
+const ALWAYS_DISALLOWED: [&str; 3] = ["/api/", "/admin", "/uploads/private/"];
+
+fn render(indexing_enabled: bool, base_url: Option<&str>) -> String {
+    let mut body = String::from("User-agent: *\n");
+    if !indexing_enabled {
+        body.push_str("Disallow: /\n");
+        return body;
+    }
+
+    body.push_str("Allow: /\n");
+    for path in ALWAYS_DISALLOWED {
+        body.push_str(&format!("Disallow: {}\n", path));
+    }
+    if let Some(base_url) = base_url {
+        body.push_str(&format!("\nSitemap: {}/sitemap.xml\n", base_url));
+    }
+    body
+}
+
+pub async fn robots_txt(State(pool): State<db::DbPool>) -> impl IntoResponse {
+    let settings = settings::get(&pool).await;
+    (
+        [
+            (CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (CACHE_CONTROL, PUBLIC_SHORT_CACHE),
+        ],
+        render(settings.indexing_enabled, public_url::public_base_url()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexing_allowed_with_sitemap() {
+        assert_eq!(
+            render(true, Some("https://linux.example")),
+            "User-agent: *\n\
+             Allow: /\n\
+             Disallow: /api/\n\
+             Disallow: /admin\n\
+             Disallow: /uploads/private/\n\
+             \n\
+             Sitemap: https://linux.example/sitemap.xml\n"
+        );
+        assert!(!render(true, None).contains("Sitemap"));
+    }
+
+    #[test]
+    fn test_indexing_disabled_disallows_everything() {
+        assert_eq!(
+            render(false, Some("https://linux.example")),
+            "User-agent: *\nDisallow: /\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_served_as_cacheable_plain_text() {
+        let pool = db::pool::create_test_pool().await;
+        let response = robots_txt(State(pool)).await.into_response();
+
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(response.headers()[CACHE_CONTROL], PUBLIC_SHORT_CACHE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"User-agent: *\n"));
+    }
+}
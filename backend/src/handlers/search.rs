@@ -10,7 +10,7 @@
 //! # Search Features
 //! - Full-text search across title, description, content, and topics
 //! - Topic-based filtering (optional)
-//! - Pagination support (default 20 results, configurable)
+//! - Pagination envelope with the total number of matches (default 50 results)
 //! - Ranked results (FTS5 BM25 ranking algorithm)
 //! - Query sanitization to prevent FTS5 syntax errors
 //!
@@ -26,7 +26,11 @@
 //! - Automatic index updates via triggers on tutorial changes
 //! - Result limit prevents excessive data transfer
 
-use crate::{db::DbPool, models::*};
+use crate::{
+    db::DbPool,
+    models::*,
+    repositories::{self, common::escape_like_pattern},
+};
 use axum::{
     extract::{Query, State},
     http::StatusCode,
@@ -41,13 +45,6 @@ pub struct SearchQuery {
 
     #[serde(default)]
     topic: Option<String>,
-
-    #[serde(default = "default_limit")]
-    limit: i64,
-}
-
-fn default_limit() -> i64 {
-    20
 }
 
 pub fn sanitize_fts_query(raw: &str) -> Result<String, String> {
@@ -103,24 +100,11 @@ pub fn sanitize_fts_query(raw: &str) -> Result<String, String> {
     }
 }
 
-fn escape_like_pattern(value: &str) -> String {
-    let mut escaped = String::with_capacity(value.len());
-    for ch in value.chars() {
-        match ch {
-            '%' | '_' | '\\' => {
-                escaped.push('\\');
-                escaped.push(ch);
-            }
-            _ => escaped.push(ch),
-        }
-    }
-    escaped
-}
-
 pub async fn search_tutorials(
     State(pool): State<DbPool>,
+    page: PageParams,
     Query(params): Query<SearchQuery>,
-) -> Result<Json<Vec<TutorialResponse>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Listing<TutorialResponse>>, (StatusCode, Json<ErrorResponse>)> {
     if params.q.trim().is_empty() {
         return Err((
             StatusCode::BAD_REQUEST,
@@ -139,8 +123,6 @@ pub async fn search_tutorials(
         ));
     }
 
-    let limit = params.limit.min(100).max(1);
-
     let search_query = sanitize_fts_query(params.q.trim())
         .map_err(|err| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: err })))?;
 
@@ -153,37 +135,20 @@ pub async fn search_tutorials(
         }
     });
 
-    let tutorials = if let Some(pattern) = topic_pattern {
-        sqlx::query_as::<_, Tutorial>(
-            r#"
-            SELECT t.* FROM tutorials t
-            INNER JOIN tutorials_fts fts ON t.id = fts.tutorial_id
-            WHERE fts MATCH ?
-            AND t.topics LIKE ? ESCAPE '\\'
-            ORDER BY bm25(fts)
-            LIMIT ?
-            "#,
-        )
-        .bind(&search_query)
-        .bind(&pattern)
-        .bind(limit)
-        .fetch_all(&pool)
-        .await
-    } else {
-        sqlx::query_as::<_, Tutorial>(
-            r#"
-            SELECT t.* FROM tutorials t
-            INNER JOIN tutorials_fts fts ON t.id = fts.tutorial_id
-            WHERE fts MATCH ?
-            ORDER BY bm25(fts)
-            LIMIT ?
-            "#,
-        )
-        .bind(&search_query)
-        .bind(limit)
-        .fetch_all(&pool)
-        .await
-    }
+    let (tutorials, total) = tokio::try_join!(
+        repositories::tutorials::search_tutorials(
+            &pool,
+            &search_query,
+            topic_pattern.as_deref(),
+            page.limit,
+            page.offset,
+        ),
+        repositories::tutorials::count_search_matches(
+            &pool,
+            &search_query,
+            topic_pattern.as_deref(),
+        ),
+    )
     .map_err(|e| {
         tracing::error!("Search error: {}", e);
         (
@@ -208,7 +173,9 @@ pub async fn search_tutorials(
         responses.push(response);
     }
 
-    Ok(Json(responses))
+    Ok(Json(
+        Paginated::new(responses, total, page.limit, page.offset).into_listing(&page),
+    ))
 }
 
 pub async fn get_all_topics(
@@ -1,176 +1,115 @@
 use crate::{
     security::auth, db,
+    error::ensure_admin,
     models::{
-        ErrorResponse, SiteContentListResponse, SiteContentResponse, UpdateSiteContentRequest,
+        ErrorResponse, SiteContent, SiteContentBundle, SiteContentDiffResponse, SiteContentExportEntry, SiteContentHistoryResponse,
+        SiteContentImportReport, SiteContentListResponse, SiteContentResponse,
+        SiteContentVersionResponse, UpdateSiteContentRequest,
     },
     repositories,
+    settings,
+    utils::{
+        conditional, json_diff,
+        timestamps::{format_timestamp, parse_timestamp},
+    },
+    validation::content,
 };
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header::IF_UNMODIFIED_SINCE, HeaderMap, StatusCode},
+    response::Response,
     Json,
 };
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use serde_json::Value;
-use std::collections::HashSet;
-
-const MAX_CONTENT_BYTES: usize = 200_000;
+use std::collections::{HashMap, HashSet};
 
-fn allowed_sections() -> &'static HashSet<&'static str> {
-    use std::sync::OnceLock;
+const DEFAULT_HISTORY_LIMIT: i64 = 20;
+const MAX_HISTORY_LIMIT: i64 = 100;
 
-    static ALLOWED: OnceLock<HashSet<&'static str>> = OnceLock::new();
-    ALLOWED.get_or_init(|| {
-        [
-            "hero",
-            "tutorial_section",
-            "header",
-            "footer",
-            "site_meta",
-            "stats",
-            "cta_section",
-            "settings",
-            "login",
-        ]
-        .into_iter()
-        .collect()
-    })
+fn history_error(section: &str, err: sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("Failed to load history for site content '{}': {}", section, err);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "Failed to load content history".to_string(),
+        }),
+    )
 }
 
-fn validate_section(section: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    if allowed_sections().contains(section) {
-        Ok(())
-    } else {
-        Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("Unknown content section '{section}'"),
-            }),
-        ))
-    }
+/// Structure rules that apply to a section's content.
+enum SectionRules {
+    /// Built-in section, checked by [`content::validate_content_structure`].
+    BuiltIn,
+    /// Section from the `content_sections` registry, optionally with a schema.
+    Custom(Option<Value>),
 }
 
-fn validate_content_structure(
+/// Accepts built-in sections and sections registered at runtime.
+async fn validate_section(
+    pool: &db::DbPool,
     section: &str,
-    content: &Value,
-) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    let result = match section {
-        "hero" => validate_hero_structure(content),
-        "tutorial_section" => validate_tutorial_section_structure(content),
-        "header" => validate_header_structure(content),
-        "footer" => validate_footer_structure(content),
-        "settings" => validate_settings_structure(content),
-        "stats" => Ok(()),
-        "cta_section" => Ok(()),
-        "login" => validate_login_structure(content),
-        _ => Ok(()),
-    };
-
-    result.map_err(|err| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Invalid structure for section '{section}': {err}"),
-            }),
-        )
-    })
-}
-
-fn validate_hero_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    if !obj.contains_key("title") || !obj.contains_key("features") {
-        return Err("Missing required fields 'title' or 'features'");
-    }
-    if !obj.get("features").map(|v| v.is_array()).unwrap_or(false) {
-        return Err("Field 'features' must be an array");
-    }
-    Ok(())
-}
-
-fn validate_tutorial_section_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    if !obj.contains_key("title") || !obj.contains_key("description") {
-        return Err("Missing required fields 'title' or 'description'");
+) -> Result<SectionRules, (StatusCode, Json<ErrorResponse>)> {
+    if content::is_builtin_section(section) {
+        return Ok(SectionRules::BuiltIn);
     }
-    Ok(())
-}
 
-fn validate_header_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    if !obj.contains_key("brand") || !obj.contains_key("navItems") {
-        return Err("Missing required fields 'brand' or 'navItems'");
-    }
-    if !obj
-        .get("navItems")
-        .and_then(|items| items.as_array())
-        .map(|items| {
-            items.iter().all(|item| {
-                let has_id_label = item.get("id").is_some() && item.get("label").is_some();
-                // Ensure at least one target property exists and is not empty
-                let has_target = item.get("slug").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false)
-                    || item.get("href").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false)
-                    || item.get("path").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false)
-                    || item.get("value").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false)
-                    || item.get("type").and_then(|t| t.as_str()).map(|s| s == "section").unwrap_or(false);
-                has_id_label && has_target
-            })
-        })
-        .unwrap_or(false)
-    {
-        return Err("Each navigation item must include 'id', 'label', and a target ('slug', 'href', 'path', 'value', or type='section')");
-    }
-    Ok(())
-}
+    let registered = repositories::content_sections::get_content_section(pool, section)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to look up content section '{}': {}", section, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to load content section".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("Unknown content section '{section}'"),
+                }),
+            )
+        })?;
 
-fn validate_footer_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    if !obj.contains_key("brand") || !obj.contains_key("quickLinks") {
-        return Err("Missing required fields 'brand' or 'quickLinks'");
-    }
-    Ok(())
+    let schema = registered
+        .schema_json
+        .as_deref()
+        .map(serde_json::from_str::<Value>)
+        .transpose()
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to parse stored section schema: {err}"),
+                }),
+            )
+        })?;
+    Ok(SectionRules::Custom(schema))
 }
 
-fn validate_settings_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    // We expect at least pdfEnabled, but we can be lenient or strict.
-    // Let's be strict about the type if it exists.
-    if let Some(val) = obj.get("pdfEnabled") {
-        if !val.is_boolean() {
-            return Err("Field 'pdfEnabled' must be a boolean");
+/// Size and structure checks shared by every write path.
+fn validate_section_content(
+    section: &str,
+    rules: &SectionRules,
+    content: &Value,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    content::validate_content_size(content)
+        .map_err(|error| (StatusCode::PAYLOAD_TOO_LARGE, Json(ErrorResponse { error })))?;
+    match rules {
+        SectionRules::BuiltIn => content::validate_content_structure(section, content),
+        SectionRules::Custom(schema) => {
+            content::validate_custom_content(section, schema.as_ref(), content)
         }
     }
-    Ok(())
-}
-
-fn validate_login_structure(content: &Value) -> Result<(), &'static str> {
-    let obj = content.as_object().ok_or("Expected JSON object")?;
-    // We can be lenient, but let's check for at least one expected field if we want strictness.
-    // For now, just ensuring it's an object is enough, or check for 'title'.
-    if !obj.contains_key("title") {
-        return Err("Missing required field 'title'");
-    }
-    Ok(())
-}
-
-fn validate_content_size(content: &Value) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    match serde_json::to_string(content) {
-        Ok(serialized) if serialized.len() <= MAX_CONTENT_BYTES => Ok(()),
-        Ok(_) => Err((
-            StatusCode::PAYLOAD_TOO_LARGE,
-            Json(ErrorResponse {
-                error: format!("Content too large (max {MAX_CONTENT_BYTES} bytes)"),
-            }),
-        )),
-        Err(err) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Invalid JSON content: {err}"),
-            }),
-        )),
-    }
+    .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
 }
 
-fn map_record(
-    record: crate::models::SiteContent,
+pub(crate) fn map_record(
+    record: SiteContent,
 ) -> Result<SiteContentResponse, (StatusCode, Json<ErrorResponse>)> {
     let content: Value = serde_json::from_str(&record.content_json).map_err(|err| {
         (
@@ -185,13 +124,15 @@ fn map_record(
         section: record.section,
         content,
         updated_at: record.updated_at,
+        updated_by: record.updated_by,
+        revision: record.revision,
     })
 }
 
-pub async fn list_site_content(
-    State(pool): State<db::DbPool>,
-) -> Result<Json<SiteContentListResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let records = repositories::content::fetch_all_site_content(&pool)
+pub(crate) async fn fetch_records(
+    pool: &db::DbPool,
+) -> Result<Vec<SiteContent>, (StatusCode, Json<ErrorResponse>)> {
+    repositories::content::fetch_all_site_content(pool)
         .await
         .map_err(|err| {
             tracing::error!("Failed to load site content: {}", err);
@@ -201,23 +142,14 @@ pub async fn list_site_content(
                     error: "Failed to load site content".to_string(),
                 }),
             )
-        })?;
-
-    let mut items = Vec::with_capacity(records.len());
-    for record in records {
-        items.push(map_record(record)?);
-    }
-
-    Ok(Json(SiteContentListResponse { items }))
+        })
 }
 
-pub async fn get_site_content(
-    State(pool): State<db::DbPool>,
-    Path(section): Path<String>,
-) -> Result<Json<SiteContentResponse>, (StatusCode, Json<ErrorResponse>)> {
-    validate_section(&section)?;
-
-    let record = repositories::content::fetch_site_content_by_section(&pool, &section)
+async fn fetch_record(
+    pool: &db::DbPool,
+    section: &str,
+) -> Result<SiteContent, (StatusCode, Json<ErrorResponse>)> {
+    repositories::content::fetch_site_content_by_section(pool, section)
         .await
         .map_err(|err| {
             tracing::error!("Failed to load site content '{}': {}", section, err);
@@ -235,9 +167,97 @@ pub async fn get_site_content(
                     error: format!("Content section '{section}' not found"),
                 }),
             )
-        })?;
+        })
+}
 
-    Ok(Json(map_record(record)?))
+/// ETag for a set of rows. Every write bumps `updated_at`, but its resolution
+/// is one second, so the stored JSON is hashed in as well.
+fn records_etag(records: &[SiteContent]) -> String {
+    let updated_at: Vec<String> = records
+        .iter()
+        .map(|record| format_timestamp(&record.updated_at))
+        .collect();
+    conditional::weak_etag(records.iter().zip(&updated_at).flat_map(|(record, updated_at)| {
+        [
+            record.section.as_str(),
+            updated_at.as_str(),
+            record.content_json.as_str(),
+        ]
+    }))
+}
+
+pub async fn list_site_content(
+    State(pool): State<db::DbPool>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let records = fetch_records(&pool).await?;
+    let etag = records_etag(&records);
+    if conditional::if_none_match(&headers, &etag) {
+        return Ok(conditional::respond(&headers, &etag, conditional::PUBLIC_SHORT_CACHE, ()));
+    }
+
+    let mut items = Vec::with_capacity(records.len());
+    for record in records {
+        items.push(map_record(record)?);
+    }
+
+    Ok(conditional::respond(
+        &headers,
+        &etag,
+        conditional::PUBLIC_SHORT_CACHE,
+        SiteContentListResponse { items },
+    ))
+}
+
+pub async fn get_site_content(
+    State(pool): State<db::DbPool>,
+    Path(section): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    validate_section(&pool, &section).await?;
+    let record = fetch_record(&pool, &section).await?;
+    let etag = records_etag(std::slice::from_ref(&record));
+
+    Ok(conditional::respond(
+        &headers,
+        &etag,
+        conditional::PUBLIC_SHORT_CACHE,
+        map_record(record)?,
+    ))
+}
+
+/// Validates and stores a section, archiving the content it replaces.
+async fn save_site_content(
+    pool: &db::DbPool,
+    claims: &auth::Claims,
+    section: &str,
+    content: &Value,
+) -> Result<SiteContentResponse, (StatusCode, Json<ErrorResponse>)> {
+    let rules = validate_section(pool, section).await?;
+    validate_section_content(section, &rules, content)?;
+
+    let record = repositories::content::upsert_site_content(
+        pool,
+        section,
+        content,
+        &claims.sub,
+        repositories::content::history_retention(),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!("Failed to update site content '{}': {}", section, err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to update site content".to_string(),
+            }),
+        )
+    })?;
+
+    if section == settings::SECTION {
+        settings::invalidate();
+    }
+    map_record(record)
 }
 
 pub async fn update_site_content(
@@ -246,112 +266,1034 @@ pub async fn update_site_content(
     Path(section): Path<String>,
     Json(payload): Json<UpdateSiteContentRequest>,
 ) -> Result<Json<SiteContentResponse>, (StatusCode, Json<ErrorResponse>)> {
-    if claims.role != "admin" {
-        return Err((
-            StatusCode::FORBIDDEN,
+    ensure_admin(&claims)?;
+
+    let response = save_site_content(&pool, &claims, &section, &payload.content).await?;
+    Ok(Json(response))
+}
+
+/// Validates proposed content and diffs it against the stored section
+/// without writing anything.
+pub async fn diff_site_content(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(section): Path<String>,
+    Json(payload): Json<UpdateSiteContentRequest>,
+) -> Result<Json<SiteContentDiffResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    let rules = validate_section(&pool, &section).await?;
+    validate_section_content(&section, &rules, &payload.content)?;
+
+    let (current, updated_at, revision) = match fetch_record(&pool, &section).await {
+        Ok(record) => {
+            let (updated_at, revision) = (record.updated_at, record.revision);
+            (map_record(record)?.content, Some(updated_at), Some(revision))
+        }
+        Err((StatusCode::NOT_FOUND, _)) => (Value::Null, None, None),
+        Err(err) => return Err(err),
+    };
+
+    Ok(Json(SiteContentDiffResponse {
+        section,
+        updated_at,
+        revision,
+        diff: json_diff::diff(&current, &payload.content),
+    }))
+}
+
+/// Applies an RFC 7386 JSON Merge Patch to `target`.
+fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                apply_merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+fn precondition_failed(section: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::PRECONDITION_FAILED,
+        Json(ErrorResponse {
+            error: format!("Content section '{section}' was modified in the meantime"),
+        }),
+    )
+}
+
+/// Whether the stored timestamp is newer than an `If-Unmodified-Since` date.
+/// Unparseable values are ignored, as RFC 9110 asks.
+fn modified_since(headers: &HeaderMap, updated_at: DateTime<Utc>) -> bool {
+    let Some(since) = headers
+        .get(IF_UNMODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+    else {
+        return false;
+    };
+
+    updated_at > since.with_timezone(&Utc)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ContentPatchQuery {
+    /// `updated_at` the client last saw, as RFC 3339 or in the legacy
+    /// `YYYY-MM-DD HH:MM:SS` form. It only has a resolution of one second,
+    /// so it cannot tell apart writes within the same second.
+    pub expected_updated_at: Option<String>,
+    /// `revision` the client last saw; unlike the timestamp it changes on
+    /// every write.
+    pub expected_revision: Option<i64>,
+}
+
+/// Applies a JSON Merge Patch to a section. The merged document goes through
+/// the same validation as a full update, and the write only happens if the
+/// section was not changed since it was read.
+pub async fn patch_site_content(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(section): Path<String>,
+    Query(query): Query<ContentPatchQuery>,
+    headers: HeaderMap,
+    Json(patch): Json<Value>,
+) -> Result<Json<SiteContentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let rules = validate_section(&pool, &section).await?;
+    let record = fetch_record(&pool, &section).await?;
+    let expected = query.expected_updated_at.as_deref();
+    if expected.is_some_and(|expected| parse_timestamp(expected) != Some(record.updated_at))
+        || query
+            .expected_revision
+            .is_some_and(|expected| expected != record.revision)
+        || modified_since(&headers, record.updated_at)
+    {
+        return Err(precondition_failed(&section));
+    }
+
+    let read_revision = record.revision;
+    let mut content = map_record(record)?.content;
+    apply_merge_patch(&mut content, &patch);
+    validate_section_content(&section, &rules, &content)?;
+
+    let record = repositories::content::upsert_site_content_if_unchanged(
+        &pool,
+        &section,
+        &content,
+        &claims.sub,
+        repositories::content::history_retention(),
+        read_revision,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!("Failed to patch site content '{}': {}", section, err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to update site content".to_string(),
+            }),
+        )
+    })?
+    .ok_or_else(|| precondition_failed(&section))?;
+
+    if section == settings::SECTION {
+        settings::invalidate();
+    }
+    Ok(Json(map_record(record)?))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ContentHistoryQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub async fn list_site_content_history(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(section): Path<String>,
+    Query(query): Query<ContentHistoryQuery>,
+) -> Result<Json<SiteContentHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    validate_section(&pool, &section).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (items, total) =
+        repositories::content::list_site_content_history(&pool, &section, limit, offset)
+            .await
+            .map_err(|err| history_error(&section, err))?;
+
+    Ok(Json(SiteContentHistoryResponse {
+        section,
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+async fn load_version(
+    pool: &db::DbPool,
+    section: &str,
+    version: i64,
+) -> Result<SiteContentVersionResponse, (StatusCode, Json<ErrorResponse>)> {
+    validate_section(pool, section).await?;
+
+    let record = repositories::content::get_site_content_version(pool, section, version)
+        .await
+        .map_err(|err| history_error(section, err))?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("Version {version} of section '{section}' not found"),
+                }),
+            )
+        })?;
+
+    let content: Value = serde_json::from_str(&record.content_json).map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "Insufficient permissions".to_string(),
+                error: format!("Failed to parse stored content JSON: {err}"),
             }),
-        ));
+        )
+    })?;
+
+    Ok(SiteContentVersionResponse {
+        version: record.version,
+        section: record.section,
+        content,
+        updated_at: record.updated_at,
+        updated_by: record.updated_by,
+        archived_at: record.archived_at,
+    })
+}
+
+pub async fn get_site_content_version(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path((section, version)): Path<(String, i64)>,
+) -> Result<Json<SiteContentVersionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    Ok(Json(load_version(&pool, &section, version).await?))
+}
+
+/// Restores an archived version. It goes through the same validation as a
+/// regular save, and the content it replaces is archived in turn.
+pub async fn rollback_site_content(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path((section, version)): Path<(String, i64)>,
+) -> Result<Json<SiteContentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let version = load_version(&pool, &section, version).await?;
+    let response = save_site_content(&pool, &claims, &section, &version.content).await?;
+    Ok(Json(response))
+}
+
+/// Exports every stored section as a single document.
+pub async fn export_site_content(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<SiteContentBundle>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let mut site_content = Vec::new();
+    for record in fetch_records(&pool).await? {
+        let item = map_record(record)?;
+        site_content.push(SiteContentExportEntry {
+            section: item.section,
+            content: item.content,
+            updated_at: Some(format_timestamp(&item.updated_at)),
+        });
+    }
+
+    Ok(Json(SiteContentBundle { site_content }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ContentImportQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Skip unknown sections instead of rejecting the whole import.
+    #[serde(default)]
+    pub skip_unknown: bool,
+}
+
+fn import_error(
+    section: &str,
+    (status, Json(body)): (StatusCode, Json<ErrorResponse>),
+) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: format!("Section '{section}': {}", body.error),
+        }),
+    )
+}
+
+/// Imports an exported document. Every section is validated before anything
+/// is written, and all writes happen in one transaction. With `dry_run` the
+/// report is computed without touching the database.
+pub async fn import_site_content(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<ContentImportQuery>,
+    Json(bundle): Json<SiteContentBundle>,
+) -> Result<Json<SiteContentImportReport>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let mut report = SiteContentImportReport {
+        dry_run: query.dry_run,
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    let mut accepted = Vec::with_capacity(bundle.site_content.len());
+
+    for entry in bundle.site_content {
+        if !seen.insert(entry.section.clone()) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Section '{}' appears more than once", entry.section),
+                }),
+            ));
+        }
+
+        let rules = match validate_section(&pool, &entry.section).await {
+            Ok(rules) => rules,
+            Err((StatusCode::NOT_FOUND, _)) if query.skip_unknown => {
+                tracing::warn!("Skipping unknown content section '{}' on import", entry.section);
+                report.skipped.push(entry.section);
+                continue;
+            }
+            Err((StatusCode::NOT_FOUND, _)) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!("Unknown content section '{}'", entry.section),
+                    }),
+                ));
+            }
+            Err(err) => return Err(err),
+        };
+
+        validate_section_content(&entry.section, &rules, &entry.content)
+            .map_err(|err| import_error(&entry.section, err))?;
+        accepted.push((entry.section, entry.content));
     }
 
-    validate_section(&section)?;
-    validate_content_size(&payload.content)?;
-    validate_content_structure(&section, &payload.content)?;
+    let mut current = HashMap::new();
+    for record in fetch_records(&pool).await? {
+        let item = map_record(record)?;
+        current.insert(item.section, item.content);
+    }
+
+    let mut changes = Vec::with_capacity(accepted.len());
+    for (section, content) in accepted {
+        match current.get(&section) {
+            Some(existing) if *existing == content => report.unchanged.push(section),
+            Some(_) => {
+                report.updated.push(section.clone());
+                changes.push((section, content));
+            }
+            None => {
+                report.created.push(section.clone());
+                changes.push((section, content));
+            }
+        }
+    }
 
-    let record = repositories::content::upsert_site_content(&pool, &section, &payload.content)
+    if !query.dry_run && !changes.is_empty() {
+        repositories::content::import_site_content(
+            &pool,
+            &changes,
+            &claims.sub,
+            repositories::content::history_retention(),
+        )
         .await
         .map_err(|err| {
-            tracing::error!("Failed to update site content '{}': {}", section, err);
+            tracing::error!("Failed to import site content: {}", err);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
-                    error: "Failed to update site content".to_string(),
+                    error: "Failed to import site content".to_string(),
                 }),
             )
         })?;
+        if changes.iter().any(|(section, _)| section == settings::SECTION) {
+            settings::invalidate();
+        }
+    }
 
-    Ok(Json(map_record(record)?))
+    Ok(Json(report))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::SITE_CONTENT_SECTIONS;
     use serde_json::json;
 
-    #[test]
-    fn test_validate_header_structure_relaxed() {
-        // Case 1: Standard link with path
-        let content_standard = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "1", "label": "Blog", "path": "/blog" }
-            ]
-        });
-        assert!(validate_header_structure(&content_standard).is_ok());
-
-        // Case 2: Section link with type="section" (no explicit target field)
-        let content_section = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "home", "label": "Home", "type": "section" }
-            ]
-        });
-        assert!(validate_header_structure(&content_section).is_ok(), "Should accept type='section' without other target fields");
-
-        // Case 3: Link with 'value' field (e.g. from some frontend logic)
-        let content_value = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "2", "label": "About", "value": "about-us" }
-            ]
-        });
-        assert!(validate_header_structure(&content_value).is_ok(), "Should accept 'value' field as target");
-
-        // Case 4: Invalid item (missing target)
-        let content_invalid = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "3", "label": "Invalid" }
-            ]
-        });
-        assert!(validate_header_structure(&content_invalid).is_err());
+    #[tokio::test]
+    async fn test_every_seeded_section_is_readable_and_updatable() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let claims = auth::test_admin_claims();
+
+        for (section, content) in crate::db::seed::default_site_content() {
+            assert!(
+                SITE_CONTENT_SECTIONS.contains(&section),
+                "seeded section '{section}' is not an allowed section"
+            );
+
+            let stored = fetch_record(&pool, section)
+                .await
+                .and_then(map_record)
+                .unwrap_or_else(|_| panic!("GET {section}"));
+            assert_eq!(stored.content, content);
+
+            let Json(updated) = update_site_content(
+                claims.clone(),
+                State(pool.clone()),
+                Path(section.to_string()),
+                Json(UpdateSiteContentRequest { content: content.clone() }),
+            )
+            .await
+            .unwrap_or_else(|(_, err)| panic!("PUT {section}: {}", err.error));
+            assert_eq!(updated.content, content);
+        }
     }
 
-    #[test]
-    fn test_validate_login_structure() {
-        // Case 1: Valid login content
-        let content_valid = json!({
-            "title": "Login",
-            "subtitle": "Welcome back"
-        });
-        assert!(validate_login_structure(&content_valid).is_ok());
+    #[tokio::test]
+    async fn test_diff_validates_and_does_not_write() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let claims = auth::test_admin_claims();
+        let before = fetch_record(&pool, "site_meta").await.expect("record");
+        let mut proposed: Value = serde_json::from_str(&before.content_json).unwrap();
+        proposed["title"] = json!("Neu");
 
-        // Case 2: Missing title
-        let content_invalid = json!({
-            "subtitle": "Welcome back"
-        });
-        assert!(validate_login_structure(&content_invalid).is_err());
+        let Json(preview) = diff_site_content(
+            claims.clone(),
+            State(pool.clone()),
+            Path("site_meta".to_string()),
+            Json(UpdateSiteContentRequest { content: proposed }),
+        )
+        .await
+        .expect("diff");
+        assert_eq!(preview.updated_at, Some(before.updated_at));
+        assert_eq!(preview.diff.changes.len(), 1);
+        assert_eq!(preview.diff.changes[0].path, "/title");
+
+        let after = fetch_record(&pool, "site_meta").await.expect("record");
+        assert_eq!(after.content_json, before.content_json);
+
+        let err = diff_site_content(
+            claims,
+            State(pool.clone()),
+            Path("hero".to_string()),
+            Json(UpdateSiteContentRequest { content: json!("x") }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
     }
 
-    #[test]
-    fn test_validate_header_structure_rejects_empty_target() {
-        // Case: Empty slug should be rejected
-        let content_empty_slug = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "1", "label": "Empty Slug", "slug": "" }
-            ]
-        });
-        assert!(validate_header_structure(&content_empty_slug).is_err(), "Should reject empty slug");
-
-        // Case: Whitespace-only slug should be rejected
-        let content_whitespace_slug = json!({
-            "brand": { "name": "Test" },
-            "navItems": [
-                { "id": "2", "label": "Whitespace Slug", "slug": "   " }
-            ]
-        });
-        assert!(validate_header_structure(&content_whitespace_slug).is_err(), "Should reject whitespace-only slug");
+    #[tokio::test]
+    async fn test_conditional_get_returns_304_until_content_changes() {
+        use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+
+        let pool = crate::db::pool::create_test_pool().await;
+        let claims = auth::test_admin_claims();
+        let get = |headers: HeaderMap| {
+            get_site_content(State(pool.clone()), Path("site_meta".to_string()), headers)
+        };
+        let list = |headers: HeaderMap| list_site_content(State(pool.clone()), headers);
+        let conditional_headers = |etag: &axum::http::HeaderValue| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IF_NONE_MATCH, etag.clone());
+            headers
+        };
+
+        let first = get(HeaderMap::new()).await.expect("get");
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(first.headers()[CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .contains("stale-while-revalidate"));
+        let etag = first.headers()[ETAG].clone();
+        let list_etag = list(HeaderMap::new()).await.expect("list").headers()[ETAG].clone();
+
+        let cached = get(conditional_headers(&etag)).await.expect("get");
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[ETAG], etag);
+        let cached_list = list(conditional_headers(&list_etag)).await.expect("list");
+        assert_eq!(cached_list.status(), StatusCode::NOT_MODIFIED);
+
+        let _ = update_site_content(
+            claims,
+            State(pool.clone()),
+            Path("site_meta".to_string()),
+            Json(UpdateSiteContentRequest {
+                content: json!({ "title": "Neu" }),
+            }),
+        )
+        .await
+        .expect("update");
+
+        let fresh = get(conditional_headers(&etag)).await.expect("get");
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_ne!(fresh.headers()[ETAG], etag);
+        let fresh_list = list(conditional_headers(&list_etag)).await.expect("list");
+        assert_eq!(fresh_list.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_settings_writes_invalidate_cache_and_reject_bad_types() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let claims = auth::test_admin_claims();
+        let update = |content: Value| {
+            update_site_content(
+                claims.clone(),
+                State(pool.clone()),
+                Path(settings::SECTION.to_string()),
+                Json(UpdateSiteContentRequest { content }),
+            )
+        };
+
+        settings::invalidate();
+        assert!(settings::get(&pool).await.pdf_enabled);
+
+        let _ = update(json!({ "pdfEnabled": false, "commentBlocklist": ["casino"] }))
+            .await
+            .expect("update");
+        let current = settings::get(&pool).await;
+        assert!(!current.pdf_enabled);
+        assert_eq!(current.comment_blocklist, vec!["casino".to_string()]);
+
+        let (status, Json(body)) = update(json!({ "pdfEnabled": "no" }))
+            .await
+            .expect_err("wrong type");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.error.contains("pdfEnabled"), "{}", body.error);
+        assert!(!settings::get(&pool).await.pdf_enabled);
+    }
+
+    mod history {
+        use super::*;
+        use crate::db::pool::create_test_pool;
+        use crate::security::auth::test_admin_claims as admin;
+
+        async fn save(pool: &db::DbPool, section: &str, content: Value) {
+            let _ = update_site_content(
+                admin(),
+                State(pool.clone()),
+                Path(section.to_string()),
+                Json(UpdateSiteContentRequest { content }),
+            )
+            .await
+            .expect("update content");
+        }
+
+        async fn versions(pool: &db::DbPool, section: &str) -> Vec<i64> {
+            let Json(history) = list_site_content_history(
+                admin(),
+                State(pool.clone()),
+                Path(section.to_string()),
+                Query(ContentHistoryQuery::default()),
+            )
+            .await
+            .expect("history");
+            history.items.iter().map(|item| item.version).collect()
+        }
+
+        #[tokio::test]
+        async fn test_updates_record_history() {
+            let pool = create_test_pool().await;
+            save(&pool, "site_meta", json!({ "title": "Eins" })).await;
+            save(&pool, "site_meta", json!({ "title": "Zwei" })).await;
+
+            let history = versions(&pool, "site_meta").await;
+            assert_eq!(history.len(), 2);
+
+            let Json(latest) = get_site_content_version(
+                admin(),
+                State(pool.clone()),
+                Path(("site_meta".to_string(), history[0])),
+            )
+            .await
+            .expect("version");
+            assert_eq!(latest.content, json!({ "title": "Eins" }));
+            assert_eq!(latest.updated_by.as_deref(), Some("admin"));
+        }
+
+        #[tokio::test]
+        async fn test_updated_by_round_trips() {
+            let pool = create_test_pool().await;
+            let seeded = fetch_record(&pool, "site_meta").await.expect("record");
+            assert_eq!(seeded.updated_by, None);
+
+            save(&pool, "site_meta", json!({ "title": "Eins" })).await;
+            let stored = fetch_record(&pool, "site_meta")
+                .await
+                .and_then(map_record)
+                .expect("record");
+            assert_eq!(stored.updated_by.as_deref(), Some("admin"));
+
+            let other = auth::test_claims("second-admin", "admin");
+            let Json(updated) = update_site_content(
+                other,
+                State(pool.clone()),
+                Path("site_meta".to_string()),
+                Json(UpdateSiteContentRequest {
+                    content: json!({ "title": "Zwei" }),
+                }),
+            )
+            .await
+            .expect("update");
+            assert_eq!(updated.updated_by.as_deref(), Some("second-admin"));
+
+            let history = versions(&pool, "site_meta").await;
+            let Json(previous) = get_site_content_version(
+                admin(),
+                State(pool.clone()),
+                Path(("site_meta".to_string(), history[0])),
+            )
+            .await
+            .expect("version");
+            assert_eq!(previous.updated_by.as_deref(), Some("admin"));
+        }
+
+        #[tokio::test]
+        async fn test_rollback_restores_exact_json() {
+            let pool = create_test_pool().await;
+            let original = json!({
+                "title": "Linux lernen",
+                "description": "Mit \"Umlauten\" äöü",
+                "nested": { "list": [1, 2.5, null, true] }
+            });
+            save(&pool, "site_meta", original.clone()).await;
+            save(&pool, "site_meta", json!({ "title": "Kaputt" })).await;
+
+            let version = versions(&pool, "site_meta").await[0];
+            let Json(restored) = rollback_site_content(
+                admin(),
+                State(pool.clone()),
+                Path(("site_meta".to_string(), version)),
+            )
+            .await
+            .expect("rollback");
+            assert_eq!(restored.content, original);
+
+            let stored = repositories::content::fetch_site_content_by_section(&pool, "site_meta")
+                .await
+                .expect("fetch")
+                .expect("section");
+            assert_eq!(stored.content_json, serde_json::to_string(&original).unwrap());
+
+            // The overwritten content is archived as well, so the rollback can be undone.
+            assert_eq!(versions(&pool, "site_meta").await.len(), 3);
+        }
+
+        #[tokio::test]
+        async fn test_rollback_still_validates_structure() {
+            let pool = create_test_pool().await;
+            let result = sqlx::query(
+                "INSERT INTO site_content_history (section, content_json, updated_at) VALUES ('header', '{\"brand\":{}}', CURRENT_TIMESTAMP)",
+            )
+            .execute(&pool)
+            .await
+            .expect("insert legacy version");
+            let version = result.last_insert_rowid();
+
+            let err = rollback_site_content(
+                admin(),
+                State(pool.clone()),
+                Path(("header".to_string(), version)),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.0, StatusCode::BAD_REQUEST);
+
+            let missing = rollback_site_content(
+                admin(),
+                State(pool.clone()),
+                Path(("site_meta".to_string(), version)),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(missing.0, StatusCode::NOT_FOUND);
+        }
+    }
+
+    mod transfer {
+        use super::*;
+        use crate::db::pool::create_test_pool;
+        use crate::security::auth::test_admin_claims as admin;
+
+        async fn export(pool: &db::DbPool) -> SiteContentBundle {
+            let Json(bundle) = export_site_content(admin(), State(pool.clone()))
+                .await
+                .expect("export");
+            bundle
+        }
+
+        async fn import(
+            pool: &db::DbPool,
+            query: ContentImportQuery,
+            bundle: SiteContentBundle,
+        ) -> Result<SiteContentImportReport, (StatusCode, Json<ErrorResponse>)> {
+            import_site_content(admin(), State(pool.clone()), Query(query), Json(bundle))
+                .await
+                .map(|Json(report)| report)
+        }
+
+        fn entry(section: &str, content: Value) -> SiteContentExportEntry {
+            SiteContentExportEntry {
+                section: section.to_string(),
+                content,
+                updated_at: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn test_export_wipe_import_roundtrip() {
+            let pool = create_test_pool().await;
+            let exported = export(&pool).await;
+            assert!(!exported.site_content.is_empty());
+
+            let document = serde_json::to_value(&exported).unwrap();
+            sqlx::query("DELETE FROM site_content")
+                .execute(&pool)
+                .await
+                .expect("wipe");
+
+            let report = import(
+                &pool,
+                ContentImportQuery::default(),
+                serde_json::from_value(document).unwrap(),
+            )
+            .await
+            .expect("import");
+            assert_eq!(report.created.len(), exported.site_content.len());
+            assert!(report.updated.is_empty());
+
+            let reimported = export(&pool).await;
+            let sections = |bundle: &SiteContentBundle| -> Vec<(String, Value)> {
+                bundle
+                    .site_content
+                    .iter()
+                    .map(|entry| (entry.section.clone(), entry.content.clone()))
+                    .collect()
+            };
+            assert_eq!(sections(&reimported), sections(&exported));
+        }
+
+        #[tokio::test]
+        async fn test_dry_run_reports_without_writing() {
+            let pool = create_test_pool().await;
+            let mut bundle = export(&pool).await;
+            let total = bundle.site_content.len();
+            let meta = bundle
+                .site_content
+                .iter_mut()
+                .find(|entry| entry.section == "site_meta")
+                .expect("site_meta");
+            meta.content = json!({ "title": "Neu" });
+
+            let report = import(
+                &pool,
+                ContentImportQuery {
+                    dry_run: true,
+                    skip_unknown: false,
+                },
+                bundle,
+            )
+            .await
+            .expect("dry run");
+            assert!(report.dry_run);
+            assert_eq!(report.updated, vec!["site_meta".to_string()]);
+            assert_eq!(report.unchanged.len(), total - 1);
+
+            let stored = repositories::content::fetch_site_content_by_section(&pool, "site_meta")
+                .await
+                .expect("fetch")
+                .expect("section");
+            assert_ne!(stored.content_json, r#"{"title":"Neu"}"#);
+        }
+
+        #[tokio::test]
+        async fn test_unknown_sections_are_rejected_or_skipped() {
+            let pool = create_test_pool().await;
+            let bundle = || SiteContentBundle {
+                site_content: vec![
+                    entry("site_meta", json!({ "title": "Neu" })),
+                    entry("sidebar", json!({})),
+                ],
+            };
+
+            let err = import(&pool, ContentImportQuery::default(), bundle())
+                .await
+                .unwrap_err();
+            assert_eq!(err.0, StatusCode::BAD_REQUEST);
+            assert!(err.1.error.contains("sidebar"));
+
+            let report = import(
+                &pool,
+                ContentImportQuery {
+                    dry_run: false,
+                    skip_unknown: true,
+                },
+                bundle(),
+            )
+            .await
+            .expect("import");
+            assert_eq!(report.skipped, vec!["sidebar".to_string()]);
+            assert_eq!(report.updated, vec!["site_meta".to_string()]);
+        }
+
+        #[tokio::test]
+        async fn test_invalid_section_aborts_whole_import() {
+            let pool = create_test_pool().await;
+            let bundle = SiteContentBundle {
+                site_content: vec![
+                    entry("site_meta", json!({ "title": "Neu" })),
+                    entry("header", json!({ "brand": {} })),
+                ],
+            };
+
+            let err = import(&pool, ContentImportQuery::default(), bundle)
+                .await
+                .unwrap_err();
+            assert_eq!(err.0, StatusCode::BAD_REQUEST);
+            assert!(err.1.error.starts_with("Section 'header':"));
+
+            let stored = repositories::content::fetch_site_content_by_section(&pool, "site_meta")
+                .await
+                .expect("fetch")
+                .expect("section");
+            assert_ne!(stored.content_json, r#"{"title":"Neu"}"#);
+        }
+    }
+
+    mod patch {
+        use super::*;
+        use crate::db::pool::create_test_pool;
+        use crate::security::auth::test_admin_claims as admin;
+        use axum::http::HeaderValue;
+
+        async fn patch(
+            pool: &db::DbPool,
+            section: &str,
+            query: ContentPatchQuery,
+            headers: HeaderMap,
+            body: Value,
+        ) -> Result<SiteContentResponse, (StatusCode, Json<ErrorResponse>)> {
+            patch_site_content(
+                admin(),
+                State(pool.clone()),
+                Path(section.to_string()),
+                Query(query),
+                headers,
+                Json(body),
+            )
+            .await
+            .map(|Json(response)| response)
+        }
+
+        #[test]
+        fn test_apply_merge_patch_follows_rfc_7386() {
+            let mut target = json!({
+                "a": "b",
+                "c": { "d": "e", "f": "g" },
+                "list": [1, 2]
+            });
+            apply_merge_patch(
+                &mut target,
+                &json!({ "a": "z", "c": { "f": null, "h": 1 }, "list": [3], "new": { "x": null } }),
+            );
+            assert_eq!(
+                target,
+                json!({ "a": "z", "c": { "d": "e", "h": 1 }, "list": [3], "new": {} })
+            );
+
+            let mut scalar = json!("text");
+            apply_merge_patch(&mut scalar, &json!({ "a": 1 }));
+            assert_eq!(scalar, json!({ "a": 1 }));
+        }
+
+        #[tokio::test]
+        async fn test_patch_merges_nested_keys_and_removes_nulls() {
+            let pool = create_test_pool().await;
+            let updated = patch(
+                &pool,
+                "footer",
+                ContentPatchQuery::default(),
+                HeaderMap::new(),
+                json!({
+                    "brand": { "title": "Neu", "icon": null },
+                    "bottom": null
+                }),
+            )
+            .await
+            .expect("patch");
+
+            assert_eq!(updated.content["brand"]["title"], "Neu");
+            assert!(updated.content["brand"]["description"].is_string());
+            assert!(updated.content["brand"].get("icon").is_none());
+            assert!(updated.content.get("bottom").is_none());
+            assert_eq!(updated.content["quickLinks"].as_array().unwrap().len(), 4);
+        }
+
+        #[tokio::test]
+        async fn test_patch_checks_preconditions() {
+            let pool = create_test_pool().await;
+            let current = fetch_record(&pool, "site_meta").await.expect("record");
+
+            let err = patch(
+                &pool,
+                "site_meta",
+                ContentPatchQuery {
+                    expected_updated_at: Some("2000-01-01 00:00:00".to_string()),
+                    ..Default::default()
+                },
+                HeaderMap::new(),
+                json!({ "title": "Neu" }),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.0, StatusCode::PRECONDITION_FAILED);
+
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                IF_UNMODIFIED_SINCE,
+                HeaderValue::from_static("Sat, 01 Jan 2000 00:00:00 GMT"),
+            );
+            let err = patch(
+                &pool,
+                "site_meta",
+                ContentPatchQuery::default(),
+                headers,
+                json!({ "title": "Neu" }),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.0, StatusCode::PRECONDITION_FAILED);
+
+            let stored = fetch_record(&pool, "site_meta").await.expect("record");
+            assert_eq!(stored.content_json, current.content_json);
+
+            let updated = patch(
+                &pool,
+                "site_meta",
+                ContentPatchQuery {
+                    expected_updated_at: Some(format_timestamp(&current.updated_at)),
+                    ..Default::default()
+                },
+                HeaderMap::new(),
+                json!({ "title": "Neu" }),
+            )
+            .await
+            .expect("patch");
+            assert_eq!(updated.content["title"], "Neu");
+        }
+
+        #[tokio::test]
+        async fn test_concurrent_write_fails_the_precondition() {
+            let pool = create_test_pool().await;
+            let current = fetch_record(&pool, "site_meta").await.expect("record");
+            sqlx::query("UPDATE site_content SET revision = revision + 1 WHERE section = 'site_meta'")
+                .execute(&pool)
+                .await
+                .expect("simulate concurrent write");
+
+            let written = repositories::content::upsert_site_content_if_unchanged(
+                &pool,
+                "site_meta",
+                &json!({ "title": "Neu" }),
+                "editor",
+                0,
+                current.revision,
+            )
+            .await
+            .expect("upsert");
+            assert!(written.is_none());
+        }
+
+        #[tokio::test]
+        async fn test_back_to_back_patches_from_the_same_base() {
+            let pool = create_test_pool().await;
+            let base = fetch_record(&pool, "site_meta").await.expect("record");
+            let query = || ContentPatchQuery {
+                expected_updated_at: Some(format_timestamp(&base.updated_at)),
+                expected_revision: Some(base.revision),
+            };
+
+            // Both writes land within the same second, so only the revision
+            // tells them apart
+            let first = patch(
+                &pool,
+                "site_meta",
+                query(),
+                HeaderMap::new(),
+                json!({ "title": "Eins" }),
+            )
+            .await
+            .expect("first patch");
+            assert_eq!(first.revision, base.revision + 1);
+
+            let err = patch(
+                &pool,
+                "site_meta",
+                query(),
+                HeaderMap::new(),
+                json!({ "description": "Zwei" }),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.0, StatusCode::PRECONDITION_FAILED);
+
+            let stored = fetch_record(&pool, "site_meta").await.expect("record");
+            let content: Value = serde_json::from_str(&stored.content_json).unwrap();
+            assert_eq!(content["title"], "Eins");
+            assert_ne!(content["description"], "Zwei");
+        }
+
+        #[tokio::test]
+        async fn test_patch_rejects_invalid_merged_result() {
+            let pool = create_test_pool().await;
+            let err = patch(
+                &pool,
+                "hero",
+                ContentPatchQuery::default(),
+                HeaderMap::new(),
+                json!({ "features": null }),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.0, StatusCode::BAD_REQUEST);
+            assert!(err.1.error.contains("Invalid structure for section 'hero'"));
+
+            let stored = fetch_record(&pool, "hero").await.expect("record");
+            let content: Value = serde_json::from_str(&stored.content_json).unwrap();
+            assert!(content["features"].is_array());
+        }
     }
 }
@@ -1,257 +1,143 @@
 use crate::{
     security::auth, db,
+    error::{ensure_admin, AppError},
     models::{
-        CreateSitePageRequest, ErrorResponse, NavigationItemResponse, NavigationResponse,
-        SitePageListResponse, SitePageResponse, SitePageWithPostsResponse, SitePostDetailResponse,
-        SitePostResponse, UpdateSitePageRequest,
+        Breadcrumb, CreateSitePageRequest, Cursor, CursorPageParams, NavigationItemResponse,
+        NavigationResponse, PageParams, Paginated, PostArchiveEntry, PublicPostSort,
+        PublicPostSummary, SitePage, SitePageResponse, SitePageWithPostsResponse,
+        SitePostDetailResponse, SitePostResponse, UpdateSitePageRequest,
+        PAGE_VISIBILITY_AUTHENTICATED,
     },
     repositories,
+    utils::layout_blocks::{resolve_layout_lenient, resolve_layout_strict, BlockLibrary},
+    validation::{pages, ValidatedJson},
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
+use chrono::NaiveDate;
+use serde::Deserialize;
 use serde_json::Value;
-use sqlx;
-
-const MAX_TITLE_LEN: usize = 200;
-const MAX_DESCRIPTION_LEN: usize = 1000;
-const MAX_NAV_LABEL_LEN: usize = 100;
-const MAX_JSON_BYTES: usize = 200_000;
-
-fn ensure_admin(claims: &auth::Claims) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    if claims.role != "admin" {
-        Err((
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: "Insufficient permissions".to_string(),
-            }),
-        ))
-    } else {
-        Ok(())
-    }
-}
-
-fn map_sqlx_error(err: sqlx::Error, context: &str) -> (StatusCode, Json<ErrorResponse>) {
-    match err {
-        sqlx::Error::RowNotFound => (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("{context} not found"),
-            }),
-        ),
-        sqlx::Error::Protocol(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ),
-        sqlx::Error::Database(db_err) => {
-            if db_err.is_unique_violation() {
-                (
-                    StatusCode::CONFLICT,
-                    Json(ErrorResponse {
-                        error: db_err
-                            .constraint()
-                            .map(|c| format!("Duplicate value violates unique constraint '{c}'"))
-                            .unwrap_or_else(|| {
-                                "Duplicate value violates unique constraint".to_string()
-                            }),
-                    }),
-                )
-            } else {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "Database error".to_string(),
-                    }),
-                )
-            }
-        }
-        other => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Unexpected database error: {other}"),
-            }),
-        ),
-    }
+
+const HOME_BREADCRUMB_LABEL: &str = "Home";
+
+/// Whether a page may be shown to a viewer with the given sign-in state.
+fn is_visible_to(page: &SitePage, authenticated: bool) -> bool {
+    authenticated || page.visibility != PAGE_VISIBILITY_AUTHENTICATED
 }
 
-fn validate_json_size(value: &Value, field: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    match serde_json::to_string(value) {
-        Ok(serialized) if serialized.len() <= MAX_JSON_BYTES => Ok(()),
-        Ok(_) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("{field} JSON exceeds maximum size of {MAX_JSON_BYTES} bytes"),
-            }),
-        )),
-        Err(err) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Invalid {field} JSON: {err}"),
-            }),
-        )),
-    }
+/// Resolves the optional viewer claims. An invalid or revoked token is treated
+/// as an anonymous visitor so stale cookies never break public pages.
+pub(crate) fn viewer_is_authenticated(claims: &Result<auth::OptionalClaims, (StatusCode, String)>) -> bool {
+    matches!(claims, Ok(auth::OptionalClaims(Some(_))))
 }
 
+/// Maps a rule broken by `field` to its validation error.
+fn invalid(field: &'static str) -> impl Fn(String) -> AppError {
+    move |message| AppError::invalid_field(field, message)
+}
+
+/// Stores the texts of a page as the rules of its request checked them:
+/// trimmed, with blank optional fields cleared.
 fn sanitize_create_payload(
     mut payload: CreateSitePageRequest,
-) -> Result<CreateSitePageRequest, (StatusCode, Json<ErrorResponse>)> {
-    payload.slug = payload.slug.trim().to_lowercase();
-    if payload.slug.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Slug cannot be empty".to_string(),
-            }),
-        ));
-    }
-
-    payload.title = payload.title.trim().to_string();
-    if payload.title.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Title cannot be empty".to_string(),
-            }),
-        ));
-    }
-    if payload.title.len() > MAX_TITLE_LEN {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Title too long (max {MAX_TITLE_LEN} characters)"),
-            }),
-        ));
-    }
-
-    payload.description = payload.description.map(|desc| desc.trim().to_string());
-    if let Some(desc) = payload.description.as_ref() {
-        if desc.len() > MAX_DESCRIPTION_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Description too long (max {MAX_DESCRIPTION_LEN} characters)"),
-                }),
-            ));
-        }
-    }
-
-    payload.nav_label = payload.nav_label.and_then(|label| {
-        let trimmed = label.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed.to_string())
-        }
-    });
-    if let Some(label) = payload.nav_label.as_ref() {
-        if label.len() > MAX_NAV_LABEL_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!(
-                        "Navigation label too long (max {MAX_NAV_LABEL_LEN} characters)"
-                    ),
-                }),
-            ));
-        }
-    }
-
-    validate_json_size(&payload.hero, "hero")?;
-    validate_json_size(&payload.layout, "layout")?;
+) -> Result<CreateSitePageRequest, AppError> {
+    payload.slug = pages::normalize_slug(&payload.slug).map_err(invalid("slug"))?;
+    payload.title = pages::normalize_title(&payload.title).map_err(invalid("title"))?;
+    payload.description = payload
+        .description
+        .map(|desc| pages::normalize_description(&desc))
+        .transpose()
+        .map_err(invalid("description"))?;
+    payload.nav_label =
+        pages::normalize_nav_label(payload.nav_label).map_err(invalid("nav_label"))?;
+    payload.visibility =
+        pages::normalize_visibility(&payload.visibility).map_err(invalid("visibility"))?;
+    payload.meta_title =
+        pages::normalize_meta_title(payload.meta_title).map_err(invalid("meta_title"))?;
+    payload.meta_description = pages::normalize_meta_description(payload.meta_description)
+        .map_err(invalid("meta_description"))?;
+    payload.og_image = pages::normalize_og_image(payload.og_image).map_err(invalid("og_image"))?;
+    pages::validate_hero(&payload.hero).map_err(invalid("hero"))?;
+    pages::validate_layout(&payload.layout).map_err(invalid("layout"))?;
 
     Ok(payload)
 }
 
 fn sanitize_update_payload(
     mut payload: UpdateSitePageRequest,
-) -> Result<UpdateSitePageRequest, (StatusCode, Json<ErrorResponse>)> {
-    if let Some(ref mut slug) = payload.slug {
-        *slug = slug.trim().to_lowercase();
-        if slug.is_empty() {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Slug cannot be empty".to_string(),
-                }),
-            ));
-        }
+) -> Result<UpdateSitePageRequest, AppError> {
+    if let Some(slug) = payload.slug.take() {
+        payload.slug = Some(pages::normalize_slug(&slug).map_err(invalid("slug"))?);
     }
-
-    if let Some(ref mut title) = payload.title {
-        *title = title.trim().to_string();
-        if title.is_empty() {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Title cannot be empty".to_string(),
-                }),
-            ));
-        }
-        if title.len() > MAX_TITLE_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Title too long (max {MAX_TITLE_LEN} characters)"),
-                }),
-            ));
-        }
+    if let Some(title) = payload.title.take() {
+        payload.title = Some(pages::normalize_title(&title).map_err(invalid("title"))?);
     }
-
-    if let Some(ref mut description) = payload.description {
-        *description = description.trim().to_string();
-        if description.len() > MAX_DESCRIPTION_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Description too long (max {MAX_DESCRIPTION_LEN} characters)"),
-                }),
-            ));
-        }
+    if let Some(description) = payload.description.take() {
+        payload.description =
+            Some(pages::normalize_description(&description).map_err(invalid("description"))?);
     }
-
-    if let Some(mut nav_label_option) = payload.nav_label.take() {
-        nav_label_option = match nav_label_option {
-            Some(label) => {
-                let trimmed = label.trim().to_string();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    if trimmed.len() > MAX_NAV_LABEL_LEN {
-                        return Err((
-                            StatusCode::BAD_REQUEST,
-                            Json(ErrorResponse {
-                                error: format!(
-                                    "Navigation label too long (max {MAX_NAV_LABEL_LEN} characters)"
-                                ),
-                            }),
-                        ));
-                    }
-                    Some(trimmed)
-                }
-            }
-            None => None,
-        };
-
-        payload.nav_label = Some(nav_label_option);
+    if let Some(nav_label) = payload.nav_label.take() {
+        payload.nav_label =
+            Some(pages::normalize_nav_label(nav_label).map_err(invalid("nav_label"))?);
+    }
+    if let Some(visibility) = payload.visibility.take() {
+        payload.visibility =
+            Some(pages::normalize_visibility(&visibility).map_err(invalid("visibility"))?);
+    }
+    if let Some(meta_title) = payload.meta_title.take() {
+        payload.meta_title =
+            Some(pages::normalize_meta_title(meta_title).map_err(invalid("meta_title"))?);
+    }
+    if let Some(meta_description) = payload.meta_description.take() {
+        payload.meta_description = Some(
+            pages::normalize_meta_description(meta_description)
+                .map_err(invalid("meta_description"))?,
+        );
+    }
+    if let Some(og_image) = payload.og_image.take() {
+        payload.og_image = Some(pages::normalize_og_image(og_image).map_err(invalid("og_image"))?);
     }
-
     if let Some(ref hero) = payload.hero {
-        validate_json_size(hero, "hero")?;
+        pages::validate_hero(hero).map_err(invalid("hero"))?;
     }
     if let Some(ref layout) = payload.layout {
-        validate_json_size(layout, "layout")?;
+        pages::validate_layout(layout).map_err(invalid("layout"))?;
     }
 
     Ok(payload)
 }
 
+/// Rejects layouts whose `ref` blocks point at missing library blocks or form
+/// a cycle, so broken references are caught while the editor is still open.
+async fn ensure_layout_refs_resolve(
+    pool: &db::DbPool,
+    layout: &Value,
+) -> Result<(), AppError> {
+    let library = load_block_library(pool).await?;
+    resolve_layout_strict(layout, &library)
+        .map_err(|err| AppError::invalid_field("layout", format!("Invalid layout at {err}")))?;
+
+    Ok(())
+}
+
+async fn load_block_library(
+    pool: &db::DbPool,
+) -> Result<BlockLibrary, AppError> {
+    repositories::blocks::load_block_library(pool, None)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Layout block"))
+}
+
+/// Maps a stored page to its response. With a block library the layout's
+/// `ref` blocks are expanded for public rendering and unresolvable ones are
+/// dropped; admin responses pass `None` and keep the references for editing.
 fn map_page(
     page: crate::models::SitePage,
-) -> Result<SitePageResponse, (StatusCode, Json<ErrorResponse>)> {
+    blocks: Option<&BlockLibrary>,
+) -> Result<SitePageResponse, AppError> {
     let crate::models::SitePage {
         id,
         slug,
@@ -261,29 +147,28 @@ fn map_page(
         show_in_nav,
         order_index,
         is_published,
+        visibility,
         hero_json,
         layout_json,
+        meta_title,
+        meta_description,
+        og_image,
         created_at,
         updated_at,
+        deleted_at,
     } = page;
 
     let hero = serde_json::from_str::<Value>(&hero_json).map_err(|err| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to parse stored hero JSON: {err}"),
-            }),
-        )
+        AppError::Internal(format!("Failed to parse stored hero JSON: {err}"))
     })?;
 
     let layout = serde_json::from_str::<Value>(&layout_json).map_err(|err| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to parse stored layout JSON: {err}"),
-            }),
-        )
+        AppError::Internal(format!("Failed to parse stored layout JSON: {err}"))
     })?;
+    let layout = match blocks {
+        Some(library) => resolve_layout_lenient(&layout, library),
+        None => layout,
+    };
 
     let sanitized_slug = slug.trim().to_lowercase();
     let sanitized_title = match title.trim() {
@@ -311,13 +196,47 @@ fn map_page(
         show_in_nav,
         order_index,
         is_published,
+        visibility,
         hero,
         layout,
+        meta_title,
+        meta_description,
+        og_image,
         created_at,
         updated_at,
+        deleted_at,
     })
 }
 
+/// Breadcrumbs for a public page, mirroring the frontend's `/pages/:slug` route.
+fn page_breadcrumbs(page: &SitePageResponse) -> Vec<Breadcrumb> {
+    let label = page
+        .nav_label
+        .clone()
+        .unwrap_or_else(|| page.title.clone());
+
+    vec![
+        Breadcrumb {
+            label: HOME_BREADCRUMB_LABEL.to_string(),
+            path: "/".to_string(),
+        },
+        Breadcrumb {
+            label,
+            path: format!("/pages/{}", page.slug),
+        },
+    ]
+}
+
+/// Breadcrumbs for a public post, mirroring `/pages/:pageSlug/posts/:postSlug`.
+fn post_breadcrumbs(page: &SitePageResponse, post: &SitePostResponse) -> Vec<Breadcrumb> {
+    let mut breadcrumbs = page_breadcrumbs(page);
+    breadcrumbs.push(Breadcrumb {
+        label: post.title.trim().to_string(),
+        path: format!("/pages/{}/posts/{}", page.slug, post.slug.trim().to_lowercase()),
+    });
+    breadcrumbs
+}
+
 fn map_post(post: crate::models::SitePost) -> SitePostResponse {
     SitePostResponse {
         id: post.id,
@@ -325,7 +244,10 @@ fn map_post(post: crate::models::SitePost) -> SitePostResponse {
         title: post.title,
         slug: post.slug,
         excerpt: post.excerpt,
+        excerpt_auto: post.excerpt_auto,
         content_markdown: post.content_markdown,
+        word_count: post.word_count,
+        reading_time_minutes: post.reading_time_minutes,
         is_published: post.is_published,
         published_at: post.published_at,
         order_index: post.order_index,
@@ -335,152 +257,327 @@ fn map_post(post: crate::models::SitePost) -> SitePostResponse {
     }
 }
 
+async fn ensure_slug_not_in_trash(
+    pool: &db::DbPool,
+    slug: &str,
+    page_id: Option<&str>,
+) -> Result<(), AppError> {
+    let in_trash = repositories::pages::is_slug_in_trash(pool, slug, page_id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?;
+
+    if in_trash {
+        return Err(AppError::Conflict(format!(
+            "Slug '{slug}' belongs to a page in the trash; restore or purge it first"
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SitePageListQuery {
+    #[serde(default)]
+    pub trashed: bool,
+}
+
 pub async fn list_site_pages(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
-) -> Result<Json<SitePageListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    page: PageParams,
+    Query(query): Query<SitePageListQuery>,
+) -> Result<Json<Paginated<SitePageResponse>>, AppError> {
     ensure_admin(&claims)?;
 
-    let records = repositories::pages::list_site_pages(&pool)
-        .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?;
+    let (records, total) = tokio::try_join!(
+        repositories::pages::list_site_pages(&pool, query.trashed, page.limit, page.offset),
+        repositories::pages::count_site_pages(&pool, query.trashed),
+    )
+    .map_err(|err| AppError::from_sqlx(err, "Site page"))?;
 
     let mut items = Vec::with_capacity(records.len());
     for record in records {
-        items.push(map_page(record)?);
+        items.push(map_page(record, None)?);
     }
 
-    Ok(Json(SitePageListResponse { items }))
+    Ok(Json(Paginated::new(items, total, page.limit, page.offset)))
 }
 
 pub async fn get_site_page(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
-) -> Result<Json<SitePageResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SitePageResponse>, AppError> {
     ensure_admin(&claims)?;
 
     let record = repositories::pages::get_site_page_by_id(&pool, &id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Site page not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?
+        .ok_or_else(|| AppError::NotFound("Site page not found".to_string()))?;
 
-    Ok(Json(map_page(record)?))
+    Ok(Json(map_page(record, None)?))
 }
 
 pub async fn create_site_page(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
-    Json(payload): Json<CreateSitePageRequest>,
-) -> Result<Json<SitePageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ValidatedJson(payload): ValidatedJson<CreateSitePageRequest>,
+) -> Result<Json<SitePageResponse>, AppError> {
     ensure_admin(&claims)?;
 
     let payload = sanitize_create_payload(payload)?;
+    ensure_slug_not_in_trash(&pool, &payload.slug, None).await?;
+    ensure_layout_refs_resolve(&pool, &payload.layout).await?;
 
     let record = repositories::pages::create_site_page(&pool, payload)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?;
 
-    Ok(Json(map_page(record)?))
+    Ok(Json(map_page(record, None)?))
 }
 
 pub async fn update_site_page(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
-    Json(payload): Json<UpdateSitePageRequest>,
-) -> Result<Json<SitePageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ValidatedJson(payload): ValidatedJson<UpdateSitePageRequest>,
+) -> Result<Json<SitePageResponse>, AppError> {
     ensure_admin(&claims)?;
 
     let payload = sanitize_update_payload(payload)?;
+    if let Some(slug) = payload.slug.as_deref() {
+        ensure_slug_not_in_trash(&pool, slug, Some(&id)).await?;
+    }
+    if let Some(layout) = payload.layout.as_ref() {
+        ensure_layout_refs_resolve(&pool, layout).await?;
+    }
 
     let record = repositories::pages::update_site_page(&pool, &id, payload)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?;
 
-    Ok(Json(map_page(record)?))
+    Ok(Json(map_page(record, None)?))
 }
 
 pub async fn delete_site_page(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<StatusCode, AppError> {
     ensure_admin(&claims)?;
 
     repositories::pages::delete_site_page(&pool, &id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-pub async fn get_published_page_by_slug(
+pub async fn restore_site_page(
+    claims: auth::Claims,
     State(pool): State<db::DbPool>,
-    Path(slug): Path<String>,
-) -> Result<Json<SitePageWithPostsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    Path(id): Path<String>,
+) -> Result<Json<SitePageResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let record = repositories::pages::restore_site_page(&pool, &id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Trashed site page"))?;
+
+    Ok(Json(map_page(record, None)?))
+}
+
+pub async fn purge_site_page(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    ensure_admin(&claims)?;
+
+    repositories::pages::purge_site_page(&pool, &id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Trashed site page"))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PublicPostListQuery {
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+}
+
+/// Computes the half-open `[start, end)` date range covered by an archive
+/// filter. A year alone selects the whole year; a month requires a year.
+fn archive_date_range(
+    year: Option<i32>,
+    month: Option<u32>,
+) -> Result<Option<(String, String)>, &'static str> {
+    let year = match (year, month) {
+        (None, None) => return Ok(None),
+        (None, Some(_)) => return Err("Month filter requires a year"),
+        (Some(year), _) => year,
+    };
+
+    if !(1..=9998).contains(&year) {
+        return Err("Year is out of range");
+    }
+
+    let (start, end) = match month {
+        None => ((year, 1), (year + 1, 1)),
+        Some(month) if (1..=11).contains(&month) => ((year, month), (year, month + 1)),
+        Some(12) => ((year, 12), (year + 1, 1)),
+        Some(_) => return Err("Month must be between 1 and 12"),
+    };
+
+    let to_date = |(year, month): (i32, u32)| {
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .ok_or("Invalid archive date")
+    };
+
+    Ok(Some((to_date(start)?, to_date(end)?)))
+}
+
+async fn load_published_page(
+    pool: &db::DbPool,
+    slug: &str,
+    authenticated: bool,
+) -> Result<SitePage, AppError> {
     let lookup_slug = slug.trim().to_lowercase();
     if lookup_slug.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Slug cannot be empty".to_string(),
-            }),
-        ));
+        return Err(AppError::invalid_field("slug", "Slug cannot be empty"));
     }
 
-    let page = repositories::pages::get_site_page_by_slug(&pool, &lookup_slug)
+    let page = repositories::pages::get_site_page_by_slug(pool, &lookup_slug)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Page not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?
+        .ok_or_else(|| AppError::NotFound("Page not found".to_string()))?;
 
     if !page.is_published {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Page not published".to_string(),
-            }),
-        ));
+        return Err(AppError::NotFound("Page not published".to_string()));
     }
 
-    let posts = repositories::posts::list_published_posts_for_page(&pool, &page.id)
-        .await
-        .map_err(|err| map_sqlx_error(err, "Posts"))?;
+    // Members-only pages are reported as missing to avoid advertising their slugs.
+    if !is_visible_to(&page, authenticated) {
+        return Err(AppError::NotFound("Page not found".to_string()));
+    }
+
+    Ok(page)
+}
+
+pub async fn get_published_page_by_slug(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
+    State(pool): State<db::DbPool>,
+    Path(slug): Path<String>,
+    Query(query): Query<PublicPostListQuery>,
+) -> Result<Json<SitePageWithPostsResponse>, AppError> {
+    let range = archive_date_range(query.year, query.month).map_err(AppError::validation)?;
+
+    let page = load_published_page(&pool, &slug, viewer_is_authenticated(&claims)).await?;
+
+    let posts = match range {
+        Some((start, end)) => {
+            repositories::posts::list_published_posts_for_page_in_range(
+                &pool, &page.id, &start, &end,
+            )
+            .await
+        }
+        None => repositories::posts::list_published_posts_for_page(&pool, &page.id).await,
+    }
+    .map_err(|err| AppError::from_sqlx(err, "Posts"))?;
 
     let mut post_responses = Vec::with_capacity(posts.len());
     for post in posts {
         post_responses.push(map_post(post));
     }
 
+    let library = load_block_library(&pool).await?;
+    let page = map_page(page, Some(&library))?;
+    let breadcrumbs = page_breadcrumbs(&page);
+
     Ok(Json(SitePageWithPostsResponse {
-        page: map_page(page)?,
+        page,
         posts: post_responses,
+        breadcrumbs,
     }))
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct PublicPostsQuery {
+    #[serde(default)]
+    pub sort: PublicPostSort,
+}
+
+pub async fn list_public_posts(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
+    State(pool): State<db::DbPool>,
+    CursorPageParams { page, cursor }: CursorPageParams,
+    Query(query): Query<PublicPostsQuery>,
+) -> Result<Json<Paginated<PublicPostSummary>>, AppError> {
+    // Cursors follow the newest-first order; titles page by offset only
+    let by_date = query.sort == PublicPostSort::PublishedAt;
+    if cursor.is_some() && !by_date {
+        return Err(AppError::invalid_field(
+            "cursor",
+            "Cursors cannot be combined with sort=title; use offset instead",
+        ));
+    }
+
+    let (items, total) = repositories::posts::list_public_posts(
+        &pool,
+        viewer_is_authenticated(&claims),
+        query.sort,
+        page.limit + 1,
+        page.offset,
+        cursor.as_ref(),
+    )
+    .await
+    .map_err(|err| AppError::from_sqlx(err, "Posts"))?;
+
+    let mut listing = Paginated::from_overfetch(items, total, page.limit, page.offset, |post| {
+        Cursor::new(&post.sorted_at, &post.id)
+    });
+    if !by_date {
+        listing.next_cursor = None;
+    }
+
+    Ok(Json(listing))
+}
+
+pub async fn get_page_archive(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
+    State(pool): State<db::DbPool>,
+    Path(slug): Path<String>,
+) -> Result<Json<Vec<PostArchiveEntry>>, AppError> {
+    let page = load_published_page(&pool, &slug, viewer_is_authenticated(&claims)).await?;
+
+    let archive = repositories::posts::list_post_archive(&pool, &page.id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Archive"))?;
+
+    Ok(Json(archive))
+}
+
 pub async fn get_navigation(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
     State(pool): State<db::DbPool>,
-) -> Result<Json<NavigationResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let pages = repositories::pages::list_nav_pages(&pool)
+) -> Result<Json<NavigationResponse>, AppError> {
+    let authenticated = viewer_is_authenticated(&claims);
+    Ok(Json(load_navigation(&pool, authenticated).await?))
+}
+
+/// Navigation entries visible to a viewer with the given sign-in state.
+pub(crate) async fn load_navigation(
+    pool: &db::DbPool,
+    authenticated: bool,
+) -> Result<NavigationResponse, AppError> {
+    let pages = repositories::pages::list_nav_pages(pool)
         .await
-        .map_err(|err| map_sqlx_error(err, "Navigation"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Navigation"))?;
 
     let mut items = Vec::with_capacity(pages.len());
-    for page in pages {
+    for page in pages.into_iter().filter(|page| is_visible_to(page, authenticated)) {
         let normalized_slug = page.slug.trim().to_lowercase();
         if normalized_slug.is_empty() {
             continue;
@@ -497,73 +594,57 @@ pub async fn get_navigation(
         });
     }
 
-    Ok(Json(NavigationResponse { items }))
+    Ok(NavigationResponse { items })
 }
 
 pub async fn get_published_post_by_slug(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
     State(pool): State<db::DbPool>,
     Path((page_slug, post_slug)): Path<(String, String)>,
-) -> Result<Json<SitePostDetailResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let lookup_page_slug = page_slug.trim().to_lowercase();
+) -> Result<Json<SitePostDetailResponse>, AppError> {
     let lookup_post_slug = post_slug.trim().to_lowercase();
-
-    if lookup_page_slug.is_empty() || lookup_post_slug.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Slug cannot be empty".to_string(),
-            }),
-        ));
+    if lookup_post_slug.is_empty() {
+        return Err(AppError::invalid_field("slug", "Slug cannot be empty"));
     }
 
-    let page = repositories::pages::get_site_page_by_slug(&pool, &lookup_page_slug)
-        .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Page not found".to_string(),
-                }),
-            )
-        })?;
-
-    if !page.is_published {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Page not published".to_string(),
-            }),
-        ));
-    }
+    let page = load_published_page(&pool, &page_slug, viewer_is_authenticated(&claims)).await?;
 
     let post = repositories::posts::get_published_post_by_slug(&pool, &page.id, &lookup_post_slug)
         .await
-        .map_err(|err| map_sqlx_error(err, "Post"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Post not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(|err| AppError::from_sqlx(err, "Post"))?
+        .ok_or_else(|| AppError::NotFound("Post not found".to_string()))?;
+
+    let (previous, next) =
+        repositories::posts::get_published_post_neighbors(&pool, &page.id, &post.id)
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Post"))?;
+
+    let library = load_block_library(&pool).await?;
+    let page = map_page(page, Some(&library))?;
+    let post = map_post(post);
+    let breadcrumbs = post_breadcrumbs(&page, &post);
 
     Ok(Json(SitePostDetailResponse {
-        page: map_page(page)?,
-        post: map_post(post),
+        page,
+        post,
+        previous,
+        next,
+        breadcrumbs,
     }))
 }
 
 pub async fn list_published_page_slugs(
+    claims: Result<auth::OptionalClaims, (StatusCode, String)>,
     State(pool): State<db::DbPool>,
-) -> Result<Json<Vec<String>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Vec<String>>, AppError> {
+    let authenticated = viewer_is_authenticated(&claims);
     let pages = repositories::pages::list_published_pages(&pool)
         .await
-        .map_err(|err| map_sqlx_error(err, "Navigation"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Navigation"))?;
 
     let slugs = pages
         .into_iter()
+        .filter(|page| is_visible_to(page, authenticated))
         .filter_map(|page| {
             let normalized = page.slug.trim().to_lowercase();
             if normalized.is_empty() {
@@ -576,3 +657,378 @@ pub async fn list_published_page_slugs(
 
     Ok(Json(slugs))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PAGE_VISIBILITY_PUBLIC;
+
+    fn sample_page(nav_label: Option<&str>) -> SitePageResponse {
+        map_page(crate::models::SitePage {
+            id: "page-1".to_string(),
+            slug: " Grundlagen ".to_string(),
+            title: "Grundlagen der Shell".to_string(),
+            description: String::new(),
+            nav_label: nav_label.map(str::to_string),
+            show_in_nav: true,
+            order_index: 0,
+            is_published: true,
+            visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+            hero_json: "{}".to_string(),
+            layout_json: "{}".to_string(),
+            meta_title: None,
+            meta_description: None,
+            og_image: None,
+            created_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            updated_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            deleted_at: None,
+        }, None)
+        .expect("map page")
+    }
+
+    fn sample_post() -> SitePostResponse {
+        map_post(crate::models::SitePost {
+            id: "post-1".to_string(),
+            page_id: "page-1".to_string(),
+            title: "Erste Schritte".to_string(),
+            slug: "erste-schritte".to_string(),
+            excerpt: String::new(),
+            excerpt_auto: true,
+            content_markdown: String::new(),
+            word_count: 0,
+            reading_time_minutes: 0,
+            is_published: true,
+            allow_comments: true,
+            published_at: None,
+            order_index: 0,
+            created_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            updated_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_page_breadcrumbs_prefer_nav_label() {
+        let crumbs = page_breadcrumbs(&sample_page(Some("Grundlagen")));
+        assert_eq!(
+            crumbs,
+            vec![
+                Breadcrumb { label: "Home".to_string(), path: "/".to_string() },
+                Breadcrumb { label: "Grundlagen".to_string(), path: "/pages/grundlagen".to_string() },
+            ]
+        );
+
+        let crumbs = page_breadcrumbs(&sample_page(None));
+        assert_eq!(crumbs[1].label, "Grundlagen der Shell");
+    }
+
+    #[test]
+    fn test_post_detail_response_shape() {
+        let page = sample_page(Some("Grundlagen"));
+        let post = sample_post();
+        let breadcrumbs = post_breadcrumbs(&page, &post);
+        let response = SitePostDetailResponse {
+            page,
+            post,
+            previous: None,
+            next: None,
+            breadcrumbs,
+        };
+
+        let json = serde_json::to_value(&response).expect("serialize");
+        assert_eq!(json["page"]["slug"], "grundlagen");
+        assert_eq!(json["page"]["nav_label"], "Grundlagen");
+        assert_eq!(json["page"]["is_published"], true);
+        assert_eq!(json["breadcrumbs"].as_array().map(Vec::len), Some(3));
+        assert_eq!(json["breadcrumbs"][2]["label"], "Erste Schritte");
+        assert_eq!(json["breadcrumbs"][2]["path"], "/pages/grundlagen/posts/erste-schritte");
+    }
+
+    #[test]
+    fn test_archive_date_range_for_month() {
+        assert_eq!(
+            archive_date_range(Some(2024), Some(2)),
+            Ok(Some(("2024-02-01".to_string(), "2024-03-01".to_string())))
+        );
+        assert_eq!(
+            archive_date_range(Some(2024), Some(12)),
+            Ok(Some(("2024-12-01".to_string(), "2025-01-01".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_archive_date_range_for_year_and_none() {
+        assert_eq!(archive_date_range(None, None), Ok(None));
+        assert_eq!(
+            archive_date_range(Some(2023), None),
+            Ok(Some(("2023-01-01".to_string(), "2024-01-01".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_archive_date_range_rejects_invalid_input() {
+        assert!(archive_date_range(None, Some(3)).is_err());
+        assert!(archive_date_range(Some(2024), Some(0)).is_err());
+        assert!(archive_date_range(Some(2024), Some(13)).is_err());
+        assert!(archive_date_range(Some(0), None).is_err());
+        assert!(archive_date_range(Some(10_000), Some(1)).is_err());
+    }
+
+    mod visibility {
+        use super::*;
+        use crate::db::pool::create_test_pool;
+        use crate::models::CreateSitePostRequest;
+
+        type Viewer = Result<auth::OptionalClaims, (StatusCode, String)>;
+
+        fn anonymous() -> Viewer {
+            Ok(auth::OptionalClaims(None))
+        }
+
+        fn member() -> Viewer {
+            Ok(auth::OptionalClaims(Some(auth::test_claims("reader", "user"))))
+        }
+
+        fn admin() -> Viewer {
+            Ok(auth::OptionalClaims(Some(auth::test_admin_claims())))
+        }
+
+        fn invalid_token() -> Viewer {
+            Err((StatusCode::UNAUTHORIZED, "Invalid token".to_string()))
+        }
+
+        async fn seed(pool: &db::DbPool) {
+            for (slug, visibility) in [
+                ("public-page", PAGE_VISIBILITY_PUBLIC),
+                ("loesungen", PAGE_VISIBILITY_AUTHENTICATED),
+            ] {
+                let page = repositories::pages::create_site_page(
+                    pool,
+                    CreateSitePageRequest {
+                        slug: slug.to_string(),
+                        title: slug.to_string(),
+                        description: None,
+                        nav_label: None,
+                        show_in_nav: true,
+                        order_index: None,
+                        is_published: true,
+                        visibility: visibility.to_string(),
+                        hero: Value::Null,
+                        layout: Value::Null,
+                        meta_title: None,
+                        meta_description: None,
+                        og_image: None,
+                    },
+                )
+                .await
+                .expect("create page");
+
+                repositories::posts::create_site_post(
+                    pool,
+                    &page.id,
+                    CreateSitePostRequest {
+                        title: "Post".to_string(),
+                        slug: "post".to_string(),
+                        excerpt: None,
+                        content_markdown: "Body".to_string(),
+                        is_published: true,
+                        allow_comments: true,
+                        published_at: Some("2024-01-15T00:00:00Z".parse().unwrap()),
+                        order_index: None,
+                    },
+                )
+                .await
+                .expect("create post");
+            }
+        }
+
+        fn status<T>(result: Result<T, AppError>) -> StatusCode {
+            match result {
+                Ok(_) => StatusCode::OK,
+                Err(err) => err.status(),
+            }
+        }
+
+        async fn page_status(pool: &db::DbPool, viewer: Viewer, slug: &str) -> StatusCode {
+            status(
+                get_published_page_by_slug(
+                    viewer,
+                    State(pool.clone()),
+                    Path(slug.to_string()),
+                    Query(PublicPostListQuery::default()),
+                )
+                .await,
+            )
+        }
+
+        async fn post_status(pool: &db::DbPool, viewer: Viewer, slug: &str) -> StatusCode {
+            status(
+                get_published_post_by_slug(
+                    viewer,
+                    State(pool.clone()),
+                    Path((slug.to_string(), "post".to_string())),
+                )
+                .await,
+            )
+        }
+
+        async fn archive_status(pool: &db::DbPool, viewer: Viewer, slug: &str) -> StatusCode {
+            status(get_page_archive(viewer, State(pool.clone()), Path(slug.to_string())).await)
+        }
+
+        async fn nav_slugs(pool: &db::DbPool, viewer: Viewer) -> Vec<String> {
+            let Json(nav) = get_navigation(viewer, State(pool.clone())).await.expect("nav");
+            nav.items.into_iter().map(|item| item.slug).collect()
+        }
+
+        async fn published_slugs(pool: &db::DbPool, viewer: Viewer) -> Vec<String> {
+            let Json(slugs) = list_published_page_slugs(viewer, State(pool.clone()))
+                .await
+                .expect("slugs");
+            slugs
+        }
+
+        #[tokio::test]
+        async fn test_anonymous_viewers_get_not_found() {
+            let pool = create_test_pool().await;
+            seed(&pool).await;
+
+            for viewer in [anonymous, invalid_token] {
+                assert_eq!(page_status(&pool, viewer(), "public-page").await, StatusCode::OK);
+                assert_eq!(page_status(&pool, viewer(), "loesungen").await, StatusCode::NOT_FOUND);
+                assert_eq!(post_status(&pool, viewer(), "public-page").await, StatusCode::OK);
+                assert_eq!(post_status(&pool, viewer(), "loesungen").await, StatusCode::NOT_FOUND);
+                assert_eq!(archive_status(&pool, viewer(), "loesungen").await, StatusCode::NOT_FOUND);
+                assert_eq!(nav_slugs(&pool, viewer()).await, vec!["public-page"]);
+                assert_eq!(published_slugs(&pool, viewer()).await, vec!["public-page"]);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_signed_in_viewers_see_members_only_pages() {
+            let pool = create_test_pool().await;
+            seed(&pool).await;
+
+            for viewer in [member, admin] {
+                assert_eq!(page_status(&pool, viewer(), "loesungen").await, StatusCode::OK);
+                assert_eq!(post_status(&pool, viewer(), "loesungen").await, StatusCode::OK);
+                assert_eq!(archive_status(&pool, viewer(), "loesungen").await, StatusCode::OK);
+
+                let mut nav = nav_slugs(&pool, viewer()).await;
+                nav.sort();
+                assert_eq!(nav, vec!["loesungen", "public-page"]);
+                assert_eq!(published_slugs(&pool, viewer()).await.len(), 2);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_scheduled_posts_are_not_found() {
+            let pool = create_test_pool().await;
+            seed(&pool).await;
+            let page = repositories::pages::get_site_page_by_slug(&pool, "public-page")
+                .await
+                .expect("load page")
+                .expect("page exists");
+            repositories::posts::create_site_post(
+                &pool,
+                &page.id,
+                CreateSitePostRequest {
+                    title: "Bald".to_string(),
+                    slug: "bald".to_string(),
+                    excerpt: None,
+                    content_markdown: "Noch geheim".to_string(),
+                    is_published: true,
+                    allow_comments: true,
+                    published_at: Some("2999-01-01T00:00:00Z".parse().unwrap()),
+                    order_index: None,
+                },
+            )
+            .await
+            .expect("create post");
+
+            let scheduled = get_published_post_by_slug(
+                anonymous(),
+                State(pool.clone()),
+                Path(("public-page".to_string(), "bald".to_string())),
+            )
+            .await;
+            assert_eq!(status(scheduled), StatusCode::NOT_FOUND);
+
+            let Json(page) = get_published_page_by_slug(
+                anonymous(),
+                State(pool.clone()),
+                Path("public-page".to_string()),
+                Query(PublicPostListQuery::default()),
+            )
+            .await
+            .expect("page");
+            let slugs: Vec<&str> = page.posts.iter().map(|post| post.slug.as_str()).collect();
+            assert_eq!(slugs, ["post"]);
+
+            let Json(post) = get_published_post_by_slug(
+                anonymous(),
+                State(pool.clone()),
+                Path(("public-page".to_string(), "post".to_string())),
+            )
+            .await
+            .expect("post");
+            assert!(post.next.is_none());
+        }
+
+        #[tokio::test]
+        async fn test_public_page_carries_seo_fields() {
+            let pool = create_test_pool().await;
+            let claims = auth::test_admin_claims();
+
+            for (slug, meta_title, og_image) in [
+                ("mit-meta", " Shell Grundlagen ", "/uploads/share.png"),
+                ("ohne-meta", "   ", ""),
+            ] {
+                let _ = create_site_page(
+                    claims.clone(),
+                    State(pool.clone()),
+                    ValidatedJson(CreateSitePageRequest {
+                        slug: slug.to_string(),
+                        title: slug.to_string(),
+                        description: None,
+                        nav_label: None,
+                        show_in_nav: false,
+                        order_index: None,
+                        is_published: true,
+                        visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+                        hero: Value::Null,
+                        layout: Value::Null,
+                        meta_title: Some(meta_title.to_string()),
+                        meta_description: None,
+                        og_image: Some(og_image.to_string()),
+                    }),
+                )
+                .await
+                .expect("create page");
+            }
+
+            let Json(with_meta) = get_published_page_by_slug(
+                anonymous(),
+                State(pool.clone()),
+                Path("mit-meta".to_string()),
+                Query(PublicPostListQuery::default()),
+            )
+            .await
+            .expect("page");
+            let json = serde_json::to_value(&with_meta.page).expect("serialize");
+            assert_eq!(json["meta_title"], "Shell Grundlagen");
+            assert_eq!(json["meta_description"], Value::Null);
+            assert_eq!(json["og_image"], "/uploads/share.png");
+
+            let Json(without_meta) = get_published_page_by_slug(
+                anonymous(),
+                State(pool.clone()),
+                Path("ohne-meta".to_string()),
+                Query(PublicPostListQuery::default()),
+            )
+            .await
+            .expect("page");
+            assert_eq!(without_meta.page.meta_title, None);
+            assert_eq!(without_meta.page.og_image, None);
+        }
+    }
+}
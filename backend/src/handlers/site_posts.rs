@@ -1,81 +1,87 @@
 use crate::{
-    security::auth, db,
+    db,
+    error::{ensure_admin, AppError},
+    handlers::upload,
     models::{
-        CreateSitePostRequest, ErrorResponse, SitePostListResponse, SitePostResponse,
-        UpdateSitePostRequest,
+        BulkPostSelection, BulkPublishAction, BulkPublishPostsRequest, CreateSitePostRequest,
+        MoveSitePostRequest, PageParams, Paginated, SitePost, SitePostListResponse,
+        SitePostResponse, UpdateSitePostRequest,
     },
     repositories,
+    security::auth,
+    storage::{self, Storage},
+    validation::posts::{
+        sanitize_slug, validate_post_fields, MAX_CONTENT_LEN, MAX_EXCERPT_LEN, MAX_SLUG_LEN,
+        MAX_TITLE_LEN,
+    },
 };
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     Json,
 };
+use chrono::SubsecRound;
 use sqlx;
 
-const MAX_TITLE_LEN: usize = 200;
-const MAX_SLUG_LEN: usize = 100;
-const MAX_EXCERPT_LEN: usize = 500;
-const MAX_CONTENT_LEN: usize = 100_000;
-
-fn ensure_admin(claims: &auth::Claims) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    if claims.role != "admin" {
-        Err((
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: "Insufficient permissions".to_string(),
-            }),
-        ))
-    } else {
-        Ok(())
+const MAX_BULK_POSTS: usize = 200;
+
+/// Publishing a post makes the draft uploads it links to public and points
+/// its content at their public URLs. Failures are logged; the post stays
+/// published either way, and its old links redirect once the files move.
+///
+/// A scheduled post keeps its uploads private until it goes live, when
+/// [`publish_uploads_of_live_posts`] picks it up.
+async fn publish_linked_uploads(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    record: SitePost,
+) -> SitePost {
+    if !record.is_published {
+        return record;
+    }
+    match repositories::posts::is_post_publicly_visible(pool, &record.id).await {
+        Ok(true) => {}
+        Ok(false) => return record,
+        Err(err) => {
+            tracing::error!("Failed to check visibility of post {}: {}", record.id, err);
+            return record;
+        }
     }
-}
 
-fn map_sqlx_error(err: sqlx::Error, context: &str) -> (StatusCode, Json<ErrorResponse>) {
-    match err {
-        sqlx::Error::RowNotFound => (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("{context} not found"),
-            }),
-        ),
-        sqlx::Error::Protocol(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ),
-        sqlx::Error::Database(db_err) => {
-            if db_err.is_unique_violation() {
-                (
-                    StatusCode::CONFLICT,
-                    Json(ErrorResponse {
-                        error: db_err
-                            .constraint()
-                            .map(|c| format!("Duplicate value violates unique constraint '{c}'"))
-                            .unwrap_or_else(|| {
-                                "Duplicate value violates unique constraint".to_string()
-                            }),
-                    }),
-                )
-            } else {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "Database error".to_string(),
-                    }),
-                )
-            }
+    let content = match upload::publish_draft_uploads(pool, storage, &record.content_markdown).await {
+        Ok(Some(content)) => content,
+        Ok(None) => return record,
+        Err(err) => {
+            tracing::error!("Failed to publish uploads of post {}: {}", record.id, err);
+            return record;
+        }
+    };
+
+    let update = UpdateSitePostRequest {
+        content_markdown: Some(content),
+        ..Default::default()
+    };
+    match repositories::posts::update_site_post(pool, &record.id, update).await {
+        Ok(updated) => updated,
+        Err(err) => {
+            tracing::error!("Failed to update upload links of post {}: {}", record.id, err);
+            record
         }
-        other => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Unexpected database error: {other}"),
-            }),
-        ),
     }
 }
 
+/// Publishes the draft uploads of posts that became visible since they were
+/// saved, i.e. scheduled posts whose time has come.
+pub(crate) async fn publish_uploads_of_live_posts(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+) -> Result<(), sqlx::Error> {
+    for post in repositories::posts::list_visible_posts_linking_drafts(pool).await? {
+        publish_linked_uploads(pool, storage, post).await;
+    }
+    Ok(())
+}
+
 fn map_post(record: crate::models::SitePost) -> SitePostResponse {
     SitePostResponse {
         id: record.id,
@@ -83,7 +89,10 @@ fn map_post(record: crate::models::SitePost) -> SitePostResponse {
         title: record.title,
         slug: record.slug,
         excerpt: record.excerpt,
+        excerpt_auto: record.excerpt_auto,
         content_markdown: record.content_markdown,
+        word_count: record.word_count,
+        reading_time_minutes: record.reading_time_minutes,
         is_published: record.is_published,
         published_at: record.published_at,
         order_index: record.order_index,
@@ -93,124 +102,41 @@ fn map_post(record: crate::models::SitePost) -> SitePostResponse {
     }
 }
 
-fn sanitize_slug(slug: &str) -> String {
-    slug.trim().to_lowercase()
-}
-
-fn validate_post_fields(
-    title: &str,
-    slug: &str,
-    excerpt: Option<&str>,
-    content: &str,
-) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
-    let title = title.trim();
-    if title.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Title cannot be empty".to_string(),
-            }),
-        ));
-    }
-    if title.len() > MAX_TITLE_LEN {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Title too long (max {MAX_TITLE_LEN} characters)"),
-            }),
-        ));
-    }
-
-    let slug = slug.trim().to_lowercase();
-    if slug.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Slug cannot be empty".to_string(),
-            }),
-        ));
-    }
-    if slug.len() > MAX_SLUG_LEN {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Slug too long (max {MAX_SLUG_LEN} characters)"),
-            }),
-        ));
-    }
-
-    if let Some(excerpt) = excerpt {
-        if excerpt.len() > MAX_EXCERPT_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Excerpt too long (max {MAX_EXCERPT_LEN} characters)"),
-                }),
-            ));
-        }
-    }
-
-    if content.len() > MAX_CONTENT_LEN {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Content too long (max {MAX_CONTENT_LEN} characters)"),
-            }),
-        ));
-    }
-
-    Ok(())
-}
-
 pub async fn list_posts_for_page(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(page_id): Path<String>,
-) -> Result<Json<SitePostListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    page: PageParams,
+) -> Result<Json<Paginated<SitePostResponse>>, AppError> {
     ensure_admin(&claims)?;
 
     repositories::pages::get_site_page_by_id(&pool, &page_id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Site page not found".to_string(),
-                }),
-            )
-        })?;
-
-    let posts = repositories::posts::list_site_posts_for_page(&pool, &page_id)
-        .await
-        .map_err(|err| map_sqlx_error(err, "Site post"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?
+        .ok_or_else(|| AppError::NotFound("Site page not found".to_string()))?;
 
-    let mut items = Vec::with_capacity(posts.len());
-    for post in posts {
-        items.push(map_post(post));
-    }
+    let (posts, total) = tokio::try_join!(
+        repositories::posts::list_site_posts_for_page(&pool, &page_id, page.limit, page.offset),
+        repositories::posts::count_site_posts_for_page(&pool, &page_id),
+    )
+    .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
 
-    Ok(Json(SitePostListResponse { items }))
+    let items = posts.into_iter().map(map_post).collect();
+
+    Ok(Json(Paginated::new(items, total, page.limit, page.offset)))
 }
 
 pub async fn get_post(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
-) -> Result<Json<SitePostResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SitePostResponse>, AppError> {
     ensure_admin(&claims)?;
 
     let post = repositories::posts::get_site_post_by_id(&pool, &id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site post"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Site post not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(|err| AppError::from_sqlx(err, "Site post"))?
+        .ok_or_else(|| AppError::NotFound("Site post not found".to_string()))?;
 
     Ok(Json(map_post(post)))
 }
@@ -220,7 +146,7 @@ pub async fn create_post(
     State(pool): State<db::DbPool>,
     Path(page_id): Path<String>,
     Json(payload): Json<CreateSitePostRequest>,
-) -> Result<Json<SitePostResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SitePostResponse>, AppError> {
     ensure_admin(&claims)?;
 
     let trimmed_title = payload.title.trim().to_string();
@@ -231,19 +157,13 @@ pub async fn create_post(
         &sanitized_slug,
         excerpt,
         &payload.content_markdown,
-    )?;
+    )
+    .map_err(AppError::validation)?;
 
     repositories::pages::get_site_page_by_id(&pool, &page_id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site page"))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Site page not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?
+        .ok_or_else(|| AppError::NotFound("Site page not found".to_string()))?;
 
     let record = repositories::posts::create_site_post(
         &pool,
@@ -260,7 +180,8 @@ pub async fn create_post(
         },
     )
     .await
-    .map_err(|err| map_sqlx_error(err, "Site post"))?;
+    .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
+    let record = publish_linked_uploads(&pool, storage::get().as_ref(), record).await;
 
     Ok(Json(map_post(record)))
 }
@@ -270,58 +191,45 @@ pub async fn update_post(
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
     Json(payload): Json<UpdateSitePostRequest>,
-) -> Result<Json<SitePostResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SitePostResponse>, AppError> {
     ensure_admin(&claims)?;
 
     if let Some(ref slug) = payload.slug {
         let sanitized = sanitize_slug(slug);
         if sanitized.is_empty() {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Slug cannot be empty".to_string(),
-                }),
-            ));
+            return Err(AppError::invalid_field("slug", "Slug cannot be empty"));
         }
         if sanitized.len() > MAX_SLUG_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Slug too long (max {MAX_SLUG_LEN} characters)"),
-                }),
+            return Err(AppError::invalid_field(
+                "slug",
+                format!("Slug too long (max {MAX_SLUG_LEN} characters)"),
             ));
         }
     }
 
     if let Some(ref excerpt) = payload.excerpt {
         if excerpt.len() > MAX_EXCERPT_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Excerpt too long (max {MAX_EXCERPT_LEN} characters)"),
-                }),
+            return Err(AppError::invalid_field(
+                "excerpt",
+                format!("Excerpt too long (max {MAX_EXCERPT_LEN} characters)"),
             ));
         }
     }
 
     if let Some(ref content) = payload.content_markdown {
         if content.len() > MAX_CONTENT_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Content too long (max {MAX_CONTENT_LEN} characters)"),
-                }),
+            return Err(AppError::invalid_field(
+                "content_markdown",
+                format!("Content too long (max {MAX_CONTENT_LEN} characters)"),
             ));
         }
     }
 
     if let Some(ref title) = payload.title {
         if title.trim().is_empty() || title.trim().len() > MAX_TITLE_LEN {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Title must be 1..={MAX_TITLE_LEN} characters"),
-                }),
+            return Err(AppError::invalid_field(
+                "title",
+                format!("Title must be 1..={MAX_TITLE_LEN} characters"),
             ));
         }
     }
@@ -333,7 +241,8 @@ pub async fn update_post(
 
     let record = repositories::posts::update_site_post(&pool, &id, payload)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site post"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
+    let record = publish_linked_uploads(&pool, storage::get().as_ref(), record).await;
 
     Ok(Json(map_post(record)))
 }
@@ -342,12 +251,242 @@ pub async fn delete_post(
     claims: auth::Claims,
     State(pool): State<db::DbPool>,
     Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<StatusCode, AppError> {
     ensure_admin(&claims)?;
 
     repositories::posts::delete_site_post(&pool, &id)
         .await
-        .map_err(|err| map_sqlx_error(err, "Site post"))?;
+        .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+pub async fn bulk_publish_posts(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(page_id): Path<String>,
+    Json(payload): Json<BulkPublishPostsRequest>,
+) -> Result<Json<SitePostListResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let ids = match payload.ids {
+        BulkPostSelection::All(_) => None,
+        BulkPostSelection::Ids(ids) => {
+            let mut unique: Vec<String> = Vec::with_capacity(ids.len());
+            for id in ids {
+                let id = id.trim().to_string();
+                if !id.is_empty() && !unique.contains(&id) {
+                    unique.push(id);
+                }
+            }
+            if unique.is_empty() {
+                return Err(AppError::invalid_field(
+                    "ids",
+                    "At least one post id is required",
+                ));
+            }
+            if unique.len() > MAX_BULK_POSTS {
+                return Err(AppError::invalid_field(
+                    "ids",
+                    format!("Too many posts (max {MAX_BULK_POSTS} per request)"),
+                ));
+            }
+            Some(unique)
+        }
+    };
+
+    let published_at = match payload.published_at.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => {
+            let parsed = chrono::DateTime::parse_from_rfc3339(value).map_err(|_| {
+                AppError::invalid_field(
+                    "published_at",
+                    "published_at must be an RFC 3339 timestamp",
+                )
+            })?;
+            Some(parsed.with_timezone(&chrono::Utc))
+        }
+        _ => None,
+    };
+    if published_at.is_some() && payload.action == BulkPublishAction::Unpublish {
+        return Err(AppError::invalid_field(
+            "published_at",
+            "published_at can only be supplied when publishing",
+        ));
+    }
+
+    repositories::pages::get_site_page_by_id(&pool, &page_id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Site page"))?
+        .ok_or_else(|| AppError::NotFound("Site page not found".to_string()))?;
+
+    let now = chrono::Utc::now().trunc_subsecs(0);
+    let posts = repositories::posts::bulk_set_posts_published(
+        &pool,
+        &page_id,
+        ids.as_deref(),
+        payload.action == BulkPublishAction::Publish,
+        published_at,
+        now,
+        MAX_BULK_POSTS,
+    )
+    .await
+    .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
+
+    let storage = storage::get();
+    let mut items = Vec::with_capacity(posts.len());
+    for post in posts {
+        items.push(map_post(publish_linked_uploads(&pool, storage.as_ref(), post).await));
+    }
+
+    Ok(Json(SitePostListResponse { items }))
+}
+
+pub async fn move_post(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+    Json(payload): Json<MoveSitePostRequest>,
+) -> Result<Json<SitePostResponse>, AppError> {
+    ensure_admin(&claims)?;
+
+    let target_page_id = payload.target_page_id.trim();
+    if target_page_id.is_empty() {
+        return Err(AppError::invalid_field(
+            "target_page_id",
+            "Target page id cannot be empty",
+        ));
+    }
+
+    repositories::posts::get_site_post_by_id(&pool, &id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Site post"))?
+        .ok_or_else(|| AppError::NotFound("Site post not found".to_string()))?;
+
+    repositories::pages::get_site_page_by_id(&pool, target_page_id)
+        .await
+        .map_err(|err| AppError::from_sqlx(err, "Target page"))?
+        .ok_or_else(|| AppError::NotFound("Target page not found".to_string()))?;
+
+    let outcome =
+        repositories::posts::move_site_post(&pool, &id, target_page_id, payload.keep_slug)
+            .await
+            .map_err(|err| AppError::from_sqlx(err, "Site post"))?;
+
+    match outcome {
+        repositories::posts::PostMoveOutcome::Moved(post) => Ok(Json(map_post(*post))),
+        repositories::posts::PostMoveOutcome::SlugConflict => Err(AppError::Conflict(
+            "A post with this slug already exists on the target page".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::models::{CreateSitePageRequest, UPLOAD_VISIBILITY_DRAFT, UPLOAD_VISIBILITY_PUBLIC};
+    use crate::repositories::uploads::NewUpload;
+    use crate::storage::LocalStorage;
+
+    #[tokio::test]
+    async fn test_scheduled_post_keeps_uploads_private_until_it_goes_live() {
+        let pool = create_test_pool().await;
+        let dir = std::env::temp_dir().join(format!("scheduled-uploads-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&dir);
+
+        storage
+            .put("private/shot.png", b"png".to_vec(), "image/png")
+            .await
+            .expect("store draft");
+        repositories::uploads::insert_upload(
+            &pool,
+            &NewUpload {
+                filename: "private/shot.png",
+                original_filename: "shot.png",
+                mime_type: "image/png",
+                kind: "image",
+                visibility: UPLOAD_VISIBILITY_DRAFT,
+                size_bytes: 3,
+                original_size_bytes: None,
+                dimensions: None,
+                alt_text: None,
+                caption: None,
+                uploaded_by: None,
+            },
+        )
+        .await
+        .expect("insert draft");
+
+        let page = repositories::pages::create_site_page(
+            &pool,
+            CreateSitePageRequest {
+                slug: "blog".to_string(),
+                title: "Blog".to_string(),
+                description: None,
+                nav_label: None,
+                show_in_nav: false,
+                order_index: None,
+                is_published: true,
+                visibility: "public".to_string(),
+                hero: serde_json::json!({}),
+                layout: serde_json::json!({}),
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("create page");
+        let post = repositories::posts::create_site_post(
+            &pool,
+            &page.id,
+            CreateSitePostRequest {
+                title: "Geplant".to_string(),
+                slug: "geplant".to_string(),
+                excerpt: None,
+                content_markdown: "![shot](/uploads/private/shot.png)".to_string(),
+                is_published: true,
+                allow_comments: true,
+                published_at: Some(chrono::Utc::now() + chrono::Duration::days(1)),
+                order_index: None,
+            },
+        )
+        .await
+        .expect("create post");
+
+        let post = publish_linked_uploads(&pool, &storage, post).await;
+        assert_eq!(post.content_markdown, "![shot](/uploads/private/shot.png)");
+        publish_uploads_of_live_posts(&pool, &storage)
+            .await
+            .expect("job run");
+        let upload = repositories::uploads::find_upload(&pool, "private/shot.png")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(upload.visibility, UPLOAD_VISIBILITY_DRAFT);
+        assert!(dir.join("private/shot.png").exists());
+
+        // The scheduled time passes
+        sqlx::query(
+            "UPDATE site_posts SET published_at = datetime('now', '-1 minute') WHERE id = ?",
+        )
+        .bind(&post.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        publish_uploads_of_live_posts(&pool, &storage)
+            .await
+            .expect("job run");
+
+        let post = repositories::posts::get_site_post_by_id(&pool, &post.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(post.content_markdown, "![shot](/uploads/shot.png)");
+        let upload = repositories::uploads::find_upload(&pool, "shot.png").await.unwrap().unwrap();
+        assert_eq!(upload.visibility, UPLOAD_VISIBILITY_PUBLIC);
+        assert!(dir.join("shot.png").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,166 +1,2428 @@
 use crate::{
+    db,
+    error::ensure_admin,
+    handlers::{content_health::ReferenceScanner, upload_quota},
+    middleware::{
+        body_limit::{body_limits, payload_too_large},
+        security::parse_env_bool,
+        uploads::{content_type_for, SVG_MIME_TYPE},
+    },
     security::auth,
-    models::{ErrorResponse, UploadResponse},
+    models::{
+        ErrorResponse, UpdateUploadMetaRequest, Upload, UploadInUseResponse, UploadItemResponse,
+        UploadListResponse, UploadMetaResponse, UploadReference, UploadResponse, UploadResult,
+        UploadVariant,
+        UPLOAD_KIND_FILE, UPLOAD_KIND_IMAGE, UPLOAD_VISIBILITY_DRAFT, UPLOAD_VISIBILITY_PUBLIC,
+    },
+    repositories::{self, uploads::NewUpload},
+    storage::{self, upload_url, Storage},
+    utils::{
+        content_refs::{
+            classify_route, is_plain_upload_name, ReferenceLocation, ReferenceTarget, RouteKind,
+            PRIVATE_UPLOAD_DIR, UPLOADS_PREFIX, UPLOAD_FILES_DIR,
+        },
+        image_metadata, image_size, png_decode, svg_sanitize, webp_encode,
+    },
 };
 use axum::{
-    extract::{Multipart, State},
-    http::StatusCode,
+    extract::{
+        multipart::{Field, MultipartError},
+        Multipart, Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
-use std::path::PathBuf;
-use tokio::fs;
+use serde::Deserialize;
 use uuid::Uuid;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-const MAX_FILE_SIZE: usize = 10 * 1024 * 1024; // 10MB
+/// Default `UPLOAD_MAX_PIXELS`: enough for any screenshot, small enough
+/// that decoding the image stays cheap.
+const DEFAULT_MAX_PIXELS: u64 = 25_000_000;
+/// Default `UPLOAD_SESSION_MAX_BYTES`.
+const DEFAULT_MAX_SESSION_BYTES: u64 = 256 * 1024 * 1024;
 const ALLOWED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+/// Accepted only with `UPLOAD_ALLOW_SVG=true`, and always sanitized.
+const SVG_EXTENSION: &str = "svg";
+const DEFAULT_LIST_LIMIT: i64 = 50;
+/// Upper bound on references listed when an upload is still in use.
+const MAX_LISTED_REFERENCES: usize = 100;
+const MAX_LIST_LIMIT: i64 = 200;
 
+/// A downloadable file type accepted by `POST /api/upload/file`.
+pub(crate) struct FileType {
+    pub extension: &'static str,
+    pub mime_type: &'static str,
+    /// Whether `infer` must detect `mime_type`. Plain text has no signature
+    /// and is checked for valid UTF-8 instead.
+    magic: bool,
+    pub max_bytes: usize,
+}
+
+const TEXT_MIME_TYPE: &str = "text/plain; charset=utf-8";
+pub(crate) const FILE_TYPES: &[FileType] = &[
+    FileType { extension: "pdf", mime_type: "application/pdf", magic: true, max_bytes: 8 * 1024 * 1024 },
+    FileType { extension: "zip", mime_type: "application/zip", magic: true, max_bytes: 8 * 1024 * 1024 },
+    FileType { extension: "gz", mime_type: "application/gzip", magic: true, max_bytes: 8 * 1024 * 1024 },
+    FileType { extension: "txt", mime_type: TEXT_MIME_TYPE, magic: false, max_bytes: 1024 * 1024 },
+    FileType { extension: "sh", mime_type: TEXT_MIME_TYPE, magic: false, max_bytes: 256 * 1024 },
+];
+
+/// Longest original file name kept in the uploads table.
+const MAX_ORIGINAL_NAME_CHARS: usize = 255;
+pub(crate) const MAX_ALT_TEXT_CHARS: usize = 300;
+pub(crate) const MAX_CAPTION_CHARS: usize = 1000;
+
+const DEFAULT_WEBP_QUALITY: u8 = 80;
+/// How long a WebP conversion may take before the original is stored instead.
+const WEBP_TIME_BUDGET: Duration = Duration::from_secs(10);
+/// Suffix of the pre-conversion copy kept with `UPLOAD_KEEP_ORIGINAL=true`,
+/// e.g. `<uuid>-original.png` next to `<uuid>.webp`.
+const KEPT_ORIGINAL_SUFFIX: &str = "-original";
+
+/// Keeps the client-supplied name for display only: path components are
+/// dropped and the length is capped.
+fn display_name(file_name: &str) -> String {
+    let base = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let base = if base.is_empty() { "unknown" } else { base };
+    base.chars().take(MAX_ORIGINAL_NAME_CHARS).collect()
+}
+
+/// Processing applied to new uploads, read from the environment.
+#[derive(Debug, Clone)]
+pub(crate) struct UploadOptions {
+    /// `UPLOAD_STRIP_METADATA` (default true): remove EXIF/XMP and similar
+    /// metadata from images before storing them.
+    pub strip_metadata: bool,
+    /// `UPLOAD_ALLOW_SVG` (default false): accept sanitized SVG files.
+    pub allow_svg: bool,
+    /// `UPLOAD_CONVERT_TO_WEBP` (default false): store PNG images as WebP
+    /// when that makes them smaller.
+    pub convert_to_webp: bool,
+    /// `UPLOAD_WEBP_QUALITY` (1-100, default 80). Below 90 low color bits
+    /// are rounded off before encoding.
+    pub webp_quality: u8,
+    /// `UPLOAD_KEEP_ORIGINAL` (default false): also keep the image as it was
+    /// before conversion.
+    pub keep_original: bool,
+    pub limits: UploadLimits,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            strip_metadata: true,
+            allow_svg: false,
+            convert_to_webp: false,
+            webp_quality: DEFAULT_WEBP_QUALITY,
+            keep_original: false,
+            limits: UploadLimits::default(),
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            strip_metadata: parse_env_bool("UPLOAD_STRIP_METADATA", defaults.strip_metadata),
+            allow_svg: parse_env_bool("UPLOAD_ALLOW_SVG", defaults.allow_svg),
+            convert_to_webp: parse_env_bool("UPLOAD_CONVERT_TO_WEBP", defaults.convert_to_webp),
+            webp_quality: std::env::var("UPLOAD_WEBP_QUALITY")
+                .ok()
+                .and_then(|value| match value.trim().parse::<u8>() {
+                    Ok(quality @ 1..=100) => Some(quality),
+                    _ => {
+                        tracing::warn!(value = %value, "Invalid UPLOAD_WEBP_QUALITY; using default");
+                        None
+                    }
+                })
+                .unwrap_or(defaults.webp_quality),
+            keep_original: parse_env_bool("UPLOAD_KEEP_ORIGINAL", defaults.keep_original),
+            limits: upload_limits().clone(),
+        }
+    }
+
+    pub(crate) fn allowed_extensions(&self) -> Vec<&'static str> {
+        let mut allowed = ALLOWED_EXTENSIONS.to_vec();
+        if self.allow_svg {
+            allowed.push(SVG_EXTENSION);
+        }
+        allowed
+    }
+}
+
+/// Size limits for image uploads, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadLimits {
+    /// `UPLOAD_MAX_BYTES`: largest image accepted.
+    pub max_bytes: usize,
+    /// `UPLOAD_MAX_BYTES_<EXT>` overrides, e.g. `UPLOAD_MAX_BYTES_GIF`.
+    pub max_bytes_by_extension: Vec<(&'static str, usize)>,
+    /// `UPLOAD_MAX_PIXELS`: largest width × height accepted.
+    pub max_pixels: u64,
+    /// `UPLOAD_SESSION_MAX_BYTES`: largest download assembled from a
+    /// resumable upload session. Each chunk is a request of its own, so this
+    /// may exceed the admin request limit.
+    pub max_session_bytes: usize,
+    /// `UPLOAD_DAILY_BYTES_LIMIT`: bytes one user may upload per UTC day.
+    /// Unset means no limit.
+    pub daily_bytes: Option<u64>,
+    /// `UPLOAD_DAILY_FILES_LIMIT`: files one user may upload per UTC day.
+    pub daily_files: Option<u64>,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: body_limits().admin,
+            max_bytes_by_extension: Vec::new(),
+            max_pixels: DEFAULT_MAX_PIXELS,
+            max_session_bytes: DEFAULT_MAX_SESSION_BYTES as usize,
+            daily_bytes: None,
+            daily_files: None,
+        }
+    }
+}
+
+static UPLOAD_LIMITS: OnceLock<UploadLimits> = OnceLock::new();
+
+impl UploadLimits {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the limits through `lookup`. Byte limits must lie between 1
+    /// and the admin body limit, since larger bodies never reach the handler;
+    /// only the session limit may go beyond.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let positive = |key: &str| -> Result<Option<u64>, String> {
+            let Some(raw) = lookup(key).filter(|raw| !raw.trim().is_empty()) else {
+                return Ok(None);
+            };
+            match raw.trim().parse::<u64>() {
+                Ok(value) if value > 0 => Ok(Some(value)),
+                _ => Err(format!("{} must be a positive integer, got '{}'", key, raw)),
+            }
+        };
+        let admin_limit = body_limits().admin;
+        let byte_limit = |key: &str| -> Result<Option<usize>, String> {
+            match positive(key)? {
+                Some(value) if value > admin_limit as u64 => Err(format!(
+                    "{} must not exceed the admin request limit of {} bytes",
+                    key, admin_limit
+                )),
+                value => Ok(value.map(|value| value as usize)),
+            }
+        };
+
+        let mut max_bytes_by_extension = Vec::new();
+        for ext in ALLOWED_EXTENSIONS.iter().chain([&SVG_EXTENSION]) {
+            let key = format!("UPLOAD_MAX_BYTES_{}", ext.to_ascii_uppercase());
+            if let Some(limit) = byte_limit(&key)? {
+                max_bytes_by_extension.push((*ext, limit));
+            }
+        }
+
+        Ok(Self {
+            max_bytes: byte_limit("UPLOAD_MAX_BYTES")?.unwrap_or(defaults.max_bytes),
+            max_bytes_by_extension,
+            max_pixels: positive("UPLOAD_MAX_PIXELS")?.unwrap_or(defaults.max_pixels),
+            max_session_bytes: positive("UPLOAD_SESSION_MAX_BYTES")?
+                .map_or(defaults.max_session_bytes, |value| value as usize),
+            daily_bytes: positive("UPLOAD_DAILY_BYTES_LIMIT")?,
+            daily_files: positive("UPLOAD_DAILY_FILES_LIMIT")?,
+        })
+    }
+
+    /// Byte limit for a file with extension `ext`.
+    pub fn max_bytes_for(&self, ext: &str) -> usize {
+        self.max_bytes_by_extension
+            .iter()
+            .find(|(limited, _)| *limited == ext)
+            .map_or(self.max_bytes, |(_, limit)| *limit)
+    }
+}
+
+/// Validates the upload limits and fixes them for the process. Called once
+/// at startup so a bad value fails fast instead of on the first upload.
+pub fn init_upload_limits() -> Result<(), String> {
+    UPLOAD_LIMITS
+        .set(UploadLimits::from_env()?)
+        .map_err(|_| "Upload limits already initialized".to_string())
+}
+
+pub(crate) fn upload_limits() -> &'static UploadLimits {
+    UPLOAD_LIMITS.get_or_init(|| {
+        UploadLimits::from_env().unwrap_or_else(|err| {
+            tracing::error!("{}; using default upload limits", err);
+            UploadLimits::default()
+        })
+    })
+}
+
+/// Uploads every `file` field of the request. Each file is validated and
+/// stored on its own, so one bad file does not fail the batch; the response
+/// lists the outcome per file, in request order.
 pub async fn upload_image(
     claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    let options = UploadOptions::from_env();
+    let response = store_uploads(&pool, storage::get().as_ref(), &options, &claims, multipart)
+        .await
+        .into_response();
+    Ok(upload_quota::with_quota_headers(&pool, &options.limits, &claims.sub, response).await)
+}
+
+/// Alt text and caption sent with an image, and the first problem found
+/// while reading it.
+#[derive(Debug, Default)]
+pub(crate) struct ImageMeta {
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
+    pub error: Option<(StatusCode, String)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct PendingImage {
+    pub file_name: String,
+    pub ext: String,
+    pub data: Vec<u8>,
+    pub meta: ImageMeta,
+}
+
+/// Reads all files of a multipart upload before storing them. `alt_text`
+/// and `caption` fields describe the file they follow; fields sent before
+/// the first file belong to it. `visibility=draft` stores the whole batch
+/// as private drafts.
+async fn store_uploads(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    options: &UploadOptions,
+    claims: &auth::Claims,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Vec<UploadResult>>), (StatusCode, Json<ErrorResponse>)> {
+    let mut images: Vec<PendingImage> = Vec::new();
+    let mut leading_meta = ImageMeta::default();
+    let mut visibility = UPLOAD_VISIBILITY_PUBLIC;
+    let mut total_bytes = 0;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(|err| {
+        multipart_error(err, StatusCode::BAD_REQUEST, "Failed to process multipart field")
+    })? {
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            "file" => {
+                let file_name = field.file_name().unwrap_or("unknown").to_string();
+
+                // Simple extension validation
+                let ext = std::path::Path::new(&file_name)
+                    .extension()
+                    .and_then(|os_str| os_str.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+
+                let mut image = PendingImage {
+                    file_name,
+                    ext,
+                    data: Vec::new(),
+                    meta: if images.is_empty() {
+                        std::mem::take(&mut leading_meta)
+                    } else {
+                        ImageMeta::default()
+                    },
+                };
+
+                let allowed_extensions = options.allowed_extensions();
+                if !allowed_extensions.contains(&image.ext.as_str()) {
+                    image.meta.error.get_or_insert((
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid file extension. Allowed: {:?}", allowed_extensions),
+                    ));
+                } else {
+                    // Per-file cap, further limited by what is left of the
+                    // request body
+                    let max_bytes = options
+                        .limits
+                        .max_bytes_for(&image.ext)
+                        .min(body_limits().admin.saturating_sub(total_bytes));
+                    match read_field(&mut field, max_bytes).await {
+                        Ok(data) => {
+                            total_bytes += data.len();
+                            image.data = data;
+                        }
+                        Err((status, Json(err))) if status.is_client_error() => {
+                            image.meta.error.get_or_insert((status, err.error));
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                images.push(image);
+            }
+            "visibility" => {
+                let value = read_text_field(&mut field, "Visibility", 16).await?;
+                visibility = match value.as_deref() {
+                    None | Some(UPLOAD_VISIBILITY_PUBLIC) => UPLOAD_VISIBILITY_PUBLIC,
+                    Some(UPLOAD_VISIBILITY_DRAFT) => UPLOAD_VISIBILITY_DRAFT,
+                    Some(other) => {
+                        return Err((
+                            StatusCode::BAD_REQUEST,
+                            Json(ErrorResponse {
+                                error: format!("Invalid visibility '{}'", other),
+                            }),
+                        ));
+                    }
+                };
+            }
+            "alt_text" | "caption" => {
+                let meta = match images.last_mut() {
+                    Some(image) => &mut image.meta,
+                    None => &mut leading_meta,
+                };
+                let read = if name == "alt_text" {
+                    read_text_field(&mut field, "Alt text", MAX_ALT_TEXT_CHARS)
+                        .await
+                        .map(|value| meta.alt_text = value)
+                } else {
+                    read_text_field(&mut field, "Caption", MAX_CAPTION_CHARS)
+                        .await
+                        .map(|value| meta.caption = value)
+                };
+                if let Err((status, Json(err))) = read {
+                    meta.error.get_or_insert((status, err.error));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if images.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "No file found in request".to_string(),
+            }),
+        ));
+    }
+
+    let mut results = Vec::with_capacity(images.len());
+    let mut first_failure = None;
+    for mut image in images {
+        let original_filename = display_name(&image.file_name);
+        let outcome = match image.meta.error.take() {
+            Some(error) => Err(error),
+            None => store_image(pool, storage, options, claims, visibility, image)
+                .await
+                .map_err(|(status, Json(err))| (status, err.error)),
+        };
+        results.push(match outcome {
+            Ok(stored) => UploadResult {
+                original_filename,
+                id: Some(stored.id),
+                url: Some(stored.url),
+                width: stored.width,
+                height: stored.height,
+                error: None,
+            },
+            Err((status, error)) => {
+                first_failure.get_or_insert(status);
+                UploadResult {
+                    original_filename,
+                    id: None,
+                    url: None,
+                    width: None,
+                    height: None,
+                    error: Some(error),
+                }
+            }
+        });
+    }
+
+    // Only a batch where nothing was stored counts as failed; it takes the
+    // status of its first error
+    let status = if results.iter().any(|result| result.error.is_none()) {
+        StatusCode::OK
+    } else {
+        first_failure.unwrap_or(StatusCode::BAD_REQUEST)
+    };
+    Ok((status, Json(results)))
+}
+
+/// Validates, processes and stores one image read by [`store_uploads`].
+pub(crate) async fn store_image(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    options: &UploadOptions,
+    claims: &auth::Claims,
+    visibility: &str,
+    image: PendingImage,
+) -> Result<UploadResponse, (StatusCode, Json<ErrorResponse>)> {
+    let PendingImage {
+        file_name,
+        ext,
+        data,
+        meta: ImageMeta {
+            alt_text, caption, ..
+        },
+    } = image;
+
+    // Validate file content using magic bytes; SVG is text, so it gets
+    // its own sniffer
+    let mime_type = if ext == SVG_EXTENSION {
+        if !svg_sanitize::looks_like_svg(&data) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "File is not an SVG image".to_string(),
+                }),
+            ));
+        }
+        SVG_MIME_TYPE.to_string()
+    } else if let Some(kind) = infer::get(&data) {
+        let mime = kind.mime_type();
+        let detected_ext = kind.extension();
+
+        // Verify the detected extension matches our allowed list
+        if !ALLOWED_EXTENSIONS.contains(&detected_ext) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!(
+                        "File type '{}' not allowed. Detected: {}",
+                        detected_ext, mime
+                    ),
+                }),
+            ));
+        }
+
+        // Verify the detected extension matches the file extension (prevent spoofing)
+        // Note: infer might return "jpeg" for "jpg", so we need to be flexible or normalize
+        let normalized_detected = if detected_ext == "jpeg" {
+            "jpg"
+        } else {
+            detected_ext
+        };
+        let normalized_ext = if ext == "jpeg" { "jpg" } else { ext.as_str() };
+
+        if normalized_detected != normalized_ext {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!(
+                        "File extension mismatch. Expected '{}', but detected '{}'",
+                        ext, detected_ext
+                    ),
+                }),
+            ));
+        }
+
+        mime.to_string()
+    } else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Could not determine file type".to_string(),
+            }),
+        ));
+    };
+
+    upload_quota::enforce(pool, &options.limits, &claims.sub, data.len()).await?;
+
+    // Reject huge images from their header, before anything decodes them
+    if let Some((width, height)) = image_size::dimensions(&data) {
+        if u64::from(width) * u64::from(height) > options.limits.max_pixels {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Image dimensions {}x{} exceed the limit of {} pixels",
+                        width, height, options.limits.max_pixels
+                    ),
+                }),
+            ));
+        }
+    }
+
+    let original_size = data.len() as i64;
+    let data = if ext == SVG_EXTENSION {
+        process_blocking(data, sanitize_svg).await?
+    } else if options.strip_metadata {
+        process_blocking(data, strip_metadata).await?
+    } else {
+        data
+    };
+
+    let dir = if visibility == UPLOAD_VISIBILITY_DRAFT {
+        PRIVATE_UPLOAD_DIR
+    } else {
+        ""
+    };
+    let stem = format!("{}{}", dir, Uuid::new_v4());
+    let (data, ext, mime_type) = match convert_png_to_webp(options, &ext, &data).await {
+        Some(webp) => {
+            if options.keep_original {
+                let key = format!("{stem}{KEPT_ORIGINAL_SUFFIX}.{ext}");
+                if let Err(err) = storage.put(&key, data, &mime_type).await {
+                    tracing::warn!("Failed to keep original upload '{}': {}", key, err);
+                }
+            }
+            (webp, "webp".to_string(), "image/webp".to_string())
+        }
+        None => (data, ext, mime_type),
+    };
+    let new_filename = format!("{stem}.{ext}");
+    let size_bytes = data.len() as i64;
+    let original_filename = display_name(&file_name);
+    let upload = NewUpload {
+        filename: &new_filename,
+        original_filename: &original_filename,
+        mime_type: &mime_type,
+        kind: UPLOAD_KIND_IMAGE,
+        visibility,
+        size_bytes,
+        original_size_bytes: (original_size != size_bytes).then_some(original_size),
+        dimensions: image_size::dimensions(&data),
+        alt_text: alt_text.as_deref(),
+        caption: caption.as_deref(),
+        uploaded_by: Some(&claims.sub),
+    };
+    persist_upload(pool, storage, &upload, data).await
+}
+
+/// Uploads a downloadable file (PDF, archive or plain text) for course
+/// material. Files are kept below `files/` and always served as attachments.
+pub async fn upload_file(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    let limits = upload_limits();
+    let response = store_file(&pool, storage::get().as_ref(), limits, &claims, multipart)
+        .await
+        .into_response();
+    Ok(upload_quota::with_quota_headers(&pool, limits, &claims.sub, response).await)
+}
+
+async fn store_file(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    limits: &UploadLimits,
+    claims: &auth::Claims,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Ensure user is admin
-    if claims.role != "admin" {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| {
+            multipart_error(err, StatusCode::BAD_REQUEST, "Failed to process multipart field")
+        })?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let file_name = field.file_name().unwrap_or("unknown").to_string();
+        let ext = std::path::Path::new(&file_name)
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let Some(file_type) = FILE_TYPES.iter().find(|file_type| file_type.extension == ext) else {
+            let allowed: Vec<_> = FILE_TYPES.iter().map(|file_type| file_type.extension).collect();
+            return Err(bad_request(format!(
+                "Invalid file extension. Allowed: {:?}",
+                allowed
+            )));
+        };
+
+        let data = read_field(&mut field, file_type.max_bytes).await?;
+        return store_download(pool, storage, limits, claims, file_type, &file_name, data)
+            .await
+            .map(Json);
+    }
+
+    Err(bad_request("No file found in request".to_string()))
+}
+
+/// Checks a download against its [`FileType`] and stores it below `files/`.
+pub(crate) async fn store_download(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    limits: &UploadLimits,
+    claims: &auth::Claims,
+    file_type: &FileType,
+    file_name: &str,
+    data: Vec<u8>,
+) -> Result<UploadResponse, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let ext = file_type.extension;
+
+    let detected = infer::get(&data);
+    if file_type.magic {
+        if detected.map(|kind| kind.mime_type()) != Some(file_type.mime_type) {
+            return Err(bad_request(format!(
+                "File extension mismatch. Expected '{}', but detected '{}'",
+                ext,
+                detected.map_or("unknown", |kind| kind.extension())
+            )));
+        }
+    } else if let Some(kind) = detected {
+        return Err(bad_request(format!(
+            "File extension mismatch. Expected '{}', but detected '{}'",
+            ext,
+            kind.extension()
+        )));
+    } else if std::str::from_utf8(&data).is_err() || data.contains(&0) {
+        return Err(bad_request("File is not a UTF-8 text file".to_string()));
+    }
+    upload_quota::enforce(pool, limits, &claims.sub, data.len()).await?;
+
+    // Keep `.tar.gz` so the download still unpacks with the usual tools.
+    let stored_ext = if ext == "gz" && file_name.to_lowercase().ends_with(".tar.gz") {
+        "tar.gz"
+    } else {
+        file_type.extension
+    };
+    let new_filename = format!("{}{}.{}", UPLOAD_FILES_DIR, Uuid::new_v4(), stored_ext);
+    let original_filename = display_name(file_name);
+    let upload = NewUpload {
+        filename: &new_filename,
+        original_filename: &original_filename,
+        mime_type: file_type.mime_type,
+        kind: UPLOAD_KIND_FILE,
+        visibility: UPLOAD_VISIBILITY_PUBLIC,
+        size_bytes: data.len() as i64,
+        original_size_bytes: None,
+        dimensions: None,
+        alt_text: None,
+        caption: None,
+        uploaded_by: Some(&claims.sub),
+    };
+    persist_upload(pool, storage, &upload, data).await
+}
+
+/// Maps a multipart read error to `status`, unless the request body ran into
+/// the admin body limit, which stays a `413`.
+fn multipart_error(
+    err: MultipartError,
+    status: StatusCode,
+    context: &str,
+) -> (StatusCode, Json<ErrorResponse>) {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return payload_too_large(body_limits().admin).into();
+    }
+    (
+        status,
+        Json(ErrorResponse {
+            error: format!("{}: {}", context, err),
+        }),
+    )
+}
+
+/// Reads a multipart file field, failing as soon as it exceeds `max_bytes`.
+async fn read_field(
+    field: &mut Field<'_>,
+    max_bytes: usize,
+) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(|err| {
+        multipart_error(err, StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file chunk")
+    })? {
+        if data.len() + chunk.len() > max_bytes {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: format!("File too large. Max size: {} bytes", max_bytes),
+                }),
+            ));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Trims an alt text or caption, mapping blank values to `None`.
+pub(crate) fn normalize_text(
+    value: Option<&str>,
+    label: &str,
+    max_chars: usize,
+) -> Result<Option<String>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    if value.chars().count() > max_chars {
         return Err((
-            StatusCode::FORBIDDEN,
+            StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "Insufficient permissions".to_string(),
+                error: format!("{} must be at most {} characters", label, max_chars),
             }),
         ));
     }
+    Ok(Some(value.to_string()))
+}
 
-    while let Some(mut field) = multipart.next_field().await.map_err(|err| {
+/// Reads a text field sent alongside the file, see [`normalize_text`].
+async fn read_text_field(
+    field: &mut Field<'_>,
+    label: &str,
+    max_chars: usize,
+) -> Result<Option<String>, (StatusCode, Json<ErrorResponse>)> {
+    // A UTF-8 character takes at most four bytes
+    let data = read_field(field, max_chars * 4).await?;
+    let text = String::from_utf8(data).map_err(|_| {
         (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: format!("Failed to process multipart field: {}", err),
+                error: format!("{} must be valid UTF-8", label),
             }),
         )
-    })? {
-        let name = field.name().unwrap_or("").to_string();
+    })?;
+    normalize_text(Some(&text), label, max_chars)
+}
 
-        if name == "file" {
-            let file_name = field.file_name().unwrap_or("unknown").to_string();
+/// Stores `data` under `upload.filename` and records it. The stored file is
+/// removed again when the row cannot be written.
+async fn persist_upload(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    upload: &NewUpload<'_>,
+    data: Vec<u8>,
+) -> Result<UploadResponse, (StatusCode, Json<ErrorResponse>)> {
+    let save_failed = || {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to save file".to_string(),
+            }),
+        )
+    };
 
-            // Simple extension validation
-            let ext = std::path::Path::new(&file_name)
-                .extension()
-                .and_then(|os_str| os_str.to_str())
-                .unwrap_or("")
-                .to_lowercase();
+    let url = storage
+        .put(upload.filename, data, upload.mime_type)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to store upload '{}': {}", upload.filename, err);
+            save_failed()
+        })?;
 
-            if !ALLOWED_EXTENSIONS.contains(&ext.as_str()) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: format!("Invalid file extension. Allowed: {:?}", ALLOWED_EXTENSIONS),
-                    }),
-                ));
+    match repositories::uploads::insert_upload(pool, upload).await {
+        Ok(record) => Ok(UploadResponse {
+            url,
+            id: record.id,
+            width: record.width,
+            height: record.height,
+        }),
+        Err(err) => {
+            tracing::error!("Failed to record upload '{}': {}", upload.filename, err);
+            if let Err(remove_err) = storage.delete(upload.filename).await {
+                tracing::warn!(
+                    "Failed to remove untracked upload '{}': {}",
+                    upload.filename,
+                    remove_err
+                );
             }
+            Err(save_failed())
+        }
+    }
+}
+
+/// Re-encodes a PNG as WebP with `UPLOAD_CONVERT_TO_WEBP=true`. Returns
+/// `None`, so the PNG is stored as it is, when conversion is off, fails,
+/// runs over [`WEBP_TIME_BUDGET`] or would not make the file smaller.
+/// JPEG, GIF and WebP always pass through: a lossless re-encode of a photo
+/// only grows it.
+async fn convert_png_to_webp(options: &UploadOptions, ext: &str, data: &[u8]) -> Option<Vec<u8>> {
+    if !options.convert_to_webp || ext != "png" {
+        return None;
+    }
+    let png = data.to_vec();
+    let quality = options.webp_quality;
+    let task = tokio::task::spawn_blocking(move || -> Result<Option<Vec<u8>>, String> {
+        let Some(image) = png_decode::decode(&png)? else {
+            return Ok(None);
+        };
+        Ok(webp_encode::encode(image.width, image.height, &image.pixels, quality))
+    });
+
+    let webp = match tokio::time::timeout(WEBP_TIME_BUDGET, task).await {
+        Ok(Ok(Ok(webp))) => webp?,
+        Ok(Ok(Err(err))) => {
+            tracing::warn!("WebP conversion failed, storing PNG: {}", err);
+            return None;
+        }
+        Ok(Err(err)) => {
+            tracing::error!("WebP conversion task failed: {}", err);
+            return None;
+        }
+        Err(_) => {
+            tracing::warn!("WebP conversion took too long, storing PNG");
+            return None;
+        }
+    };
+    (webp.len() < data.len()).then_some(webp)
+}
+
+/// Removes metadata. Formats we do not rewrite are stored as they are.
+fn strip_metadata(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    image_metadata::strip_metadata(&data).map(|stripped| stripped.unwrap_or(data))
+}
+
+fn sanitize_svg(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    svg_sanitize::sanitize_svg(&data).map(String::into_bytes)
+}
+
+/// Runs CPU-bound processing of an upload off the async runtime. Processing
+/// errors mean the file is malformed and are reported as bad requests.
+async fn process_blocking(
+    data: Vec<u8>,
+    process: fn(Vec<u8>) -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let processed = tokio::task::spawn_blocking(move || process(data))
+        .await
+        .map_err(|err| {
+            tracing::error!("Upload processing task failed: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to process image".to_string(),
+                }),
+            )
+        })?;
+
+    processed.map_err(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid image: {}", err),
+            }),
+        )
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UploadListQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub q: Option<String>,
+}
+
+/// Media library listing, newest first.
+pub async fn list_uploads(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<UploadListQuery>,
+) -> Result<Json<UploadListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
 
-            let mut data = Vec::new();
-            while let Some(chunk) = field.chunk().await.map_err(|err| {
+    let (uploads, total) =
+        repositories::uploads::list_uploads(&pool, query.q.as_deref(), limit, offset)
+            .await
+            .map_err(|err| {
+                tracing::error!("Failed to list uploads: {}", err);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     Json(ErrorResponse {
-                        error: format!("Failed to read file chunk: {}", err),
+                        error: "Failed to list uploads".to_string(),
                     }),
                 )
-            })? {
-                if data.len() + chunk.len() > MAX_FILE_SIZE {
-                    return Err((
-                        StatusCode::BAD_REQUEST,
-                        Json(ErrorResponse {
-                            error: format!("File too large. Max size: {} bytes", MAX_FILE_SIZE),
-                        }),
-                    ));
-                }
-                data.extend_from_slice(&chunk);
+            })?;
+
+    let items = uploads
+        .into_iter()
+        .map(|upload| UploadItemResponse {
+            url: upload_url(&upload.filename),
+            upload,
+        })
+        .collect();
+
+    Ok(Json(UploadListResponse {
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+/// Sets the alt text and caption of an upload.
+pub async fn update_upload(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateUploadMetaRequest>,
+) -> Result<Json<UploadItemResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+
+    let alt_text = normalize_text(payload.alt_text.as_deref(), "Alt text", MAX_ALT_TEXT_CHARS)?;
+    let caption = normalize_text(payload.caption.as_deref(), "Caption", MAX_CAPTION_CHARS)?;
+
+    let upload = repositories::uploads::update_upload_meta(
+        &pool,
+        &id,
+        alt_text.as_deref(),
+        caption.as_deref(),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!("Failed to update upload {}: {}", id, err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to update upload".to_string(),
+            }),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Upload not found".to_string(),
+            }),
+        )
+    })?;
+
+    Ok(Json(UploadItemResponse {
+        url: upload_url(&upload.filename),
+        upload,
+    }))
+}
+
+/// Public alt text, caption and pixel size of an upload. `id` is either the
+/// upload id or its file name, so images referenced by URL can be looked up.
+pub async fn get_upload_meta(
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadMetaResponse>, (StatusCode, Json<ErrorResponse>)> {
+    load_upload_meta(&pool, storage::get().as_ref(), &id).await.map(Json)
+}
+
+async fn load_upload_meta(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    id: &str,
+) -> Result<UploadMetaResponse, (StatusCode, Json<ErrorResponse>)> {
+    let upload = repositories::uploads::find_upload(pool, id)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to load upload {}: {}", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to load upload".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Upload not found".to_string(),
+                }),
+            )
+        })?;
+
+    let variants = if upload.kind == UPLOAD_KIND_IMAGE {
+        image_variants(storage, &upload.filename).await
+    } else {
+        Vec::new()
+    };
+
+    Ok(UploadMetaResponse {
+        url: upload_url(&upload.filename),
+        id: upload.id,
+        alt_text: upload.alt_text,
+        caption: upload.caption,
+        width: upload.width,
+        height: upload.height,
+        variants,
+    })
+}
+
+/// Stored renditions of an image other than `filename` itself, with the
+/// pixel size read from each file's header. Failures only drop variants.
+async fn image_variants(storage: &dyn Storage, filename: &str) -> Vec<UploadVariant> {
+    let keys = match upload_keys(storage, filename).await {
+        Ok(keys) => keys,
+        Err(err) => {
+            tracing::warn!("Failed to list variants of '{}': {}", filename, err);
+            return Vec::new();
+        }
+    };
+
+    let mut variants = Vec::new();
+    for key in keys.into_iter().skip(1) {
+        let dimensions = match storage.open(&key).await {
+            Ok(Some(data)) => image_size::dimensions(&data),
+            Ok(None) => continue,
+            Err(err) => {
+                tracing::warn!("Failed to read upload variant '{}': {}", key, err);
+                continue;
             }
+        };
+        variants.push(UploadVariant {
+            url: upload_url(&key),
+            width: dimensions.map(|(width, _)| i64::from(width)),
+            height: dimensions.map(|(_, height)| i64::from(height)),
+        });
+    }
+    variants
+}
 
-            // Validate file content using magic bytes
-            if let Some(kind) = infer::get(&data) {
-                let mime = kind.mime_type();
-                let detected_ext = kind.extension();
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteUploadQuery {
+    #[serde(default)]
+    pub force: bool,
+}
 
-                // Verify the detected extension matches our allowed list
-                if !ALLOWED_EXTENSIONS.contains(&detected_ext) {
-                    return Err((
-                        StatusCode::BAD_REQUEST,
-                        Json(ErrorResponse {
-                            error: format!(
-                                "File type '{}' not allowed. Detected: {}",
-                                detected_ext, mime
-                            ),
-                        }),
-                    ));
-                }
+/// Finds content that links to `/uploads/{filename}`, up to
+/// [`MAX_LISTED_REFERENCES`] entries.
+pub(crate) async fn find_upload_references(
+    pool: &db::DbPool,
+    filename: &str,
+) -> Result<Vec<UploadReference>, sqlx::Error> {
+    let mut scanner = ReferenceScanner::new(pool);
+    let mut found = Vec::new();
 
-                // Verify the detected extension matches the file extension (prevent spoofing)
-                // Note: infer might return "jpeg" for "jpg", so we need to be flexible or normalize
-                let normalized_detected = if detected_ext == "jpeg" {
-                    "jpg"
-                } else {
-                    detected_ext
+    while let Some(documents) = scanner.next_batch().await? {
+        for document in documents {
+            for reference in document.references {
+                let ReferenceTarget::Route(path) = &reference.target else {
+                    continue;
                 };
-                let normalized_ext = if ext == "jpeg" { "jpg" } else { ext.as_str() };
-
-                if normalized_detected != normalized_ext {
-                    return Err((
-                        StatusCode::BAD_REQUEST,
-                        Json(ErrorResponse {
-                            error: format!(
-                                "File extension mismatch. Expected '{}', but detected '{}'",
-                                ext, detected_ext
-                            ),
-                        }),
-                    ));
+                if classify_route(path) != RouteKind::Upload(filename) {
+                    continue;
                 }
+                let (pointer, line) = match reference.location {
+                    ReferenceLocation::Pointer(pointer) => (Some(pointer), None),
+                    ReferenceLocation::Line(line) => (None, Some(line)),
+                };
+                found.push(UploadReference {
+                    source: document.source,
+                    id: document.id.clone(),
+                    field: document.field.map(str::to_string),
+                    pointer,
+                    line,
+                });
+                if found.len() >= MAX_LISTED_REFERENCES {
+                    return Ok(found);
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Keys belonging to an upload: the file itself plus derived variants such
+/// as thumbnails, which share its UUID stem (`<uuid>-320w.webp`). Names that
+/// are not UUID-based have no variants, so unrelated files are never matched.
+pub(crate) async fn upload_keys(storage: &dyn Storage, filename: &str) -> std::io::Result<Vec<String>> {
+    let mut keys = vec![filename.to_string()];
+
+    let stem = filename.split('.').next().unwrap_or_default();
+    let name = stem.strip_prefix(PRIVATE_UPLOAD_DIR).unwrap_or(stem);
+    if Uuid::parse_str(name).is_err() {
+        return Ok(keys);
+    }
+
+    for key in storage.list(stem).await? {
+        let is_variant = key != filename
+            && key
+                .strip_prefix(stem)
+                .is_some_and(|rest| rest.starts_with(['-', '_', '.']));
+        if is_variant {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// Deletes an upload by id. Refuses with 409 while content still links to it
+/// unless `force=true`. Files that are already gone are ignored.
+pub async fn delete_upload(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteUploadQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    remove_upload(&pool, storage::get().as_ref(), &id, query.force).await
+}
+
+async fn remove_upload(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    id: &str,
+    force: bool,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let internal_error = |message: &str| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: message.to_string(),
+            }),
+        )
+    };
+
+    let upload = repositories::uploads::get_upload(pool, id)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to load upload '{}': {}", id, err);
+            internal_error("Failed to delete upload")
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Upload not found".to_string(),
+                }),
+            )
+        })?;
+
+    // The name comes from our own table, but never let a bad row escape the
+    // upload directory.
+    if !is_plain_upload_name(&upload.filename) {
+        tracing::error!("Refusing to delete upload with unsafe filename '{}'", upload.filename);
+        return Err(internal_error("Failed to delete upload"));
+    }
+
+    if !force {
+        let references = find_upload_references(pool, &upload.filename)
+            .await
+            .map_err(|err| {
+                tracing::error!("Failed to check references to upload '{}': {}", id, err);
+                internal_error("Failed to delete upload")
+            })?;
+        if !references.is_empty() {
+            return Ok((
+                StatusCode::CONFLICT,
+                Json(UploadInUseResponse {
+                    error: "Upload is still referenced; pass force=true to delete it anyway"
+                        .to_string(),
+                    references,
+                }),
+            )
+                .into_response());
+        }
+    }
+
+    purge_upload(pool, storage, &upload).await.map_err(|err| {
+        tracing::error!("{}", err);
+        internal_error("Failed to delete upload")
+    })?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Deletes the row of `upload` and its files, variants included, without
+/// checking references. Files that cannot be removed are only logged.
+pub(crate) async fn purge_upload(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    upload: &Upload,
+) -> Result<(), String> {
+    let keys = upload_keys(storage, &upload.filename)
+        .await
+        .map_err(|err| format!("Failed to list files of upload '{}': {}", upload.id, err))?;
+
+    repositories::uploads::delete_upload(pool, &upload.id)
+        .await
+        .map_err(|err| format!("Failed to delete upload '{}': {}", upload.id, err))?;
+
+    for key in keys {
+        if let Err(err) = storage.delete(&key).await {
+            tracing::warn!("Failed to remove upload file '{}': {}", key, err);
+        }
+    }
+    Ok(())
+}
+
+/// Records stored files that have no row in the uploads table yet, e.g.
+/// files uploaded before uploads were tracked. Returns how many rows were
+/// added.
+pub async fn backfill_uploads(pool: &db::DbPool, storage: &dyn Storage) -> std::io::Result<usize> {
+    let mut added = 0;
+
+    for filename in storage.list("").await? {
+        if !is_plain_upload_name(&filename) || is_kept_original(&filename) {
+            continue;
+        }
+        let Some(data) = storage.open(&filename).await? else {
+            continue;
+        };
+
+        let is_file = filename.starts_with(UPLOAD_FILES_DIR);
+        let mime_type = match infer::get(&data) {
+            Some(kind) => kind.mime_type(),
+            None if is_file => crate::middleware::uploads::download_type_for(&filename)
+                .unwrap_or("application/octet-stream"),
+            None if svg_sanitize::looks_like_svg(&data) => SVG_MIME_TYPE,
+            None => "application/octet-stream",
+        };
+        let upload = NewUpload {
+            filename: &filename,
+            original_filename: &filename,
+            mime_type,
+            kind: if is_file { UPLOAD_KIND_FILE } else { UPLOAD_KIND_IMAGE },
+            visibility: if filename.starts_with(PRIVATE_UPLOAD_DIR) {
+                UPLOAD_VISIBILITY_DRAFT
             } else {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: "Could not determine file type".to_string(),
-                    }),
-                ));
+                UPLOAD_VISIBILITY_PUBLIC
+            },
+            size_bytes: data.len() as i64,
+            original_size_bytes: None,
+            dimensions: image_size::dimensions(&data),
+            alt_text: None,
+            caption: None,
+            uploaded_by: None,
+        };
+
+        match repositories::uploads::insert_untracked_upload(pool, &upload).await {
+            Ok(true) => added += 1,
+            Ok(false) => {}
+            Err(err) => tracing::warn!("Failed to backfill upload '{}': {}", filename, err),
+        }
+    }
+
+    Ok(added)
+}
+
+/// Records the pixel size of image uploads stored without one. Returns how
+/// many rows were filled in; files that are gone or unreadable are skipped.
+pub async fn backfill_upload_dimensions(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+) -> Result<usize, String> {
+    let uploads = repositories::uploads::list_images_without_dimensions(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut updated = 0;
+    for upload in uploads {
+        let data = match storage.open(&upload.filename).await {
+            Ok(Some(data)) => data,
+            Ok(None) => continue,
+            Err(err) => {
+                tracing::warn!("Failed to read upload '{}': {}", upload.filename, err);
+                continue;
             }
+        };
+        let Some((width, height)) = image_size::dimensions(&data) else {
+            continue;
+        };
+        repositories::uploads::set_upload_dimensions(pool, &upload.id, width, height)
+            .await
+            .map_err(|err| err.to_string())?;
+        updated += 1;
+    }
+    Ok(updated)
+}
 
-            let new_filename = format!("{}.{}", Uuid::new_v4(), ext);
-            let upload_dir = std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
-            let mut upload_path = PathBuf::from(upload_dir);
+/// Records files that predate the uploads table, then fills in the pixel
+/// size of image rows stored without one. Each step runs once per database;
+/// its marker in `app_metadata` keeps later starts from rescanning storage.
+pub async fn backfill_uploads_once(pool: &db::DbPool, upload_storage: &dyn Storage) {
+    run_once(
+        pool,
+        "uploads_backfilled",
+        "existing upload(s)",
+        backfill_uploads(pool, upload_storage),
+    )
+    .await;
+    run_once(
+        pool,
+        "upload_dimensions_backfilled",
+        "upload dimension(s)",
+        backfill_upload_dimensions(pool, upload_storage),
+    )
+    .await;
+}
 
-            // Ensure uploads directory exists
-            if !upload_path.exists() {
-                fs::create_dir_all(&upload_path).await.map_err(|err| {
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ErrorResponse {
-                            error: format!("Failed to create uploads directory: {}", err),
-                        }),
-                    )
-                })?;
+async fn run_once<E: std::fmt::Display>(
+    pool: &db::DbPool,
+    marker: &str,
+    label: &str,
+    task: impl std::future::Future<Output = Result<usize, E>>,
+) {
+    match repositories::app_metadata::get_metadata(pool, marker).await {
+        Ok(Some(_)) => return,
+        Ok(None) => {}
+        Err(err) => {
+            tracing::error!("Failed to check backfill marker '{}': {}", marker, err);
+            return;
+        }
+    }
+
+    match task.await {
+        Ok(added) => {
+            tracing::info!("Backfilled {} {}", added, label);
+            let timestamp = chrono::Utc::now().to_rfc3339();
+            if let Err(err) =
+                repositories::app_metadata::set_metadata(pool, marker, &timestamp).await
+            {
+                tracing::error!("Failed to store backfill marker '{}': {}", marker, err);
             }
+        }
+        Err(err) => tracing::error!("Failed to backfill {}: {}", label, err),
+    }
+}
 
-            upload_path.push(&new_filename);
+/// Serves `/uploads/{key}` for backends without a local directory: redirects
+/// to the public bucket URL when one is configured, otherwise proxies the
+/// file. Headers common to all upload responses are added by
+/// [`crate::middleware::uploads::upload_headers`].
+pub async fn serve_upload(Path(key): Path<String>, headers: HeaderMap) -> Response {
+    let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
+    proxy_upload(storage::get().as_ref(), &key, range).await
+}
 
-            fs::write(&upload_path, data).await.map_err(|err| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: format!("Failed to save file: {}", err),
-                    }),
+async fn proxy_upload(storage: &dyn Storage, key: &str, range: Option<&str>) -> Response {
+    // Drafts are only reachable through `serve_private_upload`
+    if !is_plain_upload_name(key) || key.starts_with(PRIVATE_UPLOAD_DIR) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Some(url) = storage.public_url(key) {
+        return Redirect::temporary(&url).into_response();
+    }
+
+    let data = match storage.open(key).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            tracing::error!("Failed to read upload '{}': {}", key, err);
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    let len = data.len();
+    match range.map(|range| byte_range(range, len)) {
+        Some(Some(Ok((start, end)))) => (
+            StatusCode::PARTIAL_CONTENT,
+            [
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}")),
+            ],
+            data[start..=end].to_vec(),
+        )
+            .into_response(),
+        Some(Some(Err(()))) => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+        )
+            .into_response(),
+        // No or unsupported (e.g. multi-part) range: send the whole file.
+        _ => ([(header::ACCEPT_RANGES, "bytes")], data).into_response(),
+    }
+}
+
+/// Serves `/uploads/private/{file}`: draft uploads, for signed-in users only
+/// and never cached. Anonymous requests get a 404 so drafts cannot be
+/// probed for. Once a draft has been published its old URL redirects to the
+/// public file.
+pub async fn serve_private_upload(
+    auth::OptionalClaims(claims): auth::OptionalClaims,
+    State(pool): State<db::DbPool>,
+    Path(file): Path<String>,
+) -> Response {
+    private_upload(&pool, storage::get().as_ref(), claims.as_ref(), &file).await
+}
+
+async fn private_upload(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    claims: Option<&auth::Claims>,
+    file: &str,
+) -> Response {
+    // A bare file name only, so the key stays inside the private directory
+    if file.is_empty() || file.contains(['/', '\\']) || file.contains("..") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    if claims.is_some() {
+        match storage.open(&format!("{PRIVATE_UPLOAD_DIR}{file}")).await {
+            Ok(Some(data)) => {
+                let (content_type, disposition) = match content_type_for(file) {
+                    Some(content_type) => (content_type, "inline"),
+                    None => ("application/octet-stream", "attachment"),
+                };
+                return (
+                    [
+                        (header::CONTENT_TYPE, content_type),
+                        (header::CONTENT_DISPOSITION, disposition),
+                        (header::CACHE_CONTROL, "private, no-store"),
+                        (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+                    ],
+                    data,
                 )
-            })?;
+                    .into_response();
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("Failed to read draft upload '{}': {}", file, err);
+                return StatusCode::BAD_GATEWAY.into_response();
+            }
+        }
+    }
 
-            let url = format!("/uploads/{}", new_filename);
+    match repositories::uploads::find_upload(pool, file).await {
+        Ok(Some(upload))
+            if upload.filename == file && upload.visibility == UPLOAD_VISIBILITY_PUBLIC =>
+        {
+            Redirect::permanent(&upload_url(file)).into_response()
+        }
+        Ok(_) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            tracing::error!("Failed to look up upload '{}': {}", file, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
 
-            return Ok(Json(UploadResponse { url }));
+pub(crate) fn is_kept_original(key: &str) -> bool {
+    key.split('.')
+        .next()
+        .is_some_and(|stem| stem.ends_with(KEPT_ORIGINAL_SUFFIX))
+}
+
+/// Makes the draft uploads linked from `content` public: each file moves to
+/// its public name and its row follows. Returns `content` with those links
+/// rewritten, or `None` when it links to no draft.
+pub(crate) async fn publish_draft_uploads(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    content: &str,
+) -> Result<Option<String>, String> {
+    let marker = format!("{UPLOADS_PREFIX}{PRIVATE_UPLOAD_DIR}");
+    let mut names: Vec<&str> = Vec::new();
+    for (start, _) in content.match_indices(&marker) {
+        let rest = &content[start + marker.len()..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        if is_plain_upload_name(name) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut published = Vec::new();
+    for name in names {
+        let draft_key = format!("{PRIVATE_UPLOAD_DIR}{name}");
+        let Some(upload) = repositories::uploads::find_upload(pool, &draft_key)
+            .await
+            .map_err(|err| format!("Failed to look up upload '{draft_key}': {err}"))?
+        else {
+            // Published through other content already
+            if repositories::uploads::find_upload(pool, name)
+                .await
+                .map_err(|err| format!("Failed to look up upload '{name}': {err}"))?
+                .is_some_and(|upload| upload.filename == name)
+            {
+                published.push(name);
+            }
+            continue;
+        };
+        if upload.filename != draft_key {
+            continue;
+        }
+
+        let data = storage
+            .open(&draft_key)
+            .await
+            .map_err(|err| format!("Failed to read draft upload '{draft_key}': {err}"))?
+            .ok_or_else(|| format!("Draft upload '{draft_key}' is missing"))?;
+        storage
+            .put(name, data, &upload.mime_type)
+            .await
+            .map_err(|err| format!("Failed to store upload '{name}': {err}"))?;
+        repositories::uploads::publish_upload(pool, &upload.id, name)
+            .await
+            .map_err(|err| format!("Failed to publish upload '{name}': {err}"))?;
+        if let Err(err) = storage.delete(&draft_key).await {
+            tracing::warn!("Failed to remove published draft '{}': {}", draft_key, err);
+        }
+        publish_draft_variants(storage, &draft_key).await;
+        published.push(name);
+    }
+
+    if published.is_empty() {
+        return Ok(None);
+    }
+    let mut rewritten = content.to_string();
+    for name in published {
+        rewritten = rewritten.replace(
+            &format!("{marker}{name}"),
+            &format!("{UPLOADS_PREFIX}{name}"),
+        );
+    }
+    Ok(Some(rewritten))
+}
+
+/// Moves what belongs to a just-published draft, such as a kept original,
+/// out of `private/` as well. Failures only leave a stray private file.
+async fn publish_draft_variants(storage: &dyn Storage, draft_key: &str) {
+    let variants = match upload_keys(storage, draft_key).await {
+        Ok(keys) => keys,
+        Err(err) => {
+            tracing::warn!("Failed to list variants of '{}': {}", draft_key, err);
+            return;
+        }
+    };
+    for key in variants.into_iter().filter(|key| key != draft_key) {
+        let public_key = key.trim_start_matches(PRIVATE_UPLOAD_DIR);
+        let moved = match storage.open(&key).await {
+            Ok(Some(data)) => {
+                let content_type = content_type_for(&key).unwrap_or("application/octet-stream");
+                storage.put(public_key, data, content_type).await.map(|_| ())
+            }
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        match moved {
+            Ok(()) => {
+                if let Err(err) = storage.delete(&key).await {
+                    tracing::warn!("Failed to remove published draft '{}': {}", key, err);
+                }
+            }
+            Err(err) => tracing::warn!("Failed to publish draft variant '{}': {}", key, err),
+        }
+    }
+}
+
+/// Parses a single `bytes=` range into inclusive offsets. `None` means the
+/// header is not one we handle; `Err` means it cannot be satisfied.
+fn byte_range(range: &str, len: usize) -> Option<Result<(usize, usize), ()>> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let bounds = if start.is_empty() {
+        let suffix: usize = end.parse().ok()?;
+        (len.saturating_sub(suffix), len.checked_sub(1))
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end = if end.is_empty() {
+            len.checked_sub(1)
+        } else {
+            Some(end.parse::<usize>().ok()?.min(len.saturating_sub(1)))
+        };
+        (start, end)
+    };
+
+    Some(match bounds {
+        (start, Some(end)) if start <= end && start < len => Ok((start, end)),
+        _ => Err(()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth::test_admin_claims as admin;
+    use crate::storage::LocalStorage;
+    use crate::utils::image_size::tests::png_header;
+    use std::path::PathBuf;
+    use axum::{body::Body, extract::FromRequest, http::Request};
+
+    fn temp_upload_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uploads-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("temp upload dir");
+        dir
+    }
+
+    async fn multipart_with(file_name: &str, data: &[u8]) -> Multipart {
+        multipart_parts(&[("file", Some(file_name), data)]).await
+    }
+
+    /// Builds a multipart body from `(field name, file name, data)` parts.
+    async fn multipart_parts(parts: &[(&str, Option<&str>, &[u8])]) -> Multipart {
+        let boundary = "X-BOUNDARY";
+        let mut body = Vec::new();
+        for (name, file_name, data) in parts {
+            let disposition = match file_name {
+                Some(file_name) => format!("form-data; name=\"{name}\"; filename=\"{file_name}\"\r\nContent-Type: application/octet-stream"),
+                None => format!("form-data; name=\"{name}\""),
+            };
+            body.extend_from_slice(
+                format!("--{boundary}\r\nContent-Disposition: {disposition}\r\n\r\n").as_bytes(),
+            );
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        let request = Request::builder()
+            .method("POST")
+            .header("content-type", format!("multipart/form-data; boundary={boundary}"))
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, &()).await.expect("multipart")
+    }
+
+    /// Uploads a single file, returning the status of a failed upload.
+    async fn upload_one(
+        pool: &db::DbPool,
+        storage: &dyn Storage,
+        options: &UploadOptions,
+        claims: &auth::Claims,
+        multipart: Multipart,
+    ) -> Result<UploadResponse, StatusCode> {
+        let (status, Json(mut results)) = store_uploads(pool, storage, options, claims, multipart)
+            .await
+            .map_err(|(status, _)| status)?;
+        assert_eq!(results.len(), 1);
+        let result = results.remove(0);
+        match (result.id, result.url) {
+            (Some(id), Some(url)) => Ok(UploadResponse {
+                id,
+                url,
+                width: result.width,
+                height: result.height,
+            }),
+            _ => Err(status),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_records_metadata_and_lists_it() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+
+        let multipart = multipart_with("../Screens/Terminal Shot.png", &png_header(64, 32)).await;
+        let response = upload_one(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+            .await
+            .expect("upload");
+        assert!(response.url.starts_with("/uploads/"));
+
+        let Json(listing) = list_uploads(
+            admin(),
+            State(pool.clone()),
+            Query(UploadListQuery {
+                q: Some("terminal".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("list");
+        assert_eq!(listing.total, 1);
+        let item = &listing.items[0];
+        assert_eq!(item.upload.id, response.id);
+        assert_eq!(item.url, response.url);
+        assert_eq!(item.upload.original_filename, "Terminal Shot.png");
+        assert_eq!(item.upload.mime_type, "image/png");
+        assert_eq!((item.upload.width, item.upload.height), (Some(64), Some(32)));
+        assert_eq!(item.upload.uploaded_by.as_deref(), Some("admin"));
+        assert!(dir.join(&item.upload.filename).exists());
+
+        let Json(empty) = list_uploads(
+            admin(),
+            State(pool.clone()),
+            Query(UploadListQuery {
+                q: Some("logo".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("list");
+        assert_eq!(empty.total, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_batch_upload_reports_each_file() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let png = png_header(4, 4);
+
+        let multipart = multipart_parts(&[
+            ("file", Some("first.png"), &png),
+            ("alt_text", None, b"First"),
+            ("file", Some("spoofed.jpg"), &png),
+            ("file", Some("third.png"), &png),
+            ("caption", None, b"Third"),
+        ])
+        .await;
+        let (status, Json(results)) =
+            store_uploads(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+                .await
+                .expect("batch");
+        assert_eq!(status, StatusCode::OK);
+
+        let names: Vec<&str> = results.iter().map(|result| result.original_filename.as_str()).collect();
+        assert_eq!(names, vec!["first.png", "spoofed.jpg", "third.png"]);
+        assert!(results[1].id.is_none());
+        assert!(results[1].error.as_deref().unwrap().contains("mismatch"));
+
+        let first = repositories::uploads::get_upload(&pool, results[0].id.as_deref().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.alt_text.as_deref(), Some("First"));
+        let third = repositories::uploads::get_upload(&pool, results[2].id.as_deref().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((third.alt_text, third.caption.as_deref()), (None, Some("Third")));
+        assert!(dir.join(&third.filename).exists());
+
+        let (_, total) = repositories::uploads::list_uploads(&pool, None, 10, 0).await.unwrap();
+        assert_eq!(total, 2);
+
+        let multipart = multipart_parts(&[("file", Some("page.html"), b"<html></html>")]).await;
+        let (status, Json(results)) =
+            store_uploads(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+                .await
+                .expect("batch");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(results[0].error.is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_upload_limits_from_env() {
+        let parse = |vars: &[(&str, &str)]| {
+            let vars: Vec<(String, String)> =
+                vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+            UploadLimits::parse(|key| {
+                vars.iter().find(|(name, _)| name == key).map(|(_, value)| value.clone())
+            })
+        };
+
+        assert_eq!(parse(&[]).unwrap(), UploadLimits::default());
+
+        let limits = parse(&[
+            ("UPLOAD_MAX_BYTES", "2000000"),
+            ("UPLOAD_MAX_BYTES_GIF", " 500000 "),
+            ("UPLOAD_MAX_PIXELS", "1000000"),
+            ("UPLOAD_MAX_BYTES_PNG", ""),
+        ])
+        .unwrap();
+        assert_eq!(limits.max_bytes_for("png"), 2_000_000);
+        assert_eq!(limits.max_bytes_for("gif"), 500_000);
+        assert_eq!(limits.max_pixels, 1_000_000);
+        let limits = parse(&[("UPLOAD_SESSION_MAX_BYTES", "1073741824")]).unwrap();
+        assert_eq!(limits.max_session_bytes, 1 << 30);
+        let limits = parse(&[("UPLOAD_DAILY_BYTES_LIMIT", "52428800"), ("UPLOAD_DAILY_FILES_LIMIT", "20")])
+            .unwrap();
+        assert_eq!((limits.daily_bytes, limits.daily_files), (Some(50 << 20), Some(20)));
+        assert!(parse(&[("UPLOAD_DAILY_FILES_LIMIT", "0")]).is_err());
+
+        assert!(parse(&[("UPLOAD_MAX_BYTES", "10MB")]).is_err());
+        assert!(parse(&[("UPLOAD_MAX_PIXELS", "0")]).is_err());
+        assert!(parse(&[("UPLOAD_MAX_BYTES_WEBP", "-1")]).is_err());
+        let too_big = (body_limits().admin + 1).to_string();
+        assert!(parse(&[("UPLOAD_MAX_BYTES", too_big.as_str())]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upload_limits_are_enforced() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let options = UploadOptions {
+            limits: UploadLimits {
+                max_bytes_by_extension: vec![("gif", 16)],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // 40 megapixels, rejected from the header alone
+        let multipart = multipart_with("huge.png", &png_header(8000, 5000)).await;
+        let (status, Json(results)) = store_uploads(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("batch");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(results[0].error.as_deref().unwrap().contains("8000x5000"));
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.resize(32, 0);
+        let multipart = multipart_with("anim.gif", &gif).await;
+        let (status, Json(results)) = store_uploads(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("batch");
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(results[0].error.as_deref().unwrap().starts_with("File too large"));
+
+        let multipart = multipart_with("small.png", &png_header(4000, 3000)).await;
+        upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("within limits");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_records_untracked_files_once() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        std::fs::write(dir.join("legacy.png"), png_header(10, 20)).unwrap();
+        std::fs::write(dir.join("notes.bin"), b"plain bytes").unwrap();
+
+        let storage = LocalStorage::new(&dir);
+        assert_eq!(backfill_uploads(&pool, &storage).await.unwrap(), 2);
+        assert_eq!(backfill_uploads(&pool, &storage).await.unwrap(), 0);
+
+        let (items, _) = repositories::uploads::list_uploads(&pool, Some("legacy"), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(items[0].mime_type, "image/png");
+        assert_eq!(items[0].size_bytes, png_header(10, 20).len() as i64);
+        assert_eq!(items[0].uploaded_by, None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_upload_strips_gps_metadata() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let original = crate::utils::image_metadata::tests::jpeg_with_gps();
+
+        let multipart = multipart_with("phone.jpg", &original).await;
+        let options = UploadOptions::default();
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let stored = std::fs::read(dir.join(&upload.filename)).unwrap();
+        assert!(!stored.windows(11).any(|window| window == b"GPSLatitude"));
+        assert_eq!(image_size::dimensions(&stored), Some((300, 200)));
+        assert_eq!((upload.width, upload.height), (Some(300), Some(200)));
+        assert_eq!(upload.size_bytes, stored.len() as i64);
+        assert_eq!(upload.original_size_bytes, Some(original.len() as i64));
+
+        let multipart = multipart_with("phone.jpg", &original).await;
+        let options = UploadOptions {
+            strip_metadata: false,
+            ..Default::default()
+        };
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read(dir.join(&upload.filename)).unwrap(), original);
+        assert_eq!(upload.original_size_bytes, None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// An uncompressed RGB screenshot: a title bar, a sidebar and text-like
+    /// blocks on a flat background.
+    fn screenshot_png() -> Vec<u8> {
+        let (width, height) = (240u32, 160u32);
+        let mut raw = Vec::new();
+        for y in 0..height {
+            raw.push(0);
+            for x in 0..width {
+                let pixel: [u8; 3] = if y < 20 {
+                    [45, 45, 48]
+                } else if x < 50 {
+                    [37, 37, 38]
+                } else if (x / 6 + y / 10) % 5 == 0 && y % 10 < 7 {
+                    [212, 212, 212]
+                } else {
+                    [30, 30, 30]
+                };
+                raw.extend_from_slice(&pixel);
+            }
+        }
+        crate::utils::png_decode::tests::png_from_scanlines(width, height, 2, 8, &raw)
+    }
+
+    #[tokio::test]
+    async fn test_image_dimensions_are_recorded_and_backfilled() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+
+        let webp = webp_encode::encode(100, 50, &[0xFF33_6699; 100 * 50], 100).unwrap();
+        let fixtures = [
+            ("diagram.png", png_header(640, 480), (640, 480)),
+            (
+                "photo.jpg",
+                crate::utils::image_metadata::tests::jpeg_with_gps(),
+                (300, 200),
+            ),
+            (
+                "spinner.gif",
+                b"GIF89a\x20\x03\x58\x02\0\0\0\x2C\0\0\0\0\x02\0\x02\0\0\x02\x02\x44\x01\0\x3B"
+                    .to_vec(),
+                (800, 600),
+            ),
+            ("icon.webp", webp, (100, 50)),
+        ];
+
+        let mut ids = Vec::new();
+        for (name, data, (width, height)) in fixtures {
+            let multipart = multipart_with(name, &data).await;
+            let response = upload_one(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+                .await
+                .expect(name);
+            assert_eq!((response.width, response.height), (Some(width), Some(height)), "{name}");
+
+            let meta = load_upload_meta(&pool, &storage, &response.id).await.expect(name);
+            assert_eq!((meta.width, meta.height), (Some(width), Some(height)), "{name}");
+            assert!(meta.variants.is_empty());
+            ids.push((response.id, width, height));
+        }
+
+        sqlx::query("UPDATE uploads SET width = NULL, height = NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(backfill_upload_dimensions(&pool, &storage).await.unwrap(), 4);
+        for (id, width, height) in ids {
+            let upload = repositories::uploads::get_upload(&pool, &id).await.unwrap().unwrap();
+            assert_eq!((upload.width, upload.height), (Some(width), Some(height)));
+        }
+        assert_eq!(backfill_upload_dimensions(&pool, &storage).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_png_uploads_convert_to_webp() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let png = screenshot_png();
+
+        let multipart = multipart_with("shot.png", &png).await;
+        let response = upload_one(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+            .await
+            .expect("upload");
+        assert!(response.url.ends_with(".png"));
+
+        let options = UploadOptions {
+            convert_to_webp: true,
+            keep_original: true,
+            ..Default::default()
+        };
+        let multipart = multipart_with("shot.png", &png).await;
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(upload.filename.ends_with(".webp"));
+        assert_eq!(upload.mime_type, "image/webp");
+        let stored = std::fs::read(dir.join(&upload.filename)).unwrap();
+        assert_eq!(&stored[..4], b"RIFF");
+        assert_eq!(&stored[8..16], b"WEBPVP8L");
+        assert_eq!(image_size::dimensions(&stored), Some((240, 160)));
+        assert_eq!((upload.width, upload.height), (Some(240), Some(160)));
+        assert_eq!(upload.size_bytes, stored.len() as i64);
+        assert_eq!(upload.original_size_bytes, Some(png.len() as i64));
+        assert!(stored.len() * 10 < png.len());
+
+        let stem = upload.filename.trim_end_matches(".webp");
+        let original = format!("{stem}{KEPT_ORIGINAL_SUFFIX}.png");
+        assert_eq!(std::fs::read(dir.join(&original)).unwrap(), png);
+        assert!(is_kept_original(&original));
+        let keys = upload_keys(&storage, &upload.filename).await.unwrap();
+        assert!(keys.contains(&original));
+        assert_eq!(backfill_uploads(&pool, &storage).await.unwrap(), 0);
+
+        assert_eq!((response.width, response.height), (Some(240), Some(160)));
+        let meta = load_upload_meta(&pool, &storage, &response.id).await.expect("meta");
+        assert_eq!(meta.variants.len(), 1);
+        assert_eq!(meta.variants[0].url, upload_url(&original));
+        assert_eq!(
+            (meta.variants[0].width, meta.variants[0].height),
+            (Some(240), Some(160))
+        );
+
+        // JPEG is never converted
+        let jpeg = crate::utils::image_metadata::tests::jpeg_with_gps();
+        let multipart = multipart_with("phone.jpg", &jpeg).await;
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+        assert!(response.url.ends_with(".jpg"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_svg_uploads_are_opt_in_and_sanitized() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" onload="alert(1)"><script>alert(2)</script><rect width="4" height="4"/></svg>"#;
+
+        let multipart = multipart_with("diagram.svg", svg).await;
+        let defaults = UploadOptions::default();
+        let status = upload_one(&pool, &storage, &defaults, &admin(), multipart)
+            .await
+            .expect_err("svg disabled");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let options = UploadOptions {
+            allow_svg: true,
+            ..Default::default()
+        };
+        let multipart = multipart_with("not-really.svg", b"<html><body/></html>").await;
+        let status = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect_err("not svg");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let multipart = multipart_with("diagram.svg", svg).await;
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(upload.mime_type, "image/svg+xml");
+        let stored = std::fs::read_to_string(dir.join(&upload.filename)).unwrap();
+        assert_eq!(
+            stored,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect width="4" height="4"/></svg>"#
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_file_uploads_are_verified_and_kept_below_files() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+
+        for (name, data) in [
+            ("cheatsheet.pdf", &b"PK\x03\x04not a pdf"[..]),
+            ("examples.zip", &b"%PDF-1.4"[..]),
+            ("notes.txt", &b"%PDF-1.4"[..]),
+            ("notes.txt", &b"text\0with nul"[..]),
+            ("page.html", &b"<html></html>"[..]),
+        ] {
+            let multipart = multipart_with(name, data).await;
+            let (status, _) = store_file(&pool, &storage, &UploadLimits::default(), &admin(), multipart)
+                .await
+                .expect_err(name);
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{name}");
+        }
+
+        let multipart = multipart_with("script.sh", &vec![b'#'; 256 * 1024 + 1]).await;
+        let (status, Json(error)) = store_file(&pool, &storage, &UploadLimits::default(), &admin(), multipart)
+            .await
+            .expect_err("too large");
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(error.error.starts_with("File too large"));
+
+        let multipart = multipart_with("examples.tar.gz", b"\x1f\x8b\x08\x00archive").await;
+        let Json(response) = store_file(&pool, &storage, &UploadLimits::default(), &admin(), multipart)
+            .await
+            .expect("upload");
+        assert!(response.url.starts_with("/uploads/files/"));
+        assert!(response.url.ends_with(".tar.gz"));
+
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(upload.kind, UPLOAD_KIND_FILE);
+        assert_eq!(upload.mime_type, "application/gzip");
+        assert_eq!(upload.original_filename, "examples.tar.gz");
+        assert!(dir.join(&upload.filename).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_alt_text_and_caption_fields_and_public_lookup() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        let png = png_header(8, 4);
+        let options = UploadOptions::default();
+
+        let multipart = multipart_parts(&[
+            ("alt_text", None, b"  Terminal running htop  "),
+            ("file", Some("htop.png"), &png),
+            ("caption", None, "Prozesse in htop".as_bytes()),
+        ])
+        .await;
+        let response = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect("upload");
+
+        let meta = load_upload_meta(&pool, &storage, &response.id)
+            .await
+            .expect("meta by id");
+        assert_eq!(meta.alt_text.as_deref(), Some("Terminal running htop"));
+        assert_eq!(meta.caption.as_deref(), Some("Prozesse in htop"));
+        assert_eq!((meta.width, meta.height), (Some(8), Some(4)));
+
+        let filename = response.url.trim_start_matches("/uploads/").to_string();
+        let by_name = load_upload_meta(&pool, &storage, &filename)
+            .await
+            .expect("meta by file name");
+        assert_eq!(by_name.id, response.id);
+
+        let (status, _) = load_upload_meta(&pool, &storage, "missing.png")
+            .await
+            .expect_err("unknown upload");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let Json(updated) = update_upload(
+            admin(),
+            State(pool.clone()),
+            Path(response.id.clone()),
+            Json(UpdateUploadMetaRequest {
+                alt_text: Some("htop".to_string()),
+                caption: Some("   ".to_string()),
+            }),
+        )
+        .await
+        .expect("update");
+        assert_eq!(updated.upload.alt_text.as_deref(), Some("htop"));
+        assert_eq!(updated.upload.caption, None);
+
+        let long_alt = "a".repeat(MAX_ALT_TEXT_CHARS + 1);
+        let multipart = multipart_parts(&[
+            ("file", Some("htop.png"), &png),
+            ("alt_text", None, long_alt.as_bytes()),
+        ])
+        .await;
+        let status = upload_one(&pool, &storage, &options, &admin(), multipart)
+            .await
+            .expect_err("alt text too long");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = update_upload(
+            admin(),
+            State(pool.clone()),
+            Path(response.id.clone()),
+            Json(UpdateUploadMetaRequest {
+                caption: Some("c".repeat(MAX_CAPTION_CHARS + 1)),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("caption too long");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_draft_uploads_are_private_until_published() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+
+        let multipart = multipart_parts(&[
+            ("visibility", None, b"draft"),
+            ("file", Some("draft.png"), &png_header(4, 4)),
+        ])
+        .await;
+        let response = upload_one(&pool, &storage, &UploadOptions::default(), &admin(), multipart)
+            .await
+            .expect("upload");
+        let file = response.url.strip_prefix("/uploads/private/").expect("private url").to_string();
+        assert!(dir.join("private").join(&file).exists());
+
+        let response = private_upload(&pool, &storage, None, &file).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = proxy_upload(&storage, &format!("private/{file}"), None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        for name in ["../draft.png", "..", "nested/a.png", ""] {
+            let response = private_upload(&pool, &storage, Some(&admin()), name).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{name}");
+        }
+
+        let response = private_upload(&pool, &storage, Some(&admin()), &file).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "private, no-store");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+
+        let content = format!("Intro\n\n![shot](/uploads/private/{file})\n![again](/uploads/private/{file})");
+        let published = publish_draft_uploads(&pool, &storage, &content)
+            .await
+            .expect("publish")
+            .expect("rewritten");
+        assert_eq!(published, format!("Intro\n\n![shot](/uploads/{file})\n![again](/uploads/{file})"));
+        assert!(dir.join(&file).exists());
+        assert!(!dir.join("private").join(&file).exists());
+
+        let upload = repositories::uploads::find_upload(&pool, &file).await.unwrap().unwrap();
+        assert_eq!(upload.filename, file);
+        assert_eq!(upload.visibility, UPLOAD_VISIBILITY_PUBLIC);
+
+        let response = private_upload(&pool, &storage, None, &file).await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], format!("/uploads/{file}"));
+
+        assert_eq!(publish_draft_uploads(&pool, &storage, "no drafts here").await, Ok(None));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range("bytes=0-3", 10), Some(Ok((0, 3))));
+        assert_eq!(byte_range("bytes=4-", 10), Some(Ok((4, 9))));
+        assert_eq!(byte_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(byte_range("bytes=5-100", 10), Some(Ok((5, 9))));
+        assert_eq!(byte_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(byte_range("bytes=0-1,4-5", 10), None);
+        assert_eq!(byte_range("items=0-1", 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_upload_honours_range() {
+        let dir = temp_upload_dir();
+        let storage = LocalStorage::new(&dir);
+        storage.put("a.png", b"0123456789".to_vec(), "image/png").await.unwrap();
+
+        let response = proxy_upload(&storage, "a.png", Some("bytes=2-4")).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"234");
+
+        let response = proxy_upload(&storage, "a.png", Some("bytes=20-")).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        let response = proxy_upload(&storage, "a.png", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(proxy_upload(&storage, "b.png", None).await.status(), StatusCode::NOT_FOUND);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    mod delete {
+        use super::*;
+        use axum::body::to_bytes;
+
+        async fn tracked(pool: &db::DbPool, dir: &std::path::Path, filename: &str) -> String {
+            std::fs::write(dir.join(filename), png_header(4, 4)).unwrap();
+            repositories::uploads::insert_upload(
+                pool,
+                &NewUpload {
+                    filename,
+                    original_filename: filename,
+                    mime_type: "image/png",
+                    kind: UPLOAD_KIND_IMAGE,
+                    visibility: UPLOAD_VISIBILITY_PUBLIC,
+                    size_bytes: 4,
+                    original_size_bytes: None,
+                    dimensions: None,
+                    alt_text: None,
+                    caption: None,
+                    uploaded_by: Some("admin"),
+                },
+            )
+            .await
+            .unwrap()
+            .id
+        }
+
+        #[tokio::test]
+        async fn test_referenced_upload_is_blocked() {
+            let pool = crate::db::pool::create_test_pool().await;
+            let dir = temp_upload_dir();
+            let filename = format!("{}.png", Uuid::new_v4());
+            let id = tracked(&pool, &dir, &filename).await;
+
+            sqlx::query(
+                "INSERT INTO tutorials (id, title, description, icon, color, topics, content) VALUES ('t-img', 'T', 'D', 'Terminal', 'blue', '[]', ?)",
+            )
+            .bind(format!("Intro\n\n![Screenshot](/uploads/{filename})"))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let response = remove_upload(&pool, &LocalStorage::new(&dir), &id, false).await.expect("response");
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["references"][0]["source"], "tutorial");
+            assert_eq!(body["references"][0]["id"], "t-img");
+            assert_eq!(body["references"][0]["line"], 3);
+
+            assert!(dir.join(&filename).exists());
+            assert!(repositories::uploads::get_upload(&pool, &id).await.unwrap().is_some());
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[tokio::test]
+        async fn test_force_delete_removes_row_file_and_variants() {
+            let pool = crate::db::pool::create_test_pool().await;
+            let dir = temp_upload_dir();
+            let stem = Uuid::new_v4();
+            let filename = format!("{stem}.png");
+            let id = tracked(&pool, &dir, &filename).await;
+            std::fs::write(dir.join(format!("{stem}-320w.webp")), b"thumb").unwrap();
+            std::fs::write(dir.join("unrelated.png"), b"keep").unwrap();
+
+            repositories::content::upsert_site_content(
+                &pool,
+                "fixture_media",
+                &serde_json::json!({ "image": format!("/uploads/{filename}") }),
+                "admin",
+                0,
+            )
+            .await
+            .unwrap();
+
+            let response = remove_upload(&pool, &LocalStorage::new(&dir), &id, true).await.expect("response");
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+            assert!(!dir.join(&filename).exists());
+            assert!(!dir.join(format!("{stem}-320w.webp")).exists());
+            assert!(dir.join("unrelated.png").exists());
+            assert!(repositories::uploads::get_upload(&pool, &id).await.unwrap().is_none());
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[tokio::test]
+        async fn test_missing_file_is_tolerated_and_unknown_id_is_404() {
+            let pool = crate::db::pool::create_test_pool().await;
+            let dir = temp_upload_dir();
+            let filename = format!("{}.png", Uuid::new_v4());
+            let id = tracked(&pool, &dir, &filename).await;
+            std::fs::remove_file(dir.join(&filename)).unwrap();
+
+            let response = remove_upload(&pool, &LocalStorage::new(&dir), &id, false).await.expect("response");
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+            let (status, _) = remove_upload(&pool, &LocalStorage::new(&dir), &id, false).await.expect_err("gone");
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            let _ = std::fs::remove_dir_all(&dir);
         }
     }
 
-    Err((
-        StatusCode::BAD_REQUEST,
-        Json(ErrorResponse {
-            error: "No file found in request".to_string(),
-        }),
-    ))
 }
@@ -0,0 +1,372 @@
+//! Orphaned uploads.
+//!
+//! Lists uploaded files that no content links to, so the media library can
+//! be cleaned up. Content is streamed through the [`ReferenceScanner`] and
+//! the uploads table is read in batches; only the referenced names, the
+//! storage listing and the first orphans are kept in memory.
+//!
+//! Uploads younger than `UPLOAD_ORPHAN_GRACE_HOURS` (default 24) are
+//! reported but never deleted: the content using a fresh upload is often
+//! saved only after the upload finished.
+
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    handlers::{
+        content_health::ReferenceScanner,
+        upload::{is_kept_original, purge_upload},
+    },
+    models::{ErrorResponse, OrphanUpload, OrphanUploadReport},
+    repositories,
+    security::auth,
+    storage::{self, upload_url, Storage},
+    utils::content_refs::{
+        classify_route, ReferenceTarget, RouteKind, PRIVATE_UPLOAD_DIR, UPLOAD_FILES_DIR,
+    },
+};
+use axum::{
+    extract::{Query, State},
+    http::{Method, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const DEFAULT_GRACE_HOURS: u32 = 24;
+/// Upper bound on orphans listed in one report; totals cover all of them.
+const MAX_LISTED_ORPHANS: usize = 500;
+const UPLOAD_BATCH_SIZE: i64 = 200;
+
+fn grace_period_hours() -> u32 {
+    match std::env::var("UPLOAD_ORPHAN_GRACE_HOURS") {
+        Ok(value) => value.trim().parse::<u32>().unwrap_or_else(|_| {
+            tracing::warn!(
+                "Invalid UPLOAD_ORPHAN_GRACE_HOURS '{}', using {}",
+                value,
+                DEFAULT_GRACE_HOURS
+            );
+            DEFAULT_GRACE_HOURS
+        }),
+        Err(_) => DEFAULT_GRACE_HOURS,
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct OrphanQuery {
+    #[serde(default)]
+    pub delete: bool,
+}
+
+/// Reports unreferenced uploads. With `delete=true`, which needs a POST and
+/// so a CSRF token, orphans outside the grace period are removed as well.
+pub async fn orphan_uploads(
+    claims: auth::Claims,
+    method: Method,
+    State(pool): State<db::DbPool>,
+    Query(query): Query<OrphanQuery>,
+) -> Result<Json<OrphanUploadReport>, (StatusCode, Json<ErrorResponse>)> {
+    ensure_admin(&claims)?;
+    if query.delete && method != Method::POST {
+        return Err((
+            StatusCode::METHOD_NOT_ALLOWED,
+            Json(ErrorResponse {
+                error: "Deleting orphans requires a POST request".to_string(),
+            }),
+        ));
+    }
+
+    find_orphans(
+        &pool,
+        storage::get().as_ref(),
+        grace_period_hours(),
+        query.delete,
+    )
+    .await
+    .map(Json)
+    .map_err(|err| AppError::internal("Failed to scan uploads", err).into())
+}
+
+/// What a reference to `key` keeps alive: the UUID shared by an upload and
+/// its variants (`<uuid>-320w.webp`), or the whole name for legacy files.
+fn upload_stem(key: &str) -> &str {
+    let name = key
+        .strip_prefix(PRIVATE_UPLOAD_DIR)
+        .or_else(|| key.strip_prefix(UPLOAD_FILES_DIR))
+        .unwrap_or(key);
+    match name.get(..36) {
+        Some(uuid) if Uuid::parse_str(uuid).is_ok() => uuid,
+        _ => key,
+    }
+}
+
+/// Seconds since `created_at`, as stored by SQLite or as RFC 3339.
+fn age_seconds(created_at: &str) -> Option<i64> {
+    let created = chrono::NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S")
+        .map(|naive| naive.and_utc())
+        .or_else(|_| {
+            chrono::DateTime::parse_from_rfc3339(created_at)
+                .map(|date| date.with_timezone(&chrono::Utc))
+        })
+        .ok()?;
+    Some((chrono::Utc::now() - created).num_seconds())
+}
+
+async fn referenced_stems(pool: &db::DbPool) -> Result<HashSet<String>, sqlx::Error> {
+    let mut scanner = ReferenceScanner::new(pool);
+    let mut referenced = HashSet::new();
+    while let Some(documents) = scanner.next_batch().await? {
+        for reference in documents
+            .into_iter()
+            .flat_map(|document| document.references)
+        {
+            if let ReferenceTarget::Route(path) = &reference.target {
+                if let RouteKind::Upload(file) = classify_route(path) {
+                    referenced.insert(upload_stem(file).to_string());
+                }
+            }
+        }
+    }
+    Ok(referenced)
+}
+
+pub(crate) async fn find_orphans(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    grace_hours: u32,
+    delete: bool,
+) -> Result<OrphanUploadReport, String> {
+    let referenced = referenced_stems(pool)
+        .await
+        .map_err(|err| format!("Failed to scan content: {err}"))?;
+
+    // Stored files by stem; whatever no row claims is a legacy file
+    let mut untracked: HashMap<String, Vec<String>> = HashMap::new();
+    for key in storage
+        .list("")
+        .await
+        .map_err(|err| format!("Failed to list uploads: {err}"))?
+    {
+        if !is_kept_original(&key) {
+            untracked
+                .entry(upload_stem(&key).to_string())
+                .or_default()
+                .push(key);
+        }
+    }
+
+    let grace_seconds = i64::from(grace_hours) * 60 * 60;
+    let mut report = OrphanUploadReport {
+        grace_period_hours: grace_hours,
+        ..Default::default()
+    };
+    let mut after = String::new();
+    loop {
+        let batch = repositories::uploads::list_uploads_after(pool, &after, UPLOAD_BATCH_SIZE)
+            .await
+            .map_err(|err| format!("Failed to list uploads: {err}"))?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = last.filename.clone();
+
+        for upload in batch {
+            let stem = upload_stem(&upload.filename);
+            untracked.remove(stem);
+            if referenced.contains(stem) {
+                continue;
+            }
+
+            let age = age_seconds(&upload.created_at);
+            let in_grace_period = age.is_none_or(|age| age < grace_seconds);
+            report.total += 1;
+            if !in_grace_period {
+                report.reclaimable_bytes += upload.size_bytes;
+            }
+            if delete && !in_grace_period {
+                purge_upload(pool, storage, &upload).await?;
+                report.deleted += 1;
+            }
+            if report.items.len() < MAX_LISTED_ORPHANS {
+                report.items.push(OrphanUpload {
+                    url: upload_url(&upload.filename),
+                    id: Some(upload.id),
+                    filename: upload.filename,
+                    original_filename: Some(upload.original_filename),
+                    size_bytes: upload.size_bytes,
+                    created_at: Some(upload.created_at),
+                    age_seconds: age,
+                    in_grace_period,
+                });
+            }
+        }
+    }
+
+    let mut legacy: Vec<String> = untracked
+        .into_iter()
+        .filter(|(stem, _)| !referenced.contains(stem))
+        .flat_map(|(_, keys)| keys)
+        .collect();
+    legacy.sort();
+    for key in legacy {
+        report.total += 1;
+        if report.items.len() >= MAX_LISTED_ORPHANS {
+            continue;
+        }
+        let size_bytes = storage
+            .open(&key)
+            .await
+            .map_err(|err| format!("Failed to read upload '{key}': {err}"))?
+            .map_or(0, |data| data.len() as i64);
+        report.items.push(OrphanUpload {
+            id: None,
+            url: upload_url(&key),
+            filename: key,
+            original_filename: None,
+            size_bytes,
+            created_at: None,
+            age_seconds: None,
+            in_grace_period: true,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{UPLOAD_KIND_IMAGE, UPLOAD_VISIBILITY_PUBLIC};
+    use crate::repositories::uploads::NewUpload;
+    use crate::storage::LocalStorage;
+
+    async fn tracked(
+        pool: &db::DbPool,
+        dir: &std::path::Path,
+        filename: &str,
+        hours_old: u32,
+    ) -> String {
+        std::fs::write(dir.join(filename), b"0123456789").unwrap();
+        let id = repositories::uploads::insert_upload(
+            pool,
+            &NewUpload {
+                filename,
+                original_filename: filename,
+                mime_type: "image/png",
+                kind: UPLOAD_KIND_IMAGE,
+                visibility: UPLOAD_VISIBILITY_PUBLIC,
+                size_bytes: 10,
+                original_size_bytes: None,
+                dimensions: None,
+                alt_text: None,
+                caption: None,
+                uploaded_by: Some("admin"),
+            },
+        )
+        .await
+        .unwrap()
+        .id;
+        sqlx::query("UPDATE uploads SET created_at = datetime('now', ?) WHERE id = ?")
+            .bind(format!("-{hours_old} hours"))
+            .bind(&id)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_orphans_are_reported_and_deleted_after_grace_period() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = std::env::temp_dir().join(format!("orphans-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = LocalStorage::new(&dir);
+
+        let used = format!("{}.png", Uuid::new_v4());
+        let used_by_thumbnail = Uuid::new_v4();
+        let old = Uuid::new_v4();
+        let fresh = format!("{}.png", Uuid::new_v4());
+        tracked(&pool, &dir, &used, 100).await;
+        tracked(&pool, &dir, &format!("{used_by_thumbnail}.png"), 100).await;
+        std::fs::write(dir.join(format!("{used_by_thumbnail}-320w.webp")), b"thumb").unwrap();
+        let old_id = tracked(&pool, &dir, &format!("{old}.png"), 100).await;
+        std::fs::write(dir.join(format!("{old}-320w.webp")), b"thumb").unwrap();
+        tracked(&pool, &dir, &fresh, 1).await;
+        std::fs::write(dir.join("legacy.gif"), b"GIF89a").unwrap();
+
+        sqlx::query(
+            "INSERT INTO tutorials (id, title, description, icon, color, topics, content) VALUES ('t-img', 'T', 'D', 'Terminal', 'blue', '[]', ?)",
+        )
+        .bind(format!(
+            "![Shot](/uploads/{used})\n\n![Small](/uploads/{used_by_thumbnail}-320w.webp)"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let report = find_orphans(&pool, &storage, 24, false).await.unwrap();
+        let mut names: Vec<&str> = report
+            .items
+            .iter()
+            .map(|item| item.filename.as_str())
+            .collect();
+        names.sort();
+        let mut expected = vec![
+            format!("{old}.png"),
+            fresh.clone(),
+            "legacy.gif".to_string(),
+        ];
+        expected.sort();
+        assert_eq!(names, expected);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.reclaimable_bytes, 10);
+        assert_eq!(report.deleted, 0);
+        let legacy = report.items.iter().find(|item| item.id.is_none()).unwrap();
+        assert_eq!((legacy.size_bytes, legacy.in_grace_period), (6, true));
+        let old_item = report
+            .items
+            .iter()
+            .find(|item| item.id.as_deref() == Some(&old_id))
+            .unwrap();
+        assert!(!old_item.in_grace_period);
+        assert!(old_item.age_seconds.unwrap() >= 100 * 3600 - 60);
+
+        let report = find_orphans(&pool, &storage, 24, true).await.unwrap();
+        assert_eq!(report.deleted, 1);
+        assert!(repositories::uploads::get_upload(&pool, &old_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!dir.join(format!("{old}.png")).exists());
+        assert!(!dir.join(format!("{old}-320w.webp")).exists());
+        for kept in [
+            used.clone(),
+            fresh,
+            "legacy.gif".to_string(),
+            format!("{used_by_thumbnail}-320w.webp"),
+        ] {
+            assert!(dir.join(&kept).exists(), "{kept} should be kept");
+        }
+        assert_eq!(
+            find_orphans(&pool, &storage, 24, false)
+                .await
+                .unwrap()
+                .total,
+            2
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_upload_stem() {
+        let uuid = "0b6c1a9e-3f5d-4c1e-9a7b-2d8e4f6a1c3b";
+        assert_eq!(upload_stem(&format!("{uuid}.png")), uuid);
+        assert_eq!(upload_stem(&format!("{uuid}-320w.webp")), uuid);
+        assert_eq!(upload_stem(&format!("private/{uuid}.png")), uuid);
+        assert_eq!(upload_stem(&format!("files/{uuid}.tar.gz")), uuid);
+        assert_eq!(upload_stem("legacy.gif"), "legacy.gif");
+        assert_eq!(age_seconds("not a date"), None);
+        assert!(age_seconds("2020-01-01 00:00:00").unwrap() > 0);
+    }
+}
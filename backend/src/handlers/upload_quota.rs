@@ -0,0 +1,233 @@
+//! Daily upload quota per admin user.
+//!
+//! Usage is summed from the uploads table for the current UTC day, so a
+//! leaked admin token cannot fill the disk overnight. The check and the
+//! insert are not one transaction: parallel uploads may overshoot a limit
+//! by a few files, which is acceptable for a safety net.
+//!
+//! Limits come from `UPLOAD_DAILY_BYTES_LIMIT` and `UPLOAD_DAILY_FILES_LIMIT`
+//! (see [`UploadLimits`]); without them uploads are not counted.
+
+use crate::{
+    db,
+    error::{ensure_admin, AppError},
+    handlers::upload::{upload_limits, UploadLimits},
+    models::UploadQuotaResponse,
+    repositories,
+    security::auth,
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Days, NaiveTime, Utc};
+
+pub const REMAINING_BYTES_HEADER: HeaderName =
+    HeaderName::from_static("x-upload-quota-remaining-bytes");
+pub const REMAINING_FILES_HEADER: HeaderName =
+    HeaderName::from_static("x-upload-quota-remaining-files");
+
+/// Start of the UTC day containing `now`, and of the next one.
+fn day_bounds(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = now.date_naive().and_time(NaiveTime::MIN).and_utc();
+    let end = start.checked_add_days(Days::new(1)).unwrap_or(start);
+    (start, end)
+}
+
+/// What `user` has uploaded on the UTC day of `now`.
+pub(crate) async fn usage(
+    pool: &db::DbPool,
+    limits: &UploadLimits,
+    user: &str,
+    now: DateTime<Utc>,
+) -> Result<UploadQuotaResponse, sqlx::Error> {
+    let (start, end) = day_bounds(now);
+    // Same format as `CURRENT_TIMESTAMP`, so the column compares as text
+    let since = start.format("%Y-%m-%d %H:%M:%S").to_string();
+    let (files_used, bytes_used) = repositories::uploads::usage_since(pool, user, &since).await?;
+    let (files_used, bytes_used) = (files_used.max(0) as u64, bytes_used.max(0) as u64);
+
+    Ok(UploadQuotaResponse {
+        files_used,
+        bytes_used,
+        files_limit: limits.daily_files,
+        bytes_limit: limits.daily_bytes,
+        files_remaining: limits
+            .daily_files
+            .map(|limit| limit.saturating_sub(files_used)),
+        bytes_remaining: limits
+            .daily_bytes
+            .map(|limit| limit.saturating_sub(bytes_used)),
+        resets_at: end.to_rfc3339(),
+    })
+}
+
+/// Refuses with 429 when storing one more file of `bytes` would exceed the
+/// daily quota of `user`.
+pub(crate) async fn enforce(
+    pool: &db::DbPool,
+    limits: &UploadLimits,
+    user: &str,
+    bytes: usize,
+) -> Result<(), AppError> {
+    if limits.daily_bytes.is_none() && limits.daily_files.is_none() {
+        return Ok(());
+    }
+
+    let quota = usage(pool, limits, user, Utc::now())
+        .await
+        .map_err(|err| AppError::internal("Failed to load upload quota", err))?;
+    let files_left = quota.files_remaining.is_none_or(|remaining| remaining > 0);
+    let bytes_left = quota
+        .bytes_remaining
+        .is_none_or(|remaining| remaining >= bytes as u64);
+    if files_left && bytes_left {
+        return Ok(());
+    }
+
+    let mut remaining = Vec::new();
+    if let Some(files) = quota.files_remaining {
+        remaining.push(format!("{} file(s)", files));
+    }
+    if let Some(bytes) = quota.bytes_remaining {
+        remaining.push(format!("{} bytes", bytes));
+    }
+    Err(AppError::RateLimited(format!(
+        "Daily upload quota exceeded; {} left until {}",
+        remaining.join(" and "),
+        quota.resets_at
+    )))
+}
+
+/// Adds the remaining quota and `Retry-After` to a 429 upload response.
+/// Other responses pass through untouched.
+pub(crate) async fn with_quota_headers(
+    pool: &db::DbPool,
+    limits: &UploadLimits,
+    user: &str,
+    mut response: Response,
+) -> Response {
+    if response.status() != StatusCode::TOO_MANY_REQUESTS {
+        return response;
+    }
+
+    let now = Utc::now();
+    let quota = match usage(pool, limits, user, now).await {
+        Ok(quota) => quota,
+        Err(err) => {
+            tracing::warn!("Failed to load upload quota for headers: {}", err);
+            return response;
+        }
+    };
+    let headers = response.headers_mut();
+    if let Some(bytes) = quota.bytes_remaining {
+        headers.insert(REMAINING_BYTES_HEADER, HeaderValue::from(bytes));
+    }
+    if let Some(files) = quota.files_remaining {
+        headers.insert(REMAINING_FILES_HEADER, HeaderValue::from(files));
+    }
+    let (_, reset) = day_bounds(now);
+    let retry_after = (reset - now).num_seconds().max(1);
+    headers.insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+/// Uploads of the current user today, against the configured limits.
+pub async fn get_upload_quota(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+) -> Result<Json<UploadQuotaResponse>, AppError> {
+    ensure_admin(&claims)?;
+    usage(&pool, upload_limits(), &claims.sub, Utc::now())
+        .await
+        .map(Json)
+        .map_err(|err| AppError::internal("Failed to load upload quota", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::upload::{store_download, FILE_TYPES};
+    use crate::security::auth::test_admin_claims as admin;
+    use crate::storage::LocalStorage;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_quota_blocks_until_the_next_day() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let dir = std::env::temp_dir().join(format!("quota-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&dir);
+        let text = FILE_TYPES
+            .iter()
+            .find(|file_type| file_type.extension == "txt")
+            .unwrap();
+        let limits = UploadLimits {
+            daily_files: Some(3),
+            daily_bytes: Some(100),
+            ..Default::default()
+        };
+        let claims = admin();
+        let upload = |data: &'static [u8]| {
+            store_download(
+                &pool,
+                &storage,
+                &limits,
+                &claims,
+                text,
+                "notes.txt",
+                data.to_vec(),
+            )
+        };
+
+        upload(&[b'a'; 40]).await.expect("first");
+        upload(&[b'b'; 40]).await.expect("second");
+        let (status, Json(error)) = upload(&[b'c'; 40]).await.expect_err("over byte limit");
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(
+            error.error.contains("1 file(s) and 20 bytes left"),
+            "{}",
+            error.error
+        );
+        upload(&[b'd'; 20]).await.expect("fits exactly");
+        let (status, _) = upload(b"e").await.expect_err("over file limit");
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        let quota = usage(&pool, &limits, "admin", Utc::now()).await.unwrap();
+        assert_eq!((quota.files_used, quota.bytes_used), (3, 100));
+        assert_eq!(
+            (quota.files_remaining, quota.bytes_remaining),
+            (Some(0), Some(0))
+        );
+        let other = usage(&pool, &limits, "editor", Utc::now()).await.unwrap();
+        assert_eq!(other.files_remaining, Some(3));
+
+        let response =
+            with_quota_headers(&pool, &limits, "admin", (status, "quota").into_response()).await;
+        assert_eq!(response.headers()[&REMAINING_FILES_HEADER], "0");
+        assert_eq!(response.headers()[&REMAINING_BYTES_HEADER], "0");
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        // Yesterday's uploads no longer count
+        sqlx::query("UPDATE uploads SET created_at = datetime(created_at, '-1 day')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        upload(&[b'f'; 60]).await.expect("next day");
+        let quota = usage(&pool, &limits, "admin", Utc::now()).await.unwrap();
+        assert_eq!((quota.files_used, quota.bytes_used), (1, 60));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_day_bounds() {
+        let now = DateTime::parse_from_rfc3339("2026-10-15T23:59:30Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (start, end) = day_bounds(now);
+        assert_eq!(start.to_rfc3339(), "2026-10-15T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2026-10-16T00:00:00+00:00");
+    }
+}
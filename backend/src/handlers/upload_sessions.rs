@@ -0,0 +1,762 @@
+//! Resumable uploads.
+//!
+//! A client opens a session with the final size and type of the file, sends
+//! it in fixed-size chunks (in any order, retrying any chunk as often as
+//! needed) and completes the session with the SHA-256 of the whole file.
+//! Completion runs the checks of `POST /api/upload` or `POST /api/upload/file`
+//! on the assembled file. Chunks wait in `UPLOAD_SESSION_DIR`; sessions that
+//! are never completed expire after `UPLOAD_SESSION_TTL_HOURS` and are
+//! removed by [`cleanup_upload_sessions`].
+
+use crate::{
+    db,
+    error::ensure_admin,
+    handlers::{
+        upload::{
+            normalize_text, store_download, store_image, ImageMeta, PendingImage, UploadOptions,
+            FILE_TYPES, MAX_ALT_TEXT_CHARS, MAX_CAPTION_CHARS,
+        },
+        upload_quota,
+    },
+    middleware::uploads::content_type_for,
+    models::{
+        CompleteUploadSessionRequest, CreateUploadSessionRequest, ErrorResponse, UploadResponse,
+        UploadSession, UploadSessionResponse, UPLOAD_VISIBILITY_DRAFT, UPLOAD_VISIBILITY_PUBLIC,
+    },
+    repositories,
+    security::auth,
+    storage::{self, Storage},
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use std::path::{Path as FsPath, PathBuf};
+use tokio::fs;
+
+/// Size of every chunk but the last. Well below the admin request limit.
+pub const CHUNK_SIZE: i64 = 4 * 1024 * 1024;
+const DEFAULT_SESSION_TTL_HOURS: u32 = 24;
+const CHUNK_SUFFIX: &str = ".part";
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+fn error(status: StatusCode, error: impl Into<String>) -> HandlerError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.into(),
+        }),
+    )
+}
+
+/// `UPLOAD_SESSION_DIR`, by default a directory below the system temp dir.
+/// Kept apart from the upload directory so chunks are never served.
+pub fn session_root() -> PathBuf {
+    std::env::var("UPLOAD_SESSION_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("cms-upload-sessions"))
+}
+
+fn session_ttl_hours() -> u32 {
+    match std::env::var("UPLOAD_SESSION_TTL_HOURS") {
+        Ok(value) => match value.trim().parse::<u32>() {
+            Ok(hours) if hours > 0 => hours,
+            _ => {
+                tracing::warn!(
+                    "Invalid UPLOAD_SESSION_TTL_HOURS '{}', using {}",
+                    value,
+                    DEFAULT_SESSION_TTL_HOURS
+                );
+                DEFAULT_SESSION_TTL_HOURS
+            }
+        },
+        Err(_) => DEFAULT_SESSION_TTL_HOURS,
+    }
+}
+
+fn internal_error(context: &str, err: impl std::fmt::Display) -> HandlerError {
+    tracing::error!("{}: {}", context, err);
+    error(StatusCode::INTERNAL_SERVER_ERROR, context)
+}
+
+fn chunk_count(session: &UploadSession) -> i64 {
+    (session.size_bytes + session.chunk_size - 1) / session.chunk_size
+}
+
+fn chunk_len(session: &UploadSession, index: i64) -> i64 {
+    (session.size_bytes - index * session.chunk_size).min(session.chunk_size)
+}
+
+fn extension(file_name: &str) -> String {
+    std::path::Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Compares MIME types without parameters such as `charset`.
+fn same_mime_type(a: &str, b: &str) -> bool {
+    let essence = |mime: &str| {
+        mime.split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    };
+    essence(a) == essence(b)
+}
+
+/// Chunk numbers stored for `session` so far, in order.
+async fn received_chunks(root: &FsPath, session: &UploadSession) -> std::io::Result<Vec<i64>> {
+    let mut entries = match fs::read_dir(root.join(&session.id)).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut chunks = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let index = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(CHUNK_SUFFIX))
+            .and_then(|index| index.parse::<i64>().ok());
+        if let Some(index) = index.filter(|index| (0..chunk_count(session)).contains(index)) {
+            chunks.push(index);
+        }
+    }
+    chunks.sort_unstable();
+    Ok(chunks)
+}
+
+async fn session_response(
+    root: &FsPath,
+    session: UploadSession,
+) -> Result<UploadSessionResponse, HandlerError> {
+    let received_chunks = received_chunks(root, &session)
+        .await
+        .map_err(|err| internal_error("Failed to read upload session", err))?;
+    Ok(UploadSessionResponse {
+        chunk_count: chunk_count(&session),
+        received_chunks,
+        id: session.id,
+        chunk_size: session.chunk_size,
+        expires_at: session.expires_at,
+    })
+}
+
+async fn active_session(pool: &db::DbPool, id: &str) -> Result<UploadSession, HandlerError> {
+    repositories::upload_sessions::get_active_session(pool, id)
+        .await
+        .map_err(|err| internal_error("Failed to load upload session", err))?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Upload session not found or expired"))
+}
+
+/// Opens an upload session. The file type and size are checked up front,
+/// so a client does not send a large file only to have it rejected.
+pub async fn create_upload_session(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Json(payload): Json<CreateUploadSessionRequest>,
+) -> Result<Response, HandlerError> {
+    ensure_admin(&claims)?;
+    let options = UploadOptions::from_env();
+    let response = match create_session(&pool, &options, &claims, CHUNK_SIZE, payload).await {
+        Ok(session) => (
+            StatusCode::CREATED,
+            Json(session_response(&session_root(), session).await?),
+        )
+            .into_response(),
+        Err(err) => err.into_response(),
+    };
+    Ok(upload_quota::with_quota_headers(&pool, &options.limits, &claims.sub, response).await)
+}
+
+async fn create_session(
+    pool: &db::DbPool,
+    options: &UploadOptions,
+    claims: &auth::Claims,
+    chunk_size: i64,
+    payload: CreateUploadSessionRequest,
+) -> Result<UploadSession, HandlerError> {
+    let file_name = payload.file_name.trim();
+    let ext = extension(file_name);
+    let (expected_mime, max_bytes) = if options.allowed_extensions().contains(&ext.as_str()) {
+        (
+            content_type_for(file_name).unwrap_or_default(),
+            options.limits.max_bytes_for(&ext),
+        )
+    } else if let Some(file_type) = FILE_TYPES
+        .iter()
+        .find(|file_type| file_type.extension == ext)
+    {
+        // Text files are small by nature; only binary downloads get the
+        // larger session limit
+        let max_bytes = if file_type.mime_type.starts_with("text/") {
+            file_type.max_bytes
+        } else {
+            options.limits.max_session_bytes
+        };
+        (file_type.mime_type, max_bytes)
+    } else {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!("File type '{}' not allowed", ext),
+        ));
+    };
+
+    if !same_mime_type(&payload.mime_type, expected_mime) {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "MIME type '{}' does not match extension '{}'",
+                payload.mime_type, ext
+            ),
+        ));
+    }
+    if payload.size_bytes <= 0 {
+        return Err(error(StatusCode::BAD_REQUEST, "File size must be positive"));
+    }
+    if payload.size_bytes as u64 > max_bytes as u64 {
+        return Err(error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("File too large. Max size: {} bytes", max_bytes),
+        ));
+    }
+    upload_quota::enforce(pool, &options.limits, &claims.sub, payload.size_bytes as usize).await?;
+
+    repositories::upload_sessions::create_session(
+        pool,
+        file_name,
+        expected_mime,
+        payload.size_bytes,
+        chunk_size,
+        &claims.sub,
+        session_ttl_hours(),
+    )
+    .await
+    .map_err(|err| internal_error("Failed to create upload session", err))
+}
+
+/// Reports which chunks a session has, for resuming after a dropped
+/// connection.
+pub async fn get_upload_session(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadSessionResponse>, HandlerError> {
+    ensure_admin(&claims)?;
+    let session = active_session(&pool, &id).await?;
+    Ok(Json(session_response(&session_root(), session).await?))
+}
+
+/// Stores chunk `index`. Sending a chunk again replaces it, so retries are
+/// always safe.
+pub async fn put_upload_chunk(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path((id, index)): Path<(String, i64)>,
+    body: Bytes,
+) -> Result<Json<UploadSessionResponse>, HandlerError> {
+    ensure_admin(&claims)?;
+    let root = session_root();
+    let session = store_chunk(&pool, &root, &id, index, &body).await?;
+    Ok(Json(session_response(&root, session).await?))
+}
+
+async fn store_chunk(
+    pool: &db::DbPool,
+    root: &FsPath,
+    id: &str,
+    index: i64,
+    data: &[u8],
+) -> Result<UploadSession, HandlerError> {
+    let session = active_session(pool, id).await?;
+    if !(0..chunk_count(&session)).contains(&index) {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!("Chunk {} is out of range", index),
+        ));
+    }
+    let expected = chunk_len(&session, index);
+    if data.len() as i64 != expected {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Chunk {} must be {} bytes, got {}",
+                index,
+                expected,
+                data.len()
+            ),
+        ));
+    }
+
+    // Write under a temporary name first, so an interrupted write never
+    // leaves a partial chunk behind
+    let dir = root.join(&session.id);
+    let path = dir.join(format!("{index}{CHUNK_SUFFIX}"));
+    let temp = dir.join(format!("{index}{CHUNK_SUFFIX}.{}", uuid::Uuid::new_v4()));
+    let write = async {
+        fs::create_dir_all(&dir).await?;
+        fs::write(&temp, data).await?;
+        fs::rename(&temp, &path).await
+    };
+    if let Err(err) = write.await {
+        let _ = fs::remove_file(&temp).await;
+        return Err(internal_error("Failed to store chunk", err));
+    }
+    Ok(session)
+}
+
+/// Assembles the file, verifies its size and checksum and stores it like a
+/// regular upload. A checksum mismatch keeps the session so the client can
+/// resend chunks; once the checksum matches the session is closed, whether
+/// or not the file passes validation.
+pub async fn complete_upload_session(
+    claims: auth::Claims,
+    State(pool): State<db::DbPool>,
+    Path(id): Path<String>,
+    Json(payload): Json<CompleteUploadSessionRequest>,
+) -> Result<Response, HandlerError> {
+    ensure_admin(&claims)?;
+    let options = UploadOptions::from_env();
+    let response = complete_session(
+        &pool,
+        storage::get().as_ref(),
+        &options,
+        &session_root(),
+        &claims,
+        &id,
+        payload,
+    )
+    .await
+    .map(Json)
+    .into_response();
+    Ok(upload_quota::with_quota_headers(&pool, &options.limits, &claims.sub, response).await)
+}
+
+async fn complete_session(
+    pool: &db::DbPool,
+    storage: &dyn Storage,
+    options: &UploadOptions,
+    root: &FsPath,
+    claims: &auth::Claims,
+    id: &str,
+    payload: CompleteUploadSessionRequest,
+) -> Result<UploadResponse, HandlerError> {
+    let session = active_session(pool, id).await?;
+    let received = received_chunks(root, &session)
+        .await
+        .map_err(|err| internal_error("Failed to read upload session", err))?;
+    let missing: Vec<i64> = (0..chunk_count(&session))
+        .filter(|index| !received.contains(index))
+        .collect();
+    if !missing.is_empty() {
+        return Err(error(
+            StatusCode::CONFLICT,
+            format!("Missing chunks: {:?}", missing),
+        ));
+    }
+
+    let dir = root.join(&session.id);
+    let mut data = Vec::with_capacity(session.size_bytes as usize);
+    for index in 0..chunk_count(&session) {
+        let chunk = fs::read(dir.join(format!("{index}{CHUNK_SUFFIX}")))
+            .await
+            .map_err(|err| internal_error("Failed to read chunk", err))?;
+        data.extend_from_slice(&chunk);
+    }
+    if data.len() as i64 != session.size_bytes {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Assembled file is {} bytes, expected {}",
+                data.len(),
+                session.size_bytes
+            ),
+        ));
+    }
+    let checksum = format!("{:x}", Sha256::digest(&data));
+    if !payload.sha256.trim().eq_ignore_ascii_case(&checksum) {
+        return Err(error(StatusCode::BAD_REQUEST, "SHA-256 checksum mismatch"));
+    }
+    // Checked again while storing, but a session over quota stays open so
+    // it can be completed the next day
+    upload_quota::enforce(pool, &options.limits, &claims.sub, data.len()).await?;
+
+    close_session(pool, root, &session.id).await;
+
+    let ext = extension(&session.file_name);
+    if let Some(file_type) = FILE_TYPES
+        .iter()
+        .find(|file_type| file_type.extension == ext)
+    {
+        return store_download(
+            pool,
+            storage,
+            &options.limits,
+            claims,
+            file_type,
+            &session.file_name,
+            data,
+        )
+        .await;
+    }
+
+    let visibility = match payload.visibility.as_deref().map(str::trim) {
+        None | Some("") | Some(UPLOAD_VISIBILITY_PUBLIC) => UPLOAD_VISIBILITY_PUBLIC,
+        Some(UPLOAD_VISIBILITY_DRAFT) => UPLOAD_VISIBILITY_DRAFT,
+        Some(other) => {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid visibility '{}'", other),
+            ));
+        }
+    };
+    let image = PendingImage {
+        file_name: session.file_name,
+        ext,
+        data,
+        meta: ImageMeta {
+            alt_text: normalize_text(payload.alt_text.as_deref(), "Alt text", MAX_ALT_TEXT_CHARS)?,
+            caption: normalize_text(payload.caption.as_deref(), "Caption", MAX_CAPTION_CHARS)?,
+            error: None,
+        },
+    };
+    store_image(pool, storage, options, claims, visibility, image).await
+}
+
+async fn close_session(pool: &db::DbPool, root: &FsPath, id: &str) {
+    if let Err(err) = repositories::upload_sessions::delete_session(pool, id).await {
+        tracing::warn!("Failed to delete upload session '{}': {}", id, err);
+    }
+    remove_session_dir(root, id).await;
+}
+
+async fn remove_session_dir(root: &FsPath, id: &str) {
+    match fs::remove_dir_all(root.join(id)).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            tracing::warn!(
+                "Failed to remove chunks of upload session '{}': {}",
+                id,
+                err
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Deletes expired sessions with their chunks, and chunk directories no
+/// session owns anymore. Returns the number of directories removed.
+pub async fn cleanup_upload_sessions(pool: &db::DbPool, root: &FsPath) -> Result<usize, String> {
+    let expired = repositories::upload_sessions::delete_expired_sessions(pool)
+        .await
+        .map_err(|err| format!("Failed to delete expired upload sessions: {err}"))?;
+    let active = repositories::upload_sessions::list_session_ids(pool)
+        .await
+        .map_err(|err| format!("Failed to list upload sessions: {err}"))?;
+
+    let mut entries = match fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(format!("Failed to read {}: {err}", root.display())),
+    };
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if active.contains(&name) && !expired.contains(&name) {
+            continue;
+        }
+        remove_session_dir(root, &name).await;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth::test_admin_claims as admin;
+    use crate::storage::LocalStorage;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{label}-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    fn fake_pdf(len: usize) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend((0..len - pdf.len()).map(|i| b'a' + (i % 26) as u8));
+        pdf
+    }
+
+    async fn open_session(
+        pool: &db::DbPool,
+        file_name: &str,
+        data: &[u8],
+        mime: &str,
+    ) -> UploadSession {
+        let request = CreateUploadSessionRequest {
+            file_name: file_name.to_string(),
+            size_bytes: data.len() as i64,
+            mime_type: mime.to_string(),
+        };
+        create_session(pool, &UploadOptions::default(), &admin(), 10, request)
+            .await
+            .expect("session")
+    }
+
+    fn chunk(data: &[u8], index: usize) -> &[u8] {
+        &data[index * 10..data.len().min(index * 10 + 10)]
+    }
+
+    #[tokio::test]
+    async fn test_chunks_out_of_order_with_retry() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let root = temp_dir("sessions");
+        let upload_dir = temp_dir("session-uploads");
+        let storage = LocalStorage::new(&upload_dir);
+        let pdf = fake_pdf(35);
+        let session = open_session(&pool, "guide.pdf", &pdf, "application/pdf").await;
+        assert_eq!(chunk_count(&session), 4);
+
+        for index in [3, 1, 1, 0] {
+            store_chunk(
+                &pool,
+                &root,
+                &session.id,
+                index,
+                chunk(&pdf, index as usize),
+            )
+            .await
+            .expect("chunk");
+        }
+        let status = session_response(&root, session.clone()).await.unwrap();
+        assert_eq!(status.received_chunks, vec![0, 1, 3]);
+
+        let complete = |sha256: String| CompleteUploadSessionRequest {
+            sha256,
+            ..Default::default()
+        };
+        let (status, _) = complete_session(
+            &pool,
+            &storage,
+            &UploadOptions::default(),
+            &root,
+            &admin(),
+            &session.id,
+            complete(sha256_hex(&pdf)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Wrong sizes are refused, a retried chunk simply replaces the old one
+        let (status, _) = store_chunk(&pool, &root, &session.id, 2, b"short")
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = store_chunk(&pool, &root, &session.id, 4, b"x")
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        store_chunk(&pool, &root, &session.id, 2, b"0123456789")
+            .await
+            .unwrap();
+        store_chunk(&pool, &root, &session.id, 2, chunk(&pdf, 2))
+            .await
+            .unwrap();
+
+        let response = complete_session(
+            &pool,
+            &storage,
+            &UploadOptions::default(),
+            &root,
+            &admin(),
+            &session.id,
+            complete(sha256_hex(&pdf).to_uppercase()),
+        )
+        .await
+        .expect("complete");
+        assert!(response.url.starts_with("/uploads/files/"));
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            std::fs::read(upload_dir.join(&upload.filename)).unwrap(),
+            pdf
+        );
+        assert_eq!(upload.original_filename, "guide.pdf");
+        assert!(!root.join(&session.id).exists());
+        let (status, _) = store_chunk(&pool, &root, &session.id, 0, chunk(&pdf, 0))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&upload_dir);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch_keeps_session() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let root = temp_dir("sessions");
+        let upload_dir = temp_dir("session-uploads");
+        let storage = LocalStorage::new(&upload_dir);
+        let png = crate::utils::image_size::tests::png_header(4, 3);
+        let session = open_session(&pool, "diagram.png", &png, "image/png").await;
+        for index in 0..chunk_count(&session) {
+            store_chunk(
+                &pool,
+                &root,
+                &session.id,
+                index,
+                chunk(&png, index as usize),
+            )
+            .await
+            .unwrap();
+        }
+
+        let request = CompleteUploadSessionRequest {
+            sha256: sha256_hex(b"something else"),
+            ..Default::default()
+        };
+        let (status, Json(body)) = complete_session(
+            &pool,
+            &storage,
+            &UploadOptions::default(),
+            &root,
+            &admin(),
+            &session.id,
+            request,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.error.contains("checksum"));
+        assert!(root.join(&session.id).exists());
+
+        let request = CompleteUploadSessionRequest {
+            sha256: sha256_hex(&png),
+            alt_text: Some("  Network diagram ".to_string()),
+            ..Default::default()
+        };
+        let response = complete_session(
+            &pool,
+            &storage,
+            &UploadOptions::default(),
+            &root,
+            &admin(),
+            &session.id,
+            request,
+        )
+        .await
+        .expect("complete");
+        let upload = repositories::uploads::get_upload(&pool, &response.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(upload.alt_text.as_deref(), Some("Network diagram"));
+        assert_eq!((upload.width, upload.height), (Some(4), Some(3)));
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&upload_dir);
+    }
+
+    #[tokio::test]
+    async fn test_session_creation_checks_type_and_size() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let options = UploadOptions::default();
+        let create =
+            |file_name: &str, size_bytes: i64, mime_type: &str| CreateUploadSessionRequest {
+                file_name: file_name.to_string(),
+                size_bytes,
+                mime_type: mime_type.to_string(),
+            };
+
+        let big_pdf = create("iso-notes.pdf", 64 * 1024 * 1024, "application/pdf");
+        assert!(
+            create_session(&pool, &options, &admin(), CHUNK_SIZE, big_pdf)
+                .await
+                .is_ok()
+        );
+        let notes = create("notes.txt", 100, "text/plain");
+        assert!(create_session(&pool, &options, &admin(), CHUNK_SIZE, notes)
+            .await
+            .is_ok());
+
+        for (request, expected) in [
+            (
+                create("huge.png", 9 * 1024 * 1024, "image/png"),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                create("page.html", 10, "text/html"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                create("photo.jpg", 10, "image/png"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (create("empty.png", 0, "image/png"), StatusCode::BAD_REQUEST),
+        ] {
+            let (status, _) = create_session(&pool, &options, &admin(), CHUNK_SIZE, request)
+                .await
+                .unwrap_err();
+            assert_eq!(status, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_removes_expired_and_orphaned_chunks() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let root = temp_dir("sessions");
+        let pdf = fake_pdf(15);
+        let live = open_session(&pool, "live.pdf", &pdf, "application/pdf").await;
+        let stale = open_session(&pool, "stale.pdf", &pdf, "application/pdf").await;
+        for session in [&live, &stale] {
+            store_chunk(&pool, &root, &session.id, 0, chunk(&pdf, 0))
+                .await
+                .unwrap();
+        }
+        std::fs::create_dir_all(root.join("left-over")).unwrap();
+        sqlx::query(
+            "UPDATE upload_sessions SET expires_at = datetime('now', '-1 hours') WHERE id = ?",
+        )
+        .bind(&stale.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (status, _) = store_chunk(&pool, &root, &stale.id, 1, chunk(&pdf, 1))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(cleanup_upload_sessions(&pool, &root).await.unwrap(), 2);
+        assert!(root.join(&live.id).exists());
+        assert!(!root.join(&stale.id).exists());
+        assert!(!root.join("left-over").exists());
+        assert_eq!(
+            repositories::upload_sessions::list_session_ids(&pool)
+                .await
+                .unwrap(),
+            vec![live.id.clone()]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,447 @@
+//! Scheduled background jobs.
+//!
+//! A [`Job`] is a small struct saying what to run and how often; the
+//! [`Scheduler`] gives each registered job its own task that runs it every
+//! `interval`, plus a random delay of up to a tenth of the interval (at most
+//! a minute) so jobs sharing an interval do not all hit the database at once.
+//! The first run follows that delay alone.
+//!
+//! Every run is recorded in `job_runs` (time, duration, error) and beats the
+//! job's heartbeat in the [`TaskRegistry`], so `/api/health/ready` and
+//! `/api/admin/jobs` see it. A failing or even panicking run is logged and
+//! recorded; the job keeps its schedule. Flipping the shutdown channel stops
+//! every job between runs, and lets a run in progress finish.
+//!
+//! The housekeeping jobs of the server are defined here too; `main` picks
+//! which of them to register.
+
+use crate::{db::DbPool, repositories, tasks::TaskRegistry};
+use async_trait::async_trait;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Longest random delay added to a run.
+const MAX_JITTER: Duration = Duration::from_secs(60);
+
+pub type JobResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[async_trait]
+pub trait Job: Send + Sync + 'static {
+    /// Unique name, used in logs, `job_runs` and the task registry.
+    fn name(&self) -> &'static str;
+
+    /// Time between the end of one run and the start of the next.
+    fn interval(&self) -> Duration;
+
+    /// Readiness fails while a critical job is not running.
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult;
+}
+
+/// Random delay in `[0, interval / 10]`, capped at [`MAX_JITTER`].
+fn jitter(interval: Duration) -> Duration {
+    let max = (interval / 10).min(MAX_JITTER);
+    let nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+    if nanos == 0 {
+        return Duration::ZERO;
+    }
+    // Every `RandomState` is seeded differently, which is all the
+    // randomness spreading runs out needs
+    Duration::from_nanos(RandomState::new().hash_one(Instant::now()) % (nanos + 1))
+}
+
+pub struct Scheduler {
+    pool: DbPool,
+    registry: TaskRegistry,
+    jobs: Vec<Arc<dyn Job>>,
+}
+
+impl Scheduler {
+    pub fn new(pool: DbPool, registry: TaskRegistry) -> Self {
+        Self {
+            pool,
+            registry,
+            jobs: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, job: impl Job) -> &mut Self {
+        self.jobs.push(Arc::new(job));
+        self
+    }
+
+    /// Starts every registered job; the handles end once `shutdown` flips.
+    pub fn start(self, shutdown: watch::Receiver<bool>) -> Vec<(&'static str, JoinHandle<()>)> {
+        self.jobs
+            .into_iter()
+            .map(|job| {
+                let name = job.name();
+                let task = tokio::spawn(run_on_schedule(
+                    job,
+                    self.pool.clone(),
+                    self.registry.clone(),
+                    shutdown.clone(),
+                ));
+                (name, task)
+            })
+            .collect()
+    }
+}
+
+async fn run_on_schedule(
+    job: Arc<dyn Job>,
+    pool: DbPool,
+    registry: TaskRegistry,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let name = job.name();
+    let heartbeat = registry.register(name, job.critical());
+    let mut delay = jitter(job.interval());
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.changed() => break,
+        }
+
+        let started = Instant::now();
+        // In its own task, so a panic is caught like an error
+        let outcome = tokio::spawn({
+            let job = job.clone();
+            let pool = pool.clone();
+            async move { job.run(&pool).await.map_err(|err| err.to_string()) }
+        })
+        .await;
+        let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        let error = match outcome {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(err),
+            Err(err) if err.is_panic() => Some("Job panicked".to_string()),
+            Err(err) => Some(err.to_string()),
+        };
+        if let Some(error) = &error {
+            tracing::error!(job = name, duration_ms, "Job failed: {}", error);
+        } else {
+            tracing::debug!(job = name, duration_ms, "Job finished");
+        }
+        if let Err(err) =
+            repositories::job_runs::record_run(&pool, name, duration_ms, error.as_deref()).await
+        {
+            tracing::warn!(job = name, "Failed to record job run: {}", err);
+        }
+        heartbeat.beat();
+
+        delay = job.interval() + jitter(job.interval());
+    }
+}
+
+/// Purges site pages that have outlived the trash retention window.
+///
+/// `PAGE_TRASH_RETENTION_DAYS` (default 30) controls the window; `0` disables
+/// automatic purging so trashed pages are only removed manually.
+pub struct TrashPurgeJob {
+    retention_days: u32,
+}
+
+impl TrashPurgeJob {
+    pub fn from_env() -> Option<Self> {
+        let retention_days = match std::env::var("PAGE_TRASH_RETENTION_DAYS") {
+            Ok(value) => value.trim().parse::<u32>().unwrap_or_else(|_| {
+                tracing::warn!("Invalid PAGE_TRASH_RETENTION_DAYS '{}', using 30", value);
+                30
+            }),
+            Err(_) => 30,
+        };
+
+        if retention_days == 0 {
+            tracing::info!("Automatic purge of trashed pages is disabled");
+            return None;
+        }
+        Some(Self { retention_days })
+    }
+}
+
+#[async_trait]
+impl Job for TrashPurgeJob {
+    fn name(&self) -> &'static str {
+        "trash purge"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        let purged = repositories::pages::purge_expired_trash(pool, self.retention_days)
+            .await
+            .map_err(|err| format!("Failed to purge trashed pages: {}", err))?;
+        if purged > 0 {
+            tracing::info!("Purged {} page(s) from the trash", purged);
+        }
+        Ok(())
+    }
+}
+
+/// Removes expired resumable upload sessions and their chunks. Critical for
+/// readiness, since abandoned chunks would otherwise fill the disk.
+pub struct UploadSessionCleanupJob {
+    pub root: std::path::PathBuf,
+}
+
+#[async_trait]
+impl Job for UploadSessionCleanupJob {
+    fn name(&self) -> &'static str {
+        "upload session cleanup"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    fn critical(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        let removed = crate::handlers::upload_sessions::cleanup_upload_sessions(pool, &self.root)
+            .await
+            .map_err(|err| format!("Failed to clean up upload sessions: {}", err))?;
+        if removed > 0 {
+            tracing::info!("Removed {} stale upload session(s)", removed);
+        }
+        Ok(())
+    }
+}
+
+/// Makes the draft uploads of scheduled posts public once the posts go live.
+pub struct ScheduledPostUploadsJob;
+
+#[async_trait]
+impl Job for ScheduledPostUploadsJob {
+    fn name(&self) -> &'static str {
+        "scheduled post uploads"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        let storage = crate::storage::get();
+        crate::handlers::site_posts::publish_uploads_of_live_posts(pool, storage.as_ref())
+            .await
+            .map_err(|err| format!("Failed to publish uploads of scheduled posts: {}", err))?;
+        Ok(())
+    }
+}
+
+/// Deletes IP bans that have expired.
+pub struct IpBanPruneJob;
+
+#[async_trait]
+impl Job for IpBanPruneJob {
+    fn name(&self) -> &'static str {
+        "IP ban pruning"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        let pruned = repositories::ip_bans::delete_expired_bans(pool)
+            .await
+            .map_err(|err| format!("Failed to prune expired IP bans: {}", err))?;
+        if pruned > 0 {
+            tracing::info!("Pruned {} expired IP ban(s)", pruned);
+        }
+        Ok(())
+    }
+}
+
+/// Deletes revoked tokens that have expired, so every authenticated request
+/// checks a blacklist of sessions that could still be used.
+pub struct TokenBlacklistPurgeJob;
+
+#[async_trait]
+impl Job for TokenBlacklistPurgeJob {
+    fn name(&self) -> &'static str {
+        "token blacklist purge"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    async fn run(&self, pool: &DbPool) -> JobResult {
+        repositories::token_blacklist::purge_expired(pool, chrono::Utc::now())
+            .await
+            .map_err(|err| format!("Failed to purge expired blacklisted tokens: {}", err))?;
+        Ok(())
+    }
+}
+
+/// Reloads the TLS certificate when its files change.
+pub struct CertificateReloadJob {
+    pub store: Arc<crate::tls::CertStore>,
+}
+
+#[async_trait]
+impl Job for CertificateReloadJob {
+    fn name(&self) -> &'static str {
+        "TLS certificate reload"
+    }
+
+    fn interval(&self) -> Duration {
+        crate::tls::RELOAD_CHECK_INTERVAL
+    }
+
+    async fn run(&self, _pool: &DbPool) -> JobResult {
+        let reloaded = self
+            .store
+            .reload_if_changed()
+            .map_err(|err| format!("{}; keeping the previous TLS certificate", err))?;
+        if reloaded {
+            tracing::info!("Reloaded TLS certificate");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails its first run, panics on its second and succeeds afterwards.
+    struct FlakyJob {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Job for FlakyJob {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_millis(20)
+        }
+
+        async fn run(&self, _pool: &DbPool) -> JobResult {
+            match self.runs.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("disk full".into()),
+                1 => panic!("job failure"),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    struct SlowJob;
+
+    #[async_trait]
+    impl Job for SlowJob {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_secs(60 * 60)
+        }
+
+        fn critical(&self) -> bool {
+            true
+        }
+
+        async fn run(&self, _pool: &DbPool) -> JobResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        assert_eq!(jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(10)) <= Duration::from_secs(1));
+            assert!(jitter(Duration::from_secs(60 * 60)) <= MAX_JITTER);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_on_schedule_and_survive_failures() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let registry = TaskRegistry::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut scheduler = Scheduler::new(pool.clone(), registry.clone());
+        scheduler
+            .register(FlakyJob { runs: runs.clone() })
+            .register(SlowJob);
+        let handles = scheduler.start(shutdown_rx);
+        assert_eq!(
+            handles.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            ["flaky", "slow"]
+        );
+
+        for _ in 0..100 {
+            if runs.load(Ordering::SeqCst) >= 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(runs.load(Ordering::SeqCst) >= 4);
+        let tasks = registry.tasks();
+        assert!(tasks.iter().all(|task| task.running), "{tasks:?}");
+        assert!(tasks
+            .iter()
+            .any(|task| task.name == "slow" && task.critical));
+
+        // The hour-long job is waiting for its next run and must not hold
+        // up the shutdown
+        let started = Instant::now();
+        shutdown_tx.send_replace(true);
+        for (name, handle) in handles {
+            tokio::time::timeout(Duration::from_secs(1), handle)
+                .await
+                .unwrap_or_else(|_| panic!("{name} did not stop"))
+                .unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(registry.tasks().iter().all(|task| !task.running));
+
+        let recorded = repositories::job_runs::list_runs(&pool).await.unwrap();
+        let flaky = recorded.iter().find(|run| run.name == "flaky").unwrap();
+        assert!(flaky.runs >= 4);
+        assert_eq!(flaky.failures, 2);
+        assert!(flaky.succeeded);
+        assert_eq!(flaky.error, None);
+        assert!(flaky.last_success_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_runs_are_recorded() {
+        let pool = crate::db::pool::create_test_pool().await;
+        repositories::job_runs::record_run(&pool, "purge", 12, None)
+            .await
+            .unwrap();
+        repositories::job_runs::record_run(&pool, "purge", 40, Some("database is locked"))
+            .await
+            .unwrap();
+
+        let runs = repositories::job_runs::list_runs(&pool).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        let run = &runs[0];
+        assert_eq!((run.runs, run.failures), (2, 1));
+        assert!(!run.succeeded);
+        assert_eq!(run.error.as_deref(), Some("database is locked"));
+        assert_eq!(run.duration_ms, 40);
+        // The earlier success is kept
+        assert!(run.last_success_at.is_some());
+    }
+}
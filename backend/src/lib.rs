@@ -40,6 +40,12 @@
  * - [`site_pages`](mod@handlers::site_pages): Static page management
  * - [`site_posts`](mod@handlers::site_posts): Blog post management
  *
+ * ## [`app`](mod@app)
+ * Assembles the application:
+ * - [`AppConfig`](app::AppConfig): settings read from the environment
+ * - [`AppState`](app::AppState): what every listener shares
+ * - [`build_router`](app::build_router): the router with all middleware
+ *
  * ## [`models`](mod@models)
  * Defines data structures and API models including:
  * - Database entity models with serialization
@@ -83,6 +89,8 @@
  * - `CSRF_SECRET`: Secret key for CSRF token signing
  * - `ADMIN_USERNAME`: Initial admin user (optional)
  * - `ADMIN_PASSWORD`: Initial admin password (optional)
+ * - `LOG_FORMAT`: `json` for one JSON object per log line (optional)
+ * - `LOG_LEVEL`: Log filter directive such as `info,sqlx=warn` (optional)
  *
  * # Usage
  *
@@ -91,10 +99,22 @@
  * 2. A library for testing or integration
  * 3. A component in larger applications
  *
+ * Example serving the application from another binary:
+ * ```rust,no_run
+ * use rust_blog_backend::{app, db, storage};
+ *
+ * # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+ * let pool = db::create_pool().await?;
+ * let config = app::AppConfig::from_env()?;
+ * let state = app::AppState::new(pool, config, storage::init()?);
+ * let router = app::build_router(state);
+ * # Ok(())
+ * # }
+ * ```
+ *
  * Example as a library:
- * ```rust
- * use linux_tutorial_cms::db;
- * use linux_tutorial_cms::auth;
+ * ```rust,no_run
+ * use rust_blog_backend::db;
  *
  * #[tokio::main]
  * async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -105,9 +125,25 @@
  * ```
  */
 // Core application modules
+pub mod app; // Application config, shared state and the router
 pub mod security; // Authentication, authorization, and CSRF protection
+pub mod backup; // Scheduled content backups
+pub mod content_bundle; // Content export/import bundles
+pub mod content_fetch; // Content bundles fetched from a URL
+pub mod content_markdown; // Markdown directories as content bundles
+pub mod derived; // Topic index, search index and post statistics
+pub mod error; // Handler errors with machine-readable codes
 pub mod db; // Database operations and migrations
 pub mod handlers; // HTTP request handlers
+pub mod jobs; // Scheduled background jobs
+pub mod logging; // Log format, filtering and redaction
 pub mod middleware; // HTTP middleware
 pub mod models; // Data structures and API models
 pub mod repositories; // Database repositories
+pub mod routes; // Route definitions
+pub mod settings; // Cached, typed site settings
+pub mod storage; // Upload storage backends
+pub mod tasks; // Background task heartbeats
+pub mod tls; // Optional HTTPS termination
+pub mod utils; // Shared text and formatting helpers
+pub mod validation; // Field rules shared by the API and the importer
@@ -0,0 +1,324 @@
+//! Log output setup.
+//!
+//! `LOG_FORMAT=json` writes one JSON object per line with timestamp, level,
+//! target, the fields of the enclosing spans (such as `request_id`) and the
+//! event's own fields. Any other value keeps the human-readable format.
+//! `LOG_LEVEL` takes an [`EnvFilter`] directive such as `info` or
+//! `info,sqlx=warn`; without it `RUST_LOG` is used, then `info`.
+//!
+//! Both formats pass every field through [`redact_field`], so credentials
+//! that end up in a log call are masked before they are written.
+
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+use std::sync::OnceLock;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{
+    field::MakeExt,
+    fmt::{format, MakeWriter},
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Field names whose values are never logged, compared case-insensitively
+/// with `_` and `-` treated alike.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-csrf-token",
+    "csrf-token",
+    "token",
+    "password",
+    "secret",
+];
+
+/// Installs the global subscriber. Called once at startup.
+pub fn init() {
+    let filter = std::env::var("LOG_LEVEL")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .and_then(|value| match EnvFilter::try_new(value.trim()) {
+            Ok(filter) => Some(filter),
+            Err(err) => {
+                eprintln!("Invalid LOG_LEVEL '{}': {}; using the default", value, err);
+                None
+            }
+        })
+        .or_else(|| EnvFilter::try_from_default_env().ok())
+        .unwrap_or_else(|| EnvFilter::new("info"));
+
+    let json = std::env::var("LOG_FORMAT")
+        .map(|value| value.trim().eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    let registry = tracing_subscriber::registry().with(filter);
+    if json {
+        registry.with(JsonLayer::new(std::io::stdout)).init();
+    } else {
+        let fields = format::debug_fn(|writer, field, value| {
+            let value = format!("{:?}", value);
+            let value = redact_field(field.name(), &value);
+            if field.name() == "message" {
+                write!(writer, "{}", value)
+            } else {
+                write!(writer, "{}={}", field.name(), value)
+            }
+        })
+        .delimited(" ");
+        registry
+            .with(tracing_subscriber::fmt::layer().fmt_fields(fields))
+            .init();
+    }
+}
+
+fn is_sensitive_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase().replace('_', "-");
+    SENSITIVE_FIELDS.contains(&name.as_str())
+}
+
+fn secret_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // `Authorization: Bearer <token>`
+            (r"([Bb]earer|BEARER)[ \t]+[A-Za-z0-9._~+/=-]+", "$1 [REDACTED]"),
+            // Session and CSRF cookies or headers, e.g. `ltcms_session=...`
+            (
+                r"(ltcms_session|ltcms_csrf|[Xx]-[Cc][Ss][Rr][Ff]-[Tt][Oo][Kk][Ee][Nn])([ \t]*[=:][ \t]*)[^;,& \t\x22]+",
+                "$1$2[REDACTED]",
+            ),
+            // Bare JWTs
+            (r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+", "[REDACTED]"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (Regex::new(pattern).expect("valid redaction regex"), replacement)
+        })
+        .collect()
+    })
+}
+
+/// Masks bearer tokens, JWTs and session or CSRF cookie values in `text`.
+pub fn redact_secrets(text: &str) -> Cow<'_, str> {
+    let mut redacted = Cow::Borrowed(text);
+    for (pattern, replacement) in secret_patterns() {
+        if let Cow::Owned(replaced) = pattern.replace_all(&redacted, *replacement) {
+            redacted = Cow::Owned(replaced);
+        }
+    }
+    redacted
+}
+
+/// The value to log for field `name`: fully masked for credential fields
+/// such as `authorization`, otherwise with secrets inside it masked.
+pub fn redact_field<'a>(name: &str, value: &'a str) -> Cow<'a, str> {
+    if is_sensitive_field(name) {
+        Cow::Borrowed(REDACTED)
+    } else {
+        redact_secrets(value)
+    }
+}
+
+/// Fields of a span or event, redacted as they are recorded.
+#[derive(Debug, Default)]
+struct JsonFields(Map<String, Value>);
+
+impl JsonFields {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let value = if is_sensitive_field(field.name()) {
+            Value::from(REDACTED)
+        } else {
+            value
+        };
+        self.0.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(redact_secrets(value).into_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{:?}", value);
+        self.insert(field, Value::from(redact_secrets(&value).into_owned()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+}
+
+/// Writes events as single-line JSON objects to `make_writer`.
+pub struct JsonLayer<W> {
+    make_writer: W,
+}
+
+impl<W> JsonLayer<W> {
+    pub fn new(make_writer: W) -> Self {
+        Self { make_writer }
+    }
+}
+
+impl<S, W> Layer<S> for JsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + 'static,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = JsonFields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<JsonFields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+
+        // Inner spans and the event itself win over outer span fields
+        let mut fields = Map::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<JsonFields>() {
+                    fields.extend(span_fields.0.clone());
+                }
+            }
+        }
+        let mut event_fields = JsonFields::default();
+        event.record(&mut event_fields);
+        fields.extend(event_fields.0);
+        for (name, value) in fields {
+            line.entry(name).or_insert(value);
+        }
+
+        let Ok(mut json) = serde_json::to_vec(&line) else {
+            return;
+        };
+        json.push(b'\n');
+        // Nowhere to report a failed log write
+        let _ = self.make_writer.make_writer().write_all(&json);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects log output in memory.
+    #[derive(Clone, Default)]
+    pub(crate) struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        /// Every line written so far, parsed as JSON.
+        pub(crate) fn lines(&self) -> Vec<Value> {
+            let buffer = self.0.lock().unwrap();
+            String::from_utf8_lossy(&buffer)
+                .lines()
+                .map(|line| serde_json::from_str(line).expect("JSON log line"))
+                .collect()
+        }
+    }
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Captured {
+        type Writer = Captured;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields_and_redact_secrets() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(JsonLayer::new(captured.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "req-1");
+            let _entered = span.enter();
+            tracing::warn!(
+                authorization = "Bearer abc.def.ghi",
+                status = 500u16,
+                "Upstream rejected Bearer s3cr3t-token with cookie ltcms_session=abcdef; path=/"
+            );
+        });
+
+        let lines = captured.lines();
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["target"], module_path!());
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["status"], 500);
+        assert_eq!(line["authorization"], REDACTED);
+        assert_eq!(
+            line["message"],
+            "Upstream rejected Bearer [REDACTED] with cookie ltcms_session=[REDACTED]; path=/"
+        );
+    }
+
+    #[test]
+    fn test_redact_helpers() {
+        assert_eq!(redact_field("X-CSRF-Token", "abc"), REDACTED);
+        assert_eq!(redact_field("set_cookie", "abc"), REDACTED);
+        assert_eq!(redact_field("path", "/api/posts"), "/api/posts");
+        assert_eq!(
+            redact_secrets("token eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhZG1pbiJ9.c2ln expired"),
+            "token [REDACTED] expired"
+        );
+        assert_eq!(redact_secrets("x-csrf-token: 1234"), "x-csrf-token: [REDACTED]");
+        assert!(matches!(redact_secrets("nothing here"), Cow::Borrowed(_)));
+    }
+}
@@ -1,35 +1,41 @@
 // Module declarations for organizing the backend codebase
+pub mod app; // Application config, shared state and the router
+pub mod backup; // Scheduled content backups
+pub mod content_bundle; // Content export/import bundles
+pub mod content_fetch; // Content bundles fetched from a URL
 pub mod security; // Authentication, authorization, and CSRF protection
 pub mod db; // Database connection and pooling
+pub mod derived; // Topic index, search index and post statistics
+pub mod error; // Handler errors with machine-readable codes
 pub mod handlers; // HTTP request handlers organized by feature
+pub mod jobs; // Scheduled background jobs
+pub mod logging; // Log format, filtering and redaction
 pub mod middleware; // Middleware modules
 pub mod models; // Data structures and database models
 pub mod repositories; // Repository modules
+pub mod settings; // Cached, typed site settings
+pub mod storage; // Upload storage backends
 pub mod routes; // Route definitions
+pub mod tasks; // Background task heartbeats
+pub mod tls; // Optional HTTPS termination
+pub mod utils; // Shared text and formatting helpers
+pub mod validation; // Field rules shared by the API and the importer
 
-use crate::middleware::{cors, security as security_middleware};
+use crate::middleware::{body_limit, bot, cache_control, security as security_middleware};
 
 // HTTP-related imports for building the web server
-use axum::{
-    extract::DefaultBodyLimit,
-    routing::get,
-    Router,
-};
+use axum::{extract::Request, response::Response, serve::ListenerExt};
 
 // External dependencies for configuration, async runtime, and middleware
 use dotenv::dotenv;
-use std::env;
+use std::convert::Infallible;
 use std::io::ErrorKind;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::signal;
-use tower_http::cors::CorsLayer;
-use tracing_subscriber;
-
-// Custom HTTP header constants for security policies
-use axum::http::{
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-    Method,
-};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tower::Service;
 
 /// Main application entry point.
 #[tokio::main]
@@ -37,8 +43,8 @@ async fn main() {
     // Load environment variables from .env file (if present)
     dotenv().ok();
 
-    // Initialize structured logging
-    tracing_subscriber::fmt::init();
+    // Initialize structured logging, see `logging` for LOG_FORMAT and LOG_LEVEL
+    logging::init();
 
     security::auth::init_jwt_secret().expect("Failed to initialize JWT secret");
     tracing::info!("JWT secret initialized successfully");
@@ -53,118 +59,228 @@ async fn main() {
         .await
         .expect("Failed to create database pool");
 
+    // Jobs stop when `shutdown_tx` flips to true and report their heartbeats
+    // to the readiness probe through `task_registry`
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let task_registry = tasks::TaskRegistry::new();
+    let mut scheduler = jobs::Scheduler::new(pool.clone(), task_registry.clone());
+    if let Some(job) = jobs::TrashPurgeJob::from_env() {
+        scheduler.register(job);
+    }
+    match backup::BackupConfig::from_env().expect("Invalid backup settings") {
+        Some(config) => {
+            tracing::info!(backups = ?config, "Configured content backups");
+            scheduler.register(backup::BackupJob::new(config));
+        }
+        None => tracing::info!("BACKUP_CONTENT_DIR not set, content backups are disabled"),
+    }
+    scheduler
+        .register(jobs::UploadSessionCleanupJob {
+            root: handlers::upload_sessions::session_root(),
+        })
+        .register(jobs::IpBanPruneJob)
+        .register(jobs::TokenBlacklistPurgeJob)
+        .register(jobs::ScheduledPostUploadsJob);
+    let mut background_tasks = Vec::new();
+
+    security_middleware::init_security_headers().expect("Invalid security header settings");
+    cache_control::init_cache_policy().expect("Invalid cache policy settings");
+    body_limit::init_body_limits().expect("Invalid body limits");
+    content_fetch::init_fetch_config().expect("Invalid import fetch settings");
+    handlers::upload::init_upload_limits().expect("Invalid upload limits");
+    db::timing::init_slow_query_threshold().expect("Invalid slow query threshold");
+    bot::init_bot_signatures().expect("Invalid bot signatures");
+    utils::public_url::init_public_base_url().expect("Invalid PUBLIC_BASE_URL");
+    handlers::frontend_proxy::init_app_routes().expect("Invalid SPA_APP_ROUTES");
+    handlers::frontend_proxy::init_supported_locales().expect("Invalid SUPPORTED_LOCALES");
+    handlers::frontend_proxy::init_inline_bootstrap().expect("Invalid INLINE_BOOTSTRAP_MAX_BYTES");
+    let upload_storage = storage::init().expect("Failed to initialize upload storage");
     // Ensure uploads directory exists
-    let upload_dir = env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
-    if !std::path::Path::new(&upload_dir).exists() {
-        tokio::fs::create_dir_all(&upload_dir)
+    if let Some(upload_dir) = upload_storage.local_root() {
+        tokio::fs::create_dir_all(upload_dir)
             .await
             .expect("Failed to create uploads directory");
     }
-
-    // Configure CORS (Cross-Origin Resource Sharing)
-    let cors_origins = env::var("CORS_ALLOWED_ORIGINS")
-        .map(|val| {
-            val.split(',')
-                .map(|s| s.trim().to_string())
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_else(|_| {
-            cors::DEV_DEFAULT_FRONTEND_ORIGINS
-                .iter()
-                .map(|&s| s.to_string())
-                .collect()
-        });
-
-    let allowed_origins = cors::parse_allowed_origins(cors_origins.iter().map(|s| s.as_str()));
-
-    let cors_layer = CorsLayer::new()
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers([CONTENT_TYPE, AUTHORIZATION, ACCEPT])
-        .allow_credentials(true)
-        .allow_origin(allowed_origins);
-
-    tracing::info!(origins = ?cors_origins, "Configured CORS origins");
-
-    let trust_proxy_ip_headers = security_middleware::parse_env_bool("TRUST_PROXY_IP_HEADERS", false);
-    if trust_proxy_ip_headers {
-        tracing::info!("Trusting X-Forwarded-* headers for client IP extraction");
-    } else {
-        tracing::info!("Proxy headers will be stripped before rate limiting to prevent spoofing");
-    }
-
-    // Create routes
-    let app_routes = routes::create_routes(pool.clone(), upload_dir);
-
-    // Define the application router with all routes and middleware
-    let app = Router::new()
-        .merge(app_routes)
-        .route("/api/health", get(|| async { "OK" }))
-        // Serve index.html with server-side injection for root and fallback
-        .route("/", get(handlers::frontend_proxy::serve_index))
-        .route("/{*path}", get(handlers::frontend_proxy::serve_index))
-        .layer(axum::middleware::from_fn(security_middleware::security_headers))
-        .layer(cors_layer)
-        .layer(DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB body limit
-        .with_state(pool.clone());
-
-    // Apply trusted proxy middleware if configured
-    let app = if trust_proxy_ip_headers {
-        app
-    } else {
-        app.layer(axum::middleware::from_fn(
-            security_middleware::strip_untrusted_forwarded_headers,
-        ))
-    };
-    let port_str = env::var("PORT").unwrap_or_else(|_| "8489".to_string());
-    let port: u16 = match port_str.parse() {
-        Ok(port) => port,
-        Err(e) => {
-            panic!(
-                "Invalid PORT '{}': {}. Please set PORT to a valid u16 value.",
-                port_str, e
-            );
+    handlers::upload::backfill_uploads_once(&pool, upload_storage.as_ref()).await;
+
+    let config = app::AppConfig::from_env().expect("Invalid settings");
+    config.log();
+    let port = config.port;
+    // Fails startup on unreadable or mismatched certificate files
+    let tls = config.tls.clone().map(|settings| {
+        let store = tls::CertStore::load(&settings).expect("Invalid TLS certificate");
+        (settings, store)
+    });
+
+    let mut state = app::AppState::new(pool.clone(), config, upload_storage);
+    // The jobs registered above report to this registry
+    state.tasks = task_registry;
+    // With ADMIN_BIND_ADDR the admin API gets a listener of its own and the
+    // main listener only serves the public routes
+    let admin_app = state
+        .config
+        .admin_addr
+        .map(|admin_addr| (admin_addr, app::build_admin_router(state.clone())));
+    let app = app::build_router(state);
+
+    let tls_config = match &tls {
+        Some((settings, store)) => {
+            scheduler.register(jobs::CertificateReloadJob {
+                store: store.clone(),
+            });
+            if let Some(redirect_port) = settings.redirect_port {
+                let https_port = settings.public_port.unwrap_or(port);
+                background_tasks.push((
+                    "HTTPS redirect",
+                    spawn_https_redirect(redirect_port, https_port, shutdown_rx.clone()).await,
+                ));
+            }
+            Some(store.server_config().expect("Failed to configure TLS"))
         }
+        None => None,
     };
-
-    if port < 1024 {
-        tracing::warn!(
-            "PORT {} is in privileged range (< 1024). May require elevated permissions.",
-            port
-        );
+    background_tasks.extend(scheduler.start(shutdown_rx.clone()));
+
+    // Every listener stops accepting connections on the same signal
+    let (stop_tx, stop_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        stop_tx.send_replace(true);
+    });
+
+    if let Some((admin_addr, admin_app)) = admin_app {
+        let listener = bind(admin_addr, "ADMIN_BIND_ADDR").await;
+        tracing::info!("Starting admin server on {}", admin_addr);
+        let server = serve(listener, admin_app, tls_config.clone(), stop_rx.clone());
+        background_tasks.push((
+            "admin listener",
+            tokio::spawn(async move {
+                if let Err(err) = server.await {
+                    tracing::error!("Admin server error: {}", err);
+                }
+            }),
+        ));
     }
 
-    let addr = format!("0.0.0.0:{}", port);
-
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
     tracing::info!("Starting server on {}", addr);
+    let listener = bind(addr, "PORT").await;
+    if tls_config.is_some() {
+        tracing::info!("Server is ready to accept HTTPS connections");
+    } else {
+        tracing::info!("Server is ready to accept connections");
+    }
+    let result = serve(listener, app, tls_config, stop_rx).await;
+    if let Err(e) = result {
+        tracing::error!("Server error: {}", e);
+    }
+    tracing::info!("Server stopped accepting connections");
+
+    shutdown(&pool, &shutdown_tx, background_tasks).await;
+    tracing::info!("Server shutdown complete");
+}
 
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
+/// Binds `addr`; `setting` names the variable to change when it is taken.
+async fn bind(addr: SocketAddr, setting: &str) -> tokio::net::TcpListener {
+    match tokio::net::TcpListener::bind(addr).await {
         Ok(listener) => listener,
         Err(err) => {
             if err.kind() == ErrorKind::AddrInUse {
-                panic!("Failed to bind to {addr}: port {port} is already in use. Choose a different PORT value.");
+                panic!("Failed to bind to {addr}: the address is already in use. Choose a different {setting} value.");
             } else {
                 panic!("Failed to bind to {addr}: {err}");
             }
         }
-    };
-
-    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
-
-    let server = axum::serve(listener, make_service).with_graceful_shutdown(shutdown_signal());
+    }
+}
 
-    tracing::info!("Server is ready to accept connections");
+/// Serves `app` on `listener`, over TLS when `tls_config` is set, until
+/// `stop` flips to true; connections in progress are allowed to finish.
+async fn serve<S>(
+    listener: tokio::net::TcpListener,
+    app: S,
+    tls_config: Option<std::sync::Arc<tokio_rustls::rustls::ServerConfig>>,
+    mut stop: watch::Receiver<bool>,
+) -> std::io::Result<()>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    let make_service =
+        axum::ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app);
+    let stopped = async move {
+        let _ = stop.wait_for(|stop| *stop).await;
+    };
+    match tls_config {
+        None => {
+            axum::serve(listener, make_service)
+                .with_graceful_shutdown(stopped)
+                .await
+        }
+        Some(config) => {
+            let listener = tls::TlsListener::new(listener, config)?.tap_io(tls::set_nodelay);
+            axum::serve(listener, make_service)
+                .with_graceful_shutdown(stopped)
+                .await
+        }
+    }
+}
 
-    if let Err(e) = server.await {
-        tracing::error!("Server error: {}", e);
+/// How long background tasks get to finish their current run on shutdown.
+const BACKGROUND_TASK_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stops the background tasks, waits for them and closes the database.
+/// Returns how many tasks panicked or had to be aborted.
+async fn shutdown(
+    pool: &db::DbPool,
+    shutdown_tx: &watch::Sender<bool>,
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+) -> usize {
+    tracing::info!("Stopping {} background task(s)", tasks.len());
+    shutdown_tx.send_replace(true);
+
+    let deadline = tokio::time::Instant::now() + BACKGROUND_TASK_SHUTDOWN_TIMEOUT;
+    let mut failed = 0;
+    for (name, mut task) in tasks {
+        match tokio::time::timeout_at(deadline, &mut task).await {
+            Ok(Ok(())) => tracing::info!("Background task '{}' stopped", name),
+            Ok(Err(err)) => {
+                failed += 1;
+                tracing::error!("Background task '{}' failed: {}", name, err);
+            }
+            Err(_) => {
+                failed += 1;
+                task.abort();
+                tracing::warn!("Background task '{}' did not stop in time; aborted", name);
+            }
+        }
     }
 
-    tracing::info!("Server shutdown complete");
+    tracing::info!("Closing database pool");
+    db::pool::close_pool(pool).await;
+    failed
+}
+
+/// Binds the plain HTTP listener that redirects to HTTPS on `https_port`.
+async fn spawn_https_redirect(
+    port: u16,
+    https_port: u16,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    let addr = format!("0.0.0.0:{}", port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|err| panic!("Failed to bind HTTPS redirect listener to {addr}: {err}"));
+    tracing::info!("Redirecting plain HTTP on {} to HTTPS", addr);
+    tokio::spawn(async move {
+        let server = axum::serve(listener, tls::redirect_app(https_port))
+            .with_graceful_shutdown(async move {
+                let _ = shutdown.changed().await;
+            });
+        if let Err(err) = server.await {
+            tracing::error!("HTTPS redirect listener error: {}", err);
+        }
+    })
 }
 
 /// Waits for a shutdown signal and initiates graceful shutdown.
@@ -201,3 +317,98 @@ async fn shutdown_signal() {
 
     tracing::info!("Starting graceful shutdown...");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_drains_tasks_and_truncates_wal() {
+        let dir = std::env::temp_dir().join(format!("shutdown-{}", uuid::Uuid::new_v4()));
+        let db_path = dir.join("cms.db");
+        let pool = db::pool::create_pool_with_url(&format!("sqlite:{}", db_path.display()))
+            .await
+            .expect("file pool");
+        repositories::app_metadata::set_metadata(&pool, "shutdown_test", "1")
+            .await
+            .unwrap();
+        let wal_path = dir.join("cms.db-wal");
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let registry = tasks::TaskRegistry::new();
+        let mut scheduler = jobs::Scheduler::new(pool.clone(), registry.clone());
+        scheduler
+            .register(jobs::TrashPurgeJob::from_env().expect("enabled"))
+            .register(jobs::UploadSessionCleanupJob {
+                root: dir.join("sessions"),
+            })
+            .register(jobs::IpBanPruneJob)
+            .register(jobs::TokenBlacklistPurgeJob)
+            .register(jobs::ScheduledPostUploadsJob);
+        let mut tasks = scheduler.start(shutdown_rx);
+        tasks.push(("panicking", tokio::spawn(async { panic!("task failure") })));
+        // Let the first run of each task go through
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(shutdown(&pool, &shutdown_tx, tasks).await, 1);
+        assert!(pool.is_closed());
+        let wal_len = std::fs::metadata(&wal_path).map_or(0, |meta| meta.len());
+        assert_eq!(wal_len, 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Sends a bare HTTP/1.1 request and returns the status code.
+    async fn http_status(addr: SocketAddr, method: &str, path: &str) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response[9..12].parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_are_only_served_on_the_admin_listener() {
+        let pool = db::pool::create_test_pool().await;
+        let dir = std::env::temp_dir().join(format!("listeners-{}", uuid::Uuid::new_v4()));
+        let mut state = app::AppState::for_test(&pool, &dir);
+        state.config.admin_addr = Some(SocketAddr::from(([127, 0, 0, 1], 8490)));
+        let public = app::build_router(state.clone());
+        let admin = app::build_admin_router(state);
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let public_listener = bind(SocketAddr::from(([127, 0, 0, 1], 0)), "PORT").await;
+        let admin_listener = bind(SocketAddr::from(([127, 0, 0, 1], 0)), "ADMIN_BIND_ADDR").await;
+        let public_addr = public_listener.local_addr().unwrap();
+        let admin_addr = admin_listener.local_addr().unwrap();
+        let servers = [
+            tokio::spawn(serve(public_listener, public, None, stop_rx.clone())),
+            tokio::spawn(serve(admin_listener, admin, None, stop_rx)),
+        ];
+
+        assert_eq!(http_status(public_addr, "GET", "/api/admin/jobs").await, 404);
+        assert_eq!(http_status(public_addr, "POST", "/api/auth/login").await, 404);
+        assert_eq!(http_status(public_addr, "GET", "/api/public/bootstrap").await, 200);
+        assert_eq!(http_status(public_addr, "GET", "/api/health").await, 200);
+
+        assert_eq!(http_status(admin_addr, "GET", "/api/admin/jobs").await, 401);
+        assert_eq!(http_status(admin_addr, "POST", "/api/auth/login").await, 415);
+        assert_eq!(http_status(admin_addr, "GET", "/api/public/bootstrap").await, 404);
+        assert_eq!(http_status(admin_addr, "GET", "/api/health").await, 200);
+
+        stop_tx.send_replace(true);
+        for server in servers {
+            tokio::time::timeout(Duration::from_secs(5), server)
+                .await
+                .expect("listener did not stop")
+                .unwrap()
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! One log event per request with status, latency and response size, under
+//! the `access` target so it can be filtered separately, e.g.
+//! `LOG_LEVEL=info,access=off`.
+//!
+//! Built on [`TraceLayer`]: each request gets an `access` span with the
+//! method, path, matched route template, request id and client IP, and the
+//! response is logged inside it at `info` for 2xx/3xx, `warn` for 4xx and
+//! `error` for 5xx. The query string and all headers are left out since
+//! they may carry tokens or cookies. The client IP is the one
+//! [`resolve_client_ip`](super::security::resolve_client_ip) settled on.
+//!
+//! `ACCESS_LOG_SKIP_PATHS` lists paths not to log at all, comma-separated,
+//! e.g. `/api/health,/api/health/ready` for probes.
+
+use super::{request_id, security::ClientIp};
+use axum::{
+    body::HttpBody,
+    extract::MatchedPath,
+    http::{header::CONTENT_LENGTH, Request, Response},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::{
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    trace::{MakeSpan, OnResponse, TraceLayer},
+};
+use tracing::Span;
+
+pub type AccessLogLayer = TraceLayer<
+    SharedClassifier<ServerErrorsAsFailures>,
+    AccessSpan,
+    (),
+    AccessResponse,
+    (),
+    (),
+    (),
+>;
+
+/// Paths excluded from the access log, read from the environment once at
+/// startup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessLogConfig {
+    skip_paths: Arc<[String]>,
+}
+
+impl AccessLogConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let skip_paths = lookup("ACCESS_LOG_SKIP_PATHS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| {
+                if path.starts_with('/') {
+                    Ok(path.to_string())
+                } else {
+                    Err(format!(
+                        "ACCESS_LOG_SKIP_PATHS entries must start with '/', got '{}'",
+                        path
+                    ))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            skip_paths: skip_paths.into(),
+        })
+    }
+}
+
+/// Opens the `access` span, or none for skipped paths.
+#[derive(Debug, Clone)]
+pub struct AccessSpan {
+    config: AccessLogConfig,
+}
+
+impl<B> MakeSpan<B> for AccessSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let path = request.uri().path();
+        if self.config.skip_paths.iter().any(|skip| skip == path) {
+            return Span::none();
+        }
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(MatchedPath::as_str);
+        let client_ip = ClientIp::from_extensions(request.extensions());
+        tracing::info_span!(
+            target: "access",
+            "access",
+            method = %request.method(),
+            path = %path,
+            route,
+            request_id = request_id::current(),
+            client_ip = client_ip.map(tracing::field::display),
+        )
+    }
+}
+
+/// Logs the response inside the `access` span.
+#[derive(Debug, Clone, Copy)]
+pub struct AccessResponse;
+
+/// Size of the body as sent, when known up front. Streamed and compressed
+/// bodies have none.
+fn response_bytes<B: HttpBody>(response: &Response<B>) -> Option<u64> {
+    response.body().size_hint().exact().or_else(|| {
+        response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+impl<B: HttpBody> OnResponse<B> for AccessResponse {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        if span.is_none() {
+            return;
+        }
+        let status = response.status().as_u16();
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let bytes = response_bytes(response);
+        macro_rules! log {
+            ($level:ident) => {
+                tracing::$level!(
+                    target: "access",
+                    parent: span,
+                    status,
+                    latency_ms,
+                    bytes,
+                    "request completed"
+                )
+            };
+        }
+        match status {
+            500.. => log!(error),
+            400..=499 => log!(warn),
+            _ => log!(info),
+        }
+    }
+}
+
+pub fn access_log_layer(config: AccessLogConfig) -> AccessLogLayer {
+    TraceLayer::new_for_http()
+        .make_span_with(AccessSpan { config })
+        .on_request(())
+        .on_response(AccessResponse)
+        .on_body_chunk(())
+        .on_eos(())
+        .on_failure(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{tests::Captured, JsonLayer};
+    use crate::middleware::request_id::request_id;
+    use crate::middleware::security::{resolve_client_ip, TrustedProxies};
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header::AUTHORIZATION, StatusCode},
+        routing::get,
+        Router,
+    };
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn app(config: AccessLogConfig) -> Router {
+        Router::new()
+            .route("/api/tutorials/{id}", get(|| async { "tutorial body" }))
+            .route("/api/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/api/broken",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .route("/api/health", get(|| async { "OK" }))
+            .layer(access_log_layer(config))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new("127.0.0.1".parse::<TrustedProxies>().unwrap()),
+                resolve_client_ip,
+            ))
+            .layer(axum::middleware::from_fn(request_id))
+    }
+
+    async fn send(app: &Router, uri: &str) -> StatusCode {
+        let request = Request::builder()
+            .uri(uri)
+            .header("x-request-id", "req-42")
+            .header("x-forwarded-for", "198.51.100.4, 203.0.113.7")
+            .header(AUTHORIZATION, "Bearer secret-token-value")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))))
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[test]
+    fn test_skip_paths_from_env() {
+        let parse = |value: &str| {
+            AccessLogConfig::parse(|key| {
+                (key == "ACCESS_LOG_SKIP_PATHS").then(|| value.to_string())
+            })
+        };
+        assert_eq!(parse("").unwrap(), AccessLogConfig::default());
+        assert_eq!(
+            &*parse(" /api/health , /metrics ").unwrap().skip_paths,
+            ["/api/health".to_string(), "/metrics".to_string()]
+        );
+        assert!(parse("api/health").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_access_event_fields() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(JsonLayer::new(captured.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = app(AccessLogConfig::parse(|_| Some("/api/health".to_string())).unwrap());
+
+        assert_eq!(
+            send(&app, "/api/tutorials/bash?token=secret").await,
+            StatusCode::OK
+        );
+        assert_eq!(send(&app, "/api/missing").await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            send(&app, "/api/broken").await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(send(&app, "/api/health").await, StatusCode::OK);
+
+        let lines = captured.lines();
+        let access: Vec<_> = lines
+            .iter()
+            .filter(|line| line["target"] == "access")
+            .collect();
+        assert_eq!(access.len(), 3, "{access:?}");
+
+        let ok = access[0];
+        assert_eq!(ok["level"], "INFO");
+        assert_eq!(ok["method"], "GET");
+        assert_eq!(ok["path"], "/api/tutorials/bash");
+        assert_eq!(ok["route"], "/api/tutorials/{id}");
+        assert_eq!(ok["status"], 200);
+        assert_eq!(ok["bytes"], "tutorial body".len());
+        assert_eq!(ok["client_ip"], "203.0.113.7");
+        assert_eq!(ok["request_id"], "req-42");
+        assert!(ok["latency_ms"].as_f64().unwrap() >= 0.0);
+
+        assert_eq!(access[1]["level"], "WARN");
+        assert_eq!(access[1]["status"], 404);
+        assert_eq!(access[2]["level"], "ERROR");
+        assert_eq!(access[2]["status"], 500);
+
+        let output = serde_json::to_string(&lines).unwrap();
+        assert!(!output.contains("secret-token-value"));
+        assert!(!output.contains("token=secret"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Safety net recording every admin mutation in `audit_log`.
+//!
+//! Layered onto the admin router inside authentication, so the acting user is
+//! known. For each non-GET request the method, path, route template, user,
+//! status and latency are stored once the response is produced. The insert
+//! is awaited before the response is sent: the graceful shutdown waits for
+//! in-flight requests but not for detached tasks, so the entries of the last
+//! requests before a shutdown are not lost. Request and response bodies are
+//! never looked at.
+//!
+//! `AUDIT_LOG_ENABLED=false` turns the middleware off.
+
+use super::security::parse_env_bool;
+use crate::{db::DbPool, models::NewAuditEntry, repositories, security::auth::Claims};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+pub fn enabled_from_env() -> bool {
+    parse_env_bool("AUDIT_LOG_ENABLED", true)
+}
+
+pub async fn audit_mutations(State(pool): State<DbPool>, request: Request, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|route| route.as_str().to_string());
+    let username = request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub.clone());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let entry = NewAuditEntry {
+        username,
+        method,
+        path,
+        route,
+        status: response.status().as_u16(),
+        latency_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    };
+    if let Err(err) = repositories::audit_log::insert_entry(&pool, &entry).await {
+        tracing::error!(
+            method = %entry.method,
+            path = %entry.path,
+            "Failed to write audit log entry: {}",
+            err
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::StatusCode,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app(pool: &DbPool) -> Router {
+        Router::new()
+            .route(
+                "/api/posts/{id}",
+                get(|| async { "post" }).delete(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                pool.clone(),
+                audit_mutations,
+            ))
+            .with_state(pool.clone())
+    }
+
+    async fn send(app: &Router, method: Method, uri: &str) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_only_mutations_are_recorded() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let app = app(&pool);
+
+        assert_eq!(
+            send(&app, Method::GET, "/api/posts/7").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, Method::DELETE, "/api/posts/7").await,
+            StatusCode::NO_CONTENT
+        );
+
+        // Written before the response, nothing to wait for
+        let entries = repositories::audit_log::list_entries(&pool, 10)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1, "{entries:?}");
+        assert_eq!(entries[0].method, "DELETE");
+        assert_eq!(entries[0].path, "/api/posts/7");
+        assert_eq!(entries[0].route.as_deref(), Some("/api/posts/{id}"));
+        assert_eq!(entries[0].username, None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{security::auth, repositories};
+use crate::security::auth;
 use axum::{http::StatusCode, Json};
 
 /// AXUM middleware for protecting routes with authentication.
@@ -9,11 +9,15 @@ use axum::{http::StatusCode, Json};
 /// # Usage
 /// ```rust,no_run
 /// use axum::{Router, routing::get, middleware};
-/// use linux_tutorial_cms::middleware::auth;
+/// use rust_blog_backend::middleware::auth;
+/// # async fn handler() {}
+/// # fn build(pool: rust_blog_backend::db::DbPool) -> Router {
 ///
 /// let app = Router::new()
 ///     .route("/protected", get(handler))
-///     .route_layer(middleware::from_fn(auth::auth_middleware));
+///     .route_layer(middleware::from_fn_with_state(pool, auth::auth_middleware));
+/// # app
+/// # }
 /// ```
 ///
 /// # Authentication
@@ -54,8 +58,8 @@ pub async fn auth_middleware(
         )
     })?;
 
-    // Check if token is blacklisted
-    if let Ok(true) = repositories::token_blacklist::is_token_blacklisted(&pool, &token).await {
+    // Check if token is blacklisted or older than the last password change
+    if let Ok(true) = auth::is_token_revoked(&pool, &token, &claims).await {
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(crate::models::ErrorResponse {
@@ -0,0 +1,218 @@
+//! Request body size limits.
+//!
+//! Each route group is wrapped in [`with_body_limit`]: bodies over its limit
+//! are refused with a `413` and the usual JSON error body naming the limit,
+//! whether the `Content-Length` gives them away up front or an extractor runs
+//! into the limit while reading.
+//!
+//! Limits come from `LOGIN_BODY_LIMIT_BYTES` (default 64 KiB),
+//! `PUBLIC_BODY_LIMIT_BYTES` (default 2 MiB), `ADMIN_BODY_LIMIT_BYTES`
+//! (default 8 MiB, uploads included) and `GLOBAL_BODY_LIMIT_BYTES` (default
+//! 10 MiB, every other route), and must not decrease in that order.
+
+use crate::error::AppError;
+use axum::{
+    extract::{Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::sync::OnceLock;
+use tower_http::limit::RequestBodyLimitLayer;
+
+/// Limits of the route groups in bytes, read from the environment once at
+/// startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimits {
+    pub login: usize,
+    pub public: usize,
+    pub admin: usize,
+    pub global: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            login: 64 * 1024,
+            public: 2 * 1024 * 1024,
+            admin: 8 * 1024 * 1024,
+            global: 10 * 1024 * 1024,
+        }
+    }
+}
+
+static BODY_LIMITS: OnceLock<BodyLimits> = OnceLock::new();
+
+impl BodyLimits {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the limits through `lookup`; unset values keep their default.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let bytes = |key: &str, default: usize| -> Result<usize, String> {
+            let Some(raw) = lookup(key).filter(|raw| !raw.trim().is_empty()) else {
+                return Ok(default);
+            };
+            match raw.trim().parse::<usize>() {
+                Ok(value) if value > 0 => Ok(value),
+                _ => Err(format!(
+                    "{} must be a positive number of bytes, got '{}'",
+                    key, raw
+                )),
+            }
+        };
+
+        let defaults = Self::default();
+        let limits = Self {
+            login: bytes("LOGIN_BODY_LIMIT_BYTES", defaults.login)?,
+            public: bytes("PUBLIC_BODY_LIMIT_BYTES", defaults.public)?,
+            admin: bytes("ADMIN_BODY_LIMIT_BYTES", defaults.admin)?,
+            global: bytes("GLOBAL_BODY_LIMIT_BYTES", defaults.global)?,
+        };
+        if limits.login > limits.public
+            || limits.public > limits.admin
+            || limits.admin > limits.global
+        {
+            return Err(format!(
+                "Body limits must satisfy login <= public <= admin <= global, got {:?}",
+                limits
+            ));
+        }
+        Ok(limits)
+    }
+}
+
+/// Validates the body limits and fixes them for the process. Called once at
+/// startup, before anything that depends on them.
+pub fn init_body_limits() -> Result<(), String> {
+    BODY_LIMITS
+        .set(BodyLimits::from_env()?)
+        .map_err(|_| "Body limits already initialized".to_string())
+}
+
+pub fn body_limits() -> &'static BodyLimits {
+    BODY_LIMITS.get_or_init(|| {
+        BodyLimits::from_env().unwrap_or_else(|err| {
+            tracing::error!("{}; using default body limits", err);
+            BodyLimits::default()
+        })
+    })
+}
+
+/// The JSON error answering a body over `limit` bytes.
+pub fn payload_too_large(limit: usize) -> AppError {
+    AppError::PayloadTooLarge(format!(
+        "Request body too large; the limit is {} bytes",
+        limit
+    ))
+}
+
+/// Replaces the plain-text `413` of the limit layer or an extractor with the
+/// JSON one. Handlers answering `413` themselves already use JSON.
+async fn json_payload_too_large(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE || is_json {
+        return response;
+    }
+    tracing::warn!(limit, "Request body over the limit");
+    payload_too_large(limit).into_response()
+}
+
+/// Refuses bodies to `router` over `limit` bytes with a JSON `413`.
+pub fn with_body_limit<S>(router: Router<S>, limit: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(RequestBodyLimitLayer::new(limit))
+        .layer(axum::middleware::from_fn_with_state(
+            limit,
+            json_payload_too_large,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::CONTENT_LENGTH, routing::post};
+    use tower::ServiceExt;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<BodyLimits, String> {
+        BodyLimits::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_env_overrides_and_ordering() {
+        assert_eq!(parse(&[]).unwrap(), BodyLimits::default());
+
+        let limits = parse(&[
+            ("LOGIN_BODY_LIMIT_BYTES", "1024"),
+            ("ADMIN_BODY_LIMIT_BYTES", " 4194304 "),
+        ])
+        .unwrap();
+        assert_eq!((limits.login, limits.admin), (1024, 4 << 20));
+
+        assert!(parse(&[("PUBLIC_BODY_LIMIT_BYTES", "2MB")]).is_err());
+        assert!(parse(&[("GLOBAL_BODY_LIMIT_BYTES", "0")]).is_err());
+        // The admin limit may not exceed the global one
+        assert!(parse(&[("ADMIN_BODY_LIMIT_BYTES", "20971520")]).is_err());
+        assert!(parse(&[("LOGIN_BODY_LIMIT_BYTES", "4194304")]).is_err());
+    }
+
+    async fn send(app: &Router, body: Body, content_length: Option<usize>) -> (StatusCode, String) {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/echo")
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(length) = content_length {
+            request = request.header(CONTENT_LENGTH, length);
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_bodies_get_json_413() {
+        let app = with_body_limit(
+            Router::new().route("/echo", post(|body: String| async move { body })),
+            16,
+        );
+
+        let (status, body) = send(&app, Body::from("{}"), Some(2)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "{}"));
+
+        // Refused from the declared length, and while reading without one
+        for content_length in [Some(17), None] {
+            let (status, body) = send(&app, Body::from("x".repeat(17)), content_length).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(
+                body["error"],
+                "Request body too large; the limit is 16 bytes"
+            );
+        }
+    }
+}
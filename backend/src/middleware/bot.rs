@@ -0,0 +1,359 @@
+//! Throttling of bots and crawlers on the public routes.
+//!
+//! Requests whose `User-Agent` contains one of the bot signatures, or that
+//! send none at all, are tagged with [`Bot`] and held to the stricter
+//! `RATE_LIMIT_BOT_*` limit on top of the public one. They are slowed down,
+//! never blocked, so search engines can still crawl the public pages.
+//! Handlers check for [`Bot`] to leave view counts alone.
+//!
+//! Signatures are matched case-insensitively as substrings. The defaults can
+//! be replaced with a comma-separated `BOT_USER_AGENTS`, or with
+//! `BOT_USER_AGENTS_FILE` naming a file with one signature per line (`#`
+//! starts a comment).
+
+use super::{
+    rate_limit::{RateLimit, RATE_LIMIT_RESET_HEADER},
+    security::ClientIp,
+};
+use crate::models::ErrorResponse;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use governor::{
+    clock::{Clock, DefaultClock},
+    DefaultKeyedRateLimiter, Quota, RateLimiter,
+};
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::{Arc, OnceLock};
+
+const DEFAULT_SIGNATURES: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "facebookexternalhit",
+    "headlesschrome",
+    "curl",
+    "wget",
+    "python-requests",
+    "python-urllib",
+    "go-http-client",
+    "libwww-perl",
+    "scrapy",
+];
+
+/// Clients kept in the limiter before idle ones are dropped.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Request extension marking the client as a bot.
+#[derive(Debug, Clone, Copy)]
+pub struct Bot;
+
+/// Lowercase `User-Agent` substrings identifying bots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotSignatures {
+    signatures: Arc<[String]>,
+}
+
+impl Default for BotSignatures {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIGNATURES.iter().copied())
+    }
+}
+
+static BOT_SIGNATURES: OnceLock<BotSignatures> = OnceLock::new();
+
+impl BotSignatures {
+    fn new<'a>(signatures: impl Iterator<Item = &'a str>) -> Self {
+        Self {
+            signatures: signatures
+                .map(str::trim)
+                .filter(|signature| !signature.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(
+            |key| std::env::var(key).ok(),
+            |path| std::fs::read_to_string(path).map_err(|err| err.to_string()),
+        )
+    }
+
+    /// Reads the signatures through `lookup`, and the file through
+    /// `read_file`; without either variable the defaults apply.
+    fn parse(
+        lookup: impl Fn(&str) -> Option<String>,
+        read_file: impl Fn(&str) -> Result<String, String>,
+    ) -> Result<Self, String> {
+        let set = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+        let signatures = match (set("BOT_USER_AGENTS"), set("BOT_USER_AGENTS_FILE")) {
+            (None, None) => return Ok(Self::default()),
+            (Some(_), Some(_)) => {
+                return Err(
+                    "Set either BOT_USER_AGENTS or BOT_USER_AGENTS_FILE, not both".to_string(),
+                )
+            }
+            (Some(list), None) => Self::new(list.split(',')),
+            (None, Some(path)) => {
+                let contents = read_file(path.trim()).map_err(|err| {
+                    format!("Failed to read BOT_USER_AGENTS_FILE '{}': {}", path, err)
+                })?;
+                Self::new(
+                    contents
+                        .lines()
+                        .map(|line| line.split('#').next().unwrap_or_default()),
+                )
+            }
+        };
+        if signatures.signatures.is_empty() {
+            return Err("The bot signature list must not be empty".to_string());
+        }
+        Ok(signatures)
+    }
+
+    /// Whether the `User-Agent` belongs to a bot. Clients sending none are
+    /// counted as bots too.
+    pub fn is_bot(&self, user_agent: Option<&str>) -> bool {
+        let Some(user_agent) = user_agent.map(str::trim).filter(|ua| !ua.is_empty()) else {
+            return true;
+        };
+        let user_agent = user_agent.to_lowercase();
+        self.signatures
+            .iter()
+            .any(|signature| user_agent.contains(signature.as_str()))
+    }
+}
+
+/// Validates the bot signatures and fixes them for the process. Called once
+/// at startup.
+pub fn init_bot_signatures() -> Result<(), String> {
+    BOT_SIGNATURES
+        .set(BotSignatures::from_env()?)
+        .map_err(|_| "Bot signatures already initialized".to_string())
+}
+
+pub fn bot_signatures() -> &'static BotSignatures {
+    BOT_SIGNATURES.get_or_init(|| {
+        BotSignatures::from_env().unwrap_or_else(|err| {
+            tracing::error!("{}; using the default bot signatures", err);
+            BotSignatures::default()
+        })
+    })
+}
+
+/// Classifier and per-IP limiter shared by every route group it wraps.
+#[derive(Clone)]
+pub struct BotThrottle {
+    signatures: BotSignatures,
+    limiter: Arc<DefaultKeyedRateLimiter<IpAddr>>,
+}
+
+impl BotThrottle {
+    pub fn new(limit: RateLimit) -> Self {
+        Self::with_signatures(bot_signatures().clone(), limit)
+    }
+
+    pub fn with_signatures(signatures: BotSignatures, limit: RateLimit) -> Self {
+        let positive =
+            |value: u32| NonZeroU32::new(value).expect("rate limits are validated to be positive");
+        let quota =
+            Quota::per_second(positive(limit.per_second)).allow_burst(positive(limit.burst));
+        Self {
+            signatures,
+            limiter: Arc::new(RateLimiter::keyed(quota)),
+        }
+    }
+}
+
+fn too_many_requests(retry_after: u64) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse {
+            error: "Too many requests; please slow down".to_string(),
+        }),
+    )
+        .into_response();
+    let retry_after = HeaderValue::from(retry_after);
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, retry_after.clone());
+    response
+        .headers_mut()
+        .insert(RATE_LIMIT_RESET_HEADER, retry_after);
+    response
+}
+
+async fn throttle_bots(
+    State(throttle): State<BotThrottle>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    if !throttle.signatures.is_bot(user_agent) {
+        return next.run(request).await;
+    }
+    request.extensions_mut().insert(Bot);
+
+    let Some(ip) = ClientIp::from_extensions(request.extensions()) else {
+        return next.run(request).await;
+    };
+    if throttle.limiter.len() > MAX_TRACKED_CLIENTS {
+        throttle.limiter.retain_recent();
+    }
+    if let Err(not_until) = throttle.limiter.check_key(&ip) {
+        let wait = not_until.wait_time_from(DefaultClock::default().now());
+        tracing::debug!(client_ip = %ip, "Throttled bot request");
+        // Rounded up, so a bot honouring it is let through
+        return too_many_requests(wait.as_secs() + 1);
+    }
+    next.run(request).await
+}
+
+/// Tags bot requests to `router` with [`Bot`] and holds them to the bot limit.
+pub fn with_bot_throttle<S>(router: Router<S>, throttle: BotThrottle) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(axum::middleware::from_fn_with_state(
+        throttle,
+        throttle_bots,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::ConnectInfo, routing::get, Extension};
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    const BROWSER: &str =
+        "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36";
+    const GOOGLEBOT: &str =
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+    fn parse(vars: &[(&str, &str)], file: &str) -> Result<BotSignatures, String> {
+        BotSignatures::parse(
+            |key| {
+                vars.iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| value.to_string())
+            },
+            |path| {
+                if path == "/etc/bots.txt" {
+                    Ok(file.to_string())
+                } else {
+                    Err("No such file".to_string())
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_signatures_from_env_and_file() {
+        let defaults = parse(&[], "").unwrap();
+        assert_eq!(defaults, BotSignatures::default());
+        assert!(defaults.is_bot(Some(GOOGLEBOT)));
+        assert!(defaults.is_bot(Some("curl/8.5.0")));
+        assert!(defaults.is_bot(None));
+        assert!(defaults.is_bot(Some("  ")));
+        assert!(!defaults.is_bot(Some(BROWSER)));
+
+        let listed = parse(&[("BOT_USER_AGENTS", " Curl , Wget ,")], "").unwrap();
+        assert!(listed.is_bot(Some("curl/8.5.0")));
+        assert!(!listed.is_bot(Some(GOOGLEBOT)));
+
+        let from_file = parse(
+            &[("BOT_USER_AGENTS_FILE", "/etc/bots.txt")],
+            "# crawlers\nGooglebot\n\nbingbot  # Microsoft\n",
+        )
+        .unwrap();
+        assert!(from_file.is_bot(Some(GOOGLEBOT)));
+        assert!(from_file.is_bot(Some("Mozilla/5.0 (compatible; bingbot/2.0)")));
+        assert!(!from_file.is_bot(Some("curl/8.5.0")));
+
+        assert!(parse(&[("BOT_USER_AGENTS_FILE", "/missing")], "").is_err());
+        assert!(parse(&[("BOT_USER_AGENTS", ", ,")], "").is_err());
+        assert!(parse(
+            &[
+                ("BOT_USER_AGENTS", "curl"),
+                ("BOT_USER_AGENTS_FILE", "/etc/bots.txt")
+            ],
+            "bot"
+        )
+        .is_err());
+    }
+
+    async fn send(app: &Router, user_agent: &str, last_octet: u8) -> Response {
+        let request = Request::builder()
+            .uri("/api/public/pages/home")
+            .header(header::USER_AGENT, user_agent)
+            .extension(ConnectInfo(SocketAddr::from((
+                [192, 0, 2, last_octet],
+                5000,
+            ))))
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bots_are_tagged_and_throttled_sooner() {
+        let throttle = BotThrottle::with_signatures(
+            BotSignatures::default(),
+            RateLimit {
+                per_second: 1,
+                burst: 2,
+            },
+        );
+        let app = with_bot_throttle(
+            Router::new().route(
+                "/api/public/pages/{slug}",
+                get(|bot: Option<Extension<Bot>>| async move {
+                    if bot.is_some() {
+                        "bot"
+                    } else {
+                        "reader"
+                    }
+                }),
+            ),
+            throttle,
+        );
+
+        for _ in 0..5 {
+            let response = send(&app, BROWSER, 1).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], b"reader");
+        }
+
+        for _ in 0..2 {
+            let response = send(&app, GOOGLEBOT, 2).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], b"bot");
+        }
+        let response = send(&app, GOOGLEBOT, 2).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = &response.headers()[header::RETRY_AFTER];
+        assert!(retry_after.to_str().unwrap().parse::<u64>().unwrap() >= 1);
+        assert_eq!(&response.headers()[RATE_LIMIT_RESET_HEADER], retry_after);
+
+        // Each bot gets its own bucket
+        assert_eq!(send(&app, "curl/8.5.0", 3).await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,295 @@
+//! `Cache-Control` by route class.
+//!
+//! Responses without a `Cache-Control` of their own get the policy of their
+//! route class:
+//!
+//! - auth, admin and every non-GET API request: `no-store`, plus the legacy
+//!   `Pragma`/`Expires` pair
+//! - public GET JSON (tutorials, search, public content and pages):
+//!   `PUBLIC_API_CACHE_CONTROL`, default
+//!   [`PUBLIC_SHORT_CACHE`](crate::utils::conditional::PUBLIC_SHORT_CACHE),
+//!   with `Vary: Cookie, Authorization`. A request carrying a session cookie
+//!   or an `Authorization` header is `no-store` instead, since some public
+//!   routes answer signed-in viewers with members-only pages and navigation
+//! - `/uploads`: cached for a year, as upload names are never reused
+//! - HTML: `no-cache`, revalidated by its ETag; pages of the frontend proxy
+//!   set a CDN-friendly policy of their own for anonymous viewers
+//!
+//! A handler that sets the header itself, usually through
+//! [`crate::utils::conditional::respond`] alongside its ETag, keeps its
+//! choice. Error responses are never cached.
+
+use super::uploads::IMMUTABLE_CACHE_CONTROL;
+use crate::security::auth;
+use crate::utils::conditional::{PUBLIC_SHORT_CACHE, REVALIDATE};
+use axum::{
+    extract::Request,
+    http::{
+        header::{CACHE_CONTROL, EXPIRES, PRAGMA, VARY},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use std::sync::OnceLock;
+
+pub const NO_STORE: &str = "no-store, no-cache, must-revalidate";
+
+/// Policy for public API reads, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub public_api: HeaderValue,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            public_api: HeaderValue::from_static(PUBLIC_SHORT_CACHE),
+        }
+    }
+}
+
+static CACHE_POLICY: OnceLock<CachePolicy> = OnceLock::new();
+
+impl CachePolicy {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the policy through `lookup`; unset values keep their default.
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let Some(raw) = lookup("PUBLIC_API_CACHE_CONTROL").filter(|raw| !raw.trim().is_empty())
+        else {
+            return Ok(Self::default());
+        };
+        let public_api = HeaderValue::from_str(raw.trim()).map_err(|_| {
+            "PUBLIC_API_CACHE_CONTROL contains characters not allowed in a header".to_string()
+        })?;
+        Ok(Self { public_api })
+    }
+}
+
+pub fn init_cache_policy() -> Result<(), String> {
+    CACHE_POLICY
+        .set(CachePolicy::from_env()?)
+        .map_err(|_| "Cache policy already initialized".to_string())
+}
+
+fn cache_policy() -> &'static CachePolicy {
+    CACHE_POLICY.get_or_init(CachePolicy::default)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteClass {
+    Private,
+    PublicApi,
+    Immutable,
+    Html,
+}
+
+impl RouteClass {
+    pub fn of(method: &Method, path: &str) -> Self {
+        if method != Method::GET && method != Method::HEAD {
+            return Self::Private;
+        }
+        if path.starts_with("/uploads/") {
+            return Self::Immutable;
+        }
+        if path != "/api" && !path.starts_with("/api/") {
+            return Self::Html;
+        }
+        if path == "/api/tutorials"
+            || path.starts_with("/api/tutorials/")
+            || path.starts_with("/api/search/")
+            || path.starts_with("/api/public/")
+            || is_public_content_path(path)
+        {
+            Self::PublicApi
+        } else {
+            Self::Private
+        }
+    }
+
+    /// The class of a request, taking the viewer into account: public API
+    /// reads by a signed-in viewer may contain members-only data and must
+    /// not end up in a shared cache.
+    pub fn of_request(method: &Method, path: &str, headers: &HeaderMap) -> Self {
+        match Self::of(method, path) {
+            Self::PublicApi if auth::extract_token(headers).is_some() => Self::Private,
+            class => class,
+        }
+    }
+
+    fn cache_control(self, policy: &CachePolicy) -> HeaderValue {
+        match self {
+            Self::Private => HeaderValue::from_static(NO_STORE),
+            Self::PublicApi => policy.public_api.clone(),
+            Self::Immutable => HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+            Self::Html => HeaderValue::from_static(REVALIDATE),
+        }
+    }
+}
+
+/// `/api/content` and `/api/content/{section}`, but not the admin-only
+/// history routes nested below a section.
+fn is_public_content_path(path: &str) -> bool {
+    path == "/api/content"
+        || path
+            .strip_prefix("/api/content/")
+            .is_some_and(|section| !section.is_empty() && !section.contains('/'))
+}
+
+/// Sets `Cache-Control` on responses that lack one.
+pub async fn cache_control(request: Request, next: Next) -> Response {
+    let class = RouteClass::of_request(request.method(), request.uri().path(), request.headers());
+    let mut response = next.run(request).await;
+    let status = response.status();
+    let headers = response.headers_mut();
+
+    if !headers.contains_key(CACHE_CONTROL) {
+        let class = if status.is_success() || status == StatusCode::NOT_MODIFIED {
+            class
+        } else {
+            RouteClass::Private
+        };
+        headers.insert(CACHE_CONTROL, class.cache_control(cache_policy()));
+        if class == RouteClass::PublicApi {
+            headers.append(VARY, HeaderValue::from_static("Cookie, Authorization"));
+        }
+    }
+
+    let no_store = headers[CACHE_CONTROL]
+        .to_str()
+        .is_ok_and(|value| value.contains("no-store"));
+    if no_store {
+        // For HTTP/1.0 caches, which ignore Cache-Control
+        headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
+        headers.insert(EXPIRES, HeaderValue::from_static("0"));
+    } else {
+        headers.remove(PRAGMA);
+        headers.remove(EXPIRES);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, method: Method, uri: &str) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    fn cache_control_of(response: &Response) -> &str {
+        response.headers()[CACHE_CONTROL].to_str().unwrap()
+    }
+
+    #[test]
+    fn test_policy_from_env() {
+        assert_eq!(
+            CachePolicy::parse(|_| None).unwrap(),
+            CachePolicy::default()
+        );
+        let policy = CachePolicy::parse(|key| {
+            (key == "PUBLIC_API_CACHE_CONTROL").then(|| " public, max-age=10 ".to_string())
+        })
+        .unwrap();
+        assert_eq!(policy.public_api, "public, max-age=10");
+        assert!(CachePolicy::parse(|_| Some("public\nmax-age=10".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_route_classes() {
+        let class = |method: Method, path: &str| RouteClass::of(&method, path);
+        assert_eq!(class(Method::GET, "/api/tutorials"), RouteClass::PublicApi);
+        assert_eq!(
+            class(Method::HEAD, "/api/public/navigation"),
+            RouteClass::PublicApi
+        );
+        assert_eq!(
+            class(Method::GET, "/api/content/hero"),
+            RouteClass::PublicApi
+        );
+        assert_eq!(
+            class(Method::GET, "/api/content/hero/history"),
+            RouteClass::Private
+        );
+        assert_eq!(class(Method::PUT, "/api/tutorials/1"), RouteClass::Private);
+        assert_eq!(class(Method::GET, "/api/auth/me"), RouteClass::Private);
+        assert_eq!(class(Method::GET, "/api/admin/jobs"), RouteClass::Private);
+        assert_eq!(
+            class(Method::GET, "/uploads/diagram.png"),
+            RouteClass::Immutable
+        );
+        assert_eq!(class(Method::GET, "/pages/grundlagen"), RouteClass::Html);
+        assert_eq!(class(Method::GET, "/"), RouteClass::Html);
+
+        // Signed-in viewers may see members-only data on public routes
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            RouteClass::of_request(&Method::GET, "/api/public/navigation", &headers),
+            RouteClass::PublicApi
+        );
+        headers.insert(
+            axum::http::header::COOKIE,
+            HeaderValue::from_str(&format!("{}=token", auth::AUTH_COOKIE_NAME)).unwrap(),
+        );
+        assert_eq!(
+            RouteClass::of_request(&Method::GET, "/api/public/navigation", &headers),
+            RouteClass::Private
+        );
+        assert_eq!(
+            RouteClass::of_request(&Method::GET, "/uploads/diagram.png", &headers),
+            RouteClass::Immutable
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_class_gets_its_policy() {
+        let app = Router::new()
+            .route("/api/auth/login", post(|| async { "{}" }))
+            .route("/api/public/navigation", get(|| async { "[]" }))
+            .route(
+                "/api/public/bootstrap",
+                get(|| async { ([(CACHE_CONTROL, REVALIDATE)], "{}") }),
+            )
+            .route("/uploads/diagram.png", get(|| async { "png" }))
+            .route("/pages/grundlagen", get(|| async { "<html></html>" }))
+            .layer(axum::middleware::from_fn(cache_control));
+
+        let response = send(&app, Method::POST, "/api/auth/login").await;
+        assert_eq!(cache_control_of(&response), NO_STORE);
+        assert_eq!(response.headers()[PRAGMA], "no-cache");
+        assert_eq!(response.headers()[EXPIRES], "0");
+
+        let response = send(&app, Method::GET, "/api/public/navigation").await;
+        assert_eq!(cache_control_of(&response), PUBLIC_SHORT_CACHE);
+        assert!(!response.headers().contains_key(PRAGMA));
+
+        // The handler's own policy wins
+        let response = send(&app, Method::GET, "/api/public/bootstrap").await;
+        assert_eq!(cache_control_of(&response), REVALIDATE);
+
+        let response = send(&app, Method::GET, "/uploads/diagram.png").await;
+        assert_eq!(cache_control_of(&response), IMMUTABLE_CACHE_CONTROL);
+
+        let response = send(&app, Method::GET, "/pages/grundlagen").await;
+        assert_eq!(cache_control_of(&response), "no-cache");
+
+        // Errors are not cached, not even for a public route
+        let response = send(&app, Method::GET, "/uploads/missing.png").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(cache_control_of(&response), NO_STORE);
+    }
+}
@@ -0,0 +1,108 @@
+//! Turns handler panics into JSON 500 responses.
+//!
+//! Without this a panic drops the connection and the client sees a protocol
+//! error. The panic message is logged with the request id, and the client gets
+//! the standard [`ErrorResponse`] body without any internals.
+
+use crate::middleware::request_id;
+use crate::models::ErrorResponse;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::any::Any;
+use tower_http::catch_panic::CatchPanicLayer;
+
+pub type PanicResponder = fn(Box<dyn Any + Send + 'static>) -> Response;
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+fn panic_response(payload: Box<dyn Any + Send + 'static>) -> Response {
+    tracing::error!(
+        request_id = request_id::current().as_deref().unwrap_or("none"),
+        panic = panic_message(payload.as_ref()),
+        "Handler panicked"
+    );
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "Internal server error".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Layered inside the security headers, so panic responses carry them too.
+pub fn catch_panic_layer() -> CatchPanicLayer<PanicResponder> {
+    CatchPanicLayer::custom(panic_response as PanicResponder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::security::security_headers;
+    use axum::{routing::get, Router};
+
+    async fn panicking() -> &'static str {
+        panic!("index out of bounds")
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/api/test/panic", get(panicking))
+            .route("/api/test/ok", get(|| async { "OK" }))
+            .layer(catch_panic_layer())
+            .layer(axum::middleware::from_fn(security_headers))
+            .layer(axum::middleware::from_fn(request_id::request_id))
+    }
+
+    #[test]
+    fn test_panic_message() {
+        assert_eq!(panic_message(&"static"), "static");
+        assert_eq!(panic_message(&"owned".to_string()), "owned");
+        assert_eq!(panic_message(&42), "non-string panic payload");
+    }
+
+    #[tokio::test]
+    async fn test_panics_become_json_500_and_serving_continues() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { axum::serve(listener, app()).await });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{addr}/api/test/panic"))
+            .header("x-request-id", "panic-1")
+            .send()
+            .await
+            .expect("panic response instead of a dropped connection");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+        assert!(response.headers().contains_key("content-security-policy"));
+        assert_eq!(response.headers()["x-request-id"], "panic-1");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "Internal server error", "request_id": "panic-1" })
+        );
+
+        for _ in 0..2 {
+            let response = client
+                .get(format!("http://{addr}/api/test/ok"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.text().await.unwrap(), "OK");
+        }
+
+        server.abort();
+    }
+}
@@ -0,0 +1,121 @@
+//! Gzip and brotli compression of responses.
+//!
+//! Bodies below [`MIN_COMPRESS_SIZE`] and types that are already compressed
+//! (images other than SVG, PDFs and archives) are sent as they are, so
+//! `/uploads` images never pay for a second compression pass. Empty `304`
+//! responses are never compressed either.
+//!
+//! Our ETags are weak, so the compressed and identity representations can
+//! share one tag. Caches still need to know the body depends on
+//! `Accept-Encoding`: the compression layer adds that to `Vary` on what it
+//! compresses, and [`vary_accept_encoding`] adds it to every other response
+//! carrying an ETag, including the `304`s answering it.
+
+use axum::{
+    extract::Request,
+    http::header::{ACCEPT_ENCODING, ETAG, VARY},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+
+/// Smallest body, in bytes, worth compressing.
+pub const MIN_COMPRESS_SIZE: u16 = 1024;
+
+/// Compression for the whole router, layered outside the handlers.
+pub fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(MIN_COMPRESS_SIZE)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::const_new("application/pdf"))
+        .and(NotForContentType::const_new("application/zip"))
+        .and(NotForContentType::const_new("application/gzip"));
+
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(predicate)
+}
+
+/// Adds `Accept-Encoding` to `Vary` on responses with an ETag.
+pub async fn vary_accept_encoding(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    if !headers.contains_key(ETAG) {
+        return response;
+    }
+
+    let listed = headers.get_all(VARY).iter().any(|value| {
+        value.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .any(|name| name.trim().eq_ignore_ascii_case(ACCEPT_ENCODING.as_str()))
+        })
+    });
+    if !listed {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn with_headers(status: StatusCode, headers: &[(&'static str, &'static str)]) -> Response {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        (status, map).into_response()
+    }
+
+    #[tokio::test]
+    async fn test_vary_is_added_once_to_tagged_responses() {
+        let app = Router::new()
+            .route(
+                "/tagged",
+                get(|| async { with_headers(StatusCode::NOT_MODIFIED, &[("etag", "W/\"1\"")]) }),
+            )
+            .route(
+                "/listed",
+                get(|| async {
+                    with_headers(
+                        StatusCode::OK,
+                        &[("etag", "W/\"1\""), ("vary", "Cookie, Accept-Encoding")],
+                    )
+                }),
+            )
+            .route("/untagged", get(|| async { "OK" }))
+            .layer(axum::middleware::from_fn(vary_accept_encoding));
+        let vary = |response: Response| -> Vec<String> {
+            response
+                .headers()
+                .get_all(VARY)
+                .iter()
+                .map(|value| value.to_str().unwrap().to_string())
+                .collect()
+        };
+        let send = |uri: &'static str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(vary(send("/tagged").await.unwrap()), ["accept-encoding"]);
+        assert_eq!(vary(send("/listed").await.unwrap()), ["Cookie, Accept-Encoding"]);
+        assert!(vary(send("/untagged").await.unwrap()).is_empty());
+    }
+}
@@ -1,9 +1,195 @@
-use axum::http::HeaderValue;
+//! Cross-origin access for a frontend on another origin.
+//!
+//! Configured from the environment once at startup:
+//!
+//! - `CORS_ALLOWED_ORIGINS`: comma-separated origins (default: the Vite and
+//!   dev server on localhost); `*` allows any origin
+//! - `CORS_ALLOW_CREDENTIALS` (default true): whether browsers may send the
+//!   session cookie and read credentialed responses
+//! - `CORS_ALLOWED_HEADERS`: request headers a frontend may send, replacing
+//!   the default list; the CSRF header is always allowed
+//! - `CORS_EXPOSED_HEADERS`: response headers a frontend may read,
+//!   replacing the default list
+//! - `CORS_MAX_AGE_SECONDS`: how long browsers may cache a preflight
+//!
+//! Browsers refuse `*` on credentialed requests, so any wildcard combined
+//! with credentials is rejected at startup instead of failing in the browser.
+
+use super::{rate_limit, request_id};
+use crate::security::csrf;
+use axum::http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER},
+    HeaderName, HeaderValue, Method,
+};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer, ExposeHeaders};
 
 // Default CORS origins for development environment
 pub const DEV_DEFAULT_FRONTEND_ORIGINS: &[&str] =
     &["http://localhost:5173", "http://localhost:3000"];
 
+const ALLOWED_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// A list setting that is either explicit or `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsList<T> {
+    Any,
+    List(Vec<T>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsConfig {
+    pub origins: CorsList<HeaderValue>,
+    pub allow_credentials: bool,
+    pub allowed_headers: CorsList<HeaderName>,
+    pub exposed_headers: CorsList<HeaderName>,
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self::parse(|_| None).expect("built-in CORS settings are valid")
+    }
+}
+
+fn default_allowed_headers() -> Vec<HeaderName> {
+    vec![
+        CONTENT_TYPE,
+        AUTHORIZATION,
+        ACCEPT,
+        request_id::REQUEST_ID_HEADER,
+    ]
+}
+
+fn default_exposed_headers() -> Vec<HeaderName> {
+    vec![
+        request_id::REQUEST_ID_HEADER,
+        RETRY_AFTER,
+        rate_limit::RATE_LIMIT_LIMIT_HEADER,
+        rate_limit::RATE_LIMIT_REMAINING_HEADER,
+        rate_limit::RATE_LIMIT_RESET_HEADER,
+    ]
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the settings through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let value = |key: &str| {
+            lookup(key)
+                .map(|raw| raw.trim().to_string())
+                .filter(|raw| !raw.is_empty())
+        };
+        let header_list = |key: &str, default: Vec<HeaderName>| match value(key) {
+            None => Ok(CorsList::List(default)),
+            Some(raw) if raw == "*" => Ok(CorsList::Any),
+            Some(raw) => raw
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    HeaderName::from_bytes(name.as_bytes())
+                        .map_err(|_| format!("{} contains an invalid header name '{}'", key, name))
+                })
+                .collect::<Result<_, _>>()
+                .map(CorsList::List),
+        };
+
+        // Set but empty allows no other origin at all
+        let origins = match lookup("CORS_ALLOWED_ORIGINS") {
+            Some(raw) if raw.trim() == "*" => CorsList::Any,
+            Some(raw) => CorsList::List(parse_allowed_origins(raw.split(','))),
+            None => CorsList::List(parse_allowed_origins(
+                DEV_DEFAULT_FRONTEND_ORIGINS.iter().copied(),
+            )),
+        };
+        let allow_credentials = match value("CORS_ALLOW_CREDENTIALS") {
+            Some(raw) => super::security::parse_bool(&raw).ok_or_else(|| {
+                format!("CORS_ALLOW_CREDENTIALS must be a boolean, got '{}'", raw)
+            })?,
+            None => true,
+        };
+        let allowed_headers = match header_list("CORS_ALLOWED_HEADERS", default_allowed_headers())?
+        {
+            CorsList::Any => CorsList::Any,
+            CorsList::List(mut names) => {
+                // The SPA sends it on every state-changing request
+                let csrf_header = HeaderName::from_static(csrf::csrf_header_name());
+                if !names.contains(&csrf_header) {
+                    names.push(csrf_header);
+                }
+                CorsList::List(names)
+            }
+        };
+        let exposed_headers = header_list("CORS_EXPOSED_HEADERS", default_exposed_headers())?;
+        let max_age = match value("CORS_MAX_AGE_SECONDS") {
+            Some(raw) => Some(Duration::from_secs(raw.parse::<u64>().map_err(|_| {
+                format!(
+                    "CORS_MAX_AGE_SECONDS must be a number of seconds, got '{}'",
+                    raw
+                )
+            })?)),
+            None => None,
+        };
+
+        if allow_credentials {
+            for (key, wildcard) in [
+                ("CORS_ALLOWED_ORIGINS", origins == CorsList::Any),
+                ("CORS_ALLOWED_HEADERS", allowed_headers == CorsList::Any),
+                ("CORS_EXPOSED_HEADERS", exposed_headers == CorsList::Any),
+            ] {
+                if wildcard {
+                    return Err(format!(
+                        "{} cannot be '*' while CORS_ALLOW_CREDENTIALS is true",
+                        key
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            origins,
+            allow_credentials,
+            allowed_headers,
+            exposed_headers,
+            max_age,
+        })
+    }
+}
+
+/// The CORS layer for `config`.
+pub fn build_layer(config: &CorsConfig) -> CorsLayer {
+    let layer = CorsLayer::new()
+        .allow_methods(ALLOWED_METHODS)
+        .allow_origin(match &config.origins {
+            CorsList::Any => AllowOrigin::any(),
+            CorsList::List(origins) => AllowOrigin::list(origins.iter().cloned()),
+        })
+        .allow_headers(match &config.allowed_headers {
+            CorsList::Any => AllowHeaders::any(),
+            CorsList::List(names) => AllowHeaders::list(names.iter().cloned()),
+        })
+        .expose_headers(match &config.exposed_headers {
+            CorsList::Any => ExposeHeaders::any(),
+            CorsList::List(names) => ExposeHeaders::list(names.iter().cloned()),
+        })
+        .allow_credentials(config.allow_credentials);
+    match config.max_age {
+        Some(max_age) => layer.max_age(max_age),
+        None => layer,
+    }
+}
+
 /// Parses and validates a list of allowed CORS origins.
 pub fn parse_allowed_origins<'a, I>(origins: I) -> Vec<HeaderValue>
 where
@@ -44,3 +230,170 @@ where
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header, StatusCode},
+        response::Response,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<CorsConfig, String> {
+        CorsConfig::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    async fn preflight(config: &CorsConfig, origin: &str, headers: &str) -> Response {
+        let app = Router::new()
+            .route("/api/content/hero", get(|| async { "{}" }))
+            .layer(build_layer(config));
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/content/hero")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, headers)
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    fn header_value(response: &Response, name: HeaderName) -> Option<&str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_default_preflight() {
+        let config = CorsConfig::default();
+        let response = preflight(
+            &config,
+            "http://localhost:5173",
+            "content-type,x-csrf-token",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("http://localhost:5173")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            Some("true")
+        );
+        let allowed = header_value(&response, header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap();
+        assert!(allowed.contains("x-csrf-token"), "{allowed}");
+        assert!(allowed.contains("authorization"), "{allowed}");
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_MAX_AGE),
+            None
+        );
+
+        let response = preflight(&config, "https://evil.example", "content-type").await;
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_headers_and_max_age() {
+        let config = parse(&[
+            (
+                "CORS_ALLOWED_ORIGINS",
+                "https://admin.example.com, not-a-url",
+            ),
+            ("CORS_ALLOWED_HEADERS", "Content-Type, X-Admin-Client"),
+            ("CORS_EXPOSED_HEADERS", "x-request-id,etag"),
+            ("CORS_MAX_AGE_SECONDS", "600"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config.origins,
+            CorsList::List(vec![HeaderValue::from_static("https://admin.example.com")])
+        );
+
+        let response = preflight(&config, "https://admin.example.com", "x-admin-client").await;
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_HEADERS),
+            Some("content-type,x-admin-client,x-csrf-token")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_MAX_AGE),
+            Some("600")
+        );
+
+        let app = Router::new()
+            .route("/api/content/hero", get(|| async { "{}" }))
+            .layer(build_layer(&config));
+        let request = Request::builder()
+            .uri("/api/content/hero")
+            .header(header::ORIGIN, "https://admin.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_EXPOSE_HEADERS),
+            Some("x-request-id,etag")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wildcards_need_credentials_off() {
+        for key in [
+            "CORS_ALLOWED_ORIGINS",
+            "CORS_ALLOWED_HEADERS",
+            "CORS_EXPOSED_HEADERS",
+        ] {
+            assert_eq!(
+                parse(&[(key, "*")]),
+                Err(format!(
+                    "{} cannot be '*' while CORS_ALLOW_CREDENTIALS is true",
+                    key
+                ))
+            );
+        }
+
+        let config = parse(&[
+            ("CORS_ALLOWED_ORIGINS", "*"),
+            ("CORS_ALLOWED_HEADERS", "*"),
+            ("CORS_ALLOW_CREDENTIALS", "false"),
+        ])
+        .unwrap();
+        let response = preflight(&config, "https://anywhere.example", "x-custom").await;
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("*")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_HEADERS),
+            Some("*")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        assert!(parse(&[("CORS_ALLOWED_HEADERS", "x-ok, bad header")]).is_err());
+        assert!(parse(&[("CORS_MAX_AGE_SECONDS", "ten")]).is_err());
+        assert!(parse(&[("CORS_ALLOW_CREDENTIALS", "maybe")]).is_err());
+        assert_eq!(
+            parse(&[("CORS_ALLOWED_ORIGINS", "")]).unwrap().origins,
+            CorsList::List(Vec::new())
+        );
+    }
+}
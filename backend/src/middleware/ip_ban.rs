@@ -0,0 +1,270 @@
+//! Runtime IP ban list.
+//!
+//! Rate limiting only slows abusive scrapers down; addresses and CIDR ranges
+//! in the `ip_bans` table are refused with a 403 before any routing. The
+//! client address is the one
+//! [`resolve_client_ip`](super::security::resolve_client_ip) settled on, so
+//! bans apply to the real client behind a trusted proxy.
+//!
+//! The active bans are kept in a process-wide cache refreshed every
+//! [`CACHE_TTL`]; the admin endpoints call [`invalidate`] so changes apply
+//! immediately. Expired rows are deleted by an hourly background task.
+
+use super::security::{ClientIp, IpRange};
+use crate::db::DbPool;
+use crate::models::{ErrorResponse, IpBan};
+use crate::repositories;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+pub const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Ban {
+    range: IpRange,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl Ban {
+    fn from_row(row: &IpBan) -> Option<Self> {
+        let range = match row.ip.parse::<IpRange>() {
+            Ok(range) => range,
+            Err(err) => {
+                tracing::warn!(id = row.id, "Ignoring unreadable IP ban: {}", err);
+                return None;
+            }
+        };
+        // An unreadable expiry keeps the ban in force rather than lifting it
+        let expires_at = row.expires_at.as_deref().and_then(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|at| at.with_timezone(&Utc))
+                .inspect_err(|_| {
+                    tracing::warn!(id = row.id, "Unreadable IP ban expiry '{}'", value)
+                })
+                .ok()
+        });
+        Some(Self { range, expires_at })
+    }
+
+    fn matches(&self, ip: IpAddr, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|at| at > now) && self.range.contains(ip)
+    }
+}
+
+type Cached = Option<(Instant, Arc<Vec<Ban>>)>;
+
+fn cache() -> &'static RwLock<Cached> {
+    static CACHE: OnceLock<RwLock<Cached>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// Active bans, served from the cache while it is fresh. When the database
+/// cannot be read the previous list stays in force.
+async fn bans(pool: &DbPool) -> Arc<Vec<Ban>> {
+    let stale = match cache().read() {
+        Ok(guard) => match guard.as_ref() {
+            Some((loaded_at, bans)) if loaded_at.elapsed() < CACHE_TTL => return bans.clone(),
+            Some((_, bans)) => Some(bans.clone()),
+            None => None,
+        },
+        Err(_) => None,
+    };
+
+    let bans = match repositories::ip_bans::list_active_bans(pool).await {
+        Ok(rows) => Arc::new(rows.iter().filter_map(Ban::from_row).collect::<Vec<_>>()),
+        Err(err) => {
+            tracing::error!("Failed to load IP bans: {}", err);
+            stale.unwrap_or_default()
+        }
+    };
+    if let Ok(mut guard) = cache().write() {
+        *guard = Some((Instant::now(), bans.clone()));
+    }
+    bans
+}
+
+/// Drops the cached bans so the next request reads the database.
+pub fn invalidate() {
+    if let Ok(mut guard) = cache().write() {
+        *guard = None;
+    }
+}
+
+/// Whether `ip` is covered by an active ban.
+pub async fn is_banned(pool: &DbPool, ip: IpAddr) -> bool {
+    let now = Utc::now();
+    bans(pool).await.iter().any(|ban| ban.matches(ip, now))
+}
+
+pub async fn ip_ban(State(pool): State<DbPool>, request: Request, next: Next) -> Response {
+    if let Some(ip) = ClientIp::from_extensions(request.extensions()) {
+        if is_banned(&pool, ip).await {
+            tracing::debug!(client_ip = %ip, "Refused request from banned address");
+            return (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Access denied".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::ip_bans::{create_ip_ban, delete_ip_ban, list_ip_bans};
+    use crate::models::{CreateIpBanRequest, DeleteIpBanQuery};
+    use crate::security::auth;
+    use axum::{
+        body::Body,
+        extract::{ConnectInfo, Query},
+        routing::get,
+        Router,
+    };
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    fn admin_ip() -> ClientIp {
+        ClientIp("192.0.2.1".parse().unwrap())
+    }
+
+    async fn status_for(app: &Router, peer: [u8; 4]) -> StatusCode {
+        let request = Request::builder()
+            .uri("/api/tutorials")
+            .extension(ConnectInfo(SocketAddr::from((peer, 4000))))
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    async fn ban(pool: &DbPool, ip: &str, expires_at: Option<String>) -> Result<IpBan, StatusCode> {
+        create_ip_ban(
+            auth::test_admin_claims(),
+            admin_ip(),
+            State(pool.clone()),
+            Json(CreateIpBanRequest {
+                ip: ip.to_string(),
+                reason: Some("  scraper  ".to_string()),
+                expires_at,
+            }),
+        )
+        .await
+        .map(|(_, Json(ban))| ban)
+        .map_err(|err| err.status())
+    }
+
+    #[test]
+    fn test_expired_bans_do_not_match() {
+        let now = Utc::now();
+        let ban = |expires_at| Ban {
+            range: "203.0.113.0/24".parse().unwrap(),
+            expires_at,
+        };
+        let ip = "203.0.113.9".parse().unwrap();
+        assert!(ban(None).matches(ip, now));
+        assert!(ban(Some(now + chrono::Duration::minutes(5))).matches(ip, now));
+        assert!(!ban(Some(now - chrono::Duration::seconds(1))).matches(ip, now));
+        assert!(!ban(None).matches("203.0.114.9".parse().unwrap(), now));
+    }
+
+    // One test, since the cache is shared by the whole process
+    #[tokio::test]
+    async fn test_bans_block_clients_until_lifted_or_expired() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let app = Router::new()
+            .route("/api/tutorials", get(|| async { "[]" }))
+            .layer(axum::middleware::from_fn_with_state(pool.clone(), ip_ban))
+            .with_state(pool.clone());
+        invalidate();
+
+        assert_eq!(status_for(&app, [203, 0, 113, 9]).await, StatusCode::OK);
+
+        // Round trip through the admin endpoints
+        let created = ban(&pool, "203.0.113.77/24", None).await.unwrap();
+        assert_eq!(created.ip, "203.0.113.0/24");
+        assert_eq!(created.reason.as_deref(), Some("scraper"));
+        assert_eq!(created.created_by.as_deref(), Some("admin"));
+        assert_eq!(
+            ban(&pool, "203.0.113.0/24", None).await,
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(
+            ban(&pool, "not-an-ip", None).await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            ban(&pool, "192.0.2.0/24", None).await,
+            Err(StatusCode::BAD_REQUEST),
+            "admins cannot ban themselves"
+        );
+        let past = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        assert_eq!(
+            ban(&pool, "198.51.100.1", Some(past)).await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+        let Json(listed) = list_ip_bans(auth::test_admin_claims(), State(pool.clone()))
+            .await
+            .unwrap();
+        assert_eq!(listed, vec![created]);
+        assert!(list_ip_bans(auth::test_claims("admin", "editor"), State(pool.clone()))
+            .await
+            .is_err());
+
+        // Banned addresses are refused, everyone else passes
+        assert_eq!(
+            status_for(&app, [203, 0, 113, 9]).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status_for(&app, [198, 51, 100, 1]).await, StatusCode::OK);
+
+        // Expiry is honored without waiting for the cache or the prune task
+        let soon = (Utc::now() + chrono::Duration::milliseconds(1500)).to_rfc3339();
+        ban(&pool, "198.51.100.1", Some(soon)).await.unwrap();
+        assert_eq!(
+            status_for(&app, [198, 51, 100, 1]).await,
+            StatusCode::FORBIDDEN
+        );
+        tokio::time::sleep(Duration::from_millis(1600)).await;
+        assert_eq!(status_for(&app, [198, 51, 100, 1]).await, StatusCode::OK);
+        assert_eq!(
+            repositories::ip_bans::delete_expired_bans(&pool)
+                .await
+                .unwrap(),
+            1
+        );
+
+        // Lifting a ban applies immediately
+        let status = delete_ip_ban(
+            auth::test_admin_claims(),
+            State(pool.clone()),
+            Query(DeleteIpBanQuery {
+                ip: "203.0.113.0/24".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(status_for(&app, [203, 0, 113, 9]).await, StatusCode::OK);
+        let missing = delete_ip_ban(
+            auth::test_admin_claims(),
+            State(pool.clone()),
+            Query(DeleteIpBanQuery {
+                ip: "203.0.113.0/24".to_string(),
+            }),
+        )
+        .await;
+        assert_eq!(missing.unwrap_err().status(), StatusCode::NOT_FOUND);
+    }
+}
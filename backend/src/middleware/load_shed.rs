@@ -0,0 +1,335 @@
+//! Concurrency limits with load shedding.
+//!
+//! SQLite takes one writer at a time and the pool holds a handful of
+//! connections, so a burst of requests beyond that only queues up until the
+//! pool's acquire timeout. Instead, each [`LoadBudget`] admits a fixed number
+//! of requests at once and answers the surplus right away with a `503`,
+//! `Retry-After` and the usual JSON error body.
+//!
+//! Every route but the health probes shares the `requests` budget
+//! (`MAX_CONCURRENT_REQUESTS`, default 64). Admin writes additionally draw
+//! from the smaller `admin_writes` budget (`MAX_CONCURRENT_ADMIN_WRITES`,
+//! default 4), so they cannot crowd out the public reads. How many requests
+//! each budget shed is listed at `/api/admin/metrics`.
+
+use crate::models::ErrorResponse;
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    BoxError, Json, Router,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tower::{limit::GlobalConcurrencyLimitLayer, ServiceBuilder};
+
+/// Seconds a shed client is asked to wait; the surplus is usually gone by then.
+const RETRY_AFTER_SECONDS: u32 = 1;
+
+/// Sizes of the budgets, read from the environment once at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    pub requests: usize,
+    pub admin_writes: usize,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            requests: 64,
+            admin_writes: 4,
+        }
+    }
+}
+
+impl ConcurrencyLimits {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the limits through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let count = |key: &str, default: usize| -> Result<usize, String> {
+            let Some(raw) = lookup(key).filter(|raw| !raw.trim().is_empty()) else {
+                return Ok(default);
+            };
+            match raw.trim().parse::<usize>() {
+                Ok(value) if value > 0 => Ok(value),
+                _ => Err(format!("{} must be a positive integer, got '{}'", key, raw)),
+            }
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
+            requests: count("MAX_CONCURRENT_REQUESTS", defaults.requests)?,
+            admin_writes: count("MAX_CONCURRENT_ADMIN_WRITES", defaults.admin_writes)?,
+        })
+    }
+}
+
+/// A number of requests allowed in flight at once. Clones share the budget.
+#[derive(Debug, Clone)]
+pub struct LoadBudget {
+    name: &'static str,
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    shed: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoadBudgetStats {
+    pub name: &'static str,
+    pub limit: usize,
+    pub in_flight: usize,
+    /// Requests turned away since startup.
+    pub shed: u64,
+}
+
+impl LoadBudget {
+    pub fn new(name: &'static str, limit: usize) -> Self {
+        Self {
+            name,
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            shed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn stats(&self) -> LoadBudgetStats {
+        LoadBudgetStats {
+            name: self.name,
+            limit: self.limit,
+            in_flight: self.limit - self.semaphore.available_permits(),
+            shed: self.shed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn shed(&self) -> Response {
+        let shed = self.shed.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::warn!(
+            budget = self.name,
+            shed,
+            "Shedding request over the concurrency limit"
+        );
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Server is busy; please retry shortly".to_string(),
+            }),
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECONDS));
+        response
+    }
+}
+
+/// The budgets of one application, shared by the routers built from it.
+#[derive(Debug, Clone)]
+pub struct LoadBudgets {
+    pub requests: LoadBudget,
+    pub admin_writes: LoadBudget,
+}
+
+impl LoadBudgets {
+    pub fn new(limits: ConcurrencyLimits) -> Self {
+        Self {
+            requests: LoadBudget::new("requests", limits.requests),
+            admin_writes: LoadBudget::new("admin_writes", limits.admin_writes),
+        }
+    }
+
+    pub fn stats(&self) -> Vec<LoadBudgetStats> {
+        vec![self.requests.stats(), self.admin_writes.stats()]
+    }
+}
+
+impl Default for LoadBudgets {
+    fn default() -> Self {
+        Self::new(ConcurrencyLimits::default())
+    }
+}
+
+/// Sheds requests to `router` while `budget` is used up.
+pub fn with_load_shedding<S>(router: Router<S>, budget: &LoadBudget) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let on_overload = {
+        let budget = budget.clone();
+        move |err: BoxError| {
+            let budget = budget.clone();
+            async move {
+                if err.is::<tower::load_shed::error::Overloaded>() {
+                    budget.shed()
+                } else {
+                    tracing::error!("Request failed: {}", err);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse {
+                            error: "Internal server error".to_string(),
+                        }),
+                    )
+                        .into_response()
+                }
+            }
+        }
+    };
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(on_overload))
+            .load_shed()
+            .layer(GlobalConcurrencyLimitLayer::with_semaphore(
+                budget.semaphore.clone(),
+            )),
+    )
+}
+
+/// Sheds writes while `budget` is used up; reads pass through.
+pub async fn limit_writes(
+    State(budget): State<LoadBudget>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method().is_safe() {
+        return next.run(request).await;
+    }
+    let Ok(_permit) = budget.semaphore.clone().try_acquire_owned() else {
+        return budget.shed();
+    };
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Method, routing::get};
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<ConcurrencyLimits, String> {
+        ConcurrencyLimits::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        "done"
+    }
+
+    async fn send(app: &Router, method: Method, uri: &str) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    /// Waits until `budget` has `count` requests in flight.
+    async fn saturated(budget: &LoadBudget, count: usize) {
+        for _ in 0..100 {
+            if budget.stats().in_flight == count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("budget never reached {count} requests in flight");
+    }
+
+    #[test]
+    fn test_env_overrides_and_defaults() {
+        assert_eq!(parse(&[]).unwrap(), ConcurrencyLimits::default());
+        assert_eq!(
+            parse(&[
+                ("MAX_CONCURRENT_REQUESTS", " 8 "),
+                ("MAX_CONCURRENT_ADMIN_WRITES", "1")
+            ]),
+            Ok(ConcurrencyLimits {
+                requests: 8,
+                admin_writes: 1,
+            })
+        );
+        assert_eq!(
+            parse(&[("MAX_CONCURRENT_ADMIN_WRITES", "0")]),
+            Err("MAX_CONCURRENT_ADMIN_WRITES must be a positive integer, got '0'".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_surplus_requests_are_shed_quickly() {
+        let budget = LoadBudget::new("requests", 2);
+        let app = with_load_shedding(Router::new().route("/slow", get(slow)), &budget);
+
+        let in_flight: Vec<_> = (0..2)
+            .map(|_| {
+                let app = app.clone();
+                tokio::spawn(async move { send(&app, Method::GET, "/slow").await.status() })
+            })
+            .collect();
+        saturated(&budget, 2).await;
+
+        for _ in 0..5 {
+            let started = Instant::now();
+            let response = send(&app, Method::GET, "/slow").await;
+            assert!(started.elapsed() < Duration::from_millis(100));
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(response.headers()[RETRY_AFTER], "1");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["error"], "Server is busy; please retry shortly");
+        }
+        for request in in_flight {
+            assert_eq!(request.await.unwrap(), StatusCode::OK);
+        }
+
+        // Capacity is back once the slow requests are done
+        assert_eq!(
+            budget.stats(),
+            LoadBudgetStats {
+                name: "requests",
+                limit: 2,
+                in_flight: 0,
+                shed: 5,
+            }
+        );
+        assert_eq!(
+            send(&app, Method::GET, "/slow").await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_writes_have_their_own_budget() {
+        let budget = LoadBudget::new("admin_writes", 1);
+        let app = Router::new().route("/slow", get(slow).post(slow)).layer(
+            axum::middleware::from_fn_with_state(budget.clone(), limit_writes),
+        );
+
+        let write = tokio::spawn({
+            let app = app.clone();
+            async move { send(&app, Method::POST, "/slow").await.status() }
+        });
+        saturated(&budget, 1).await;
+
+        let response = send(&app, Method::POST, "/slow").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        // Reads do not draw from the write budget
+        assert_eq!(
+            send(&app, Method::GET, "/slow").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(write.await.unwrap(), StatusCode::OK);
+        assert_eq!(budget.stats().shed, 1);
+    }
+}
@@ -0,0 +1,319 @@
+//! Maintenance mode.
+//!
+//! While the `maintenance_mode` flag in `app_metadata` is set, public
+//! requests get a 503 with `Retry-After`: API clients the usual JSON error,
+//! browsers a minimal static page. Sign-in (`/api/auth/*` and the `/login`
+//! page, along with the frontend assets it loads), the `/api/health` probes
+//! and requests carrying an admin token pass through, so admins keep working
+//! during a migration.
+//!
+//! The flag is read through a process-wide cache with a short TTL; toggling
+//! it calls [`invalidate`] so the change applies immediately.
+
+use crate::db::DbPool;
+use crate::handlers::frontend_proxy;
+use crate::models::{ErrorResponse, MaintenanceStatus};
+use crate::{repositories, security::auth, settings};
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+pub const MAINTENANCE_MODE_KEY: &str = "maintenance_mode";
+pub const MAINTENANCE_MESSAGE_KEY: &str = "maintenance_message";
+
+const CACHE_TTL: Duration = Duration::from_secs(5);
+/// Seconds clients are asked to wait before trying again.
+const RETRY_AFTER_SECONDS: u32 = 120;
+/// Shown when neither the toggle nor the settings section set a message.
+const DEFAULT_MESSAGE: &str = "Die Seite wird gerade gewartet. Bitte versuche es in Kürze erneut.";
+
+type Cached = Option<(Instant, MaintenanceStatus)>;
+
+fn cache() -> &'static RwLock<Cached> {
+    static CACHE: OnceLock<RwLock<Cached>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// Current status, served from the cache while it is fresh. A database error
+/// counts as "off" so an outage does not lock admins out of the toggle.
+pub async fn status(pool: &DbPool) -> MaintenanceStatus {
+    if let Ok(guard) = cache().read() {
+        if let Some((loaded_at, status)) = guard.as_ref() {
+            if loaded_at.elapsed() < CACHE_TTL {
+                return status.clone();
+            }
+        }
+    }
+
+    let status = load(pool).await.unwrap_or_else(|err| {
+        tracing::error!("Failed to load maintenance mode: {}", err);
+        MaintenanceStatus::default()
+    });
+    if let Ok(mut guard) = cache().write() {
+        *guard = Some((Instant::now(), status.clone()));
+    }
+    status
+}
+
+/// Drops the cached status so the next [`status`] reads the database.
+pub fn invalidate() {
+    if let Ok(mut guard) = cache().write() {
+        *guard = None;
+    }
+}
+
+/// Reads the status, bypassing the cache.
+pub async fn load(pool: &DbPool) -> Result<MaintenanceStatus, sqlx::Error> {
+    let enabled = repositories::app_metadata::get_metadata(pool, MAINTENANCE_MODE_KEY).await?;
+    let message = repositories::app_metadata::get_metadata(pool, MAINTENANCE_MESSAGE_KEY).await?;
+    Ok(MaintenanceStatus {
+        enabled: enabled.as_deref() == Some("true"),
+        message: message.filter(|message| !message.is_empty()),
+    })
+}
+
+/// Stores the status and invalidates the cache.
+pub async fn set(pool: &DbPool, status: &MaintenanceStatus) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    repositories::app_metadata::set_metadata(
+        &mut *tx,
+        MAINTENANCE_MODE_KEY,
+        if status.enabled { "true" } else { "false" },
+    )
+    .await?;
+    repositories::app_metadata::set_metadata(
+        &mut *tx,
+        MAINTENANCE_MESSAGE_KEY,
+        status.message.as_deref().unwrap_or(""),
+    )
+    .await?;
+    tx.commit().await?;
+    invalidate();
+    Ok(())
+}
+
+/// Paths that stay reachable for everyone, so admins can still sign in.
+/// The login page is the SPA, so its scripts and styles are exempt as well;
+/// uploads are not, they are content.
+fn is_exempt(path: &str) -> bool {
+    path.starts_with("/api/auth/")
+        || path == "/api/health"
+        || path.starts_with("/api/health/")
+        || path == "/login"
+        || (!path.starts_with("/api/")
+            && !path.starts_with("/uploads/")
+            && frontend_proxy::is_asset(path))
+}
+
+async fn is_admin(pool: &DbPool, headers: &HeaderMap) -> bool {
+    let Some(token) = auth::extract_token(headers) else {
+        return false;
+    };
+    let Ok(claims) = auth::verify_jwt(&token) else {
+        return false;
+    };
+    claims.role == "admin"
+        && matches!(
+            auth::is_token_revoked(pool, &token, &claims).await,
+            Ok(false)
+        )
+}
+
+fn maintenance_page(message: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"de\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n<title>Wartungsarbeiten</title>\n\
+         <style>body{{font-family:system-ui,sans-serif;display:flex;min-height:100vh;\
+         margin:0;align-items:center;justify-content:center;background:#f8fafc;color:#0f172a}}\
+         main{{max-width:32rem;padding:2rem;text-align:center}}</style>\n</head>\n\
+         <body>\n<main>\n<h1>Wartungsarbeiten</h1>\n<p>{}</p>\n</main>\n</body>\n</html>\n",
+        html_escape::encode_text(message)
+    )
+}
+
+/// Middleware answering public requests with 503 while maintenance is on.
+pub async fn maintenance_mode(
+    State(pool): State<DbPool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if is_exempt(path) {
+        return next.run(request).await;
+    }
+    let status = status(&pool).await;
+    if !status.enabled || is_admin(&pool, request.headers()).await {
+        return next.run(request).await;
+    }
+
+    let message = match status.message {
+        Some(message) => message,
+        None => settings::get(&pool)
+            .await
+            .maintenance_message
+            .filter(|message| !message.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+    };
+    let is_api = path.starts_with("/api/") || path == "/api" || path.starts_with("/uploads/");
+    let mut response = if is_api {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse { error: message }),
+        )
+            .into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Html(maintenance_page(&message)),
+        )
+            .into_response()
+    };
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECONDS));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::maintenance::{get_maintenance, update_maintenance};
+    use crate::models::UpdateMaintenanceRequest;
+    use axum::{body::Body, http::header::AUTHORIZATION, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(pool: &DbPool) -> Router {
+        Router::new()
+            .route("/api/tutorials", get(|| async { "[]" }))
+            .route("/api/health", get(|| async { "OK" }))
+            .route("/api/auth/me", get(|| async { "me" }))
+            .route("/login", get(|| async { Html("<div id=root></div>") }))
+            .route("/assets/index-3f2a.js", get(|| async { "render()" }))
+            .route("/uploads/diagram.png", get(|| async { "png" }))
+            .route(
+                "/tutorials/bash",
+                get(|| async { Html("<div id=root></div>") }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                pool.clone(),
+                maintenance_mode,
+            ))
+            .with_state(pool.clone())
+    }
+
+    async fn send(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, HeaderMap, String) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (
+            parts.status,
+            parts.headers,
+            String::from_utf8_lossy(&body).into_owned(),
+        )
+    }
+
+    async fn toggle(pool: &DbPool, enabled: bool, message: Option<&str>) -> MaintenanceStatus {
+        let Json(status) = update_maintenance(
+            auth::test_admin_claims(),
+            State(pool.clone()),
+            Json(UpdateMaintenanceRequest {
+                enabled,
+                message: message.map(str::to_string),
+            }),
+        )
+        .await
+        .expect("toggle maintenance");
+        status
+    }
+
+    // One test, since the cache is shared by the whole process
+    #[tokio::test]
+    async fn test_toggle_blocks_public_routes_and_lets_admins_through() {
+        auth::JWT_SECRET
+            .get_or_init(|| "maintenance-test-secret-0123456789-ABCDEFGH-xyz".to_string());
+        let pool = crate::db::pool::create_test_pool().await;
+        let app = app(&pool);
+        let admin_token = auth::create_jwt("admin".to_string(), "admin".to_string(), 0).unwrap();
+        let editor_token = auth::create_jwt("editor".to_string(), "editor".to_string(), 0).unwrap();
+
+        let (status, _, _) = send(&app, "/api/tutorials", None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Round trip through the admin endpoints
+        let stored = toggle(&pool, true, Some("  Datenbank-Migration bis 14 Uhr  ")).await;
+        assert_eq!(
+            stored.message.as_deref(),
+            Some("Datenbank-Migration bis 14 Uhr")
+        );
+        let Json(loaded) = get_maintenance(auth::test_admin_claims(), State(pool.clone()))
+            .await
+            .unwrap();
+        assert_eq!(loaded, stored);
+        assert!(get_maintenance(auth::test_claims("admin", "editor"), State(pool.clone()))
+            .await
+            .is_err());
+
+        // Public API and pages are blocked
+        let (status, headers, body) = send(&app, "/api/tutorials", None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(headers[RETRY_AFTER], "120");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"], "Datenbank-Migration bis 14 Uhr");
+        let (status, headers, body) = send(&app, "/tutorials/bash", Some(&editor_token)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(headers["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        assert!(body.contains("Datenbank-Migration bis 14 Uhr"));
+
+        // Sign-in, the assets the login page loads, health and admins pass
+        for uri in [
+            "/api/auth/me",
+            "/api/health",
+            "/login",
+            "/assets/index-3f2a.js",
+        ] {
+            let (status, _, _) = send(&app, uri, None).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+        }
+        let (status, _, _) = send(&app, "/uploads/diagram.png", None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let (status, _, body) = send(&app, "/api/tutorials", Some(&admin_token)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "[]"));
+        let (status, _, _) = send(&app, "/api/tutorials", Some("not-a-token")).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        // Without a message the default text is shown
+        toggle(&pool, true, None).await;
+        let (_, _, body) = send(&app, "/tutorials/bash", None).await;
+        assert!(body.contains(DEFAULT_MESSAGE));
+
+        toggle(&pool, false, None).await;
+        let (status, _, _) = send(&app, "/api/tutorials", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!load(&pool).await.unwrap().enabled);
+    }
+
+    #[test]
+    fn test_page_escapes_the_message() {
+        let page = maintenance_page("<script>alert(1)</script>");
+        assert!(!page.contains("<script>"));
+        assert!(page.contains("&lt;script&gt;"));
+    }
+}
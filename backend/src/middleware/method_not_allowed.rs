@@ -0,0 +1,96 @@
+//! JSON bodies for `405 Method Not Allowed`.
+//!
+//! The router answers a known path with an unsupported method with an empty
+//! 405 and an `Allow` header. This middleware replaces the empty body with
+//! the usual [`ErrorResponse`], naming the allowed methods. It has to wrap
+//! the router as a whole, since the router adds `Allow` after every
+//! per-route layer has run.
+
+use crate::models::ErrorResponse;
+use axum::{
+    extract::Request,
+    http::{
+        header::{ALLOW, CONTENT_LENGTH},
+        StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+pub async fn method_not_allowed(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allowed = response
+        .headers()
+        .get(ALLOW)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|method| !method.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+    let error = if allowed.is_empty() {
+        "Method not allowed".to_string()
+    } else {
+        format!("Method not allowed; allowed methods: {}", allowed)
+    };
+
+    // Keep the headers earlier layers added, such as the security headers
+    let (mut parts, _) = response.into_parts();
+    let (json_parts, body) = Json(ErrorResponse { error }).into_response().into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.extend(json_parts.headers);
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::security::security_headers;
+    use axum::{body::Body, routing::get, Router};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn test_body_lists_allowed_methods_and_keeps_headers() {
+        let router = Router::new()
+            .route("/api/tutorials", get(|| async { "[]" }).post(|| async { "{}" }))
+            .layer(axum::middleware::from_fn(security_headers));
+        let app = ServiceBuilder::new()
+            .layer(axum::middleware::from_fn(method_not_allowed))
+            .service(router);
+
+        let request = Request::builder()
+            .method("PATCH")
+            .uri("/api/tutorials")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[ALLOW], "GET,HEAD,POST");
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"],
+            "Method not allowed; allowed methods: GET, HEAD, POST"
+        );
+
+        let request = Request::builder()
+            .uri("/api/tutorials")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -1,3 +1,18 @@
+pub mod access_log;
+pub mod audit;
 pub mod auth;
+pub mod body_limit;
+pub mod bot;
+pub mod cache_control;
+pub mod catch_panic;
+pub mod compression;
 pub mod cors;
+pub mod ip_ban;
+pub mod load_shed;
+pub mod maintenance;
+pub mod method_not_allowed;
+pub mod rate_limit;
+pub mod request_id;
 pub mod security;
+pub mod timeout;
+pub mod uploads;
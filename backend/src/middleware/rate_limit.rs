@@ -0,0 +1,223 @@
+//! Per-IP rate limits for the login, admin and public API routes, and for
+//! bots on the public routes (see [`bot`](super::bot)).
+//!
+//! Each limiter reads `RATE_LIMIT_<NAME>_PER_SECOND` (requests replenished
+//! per second) and `RATE_LIMIT_<NAME>_BURST` (requests allowed at once),
+//! with `<NAME>` one of `LOGIN`, `ADMIN`, `PUBLIC` or `BOT`.
+//!
+//! Responses carry `X-RateLimit-Limit` and `X-RateLimit-Remaining`; a 429
+//! adds `Retry-After` and `X-RateLimit-Reset`, both in seconds.
+//!
+//! Clients are keyed by the address
+//! [`resolve_client_ip`](super::security::resolve_client_ip) settled on, so
+//! forwarded headers only count when they came through a trusted proxy.
+
+use super::security::ClientIp;
+use axum::{
+    extract::Request,
+    http::HeaderName,
+    middleware::Next,
+    response::Response,
+};
+use governor::middleware::StateInformationMiddleware;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_governor::{
+    governor::{GovernorConfig, GovernorConfigBuilder},
+    key_extractor::KeyExtractor,
+    GovernorError,
+};
+
+pub type RateLimitConfig = Arc<GovernorConfig<ClientIpKeyExtractor, StateInformationMiddleware>>;
+
+pub const RATE_LIMIT_LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+pub const RATE_LIMIT_REMAINING_HEADER: HeaderName =
+    HeaderName::from_static("x-ratelimit-remaining");
+pub const RATE_LIMIT_RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+/// Seconds until the next request is allowed, set by `tower_governor` on 429.
+const RATE_LIMIT_AFTER_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-after");
+
+/// Keys requests by their [`ClientIp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIpKeyExtractor;
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+
+    fn extract<T>(&self, req: &axum::http::Request<T>) -> Result<Self::Key, GovernorError> {
+        ClientIp::from_extensions(req.extensions()).ok_or(GovernorError::UnableToExtractKey)
+    }
+}
+
+/// Refill rate and burst size of one limiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    fn config(&self) -> RateLimitConfig {
+        Arc::new(
+            GovernorConfigBuilder::default()
+                .period(Duration::from_secs(1) / self.per_second)
+                .burst_size(self.burst)
+                .key_extractor(ClientIpKeyExtractor)
+                .use_headers()
+                .finish()
+                .expect("rate limits are validated to be positive"),
+        )
+    }
+}
+
+/// Settings of all limiters, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimits {
+    pub login: RateLimit,
+    pub admin: RateLimit,
+    pub public: RateLimit,
+    pub bot: RateLimit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            login: RateLimit { per_second: 1, burst: 5 },
+            admin: RateLimit { per_second: 1, burst: 3 },
+            // A page view fetches several public endpoints at once
+            public: RateLimit { per_second: 10, burst: 30 },
+            // Enough for a polite crawler, not for a scraper
+            bot: RateLimit { per_second: 1, burst: 5 },
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the limits through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let positive = |key: String, default: u32| -> Result<u32, String> {
+            let Some(raw) = lookup(&key).filter(|raw| !raw.trim().is_empty()) else {
+                return Ok(default);
+            };
+            match raw.trim().parse::<u32>() {
+                Ok(value) if value > 0 => Ok(value),
+                _ => Err(format!("{} must be a positive integer, got '{}'", key, raw)),
+            }
+        };
+        let limit = |name: &str, default: RateLimit| -> Result<RateLimit, String> {
+            Ok(RateLimit {
+                per_second: positive(format!("RATE_LIMIT_{name}_PER_SECOND"), default.per_second)?,
+                burst: positive(format!("RATE_LIMIT_{name}_BURST"), default.burst)?,
+            })
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
+            login: limit("LOGIN", defaults.login)?,
+            admin: limit("ADMIN", defaults.admin)?,
+            public: limit("PUBLIC", defaults.public)?,
+            bot: limit("BOT", defaults.bot)?,
+        })
+    }
+
+    pub fn login_config(&self) -> RateLimitConfig {
+        self.login.config()
+    }
+
+    pub fn admin_config(&self) -> RateLimitConfig {
+        self.admin.config()
+    }
+
+    pub fn public_config(&self) -> RateLimitConfig {
+        self.public.config()
+    }
+}
+
+/// Adds `X-RateLimit-Reset` to rate limited responses.
+pub async fn rate_limit_reset_header(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    if let Some(after) = response.headers().get(RATE_LIMIT_AFTER_HEADER).cloned() {
+        response.headers_mut().insert(RATE_LIMIT_RESET_HEADER, after);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header, StatusCode},
+        routing::get,
+        Router,
+    };
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+    use tower_governor::GovernorLayer;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<RateLimits, String> {
+        RateLimits::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_env_overrides_and_defaults() {
+        let defaults = parse(&[]).unwrap();
+        assert_eq!(defaults, RateLimits::default());
+        assert_eq!(defaults.login, RateLimit { per_second: 1, burst: 5 });
+        assert_eq!(defaults.admin, RateLimit { per_second: 1, burst: 3 });
+
+        let limits = parse(&[
+            ("RATE_LIMIT_LOGIN_BURST", "10"),
+            ("RATE_LIMIT_ADMIN_PER_SECOND", " 4 "),
+            ("RATE_LIMIT_PUBLIC_BURST", ""),
+            ("RATE_LIMIT_BOT_PER_SECOND", "2"),
+        ])
+        .unwrap();
+        assert_eq!(limits.login, RateLimit { per_second: 1, burst: 10 });
+        assert_eq!(limits.admin, RateLimit { per_second: 4, burst: 3 });
+        assert_eq!(limits.public, RateLimits::default().public);
+        assert_eq!(limits.bot, RateLimit { per_second: 2, burst: 5 });
+
+        assert!(parse(&[("RATE_LIMIT_LOGIN_PER_SECOND", "0")]).is_err());
+        assert!(parse(&[("RATE_LIMIT_PUBLIC_BURST", "lots")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_response_headers() {
+        let limit = RateLimit { per_second: 1, burst: 2 };
+        let app = Router::new()
+            .route("/api/tutorials", get(|| async { "OK" }))
+            .layer(GovernorLayer::new(limit.config()))
+            .layer(axum::middleware::from_fn(rate_limit_reset_header));
+        let request = || {
+            Request::builder()
+                .uri("/api/tutorials")
+                .extension(ConnectInfo(SocketAddr::from(([192, 0, 2, 9], 5000))))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "2");
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "1");
+
+        app.clone().oneshot(request()).await.unwrap();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = &response.headers()[header::RETRY_AFTER];
+        assert!(retry_after.to_str().unwrap().parse::<u64>().is_ok());
+        assert_eq!(&response.headers()[RATE_LIMIT_RESET_HEADER], retry_after);
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "0");
+    }
+}
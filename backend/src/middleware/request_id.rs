@@ -0,0 +1,137 @@
+//! Request ids for correlating client reports with server logs.
+//!
+//! Every request gets an id, taken from a well-formed `X-Request-Id` header
+//! or generated. It is stored in the request extensions, recorded on the
+//! request's tracing span, echoed in the response and added to every
+//! [`ErrorResponse`](crate::models::ErrorResponse) body.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest client-supplied id accepted; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// Id of the request being handled, available as a request extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Id of the request the current task is handling, if any.
+pub fn current() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.0.clone()).ok()
+}
+
+/// Accepts client ids made of characters that are safe to log verbatim.
+fn client_request_id(request: &Request) -> Option<String> {
+    let value = request.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b':'));
+    valid.then(|| value.to_string())
+}
+
+/// Middleware assigning the request id. Layered outermost, so logs and
+/// errors of every other layer carry the id.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = client_request_id(&request).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let mut response = CURRENT_REQUEST_ID
+        .scope(RequestId(id.clone()), next.run(request))
+        .instrument(span)
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorResponse;
+    use axum::{body::Body, http::StatusCode, routing::get, Json, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "OK" }))
+            .route(
+                "/fail",
+                get(|| async {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse {
+                            error: "Failed to save tutorial".to_string(),
+                        }),
+                    )
+                }),
+            )
+            .layer(axum::middleware::from_fn(request_id))
+    }
+
+    async fn send(uri: &str, id: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(id) = id {
+            request = request.header(REQUEST_ID_HEADER, id);
+        }
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn header(response: &Response) -> &str {
+        response.headers()[REQUEST_ID_HEADER].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_generated_and_client_ids_are_echoed() {
+        let response = send("/ok", None).await;
+        assert!(uuid::Uuid::parse_str(header(&response)).is_ok());
+
+        let response = send("/ok", Some("support-4711")).await;
+        assert_eq!(header(&response), "support-4711");
+
+        let response = send("/ok", Some("bad id\twith spaces")).await;
+        assert!(uuid::Uuid::parse_str(header(&response)).is_ok());
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        let response = send("/ok", Some(&too_long)).await;
+        assert_ne!(header(&response), too_long);
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_carry_the_id() {
+        let response = send("/fail", Some("support-4711")).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Failed to save tutorial");
+        assert_eq!(body["request_id"], "support-4711");
+
+        let outside = serde_json::to_value(ErrorResponse {
+            error: "no request".to_string(),
+        })
+        .unwrap();
+        assert!(outside.get("request_id").is_none());
+    }
+}
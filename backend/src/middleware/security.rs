@@ -1,16 +1,23 @@
 use axum::{
-    extract::Request,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
     http::{
         header::{
-            CACHE_CONTROL, CONTENT_SECURITY_POLICY, EXPIRES, PRAGMA, STRICT_TRANSPORT_SECURITY,
-            X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+            CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY,
+            STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
         },
-        HeaderName, HeaderValue, Method,
+        request::Parts,
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
     },
     middleware::Next,
     response::Response,
 };
+use base64ct::{Base64, Encoding};
+use sha2::{Digest, Sha256};
 use std::env;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
 
 // Custom HTTP header constants for security policies
 const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
@@ -24,96 +31,577 @@ const X_FORWARDED_PROTO_HEADER: HeaderName = HeaderName::from_static("x-forwarde
 const X_FORWARDED_HOST_HEADER: HeaderName = HeaderName::from_static("x-forwarded-host");
 const X_REAL_IP_HEADER: HeaderName = HeaderName::from_static("x-real-ip");
 
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
 pub fn parse_env_bool(key: &str, default: bool) -> bool {
     env::var(key)
         .ok()
         .and_then(|value| {
-            match value.trim().to_ascii_lowercase().as_str() {
-                "1" | "true" | "yes" | "on" => Some(true),
-                "0" | "false" | "no" | "off" => Some(false),
-                _ => {
-                    tracing::warn!(key = %key, value = %value, "Invalid boolean env value; using default");
-                    None
-                }
+            let parsed = parse_bool(&value);
+            if parsed.is_none() {
+                tracing::warn!(key = %key, value = %value, "Invalid boolean env value; using default");
             }
+            parsed
         })
         .unwrap_or(default)
 }
 
-/// Middleware to strip potentially spoofable forwarded headers from incoming requests.
-pub async fn strip_untrusted_forwarded_headers(mut request: Request, next: Next) -> Response {
-    {
-        let headers = request.headers_mut();
+/// An address range in CIDR notation; a bare address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+/// `ip` with every bit past the first `prefix` cleared.
+fn truncate(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    }
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_ipv4() == self.network.is_ipv4() && truncate(ip, self.prefix) == self.network
+    }
+
+    fn max_prefix(&self) -> u8 {
+        if self.network.is_ipv4() {
+            32
+        } else {
+            128
+        }
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix == self.max_prefix() {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix)
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("'{}' is not an IP address or CIDR range", value))?
+            .to_canonical();
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(|| format!("'{}' has an invalid prefix length", value))?,
+            None => max,
+        };
+        Ok(Self {
+            network: truncate(network, prefix),
+            prefix,
+        })
+    }
+}
+
+/// Proxies whose forwarded headers are honored, from `TRUSTED_PROXIES`.
+///
+/// Requests from any other peer have their forwarded headers stripped, so
+/// clients cannot choose the IP they are rate limited and logged under.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<IpRange>);
 
-        // Remove all potentially spoofable forwarded headers
-        headers.remove(FORWARDED_HEADER);
-        headers.remove(X_FORWARDED_FOR_HEADER);
-        headers.remove(X_FORWARDED_PROTO_HEADER);
-        headers.remove(X_FORWARDED_HOST_HEADER);
-        headers.remove(X_REAL_IP_HEADER);
+impl TrustedProxies {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| env::var(key).ok())
+    }
+
+    /// Reads `TRUSTED_PROXIES`, a comma-separated list of CIDR ranges.
+    /// Without it, the deprecated `TRUST_PROXY_IP_HEADERS=true` trusts every
+    /// peer.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        if let Some(list) = lookup("TRUSTED_PROXIES").filter(|list| !list.trim().is_empty()) {
+            return list
+                .parse()
+                .map_err(|err| format!("TRUSTED_PROXIES: {}", err));
+        }
+
+        let Some(legacy) = lookup("TRUST_PROXY_IP_HEADERS") else {
+            return Ok(Self::default());
+        };
+        match parse_bool(&legacy) {
+            Some(true) => {
+                tracing::warn!(
+                    "TRUST_PROXY_IP_HEADERS is deprecated and trusts every peer; set TRUSTED_PROXIES to the proxy's address range instead"
+                );
+                Ok(Self(vec![
+                    IpRange {
+                        network: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                        prefix: 0,
+                    },
+                    IpRange {
+                        network: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                        prefix: 0,
+                    },
+                ]))
+            }
+            Some(false) => Ok(Self::default()),
+            None => Err(format!(
+                "TRUST_PROXY_IP_HEADERS must be a boolean, got '{}'",
+                legacy
+            )),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|range| range.contains(ip))
+    }
+
+    /// The client address of a request from `peer`.
+    ///
+    /// Walks `X-Forwarded-For` from the right, past our own proxies, and
+    /// takes the first address they did not add themselves; entries left of
+    /// it were written by the client and prove nothing. Only called for
+    /// trusted peers.
+    fn forwarded_client(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let hops: Vec<&str> = headers
+            .get_all(X_FORWARDED_FOR_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|hop| !hop.is_empty())
+            .collect();
+        if hops.is_empty() {
+            return headers
+                .get(X_REAL_IP_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok())
+                .unwrap_or(peer)
+                .to_canonical();
+        }
+
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            // A malformed entry cannot be attributed; stop at the last hop we know
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip.to_canonical();
+            if !self.contains(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(list: &str) -> Result<Self, Self::Err> {
+        list.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+/// Resolved address of the client, set by [`resolve_client_ip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    /// The resolved address, or the peer's when the middleware did not run.
+    pub fn from_extensions(extensions: &axum::http::Extensions) -> Option<IpAddr> {
+        extensions
+            .get::<ClientIp>()
+            .map(|ClientIp(ip)| *ip)
+            .or_else(|| {
+                extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip().to_canonical())
+            })
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_extensions(&parts.extensions)
+            .map(ClientIp)
+            .ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Client address unavailable",
+            ))
+    }
+}
+
+/// Middleware resolving the client IP into [`ClientIp`].
+///
+/// Forwarded headers are only honored when the connection comes from a
+/// trusted proxy; from anyone else they are stripped before any handler or
+/// rate limiter sees them.
+pub async fn resolve_client_ip(
+    State(trusted): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical());
+
+    let client = match peer {
+        Some(peer) if trusted.contains(peer) => {
+            Some(trusted.forwarded_client(peer, request.headers()))
+        }
+        _ => {
+            let headers = request.headers_mut();
+            headers.remove(FORWARDED_HEADER);
+            headers.remove(X_FORWARDED_FOR_HEADER);
+            headers.remove(X_FORWARDED_PROTO_HEADER);
+            headers.remove(X_FORWARDED_HOST_HEADER);
+            headers.remove(X_REAL_IP_HEADER);
+            peer
+        }
+    };
+    if let Some(client) = client {
+        request.extensions_mut().insert(ClientIp(client));
     }
 
     next.run(request).await
 }
 
-/// Middleware to add security headers to all HTTP responses.
-pub async fn security_headers(request: Request, next: Next) -> Response {
-    let method = request.method().clone();
-    let path = request.uri().path().to_string();
+const DEFAULT_CSP: &str = if cfg!(debug_assertions) {
+    // Development CSP - allows websocket connections for the dev server
+    "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; font-src 'self' https://fonts.gstatic.com data:; img-src 'self' data:; connect-src 'self' ws: wss:; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none';"
+} else {
+    // Production CSP - stricter, but needs unsafe-inline for html2pdf/html2canvas
+    "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; font-src 'self' https://fonts.gstatic.com data:; img-src 'self' data:; connect-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'; upgrade-insecure-requests;"
+};
+const DEFAULT_HSTS_MAX_AGE: u64 = 31_536_000;
+const DEFAULT_REFERRER_POLICY: &str = "no-referrer";
+const DEFAULT_PERMISSIONS_POLICY: &str = "geolocation=(), microphone=(), camera=()";
+const REFERRER_POLICIES: &[&str] = &[
+    "no-referrer",
+    "no-referrer-when-downgrade",
+    "origin",
+    "origin-when-cross-origin",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+    "unsafe-url",
+];
 
-    // Detect if request is over HTTPS for HSTS header
-    let is_https = request
-        .headers()
-        .get("x-forwarded-proto")
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v == "https")
-        .unwrap_or(false);
+static SECURITY_HEADERS: OnceLock<SecurityHeadersConfig> = OnceLock::new();
 
-    let mut response = next.run(request).await;
-    let headers = response.headers_mut();
+/// Values of the headers set by [`security_headers`], read from the
+/// environment once at startup. The defaults are the built-in policy.
+///
+/// - `CSP_POLICY`: the Content-Security-Policy
+/// - `CSP_REPORT_ONLY`: send it as `Content-Security-Policy-Report-Only`
+/// - `CSP_REPORT_URI`: appended as `report-uri`; `/api/csp-report` logs reports
+/// - `HSTS_MAX_AGE_SECONDS` (default one year, `0` disables HSTS),
+///   `HSTS_INCLUDE_SUBDOMAINS` and `HSTS_PRELOAD` (both default true)
+/// - `REFERRER_POLICY` and `PERMISSIONS_POLICY`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeadersConfig {
+    csp_header: HeaderName,
+    csp: HeaderValue,
+    /// Only sent on requests that arrived over HTTPS.
+    hsts: Option<HeaderValue>,
+    referrer_policy: HeaderValue,
+    permissions_policy: HeaderValue,
+}
 
-    // Configure cache control based on endpoint type
-    // Public endpoints can be cached, sensitive endpoints cannot
-    let cacheable = method == Method::GET
-        && (path == "/api/tutorials"
-            || path.starts_with("/api/tutorials/")
-            || path.starts_with("/api/public/"));
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self::parse(|_| None).expect("built-in security headers are valid")
+    }
+}
 
-    if cacheable {
-        // Allow caching for public read-only endpoints (5 minutes)
-        headers.insert(
-            CACHE_CONTROL,
-            HeaderValue::from_static("public, max-age=300, stale-while-revalidate=60"),
-        );
-        headers.remove(PRAGMA);
-        headers.remove(EXPIRES);
-    } else {
-        // No caching for sensitive endpoints (auth, admin, etc.)
-        headers.insert(
-            CACHE_CONTROL,
-            HeaderValue::from_static("no-store, no-cache, must-revalidate"),
-        );
-        headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
-        headers.insert(EXPIRES, HeaderValue::from_static("0"));
+impl SecurityHeadersConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| env::var(key).ok())
+    }
+
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let value = |key: &str| {
+            lookup(key)
+                .map(|raw| raw.trim().to_string())
+                .filter(|raw| !raw.is_empty())
+        };
+        let flag = |key: &str, default: bool| -> Result<bool, String> {
+            match value(key) {
+                Some(raw) => parse_bool(&raw)
+                    .ok_or_else(|| format!("{} must be a boolean, got '{}'", key, raw)),
+                None => Ok(default),
+            }
+        };
+        let header = |key: &str, raw: String| -> Result<HeaderValue, String> {
+            HeaderValue::from_str(&raw)
+                .map_err(|_| format!("{} contains characters not allowed in a header", key))
+        };
+
+        let mut csp = value("CSP_POLICY").unwrap_or_else(|| DEFAULT_CSP.to_string());
+        if let Some(uri) = value("CSP_REPORT_URI") {
+            if uri.contains(|c: char| c.is_whitespace() || c == ';' || c == ',') {
+                return Err(format!(
+                    "CSP_REPORT_URI must be a single URI, got '{}'",
+                    uri
+                ));
+            }
+            if !csp.ends_with(';') {
+                csp.push(';');
+            }
+            csp.push_str(&format!(" report-uri {};", uri));
+        }
+        let csp_header = if flag("CSP_REPORT_ONLY", false)? {
+            CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            CONTENT_SECURITY_POLICY
+        };
+
+        let max_age = match value("HSTS_MAX_AGE_SECONDS") {
+            Some(raw) => raw.parse::<u64>().map_err(|_| {
+                format!(
+                    "HSTS_MAX_AGE_SECONDS must be a number of seconds, got '{}'",
+                    raw
+                )
+            })?,
+            None => DEFAULT_HSTS_MAX_AGE,
+        };
+        let include_subdomains = flag("HSTS_INCLUDE_SUBDOMAINS", true)?;
+        let preload = flag("HSTS_PRELOAD", true)?;
+        let hsts = if max_age == 0 {
+            None
+        } else {
+            // Browsers' preload lists reject anything weaker
+            if preload && (max_age < DEFAULT_HSTS_MAX_AGE || !include_subdomains) {
+                return Err(
+                    "HSTS_PRELOAD requires HSTS_MAX_AGE_SECONDS of at least 31536000 and HSTS_INCLUDE_SUBDOMAINS"
+                        .to_string(),
+                );
+            }
+            let mut hsts = format!("max-age={}", max_age);
+            if include_subdomains {
+                hsts.push_str("; includeSubDomains");
+            }
+            if preload {
+                hsts.push_str("; preload");
+            }
+            Some(HeaderValue::from_str(&hsts).expect("HSTS value is ASCII"))
+        };
+
+        let referrer_policy =
+            value("REFERRER_POLICY").unwrap_or_else(|| DEFAULT_REFERRER_POLICY.to_string());
+        // A comma-separated list falls back to the last policy the browser knows
+        if !referrer_policy
+            .split(',')
+            .all(|policy| REFERRER_POLICIES.contains(&policy.trim()))
+        {
+            return Err(format!(
+                "REFERRER_POLICY '{}' is not a known policy",
+                referrer_policy
+            ));
+        }
+
+        Ok(Self {
+            csp_header,
+            csp: header("CSP_POLICY", csp)?,
+            hsts,
+            referrer_policy: header("REFERRER_POLICY", referrer_policy)?,
+            permissions_policy: header(
+                "PERMISSIONS_POLICY",
+                value("PERMISSIONS_POLICY")
+                    .unwrap_or_else(|| DEFAULT_PERMISSIONS_POLICY.to_string()),
+            )?,
+        })
+    }
+}
+
+pub fn init_security_headers() -> Result<(), String> {
+    SECURITY_HEADERS
+        .set(SecurityHeadersConfig::from_env()?)
+        .map_err(|_| "Security headers already initialized".to_string())
+}
+
+fn security_headers_config() -> &'static SecurityHeadersConfig {
+    SECURITY_HEADERS.get_or_init(SecurityHeadersConfig::default)
+}
+
+/// Response extension adjusting [`security_headers`] for one response.
+///
+/// Handlers insert it to replace or leave out individual headers, e.g. a
+/// page needing an extra `connect-src`, or to let the configured CSP run an
+/// inline script. A CSP override applies to the report-only header when
+/// `CSP_REPORT_ONLY` is set.
+#[derive(Debug, Clone, Default)]
+pub struct SecurityHeaderOverrides {
+    headers: Vec<(HeaderName, Option<HeaderValue>)>,
+    /// CSP sources of the inline scripts the response carries.
+    script_hashes: Vec<String>,
+}
+
+impl SecurityHeaderOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends `value` instead of the configured value of `name`.
+    pub fn set(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, Some(value)));
+        self
+    }
+
+    /// Leaves `name` out of the response.
+    pub fn omit(mut self, name: HeaderName) -> Self {
+        self.headers.push((name, None));
+        self
     }
 
-    // Content Security Policy - varies between dev and production
-    let csp = if cfg!(debug_assertions) {
-        // Development CSP - allows unsafe-inline for easier debugging
-        "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; font-src 'self' https://fonts.gstatic.com data:; img-src 'self' data:; connect-src 'self' ws: wss:; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none';"
-    } else {
-        // Production CSP - stricter, but needs unsafe-inline for html2pdf/html2canvas
-        "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; font-src 'self' https://fonts.gstatic.com data:; img-src 'self' data:; connect-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'; upgrade-insecure-requests;"
+    /// Allows the inline `<script>` whose content is exactly `source` by
+    /// adding its hash to the configured `script-src`.
+    pub fn allow_inline_script(mut self, source: &str) -> Self {
+        let digest = Sha256::digest(source.as_bytes());
+        self.script_hashes
+            .push(format!("'sha256-{}'", Base64::encode_string(&digest)));
+        self
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, csp_header: &HeaderName) {
+        if !self.script_hashes.is_empty() {
+            let csp = headers
+                .get(csp_header)
+                .and_then(|value| value.to_str().ok())
+                .map(|csp| with_script_hashes(csp, &self.script_hashes))
+                .and_then(|csp| HeaderValue::from_str(&csp).ok());
+            if let Some(csp) = csp {
+                headers.insert(csp_header, csp);
+            }
+        }
+        for (name, value) in &self.headers {
+            let name = if name == CONTENT_SECURITY_POLICY {
+                csp_header
+            } else {
+                name
+            };
+            match value {
+                Some(value) => {
+                    headers.insert(name, value.clone());
+                }
+                None => {
+                    headers.remove(name);
+                }
+            }
+        }
+    }
+}
+
+/// `csp` with `hashes` added to the sources scripts may come from. Without a
+/// `script-src`, one is made from `default-src`. A policy already allowing
+/// every inline script is kept: next to a hash, browsers ignore
+/// `'unsafe-inline'`.
+fn with_script_hashes(csp: &str, hashes: &[String]) -> String {
+    let name = |directive: &str| {
+        directive
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
     };
+    let mut directives: Vec<String> = csp.split(';').map(str::to_string).collect();
+    let (index, mut directive) = match directives
+        .iter()
+        .position(|directive| name(directive) == "script-src")
+    {
+        Some(index) => (index, directives[index].trim_end().to_string()),
+        None => match directives
+            .iter()
+            .position(|directive| name(directive) == "default-src")
+        {
+            Some(index) => {
+                let sources = directives[index].split_whitespace().skip(1);
+                let directive = std::iter::once(" script-src")
+                    .chain(sources)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                directives.insert(index + 1, String::new());
+                (index + 1, directive)
+            }
+            // Nothing restricts scripts
+            None => return csp.to_string(),
+        },
+    };
+    if directive
+        .split_whitespace()
+        .any(|source| source.eq_ignore_ascii_case("'unsafe-inline'"))
+    {
+        return csp.to_string();
+    }
+    for hash in hashes {
+        directive.push(' ');
+        directive.push_str(hash);
+    }
+    directives[index] = directive;
+    directives.join(";")
+}
 
-    headers.insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static(csp));
+/// Middleware to add security headers to all HTTP responses.
+pub async fn security_headers(request: Request, next: Next) -> Response {
+    let config = security_headers_config();
+
+    // Detect if request is over HTTPS for HSTS header
+    let is_https = request.extensions().get::<crate::tls::Https>().is_some()
+        || request
+            .headers()
+            .get("x-forwarded-proto")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v == "https")
+            .unwrap_or(false);
+
+    let mut response = next.run(request).await;
+    let overrides = response
+        .extensions_mut()
+        .remove::<SecurityHeaderOverrides>()
+        .unwrap_or_default();
+    let headers = response.headers_mut();
+
+    headers.insert(&config.csp_header, config.csp.clone());
 
     // HSTS - only add if already using HTTPS
-    if is_https {
-        headers.insert(
-            STRICT_TRANSPORT_SECURITY,
-            HeaderValue::from_static("max-age=31536000; includeSubDomains; preload"),
-        );
+    if let Some(hsts) = config.hsts.as_ref().filter(|_| is_https) {
+        headers.insert(STRICT_TRANSPORT_SECURITY, hsts.clone());
     }
 
     // Anti-MIME-sniffing header
@@ -123,16 +611,368 @@ pub async fn security_headers(request: Request, next: Next) -> Response {
     headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
 
     // Referrer privacy
-    headers.insert(REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    headers.insert(REFERRER_POLICY, config.referrer_policy.clone());
 
     // Disable browser features that could compromise privacy
-    headers.insert(
-        PERMISSIONS_POLICY,
-        HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
-    );
+    headers.insert(PERMISSIONS_POLICY, config.permissions_policy.clone());
 
     // Legacy XSS filter (disabled in favor of CSP)
     headers.insert(X_XSS_PROTECTION, HeaderValue::from_static("0"));
 
+    overrides.apply(headers, &config.csp_header);
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<TrustedProxies, String> {
+        TrustedProxies::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    /// Sends a request from `peer` and returns the resolved client IP and
+    /// the `X-Forwarded-For` the handler saw.
+    async fn resolve(trusted: &str, peer: &str, headers: &[(&str, &str)]) -> (String, String) {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|ClientIp(ip): ClientIp, headers: HeaderMap| async move {
+                    let forwarded = headers
+                        .get(X_FORWARDED_FOR_HEADER)
+                        .map(|value| value.to_str().unwrap().to_string())
+                        .unwrap_or_default();
+                    format!("{}|{}", ip, forwarded)
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(trusted.parse::<TrustedProxies>().unwrap()),
+                resolve_client_ip,
+            ));
+        let mut request = Request::builder()
+            .uri("/")
+            .extension(ConnectInfo(SocketAddr::new(ip(peer), 40000)));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let (ip, forwarded) = body.split_once('|').unwrap();
+        (ip.to_string(), forwarded.to_string())
+    }
+
+    fn parse_headers(vars: &[(&str, &str)]) -> Result<SecurityHeadersConfig, String> {
+        SecurityHeadersConfig::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_security_header_env_values() {
+        let defaults = parse_headers(&[]).unwrap();
+        assert_eq!(defaults.csp_header, CONTENT_SECURITY_POLICY);
+        assert_eq!(defaults.csp, DEFAULT_CSP);
+        assert_eq!(
+            defaults.hsts.as_ref().unwrap(),
+            "max-age=31536000; includeSubDomains; preload"
+        );
+        assert_eq!(defaults.referrer_policy, "no-referrer");
+        assert_eq!(
+            defaults.permissions_policy,
+            "geolocation=(), microphone=(), camera=()"
+        );
+
+        let config = parse_headers(&[
+            (
+                "CSP_POLICY",
+                "default-src 'self'; connect-src 'self' https://api.example.com",
+            ),
+            ("CSP_REPORT_URI", "/api/csp-report"),
+            ("HSTS_MAX_AGE_SECONDS", "600"),
+            ("HSTS_PRELOAD", "false"),
+            ("HSTS_INCLUDE_SUBDOMAINS", "no"),
+            ("REFERRER_POLICY", "strict-origin-when-cross-origin"),
+            ("PERMISSIONS_POLICY", "camera=()"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config.csp,
+            "default-src 'self'; connect-src 'self' https://api.example.com; report-uri /api/csp-report;"
+        );
+        assert_eq!(config.hsts.as_ref().unwrap(), "max-age=600");
+        assert_eq!(config.referrer_policy, "strict-origin-when-cross-origin");
+        assert_eq!(config.permissions_policy, "camera=()");
+
+        assert_eq!(
+            parse_headers(&[("HSTS_MAX_AGE_SECONDS", "0")])
+                .unwrap()
+                .hsts,
+            None
+        );
+        // Preloading needs a long max-age covering subdomains
+        assert!(parse_headers(&[("HSTS_MAX_AGE_SECONDS", "600")]).is_err());
+        assert!(parse_headers(&[("HSTS_INCLUDE_SUBDOMAINS", "false")]).is_err());
+        assert!(parse_headers(&[("HSTS_MAX_AGE_SECONDS", "-1")]).is_err());
+        assert!(parse_headers(&[("REFERRER_POLICY", "sometimes")]).is_err());
+        assert!(parse_headers(&[("CSP_REPORT_URI", "/a /b")]).is_err());
+        assert!(parse_headers(&[("CSP_POLICY", "default-src\n'self'")]).is_err());
+        assert!(parse_headers(&[("CSP_REPORT_ONLY", "perhaps")]).is_err());
+    }
+
+    #[test]
+    fn test_report_only_mode_renames_the_csp_header() {
+        let config = parse_headers(&[("CSP_REPORT_ONLY", "true")]).unwrap();
+        assert_eq!(config.csp_header, CONTENT_SECURITY_POLICY_REPORT_ONLY);
+        assert_eq!(config.csp, DEFAULT_CSP);
+
+        // Overrides follow the header to its report-only name
+        let mut headers = HeaderMap::new();
+        headers.insert(&config.csp_header, config.csp.clone());
+        SecurityHeaderOverrides::new()
+            .set(
+                CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static("default-src 'none'"),
+            )
+            .apply(&mut headers, &config.csp_header);
+        assert!(!headers.contains_key(CONTENT_SECURITY_POLICY));
+        assert_eq!(
+            headers[CONTENT_SECURITY_POLICY_REPORT_ONLY],
+            "default-src 'none'"
+        );
+    }
+
+    #[test]
+    fn test_inline_scripts_are_allowed_by_hash() {
+        let hashes = ["'sha256-abc='".to_string()];
+        assert_eq!(
+            with_script_hashes(DEFAULT_CSP, &hashes),
+            DEFAULT_CSP.replacen("script-src 'self';", "script-src 'self' 'sha256-abc=';", 1)
+        );
+        assert_eq!(
+            with_script_hashes("default-src 'self' https://cdn.example; img-src *", &hashes),
+            "default-src 'self' https://cdn.example; script-src 'self' https://cdn.example 'sha256-abc='; img-src *"
+        );
+        for csp in ["img-src 'self'", "script-src 'self' 'unsafe-inline'"] {
+            assert_eq!(with_script_hashes(csp, &hashes), csp);
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("script-src 'self'"),
+        );
+        SecurityHeaderOverrides::new()
+            .allow_inline_script("window.__LOCALE__=\"de\";")
+            .apply(&mut headers, &CONTENT_SECURITY_POLICY);
+        let expected = format!(
+            "script-src 'self' 'sha256-{}'",
+            Base64::encode_string(&Sha256::digest(b"window.__LOCALE__=\"de\";"))
+        );
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], expected.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_handlers_can_override_security_headers() {
+        let app = Router::new()
+            .route("/plain", get(|| async { "plain" }))
+            .route(
+                "/custom",
+                get(|| async {
+                    (
+                        axum::Extension(
+                            SecurityHeaderOverrides::new()
+                                .set(
+                                    CONTENT_SECURITY_POLICY,
+                                    HeaderValue::from_static(
+                                        "default-src 'self'; connect-src 'self' https://api.example.com",
+                                    ),
+                                )
+                                .omit(X_FRAME_OPTIONS),
+                        ),
+                        "custom",
+                    )
+                }),
+            )
+            .layer(axum::middleware::from_fn(security_headers));
+        let send = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = send("/plain").await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], DEFAULT_CSP);
+        assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+
+        let response = send("/custom").await.unwrap();
+        let headers = response.headers();
+        assert_eq!(
+            headers[CONTENT_SECURITY_POLICY],
+            "default-src 'self'; connect-src 'self' https://api.example.com"
+        );
+        assert!(!headers.contains_key(X_FRAME_OPTIONS));
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    }
+
+    #[test]
+    fn test_ip_ranges() {
+        let range: IpRange = "10.1.0.0/16".parse().unwrap();
+        assert!(range.contains(ip("10.1.200.3")));
+        assert!(!range.contains(ip("10.2.0.1")));
+        assert!(range.contains(ip("::ffff:10.1.0.9")));
+        assert!(!range.contains(ip("fd00::1")));
+
+        let single: IpRange = "192.0.2.5".parse().unwrap();
+        assert!(single.contains(ip("192.0.2.5")));
+        assert!(!single.contains(ip("192.0.2.6")));
+
+        let v6: IpRange = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(ip("fd12:3456::1")));
+        assert!(!v6.contains(ip("fe80::1")));
+
+        let everything: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("203.0.113.1")));
+
+        // Host bits are dropped when normalizing
+        assert_eq!(
+            "10.1.2.3/16".parse::<IpRange>().unwrap().to_string(),
+            "10.1.0.0/16"
+        );
+        assert_eq!(
+            "::ffff:192.0.2.5".parse::<IpRange>().unwrap().to_string(),
+            "192.0.2.5"
+        );
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("fd00::/129".parse::<IpRange>().is_err());
+        assert!("proxy.local".parse::<IpRange>().is_err());
+        assert!("10.0.0.0/".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_env_parsing_and_legacy_alias() {
+        assert!(parse(&[]).unwrap().is_empty());
+
+        let proxies = parse(&[("TRUSTED_PROXIES", " 172.16.0.0/12 , ::1 ")]).unwrap();
+        assert!(proxies.contains(ip("172.20.0.2")));
+        assert!(proxies.contains(ip("::1")));
+        assert!(!proxies.contains(ip("192.168.0.1")));
+        assert!(parse(&[("TRUSTED_PROXIES", "172.16.0.0/12,nope")]).is_err());
+
+        let legacy = parse(&[("TRUST_PROXY_IP_HEADERS", "true")]).unwrap();
+        assert!(legacy.contains(ip("203.0.113.9")));
+        assert!(legacy.contains(ip("2001:db8::1")));
+        assert!(parse(&[("TRUST_PROXY_IP_HEADERS", "false")])
+            .unwrap()
+            .is_empty());
+        assert!(parse(&[("TRUST_PROXY_IP_HEADERS", "maybe")]).is_err());
+
+        // The explicit list wins over the deprecated flag
+        let both = parse(&[
+            ("TRUSTED_PROXIES", "10.0.0.1"),
+            ("TRUST_PROXY_IP_HEADERS", "true"),
+        ])
+        .unwrap();
+        assert!(!both.contains(ip("203.0.113.9")));
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_peers_cannot_spoof_their_address() {
+        // No proxies configured
+        let (client, forwarded) = resolve(
+            "",
+            "203.0.113.50",
+            &[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")],
+        )
+        .await;
+        assert_eq!(client, "203.0.113.50");
+        assert_eq!(forwarded, "");
+
+        // A proxy is configured, but the request bypassed it
+        let (client, forwarded) = resolve(
+            "10.0.0.0/8",
+            "203.0.113.50",
+            &[("x-forwarded-for", "10.0.0.2")],
+        )
+        .await;
+        assert_eq!(client, "203.0.113.50");
+        assert_eq!(forwarded, "");
+    }
+
+    #[tokio::test]
+    async fn test_forged_hops_before_the_proxy_are_ignored() {
+        // The client sent its own X-Forwarded-For, the proxy appended the real peer
+        let (client, forwarded) = resolve(
+            "10.0.0.0/8",
+            "10.0.0.2",
+            &[("x-forwarded-for", "1.2.3.4, 198.51.100.7")],
+        )
+        .await;
+        assert_eq!(client, "198.51.100.7");
+        assert_eq!(forwarded, "1.2.3.4, 198.51.100.7");
+
+        // Claiming to be one of our proxies does not help either
+        let (client, _) = resolve(
+            "10.0.0.0/8",
+            "10.0.0.2",
+            &[("x-forwarded-for", "10.0.0.9, 198.51.100.7")],
+        )
+        .await;
+        assert_eq!(client, "198.51.100.7");
+    }
+
+    #[tokio::test]
+    async fn test_chains_through_trusted_proxies() {
+        // CDN edge -> internal load balancer -> backend, spread over two headers
+        let (client, _) = resolve(
+            "10.0.0.0/8, 2001:db8::/32",
+            "10.0.0.2",
+            &[
+                ("x-forwarded-for", "198.51.100.7, 2001:db8::10"),
+                ("x-forwarded-for", "10.0.0.3"),
+            ],
+        )
+        .await;
+        assert_eq!(client, "198.51.100.7");
+
+        // Without X-Forwarded-For, X-Real-IP from a trusted proxy is used
+        let (client, _) = resolve("127.0.0.1", "127.0.0.1", &[("x-real-ip", "198.51.100.8")]).await;
+        assert_eq!(client, "198.51.100.8");
+
+        // A trusted peer sending no forwarded headers is the client itself
+        let (client, _) = resolve("127.0.0.1", "127.0.0.1", &[]).await;
+        assert_eq!(client, "127.0.0.1");
+
+        // Every hop trusted: the left-most one is the best we know
+        let (client, _) =
+            resolve("10.0.0.0/8", "10.0.0.2", &[("x-forwarded-for", "10.0.0.5")]).await;
+        assert_eq!(client, "10.0.0.5");
+
+        // A malformed hop stops the walk at the last address we could read
+        let (client, _) = resolve(
+            "10.0.0.0/8",
+            "10.0.0.2",
+            &[("x-forwarded-for", "198.51.100.7, garbage, 10.0.0.3")],
+        )
+        .await;
+        assert_eq!(client, "10.0.0.3");
+    }
+}
@@ -0,0 +1,176 @@
+//! Time budgets for requests.
+//!
+//! A hung frontend fetch or a long-locked SQLite write would otherwise keep
+//! the connection open indefinitely. Each route group is wrapped in
+//! [`with_timeout`]; when its budget runs out the handler is dropped and the
+//! client gets a `503` with the usual JSON error body.
+//!
+//! Budgets come from `REQUEST_TIMEOUT_SECONDS` (default 30),
+//! `UPLOAD_REQUEST_TIMEOUT_SECONDS` (default 300) for upload requests, which
+//! read their whole body inside the handler, `IMPORT_REQUEST_TIMEOUT_SECONDS`
+//! (default 300) for content bundle imports and exports, and
+//! `FRONTEND_PROXY_TIMEOUT_SECONDS` (default 60) for the pages served through
+//! the frontend proxy.
+
+use crate::models::ErrorResponse;
+use axum::{error_handling::HandleErrorLayer, http::StatusCode, BoxError, Json, Router};
+use std::time::Duration;
+use tower::{timeout::error::Elapsed, ServiceBuilder};
+
+/// Budgets of the route groups, read from the environment once at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    pub default: Duration,
+    pub uploads: Duration,
+    pub imports: Duration,
+    pub frontend: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_secs(30),
+            uploads: Duration::from_secs(300),
+            imports: Duration::from_secs(300),
+            frontend: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the budgets through `lookup`; unset values keep their default.
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let seconds = |key: &str, default: Duration| -> Result<Duration, String> {
+            let Some(raw) = lookup(key).filter(|raw| !raw.trim().is_empty()) else {
+                return Ok(default);
+            };
+            match raw.trim().parse::<u64>() {
+                Ok(value) if value > 0 => Ok(Duration::from_secs(value)),
+                _ => Err(format!(
+                    "{} must be a positive number of seconds, got '{}'",
+                    key, raw
+                )),
+            }
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
+            default: seconds("REQUEST_TIMEOUT_SECONDS", defaults.default)?,
+            uploads: seconds("UPLOAD_REQUEST_TIMEOUT_SECONDS", defaults.uploads)?,
+            imports: seconds("IMPORT_REQUEST_TIMEOUT_SECONDS", defaults.imports)?,
+            frontend: seconds("FRONTEND_PROXY_TIMEOUT_SECONDS", defaults.frontend)?,
+        })
+    }
+}
+
+async fn handle_timeout_error(err: BoxError) -> (StatusCode, Json<ErrorResponse>) {
+    if err.is::<Elapsed>() {
+        tracing::warn!("Request timed out");
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Request timed out".to_string(),
+            }),
+        )
+    } else {
+        tracing::error!("Request failed: {}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Internal server error".to_string(),
+            }),
+        )
+    }
+}
+
+/// Answers requests to `router` that take longer than `budget` with `503`.
+pub fn with_timeout<S>(router: Router<S>, budget: Duration) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_timeout_error))
+            .timeout(budget),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::request_id::request_id;
+    use axum::{body::Body, extract::Request, routing::get};
+    use tower::ServiceExt;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<RequestTimeouts, String> {
+        RequestTimeouts::parse(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_env_overrides_and_defaults() {
+        assert_eq!(parse(&[]).unwrap(), RequestTimeouts::default());
+
+        let timeouts = parse(&[
+            ("REQUEST_TIMEOUT_SECONDS", "5"),
+            ("FRONTEND_PROXY_TIMEOUT_SECONDS", " 90 "),
+            ("IMPORT_REQUEST_TIMEOUT_SECONDS", "900"),
+        ])
+        .unwrap();
+        assert_eq!(timeouts.default, Duration::from_secs(5));
+        assert_eq!(timeouts.uploads, Duration::from_secs(300));
+        assert_eq!(timeouts.imports, Duration::from_secs(900));
+        assert_eq!(timeouts.frontend, Duration::from_secs(90));
+
+        assert!(parse(&[("REQUEST_TIMEOUT_SECONDS", "0")]).is_err());
+        assert!(parse(&[("UPLOAD_REQUEST_TIMEOUT_SECONDS", "1.5")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_handlers_get_a_json_503() {
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            "done"
+        };
+        let short = with_timeout(
+            Router::new().route("/api/slow", get(slow)),
+            Duration::from_millis(20),
+        );
+        let long = with_timeout(
+            Router::new().route("/api/upload", get(slow)),
+            Duration::from_secs(5),
+        );
+        let app = short
+            .merge(long)
+            .layer(axum::middleware::from_fn(request_id));
+        let send = |uri: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("x-request-id", "slow-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = send("/api/slow").await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Request timed out");
+        assert_eq!(body["request_id"], "slow-1");
+
+        let response = send("/api/upload").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
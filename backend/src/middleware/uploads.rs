@@ -0,0 +1,217 @@
+//! Response headers for `/uploads`.
+//!
+//! Upload names are random UUIDs and never reused, so
+//! [`super::cache_control`] lets a stored file be cached forever. The content type is derived from the extension against a
+//! fixed list; anything else is sent as an opaque download so a stray HTML or
+//! script file is never rendered from our origin. Downloads below `files/`
+//! are always sent as attachments, whatever their type. Drafts below
+//! `private/` are never served here; see
+//! [`crate::handlers::upload::serve_private_upload`]. Both folders are
+//! matched on the path as `ServeDir` resolves it, so `%70rivate/`,
+//! `./private/` or `//private/` name the same folder.
+
+use crate::utils::content_refs::{PRIVATE_UPLOAD_DIR, UPLOADS_PREFIX, UPLOAD_FILES_DIR};
+use axum::{
+    extract::Request,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+        HeaderValue, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use percent_encoding::percent_decode_str;
+use std::path::{Component, Path};
+
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+pub const SVG_MIME_TYPE: &str = "image/svg+xml";
+
+/// Content type served for an upload, or `None` when the extension is not
+/// one we serve inline.
+pub fn content_type_for(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match ext.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        // Only sanitized SVGs are stored; the global CSP still blocks scripts.
+        "svg" => Some(SVG_MIME_TYPE),
+        _ => None,
+    }
+}
+
+/// Content type served for a download below `files/`.
+pub fn download_type_for(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some("application/pdf"),
+        "zip" => Some("application/zip"),
+        "gz" => Some("application/gzip"),
+        "txt" | "sh" => Some("text/plain; charset=utf-8"),
+        _ => None,
+    }
+}
+
+/// The key `ServeDir` opens for a request path: percent-decoded, with `.`
+/// and empty segments dropped. `None` for paths it refuses to serve.
+fn upload_key(path: &str) -> Option<String> {
+    let path = path.strip_prefix(UPLOADS_PREFIX).unwrap_or(path);
+    let decoded = percent_decode_str(path.trim_start_matches('/'))
+        .decode_utf8()
+        .ok()?;
+    let mut segments = Vec::new();
+    for component in Path::new(&*decoded).components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_str()?),
+            Component::CurDir => {}
+            Component::Prefix(_) | Component::RootDir | Component::ParentDir => return None,
+        }
+    }
+    Some(segments.join("/"))
+}
+
+fn is_private(key: &str) -> bool {
+    format!("{key}/").starts_with(PRIVATE_UPLOAD_DIR)
+}
+
+/// Sets caching, content type and sniffing headers on upload responses.
+pub async fn upload_headers(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let Some(key) = upload_key(&path).filter(|key| !is_private(key)) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut response = next.run(request).await;
+    let status = response.status();
+    let headers = response.headers_mut();
+
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    if !(status.is_success() || status == StatusCode::NOT_MODIFIED) {
+        return response;
+    }
+
+    let (content_type, disposition) = if key.starts_with(UPLOAD_FILES_DIR) {
+        (download_type_for(&key), "attachment")
+    } else {
+        (content_type_for(&key), "inline")
+    };
+    match content_type {
+        Some(content_type) => {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            headers.insert(CONTENT_DISPOSITION, HeaderValue::from_static(disposition));
+        }
+        None => {
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/octet-stream"),
+            );
+            headers.insert(CONTENT_DISPOSITION, HeaderValue::from_static("attachment"));
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{cache_control::cache_control, security::security_headers};
+    use axum::{
+        body::Body,
+        http::header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_RANGE, CONTENT_SECURITY_POLICY, PRAGMA, RANGE,
+        },
+        Router,
+    };
+    use tower::ServiceExt;
+    use tower_http::services::ServeDir;
+
+    fn app(dir: &std::path::Path) -> Router {
+        Router::new()
+            .nest_service("/uploads", ServeDir::new(dir))
+            .layer(axum::middleware::from_fn(upload_headers))
+            .layer(axum::middleware::from_fn(cache_control))
+            .layer(axum::middleware::from_fn(security_headers))
+    }
+
+    async fn get(app: Router, path: &str, range: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(path);
+        if let Some(range) = range {
+            request = request.header(RANGE, range);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_headers_survive_security_middleware() {
+        let dir = std::env::temp_dir().join(format!("upload-headers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("fixture.png"), crate::utils::image_size::tests::png_header(2, 2))
+            .unwrap();
+        std::fs::write(dir.join("page.html"), "<script>alert(1)</script>").unwrap();
+        std::fs::write(dir.join("diagram.svg"), "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+
+        let response = get(app(&dir), "/uploads/fixture.png", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[CACHE_CONTROL], IMMUTABLE_CACHE_CONTROL);
+        assert_eq!(headers[CONTENT_TYPE], "image/png");
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert!(headers.get(PRAGMA).is_none());
+
+        let response = get(app(&dir), "/uploads/fixture.png", Some("bytes=0-7")).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(response.headers().contains_key(CONTENT_RANGE));
+        assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
+
+        let response = get(app(&dir), "/uploads/diagram.svg", None).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "inline");
+        assert!(response.headers().contains_key(CONTENT_SECURITY_POLICY));
+
+        let response = get(app(&dir), "/uploads/page.html", None).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/octet-stream");
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "attachment");
+
+        std::fs::create_dir_all(dir.join("files")).unwrap();
+        std::fs::write(dir.join("files/notes.txt"), "<html><script>alert(1)</script>").unwrap();
+        std::fs::write(dir.join("files/cheatsheet.pdf"), "%PDF-1.4").unwrap();
+        std::fs::write(dir.join("files/sneaky.png"), "not really").unwrap();
+
+        let response = get(app(&dir), "/uploads/files/notes.txt", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "attachment");
+
+        let response = get(app(&dir), "/uploads/files/cheatsheet.pdf", None).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/pdf");
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "attachment");
+
+        let response = get(app(&dir), "/uploads/files/sneaky.png", None).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/octet-stream");
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "attachment");
+
+        std::fs::create_dir_all(dir.join("private/nested")).unwrap();
+        std::fs::write(dir.join("private/nested/draft.png"), "draft").unwrap();
+        for path in [
+            "/uploads/private/nested/draft.png",
+            "/uploads/%70rivate/nested/draft.png",
+            "/uploads/./private/nested/draft.png",
+            "/uploads//private/nested/draft.png",
+            "/uploads/private",
+        ] {
+            let response = get(app(&dir), path, None).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+
+        let response = get(app(&dir), "/uploads/%66iles/./notes.txt", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_DISPOSITION], "attachment");
+
+        let response = get(app(&dir), "/uploads/missing.png", None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-store, no-cache, must-revalidate");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
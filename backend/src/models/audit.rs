@@ -0,0 +1,28 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A recorded admin mutation.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Acting user; `None` when the request was not authenticated.
+    pub username: Option<String>,
+    pub method: String,
+    pub path: String,
+    /// Route template, e.g. `/api/content/{section}`.
+    pub route: Option<String>,
+    pub status: i64,
+    pub latency_ms: i64,
+    pub created_at: String,
+}
+
+/// Fields of an [`AuditEntry`] before it is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewAuditEntry {
+    pub username: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub route: Option<String>,
+    pub status: u16,
+    pub latency_ms: u64,
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -8,7 +9,11 @@ pub struct Comment {
     pub post_id: Option<String>,
     pub author: String,
     pub content: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    /// `created_at` as stored, which pagination cursors compare against
+    #[serde(skip)]
+    #[sqlx(rename = "created_at")]
+    pub stored_created_at: String,
     pub votes: i64,
     pub is_admin: bool,
 }
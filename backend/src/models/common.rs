@@ -0,0 +1,90 @@
+//! Bodies shared by several handlers.
+
+use serde::{Deserialize, Serialize};
+
+/// The body of an error response, `{"error": "..."}`.
+#[derive(Debug)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Adds the id of the request being handled, so users can quote it when
+/// reporting an error. Bodies built outside a request leave it out.
+impl Serialize for ErrorResponse {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            error: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            request_id: Option<String>,
+        }
+
+        Body {
+            error: &self.error,
+            request_id: crate::middleware::request_id::current(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A stored upload, as answered by `POST /api/upload` and by completed
+/// upload sessions.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadResponse {
+    pub id: String,
+    pub url: String,
+    /// Pixel size of images whose header could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body_outside_a_request() {
+        let body = ErrorResponse {
+            error: "Not found".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(body).unwrap(),
+            serde_json::json!({ "error": "Not found" })
+        );
+    }
+
+    #[test]
+    fn test_upload_body_leaves_out_unknown_sizes() {
+        let image = UploadResponse {
+            id: "4711".to_string(),
+            url: "/uploads/4711.png".to_string(),
+            width: Some(640),
+            height: Some(480),
+        };
+        assert_eq!(
+            serde_json::to_value(&image).unwrap(),
+            serde_json::json!({
+                "id": "4711",
+                "url": "/uploads/4711.png",
+                "width": 640,
+                "height": 480,
+            })
+        );
+
+        let file = UploadResponse {
+            id: "4712".to_string(),
+            url: "/uploads/files/4712.pdf".to_string(),
+            width: None,
+            height: None,
+        };
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": "4712", "url": "/uploads/files/4712.pdf" })
+        );
+        let parsed: UploadResponse = serde_json::from_value(json).unwrap();
+        assert_eq!((parsed.width, parsed.height), (None, None));
+    }
+}
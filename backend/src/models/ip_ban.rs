@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A banned address or CIDR range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct IpBan {
+    pub id: i64,
+    /// Normalized range, e.g. `203.0.113.0/24`; single addresses have no prefix.
+    pub ip: String,
+    pub reason: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    /// RFC 3339 timestamp; `None` bans permanently.
+    pub expires_at: Option<String>,
+}
+
+/// Body of `POST /api/admin/bans/ip`.
+#[derive(Debug, Deserialize)]
+pub struct CreateIpBanRequest {
+    pub ip: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// RFC 3339 timestamp in the future; omit for a permanent ban.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+/// Query of `DELETE /api/admin/bans/ip`.
+#[derive(Debug, Deserialize)]
+pub struct DeleteIpBanQuery {
+    pub ip: String,
+}
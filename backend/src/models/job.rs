@@ -0,0 +1,18 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// Outcome of the latest run of a scheduled job.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct JobRun {
+    pub name: String,
+    pub last_run_at: String,
+    /// When the job last ran without an error, if ever.
+    pub last_success_at: Option<String>,
+    pub duration_ms: i64,
+    pub succeeded: bool,
+    /// Error of the latest run when it failed.
+    pub error: Option<String>,
+    /// Runs and failed runs since the job was first scheduled.
+    pub runs: i64,
+    pub failures: i64,
+}
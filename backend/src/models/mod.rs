@@ -1,9 +1,440 @@
+pub mod audit;
 pub mod comment;
+pub mod common;
+pub mod ip_ban;
+pub mod job;
+pub mod pagination;
 pub mod site;
 pub mod tutorial;
+pub mod upload;
 pub mod user;
 
+pub use audit::*;
 pub use comment::*;
+pub use common::*;
+pub use ip_ban::*;
+pub use job::*;
+pub use pagination::*;
 pub use site::*;
 pub use tutorial::*;
+pub use upload::*;
 pub use user::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::json_diff::JsonDiff;
+    use chrono::Utc;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+
+    /// Fails when `body` serializes to other top-level fields than `fields`.
+    fn assert_shape(body: impl Serialize, fields: &[&str]) {
+        let value = serde_json::to_value(body).unwrap();
+        let mut actual: Vec<&str> = value
+            .as_object()
+            .expect("object body")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut expected = fields.to_vec();
+        actual.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(actual, expected);
+    }
+
+    fn page() -> SitePageResponse {
+        SitePageResponse {
+            id: "p1".to_string(),
+            slug: "grundlagen".to_string(),
+            title: "Grundlagen".to_string(),
+            description: String::new(),
+            nav_label: None,
+            show_in_nav: true,
+            order_index: 0,
+            is_published: true,
+            visibility: PAGE_VISIBILITY_PUBLIC.to_string(),
+            hero: Value::Null,
+            layout: Value::Null,
+            meta_title: None,
+            meta_description: None,
+            og_image: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: Some(Utc::now()),
+        }
+    }
+
+    fn post() -> SitePostResponse {
+        SitePostResponse {
+            id: "s1".to_string(),
+            page_id: "p1".to_string(),
+            title: "Erste Schritte".to_string(),
+            slug: "erste-schritte".to_string(),
+            excerpt: String::new(),
+            excerpt_auto: true,
+            content_markdown: String::new(),
+            word_count: 0,
+            reading_time_minutes: 1,
+            is_published: true,
+            allow_comments: true,
+            published_at: None,
+            order_index: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn tutorial_summary() -> TutorialSummaryResponse {
+        TutorialSummaryResponse {
+            id: "t1".to_string(),
+            title: "Bash".to_string(),
+            description: String::new(),
+            icon: "Terminal".to_string(),
+            color: "from-blue-500 to-cyan-600".to_string(),
+            topics: Vec::new(),
+            version: 1,
+            word_count: 0,
+            reading_time_minutes: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn user() -> UserResponse {
+        UserResponse {
+            username: "admin".to_string(),
+            role: "admin".to_string(),
+        }
+    }
+
+    /// Builds every response body field by field, so a field added, dropped
+    /// or renamed in any of them breaks this test instead of a client.
+    #[test]
+    fn test_response_shapes() {
+        assert_shape(
+            ErrorResponse {
+                error: "Not found".to_string(),
+            },
+            &["error"],
+        );
+        assert_shape(
+            UploadResponse {
+                id: "4711".to_string(),
+                url: "/uploads/4711.png".to_string(),
+                width: Some(640),
+                height: Some(480),
+            },
+            &["id", "url", "width", "height"],
+        );
+
+        assert_shape(
+            SiteContentResponse {
+                section: "hero".to_string(),
+                content: json!({}),
+                updated_at: Utc::now(),
+                updated_by: None,
+                revision: 1,
+            },
+            &["section", "content", "updated_at", "updated_by", "revision"],
+        );
+        assert_shape(SiteContentListResponse { items: Vec::new() }, &["items"]);
+        assert_shape(
+            ContentSectionResponse {
+                name: "hero".to_string(),
+                description: String::new(),
+                schema: None,
+                builtin: true,
+                created_at: Some("2024-01-01 00:00:00".to_string()),
+            },
+            &["name", "description", "schema", "builtin", "created_at"],
+        );
+        assert_shape(ContentSectionListResponse { items: Vec::new() }, &["items"]);
+        assert_shape(
+            SiteContentDiffResponse {
+                section: "hero".to_string(),
+                updated_at: None,
+                revision: None,
+                diff: JsonDiff::default(),
+            },
+            &["section", "updated_at", "revision", "changes", "unchanged"],
+        );
+        assert_shape(
+            SiteContentHistoryResponse {
+                section: "hero".to_string(),
+                items: Vec::new(),
+                total: 0,
+                limit: 20,
+                offset: 0,
+            },
+            &["section", "items", "total", "limit", "offset"],
+        );
+        assert_shape(
+            SiteContentVersionResponse {
+                version: 1,
+                section: "hero".to_string(),
+                content: json!({}),
+                updated_at: String::new(),
+                updated_by: None,
+                archived_at: String::new(),
+            },
+            &["version", "section", "content", "updated_at", "updated_by", "archived_at"],
+        );
+
+        assert_shape(
+            page(),
+            &[
+                "id",
+                "slug",
+                "title",
+                "description",
+                "nav_label",
+                "show_in_nav",
+                "order_index",
+                "is_published",
+                "visibility",
+                "hero",
+                "layout",
+                "meta_title",
+                "meta_description",
+                "og_image",
+                "created_at",
+                "updated_at",
+                "deleted_at",
+            ],
+        );
+        assert_shape(
+            SitePageWithPostsResponse {
+                page: page(),
+                posts: Vec::new(),
+                breadcrumbs: Vec::new(),
+            },
+            &["page", "posts", "breadcrumbs"],
+        );
+        assert_shape(
+            SitePostDetailResponse {
+                page: page(),
+                post: post(),
+                previous: None,
+                next: None,
+                breadcrumbs: Vec::new(),
+            },
+            &["page", "post", "previous", "next", "breadcrumbs"],
+        );
+        assert_shape(
+            post(),
+            &[
+                "id",
+                "page_id",
+                "title",
+                "slug",
+                "excerpt",
+                "excerpt_auto",
+                "content_markdown",
+                "word_count",
+                "reading_time_minutes",
+                "is_published",
+                "allow_comments",
+                "published_at",
+                "order_index",
+                "created_at",
+                "updated_at",
+            ],
+        );
+        assert_shape(SitePostListResponse { items: Vec::new() }, &["items"]);
+        assert_shape(
+            NavigationItemResponse {
+                id: "p1".to_string(),
+                slug: "grundlagen".to_string(),
+                label: "Grundlagen".to_string(),
+                order_index: 0,
+            },
+            &["id", "slug", "label", "order_index"],
+        );
+        assert_shape(NavigationResponse { items: Vec::new() }, &["items"]);
+        assert_shape(
+            BootstrapResponse {
+                content: serde_json::Map::new(),
+                navigation: NavigationResponse { items: Vec::new() },
+                tutorials: Vec::new(),
+                generated_at: String::new(),
+            },
+            &["content", "navigation", "tutorials", "generated_at"],
+        );
+        assert_shape(
+            LayoutBlockResponse {
+                id: "b1".to_string(),
+                name: "banner".to_string(),
+                block: json!({}),
+                updated_at: String::new(),
+            },
+            &["id", "name", "block", "updated_at"],
+        );
+        assert_shape(LayoutBlockListResponse { items: Vec::new() }, &["items"]);
+        assert_shape(
+            LayoutBlockInUseResponse {
+                error: "in use".to_string(),
+                pages: Vec::new(),
+                blocks: Vec::new(),
+            },
+            &["error", "pages", "blocks"],
+        );
+
+        assert_shape(
+            TutorialResponse {
+                id: "t1".to_string(),
+                title: "Bash".to_string(),
+                description: String::new(),
+                icon: "Terminal".to_string(),
+                color: "from-blue-500 to-cyan-600".to_string(),
+                topics: Vec::new(),
+                content: String::new(),
+                version: 1,
+                word_count: 0,
+                reading_time_minutes: 0,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+            &[
+                "id",
+                "title",
+                "description",
+                "icon",
+                "color",
+                "topics",
+                "content",
+                "version",
+                "word_count",
+                "reading_time_minutes",
+                "created_at",
+                "updated_at",
+            ],
+        );
+        assert_shape(
+            tutorial_summary(),
+            &[
+                "id",
+                "title",
+                "description",
+                "icon",
+                "color",
+                "topics",
+                "version",
+                "word_count",
+                "reading_time_minutes",
+                "created_at",
+                "updated_at",
+            ],
+        );
+
+        assert_shape(
+            UploadItemResponse {
+                upload: Upload {
+                    id: "4711".to_string(),
+                    filename: "4711.png".to_string(),
+                    original_filename: "diagramm.png".to_string(),
+                    mime_type: "image/png".to_string(),
+                    kind: UPLOAD_KIND_IMAGE.to_string(),
+                    visibility: UPLOAD_VISIBILITY_PUBLIC.to_string(),
+                    size_bytes: 1024,
+                    original_size_bytes: None,
+                    width: None,
+                    height: None,
+                    alt_text: None,
+                    caption: None,
+                    uploaded_by: None,
+                    created_at: String::new(),
+                },
+                url: "/uploads/4711.png".to_string(),
+            },
+            &[
+                "id",
+                "filename",
+                "original_filename",
+                "mime_type",
+                "kind",
+                "visibility",
+                "size_bytes",
+                "original_size_bytes",
+                "width",
+                "height",
+                "alt_text",
+                "caption",
+                "uploaded_by",
+                "created_at",
+                "url",
+            ],
+        );
+        assert_shape(
+            UploadListResponse {
+                items: Vec::new(),
+                total: 0,
+                limit: 20,
+                offset: 0,
+            },
+            &["items", "total", "limit", "offset"],
+        );
+        assert_shape(
+            UploadMetaResponse {
+                id: "4711".to_string(),
+                url: "/uploads/4711.png".to_string(),
+                alt_text: None,
+                caption: None,
+                width: None,
+                height: None,
+                variants: vec![UploadVariant {
+                    url: "/uploads/4711-320.webp".to_string(),
+                    width: Some(320),
+                    height: None,
+                }],
+            },
+            &["id", "url", "alt_text", "caption", "width", "height", "variants"],
+        );
+        assert_shape(
+            UploadQuotaResponse {
+                files_used: 0,
+                bytes_used: 0,
+                files_limit: None,
+                bytes_limit: None,
+                files_remaining: None,
+                bytes_remaining: None,
+                resets_at: String::new(),
+            },
+            &[
+                "files_used",
+                "bytes_used",
+                "files_limit",
+                "bytes_limit",
+                "files_remaining",
+                "bytes_remaining",
+                "resets_at",
+            ],
+        );
+        assert_shape(
+            UploadInUseResponse {
+                error: "in use".to_string(),
+                references: Vec::new(),
+            },
+            &["error", "references"],
+        );
+        assert_shape(
+            UploadSessionResponse {
+                id: "u1".to_string(),
+                chunk_size: 1024,
+                chunk_count: 1,
+                received_chunks: Vec::new(),
+                expires_at: String::new(),
+            },
+            &["id", "chunk_size", "chunk_count", "received_chunks", "expires_at"],
+        );
+
+        assert_shape(
+            LoginResponse {
+                token: "jwt".to_string(),
+                user: user(),
+            },
+            &["token", "user"],
+        );
+        assert_shape(user(), &["username", "role"]);
+    }
+}
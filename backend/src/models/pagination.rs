@@ -0,0 +1,326 @@
+//! Pagination shared by the list endpoints.
+//!
+//! Lists answer with a [`Paginated`] envelope:
+//!
+//! ```json
+//! { "items": [...], "total": 120, "limit": 50, "offset": 50, "has_more": true }
+//! ```
+//!
+//! `total` counts every row matching the request's filters, not just the
+//! returned ones. Endpoints that used to answer with a bare array still do
+//! so for `?envelope=false`; that escape hatch goes away in the next release.
+//!
+//! Comments and the public post feed also page by [`Cursor`], reading
+//! [`CursorPageParams`]: their pages carry a `next_cursor`, and `?cursor=`
+//! continues after the row it names. Unlike offsets, cursors neither repeat
+//! nor skip rows when new ones arrive between two requests. Offsets keep
+//! working for admin tooling. Every other list refuses a `cursor`.
+
+use crate::error::AppError;
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 50;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Longest cursor accepted; real ones stay well below.
+const MAX_CURSOR_LEN: usize = 512;
+
+/// The position of the last row of a page in a newest-first listing: its
+/// timestamp and, to break ties between equal timestamps, its id.
+///
+/// Clients get it as opaque base64 and must not build their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: String,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn new(created_at: &str, id: &str) -> Self {
+        Self {
+            created_at: created_at.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        Base64UrlUnpadded::encode_string(format!("{}|{}", self.created_at, self.id).as_bytes())
+    }
+
+    /// Reads a cursor from [`Cursor::encode`]; anything else, including a
+    /// cursor edited by hand, is an error for the client.
+    pub fn decode(encoded: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::invalid_field("cursor", "Invalid pagination cursor");
+        if encoded.len() > MAX_CURSOR_LEN {
+            return Err(invalid());
+        }
+
+        let bytes = Base64UrlUnpadded::decode_vec(encoded).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+        // Timestamps never contain `|`; ids might, so split at the first one
+        match decoded.split_once('|') {
+            Some((created_at, id)) if !created_at.is_empty() && !id.is_empty() => {
+                Ok(Self::new(created_at, id))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// `limit`, `offset` and `envelope` from the query string, with the limit
+/// clamped to `1..=MAX_PAGE_LIMIT` and negative offsets treated as zero.
+///
+/// For lists that page by offset alone: a `cursor` is refused rather than
+/// ignored, so a client never walks the same page over and over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageParams {
+    pub limit: i64,
+    pub offset: i64,
+    pub envelope: bool,
+}
+
+impl Default for PageParams {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PAGE_LIMIT,
+            offset: 0,
+            envelope: true,
+        }
+    }
+}
+
+/// [`PageParams`] plus the `cursor` of a list that also pages by [`Cursor`].
+/// A cursor replaces the offset, which is then zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CursorPageParams {
+    pub page: PageParams,
+    pub cursor: Option<Cursor>,
+}
+
+#[derive(Deserialize)]
+struct RawPageParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    cursor: Option<String>,
+    envelope: Option<bool>,
+}
+
+impl RawPageParams {
+    async fn from_parts<S: Send + Sync>(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let Query(raw) = Query::<RawPageParams>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| AppError::validation(rejection.body_text()))?;
+        Ok(raw)
+    }
+
+    fn page(&self, has_cursor: bool) -> PageParams {
+        PageParams {
+            limit: self
+                .limit
+                .unwrap_or(DEFAULT_PAGE_LIMIT)
+                .clamp(1, MAX_PAGE_LIMIT),
+            offset: if has_cursor {
+                0
+            } else {
+                self.offset.unwrap_or(0).max(0)
+            },
+            envelope: self.envelope.unwrap_or(true),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for PageParams {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let raw = RawPageParams::from_parts(parts, state).await?;
+        if raw.cursor.is_some() {
+            return Err(AppError::invalid_field(
+                "cursor",
+                "This list does not page by cursor; use offset instead",
+            ));
+        }
+        Ok(raw.page(false))
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CursorPageParams {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let raw = RawPageParams::from_parts(parts, state).await?;
+        let cursor = raw.cursor.as_deref().map(Cursor::decode).transpose()?;
+        Ok(Self {
+            page: raw.page(cursor.is_some()),
+            cursor,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, total: i64, limit: i64, offset: i64) -> Self {
+        let has_more = offset + (items.len() as i64) < total;
+        Self {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+            next_cursor: None,
+        }
+    }
+
+    /// A page of a cursor listing, from up to `limit + 1` rows: the extra
+    /// row is dropped and only tells that another page follows, which then
+    /// starts after the [`Cursor`] `cursor_of` gives for the last kept row.
+    pub fn from_overfetch(
+        mut items: Vec<T>,
+        total: i64,
+        limit: i64,
+        offset: i64,
+        cursor_of: impl Fn(&T) -> Cursor,
+    ) -> Self {
+        let has_more = items.len() as i64 > limit;
+        items.truncate(limit.max(0) as usize);
+        let next_cursor = items
+            .last()
+            .filter(|_| has_more)
+            .map(|last| cursor_of(last).encode());
+
+        Self {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+            next_cursor,
+        }
+    }
+
+    /// The page as requested by `params`, or its bare items for clients
+    /// that asked for the old array shape.
+    pub fn into_listing(self, params: &PageParams) -> Listing<T> {
+        if params.envelope {
+            Listing::Page(self)
+        } else {
+            Listing::Bare(self.items)
+        }
+    }
+}
+
+/// A [`Paginated`] page, or its items alone for `?envelope=false`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    Page(Paginated<T>),
+    Bare(Vec<T>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn params<P: FromRequestParts<(), Rejection = AppError>>(
+        query: &str,
+    ) -> Result<P, AppError> {
+        let (mut parts, _) = Request::builder()
+            .uri(format!("/api/tutorials{query}"))
+            .body(())
+            .unwrap()
+            .into_parts();
+        P::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_page_params_are_clamped() {
+        assert_eq!(
+            params::<PageParams>("").await.unwrap(),
+            PageParams::default()
+        );
+
+        let page: PageParams = params("?limit=5000&offset=-3&sort=top").await.unwrap();
+        assert_eq!((page.limit, page.offset), (MAX_PAGE_LIMIT, 0));
+
+        let page: PageParams = params("?limit=0&offset=20&envelope=false").await.unwrap();
+        assert_eq!((page.limit, page.offset, page.envelope), (1, 20, false));
+
+        let err = params::<PageParams>("?limit=viele").await.unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+    }
+
+    #[tokio::test]
+    async fn test_cursors_replace_the_offset_where_supported() {
+        let cursor = Cursor::new("2024-05-01 10:00:00", "c-17");
+        let query = format!("?offset=40&cursor={}", cursor.encode());
+        let params_with_cursor: CursorPageParams = params(&query).await.unwrap();
+        assert_eq!(params_with_cursor.page.offset, 0);
+        assert_eq!(params_with_cursor.cursor, Some(cursor));
+
+        let without: CursorPageParams = params("?offset=40").await.unwrap();
+        assert_eq!((without.page.offset, without.cursor), (40, None));
+
+        let err = params::<PageParams>(&query).await.unwrap_err();
+        assert_eq!(err.field(), Some("cursor"));
+    }
+
+    #[test]
+    fn test_cursors_round_trip_and_reject_tampering() {
+        let cursor = Cursor::new("2024-05-01 10:00:00", "id|with|bars");
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let tampered = [
+            "not base64!".to_string(),
+            Base64UrlUnpadded::encode_string(b"no separator"),
+            Base64UrlUnpadded::encode_string(b"|missing-time"),
+            Base64UrlUnpadded::encode_string(b"2024-05-01|"),
+            Base64UrlUnpadded::encode_string(&[0xff, 0xfe, b'|', b'x']),
+            "A".repeat(MAX_CURSOR_LEN + 1),
+        ];
+        for encoded in tampered {
+            let err = Cursor::decode(&encoded).unwrap_err();
+            assert_eq!(err.field(), Some("cursor"), "{encoded}");
+        }
+    }
+
+    #[test]
+    fn test_has_more_follows_the_total() {
+        assert!(Paginated::new(vec![1, 2], 5, 2, 2).has_more);
+        assert!(!Paginated::new(vec![5], 5, 2, 4).has_more);
+        assert!(!Paginated::<i32>::new(Vec::new(), 5, 2, 10).has_more);
+
+        let cursor_of = |n: &i32| Cursor::new("2024-05-01", &n.to_string());
+        let page = Paginated::from_overfetch(vec![9, 8, 7], 5, 2, 0, cursor_of);
+        assert_eq!(page.items, vec![9, 8]);
+        assert!(page.has_more);
+        assert_eq!(
+            page.next_cursor,
+            Some(Cursor::new("2024-05-01", "8").encode())
+        );
+        let page = Paginated::from_overfetch(vec![6, 5], 5, 2, 0, cursor_of);
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+
+        let bare = Paginated::new(vec![1], 1, 50, 0).into_listing(&PageParams {
+            envelope: false,
+            ..PageParams::default()
+        });
+        assert_eq!(serde_json::to_value(bare).unwrap(), serde_json::json!([1]));
+    }
+}
@@ -1,19 +1,44 @@
+use crate::models::TutorialSummaryResponse;
+use crate::utils::json_diff::JsonDiff;
+use crate::validation::{self, pages, Validate, Violations};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+/// Every content section the API accepts. Seeded defaults must use these names.
+pub const SITE_CONTENT_SECTIONS: &[&str] = &[
+    "hero",
+    "tutorial_section",
+    "header",
+    "footer",
+    "site_meta",
+    "stats",
+    "cta_section",
+    "settings",
+    "login",
+    "grundlagen_page",
+];
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct SiteContent {
     pub section: String,
     pub content_json: String,
-    pub updated_at: String,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<String>,
+    pub revision: i64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SiteContentResponse {
     pub section: String,
     pub content: Value,
-    pub updated_at: String,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<String>,
+    /// Counts the writes to the section; pass it as `expected_revision` to
+    /// patch without overwriting a concurrent change.
+    pub revision: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -26,6 +51,229 @@ pub struct UpdateSiteContentRequest {
     pub content: Value,
 }
 
+/// Custom content section registered at runtime, on top of the built-ins.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ContentSection {
+    pub name: String,
+    pub description: String,
+    pub schema_json: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentSectionResponse {
+    pub name: String,
+    pub description: String,
+    pub schema: Option<Value>,
+    pub builtin: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentSectionListResponse {
+    pub items: Vec<ContentSectionResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateContentSectionRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub schema: Option<Value>,
+}
+
+/// Replaces description and schema; omitting `schema` removes it.
+#[derive(Debug, Deserialize)]
+pub struct UpdateContentSectionRequest {
+    #[serde(default)]
+    pub description: String,
+    pub schema: Option<Value>,
+}
+
+/// Preview of what saving `content` would change.
+#[derive(Debug, Serialize)]
+pub struct SiteContentDiffResponse {
+    pub section: String,
+    /// `updated_at` and `revision` of the stored section the diff was
+    /// computed against, so the confirm step can pass them as
+    /// `expected_updated_at` or `expected_revision`.
+    pub updated_at: Option<DateTime<Utc>>,
+    pub revision: Option<i64>,
+    #[serde(flatten)]
+    pub diff: JsonDiff,
+}
+
+/// One section in a content export. `updated_at` is informational and is
+/// ignored on import.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SiteContentExportEntry {
+    pub section: String,
+    pub content: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+/// Export/import document. Shares the `site_content` key with the CLI bundle,
+/// so a CLI export can be imported through the API as well.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SiteContentBundle {
+    pub site_content: Vec<SiteContentExportEntry>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct SiteContentImportReport {
+    pub dry_run: bool,
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Class of a broken internal reference, used to group the health report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrokenReferenceKind {
+    Upload,
+    Section,
+    Route,
+    Page,
+    Post,
+    Tutorial,
+}
+
+/// Where a reference lives: a content section, a page, a post or a tutorial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceSource {
+    SiteContent,
+    Page,
+    Post,
+    Tutorial,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenReference {
+    pub source: ReferenceSource,
+    /// Section name, page, post or tutorial id.
+    pub id: String,
+    /// Column the reference was found in (`hero`, `layout`, `og_image`,
+    /// `content_markdown`, `content`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// JSON Pointer inside the document, for JSON sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    /// 1-based line, for markdown sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub reference: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ContentHealthScanned {
+    pub site_content: usize,
+    pub pages: usize,
+    pub posts: usize,
+    pub tutorials: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ContentHealthReport {
+    pub scanned: ContentHealthScanned,
+    pub broken_count: usize,
+    /// Set when more broken references exist than the report lists.
+    pub truncated: bool,
+    pub broken: BTreeMap<BrokenReferenceKind, Vec<BrokenReference>>,
+}
+
+/// Typed view of the "settings" content section. Every field has a default,
+/// so a missing section or missing key never breaks the features that read it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SiteSettings {
+    /// Whether posts offer a PDF download.
+    pub pdf_enabled: bool,
+    /// Icon names accepted in addition to the built-in set.
+    pub allowed_icons: Vec<String>,
+    /// Terms that cause a comment to be rejected (case-insensitive).
+    pub comment_blocklist: Vec<String>,
+    /// Shown instead of public pages while set.
+    pub maintenance_message: Option<String>,
+    /// Whether `robots.txt` lets crawlers index the site; off for staging.
+    pub indexing_enabled: bool,
+}
+
+impl Default for SiteSettings {
+    fn default() -> Self {
+        Self {
+            pdf_enabled: true,
+            allowed_icons: Vec::new(),
+            comment_blocklist: Vec::new(),
+            maintenance_message: None,
+            indexing_enabled: true,
+        }
+    }
+}
+
+/// Maintenance mode as stored in `app_metadata`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    /// Shown to blocked visitors; the settings section's message otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMaintenanceRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Earlier revision of a content section, archived when it was overwritten.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SiteContentVersion {
+    pub version: i64,
+    pub section: String,
+    pub content_json: String,
+    /// When this revision was originally saved.
+    pub updated_at: String,
+    /// Who saved this revision, copied from `site_content.updated_by`.
+    pub updated_by: Option<String>,
+    pub archived_at: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SiteContentHistoryItem {
+    pub version: i64,
+    pub updated_at: String,
+    pub updated_by: Option<String>,
+    pub archived_at: String,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteContentHistoryResponse {
+    pub section: String,
+    pub items: Vec<SiteContentHistoryItem>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteContentVersionResponse {
+    pub version: i64,
+    pub section: String,
+    pub content: Value,
+    pub updated_at: String,
+    pub updated_by: Option<String>,
+    pub archived_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct SitePage {
     pub id: String,
@@ -36,10 +284,15 @@ pub struct SitePage {
     pub show_in_nav: bool,
     pub order_index: i64,
     pub is_published: bool,
+    pub visibility: String,
     pub hero_json: String,
     pub layout_json: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub og_image: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,27 +305,46 @@ pub struct SitePageResponse {
     pub show_in_nav: bool,
     pub order_index: i64,
     pub is_published: bool,
+    pub visibility: String,
     pub hero: Value,
     pub layout: Value,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct SitePageListResponse {
-    pub items: Vec<SitePageResponse>,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub og_image: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SitePageWithPostsResponse {
     pub page: SitePageResponse,
     pub posts: Vec<SitePostResponse>,
+    pub breadcrumbs: Vec<Breadcrumb>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SitePostDetailResponse {
     pub page: SitePageResponse,
     pub post: SitePostResponse,
+    pub previous: Option<SitePostNeighbor>,
+    pub next: Option<SitePostNeighbor>,
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+/// One entry of a public breadcrumb trail; `path` is relative to the site root.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Breadcrumb {
+    pub label: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SitePostNeighbor {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,10 +358,23 @@ pub struct CreateSitePageRequest {
     pub order_index: Option<i64>,
     #[serde(default)]
     pub is_published: bool,
+    #[serde(default = "default_page_visibility")]
+    pub visibility: String,
     #[serde(default)]
     pub hero: Value,
     #[serde(default)]
     pub layout: Value,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub og_image: Option<String>,
+}
+
+/// Pages are visible to everyone unless restricted to signed-in users.
+pub const PAGE_VISIBILITY_PUBLIC: &str = "public";
+pub const PAGE_VISIBILITY_AUTHENTICATED: &str = "authenticated";
+
+fn default_page_visibility() -> String {
+    PAGE_VISIBILITY_PUBLIC.to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -101,8 +386,103 @@ pub struct UpdateSitePageRequest {
     pub show_in_nav: Option<bool>,
     pub order_index: Option<i64>,
     pub is_published: Option<bool>,
+    pub visibility: Option<String>,
     pub hero: Option<Value>,
     pub layout: Option<Value>,
+    pub meta_title: Option<Option<String>>,
+    pub meta_description: Option<Option<String>>,
+    pub og_image: Option<Option<String>>,
+}
+
+fn validate_page_slug(slug: &str) -> Result<(), String> {
+    validation::validate_slug(&pages::normalize_slug(slug)?)
+}
+
+fn validate_nav_label(label: &str) -> Result<Option<String>, String> {
+    pages::normalize_nav_label(Some(label.to_string()))
+}
+
+fn validate_meta_title(title: &str) -> Result<Option<String>, String> {
+    pages::normalize_meta_title(Some(title.to_string()))
+}
+
+fn validate_meta_description(description: &str) -> Result<Option<String>, String> {
+    pages::normalize_meta_description(Some(description.to_string()))
+}
+
+fn validate_og_image(image: &str) -> Result<Option<String>, String> {
+    pages::normalize_og_image(Some(image.to_string()))
+}
+
+impl Validate for CreateSitePageRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.field("slug", "Slug", &self.slug).rule(validate_page_slug);
+        v.field("title", "Title", &self.title)
+            .rule(pages::normalize_title);
+        v.optional("description", "Description", self.description.as_deref())
+            .rule(pages::normalize_description);
+        v.optional("nav_label", "Navigation label", self.nav_label.as_deref())
+            .rule(validate_nav_label);
+        v.field("visibility", "Visibility", &self.visibility)
+            .rule(pages::normalize_visibility);
+        v.check("hero", pages::validate_hero(&self.hero));
+        v.check("layout", pages::validate_layout(&self.layout));
+        v.optional("meta_title", "Meta title", self.meta_title.as_deref())
+            .rule(validate_meta_title);
+        v.optional(
+            "meta_description",
+            "Meta description",
+            self.meta_description.as_deref(),
+        )
+        .rule(validate_meta_description);
+        v.optional("og_image", "Share image", self.og_image.as_deref())
+            .rule(validate_og_image);
+    }
+}
+
+/// Missing fields keep their stored value; sent ones obey the rules of
+/// [`CreateSitePageRequest`].
+impl Validate for UpdateSitePageRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.optional("slug", "Slug", self.slug.as_deref())
+            .rule(validate_page_slug);
+        v.optional("title", "Title", self.title.as_deref())
+            .rule(pages::normalize_title);
+        v.optional("description", "Description", self.description.as_deref())
+            .rule(pages::normalize_description);
+        v.optional(
+            "nav_label",
+            "Navigation label",
+            self.nav_label.as_ref().and_then(Option::as_deref),
+        )
+        .rule(validate_nav_label);
+        v.optional("visibility", "Visibility", self.visibility.as_deref())
+            .rule(pages::normalize_visibility);
+        if let Some(hero) = &self.hero {
+            v.check("hero", pages::validate_hero(hero));
+        }
+        if let Some(layout) = &self.layout {
+            v.check("layout", pages::validate_layout(layout));
+        }
+        v.optional(
+            "meta_title",
+            "Meta title",
+            self.meta_title.as_ref().and_then(Option::as_deref),
+        )
+        .rule(validate_meta_title);
+        v.optional(
+            "meta_description",
+            "Meta description",
+            self.meta_description.as_ref().and_then(Option::as_deref),
+        )
+        .rule(validate_meta_description);
+        v.optional(
+            "og_image",
+            "Share image",
+            self.og_image.as_ref().and_then(Option::as_deref),
+        )
+        .rule(validate_og_image);
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -112,13 +492,16 @@ pub struct SitePost {
     pub title: String,
     pub slug: String,
     pub excerpt: String,
+    pub excerpt_auto: bool,
     pub content_markdown: String,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
     pub is_published: bool,
     pub allow_comments: bool,
-    pub published_at: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
     pub order_index: i64,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -128,13 +511,16 @@ pub struct SitePostResponse {
     pub title: String,
     pub slug: String,
     pub excerpt: String,
+    pub excerpt_auto: bool,
     pub content_markdown: String,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
     pub is_published: bool,
     pub allow_comments: bool,
-    pub published_at: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
     pub order_index: i64,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -152,7 +538,7 @@ pub struct CreateSitePostRequest {
     pub is_published: bool,
     #[serde(default = "default_allow_comments")]
     pub allow_comments: bool,
-    pub published_at: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
     pub order_index: Option<i64>,
 }
 
@@ -160,7 +546,7 @@ fn default_allow_comments() -> bool {
     true
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct UpdateSitePostRequest {
     pub title: Option<String>,
     pub slug: Option<String>,
@@ -168,10 +554,77 @@ pub struct UpdateSitePostRequest {
     pub content_markdown: Option<String>,
     pub is_published: Option<bool>,
     pub allow_comments: Option<bool>,
-    pub published_at: Option<Option<String>>,
+    pub published_at: Option<Option<DateTime<Utc>>>,
     pub order_index: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MoveSitePostRequest {
+    pub target_page_id: String,
+    #[serde(default)]
+    pub keep_slug: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkPostKeyword {
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BulkPostSelection {
+    All(BulkPostKeyword),
+    Ids(Vec<String>),
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkPublishAction {
+    Publish,
+    Unpublish,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkPublishPostsRequest {
+    pub ids: BulkPostSelection,
+    pub action: BulkPublishAction,
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow, PartialEq)]
+pub struct PostArchiveEntry {
+    pub year: i64,
+    pub month: i64,
+    pub count: i64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicPostSort {
+    #[default]
+    PublishedAt,
+    Title,
+}
+
+/// A published post in cross-page listings, with its page for link building.
+#[derive(Debug, Serialize, FromRow)]
+pub struct PublicPostSummary {
+    pub id: String,
+    pub title: String,
+    pub slug: String,
+    pub excerpt: String,
+    pub published_at: Option<String>,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
+    pub page_slug: String,
+    pub page_title: String,
+    /// The publication time, or the creation time of posts without one;
+    /// what the feed sorts and pages by.
+    #[serde(skip)]
+    pub sorted_at: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct NavigationItemResponse {
     pub id: String,
@@ -184,3 +637,136 @@ pub struct NavigationItemResponse {
 pub struct NavigationResponse {
     pub items: Vec<NavigationItemResponse>,
 }
+
+/// Everything the SPA needs for its first paint, in one response.
+#[derive(Debug, Serialize)]
+pub struct BootstrapResponse {
+    /// Every stored content section, keyed by section name.
+    pub content: serde_json::Map<String, Value>,
+    pub navigation: NavigationResponse,
+    pub tutorials: Vec<TutorialSummaryResponse>,
+    pub generated_at: String,
+}
+
+/// Reusable layout block that pages embed via `{ "type": "ref", "block": ... }`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct LayoutBlock {
+    pub id: String,
+    pub name: String,
+    pub block_json: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutBlockResponse {
+    pub id: String,
+    pub name: String,
+    pub block: Value,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutBlockListResponse {
+    pub items: Vec<LayoutBlockResponse>,
+}
+
+/// Body of the `409 Conflict` returned when renaming or deleting a layout
+/// block that is still referenced.
+#[derive(Debug, Serialize)]
+pub struct LayoutBlockInUseResponse {
+    pub error: String,
+    /// Slugs of referencing pages.
+    pub pages: Vec<String>,
+    /// Names of library blocks that alias this one.
+    pub blocks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateLayoutBlockRequest {
+    pub name: String,
+    pub block: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateLayoutBlockRequest {
+    pub name: Option<String>,
+    pub block: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn broken_fields<T: Validate + serde::de::DeserializeOwned>(body: Value) -> Vec<String> {
+        let request: T = serde_json::from_value(body).unwrap();
+        match request.check() {
+            Ok(()) => Vec::new(),
+            Err(err) => err.field_errors().iter().map(|e| e.field.clone()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_page_rules() {
+        let cases: &[(Value, &[&str])] = &[
+            (json!({ "slug": "start", "title": "Start" }), &[]),
+            (
+                json!({ "slug": " Start ", "title": "Start", "nav_label": " " }),
+                &[],
+            ),
+            (json!({ "slug": "", "title": "Start" }), &["slug"]),
+            (json!({ "slug": "a b", "title": "" }), &["slug", "title"]),
+            (
+                json!({ "slug": "start", "title": "Start", "visibility": "geheim" }),
+                &["visibility"],
+            ),
+            (
+                json!({ "slug": "start", "title": "Start", "hero": "nope" }),
+                &["hero"],
+            ),
+            (
+                json!({
+                    "slug": "start",
+                    "title": "Start",
+                    "meta_title": "x".repeat(121),
+                    "og_image": "http://example.com/a.png",
+                }),
+                &["meta_title", "og_image"],
+            ),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(
+                broken_fields::<CreateSitePageRequest>(body.clone()),
+                *expected,
+                "{body}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_page_update_rules() {
+        let cases: &[(Value, &[&str])] = &[
+            (json!({}), &[]),
+            (json!({ "nav_label": null, "og_image": null }), &[]),
+            (json!({ "og_image": "/uploads/bild.png" }), &[]),
+            (json!({ "slug": "-", "layout": 1 }), &["slug", "layout"]),
+            (
+                json!({ "title": " ", "nav_label": "x".repeat(101) }),
+                &["title", "nav_label"],
+            ),
+            (
+                json!({ "meta_description": "x".repeat(301) }),
+                &["meta_description"],
+            ),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(
+                broken_fields::<UpdateSitePageRequest>(body.clone()),
+                *expected,
+                "{body}"
+            );
+        }
+    }
+}
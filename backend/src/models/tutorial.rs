@@ -1,3 +1,9 @@
+use crate::validation::tutorials::{
+    sanitize_topics, validate_color, validate_icon, validate_tutorial_id, MAX_CONTENT_LEN,
+    MAX_DESCRIPTION_LEN, MAX_TITLE_LEN,
+};
+use crate::validation::{Validate, Violations};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::convert::TryFrom;
@@ -12,8 +18,10 @@ pub struct Tutorial {
     pub topics: String,
     pub content: String,
     pub version: i64,
-    pub created_at: String,
-    pub updated_at: String,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -37,6 +45,48 @@ pub struct UpdateTutorialRequest {
     pub content: Option<String>,
 }
 
+impl Validate for CreateTutorialRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.optional("id", "Tutorial ID", self.id.as_deref().map(str::trim))
+            .rule(validate_tutorial_id);
+        v.field("title", "Title", &self.title)
+            .required()
+            .max_len(MAX_TITLE_LEN);
+        v.field("description", "Description", &self.description)
+            .required()
+            .max_len(MAX_DESCRIPTION_LEN);
+        v.field("content", "Content", &self.content)
+            .required()
+            .max_bytes(MAX_CONTENT_LEN);
+        v.field("icon", "Icon", &self.icon).rule(validate_icon);
+        v.field("color", "Color", &self.color).rule(validate_color);
+        v.check("topics", sanitize_topics(&self.topics));
+    }
+}
+
+/// Missing fields keep their stored value; sent ones obey the rules of
+/// [`CreateTutorialRequest`].
+impl Validate for UpdateTutorialRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.optional("title", "Title", self.title.as_deref())
+            .required()
+            .max_len(MAX_TITLE_LEN);
+        v.optional("description", "Description", self.description.as_deref())
+            .required()
+            .max_len(MAX_DESCRIPTION_LEN);
+        v.optional("content", "Content", self.content.as_deref())
+            .required()
+            .max_bytes(MAX_CONTENT_LEN);
+        v.optional("icon", "Icon", self.icon.as_deref())
+            .rule(validate_icon);
+        v.optional("color", "Color", self.color.as_deref())
+            .rule(validate_color);
+        if let Some(topics) = &self.topics {
+            v.check("topics", sanitize_topics(topics));
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct TutorialResponse {
     pub id: String,
@@ -47,8 +97,10 @@ pub struct TutorialResponse {
     pub topics: Vec<String>,
     pub content: String,
     pub version: i64,
-    pub created_at: String,
-    pub updated_at: String,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -60,8 +112,10 @@ pub struct TutorialSummaryResponse {
     pub color: String,
     pub topics: Vec<String>,
     pub version: i64,
-    pub created_at: String,
-    pub updated_at: String,
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 impl TryFrom<Tutorial> for TutorialResponse {
@@ -89,6 +143,8 @@ impl TryFrom<Tutorial> for TutorialResponse {
             topics,
             content: tutorial.content,
             version: tutorial.version,
+            word_count: tutorial.word_count,
+            reading_time_minutes: tutorial.reading_time_minutes,
             created_at: tutorial.created_at,
             updated_at: tutorial.updated_at,
         })
@@ -117,18 +173,109 @@ impl TryFrom<Tutorial> for TutorialSummaryResponse {
             color: tutorial.color,
             topics,
             version: tutorial.version,
+            word_count: tutorial.word_count,
+            reading_time_minutes: tutorial.reading_time_minutes,
             created_at: tutorial.created_at,
             updated_at: tutorial.updated_at,
         })
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn broken_fields<T: Validate + serde::de::DeserializeOwned>(
+        body: serde_json::Value,
+    ) -> Vec<String> {
+        let request: T = serde_json::from_value(body).unwrap();
+        match request.check() {
+            Ok(()) => Vec::new(),
+            Err(err) => err.field_errors().iter().map(|e| e.field.clone()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_create_rules() {
+        let valid = json!({
+            "title": "Pakete verwalten",
+            "description": "apt, dnf und pacman",
+            "icon": "Terminal",
+            "color": "from-blue-500 to-cyan-600",
+            "topics": ["Pakete"],
+            "content": "sudo apt install htop",
+        });
+        let cases: &[(&str, serde_json::Value, &[&str])] = &[
+            ("valid", json!({}), &[]),
+            ("custom id", json!({ "id": " pakete-1 " }), &[]),
+            ("bad id", json!({ "id": "pakete/1" }), &["id"]),
+            ("blank title", json!({ "title": "  " }), &["title"]),
+            (
+                "long title",
+                json!({ "title": "x".repeat(MAX_TITLE_LEN + 1) }),
+                &["title"],
+            ),
+            (
+                "umlaut title",
+                json!({ "title": "ü".repeat(MAX_TITLE_LEN) }),
+                &[],
+            ),
+            (
+                "long content",
+                json!({ "content": "x".repeat(MAX_CONTENT_LEN + 1) }),
+                &["content"],
+            ),
+            (
+                "long umlaut content",
+                json!({ "content": "ü".repeat(MAX_CONTENT_LEN / 2 + 1) }),
+                &["content"],
+            ),
+            ("unknown icon", json!({ "icon": "Toaster" }), &["icon"]),
+            ("bad color", json!({ "color": "bg-red-500" }), &["color"]),
+            ("no topics", json!({ "topics": [" "] }), &["topics"]),
+            (
+                "everything",
+                json!({ "title": "", "description": "", "content": "", "icon": "", "color": "" }),
+                &["title", "description", "content", "icon", "color"],
+            ),
+        ];
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UploadResponse {
-    pub url: String,
+        for (name, overrides, expected) in cases {
+            let mut body = valid.clone();
+            for (key, value) in overrides.as_object().unwrap() {
+                body[key] = value.clone();
+            }
+            assert_eq!(
+                broken_fields::<CreateTutorialRequest>(body),
+                *expected,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_rules() {
+        let cases: &[(serde_json::Value, &[&str])] = &[
+            (json!({}), &[]),
+            (json!({ "title": "Neu", "topics": ["A", "b"] }), &[]),
+            (json!({ "title": "" }), &["title"]),
+            (
+                json!({ "description": " ", "icon": "Shield" }),
+                &["description"],
+            ),
+            (
+                json!({ "topics": ["A", "a"], "color": "to-red-500" }),
+                &["color", "topics"],
+            ),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(
+                broken_fields::<UpdateTutorialRequest>(body.clone()),
+                *expected,
+                "{body}"
+            );
+        }
+    }
 }
@@ -0,0 +1,220 @@
+use crate::models::ReferenceSource;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// `kind` of uploads embedded as images.
+pub const UPLOAD_KIND_IMAGE: &str = "image";
+/// `kind` of downloadable files below `/uploads/files/`.
+pub const UPLOAD_KIND_FILE: &str = "file";
+
+/// `visibility` of uploads served to everyone.
+pub const UPLOAD_VISIBILITY_PUBLIC: &str = "public";
+/// `visibility` of uploads for unpublished content, kept below
+/// `/uploads/private/` and only served to signed-in users.
+pub const UPLOAD_VISIBILITY_DRAFT: &str = "draft";
+
+/// Metadata recorded for every file stored under `/uploads`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Upload {
+    pub id: String,
+    /// Name of the file on disk, also the last segment of its URL.
+    pub filename: String,
+    pub original_filename: String,
+    pub mime_type: String,
+    /// [`UPLOAD_KIND_IMAGE`] or [`UPLOAD_KIND_FILE`].
+    pub kind: String,
+    /// [`UPLOAD_VISIBILITY_PUBLIC`] or [`UPLOAD_VISIBILITY_DRAFT`].
+    pub visibility: String,
+    /// Size of the stored file.
+    pub size_bytes: i64,
+    /// Size as uploaded, when metadata stripping or sanitizing changed the
+    /// file.
+    pub original_size_bytes: Option<i64>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
+    /// `None` for files that predate upload tracking.
+    pub uploaded_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadItemResponse {
+    #[serde(flatten)]
+    pub upload: Upload,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadListResponse {
+    pub items: Vec<UploadItemResponse>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Outcome for one file of a `POST /api/upload` request: `id` and `url` when
+/// it was stored, `error` otherwise.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadResult {
+    pub original_filename: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Body of `PUT /api/admin/uploads/{id}`. Both fields are replaced; a
+/// missing or blank value clears it.
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateUploadMetaRequest {
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+/// Public metadata of an upload, used to fill in alt text for images that
+/// content references by URL.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadMetaResponse {
+    pub id: String,
+    pub url: String,
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    /// Other stored renditions of the same image, such as the original kept
+    /// next to a WebP conversion.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<UploadVariant>,
+}
+
+/// Uploads of one user on the current UTC day, from
+/// `GET /api/admin/uploads/quota`. Limits and remaining amounts are `null`
+/// when no limit is configured.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadQuotaResponse {
+    pub files_used: u64,
+    pub bytes_used: u64,
+    pub files_limit: Option<u64>,
+    pub bytes_limit: Option<u64>,
+    pub files_remaining: Option<u64>,
+    pub bytes_remaining: Option<u64>,
+    /// Start of the next UTC day, when the usage starts over.
+    pub resets_at: String,
+}
+
+/// One stored rendition of an image and its pixel size.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadVariant {
+    pub url: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+}
+
+/// A place that links to an upload.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadReference {
+    pub source: ReferenceSource,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+/// Body of the `409 Conflict` returned when deleting an upload still in use.
+#[derive(Debug, Serialize)]
+pub struct UploadInUseResponse {
+    pub error: String,
+    pub references: Vec<UploadReference>,
+}
+
+/// A resumable upload in progress. Chunks are kept on local disk until the
+/// session is completed or expires.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UploadSession {
+    pub id: String,
+    pub file_name: String,
+    pub mime_type: String,
+    /// Size of the assembled file.
+    pub size_bytes: i64,
+    /// Size of every chunk but the last.
+    pub chunk_size: i64,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+/// Body of `POST /api/upload/sessions`.
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadSessionRequest {
+    pub file_name: String,
+    pub size_bytes: i64,
+    pub mime_type: String,
+}
+
+/// State of an upload session. `received_chunks` lists the chunk numbers
+/// already stored, so a client can resume by sending the rest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadSessionResponse {
+    pub id: String,
+    pub chunk_size: i64,
+    pub chunk_count: i64,
+    pub received_chunks: Vec<i64>,
+    pub expires_at: String,
+}
+
+/// Body of `POST /api/upload/sessions/{id}/complete`. `alt_text`, `caption`
+/// and `visibility` apply to images, as in `POST /api/upload`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteUploadSessionRequest {
+    /// Hex-encoded SHA-256 of the whole file.
+    pub sha256: String,
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+/// An upload no content links to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrphanUpload {
+    /// `None` for files in storage without a row in the uploads table.
+    pub id: Option<String>,
+    pub filename: String,
+    pub url: String,
+    pub original_filename: Option<String>,
+    pub size_bytes: i64,
+    pub created_at: Option<String>,
+    pub age_seconds: Option<i64>,
+    /// Too new to be deleted: content using it may not be saved yet. Files
+    /// without a row have no known age and always count as new.
+    pub in_grace_period: bool,
+}
+
+/// Response of `GET|POST /api/admin/uploads/orphans`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OrphanUploadReport {
+    /// The first orphans found, up to a fixed limit.
+    pub items: Vec<OrphanUpload>,
+    /// All orphans, including those not listed.
+    pub total: usize,
+    /// Bytes freed by deleting every orphan outside the grace period.
+    pub reclaimable_bytes: i64,
+    pub grace_period_hours: u32,
+    /// Orphans removed by this request, with `delete=true`.
+    pub deleted: usize,
+}
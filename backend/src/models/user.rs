@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -8,7 +9,11 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub role: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    /// Bumped on every password change; tokens carrying an older one are
+    /// revoked.
+    #[serde(default, skip_serializing)]
+    pub token_version: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,6 +22,12 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub token: String,
@@ -0,0 +1,30 @@
+use crate::db::DbPool;
+use crate::models::{AuditEntry, NewAuditEntry};
+use sqlx;
+
+pub async fn insert_entry(pool: &DbPool, entry: &NewAuditEntry) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO audit_log (username, method, path, route, status, latency_ms)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&entry.username)
+    .bind(&entry.method)
+    .bind(&entry.path)
+    .bind(&entry.route)
+    .bind(i64::from(entry.status))
+    .bind(i64::try_from(entry.latency_ms).unwrap_or(i64::MAX))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Most recent entries first.
+pub async fn list_entries(pool: &DbPool, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, username, method, path, route, status, latency_ms, created_at
+         FROM audit_log ORDER BY id DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
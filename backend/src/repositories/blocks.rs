@@ -0,0 +1,256 @@
+use crate::db::DbPool;
+use crate::models::LayoutBlock;
+use crate::repositories::common::{deserialize_json_value, serialize_json_value, validate_slug};
+use crate::utils::layout_blocks::{layout_references, ref_target, BlockLibrary};
+use serde_json::Value;
+use sqlx;
+
+/// Pages and blocks that reference a library block by id or name.
+#[derive(Debug, Default, PartialEq)]
+pub struct BlockReferences {
+    /// Slugs of referencing pages, including pages in the trash.
+    pub pages: Vec<String>,
+    /// Names of other library blocks that alias this one.
+    pub blocks: Vec<String>,
+}
+
+impl BlockReferences {
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty() && self.blocks.is_empty()
+    }
+}
+
+pub async fn list_layout_blocks(pool: &DbPool) -> Result<Vec<LayoutBlock>, sqlx::Error> {
+    sqlx::query_as::<_, LayoutBlock>(
+        "SELECT id, name, block_json, updated_at FROM layout_blocks ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_layout_block(pool: &DbPool, id: &str) -> Result<Option<LayoutBlock>, sqlx::Error> {
+    sqlx::query_as::<_, LayoutBlock>(
+        "SELECT id, name, block_json, updated_at FROM layout_blocks WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create_layout_block(
+    pool: &DbPool,
+    name: &str,
+    block: &Value,
+) -> Result<LayoutBlock, sqlx::Error> {
+    validate_slug(name)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO layout_blocks (id, name, block_json) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(name)
+        .bind(serialize_json_value(block)?)
+        .execute(pool)
+        .await?;
+
+    get_layout_block(pool, &id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn update_layout_block(
+    pool: &DbPool,
+    id: &str,
+    name: Option<&str>,
+    block: Option<&Value>,
+) -> Result<LayoutBlock, sqlx::Error> {
+    let mut existing = get_layout_block(pool, id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    if let Some(name) = name {
+        validate_slug(name)?;
+        existing.name = name.to_string();
+    }
+    if let Some(block) = block {
+        existing.block_json = serialize_json_value(block)?;
+    }
+
+    sqlx::query(
+        "UPDATE layout_blocks SET name = ?, block_json = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&existing.name)
+    .bind(&existing.block_json)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    get_layout_block(pool, id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn delete_layout_block(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM layout_blocks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        Err(sqlx::Error::RowNotFound)
+    } else {
+        Ok(())
+    }
+}
+
+/// Loads the block library for reference resolution, optionally leaving out
+/// `exclude_id` (the block being edited). Rows with unparsable JSON are left
+/// out, so references to them resolve as missing.
+pub async fn load_block_library(
+    pool: &DbPool,
+    exclude_id: Option<&str>,
+) -> Result<BlockLibrary, sqlx::Error> {
+    let mut library = BlockLibrary::new();
+    let blocks = list_layout_blocks(pool).await?;
+    for block in blocks
+        .into_iter()
+        .filter(|block| Some(block.id.as_str()) != exclude_id)
+    {
+        match deserialize_json_value(&block.block_json) {
+            Ok(value) => library.insert(&block.id, &block.name, value),
+            Err(err) => tracing::warn!("Ignoring layout block '{}': {}", block.name, err),
+        }
+    }
+    Ok(library)
+}
+
+/// Finds the pages and blocks whose JSON references any of `keys`
+/// (a block's id and/or name).
+pub async fn find_block_references(
+    pool: &DbPool,
+    keys: &[&str],
+) -> Result<BlockReferences, sqlx::Error> {
+    let pages: Vec<(String, String)> =
+        sqlx::query_as("SELECT slug, layout_json FROM site_pages ORDER BY slug")
+            .fetch_all(pool)
+            .await?;
+
+    let mut references = BlockReferences::default();
+    for (slug, layout_json) in pages {
+        let references_block = serde_json::from_str::<Value>(&layout_json)
+            .map(|layout| layout_references(&layout, keys))
+            .unwrap_or(false);
+        if references_block {
+            references.pages.push(slug);
+        }
+    }
+
+    for block in list_layout_blocks(pool).await? {
+        let aliases_block = serde_json::from_str::<Value>(&block.block_json)
+            .ok()
+            .and_then(|value| ref_target(&value).map(|target| keys.contains(&target)))
+            .unwrap_or(false);
+        if aliases_block {
+            references.blocks.push(block.name);
+        }
+    }
+
+    Ok(references)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use serde_json::json;
+
+    async fn seed_page(pool: &DbPool, slug: &str, layout: Value) {
+        sqlx::query("INSERT INTO site_pages (id, slug, title, layout_json) VALUES (?, ?, ?, ?)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(slug)
+            .bind(slug)
+            .bind(layout.to_string())
+            .execute(pool)
+            .await
+            .expect("insert page");
+    }
+
+    #[tokio::test]
+    async fn test_find_block_references_by_id_and_name() {
+        let pool = create_test_pool().await;
+        let hint = create_layout_block(
+            &pool,
+            "shell-hint",
+            &json!({ "type": "text", "content": "Tipp" }),
+        )
+        .await
+        .expect("create block");
+        create_layout_block(
+            &pool,
+            "hint-alias",
+            &json!({ "type": "ref", "block": "shell-hint" }),
+        )
+        .await
+        .expect("create alias");
+
+        seed_page(
+            &pool,
+            "by-name",
+            json!({ "blocks": [{ "type": "ref", "block": "shell-hint" }] }),
+        )
+        .await;
+        seed_page(
+            &pool,
+            "by-id",
+            json!({ "blocks": [{ "type": "ref", "block": hint.id }] }),
+        )
+        .await;
+        seed_page(
+            &pool,
+            "unrelated",
+            json!({ "blocks": [{ "type": "text", "content": "x" }] }),
+        )
+        .await;
+
+        let references = find_block_references(&pool, &[&hint.id, &hint.name])
+            .await
+            .expect("references");
+        assert_eq!(references.pages, vec!["by-id", "by-name"]);
+        assert_eq!(references.blocks, vec!["hint-alias"]);
+
+        let library = load_block_library(&pool, None).await.expect("library");
+        let resolved = crate::utils::layout_blocks::resolve_block(
+            &json!({ "type": "ref", "block": "hint-alias" }),
+            &library,
+            "/blocks/0",
+        )
+        .expect("resolve");
+        assert_eq!(resolved["content"], "Tipp");
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_layout_block() {
+        let pool = create_test_pool().await;
+        let block =
+            create_layout_block(&pool, "banner", &json!({ "type": "text", "content": "a" }))
+                .await
+                .expect("create block");
+
+        let updated = update_layout_block(&pool, &block.id, Some("promo-banner"), None)
+            .await
+            .expect("update block");
+        assert_eq!(updated.name, "promo-banner");
+        assert_eq!(updated.block_json, block.block_json);
+
+        assert!(
+            update_layout_block(&pool, &block.id, Some("Not A Slug"), None)
+                .await
+                .is_err()
+        );
+
+        delete_layout_block(&pool, &block.id).await.expect("delete");
+        assert!(matches!(
+            delete_layout_block(&pool, &block.id).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+}
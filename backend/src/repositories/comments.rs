@@ -1,37 +1,65 @@
-use crate::db::DbPool;
-use crate::models::Comment;
+use crate::db::{timing::timed_query, DbPool};
+use crate::models::{Comment, Cursor};
+use chrono::{DateTime, Utc};
 use sqlx;
 
+/// Orders newest first, with the id breaking ties between comments of the
+/// same second, and continues after `after` when given; `after` is ignored
+/// for `top`, which the handlers refuse to combine with a cursor.
+///
+/// `created_at` holds both SQLite's and RFC 3339 text (see
+/// [`crate::utils::timestamps`]), which do not compare as strings, so both
+/// the column and the cursor go through `datetime()`.
+fn push_page(
+    query_builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>,
+    limit: i64,
+    offset: i64,
+    sort: Option<&str>,
+    after: Option<&Cursor>,
+) {
+    match (sort, after) {
+        (Some("top"), _) => {
+            query_builder.push(" ORDER BY votes DESC, created_at DESC, id DESC");
+        }
+        (_, Some(cursor)) => {
+            query_builder.push(" AND (datetime(created_at), id) < (datetime(");
+            query_builder.push_bind(cursor.created_at.clone());
+            query_builder.push("), ");
+            query_builder.push_bind(cursor.id.clone());
+            query_builder.push(") ORDER BY datetime(created_at) DESC, id DESC");
+        }
+        _ => {
+            query_builder.push(" ORDER BY datetime(created_at) DESC, id DESC");
+        }
+    }
+
+    query_builder.push(" LIMIT ");
+    query_builder.push_bind(limit);
+    if after.is_none() {
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(offset);
+    }
+}
+
 pub async fn list_comments(
     pool: &DbPool,
     tutorial_id: &str,
     limit: i64,
     offset: i64,
     sort: Option<&str>,
+    after: Option<&Cursor>,
 ) -> Result<Vec<Comment>, sqlx::Error> {
     let mut query_builder = sqlx::QueryBuilder::new(
         "SELECT id, tutorial_id, post_id, author, content, created_at, votes, is_admin FROM comments WHERE tutorial_id = "
     );
     query_builder.push_bind(tutorial_id);
+    push_page(&mut query_builder, limit, offset, sort, after);
 
-    match sort {
-        Some("top") => {
-            query_builder.push(" ORDER BY votes DESC, created_at DESC");
-        }
-        _ => {
-            query_builder.push(" ORDER BY created_at DESC");
-        }
-    }
-
-    query_builder.push(" LIMIT ");
-    query_builder.push_bind(limit);
-    query_builder.push(" OFFSET ");
-    query_builder.push_bind(offset);
-
-    query_builder
-        .build_query_as::<Comment>()
-        .fetch_all(pool)
-        .await
+    timed_query(
+        "comments.list",
+        query_builder.build_query_as::<Comment>().fetch_all(pool),
+    )
+    .await
 }
 
 pub async fn list_post_comments(
@@ -40,32 +68,42 @@ pub async fn list_post_comments(
     limit: i64,
     offset: i64,
     sort: Option<&str>,
+    after: Option<&Cursor>,
 ) -> Result<Vec<Comment>, sqlx::Error> {
     let mut query_builder = sqlx::QueryBuilder::new(
         "SELECT id, tutorial_id, post_id, author, content, created_at, votes, is_admin FROM comments WHERE post_id = "
     );
     query_builder.push_bind(post_id);
+    push_page(&mut query_builder, limit, offset, sort, after);
 
-    match sort {
-        Some("top") => {
-            query_builder.push(" ORDER BY votes DESC, created_at DESC");
-        }
-        _ => {
-            query_builder.push(" ORDER BY created_at DESC");
-        }
-    }
+    timed_query(
+        "comments.list_for_post",
+        query_builder.build_query_as::<Comment>().fetch_all(pool),
+    )
+    .await
+}
 
-    query_builder.push(" LIMIT ");
-    query_builder.push_bind(limit);
-    query_builder.push(" OFFSET ");
-    query_builder.push_bind(offset);
+pub async fn count_comments(pool: &DbPool, tutorial_id: &str) -> Result<i64, sqlx::Error> {
+    timed_query(
+        "comments.count",
+        sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE tutorial_id = ?")
+            .bind(tutorial_id)
+            .fetch_one(pool),
+    )
+    .await
+}
 
-    query_builder
-        .build_query_as::<Comment>()
-        .fetch_all(pool)
-        .await
+pub async fn count_post_comments(pool: &DbPool, post_id: &str) -> Result<i64, sqlx::Error> {
+    timed_query(
+        "comments.count_for_post",
+        sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE post_id = ?")
+            .bind(post_id)
+            .fetch_one(pool),
+    )
+    .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_comment(
     pool: &DbPool,
     id: &str,
@@ -73,9 +111,10 @@ pub async fn create_comment(
     post_id: Option<String>,
     author: &str,
     content: &str,
-    created_at: &str,
+    created_at: DateTime<Utc>,
     is_admin: bool,
 ) -> Result<Comment, sqlx::Error> {
+    let stored_created_at = created_at.to_rfc3339();
     sqlx::query(
         "INSERT INTO comments (id, tutorial_id, post_id, author, content, created_at, votes, is_admin) VALUES (?, ?, ?, ?, ?, ?, 0, ?)"
     )
@@ -84,7 +123,7 @@ pub async fn create_comment(
     .bind(&post_id)
     .bind(author)
     .bind(content)
-    .bind(created_at)
+    .bind(&stored_created_at)
     .bind(is_admin)
     .execute(pool)
     .await?;
@@ -95,7 +134,8 @@ pub async fn create_comment(
         post_id,
         author: author.to_string(),
         content: content.to_string(),
-        created_at: created_at.to_string(),
+        created_at,
+        stored_created_at,
         votes: 0,
         is_admin,
     })
@@ -157,8 +197,8 @@ pub async fn add_vote(pool: &DbPool, comment_id: &str, voter_id: &str) -> Result
 pub async fn get_last_comment_time(
     pool: &DbPool,
     author: &str,
-) -> Result<Option<String>, sqlx::Error> {
-    let last_comment: Option<(String,)> = sqlx::query_as(
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let last_comment: Option<(DateTime<Utc>,)> = sqlx::query_as(
         "SELECT created_at FROM comments WHERE author = ? ORDER BY created_at DESC LIMIT 1",
     )
     .bind(author)
@@ -167,3 +207,159 @@ pub async fn get_last_comment_time(
 
     Ok(last_comment.map(|(t,)| t))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use std::collections::HashSet;
+
+    async fn seed_comment(pool: &DbPool, id: &str, created_at: &str) {
+        sqlx::query(
+            "INSERT INTO comments (id, tutorial_id, author, content, created_at) \
+             VALUES (?, 'cursor-tutorial', 'Gast', 'Hallo', ?)",
+        )
+        .bind(id)
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .expect("insert comment");
+    }
+
+    async fn seed(pool: &DbPool) {
+        sqlx::query(
+            "INSERT INTO tutorials (id, title, description, icon, color, topics) \
+             VALUES ('cursor-tutorial', 'T', 'D', 'Terminal', 'from-blue-500 to-cyan-500', '[]')",
+        )
+        .execute(pool)
+        .await
+        .expect("insert tutorial");
+    }
+
+    /// Walks all pages of `limit` comments by cursor.
+    async fn walk(pool: &DbPool, limit: i64) -> Vec<String> {
+        let mut seen = Vec::new();
+        let mut after: Option<Cursor> = None;
+        loop {
+            let page = list_comments(pool, "cursor-tutorial", limit, 0, None, after.as_ref())
+                .await
+                .expect("list page");
+            let Some(last) = page.last() else {
+                return seen;
+            };
+            after = Some(Cursor::new(&last.stored_created_at, &last.id));
+            seen.extend(page.into_iter().map(|c| c.id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cursor_orders_equal_timestamps_by_id() {
+        let pool = create_test_pool().await;
+        seed(&pool).await;
+        for id in ["b", "e", "a", "d", "c"] {
+            seed_comment(&pool, id, "2024-05-01 10:00:00").await;
+        }
+        seed_comment(&pool, "newest", "2024-05-01 10:00:01").await;
+        seed_comment(&pool, "oldest", "2024-05-01 09:59:59").await;
+
+        assert_eq!(
+            walk(&pool, 2).await,
+            vec!["newest", "e", "d", "c", "b", "a", "oldest"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pages_stay_stable_while_comments_arrive() {
+        let pool = create_test_pool().await;
+        seed(&pool).await;
+        let existing: Vec<String> = (0..30).map(|n| format!("old-{n:02}")).collect();
+        for (n, id) in existing.iter().enumerate() {
+            seed_comment(&pool, id, &format!("2024-05-01 10:00:{:02}", n / 3)).await;
+        }
+
+        let writer = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for n in 0..30 {
+                    seed_comment(&pool, &format!("new-{n:02}"), "2024-06-01 12:00:00").await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        let seen = walk(&pool, 4).await;
+        writer.await.unwrap();
+
+        // Nothing repeats, and every comment that existed up front shows up
+        // once, newest first, however the new ones interleaved.
+        let unique: HashSet<&String> = seen.iter().collect();
+        assert_eq!(unique.len(), seen.len());
+        let old: Vec<&String> = seen.iter().filter(|id| id.starts_with("old-")).collect();
+        let expected: Vec<&String> = existing.iter().rev().collect();
+        assert_eq!(old, expected);
+    }
+
+    #[tokio::test]
+    async fn test_cursor_compares_mixed_timestamp_formats_by_time() {
+        let pool = create_test_pool().await;
+        seed(&pool).await;
+        // As text "2024-05-01T..." sorts after every "2024-05-01 ..."
+        seed_comment(&pool, "rfc-late", "2024-05-01T10:00:02Z").await;
+        seed_comment(&pool, "legacy-mid", "2024-05-01 10:00:01").await;
+        seed_comment(&pool, "rfc-offset", "2024-05-01T12:00:00.500+02:00").await;
+        seed_comment(&pool, "legacy-late", "2024-05-01 10:00:03").await;
+        seed_comment(&pool, "legacy-early", "2024-05-01 09:59:59").await;
+
+        let expected = vec![
+            "legacy-late",
+            "rfc-late",
+            "legacy-mid",
+            "rfc-offset",
+            "legacy-early",
+        ];
+        assert_eq!(walk(&pool, 1).await, expected);
+        assert_eq!(walk(&pool, 2).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_decodes_legacy_and_rfc3339_timestamps() {
+        let pool = create_test_pool().await;
+        seed(&pool).await;
+        seed_comment(&pool, "legacy", "2024-05-01 10:00:00").await;
+        let written = create_comment(
+            &pool,
+            "current",
+            Some("cursor-tutorial".to_string()),
+            None,
+            "Gast",
+            "Hallo",
+            "2024-05-01T12:30:00.250+02:00".parse().unwrap(),
+            false,
+        )
+        .await
+        .expect("create comment");
+
+        let listed = list_comments(&pool, "cursor-tutorial", 10, 0, None, None)
+            .await
+            .expect("list comments");
+        let by_id = |id: &str| listed.iter().find(|c| c.id == id).unwrap();
+        assert_eq!(
+            by_id("legacy").created_at.to_rfc3339(),
+            "2024-05-01T10:00:00+00:00"
+        );
+        assert_eq!(by_id("current").created_at, written.created_at);
+        assert_eq!(
+            by_id("current").created_at.to_rfc3339(),
+            "2024-05-01T10:30:00.250+00:00"
+        );
+        // Cursors keep comparing against the text as stored
+        assert_eq!(by_id("legacy").stored_created_at, "2024-05-01 10:00:00");
+        assert_eq!(
+            by_id("current").stored_created_at,
+            written.stored_created_at
+        );
+
+        let json = serde_json::to_value(by_id("legacy")).unwrap();
+        assert_eq!(json["created_at"], "2024-05-01T10:00:00Z");
+        assert!(json.get("stored_created_at").is_none());
+    }
+}
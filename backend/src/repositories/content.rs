@@ -1,12 +1,24 @@
-use crate::db::DbPool;
-use crate::models::SiteContent;
+use crate::db::{timing::timed_query, DbPool};
+use crate::models::{SiteContent, SiteContentHistoryItem, SiteContentVersion};
 use crate::repositories::common::serialize_json_value;
 use serde_json::Value;
-use sqlx;
+use sqlx::{self, Sqlite, Transaction};
+use std::env;
+
+const DEFAULT_HISTORY_RETENTION: u32 = 50;
+
+/// Archived versions kept per section, from `CONTENT_HISTORY_RETENTION`
+/// (`0` keeps every version).
+pub fn history_retention() -> u32 {
+    env::var("CONTENT_HISTORY_RETENTION")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_RETENTION)
+}
 
 pub async fn fetch_all_site_content(pool: &DbPool) -> Result<Vec<SiteContent>, sqlx::Error> {
     sqlx::query_as::<_, SiteContent>(
-        "SELECT section, content_json, updated_at FROM site_content ORDER BY section",
+        "SELECT section, content_json, updated_at, updated_by, revision FROM site_content ORDER BY section",
     )
     .fetch_all(pool)
     .await
@@ -17,30 +29,275 @@ pub async fn fetch_site_content_by_section(
     section: &str,
 ) -> Result<Option<SiteContent>, sqlx::Error> {
     sqlx::query_as::<_, SiteContent>(
-        "SELECT section, content_json, updated_at FROM site_content WHERE section = ?",
+        "SELECT section, content_json, updated_at, updated_by, revision FROM site_content WHERE section = ?",
     )
     .bind(section)
     .fetch_optional(pool)
     .await
 }
 
+/// Returns up to `limit` sections ordered by name, starting after `after`.
+/// Used to walk all content in bounded batches.
+pub async fn list_site_content_after(
+    pool: &DbPool,
+    after: &str,
+    limit: i64,
+) -> Result<Vec<SiteContent>, sqlx::Error> {
+    sqlx::query_as::<_, SiteContent>(
+        "SELECT section, content_json, updated_at, updated_by, revision FROM site_content WHERE section > ? ORDER BY section LIMIT ?",
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Writes a section and archives the content it replaces in the same
+/// transaction. `retention` caps the archived versions kept per section
+/// (`0` keeps all of them).
 pub async fn upsert_site_content(
     pool: &DbPool,
     section: &str,
     content: &Value,
+    updated_by: &str,
+    retention: u32,
 ) -> Result<SiteContent, sqlx::Error> {
+    timed_query("content.upsert", async {
+        let mut tx = pool.begin().await?;
+        upsert_site_content_tx(&mut tx, section, content, None, updated_by, retention).await?;
+        tx.commit().await?;
+
+        fetch_site_content_by_section(pool, section)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    })
+    .await
+}
+
+/// Writes several sections atomically, archiving each replaced version.
+pub async fn import_site_content(
+    pool: &DbPool,
+    sections: &[(String, Value)],
+    updated_by: &str,
+    retention: u32,
+) -> Result<(), sqlx::Error> {
+    timed_query("content.import", async {
+        let mut tx = pool.begin().await?;
+        for (section, content) in sections {
+            upsert_site_content_tx(&mut tx, section, content, None, updated_by, retention).await?;
+        }
+        tx.commit().await
+    })
+    .await
+}
+
+/// Like [`upsert_site_content`], but only writes while the stored row still
+/// has `expected_revision`. Returns `None` when another write got there first.
+pub async fn upsert_site_content_if_unchanged(
+    pool: &DbPool,
+    section: &str,
+    content: &Value,
+    updated_by: &str,
+    retention: u32,
+    expected_revision: i64,
+) -> Result<Option<SiteContent>, sqlx::Error> {
+    timed_query("content.upsert_if_unchanged", async {
+        let mut tx = pool.begin().await?;
+        // Archiving is the check: it only copies the row at the expected
+        // revision, and as the first write of the transaction it holds the
+        // write lock until the update below is committed.
+        if archive_site_content_tx(&mut tx, section, Some(expected_revision)).await? == 0 {
+            return Ok(None);
+        }
+        write_site_content_tx(&mut tx, section, content, None, updated_by, retention).await?;
+        tx.commit().await?;
+
+        fetch_site_content_by_section(pool, section).await
+    })
+    .await
+}
+
+/// Archives the stored version of a section and writes `content` in its
+/// place, within the caller's transaction. `updated_at` defaults to now;
+/// imports pass the timestamp of the bundle.
+pub async fn upsert_site_content_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    section: &str,
+    content: &Value,
+    updated_at: Option<&str>,
+    updated_by: &str,
+    retention: u32,
+) -> Result<(), sqlx::Error> {
+    archive_site_content_tx(tx, section, None).await?;
+    write_site_content_tx(tx, section, content, updated_at, updated_by, retention).await
+}
+
+/// Copies the stored row of a section into the history, if it is at
+/// `revision` (any revision for `None`). Returns the number of rows copied.
+async fn archive_site_content_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    section: &str,
+    revision: Option<i64>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO site_content_history (section, content_json, updated_at, updated_by)
+         SELECT section, content_json, updated_at, updated_by FROM site_content
+         WHERE section = ? AND (? IS NULL OR revision = ?)",
+    )
+    .bind(section)
+    .bind(revision)
+    .bind(revision)
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+async fn write_site_content_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    section: &str,
+    content: &Value,
+    updated_at: Option<&str>,
+    updated_by: &str,
+    retention: u32,
+) -> Result<(), sqlx::Error> {
     let serialized = serialize_json_value(content)?;
 
     sqlx::query(
-        "INSERT INTO site_content (section, content_json, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP) \
-         ON CONFLICT(section) DO UPDATE SET content_json = excluded.content_json, updated_at = CURRENT_TIMESTAMP",
+        "INSERT INTO site_content (section, content_json, updated_at, updated_by) VALUES (?, ?, COALESCE(?, CURRENT_TIMESTAMP), ?) \
+         ON CONFLICT(section) DO UPDATE SET content_json = excluded.content_json, updated_at = excluded.updated_at, \
+         updated_by = excluded.updated_by, revision = site_content.revision + 1",
     )
     .bind(section)
     .bind(serialized)
-    .execute(pool)
+    .bind(updated_at)
+    .bind(updated_by)
+    .execute(&mut **tx)
     .await?;
 
-    fetch_site_content_by_section(pool, section)
-        .await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)
+    if retention > 0 {
+        sqlx::query(
+            "DELETE FROM site_content_history
+             WHERE section = ? AND id NOT IN (
+                 SELECT id FROM site_content_history WHERE section = ? ORDER BY id DESC LIMIT ?
+             )",
+        )
+        .bind(section)
+        .bind(section)
+        .bind(i64::from(retention))
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Lists archived versions of a section, newest first, without their JSON.
+pub async fn list_site_content_history(
+    pool: &DbPool,
+    section: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<SiteContentHistoryItem>, i64), sqlx::Error> {
+    let items = sqlx::query_as::<_, SiteContentHistoryItem>(
+        "SELECT id AS version, updated_at, updated_by, archived_at, LENGTH(CAST(content_json AS BLOB)) AS size_bytes
+         FROM site_content_history
+         WHERE section = ?
+         ORDER BY id DESC
+         LIMIT ? OFFSET ?",
+    )
+    .bind(section)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM site_content_history WHERE section = ?")
+            .bind(section)
+            .fetch_one(pool)
+            .await?;
+
+    Ok((items, total))
+}
+
+pub async fn get_site_content_version(
+    pool: &DbPool,
+    section: &str,
+    version: i64,
+) -> Result<Option<SiteContentVersion>, sqlx::Error> {
+    sqlx::query_as::<_, SiteContentVersion>(
+        "SELECT id AS version, section, content_json, updated_at, updated_by, archived_at
+         FROM site_content_history
+         WHERE section = ? AND id = ?",
+    )
+    .bind(section)
+    .bind(version)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::utils::timestamps::parse_timestamp;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_upsert_archives_previous_content() {
+        let pool = create_test_pool().await;
+        let original = fetch_site_content_by_section(&pool, "site_meta")
+            .await
+            .expect("fetch")
+            .expect("seeded section");
+
+        let stored =
+            upsert_site_content(&pool, "site_meta", &json!({ "title": "Neu" }), "admin", 0)
+                .await
+                .expect("upsert");
+        assert_eq!(stored.updated_by.as_deref(), Some("admin"));
+
+        let (items, total) = list_site_content_history(&pool, "site_meta", 10, 0)
+            .await
+            .expect("history");
+        assert_eq!(total, 1);
+        // The archived row keeps its own author; seeded content has none.
+        assert_eq!(items[0].updated_by, None);
+        assert_eq!(
+            parse_timestamp(&items[0].updated_at),
+            Some(original.updated_at)
+        );
+        assert_eq!(items[0].size_bytes, original.content_json.len() as i64);
+
+        let version = get_site_content_version(&pool, "site_meta", items[0].version)
+            .await
+            .expect("version")
+            .expect("stored version");
+        assert_eq!(version.content_json, original.content_json);
+        assert!(get_site_content_version(&pool, "hero", items[0].version)
+            .await
+            .expect("version")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_newest_versions() {
+        let pool = create_test_pool().await;
+        for index in 0..5 {
+            upsert_site_content(&pool, "site_meta", &json!({ "title": index }), "admin", 3)
+                .await
+                .expect("upsert");
+        }
+
+        let (items, total) = list_site_content_history(&pool, "site_meta", 10, 0)
+            .await
+            .expect("history");
+        assert_eq!(total, 3);
+
+        let newest = get_site_content_version(&pool, "site_meta", items[0].version)
+            .await
+            .expect("version")
+            .expect("stored version");
+        assert_eq!(newest.content_json, r#"{"title":3}"#);
+    }
 }
@@ -0,0 +1,146 @@
+use crate::db::{timing::timed_query, DbPool};
+use crate::models::ContentSection;
+use crate::repositories::common::{serialize_json_value, validate_slug};
+use serde_json::Value;
+use sqlx;
+
+pub async fn list_content_sections(pool: &DbPool) -> Result<Vec<ContentSection>, sqlx::Error> {
+    sqlx::query_as::<_, ContentSection>(
+        "SELECT name, description, schema_json, created_at FROM content_sections ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_content_section(
+    pool: &DbPool,
+    name: &str,
+) -> Result<Option<ContentSection>, sqlx::Error> {
+    sqlx::query_as::<_, ContentSection>(
+        "SELECT name, description, schema_json, created_at FROM content_sections WHERE name = ?",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create_content_section(
+    pool: &DbPool,
+    name: &str,
+    description: &str,
+    schema: Option<&Value>,
+) -> Result<ContentSection, sqlx::Error> {
+    validate_slug(name)?;
+    let schema_json = schema.map(serialize_json_value).transpose()?;
+
+    sqlx::query("INSERT INTO content_sections (name, description, schema_json) VALUES (?, ?, ?)")
+        .bind(name)
+        .bind(description)
+        .bind(schema_json)
+        .execute(pool)
+        .await?;
+
+    get_content_section(pool, name)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn update_content_section(
+    pool: &DbPool,
+    name: &str,
+    description: &str,
+    schema: Option<&Value>,
+) -> Result<ContentSection, sqlx::Error> {
+    let schema_json = schema.map(serialize_json_value).transpose()?;
+
+    let result =
+        sqlx::query("UPDATE content_sections SET description = ?, schema_json = ? WHERE name = ?")
+            .bind(description)
+            .bind(schema_json)
+            .bind(name)
+            .execute(pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    get_content_section(pool, name)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Removes a registered section. With `cascade` its stored content and
+/// history go too; without it, callers must make sure no content exists.
+pub async fn delete_content_section(
+    pool: &DbPool,
+    name: &str,
+    cascade: bool,
+) -> Result<(), sqlx::Error> {
+    timed_query("content_sections.delete", async {
+        let mut tx = pool.begin().await?;
+
+        if cascade {
+            sqlx::query("DELETE FROM site_content_history WHERE section = ?")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM site_content WHERE section = ?")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let result = sqlx::query("DELETE FROM content_sections WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        tx.commit().await
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::repositories::content::{fetch_site_content_by_section, upsert_site_content};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_cascade_delete_removes_content_and_history() {
+        let pool = create_test_pool().await;
+        create_content_section(&pool, "banner", "", None)
+            .await
+            .expect("create");
+        for text in ["a", "b"] {
+            upsert_site_content(&pool, "banner", &json!({ "text": text }), "admin", 0)
+                .await
+                .expect("upsert");
+        }
+
+        delete_content_section(&pool, "banner", true)
+            .await
+            .expect("delete");
+
+        assert!(get_content_section(&pool, "banner").await.unwrap().is_none());
+        assert!(fetch_site_content_by_section(&pool, "banner")
+            .await
+            .unwrap()
+            .is_none());
+        let history: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM site_content_history WHERE section = 'banner'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(history, 0);
+
+        assert!(matches!(
+            delete_content_section(&pool, "banner", false).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+}
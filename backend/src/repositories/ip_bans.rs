@@ -0,0 +1,60 @@
+use crate::db::DbPool;
+use crate::models::IpBan;
+use sqlx;
+
+const BAN_COLUMNS: &str = "id, ip, reason, created_by, created_at, expires_at";
+
+pub async fn list_bans(pool: &DbPool) -> Result<Vec<IpBan>, sqlx::Error> {
+    let sql = format!("SELECT {BAN_COLUMNS} FROM ip_bans ORDER BY created_at DESC, id DESC");
+    sqlx::query_as::<_, IpBan>(&sql).fetch_all(pool).await
+}
+
+/// Bans that have not expired yet.
+pub async fn list_active_bans(pool: &DbPool) -> Result<Vec<IpBan>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {BAN_COLUMNS} FROM ip_bans
+         WHERE expires_at IS NULL OR datetime(expires_at) > datetime('now')"
+    );
+    sqlx::query_as::<_, IpBan>(&sql).fetch_all(pool).await
+}
+
+/// Inserts a ban, or `None` when `ip` is already banned.
+pub async fn create_ban(
+    pool: &DbPool,
+    ip: &str,
+    reason: Option<&str>,
+    created_by: &str,
+    expires_at: Option<&str>,
+) -> Result<Option<IpBan>, sqlx::Error> {
+    let sql = format!(
+        "INSERT INTO ip_bans (ip, reason, created_by, expires_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT(ip) DO NOTHING
+         RETURNING {BAN_COLUMNS}"
+    );
+    sqlx::query_as::<_, IpBan>(&sql)
+        .bind(ip)
+        .bind(reason)
+        .bind(created_by)
+        .bind(expires_at)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Returns whether a ban for `ip` existed.
+pub async fn delete_ban(pool: &DbPool, ip: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM ip_bans WHERE ip = ?")
+        .bind(ip)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_expired_bans(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM ip_bans
+         WHERE expires_at IS NOT NULL AND datetime(expires_at) <= datetime('now')",
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,39 @@
+use crate::db::DbPool;
+use crate::models::JobRun;
+use sqlx;
+
+/// Records a finished run of `name`; `error` is set when it failed.
+pub async fn record_run(
+    pool: &DbPool,
+    name: &str,
+    duration_ms: u64,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO job_runs (name, last_run_at, last_success_at, duration_ms, succeeded, error, runs, failures)
+         VALUES (?1, datetime('now'), CASE WHEN ?3 IS NULL THEN datetime('now') END, ?2, ?3 IS NULL, ?3, 1, ?3 IS NOT NULL)
+         ON CONFLICT(name) DO UPDATE SET
+             last_run_at = excluded.last_run_at,
+             last_success_at = COALESCE(excluded.last_success_at, job_runs.last_success_at),
+             duration_ms = excluded.duration_ms,
+             succeeded = excluded.succeeded,
+             error = excluded.error,
+             runs = job_runs.runs + 1,
+             failures = job_runs.failures + excluded.failures",
+    )
+    .bind(name)
+    .bind(i64::try_from(duration_ms).unwrap_or(i64::MAX))
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_runs(pool: &DbPool) -> Result<Vec<JobRun>, sqlx::Error> {
+    sqlx::query_as::<_, JobRun>(
+        "SELECT name, last_run_at, last_success_at, duration_ms, succeeded, error, runs, failures
+         FROM job_runs ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -1,9 +1,16 @@
 pub mod app_metadata;
+pub mod audit_log;
+pub mod blocks;
 pub mod comments;
 pub mod common;
 pub mod content;
+pub mod content_sections;
+pub mod ip_bans;
+pub mod job_runs;
 pub mod pages;
 pub mod posts;
 pub mod token_blacklist;
 pub mod tutorials;
+pub mod upload_sessions;
+pub mod uploads;
 pub mod users;
@@ -1,44 +1,105 @@
-use crate::db::DbPool;
+use crate::db::{timing::timed_query, DbPool};
 use crate::models::{CreateSitePageRequest, SitePage, UpdateSitePageRequest};
 use crate::repositories::common::{serialize_json_value, validate_slug};
 use sqlx;
 
-pub async fn list_site_pages(pool: &DbPool) -> Result<Vec<SitePage>, sqlx::Error> {
-    sqlx::query_as::<_, SitePage>(
-        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at FROM site_pages ORDER BY order_index, title",
+fn trash_filter(trashed: bool) -> &'static str {
+    if trashed {
+        "deleted_at IS NOT NULL"
+    } else {
+        "deleted_at IS NULL"
+    }
+}
+
+/// Lists pages for the admin UI, either the live pages or the trash.
+pub async fn list_site_pages(
+    pool: &DbPool,
+    trashed: bool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SitePage>, sqlx::Error> {
+    let filter = trash_filter(trashed);
+
+    timed_query(
+        "pages.list",
+        sqlx::query_as::<_, SitePage>(&format!(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at FROM site_pages WHERE {filter} ORDER BY order_index, title LIMIT ? OFFSET ?",
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool),
+    )
+    .await
+}
+
+pub async fn count_site_pages(pool: &DbPool, trashed: bool) -> Result<i64, sqlx::Error> {
+    let filter = trash_filter(trashed);
+
+    timed_query(
+        "pages.count",
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM site_pages WHERE {filter}"))
+            .fetch_one(pool),
     )
-    .fetch_all(pool)
     .await
 }
 
 pub async fn list_nav_pages(pool: &DbPool) -> Result<Vec<SitePage>, sqlx::Error> {
-    sqlx::query_as::<_, SitePage>(
-        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at
-         FROM site_pages
-         WHERE show_in_nav = 1 AND is_published = 1
-         ORDER BY order_index, title",
+    timed_query(
+        "pages.list_nav",
+        sqlx::query_as::<_, SitePage>(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at
+             FROM site_pages
+             WHERE show_in_nav = 1 AND is_published = 1 AND deleted_at IS NULL
+             ORDER BY order_index, title",
+        )
+        .fetch_all(pool),
     )
-    .fetch_all(pool)
     .await
 }
 
 pub async fn list_published_pages(pool: &DbPool) -> Result<Vec<SitePage>, sqlx::Error> {
+    timed_query(
+        "pages.list_published",
+        sqlx::query_as::<_, SitePage>(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at
+             FROM site_pages
+             WHERE is_published = 1 AND deleted_at IS NULL
+             ORDER BY order_index, title",
+        )
+        .fetch_all(pool),
+    )
+    .await
+}
+
+/// Returns up to `limit` live pages (drafts included) ordered by id, starting
+/// after `after`. Used to walk all pages in bounded batches.
+pub async fn list_site_pages_after(
+    pool: &DbPool,
+    after: &str,
+    limit: i64,
+) -> Result<Vec<SitePage>, sqlx::Error> {
     sqlx::query_as::<_, SitePage>(
-        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at
+        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at
          FROM site_pages
-         WHERE is_published = 1
-         ORDER BY order_index, title",
+         WHERE deleted_at IS NULL AND id > ?
+         ORDER BY id
+         LIMIT ?",
     )
+    .bind(after)
+    .bind(limit)
     .fetch_all(pool)
     .await
 }
 
 pub async fn get_site_page_by_id(pool: &DbPool, id: &str) -> Result<Option<SitePage>, sqlx::Error> {
-    sqlx::query_as::<_, SitePage>(
-        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at FROM site_pages WHERE id = ?",
+    timed_query(
+        "pages.get_by_id",
+        sqlx::query_as::<_, SitePage>(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at FROM site_pages WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(pool),
     )
-    .bind(id)
-    .fetch_optional(pool)
     .await
 }
 
@@ -46,11 +107,14 @@ pub async fn get_site_page_by_slug(
     pool: &DbPool,
     slug: &str,
 ) -> Result<Option<SitePage>, sqlx::Error> {
-    sqlx::query_as::<_, SitePage>(
-        "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json, created_at, updated_at FROM site_pages WHERE slug = ?",
+    timed_query(
+        "pages.get_by_slug",
+        sqlx::query_as::<_, SitePage>(
+            "SELECT id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image, created_at, updated_at, deleted_at FROM site_pages WHERE slug = ? AND deleted_at IS NULL",
+        )
+        .bind(slug)
+        .fetch_optional(pool),
     )
-    .bind(slug)
-    .fetch_optional(pool)
     .await
 }
 
@@ -58,34 +122,41 @@ pub async fn create_site_page(
     pool: &DbPool,
     page: CreateSitePageRequest,
 ) -> Result<SitePage, sqlx::Error> {
-    validate_slug(&page.slug)?;
+    timed_query("pages.create", async {
+        validate_slug(&page.slug)?;
 
-    let id = uuid::Uuid::new_v4().to_string();
-    let hero_json = serialize_json_value(&page.hero)?;
-    let layout_json = serialize_json_value(&page.layout)?;
-    let description = page.description.unwrap_or_default();
-    let order_index = page.order_index.unwrap_or(0);
+        let id = uuid::Uuid::new_v4().to_string();
+        let hero_json = serialize_json_value(&page.hero)?;
+        let layout_json = serialize_json_value(&page.layout)?;
+        let description = page.description.unwrap_or_default();
+        let order_index = page.order_index.unwrap_or(0);
 
-    sqlx::query(
-        "INSERT INTO site_pages (id, slug, title, description, nav_label, show_in_nav, order_index, is_published, hero_json, layout_json)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(&id)
-    .bind(&page.slug)
-    .bind(&page.title)
-    .bind(description)
-    .bind(page.nav_label)
-    .bind(if page.show_in_nav { 1 } else { 0 })
-    .bind(order_index)
-    .bind(if page.is_published { 1 } else { 0 })
-    .bind(hero_json)
-    .bind(layout_json)
-    .execute(pool)
-    .await?;
+        sqlx::query(
+            "INSERT INTO site_pages (id, slug, title, description, nav_label, show_in_nav, order_index, is_published, visibility, hero_json, layout_json, meta_title, meta_description, og_image)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&page.slug)
+        .bind(&page.title)
+        .bind(description)
+        .bind(page.nav_label)
+        .bind(if page.show_in_nav { 1 } else { 0 })
+        .bind(order_index)
+        .bind(if page.is_published { 1 } else { 0 })
+        .bind(&page.visibility)
+        .bind(hero_json)
+        .bind(layout_json)
+        .bind(&page.meta_title)
+        .bind(&page.meta_description)
+        .bind(&page.og_image)
+        .execute(pool)
+        .await?;
 
-    get_site_page_by_id(pool, &id)
-        .await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)
+        get_site_page_by_id(pool, &id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    })
+    .await
 }
 
 pub async fn update_site_page(
@@ -93,67 +164,138 @@ pub async fn update_site_page(
     id: &str,
     payload: UpdateSitePageRequest,
 ) -> Result<SitePage, sqlx::Error> {
-    if let Some(slug) = payload.slug.as_deref() {
-        validate_slug(slug)?;
-    }
+    timed_query("pages.update", async {
+        if let Some(slug) = payload.slug.as_deref() {
+            validate_slug(slug)?;
+        }
 
-    let mut existing = get_site_page_by_id(pool, id)
-        .await?
-        .ok_or(sqlx::Error::RowNotFound)?;
+        let mut existing = get_site_page_by_id(pool, id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
 
-    if let Some(slug) = payload.slug {
-        existing.slug = slug;
-    }
-    if let Some(title) = payload.title {
-        existing.title = title;
-    }
-    if let Some(description) = payload.description {
-        existing.description = description;
-    }
-    if let Some(nav_label_opt) = payload.nav_label {
-        existing.nav_label = nav_label_opt;
-    }
-    if let Some(show_in_nav) = payload.show_in_nav {
-        existing.show_in_nav = show_in_nav;
-    }
-    if let Some(order_index) = payload.order_index {
-        existing.order_index = order_index;
-    }
-    if let Some(is_published) = payload.is_published {
-        existing.is_published = is_published;
-    }
-    if let Some(hero) = payload.hero {
-        existing.hero_json = serialize_json_value(&hero)?;
-    }
-    if let Some(layout) = payload.layout {
-        existing.layout_json = serialize_json_value(&layout)?;
+        if let Some(slug) = payload.slug {
+            existing.slug = slug;
+        }
+        if let Some(title) = payload.title {
+            existing.title = title;
+        }
+        if let Some(description) = payload.description {
+            existing.description = description;
+        }
+        if let Some(nav_label_opt) = payload.nav_label {
+            existing.nav_label = nav_label_opt;
+        }
+        if let Some(show_in_nav) = payload.show_in_nav {
+            existing.show_in_nav = show_in_nav;
+        }
+        if let Some(order_index) = payload.order_index {
+            existing.order_index = order_index;
+        }
+        if let Some(is_published) = payload.is_published {
+            existing.is_published = is_published;
+        }
+        if let Some(visibility) = payload.visibility {
+            existing.visibility = visibility;
+        }
+        if let Some(hero) = payload.hero {
+            existing.hero_json = serialize_json_value(&hero)?;
+        }
+        if let Some(layout) = payload.layout {
+            existing.layout_json = serialize_json_value(&layout)?;
+        }
+        if let Some(meta_title) = payload.meta_title {
+            existing.meta_title = meta_title;
+        }
+        if let Some(meta_description) = payload.meta_description {
+            existing.meta_description = meta_description;
+        }
+        if let Some(og_image) = payload.og_image {
+            existing.og_image = og_image;
+        }
+
+        sqlx::query(
+            "UPDATE site_pages
+             SET slug = ?, title = ?, description = ?, nav_label = ?, show_in_nav = ?, order_index = ?, is_published = ?, visibility = ?, hero_json = ?, layout_json = ?, meta_title = ?, meta_description = ?, og_image = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&existing.slug)
+        .bind(&existing.title)
+        .bind(&existing.description)
+        .bind(&existing.nav_label)
+        .bind(if existing.show_in_nav { 1 } else { 0 })
+        .bind(existing.order_index)
+        .bind(if existing.is_published { 1 } else { 0 })
+        .bind(&existing.visibility)
+        .bind(&existing.hero_json)
+        .bind(&existing.layout_json)
+        .bind(&existing.meta_title)
+        .bind(&existing.meta_description)
+        .bind(&existing.og_image)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        get_site_page_by_id(pool, id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    })
+    .await
+}
+
+/// Returns true when `slug` is held by a page (other than `exclude_id`) that
+/// currently sits in the trash.
+pub async fn is_slug_in_trash(
+    pool: &DbPool,
+    slug: &str,
+    exclude_id: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM site_pages WHERE slug = ? AND deleted_at IS NOT NULL AND id IS NOT ?",
+    )
+    .bind(slug)
+    .bind(exclude_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count > 0)
+}
+
+/// Moves a page to the trash. Its posts are kept until the page is purged.
+pub async fn delete_site_page(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE site_pages SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        Err(sqlx::Error::RowNotFound)
+    } else {
+        Ok(())
     }
+}
 
-    sqlx::query(
-        "UPDATE site_pages
-         SET slug = ?, title = ?, description = ?, nav_label = ?, show_in_nav = ?, order_index = ?, is_published = ?, hero_json = ?, layout_json = ?, updated_at = CURRENT_TIMESTAMP
-         WHERE id = ?",
+pub async fn restore_site_page(pool: &DbPool, id: &str) -> Result<SitePage, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE site_pages SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NOT NULL",
     )
-    .bind(&existing.slug)
-    .bind(&existing.title)
-    .bind(&existing.description)
-    .bind(&existing.nav_label)
-    .bind(if existing.show_in_nav { 1 } else { 0 })
-    .bind(existing.order_index)
-    .bind(if existing.is_published { 1 } else { 0 })
-    .bind(&existing.hero_json)
-    .bind(&existing.layout_json)
     .bind(id)
     .execute(pool)
     .await?;
 
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
     get_site_page_by_id(pool, id)
         .await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)
+        .ok_or(sqlx::Error::RowNotFound)
 }
 
-pub async fn delete_site_page(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
-    let result = sqlx::query("DELETE FROM site_pages WHERE id = ?")
+/// Permanently deletes a trashed page; its posts are removed by the cascade.
+pub async fn purge_site_page(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM site_pages WHERE id = ? AND deleted_at IS NOT NULL")
         .bind(id)
         .execute(pool)
         .await?;
@@ -164,3 +306,139 @@ pub async fn delete_site_page(pool: &DbPool, id: &str) -> Result<(), sqlx::Error
         Ok(())
     }
 }
+
+/// Purges every page that has been in the trash for longer than `retention_days`.
+pub async fn purge_expired_trash(pool: &DbPool, retention_days: u32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM site_pages WHERE deleted_at IS NOT NULL AND datetime(deleted_at) <= datetime('now', ?)",
+    )
+    .bind(format!("-{retention_days} days"))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::models::CreateSitePostRequest;
+    use crate::repositories::posts;
+
+    async fn seed_page_with_post(pool: &DbPool, slug: &str) -> (String, String) {
+        let page = create_site_page(
+            pool,
+            CreateSitePageRequest {
+                slug: slug.to_string(),
+                title: slug.to_uppercase(),
+                description: None,
+                nav_label: None,
+                show_in_nav: true,
+                order_index: None,
+                is_published: true,
+                visibility: "public".to_string(),
+                hero: serde_json::json!({}),
+                layout: serde_json::json!({}),
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("create page");
+
+        let post = posts::create_site_post(
+            pool,
+            &page.id,
+            CreateSitePostRequest {
+                title: "Post".to_string(),
+                slug: "post".to_string(),
+                excerpt: None,
+                content_markdown: "Body".to_string(),
+                is_published: true,
+                allow_comments: true,
+                published_at: Some("2024-01-01T00:00:00Z".parse().unwrap()),
+                order_index: None,
+            },
+        )
+        .await
+        .expect("create post");
+
+        (page.id, post.id)
+    }
+
+    #[tokio::test]
+    async fn test_trash_hides_page_and_restore_keeps_posts() {
+        let pool = create_test_pool().await;
+        let (page_id, post_id) = seed_page_with_post(&pool, "grundlagen").await;
+
+        delete_site_page(&pool, &page_id).await.expect("trash page");
+
+        assert!(get_site_page_by_slug(&pool, "grundlagen").await.unwrap().is_none());
+        assert!(list_nav_pages(&pool).await.unwrap().is_empty());
+        assert!(list_published_pages(&pool).await.unwrap().is_empty());
+        assert!(list_site_pages(&pool, false, 50, 0).await.unwrap().is_empty());
+
+        let trashed = list_site_pages(&pool, true, 50, 0).await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert!(trashed[0].deleted_at.is_some());
+        assert_eq!(count_site_pages(&pool, true).await.unwrap(), 1);
+        assert_eq!(count_site_pages(&pool, false).await.unwrap(), 0);
+        assert!(is_slug_in_trash(&pool, "grundlagen", None).await.unwrap());
+        assert!(!is_slug_in_trash(&pool, "grundlagen", Some(&page_id)).await.unwrap());
+
+        // Trashing twice is a not-found, not a silent success.
+        assert!(matches!(
+            delete_site_page(&pool, &page_id).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        let restored = restore_site_page(&pool, &page_id).await.expect("restore");
+        assert!(restored.deleted_at.is_none());
+        assert!(get_site_page_by_slug(&pool, "grundlagen").await.unwrap().is_some());
+
+        let post = posts::get_site_post_by_id(&pool, &post_id).await.unwrap();
+        assert!(post.is_some(), "posts must survive a trash/restore cycle");
+
+        assert!(matches!(
+            restore_site_page(&pool, &page_id).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_requires_trash_and_cascades_posts() {
+        let pool = create_test_pool().await;
+        let (page_id, post_id) = seed_page_with_post(&pool, "purge-me").await;
+
+        assert!(matches!(
+            purge_site_page(&pool, &page_id).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        delete_site_page(&pool, &page_id).await.expect("trash page");
+        purge_site_page(&pool, &page_id).await.expect("purge page");
+
+        assert!(get_site_page_by_id(&pool, &page_id).await.unwrap().is_none());
+        assert!(posts::get_site_post_by_id(&pool, &post_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_trash_honors_retention() {
+        let pool = create_test_pool().await;
+        let (old_id, _) = seed_page_with_post(&pool, "old").await;
+        let (recent_id, _) = seed_page_with_post(&pool, "recent").await;
+
+        delete_site_page(&pool, &recent_id).await.unwrap();
+        sqlx::query("UPDATE site_pages SET deleted_at = datetime('now', '-40 days') WHERE id = ?")
+            .bind(&old_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(purge_expired_trash(&pool, 30).await.unwrap(), 1);
+        assert!(get_site_page_by_id(&pool, &old_id).await.unwrap().is_none());
+        assert!(get_site_page_by_id(&pool, &recent_id).await.unwrap().is_some());
+    }
+}
@@ -1,19 +1,85 @@
-use crate::db::DbPool;
-use crate::models::{CreateSitePostRequest, SitePost, UpdateSitePostRequest};
+use crate::db::{timing::timed_query, DbPool};
+use crate::models::{
+    CreateSitePostRequest, Cursor, PostArchiveEntry, PublicPostSort, PublicPostSummary, SitePost, SitePostNeighbor, UpdateSitePostRequest,
+};
 use crate::repositories::common::validate_slug;
-use sqlx;
+use crate::utils::{markdown::derive_excerpt, textstats};
+use chrono::{DateTime, Utc};
+use sqlx::{self, FromRow};
+
+/// Applies the excerpt rules to a post before it is written.
+///
+/// A non-empty requested excerpt is treated as hand-written and disables
+/// auto-generation. An explicitly empty excerpt switches the post back to an
+/// auto excerpt. Auto excerpts are regenerated whenever the content changes,
+/// while hand-written excerpts are never overwritten.
+fn apply_excerpt_rules(post: &mut SitePost, requested_excerpt: Option<String>, content_changed: bool) {
+    match requested_excerpt {
+        Some(excerpt) if !excerpt.trim().is_empty() => {
+            post.excerpt = excerpt;
+            post.excerpt_auto = false;
+        }
+        Some(_) => {
+            post.excerpt_auto = true;
+            post.excerpt = derive_excerpt(&post.content_markdown);
+        }
+        None if post.excerpt_auto && content_changed => {
+            post.excerpt = derive_excerpt(&post.content_markdown);
+        }
+        None => {}
+    }
+}
 
 pub async fn list_site_posts_for_page(
     pool: &DbPool,
     page_id: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SitePost>, sqlx::Error> {
+    timed_query(
+        "posts.list_for_page",
+        sqlx::query_as::<_, SitePost>(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts
+             WHERE page_id = ?
+             ORDER BY order_index, created_at
+             LIMIT ? OFFSET ?",
+        )
+        .bind(page_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool),
+    )
+    .await
+}
+
+pub async fn count_site_posts_for_page(pool: &DbPool, page_id: &str) -> Result<i64, sqlx::Error> {
+    timed_query(
+        "posts.count_for_page",
+        sqlx::query_scalar("SELECT COUNT(*) FROM site_posts WHERE page_id = ?")
+            .bind(page_id)
+            .fetch_one(pool),
+    )
+    .await
+}
+
+/// Returns up to `limit` posts of live pages ordered by id, starting after
+/// `after`. Used to walk all posts in bounded batches.
+pub async fn list_site_posts_after(
+    pool: &DbPool,
+    after: &str,
+    limit: i64,
 ) -> Result<Vec<SitePost>, sqlx::Error> {
     sqlx::query_as::<_, SitePost>(
-        "SELECT id, page_id, title, slug, excerpt, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at
-         FROM site_posts
-         WHERE page_id = ?
-         ORDER BY order_index, created_at",
+        "SELECT p.id, p.page_id, p.title, p.slug, p.excerpt, p.excerpt_auto, p.content_markdown, p.word_count, p.reading_time_minutes, p.is_published, p.allow_comments, p.published_at, p.order_index, p.created_at, p.updated_at
+         FROM site_posts p
+         JOIN site_pages sp ON sp.id = p.page_id
+         WHERE sp.deleted_at IS NULL AND p.id > ?
+         ORDER BY p.id
+         LIMIT ?",
     )
-    .bind(page_id)
+    .bind(after)
+    .bind(limit)
     .fetch_all(pool)
     .await
 }
@@ -22,14 +88,146 @@ pub async fn list_published_posts_for_page(
     pool: &DbPool,
     page_id: &str,
 ) -> Result<Vec<SitePost>, sqlx::Error> {
-    sqlx::query_as::<_, SitePost>(
-        "SELECT id, page_id, title, slug, excerpt, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at
-         FROM site_posts
-         WHERE page_id = ? AND is_published = 1
-         ORDER BY order_index, COALESCE(published_at, created_at)",
+    timed_query(
+        "posts.list_published_for_page",
+        sqlx::query_as::<_, SitePost>(&format!(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts
+             WHERE page_id = ? AND {PUBLICLY_VISIBLE_POST}
+             ORDER BY order_index, COALESCE(published_at, created_at), id"
+        ))
+        .bind(page_id)
+        .fetch_all(pool),
+    )
+    .await
+}
+
+/// SQL predicate matching posts that are publicly visible right now.
+///
+/// A published post whose `published_at` lies in the future is scheduled and
+/// stays hidden until that moment has passed.
+pub(crate) const PUBLICLY_VISIBLE_POST: &str = "site_posts.is_published = 1 AND (site_posts.published_at IS NULL OR datetime(site_posts.published_at) <= datetime('now'))";
+
+/// Lists visible posts of a page whose `published_at` lies in `[start, end)`.
+///
+/// Bounds are ISO-8601 date strings computed by the caller, so the comparison
+/// is a plain lexicographic range on the stored timestamp.
+pub async fn list_published_posts_for_page_in_range(
+    pool: &DbPool,
+    page_id: &str,
+    start: &str,
+    end: &str,
+) -> Result<Vec<SitePost>, sqlx::Error> {
+    timed_query(
+        "posts.list_published_for_page_in_range",
+        sqlx::query_as::<_, SitePost>(&format!(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts
+             WHERE page_id = ? AND {PUBLICLY_VISIBLE_POST} AND published_at >= ? AND published_at < ?
+             ORDER BY order_index, published_at, id"
+        ))
+        .bind(page_id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(pool),
+    )
+    .await
+}
+
+/// The timestamp the public feed sorts by, and its cursors point at.
+///
+/// Normalized through `datetime()`, since the columns hold both SQLite's and
+/// RFC 3339 text (see [`crate::utils::timestamps`]), which do not compare as
+/// strings.
+const PUBLIC_POST_SORTED_AT: &str =
+    "datetime(COALESCE(site_posts.published_at, site_posts.created_at))";
+
+/// Lists visible posts across all published pages together with the total.
+///
+/// Posts of trashed or unpublished pages are excluded, as are posts of
+/// members-only pages unless `include_members_only` is set. With `after`,
+/// the newest-first listing continues after that post instead of skipping
+/// `offset` rows; the total still counts every visible post.
+pub async fn list_public_posts(
+    pool: &DbPool,
+    include_members_only: bool,
+    sort: PublicPostSort,
+    limit: i64,
+    offset: i64,
+    after: Option<&Cursor>,
+) -> Result<(Vec<PublicPostSummary>, i64), sqlx::Error> {
+    timed_query("posts.list_public", async {
+        let visibility_filter = if include_members_only {
+            ""
+        } else {
+            "AND site_pages.visibility = 'public'"
+        };
+        let filter = format!(
+            "site_pages.is_published = 1 AND site_pages.deleted_at IS NULL {visibility_filter} AND {PUBLICLY_VISIBLE_POST}"
+        );
+        let order = match sort {
+            PublicPostSort::PublishedAt => "sorted_at DESC, site_posts.id DESC",
+            PublicPostSort::Title => "site_posts.title COLLATE NOCASE, site_posts.id",
+        };
+        let (position, skip) = match after {
+            Some(_) => (
+                format!("AND ({PUBLIC_POST_SORTED_AT}, site_posts.id) < (datetime(?), ?)"),
+                "",
+            ),
+            None => (String::new(), "OFFSET ?"),
+        };
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM site_posts JOIN site_pages ON site_pages.id = site_posts.page_id WHERE {filter}"
+        ))
+        .fetch_one(pool)
+        .await?;
+
+        let sql = format!(
+            "SELECT site_posts.id, site_posts.title, site_posts.slug, site_posts.excerpt, site_posts.published_at,
+                    site_posts.word_count, site_posts.reading_time_minutes,
+                    site_pages.slug AS page_slug, site_pages.title AS page_title,
+                    {PUBLIC_POST_SORTED_AT} AS sorted_at
+             FROM site_posts
+             JOIN site_pages ON site_pages.id = site_posts.page_id
+             WHERE {filter} {position}
+             ORDER BY {order}
+             LIMIT ? {skip}"
+        );
+        let mut query = sqlx::query_as::<_, PublicPostSummary>(&sql);
+        if let Some(cursor) = after {
+            query = query.bind(&cursor.created_at).bind(&cursor.id);
+        }
+        query = query.bind(limit);
+        if after.is_none() {
+            query = query.bind(offset);
+        }
+        let items = query.fetch_all(pool).await?;
+
+        Ok((items, total))
+    })
+    .await
+}
+
+/// Counts visible posts of a page per publication month, newest first.
+pub async fn list_post_archive(
+    pool: &DbPool,
+    page_id: &str,
+) -> Result<Vec<PostArchiveEntry>, sqlx::Error> {
+    timed_query(
+        "posts.archive",
+        sqlx::query_as::<_, PostArchiveEntry>(&format!(
+            "SELECT CAST(strftime('%Y', published_at) AS INTEGER) AS year,
+                    CAST(strftime('%m', published_at) AS INTEGER) AS month,
+                    COUNT(*) AS count
+             FROM site_posts
+             WHERE page_id = ? AND {PUBLICLY_VISIBLE_POST} AND strftime('%Y-%m', published_at) IS NOT NULL
+             GROUP BY strftime('%Y-%m', published_at)
+             ORDER BY year DESC, month DESC"
+        ))
+        .bind(page_id)
+        .fetch_all(pool),
     )
-    .bind(page_id)
-    .fetch_all(pool)
     .await
 }
 
@@ -38,24 +236,92 @@ pub async fn get_published_post_by_slug(
     page_id: &str,
     post_slug: &str,
 ) -> Result<Option<SitePost>, sqlx::Error> {
-    sqlx::query_as::<_, SitePost>(
-        "SELECT id, page_id, title, slug, excerpt, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at
-         FROM site_posts
-         WHERE page_id = ? AND slug = ? AND is_published = 1",
+    timed_query(
+        "posts.get_published_by_slug",
+        sqlx::query_as::<_, SitePost>(&format!(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts
+             WHERE page_id = ? AND slug = ? AND {PUBLICLY_VISIBLE_POST}"
+        ))
+        .bind(page_id)
+        .bind(post_slug)
+        .fetch_optional(pool),
     )
-    .bind(page_id)
-    .bind(post_slug)
-    .fetch_optional(pool)
+    .await
+}
+
+#[derive(Debug, FromRow)]
+struct NeighborRow {
+    prev_id: Option<String>,
+    prev_slug: Option<String>,
+    prev_title: Option<String>,
+    next_id: Option<String>,
+    next_slug: Option<String>,
+    next_title: Option<String>,
+}
+
+fn neighbor_from_parts(
+    id: Option<String>,
+    slug: Option<String>,
+    title: Option<String>,
+) -> Option<SitePostNeighbor> {
+    match (id, slug, title) {
+        (Some(id), Some(slug), Some(title)) => Some(SitePostNeighbor { id, slug, title }),
+        _ => None,
+    }
+}
+
+/// Returns the previous and next visible posts of the same page.
+///
+/// Posts are ordered the same way as the public listing: by `order_index`,
+/// then publication date (falling back to creation date), then id so that
+/// posts sharing an order index still have a stable sequence.
+pub async fn get_published_post_neighbors(
+    pool: &DbPool,
+    page_id: &str,
+    post_id: &str,
+) -> Result<(Option<SitePostNeighbor>, Option<SitePostNeighbor>), sqlx::Error> {
+    timed_query("posts.get_published_neighbors", async {
+        let row = sqlx::query_as::<_, NeighborRow>(&format!(
+            "SELECT prev_id, prev_slug, prev_title, next_id, next_slug, next_title FROM (
+                 SELECT id,
+                        LAG(id) OVER w AS prev_id,
+                        LAG(slug) OVER w AS prev_slug,
+                        LAG(title) OVER w AS prev_title,
+                        LEAD(id) OVER w AS next_id,
+                        LEAD(slug) OVER w AS next_slug,
+                        LEAD(title) OVER w AS next_title
+                 FROM site_posts
+                 WHERE page_id = ? AND {PUBLICLY_VISIBLE_POST}
+                 WINDOW w AS (ORDER BY order_index, COALESCE(published_at, created_at), id)
+             ) WHERE id = ?"
+        ))
+        .bind(page_id)
+        .bind(post_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(match row {
+            Some(row) => (
+                neighbor_from_parts(row.prev_id, row.prev_slug, row.prev_title),
+                neighbor_from_parts(row.next_id, row.next_slug, row.next_title),
+            ),
+            None => (None, None),
+        })
+    })
     .await
 }
 
 pub async fn get_site_post_by_id(pool: &DbPool, id: &str) -> Result<Option<SitePost>, sqlx::Error> {
-    sqlx::query_as::<_, SitePost>(
-        "SELECT id, page_id, title, slug, excerpt, content_markdown, is_published, allow_comments, published_at, order_index, created_at, updated_at
-         FROM site_posts WHERE id = ?",
+    timed_query(
+        "posts.get_by_id",
+        sqlx::query_as::<_, SitePost>(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(pool),
     )
-    .bind(id)
-    .fetch_optional(pool)
     .await
 }
 
@@ -64,32 +330,42 @@ pub async fn create_site_post(
     page_id: &str,
     payload: CreateSitePostRequest,
 ) -> Result<SitePost, sqlx::Error> {
-    validate_slug(&payload.slug)?;
+    timed_query("posts.create", async {
+        validate_slug(&payload.slug)?;
 
-    let id = uuid::Uuid::new_v4().to_string();
-    let excerpt = payload.excerpt.unwrap_or_default();
-    let order_index = payload.order_index.unwrap_or(0);
+        let id = uuid::Uuid::new_v4().to_string();
+        let (excerpt, excerpt_auto) = match payload.excerpt {
+            Some(excerpt) if !excerpt.trim().is_empty() => (excerpt, false),
+            _ => (derive_excerpt(&payload.content_markdown), true),
+        };
+        let order_index = payload.order_index.unwrap_or(0);
+        let stats = textstats::compute(&payload.content_markdown);
 
-    sqlx::query(
-        "INSERT INTO site_posts (id, page_id, title, slug, excerpt, content_markdown, is_published, allow_comments, published_at, order_index)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(&id)
-    .bind(page_id)
-    .bind(&payload.title)
-    .bind(&payload.slug)
-    .bind(excerpt)
-    .bind(&payload.content_markdown)
-    .bind(if payload.is_published { 1 } else { 0 })
-    .bind(if payload.allow_comments { 1 } else { 0 })
-    .bind(payload.published_at)
-    .bind(order_index)
-    .execute(pool)
-    .await?;
+        sqlx::query(
+            "INSERT INTO site_posts (id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(page_id)
+        .bind(&payload.title)
+        .bind(&payload.slug)
+        .bind(excerpt)
+        .bind(if excerpt_auto { 1 } else { 0 })
+        .bind(&payload.content_markdown)
+        .bind(stats.word_count)
+        .bind(stats.reading_time_minutes)
+        .bind(if payload.is_published { 1 } else { 0 })
+        .bind(if payload.allow_comments { 1 } else { 0 })
+        .bind(payload.published_at)
+        .bind(order_index)
+        .execute(pool)
+        .await?;
 
-    get_site_post_by_id(pool, &id)
-        .await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)
+        get_site_post_by_id(pool, &id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    })
+    .await
 }
 
 pub async fn update_site_post(
@@ -97,59 +373,67 @@ pub async fn update_site_post(
     id: &str,
     payload: UpdateSitePostRequest,
 ) -> Result<SitePost, sqlx::Error> {
-    if let Some(slug) = payload.slug.as_deref() {
-        validate_slug(slug)?;
-    }
+    timed_query("posts.update", async {
+        if let Some(slug) = payload.slug.as_deref() {
+            validate_slug(slug)?;
+        }
 
-    let mut existing = get_site_post_by_id(pool, id)
-        .await?
-        .ok_or(sqlx::Error::RowNotFound)?;
+        let mut existing = get_site_post_by_id(pool, id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
 
-    if let Some(title) = payload.title {
-        existing.title = title;
-    }
-    if let Some(slug) = payload.slug {
-        existing.slug = slug;
-    }
-    if let Some(excerpt) = payload.excerpt {
-        existing.excerpt = excerpt;
-    }
-    if let Some(content) = payload.content_markdown {
-        existing.content_markdown = content;
-    }
-    if let Some(is_published) = payload.is_published {
-        existing.is_published = is_published;
-    }
-    if let Some(allow_comments) = payload.allow_comments {
-        existing.allow_comments = allow_comments;
-    }
-    if let Some(published_at) = payload.published_at {
-        existing.published_at = published_at;
-    }
-    if let Some(order_index) = payload.order_index {
-        existing.order_index = order_index;
-    }
+        if let Some(title) = payload.title {
+            existing.title = title;
+        }
+        if let Some(slug) = payload.slug {
+            existing.slug = slug;
+        }
+        let content_changed = payload.content_markdown.is_some();
+        if let Some(content) = payload.content_markdown {
+            let stats = textstats::compute(&content);
+            existing.word_count = stats.word_count;
+            existing.reading_time_minutes = stats.reading_time_minutes;
+            existing.content_markdown = content;
+        }
+        apply_excerpt_rules(&mut existing, payload.excerpt, content_changed);
+        if let Some(is_published) = payload.is_published {
+            existing.is_published = is_published;
+        }
+        if let Some(allow_comments) = payload.allow_comments {
+            existing.allow_comments = allow_comments;
+        }
+        if let Some(published_at) = payload.published_at {
+            existing.published_at = published_at;
+        }
+        if let Some(order_index) = payload.order_index {
+            existing.order_index = order_index;
+        }
 
-    sqlx::query(
-        "UPDATE site_posts
-         SET title = ?, slug = ?, excerpt = ?, content_markdown = ?, is_published = ?, allow_comments = ?, published_at = ?, order_index = ?, updated_at = CURRENT_TIMESTAMP
-         WHERE id = ?",
-    )
-    .bind(&existing.title)
-    .bind(&existing.slug)
-    .bind(&existing.excerpt)
-    .bind(&existing.content_markdown)
-    .bind(if existing.is_published { 1 } else { 0 })
-    .bind(if existing.allow_comments { 1 } else { 0 })
-    .bind(&existing.published_at)
-    .bind(existing.order_index)
-    .bind(id)
-    .execute(pool)
-    .await?;
+        sqlx::query(
+            "UPDATE site_posts
+             SET title = ?, slug = ?, excerpt = ?, excerpt_auto = ?, content_markdown = ?, word_count = ?, reading_time_minutes = ?, is_published = ?, allow_comments = ?, published_at = ?, order_index = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&existing.title)
+        .bind(&existing.slug)
+        .bind(&existing.excerpt)
+        .bind(if existing.excerpt_auto { 1 } else { 0 })
+        .bind(&existing.content_markdown)
+        .bind(existing.word_count)
+        .bind(existing.reading_time_minutes)
+        .bind(if existing.is_published { 1 } else { 0 })
+        .bind(if existing.allow_comments { 1 } else { 0 })
+        .bind(existing.published_at)
+        .bind(existing.order_index)
+        .bind(id)
+        .execute(pool)
+        .await?;
 
-    get_site_post_by_id(pool, id)
-        .await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)
+        get_site_post_by_id(pool, id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    })
+    .await
 }
 
 pub async fn delete_site_post(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
@@ -165,6 +449,230 @@ pub async fn delete_site_post(pool: &DbPool, id: &str) -> Result<(), sqlx::Error
     }
 }
 
+/// Publishes or unpublishes several posts of one page in a single transaction.
+///
+/// `ids = None` targets every post of the page, failing when there are more
+/// than `cap`. Explicit ids must all belong to `page_id`. Publishing sets
+/// `published_at` to the supplied value or to `now`, also for posts that were
+/// published before; unpublishing only flips the flag.
+pub async fn bulk_set_posts_published(
+    pool: &DbPool,
+    page_id: &str,
+    ids: Option<&[String]>,
+    publish: bool,
+    published_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    cap: usize,
+) -> Result<Vec<SitePost>, sqlx::Error> {
+    timed_query("posts.bulk_set_published", async {
+        let mut tx = pool.begin().await?;
+
+        let target_ids: Vec<String> = match ids {
+            None => {
+                let ids: Vec<String> =
+                    sqlx::query_scalar("SELECT id FROM site_posts WHERE page_id = ? ORDER BY order_index, created_at")
+                        .bind(page_id)
+                        .fetch_all(&mut *tx)
+                        .await?;
+                if ids.len() > cap {
+                    return Err(sqlx::Error::Protocol(format!(
+                        "Page has {} posts; bulk updates are limited to {cap}",
+                        ids.len()
+                    )));
+                }
+                ids
+            }
+            Some(ids) => {
+                let mut foreign = Vec::new();
+                for id in ids {
+                    let owner: Option<String> =
+                        sqlx::query_scalar("SELECT page_id FROM site_posts WHERE id = ?")
+                            .bind(id)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                    if owner.as_deref() != Some(page_id) {
+                        foreign.push(id.as_str());
+                    }
+                }
+                if !foreign.is_empty() {
+                    return Err(sqlx::Error::Protocol(format!(
+                        "Posts do not belong to this page: {}",
+                        foreign.join(", ")
+                    )));
+                }
+                ids.to_vec()
+            }
+        };
+
+        for id in &target_ids {
+            if publish {
+                sqlx::query(
+                    "UPDATE site_posts
+                     SET is_published = 1, published_at = COALESCE(?, ?), updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?",
+                )
+                .bind(published_at)
+                .bind(now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            } else {
+                sqlx::query(
+                    "UPDATE site_posts SET is_published = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                )
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        // A negative LIMIT is unbounded in SQLite: the caller gets every post
+        list_site_posts_for_page(pool, page_id, -1, 0).await
+    })
+    .await
+}
+
+/// Result of moving a post to another page.
+#[derive(Debug)]
+pub enum PostMoveOutcome {
+    Moved(Box<SitePost>),
+    /// The slug is already taken on the target page and `keep_slug` was set.
+    SlugConflict,
+}
+
+const MAX_SLUG_SUFFIX: u32 = 100;
+
+/// Picks `slug`, `slug-2`, `slug-3`, ... whichever is still free on `page_id`.
+async fn free_slug_on_page(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    page_id: &str,
+    slug: &str,
+) -> Result<String, sqlx::Error> {
+    for attempt in 1..=MAX_SLUG_SUFFIX {
+        let candidate = if attempt == 1 {
+            slug.to_string()
+        } else {
+            let suffix = format!("-{attempt}");
+            let base: String = slug.chars().take(100 - suffix.len()).collect();
+            format!("{}{suffix}", base.trim_end_matches('-'))
+        };
+
+        let taken: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM site_posts WHERE page_id = ? AND slug = ?")
+                .bind(page_id)
+                .bind(&candidate)
+                .fetch_optional(&mut **tx)
+                .await?;
+        if taken.is_none() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(sqlx::Error::Protocol(format!(
+        "Could not find a free slug for '{slug}' on the target page"
+    )))
+}
+
+/// Re-parents a post onto another page, appending it after the target's
+/// existing posts. Comments reference the post id and are unaffected.
+pub async fn move_site_post(
+    pool: &DbPool,
+    id: &str,
+    target_page_id: &str,
+    keep_slug: bool,
+) -> Result<PostMoveOutcome, sqlx::Error> {
+    timed_query("posts.move", async {
+        let mut tx = pool.begin().await?;
+
+        let slug: String = sqlx::query_scalar("SELECT slug FROM site_posts WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        let target_exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM site_pages WHERE id = ?")
+            .bind(target_page_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if target_exists.is_none() {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        let new_slug = if keep_slug {
+            let taken: Option<i64> =
+                sqlx::query_scalar("SELECT 1 FROM site_posts WHERE page_id = ? AND slug = ? AND id != ?")
+                    .bind(target_page_id)
+                    .bind(&slug)
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if taken.is_some() {
+                return Ok(PostMoveOutcome::SlugConflict);
+            }
+            slug
+        } else {
+            free_slug_on_page(&mut tx, target_page_id, &slug).await?
+        };
+
+        let order_index: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(order_index) + 1, 0) FROM site_posts WHERE page_id = ? AND id != ?",
+        )
+        .bind(target_page_id)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE site_posts SET page_id = ?, slug = ?, order_index = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(target_page_id)
+        .bind(&new_slug)
+        .bind(order_index)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        get_site_post_by_id(pool, id)
+            .await?
+            .map(|post| PostMoveOutcome::Moved(Box::new(post)))
+            .ok_or(sqlx::Error::RowNotFound)
+    })
+    .await
+}
+
+/// Whether the post is publicly visible right now, see [`PUBLICLY_VISIBLE_POST`].
+pub async fn is_post_publicly_visible(pool: &DbPool, id: &str) -> Result<bool, sqlx::Error> {
+    let visible: Option<(i64,)> = sqlx::query_as(&format!(
+        "SELECT 1 FROM site_posts WHERE id = ? AND {PUBLICLY_VISIBLE_POST}"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(visible.is_some())
+}
+
+/// Lists visible posts whose content still links to draft uploads, such as
+/// scheduled posts that went live since they were saved.
+pub async fn list_visible_posts_linking_drafts(
+    pool: &DbPool,
+) -> Result<Vec<SitePost>, sqlx::Error> {
+    timed_query(
+        "posts.list_visible_linking_drafts",
+        sqlx::query_as::<_, SitePost>(&format!(
+            "SELECT id, page_id, title, slug, excerpt, excerpt_auto, content_markdown, word_count, reading_time_minutes, is_published, allow_comments, published_at, order_index, created_at, updated_at
+             FROM site_posts
+             WHERE {PUBLICLY_VISIBLE_POST} AND instr(content_markdown, '/uploads/private/') > 0
+             ORDER BY id"
+        ))
+        .fetch_all(pool),
+    )
+    .await
+}
+
 pub async fn check_post_exists(pool: &DbPool, id: &str) -> Result<bool, sqlx::Error> {
     let exists: Option<(i64,)> = sqlx::query_as("SELECT 1 FROM site_posts WHERE id = ?")
         .bind(id)
@@ -172,3 +680,589 @@ pub async fn check_post_exists(pool: &DbPool, id: &str) -> Result<bool, sqlx::Er
         .await?;
     Ok(exists.is_some())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+    use crate::models::CreateSitePageRequest;
+    use crate::repositories::pages;
+
+    fn sample_post(excerpt: &str, excerpt_auto: bool) -> SitePost {
+        SitePost {
+            id: "post-1".to_string(),
+            page_id: "page-1".to_string(),
+            title: "Title".to_string(),
+            slug: "title".to_string(),
+            excerpt: excerpt.to_string(),
+            excerpt_auto,
+            content_markdown: "Original body".to_string(),
+            word_count: 2,
+            reading_time_minutes: 1,
+            is_published: false,
+            allow_comments: true,
+            published_at: None,
+            order_index: 0,
+            created_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+            updated_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_auto_excerpt_regenerates_on_content_change() {
+        let mut post = sample_post("Original body", true);
+        post.content_markdown = "# New\n\nUpdated body".to_string();
+        apply_excerpt_rules(&mut post, None, true);
+        assert_eq!(post.excerpt, "New Updated body");
+        assert!(post.excerpt_auto);
+    }
+
+    #[test]
+    fn test_manual_excerpt_is_never_overwritten() {
+        let mut post = sample_post("Hand-written summary", false);
+        post.content_markdown = "Completely different body".to_string();
+        apply_excerpt_rules(&mut post, None, true);
+        assert_eq!(post.excerpt, "Hand-written summary");
+        assert!(!post.excerpt_auto);
+    }
+
+    #[test]
+    fn test_explicit_excerpt_disables_auto_generation() {
+        let mut post = sample_post("Original body", true);
+        apply_excerpt_rules(&mut post, Some("Custom".to_string()), false);
+        assert_eq!(post.excerpt, "Custom");
+        assert!(!post.excerpt_auto);
+    }
+
+    #[test]
+    fn test_empty_excerpt_switches_back_to_auto() {
+        let mut post = sample_post("Hand-written summary", false);
+        apply_excerpt_rules(&mut post, Some("   ".to_string()), false);
+        assert_eq!(post.excerpt, "Original body");
+        assert!(post.excerpt_auto);
+    }
+
+    #[test]
+    fn test_auto_excerpt_untouched_without_content_change() {
+        let mut post = sample_post("Stale auto excerpt", true);
+        apply_excerpt_rules(&mut post, None, false);
+        assert_eq!(post.excerpt, "Stale auto excerpt");
+    }
+
+    async fn seed_page(pool: &DbPool, slug: &str) -> String {
+        pages::create_site_page(
+            pool,
+            CreateSitePageRequest {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                description: None,
+                nav_label: None,
+                show_in_nav: false,
+                order_index: None,
+                is_published: true,
+                visibility: "public".to_string(),
+                hero: serde_json::json!({}),
+                layout: serde_json::json!({}),
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("create page")
+        .id
+    }
+
+    async fn seed_post(
+        pool: &DbPool,
+        page_id: &str,
+        slug: &str,
+        order_index: i64,
+        published_at: &str,
+    ) -> String {
+        create_site_post(
+            pool,
+            page_id,
+            CreateSitePostRequest {
+                title: slug.to_uppercase(),
+                slug: slug.to_string(),
+                excerpt: None,
+                content_markdown: "Body".to_string(),
+                is_published: true,
+                allow_comments: true,
+                published_at: Some(published_at.parse().unwrap()),
+                order_index: Some(order_index),
+            },
+        )
+        .await
+        .expect("create post")
+        .id
+    }
+
+    #[tokio::test]
+    async fn test_published_post_neighbors() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "neighbors").await;
+
+        // "b" and "c" share an order_index and are ordered by published_at.
+        let a = seed_post(&pool, &page_id, "a", 0, "2024-01-01T00:00:00Z").await;
+        let c = seed_post(&pool, &page_id, "c", 1, "2024-01-03T00:00:00Z").await;
+        let b = seed_post(&pool, &page_id, "b", 1, "2024-01-02T00:00:00Z").await;
+        let d = seed_post(&pool, &page_id, "d", 2, "2024-01-01T00:00:00Z").await;
+        let e = seed_post(&pool, &page_id, "e", 3, "2024-01-01T00:00:00Z").await;
+
+        let ordered = [&a, &b, &c, &d, &e];
+        for (idx, id) in ordered.iter().enumerate() {
+            let (previous, next) = get_published_post_neighbors(&pool, &page_id, id)
+                .await
+                .expect("neighbors");
+            let expected_prev = idx.checked_sub(1).map(|i| ordered[i].as_str());
+            let expected_next = ordered.get(idx + 1).map(|id| id.as_str());
+            assert_eq!(previous.as_ref().map(|n| n.id.as_str()), expected_prev);
+            assert_eq!(next.as_ref().map(|n| n.id.as_str()), expected_next);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_skip_unpublished_posts() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "drafts").await;
+
+        let first = seed_post(&pool, &page_id, "first", 0, "2024-01-01T00:00:00Z").await;
+        let draft = seed_post(&pool, &page_id, "draft", 1, "2024-01-02T00:00:00Z").await;
+        let last = seed_post(&pool, &page_id, "last", 2, "2024-01-03T00:00:00Z").await;
+
+        update_site_post(
+            &pool,
+            &draft,
+            UpdateSitePostRequest {
+                title: None,
+                slug: None,
+                excerpt: None,
+                content_markdown: None,
+                is_published: Some(false),
+                allow_comments: None,
+                published_at: None,
+                order_index: None,
+            },
+        )
+        .await
+        .expect("unpublish");
+
+        let (previous, next) = get_published_post_neighbors(&pool, &page_id, &first)
+            .await
+            .expect("neighbors");
+        assert!(previous.is_none());
+        assert_eq!(next.map(|n| n.id), Some(last.clone()));
+    }
+
+    #[tokio::test]
+    async fn test_post_archive_groups_by_month_boundaries() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "archive").await;
+
+        seed_post(&pool, &page_id, "jan-first", 0, "2024-01-01T00:00:00Z").await;
+        seed_post(&pool, &page_id, "jan-last", 1, "2024-01-31T23:59:59Z").await;
+        seed_post(&pool, &page_id, "feb-first", 2, "2024-02-01T00:00:00Z").await;
+        // Only scheduled content in 2999-05: the month must not show up.
+        seed_post(&pool, &page_id, "scheduled", 3, "2999-05-10T00:00:00Z").await;
+
+        let archive = list_post_archive(&pool, &page_id).await.expect("archive");
+        assert_eq!(
+            archive,
+            vec![
+                PostArchiveEntry { year: 2024, month: 2, count: 1 },
+                PostArchiveEntry { year: 2024, month: 1, count: 2 },
+            ]
+        );
+
+        let january =
+            list_published_posts_for_page_in_range(&pool, &page_id, "2024-01-01", "2024-02-01")
+                .await
+                .expect("range");
+        let slugs: Vec<_> = january.into_iter().map(|p| p.slug).collect();
+        assert_eq!(slugs, vec!["jan-first", "jan-last"]);
+
+        let empty =
+            list_published_posts_for_page_in_range(&pool, &page_id, "2024-03-01", "2024-04-01")
+                .await
+                .expect("range");
+        assert!(empty.is_empty());
+
+        let scheduled =
+            list_published_posts_for_page_in_range(&pool, &page_id, "2999-05-01", "2999-06-01")
+                .await
+                .expect("range");
+        assert!(scheduled.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_archive_is_empty_without_published_posts() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "empty-archive").await;
+
+        let archive = list_post_archive(&pool, &page_id).await.expect("archive");
+        assert!(archive.is_empty());
+    }
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    /// The `now` bulk publishing falls back to.
+    fn bulk_now() -> DateTime<Utc> {
+        "2024-06-01T00:00:00Z".parse().unwrap()
+    }
+
+    async fn seed_draft(pool: &DbPool, page_id: &str, slug: &str) -> String {
+        create_site_post(
+            pool,
+            page_id,
+            CreateSitePostRequest {
+                title: slug.to_uppercase(),
+                slug: slug.to_string(),
+                excerpt: None,
+                content_markdown: "Body".to_string(),
+                is_published: false,
+                allow_comments: true,
+                published_at: None,
+                order_index: None,
+            },
+        )
+        .await
+        .expect("create draft")
+        .id
+    }
+
+    #[tokio::test]
+    async fn test_bulk_publish_rejects_cross_page_ids() {
+        let pool = create_test_pool().await;
+        let page_a = seed_page(&pool, "bulk-a").await;
+        let page_b = seed_page(&pool, "bulk-b").await;
+        let own = seed_draft(&pool, &page_a, "own").await;
+        let foreign = seed_draft(&pool, &page_b, "foreign").await;
+
+        let err = bulk_set_posts_published(
+            &pool,
+            &page_a,
+            Some(&ids(&[&own, &foreign, "missing"])),
+            true,
+            None,
+            bulk_now(),
+            10,
+        )
+        .await
+        .expect_err("foreign ids must be rejected");
+        match err {
+            sqlx::Error::Protocol(message) => {
+                assert!(message.contains(&foreign));
+                assert!(message.contains("missing"));
+                assert!(!message.contains(&own));
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+
+        // Nothing was applied, not even to the valid id.
+        let post = get_site_post_by_id(&pool, &own).await.unwrap().unwrap();
+        assert!(!post.is_published);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_publish_sets_published_at() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "bulk-dates").await;
+        let draft = seed_draft(&pool, &page_id, "draft").await;
+        let dated = seed_post(&pool, &page_id, "dated", 1, "2023-03-03T00:00:00Z").await;
+
+        let posts = bulk_set_posts_published(&pool, &page_id, None, true, None, bulk_now(), 10)
+            .await
+            .expect("bulk publish");
+        assert!(posts.iter().all(|post| post.is_published));
+
+        let by_id = |id: &str| posts.iter().find(|post| post.id == id).unwrap().published_at;
+        assert_eq!(by_id(&draft), "2024-06-01T00:00:00Z".parse().ok());
+        assert_eq!(by_id(&dated), "2024-06-01T00:00:00Z".parse().ok());
+
+        let posts = bulk_set_posts_published(
+            &pool,
+            &page_id,
+            Some(&ids(&[&draft])),
+            true,
+            "2025-01-01T08:00:00Z".parse().ok(),
+            bulk_now(),
+            10,
+        )
+        .await
+        .expect("bulk publish with explicit date");
+        let draft_post = posts.iter().find(|post| post.id == draft).unwrap();
+        assert_eq!(draft_post.published_at, "2025-01-01T08:00:00Z".parse().ok());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_unpublish_only_flips_flag() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "bulk-unpublish").await;
+        let first = seed_post(&pool, &page_id, "first", 0, "2024-01-01T00:00:00Z").await;
+        let second = seed_post(&pool, &page_id, "second", 1, "2024-01-02T00:00:00Z").await;
+
+        let posts = bulk_set_posts_published(
+            &pool,
+            &page_id,
+            Some(&ids(&[&first])),
+            false,
+            None,
+            bulk_now(),
+            10,
+        )
+        .await
+        .expect("bulk unpublish");
+
+        let first_post = posts.iter().find(|post| post.id == first).unwrap();
+        assert!(!first_post.is_published);
+        assert_eq!(first_post.published_at, "2024-01-01T00:00:00Z".parse().ok());
+        assert_eq!(first_post.title, "FIRST");
+        assert!(posts.iter().find(|post| post.id == second).unwrap().is_published);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_republish_moves_published_at_to_now() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "bulk-republish").await;
+        let post = seed_post(&pool, &page_id, "post", 0, "2024-01-01T00:00:00Z").await;
+        let selection = ids(&[&post]);
+
+        bulk_set_posts_published(&pool, &page_id, Some(&selection), false, None, bulk_now(), 10)
+            .await
+            .expect("bulk unpublish");
+        let posts =
+            bulk_set_posts_published(&pool, &page_id, Some(&selection), true, None, bulk_now(), 10)
+                .await
+                .expect("bulk republish");
+
+        let republished = posts.iter().find(|item| item.id == post).unwrap();
+        assert!(republished.is_published);
+        assert_eq!(republished.published_at, Some(bulk_now()));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_all_respects_cap() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "bulk-cap").await;
+        seed_draft(&pool, &page_id, "one").await;
+        seed_draft(&pool, &page_id, "two").await;
+
+        let result =
+            bulk_set_posts_published(&pool, &page_id, None, true, None, bulk_now(), 1).await;
+        assert!(matches!(result, Err(sqlx::Error::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn test_text_stats_follow_content_updates() {
+        let pool = create_test_pool().await;
+        let page_id = seed_page(&pool, "stats").await;
+        let id = seed_post(&pool, &page_id, "stats", 0, "2024-01-01T00:00:00Z").await;
+
+        let post = get_site_post_by_id(&pool, &id).await.unwrap().unwrap();
+        assert_eq!((post.word_count, post.reading_time_minutes), (1, 1));
+
+        let updated = update_site_post(
+            &pool,
+            &id,
+            UpdateSitePostRequest {
+                title: None,
+                slug: None,
+                excerpt: None,
+                content_markdown: Some(format!("{}\n```\ncode only\n```", "wort ".repeat(250))),
+                is_published: None,
+                allow_comments: None,
+                published_at: None,
+                order_index: None,
+            },
+        )
+        .await
+        .expect("update");
+        assert_eq!((updated.word_count, updated.reading_time_minutes), (250, 2));
+    }
+
+    fn moved(outcome: PostMoveOutcome) -> SitePost {
+        match outcome {
+            PostMoveOutcome::Moved(post) => *post,
+            PostMoveOutcome::SlugConflict => panic!("unexpected slug conflict"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_move_post_suffixes_colliding_slug() {
+        let pool = create_test_pool().await;
+        let source = seed_page(&pool, "move-source").await;
+        let target = seed_page(&pool, "move-target").await;
+        let post = seed_post(&pool, &source, "intro", 0, "2024-01-01T00:00:00Z").await;
+        seed_post(&pool, &target, "intro", 3, "2024-01-01T00:00:00Z").await;
+        seed_post(&pool, &target, "intro-2", 7, "2024-01-01T00:00:00Z").await;
+
+        let post = moved(move_site_post(&pool, &post, &target, false).await.expect("move"));
+        assert_eq!(post.page_id, target);
+        assert_eq!(post.slug, "intro-3");
+        assert_eq!(post.order_index, 8);
+    }
+
+    #[tokio::test]
+    async fn test_move_post_keep_slug_conflicts() {
+        let pool = create_test_pool().await;
+        let source = seed_page(&pool, "keep-source").await;
+        let target = seed_page(&pool, "keep-target").await;
+        let post = seed_post(&pool, &source, "intro", 0, "2024-01-01T00:00:00Z").await;
+        seed_post(&pool, &target, "intro", 0, "2024-01-01T00:00:00Z").await;
+
+        let outcome = move_site_post(&pool, &post, &target, true).await.expect("move");
+        assert!(matches!(outcome, PostMoveOutcome::SlugConflict));
+
+        let unchanged = get_site_post_by_id(&pool, &post).await.unwrap().unwrap();
+        assert_eq!(unchanged.page_id, source);
+    }
+
+    #[tokio::test]
+    async fn test_move_post_to_empty_page_keeps_slug() {
+        let pool = create_test_pool().await;
+        let source = seed_page(&pool, "empty-source").await;
+        let target = seed_page(&pool, "empty-target").await;
+        let post = seed_post(&pool, &source, "intro", 5, "2024-01-01T00:00:00Z").await;
+
+        let post = moved(move_site_post(&pool, &post, &target, true).await.expect("move"));
+        assert_eq!(post.slug, "intro");
+        assert_eq!(post.order_index, 0);
+    }
+
+    #[tokio::test]
+    async fn test_move_post_to_missing_page() {
+        let pool = create_test_pool().await;
+        let source = seed_page(&pool, "missing-source").await;
+        let post = seed_post(&pool, &source, "intro", 0, "2024-01-01T00:00:00Z").await;
+
+        let result = move_site_post(&pool, &post, "does-not-exist", false).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_public_posts_span_pages_and_skip_hidden_pages() {
+        let pool = create_test_pool().await;
+        let first = seed_page(&pool, "first-page").await;
+        let second = seed_page(&pool, "second-page").await;
+        let hidden = seed_page(&pool, "hidden-page").await;
+
+        seed_post(&pool, &first, "alpha", 0, "2024-01-01T00:00:00Z").await;
+        seed_post(&pool, &second, "beta", 0, "2024-03-01T00:00:00Z").await;
+        seed_post(&pool, &first, "gamma", 1, "2024-02-01T00:00:00Z").await;
+        seed_post(&pool, &second, "scheduled", 1, "2999-01-01T00:00:00Z").await;
+        seed_post(&pool, &hidden, "secret", 0, "2024-04-01T00:00:00Z").await;
+
+        pages::update_site_page(
+            &pool,
+            &hidden,
+            crate::models::UpdateSitePageRequest {
+                slug: None,
+                title: None,
+                description: None,
+                nav_label: None,
+                show_in_nav: None,
+                order_index: None,
+                is_published: Some(false),
+                visibility: None,
+                hero: None,
+                layout: None,
+                meta_title: None,
+                meta_description: None,
+                og_image: None,
+            },
+        )
+        .await
+        .expect("unpublish page");
+
+        let (items, total) =
+            list_public_posts(&pool, false, PublicPostSort::PublishedAt, 10, 0, None)
+                .await
+                .expect("list");
+        assert_eq!(total, 3);
+        let slugs: Vec<_> = items.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["beta", "gamma", "alpha"]);
+        assert_eq!(items[0].page_slug, "second-page");
+        assert_eq!(items[1].page_title, "first-page");
+
+        let (items, total) = list_public_posts(&pool, false, PublicPostSort::Title, 2, 1, None)
+            .await
+            .expect("list");
+        assert_eq!(total, 3);
+        let slugs: Vec<_> = items.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["beta", "gamma"]);
+    }
+
+    #[tokio::test]
+    async fn test_public_post_cursor_breaks_ties_by_id() {
+        let pool = create_test_pool().await;
+        let page = seed_page(&pool, "feed").await;
+        for slug in ["one", "two", "three"] {
+            seed_post(&pool, &page, slug, 0, "2024-02-01T00:00:00Z").await;
+        }
+        seed_post(&pool, &page, "older", 0, "2024-01-01T00:00:00Z").await;
+
+        let mut seen = Vec::new();
+        let mut after: Option<Cursor> = None;
+        loop {
+            let (items, total) = list_public_posts(
+                &pool,
+                false,
+                PublicPostSort::PublishedAt,
+                2,
+                0,
+                after.as_ref(),
+            )
+            .await
+            .expect("list");
+            assert_eq!(total, 4);
+            let Some(last) = items.last() else { break };
+            after = Some(Cursor::new(&last.sorted_at, &last.id));
+            seen.extend(items.into_iter().map(|p| (p.slug, p.id)));
+        }
+
+        let (slugs, ids): (Vec<_>, Vec<_>) = seen.into_iter().unzip();
+        assert_eq!(slugs.len(), 4);
+        assert_eq!(slugs[3], "older");
+        let mut tied = ids[..3].to_vec();
+        tied.sort_by(|a, b| b.cmp(a));
+        assert_eq!(tied, ids[..3]);
+    }
+
+    #[tokio::test]
+    async fn test_public_post_cursor_compares_mixed_timestamp_formats_by_time() {
+        let pool = create_test_pool().await;
+        let page = seed_page(&pool, "mixed").await;
+        seed_post(&pool, &page, "rfc-early", 0, "2024-02-01T09:00:00Z").await;
+        seed_post(&pool, &page, "rfc-late", 0, "2024-02-01T11:00:00Z").await;
+        let legacy = seed_post(&pool, &page, "legacy-mid", 0, "2024-01-01T00:00:00Z").await;
+        // As text "2024-02-01T..." sorts after every "2024-02-01 ..."
+        sqlx::query("UPDATE site_posts SET published_at = '2024-02-01 10:00:00' WHERE id = ?")
+            .bind(&legacy)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut slugs = Vec::new();
+        let mut after: Option<Cursor> = None;
+        loop {
+            let (items, _) = list_public_posts(
+                &pool,
+                false,
+                PublicPostSort::PublishedAt,
+                1,
+                0,
+                after.as_ref(),
+            )
+            .await
+            .expect("list");
+            let Some(last) = items.last() else { break };
+            after = Some(Cursor::new(&last.sorted_at, &last.id));
+            slugs.extend(items.into_iter().map(|p| p.slug));
+        }
+        assert_eq!(slugs, vec!["rfc-late", "legacy-mid", "rfc-early"]);
+    }
+}
@@ -1,6 +1,11 @@
 use crate::db::DbPool;
+use crate::security::auth::JWT_LEEWAY_SECONDS;
+use chrono::{DateTime, Duration, Utc};
 use sqlx;
 
+/// Revokes `token` until `expires_at` (seconds since the epoch), after which
+/// it would be refused anyway. Revoking a token twice, e.g. by two logouts
+/// racing, is not an error.
 pub async fn blacklist_token(
     pool: &DbPool,
     token: &str,
@@ -11,11 +16,14 @@ pub async fn blacklist_token(
     )
     .to_rfc3339();
 
-    sqlx::query("INSERT INTO token_blacklist (token, expires_at) VALUES (?, ?)")
-        .bind(token)
-        .bind(expires_at_str)
-        .execute(pool)
-        .await?;
+    sqlx::query(
+        "INSERT INTO token_blacklist (token, expires_at) VALUES (?, ?) \
+         ON CONFLICT(token) DO NOTHING",
+    )
+    .bind(token)
+    .bind(expires_at_str)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
@@ -27,3 +35,60 @@ pub async fn is_token_blacklisted(pool: &DbPool, token: &str) -> Result<bool, sq
             .await?;
     Ok(exists.is_some())
 }
+
+/// Deletes revoked tokens that `verify_jwt` refuses as expired by `now`,
+/// i.e. once the leeway after their expiry has passed as well. Returns how
+/// many were removed.
+pub async fn purge_expired(pool: &DbPool, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let cutoff = now - Duration::seconds(JWT_LEEWAY_SECONDS as i64);
+    let mut tx = pool.begin().await?;
+    let result =
+        sqlx::query("DELETE FROM token_blacklist WHERE julianday(expires_at) < julianday(?)")
+            .bind(cutoff.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+    tx.commit().await?;
+
+    let purged = result.rows_affected();
+    if purged > 0 {
+        tracing::info!("Purged {} expired token(s) from the blacklist", purged);
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+
+    #[tokio::test]
+    async fn test_purge_removes_only_expired_tokens() {
+        let pool = create_test_pool().await;
+        let now = Utc::now();
+        let leeway = JWT_LEEWAY_SECONDS as i64;
+        for (token, expires_at) in [
+            ("expired-yesterday", now.timestamp() - 86_400),
+            ("expired-beyond-leeway", now.timestamp() - leeway - 5),
+            ("within-leeway", now.timestamp() - leeway + 5),
+            ("live", now.timestamp() + 3600),
+        ] {
+            blacklist_token(&pool, token, expires_at).await.unwrap();
+        }
+
+        assert_eq!(purge_expired(&pool, now).await.unwrap(), 2);
+        for (token, kept) in [
+            ("expired-yesterday", false),
+            ("expired-beyond-leeway", false),
+            ("within-leeway", true),
+            ("live", true),
+        ] {
+            assert_eq!(
+                is_token_blacklisted(&pool, token).await.unwrap(),
+                kept,
+                "{token}"
+            );
+        }
+
+        assert_eq!(purge_expired(&pool, now).await.unwrap(), 0);
+    }
+}
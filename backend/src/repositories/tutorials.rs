@@ -1,5 +1,6 @@
-use crate::db::DbPool;
+use crate::db::{timing::timed_query, DbPool};
 use crate::models::Tutorial;
+use crate::utils::textstats;
 use sqlx;
 
 pub async fn list_tutorials(
@@ -7,16 +8,98 @@ pub async fn list_tutorials(
     limit: i64,
     offset: i64,
 ) -> Result<Vec<Tutorial>, sqlx::Error> {
-    sqlx::query_as::<_, Tutorial>(
-        "SELECT id, title, description, icon, color, topics, '' as content, version, created_at, updated_at \
-         FROM tutorials ORDER BY created_at ASC LIMIT ? OFFSET ?"
+    timed_query(
+        "tutorials.list",
+        sqlx::query_as::<_, Tutorial>(
+            "SELECT id, title, description, icon, color, topics, '' as content, version, word_count, reading_time_minutes, created_at, updated_at \
+             FROM tutorials ORDER BY created_at ASC LIMIT ? OFFSET ?"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool),
     )
-    .bind(limit)
-    .bind(offset)
-    .fetch_all(pool)
     .await
 }
 
+pub async fn count_tutorials(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    timed_query(
+        "tutorials.count",
+        sqlx::query_scalar("SELECT COUNT(*) FROM tutorials").fetch_one(pool),
+    )
+    .await
+}
+
+/// Full-text matches for an FTS5 `query`, best first, optionally only those
+/// whose topics match the `LIKE` pattern `topic_pattern`.
+pub async fn search_tutorials(
+    pool: &DbPool,
+    query: &str,
+    topic_pattern: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Tutorial>, sqlx::Error> {
+    let mut query_builder = sqlx::QueryBuilder::new(
+        "SELECT t.* FROM tutorials t \
+         INNER JOIN tutorials_fts ON t.id = tutorials_fts.tutorial_id \
+         WHERE tutorials_fts MATCH ",
+    );
+    query_builder.push_bind(query);
+    if let Some(pattern) = topic_pattern {
+        query_builder.push(" AND t.topics LIKE ");
+        query_builder.push_bind(pattern);
+        query_builder.push(" ESCAPE '\\'");
+    }
+    query_builder.push(" ORDER BY bm25(tutorials_fts) LIMIT ");
+    query_builder.push_bind(limit);
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(offset);
+
+    timed_query(
+        "tutorials.search",
+        query_builder.build_query_as::<Tutorial>().fetch_all(pool),
+    )
+    .await
+}
+
+/// Counts the matches [`search_tutorials`] pages through.
+pub async fn count_search_matches(
+    pool: &DbPool,
+    query: &str,
+    topic_pattern: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    let mut query_builder = sqlx::QueryBuilder::new(
+        "SELECT COUNT(*) FROM tutorials t \
+         INNER JOIN tutorials_fts ON t.id = tutorials_fts.tutorial_id \
+         WHERE tutorials_fts MATCH ",
+    );
+    query_builder.push_bind(query);
+    if let Some(pattern) = topic_pattern {
+        query_builder.push(" AND t.topics LIKE ");
+        query_builder.push_bind(pattern);
+        query_builder.push(" ESCAPE '\\'");
+    }
+
+    timed_query(
+        "tutorials.search_count",
+        query_builder.build_query_scalar::<i64>().fetch_one(pool),
+    )
+    .await
+}
+
+/// Returns up to `limit` tutorials (with content) ordered by id, starting
+/// after `after`. Used to walk all tutorials in bounded batches.
+pub async fn list_tutorials_after(
+    pool: &DbPool,
+    after: &str,
+    limit: i64,
+) -> Result<Vec<Tutorial>, sqlx::Error> {
+    sqlx::query_as::<_, Tutorial>("SELECT * FROM tutorials WHERE id > ? ORDER BY id LIMIT ?")
+        .bind(after)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
 pub async fn get_tutorial(pool: &DbPool, id: &str) -> Result<Option<Tutorial>, sqlx::Error> {
     sqlx::query_as::<_, Tutorial>("SELECT * FROM tutorials WHERE id = ?")
         .bind(id)
@@ -24,6 +107,27 @@ pub async fn get_tutorial(pool: &DbPool, id: &str) -> Result<Option<Tutorial>, s
         .await
 }
 
+/// Counts one view of the tutorial.
+pub async fn record_view(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO tutorial_views (tutorial_id, views) VALUES (?, 1) \
+         ON CONFLICT(tutorial_id) DO UPDATE SET views = views + 1",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn view_count(pool: &DbPool, id: &str) -> Result<i64, sqlx::Error> {
+    let views: Option<(i64,)> =
+        sqlx::query_as("SELECT views FROM tutorial_views WHERE tutorial_id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(views.map_or(0, |(views,)| views))
+}
+
 pub async fn check_tutorial_exists(pool: &DbPool, id: &str) -> Result<bool, sqlx::Error> {
     let exists: Option<(i64,)> = sqlx::query_as("SELECT 1 FROM tutorials WHERE id = ?")
         .bind(id)
@@ -32,6 +136,7 @@ pub async fn check_tutorial_exists(pool: &DbPool, id: &str) -> Result<bool, sqlx
     Ok(exists.is_some())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_tutorial(
     pool: &DbPool,
     id: &str,
@@ -43,38 +148,45 @@ pub async fn create_tutorial(
     topics_json: &str,
     topics_vec: &[String],
 ) -> Result<Tutorial, sqlx::Error> {
-    let mut tx = pool.begin().await?;
+    timed_query("tutorials.create", async {
+        let mut tx = pool.begin().await?;
+        let stats = textstats::compute(content);
 
-    sqlx::query(
-        r#"
-        INSERT INTO tutorials (id, title, description, icon, color, topics, content, version)
-        VALUES (?, ?, ?, ?, ?, ?, ?, 1)
-        "#,
-    )
-    .bind(id)
-    .bind(title)
-    .bind(description)
-    .bind(icon)
-    .bind(color)
-    .bind(topics_json)
-    .bind(content)
-    .execute(&mut *tx)
-    .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO tutorials (id, title, description, icon, color, topics, content, word_count, reading_time_minutes, version)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1)
+            "#,
+        )
+        .bind(id)
+        .bind(title)
+        .bind(description)
+        .bind(icon)
+        .bind(color)
+        .bind(topics_json)
+        .bind(content)
+        .bind(stats.word_count)
+        .bind(stats.reading_time_minutes)
+        .execute(&mut *tx)
+        .await?;
 
-    replace_tutorial_topics_tx(&mut tx, id, topics_vec).await?;
+        replace_tutorial_topics_tx(&mut tx, id, topics_vec).await?;
 
-    let tutorial = sqlx::query_as::<_, Tutorial>(
-        "SELECT id, title, description, icon, color, topics, content, version, created_at, updated_at FROM tutorials WHERE id = ?"
-    )
-    .bind(id)
-    .fetch_one(&mut *tx)
-    .await?;
+        let tutorial = sqlx::query_as::<_, Tutorial>(
+            "SELECT id, title, description, icon, color, topics, content, version, word_count, reading_time_minutes, created_at, updated_at FROM tutorials WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
 
-    tx.commit().await?;
+        tx.commit().await?;
 
-    Ok(tutorial)
+        Ok(tutorial)
+    })
+    .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_tutorial(
     pool: &DbPool,
     id: &str,
@@ -87,45 +199,51 @@ pub async fn update_tutorial(
     topics_vec: &[String],
     current_version: i32,
 ) -> Result<Option<Tutorial>, sqlx::Error> {
-    let mut tx = pool.begin().await?;
+    timed_query("tutorials.update", async {
+        let mut tx = pool.begin().await?;
 
-    let new_version = current_version + 1;
+        let new_version = current_version + 1;
+        let stats = textstats::compute(content);
 
-    let result = sqlx::query(
-        r#"
-        UPDATE tutorials
-        SET title = ?, description = ?, icon = ?, color = ?, topics = ?, content = ?, version = ?, updated_at = datetime('now')
-        WHERE id = ? AND version = ?
-        "#,
-    )
-    .bind(title)
-    .bind(description)
-    .bind(icon)
-    .bind(color)
-    .bind(topics_json)
-    .bind(content)
-    .bind(new_version)
-    .bind(id)
-    .bind(current_version)
-    .execute(&mut *tx)
-    .await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE tutorials
+            SET title = ?, description = ?, icon = ?, color = ?, topics = ?, content = ?, word_count = ?, reading_time_minutes = ?, version = ?, updated_at = datetime('now')
+            WHERE id = ? AND version = ?
+            "#,
+        )
+        .bind(title)
+        .bind(description)
+        .bind(icon)
+        .bind(color)
+        .bind(topics_json)
+        .bind(content)
+        .bind(stats.word_count)
+        .bind(stats.reading_time_minutes)
+        .bind(new_version)
+        .bind(id)
+        .bind(current_version)
+        .execute(&mut *tx)
+        .await?;
 
-    if result.rows_affected() == 0 {
-        return Ok(None);
-    }
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
 
-    replace_tutorial_topics_tx(&mut tx, id, topics_vec).await?;
+        replace_tutorial_topics_tx(&mut tx, id, topics_vec).await?;
 
-    let tutorial = sqlx::query_as::<_, Tutorial>(
-        "SELECT id, title, description, icon, color, topics, content, version, created_at, updated_at FROM tutorials WHERE id = ?"
-    )
-    .bind(id)
-    .fetch_one(&mut *tx)
-    .await?;
+        let tutorial = sqlx::query_as::<_, Tutorial>(
+            "SELECT id, title, description, icon, color, topics, content, version, word_count, reading_time_minutes, created_at, updated_at FROM tutorials WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
 
-    tx.commit().await?;
+        tx.commit().await?;
 
-    Ok(Some(tutorial))
+        Ok(Some(tutorial))
+    })
+    .await
 }
 
 pub async fn delete_tutorial(pool: &DbPool, id: &str) -> Result<bool, sqlx::Error> {
@@ -163,8 +281,55 @@ pub async fn replace_tutorial_topics(
     tutorial_id: &str,
     topics: &[String],
 ) -> Result<(), sqlx::Error> {
-    let mut tx = pool.begin().await?;
-    replace_tutorial_topics_tx(&mut tx, tutorial_id, topics).await?;
-    tx.commit().await?;
-    Ok(())
+    timed_query("tutorials.replace_topics", async {
+        let mut tx = pool.begin().await?;
+        replace_tutorial_topics_tx(&mut tx, tutorial_id, topics).await?;
+        tx.commit().await?;
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_test_pool;
+
+    #[tokio::test]
+    async fn test_text_stats_follow_content_updates() {
+        let pool = create_test_pool().await;
+        let topics = vec!["Shell".to_string()];
+        let created = create_tutorial(
+            &pool,
+            "stats",
+            "Stats",
+            "Wörter zählen",
+            "Mit `wc -w` zählst du Wörter",
+            "Terminal",
+            "from-blue-500 to-cyan-600",
+            r#"["Shell"]"#,
+            &topics,
+        )
+        .await
+        .expect("create");
+        assert_eq!((created.word_count, created.reading_time_minutes), (6, 1));
+
+        let content = format!("{}\n```\ncode only\n```", "wort ".repeat(250));
+        let updated = update_tutorial(
+            &pool,
+            "stats",
+            "Stats",
+            "Wörter zählen",
+            &content,
+            "Terminal",
+            "from-blue-500 to-cyan-600",
+            r#"["Shell"]"#,
+            &topics,
+            1,
+        )
+        .await
+        .expect("update")
+        .expect("current version");
+        assert_eq!((updated.word_count, updated.reading_time_minutes), (250, 2));
+    }
 }
@@ -0,0 +1,71 @@
+use crate::db::DbPool;
+use crate::models::UploadSession;
+use sqlx;
+
+const SESSION_COLUMNS: &str =
+    "id, file_name, mime_type, size_bytes, chunk_size, created_by, created_at, expires_at";
+
+pub async fn create_session(
+    pool: &DbPool,
+    file_name: &str,
+    mime_type: &str,
+    size_bytes: i64,
+    chunk_size: i64,
+    created_by: &str,
+    ttl_hours: u32,
+) -> Result<UploadSession, sqlx::Error> {
+    let sql = format!(
+        "INSERT INTO upload_sessions (id, file_name, mime_type, size_bytes, chunk_size, created_by, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, datetime('now', ?))
+         RETURNING {SESSION_COLUMNS}"
+    );
+    sqlx::query_as::<_, UploadSession>(&sql)
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(file_name)
+        .bind(mime_type)
+        .bind(size_bytes)
+        .bind(chunk_size)
+        .bind(created_by)
+        .bind(format!("+{ttl_hours} hours"))
+        .fetch_one(pool)
+        .await
+}
+
+/// The session with `id`, unless it has expired.
+pub async fn get_active_session(
+    pool: &DbPool,
+    id: &str,
+) -> Result<Option<UploadSession>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {SESSION_COLUMNS} FROM upload_sessions
+         WHERE id = ? AND datetime(expires_at) > datetime('now')"
+    );
+    sqlx::query_as::<_, UploadSession>(&sql)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn delete_session(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM upload_sessions WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Removes expired sessions and returns their ids, so their chunks can be
+/// deleted as well.
+pub async fn delete_expired_sessions(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "DELETE FROM upload_sessions WHERE datetime(expires_at) <= datetime('now') RETURNING id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_session_ids(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM upload_sessions")
+        .fetch_all(pool)
+        .await
+}
@@ -0,0 +1,294 @@
+use crate::db::DbPool;
+use crate::models::Upload;
+use crate::repositories::common::escape_like_pattern;
+use sqlx;
+
+const UPLOAD_COLUMNS: &str = "id, filename, original_filename, mime_type, kind, visibility, size_bytes, original_size_bytes, width, height, alt_text, caption, uploaded_by, created_at";
+
+/// Metadata for a file that was just written to the upload directory.
+#[derive(Debug, Clone)]
+pub struct NewUpload<'a> {
+    pub filename: &'a str,
+    pub original_filename: &'a str,
+    pub mime_type: &'a str,
+    pub kind: &'a str,
+    pub visibility: &'a str,
+    pub size_bytes: i64,
+    /// Size before processing, when it differs from `size_bytes`.
+    pub original_size_bytes: Option<i64>,
+    pub dimensions: Option<(u32, u32)>,
+    pub alt_text: Option<&'a str>,
+    pub caption: Option<&'a str>,
+    pub uploaded_by: Option<&'a str>,
+}
+
+pub async fn insert_upload(pool: &DbPool, upload: &NewUpload<'_>) -> Result<Upload, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let (width, height) = upload
+        .dimensions
+        .map(|(width, height)| (Some(i64::from(width)), Some(i64::from(height))))
+        .unwrap_or((None, None));
+
+    let sql = format!(
+        "INSERT INTO uploads (id, filename, original_filename, mime_type, kind, visibility, size_bytes, original_size_bytes, width, height, alt_text, caption, uploaded_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING {UPLOAD_COLUMNS}"
+    );
+    sqlx::query_as::<_, Upload>(&sql)
+        .bind(&id)
+        .bind(upload.filename)
+        .bind(upload.original_filename)
+        .bind(upload.mime_type)
+        .bind(upload.kind)
+        .bind(upload.visibility)
+        .bind(upload.size_bytes)
+        .bind(upload.original_size_bytes)
+        .bind(width)
+        .bind(height)
+        .bind(upload.alt_text)
+        .bind(upload.caption)
+        .bind(upload.uploaded_by)
+        .fetch_one(pool)
+        .await
+}
+
+/// Records a file found on disk unless it is already tracked. Returns whether
+/// a row was added.
+pub async fn insert_untracked_upload(
+    pool: &DbPool,
+    upload: &NewUpload<'_>,
+) -> Result<bool, sqlx::Error> {
+    let (width, height) = upload
+        .dimensions
+        .map(|(width, height)| (Some(i64::from(width)), Some(i64::from(height))))
+        .unwrap_or((None, None));
+
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO uploads (id, filename, original_filename, mime_type, kind, visibility, size_bytes, original_size_bytes, width, height, alt_text, caption, uploaded_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(upload.filename)
+    .bind(upload.original_filename)
+    .bind(upload.mime_type)
+    .bind(upload.kind)
+    .bind(upload.visibility)
+    .bind(upload.size_bytes)
+    .bind(upload.original_size_bytes)
+    .bind(width)
+    .bind(height)
+    .bind(upload.alt_text)
+    .bind(upload.caption)
+    .bind(upload.uploaded_by)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_upload(pool: &DbPool, id: &str) -> Result<Option<Upload>, sqlx::Error> {
+    let sql = format!("SELECT {UPLOAD_COLUMNS} FROM uploads WHERE id = ?");
+    sqlx::query_as::<_, Upload>(&sql)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Looks an upload up by id or by its stored file name, as found in
+/// `/uploads/<filename>` URLs. Both columns are unique and indexed.
+pub async fn find_upload(pool: &DbPool, id_or_filename: &str) -> Result<Option<Upload>, sqlx::Error> {
+    let sql = format!("SELECT {UPLOAD_COLUMNS} FROM uploads WHERE id = ? OR filename = ? LIMIT 1");
+    sqlx::query_as::<_, Upload>(&sql)
+        .bind(id_or_filename)
+        .bind(id_or_filename)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Replaces the alt text and caption of an upload. Returns `None` when the
+/// upload does not exist.
+pub async fn update_upload_meta(
+    pool: &DbPool,
+    id: &str,
+    alt_text: Option<&str>,
+    caption: Option<&str>,
+) -> Result<Option<Upload>, sqlx::Error> {
+    let sql = format!(
+        "UPDATE uploads SET alt_text = ?, caption = ? WHERE id = ? RETURNING {UPLOAD_COLUMNS}"
+    );
+    sqlx::query_as::<_, Upload>(&sql)
+        .bind(alt_text)
+        .bind(caption)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Points a draft upload at its public file name and marks it public.
+pub async fn publish_upload(
+    pool: &DbPool,
+    id: &str,
+    filename: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE uploads SET filename = ?, visibility = 'public' WHERE id = ?")
+        .bind(filename)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_upload(pool: &DbPool, id: &str) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM uploads WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Uploads ordered by file name, starting after `after`, for scans that
+/// walk the whole table in batches.
+pub async fn list_uploads_after(
+    pool: &DbPool,
+    after: &str,
+    limit: i64,
+) -> Result<Vec<Upload>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {UPLOAD_COLUMNS} FROM uploads WHERE filename > ? ORDER BY filename LIMIT ?"
+    );
+    sqlx::query_as::<_, Upload>(&sql)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// Number and total size of the uploads `uploaded_by` stored since `since`,
+/// a `YYYY-MM-DD HH:MM:SS` UTC timestamp.
+pub async fn usage_since(
+    pool: &DbPool,
+    uploaded_by: &str,
+    since: &str,
+) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0)
+         FROM uploads WHERE uploaded_by = ? AND created_at >= ?",
+    )
+    .bind(uploaded_by)
+    .bind(since)
+    .fetch_one(pool)
+    .await
+}
+
+/// Image uploads whose pixel size was never recorded.
+pub async fn list_images_without_dimensions(pool: &DbPool) -> Result<Vec<Upload>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {UPLOAD_COLUMNS} FROM uploads WHERE kind = 'image' AND width IS NULL ORDER BY filename"
+    );
+    sqlx::query_as::<_, Upload>(&sql).fetch_all(pool).await
+}
+
+pub async fn set_upload_dimensions(
+    pool: &DbPool,
+    id: &str,
+    width: u32,
+    height: u32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE uploads SET width = ?, height = ? WHERE id = ?")
+        .bind(i64::from(width))
+        .bind(i64::from(height))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Lists uploads newest first. `query` filters on the original file name.
+pub async fn list_uploads(
+    pool: &DbPool,
+    query: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<Upload>, i64), sqlx::Error> {
+    let pattern = query
+        .map(str::trim)
+        .filter(|query| !query.is_empty())
+        .map(|query| format!("%{}%", escape_like_pattern(query)));
+
+    let filter = if pattern.is_some() {
+        "WHERE original_filename LIKE ? ESCAPE '\\'"
+    } else {
+        ""
+    };
+
+    let items_sql = format!(
+        "SELECT {UPLOAD_COLUMNS}
+         FROM uploads {filter}
+         ORDER BY created_at DESC, rowid DESC
+         LIMIT ? OFFSET ?",
+    );
+    let total_sql = format!("SELECT COUNT(*) FROM uploads {filter}");
+
+    let mut items = sqlx::query_as::<_, Upload>(&items_sql);
+    let mut total = sqlx::query_as::<_, (i64,)>(&total_sql);
+    if let Some(pattern) = &pattern {
+        items = items.bind(pattern);
+        total = total.bind(pattern);
+    }
+
+    let items = items.bind(limit).bind(offset).fetch_all(pool).await?;
+    let (total,) = total.fetch_one(pool).await?;
+    Ok((items, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload<'a>(filename: &'a str, original: &'a str) -> NewUpload<'a> {
+        NewUpload {
+            filename,
+            original_filename: original,
+            mime_type: "image/png",
+            kind: crate::models::UPLOAD_KIND_IMAGE,
+            visibility: crate::models::UPLOAD_VISIBILITY_PUBLIC,
+            size_bytes: 10,
+            original_size_bytes: None,
+            dimensions: Some((2, 1)),
+            alt_text: None,
+            caption: None,
+            uploaded_by: Some("admin"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_and_orders_newest_first() {
+        let pool = crate::db::pool::create_test_pool().await;
+        insert_upload(&pool, &upload("a.png", "Terminal_100%.png")).await.unwrap();
+        insert_upload(&pool, &upload("b.png", "terminal-dark.png")).await.unwrap();
+        insert_upload(&pool, &upload("c.png", "logo.png")).await.unwrap();
+
+        let (all, total) = list_uploads(&pool, None, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        let names: Vec<&str> = all.iter().map(|item| item.filename.as_str()).collect();
+        assert_eq!(names, vec!["c.png", "b.png", "a.png"]);
+        assert_eq!((all[0].width, all[0].height), (Some(2), Some(1)));
+
+        let (matches, total) = list_uploads(&pool, Some("TERMINAL"), 1, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(matches[0].filename, "b.png");
+
+        let (literal, _) = list_uploads(&pool, Some("100%"), 10, 0).await.unwrap();
+        assert_eq!(literal.len(), 1);
+        let (underscore, total) = list_uploads(&pool, Some("_"), 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(underscore[0].filename, "a.png");
+
+        assert!(!insert_untracked_upload(&pool, &upload("c.png", "c.png")).await.unwrap());
+        assert!(insert_untracked_upload(&pool, &upload("d.png", "d.png")).await.unwrap());
+    }
+}
@@ -1,11 +1,12 @@
 use crate::db::DbPool;
 use crate::models::User;
+use chrono::{DateTime, Utc};
 use sqlx::{self, FromRow};
 
 #[derive(Debug, FromRow, Clone)]
 pub struct LoginAttempt {
     pub fail_count: i64,
-    pub blocked_until: Option<String>,
+    pub blocked_until: Option<DateTime<Utc>>,
 }
 
 pub async fn get_user_by_username(
@@ -33,8 +34,8 @@ pub async fn get_login_attempt(
 pub async fn record_failed_login(
     pool: &DbPool,
     username_hash: &str,
-    long_block: &str,
-    short_block: &str,
+    long_block: DateTime<Utc>,
+    short_block: DateTime<Utc>,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
         "INSERT INTO login_attempts (username, fail_count, blocked_until) VALUES (?, 1, NULL) \
@@ -68,3 +69,29 @@ pub async fn check_user_exists_by_name(pool: &DbPool, username: &str) -> Result<
         .await?;
     Ok(exists.is_some())
 }
+
+/// Stores a new bcrypt hash for `username` and bumps its token version, which
+/// revokes every token issued before. Returns the new version, or `None` when
+/// there is no such user.
+pub async fn update_password_hash(
+    pool: &DbPool,
+    username: &str,
+    password_hash: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE users SET password_hash = ?, token_version = token_version + 1 \
+         WHERE username = ? RETURNING token_version",
+    )
+    .bind(password_hash)
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The token version of `username`, or `None` when there is no such user.
+pub async fn get_token_version(pool: &DbPool, username: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT token_version FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+}
@@ -1,23 +1,83 @@
 use axum::{routing::{delete, get, post, put}, Router};
-use tower_governor::{governor::GovernorConfig, key_extractor::SmartIpKeyExtractor, GovernorLayer};
-use tower_http::limit::RequestBodyLimitLayer;
-use crate::handlers::{tutorials, site_content, site_pages, site_posts, comments, upload};
-use crate::middleware::auth::auth_middleware;
+use tower_governor::GovernorLayer;
+use crate::handlers::{tutorials, content_bundles, content_health, content_sections, frontend_proxy, ip_bans, jobs, metrics, site_content, site_pages, site_posts, comments, layout_blocks, maintenance, upload, upload_orphans, upload_quota, upload_sessions};
+use crate::middleware::{audit, auth::auth_middleware, body_limit::{body_limits, with_body_limit}, load_shed::{limit_writes, LoadBudget}};
 use crate::security::csrf::enforce_csrf;
 use crate::db::DbPool;
-use std::sync::Arc;
-use governor::middleware::NoOpMiddleware;
+use crate::middleware::rate_limit::RateLimitConfig;
+use crate::middleware::timeout::{with_timeout, RequestTimeouts};
 
-const ADMIN_BODY_LIMIT: usize = 8 * 1024 * 1024;
-
-pub fn routes(pool: DbPool, rate_limit_config: Arc<GovernorConfig<SmartIpKeyExtractor, NoOpMiddleware>>) -> Router<DbPool> {
-    Router::new()
+pub fn routes(
+    pool: DbPool,
+    rate_limit_config: RateLimitConfig,
+    write_budget: LoadBudget,
+    timeouts: &RequestTimeouts,
+) -> Router<DbPool> {
+    let router = Router::new()
         .route("/api/tutorials", post(tutorials::create_tutorial))
         .route(
             "/api/tutorials/{id}",
             put(tutorials::update_tutorial).delete(tutorials::delete_tutorial),
         )
 
+        .route(
+            "/api/admin/content-sections",
+            get(content_sections::list_content_sections)
+                .post(content_sections::create_content_section),
+        )
+        .route(
+            "/api/admin/content-sections/{name}",
+            get(content_sections::get_content_section)
+                .put(content_sections::update_content_section)
+                .delete(content_sections::delete_content_section),
+        )
+        .route("/api/admin/content/export", get(site_content::export_site_content))
+        .route("/api/admin/content/import", post(site_content::import_site_content))
+        .route("/api/admin/content/health", get(content_health::get_content_health))
+        .route(
+            "/api/admin/maintenance",
+            get(maintenance::get_maintenance).post(maintenance::update_maintenance),
+        )
+        .route("/api/admin/jobs", get(jobs::list_jobs))
+        .route("/api/admin/metrics", get(metrics::get_metrics))
+        .route(
+            "/api/admin/cache/frontend/purge",
+            post(frontend_proxy::purge_cache),
+        )
+        .route(
+            "/api/admin/cache/html/purge",
+            post(frontend_proxy::purge_html_cache),
+        )
+        .route(
+            "/api/admin/frontend/status",
+            get(frontend_proxy::frontend_status),
+        )
+        .route(
+            "/api/admin/bans/ip",
+            get(ip_bans::list_ip_bans)
+                .post(ip_bans::create_ip_ban)
+                .delete(ip_bans::delete_ip_ban),
+        )
+        .route(
+            "/api/admin/content/{section}/diff",
+            post(site_content::diff_site_content),
+        )
+        .route(
+            "/api/content/{section}",
+            put(site_content::update_site_content).patch(site_content::patch_site_content),
+        )
+        .route(
+            "/api/content/{section}/history",
+            get(site_content::list_site_content_history),
+        )
+        .route(
+            "/api/content/{section}/history/{version}",
+            get(site_content::get_site_content_version),
+        )
+        .route(
+            "/api/content/{section}/rollback/{version}",
+            post(site_content::rollback_site_content),
+        )
         .route(
             "/api/pages",
             get(site_pages::list_site_pages).post(site_pages::create_site_page),
@@ -28,16 +88,39 @@ pub fn routes(pool: DbPool, rate_limit_config: Arc<GovernorConfig<SmartIpKeyExtr
                 .put(site_pages::update_site_page)
                 .delete(site_pages::delete_site_page),
         )
+        .route(
+            "/api/pages/{id}/restore",
+            post(site_pages::restore_site_page),
+        )
+        .route(
+            "/api/pages/{id}/purge",
+            delete(site_pages::purge_site_page),
+        )
+        .route(
+            "/api/admin/blocks",
+            get(layout_blocks::list_layout_blocks).post(layout_blocks::create_layout_block),
+        )
+        .route(
+            "/api/admin/blocks/{id}",
+            get(layout_blocks::get_layout_block)
+                .put(layout_blocks::update_layout_block)
+                .delete(layout_blocks::delete_layout_block),
+        )
         .route(
             "/api/pages/{page_id}/posts",
             get(site_posts::list_posts_for_page).post(site_posts::create_post),
         )
+        .route(
+            "/api/pages/{page_id}/posts/bulk-publish",
+            post(site_posts::bulk_publish_posts),
+        )
         .route(
             "/api/posts/{id}",
             get(site_posts::get_post)
                 .put(site_posts::update_post)
                 .delete(site_posts::delete_post),
         )
+        .route("/api/posts/{id}/move", post(site_posts::move_post))
         .route(
             "/api/tutorials/{id}/comments",
             post(comments::create_comment),
@@ -46,15 +129,66 @@ pub fn routes(pool: DbPool, rate_limit_config: Arc<GovernorConfig<SmartIpKeyExtr
             "/api/comments/{id}",
             delete(comments::delete_comment),
         )
+        .route("/api/admin/uploads", get(upload::list_uploads))
+        .route(
+            "/api/admin/uploads/orphans",
+            get(upload_orphans::orphan_uploads).post(upload_orphans::orphan_uploads),
+        )
+        .route("/api/admin/uploads/quota", get(upload_quota::get_upload_quota))
+        .route(
+            "/api/admin/uploads/{id}",
+            put(upload::update_upload).delete(upload::delete_upload),
+        );
+
+    // Uploads read their whole body inside the handler
+    let uploads = Router::new()
         .route("/api/upload", post(upload::upload_image))
+        .route("/api/upload/file", post(upload::upload_file))
+        .route("/api/upload/sessions", post(upload_sessions::create_upload_session))
+        .route("/api/upload/sessions/{id}", get(upload_sessions::get_upload_session))
+        .route(
+            "/api/upload/sessions/{id}/chunks/{index}",
+            put(upload_sessions::put_upload_chunk),
+        )
+        .route(
+            "/api/upload/sessions/{id}/complete",
+            post(upload_sessions::complete_upload_session),
+        );
+
+    // Whole-site imports can run well past the default budget
+    let bundles = Router::new()
+        .route("/api/admin/import", post(content_bundles::import_bundle))
+        .route("/api/admin/export", get(content_bundles::export_bundle))
+        .route("/api/admin/rebuild-derived", post(content_bundles::rebuild_derived));
+
+    let router = with_timeout(router, timeouts.default)
+        .merge(with_timeout(uploads, timeouts.uploads))
+        .merge(with_timeout(bundles, timeouts.imports))
+        // Innermost, so only authenticated writes use up the budget
+        .route_layer(axum::middleware::from_fn_with_state(
+            write_budget,
+            limit_writes,
+        ))
         .route_layer(axum::middleware::from_fn_with_state(
             pool.clone(),
             enforce_csrf,
+        ));
+
+    // Inside authentication to know the user, outside CSRF to record rejections
+    let router = if audit::enabled_from_env() {
+        router.route_layer(axum::middleware::from_fn_with_state(
+            pool.clone(),
+            audit::audit_mutations,
         ))
+    } else {
+        router
+    };
+
+    let router = router
         .route_layer(axum::middleware::from_fn_with_state(
             pool.clone(),
             auth_middleware,
-        ))
-        .layer(RequestBodyLimitLayer::new(ADMIN_BODY_LIMIT))
-        .layer(GovernorLayer::new(rate_limit_config))
+        ));
+
+    with_body_limit(router, body_limits().admin).layer(GovernorLayer::new(rate_limit_config))
 }
@@ -1,13 +1,26 @@
 use axum::{routing::{get, post}, Router};
-use tower_governor::{governor::GovernorConfig, key_extractor::SmartIpKeyExtractor, GovernorLayer};
+use tower_governor::GovernorLayer;
 use tower_http::services::ServeDir;
-use crate::handlers::{auth, tutorials, search, comments, site_content, site_pages};
+use crate::handlers::{auth, bootstrap, csp_report, tutorials, search, comments, site_content, site_pages, upload};
 use crate::db::DbPool;
+use crate::middleware::{
+    body_limit::{body_limits, with_body_limit},
+    bot::{with_bot_throttle, BotThrottle},
+    rate_limit::RateLimitConfig,
+    timeout::{with_timeout, RequestTimeouts},
+    uploads,
+};
+use crate::storage::Storage;
 use std::sync::Arc;
-use governor::middleware::NoOpMiddleware;
 
-pub fn routes(upload_dir: String, admin_rate_limit_config: Arc<GovernorConfig<SmartIpKeyExtractor, NoOpMiddleware>>) -> Router<DbPool> {
-    Router::new()
+pub fn routes(
+    upload_storage: Arc<dyn Storage>,
+    admin_rate_limit_config: RateLimitConfig,
+    public_rate_limit_config: RateLimitConfig,
+    bot_throttle: BotThrottle,
+    timeouts: &RequestTimeouts,
+) -> Router<DbPool> {
+    let router = Router::new()
         .route("/api/auth/me", get(auth::me))
         .route("/api/tutorials", get(tutorials::list_tutorials))
         .route(
@@ -29,7 +42,7 @@ pub fn routes(upload_dir: String, admin_rate_limit_config: Arc<GovernorConfig<Sm
         )
         .route(
             "/api/content/{section}",
-            get(site_content::get_site_content).put(site_content::update_site_content),
+            get(site_content::get_site_content),
         )
         .route(
             "/api/posts/{id}/comments",
@@ -45,10 +58,16 @@ pub fn routes(upload_dir: String, admin_rate_limit_config: Arc<GovernorConfig<Sm
             "/api/public/pages/{slug}",
             get(site_pages::get_published_page_by_slug),
         )
+        .route(
+            "/api/public/pages/{slug}/archive",
+            get(site_pages::get_page_archive),
+        )
         .route(
             "/api/public/pages/{slug}/posts/{post_slug}",
             get(site_pages::get_published_post_by_slug),
         )
+        .route("/api/public/posts", get(site_pages::list_public_posts))
+        .route("/api/public/bootstrap", get(bootstrap::get_bootstrap))
         .route(
             "/api/public/navigation",
             get(site_pages::get_navigation),
@@ -57,5 +76,28 @@ pub fn routes(upload_dir: String, admin_rate_limit_config: Arc<GovernorConfig<Sm
             "/api/public/published-pages",
             get(site_pages::list_published_page_slugs),
         )
-        .nest_service("/uploads", ServeDir::new(upload_dir))
+        .route(
+            "/api/public/uploads/{id}/meta",
+            get(upload::get_upload_meta),
+        )
+        .route("/uploads/private/{file}", get(upload::serve_private_upload))
+        .merge(with_body_limit(
+            Router::new().route("/api/csp-report", post(csp_report::csp_report)),
+            csp_report::CSP_REPORT_BODY_LIMIT,
+        ));
+    let router = with_body_limit(with_timeout(router, timeouts.default), body_limits().public)
+        .layer(GovernorLayer::new(public_rate_limit_config));
+    let router = with_bot_throttle(router, bot_throttle);
+
+    // Local files are served straight from disk; other backends go through
+    // a redirect or proxy handler.
+    let uploads = match upload_storage.local_root() {
+        Some(upload_dir) => Router::new().nest_service("/uploads", ServeDir::new(upload_dir)),
+        None => Router::new().route("/uploads/{*key}", get(upload::serve_upload)),
+    };
+
+    router.merge(
+        with_timeout(uploads, timeouts.default)
+            .layer(axum::middleware::from_fn(uploads::upload_headers)),
+    )
 }
@@ -1,26 +1,29 @@
-use axum::{routing::post, Router};
-use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
-use tower_governor::key_extractor::SmartIpKeyExtractor;
-use tower_http::limit::RequestBodyLimitLayer;
+use axum::{routing::{post, put}, Router};
+use tower_governor::GovernorLayer;
 use crate::handlers::auth;
 use crate::db::DbPool;
-use std::sync::Arc;
+use crate::middleware::auth::auth_middleware;
+use crate::middleware::body_limit::{body_limits, with_body_limit};
+use crate::middleware::rate_limit::RateLimitConfig;
+use crate::middleware::timeout::{with_timeout, RequestTimeouts};
 
-const LOGIN_BODY_LIMIT: usize = 64 * 1024;
-
-pub fn routes() -> Router<DbPool> {
-    let rate_limit_config = Arc::new(
-        GovernorConfigBuilder::default()
-            .per_second(1)
-            .burst_size(5)
-            .key_extractor(SmartIpKeyExtractor)
-            .finish()
-            .expect("Failed to build governor config"),
-    );
-
-    Router::new()
+pub fn routes(
+    pool: DbPool,
+    rate_limit_config: RateLimitConfig,
+    timeouts: &RequestTimeouts,
+) -> Router<DbPool> {
+    let router = Router::new()
         .route("/api/auth/login", post(auth::login))
         .route("/api/auth/logout", post(auth::logout))
-        .layer(RequestBodyLimitLayer::new(LOGIN_BODY_LIMIT))
+        // Shares the login limiter, as it checks a password too
+        .route(
+            "/api/auth/password",
+            put(auth::change_password).route_layer(axum::middleware::from_fn_with_state(
+                pool,
+                auth_middleware,
+            )),
+        );
+
+    with_body_limit(with_timeout(router, timeouts.default), body_limits().login)
         .layer(GovernorLayer::new(rate_limit_config))
 }
@@ -4,25 +4,45 @@ pub mod auth;
 
 use axum::Router;
 use crate::db::DbPool;
-use tower_governor::{governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor};
+use crate::middleware::bot::BotThrottle;
+use crate::middleware::load_shed::LoadBudget;
+use crate::middleware::rate_limit::{rate_limit_reset_header, RateLimitConfig};
+use crate::middleware::timeout::RequestTimeouts;
+use crate::storage::Storage;
 use std::sync::Arc;
 
-pub fn create_routes(pool: DbPool, upload_dir: String) -> Router<DbPool> {
-    let admin_rate_limit_config = Arc::new(
-        GovernorConfigBuilder::default()
-            .per_second(1)
-            .burst_size(3)
-            .key_extractor(SmartIpKeyExtractor)
-            .finish()
-            .expect("Failed to build governor config for write routes"),
-    );
+/// Public API routes, served on the main listener.
+pub fn create_public_routes(
+    upload_storage: Arc<dyn Storage>,
+    admin_rate_limit_config: RateLimitConfig,
+    public_rate_limit_config: RateLimitConfig,
+    bot_throttle: BotThrottle,
+    timeouts: &RequestTimeouts,
+) -> Router<DbPool> {
+    api::routes(
+        upload_storage,
+        admin_rate_limit_config,
+        public_rate_limit_config,
+        bot_throttle,
+        timeouts,
+    )
+    .layer(axum::middleware::from_fn(rate_limit_reset_header))
+}
 
-    let login_router = auth::routes();
-    let admin_router = admin::routes(pool.clone(), admin_rate_limit_config.clone());
-    let api_router = api::routes(upload_dir, admin_rate_limit_config);
+/// Login and admin API routes, served on the admin listener when
+/// `ADMIN_BIND_ADDR` is set and on the main listener otherwise.
+pub fn create_admin_routes(
+    pool: DbPool,
+    login_rate_limit_config: RateLimitConfig,
+    admin_rate_limit_config: RateLimitConfig,
+    write_budget: LoadBudget,
+    timeouts: &RequestTimeouts,
+) -> Router<DbPool> {
+    let login_router = auth::routes(pool.clone(), login_rate_limit_config, timeouts);
+    let admin_router = admin::routes(pool, admin_rate_limit_config, write_budget, timeouts);
 
     Router::new()
         .merge(login_router)
         .merge(admin_router)
-        .merge(api_router)
+        .layer(axum::middleware::from_fn(rate_limit_reset_header))
 }
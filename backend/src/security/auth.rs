@@ -14,7 +14,7 @@
 //! # Usage
 //! Before using any authentication functions, initialize the JWT secret:
 //! ```rust,no_run
-//! use linux_tutorial_cms::auth;
+//! use rust_blog_backend::security::auth;
 //! auth::init_jwt_secret().expect("Failed to initialize JWT secret");
 //! ```
 
@@ -26,7 +26,6 @@ use axum::{
         request::Parts,
         HeaderMap, HeaderValue, StatusCode,
     },
-    Json,
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use chrono::{Duration, Utc};
@@ -38,7 +37,7 @@ use std::sync::LazyLock;
 use std::sync::OnceLock;
 use time::{Duration as TimeDuration, OffsetDateTime};
 
-use crate::db::{self, DbPool};
+use crate::db::DbPool;
 
 /// Global storage for the JWT secret key.
 /// Initialized once at application startup via init_jwt_secret().
@@ -75,6 +74,9 @@ pub const AUTH_COOKIE_NAME: &str = "ltcms_session";
 /// Authentication cookie time-to-live in seconds (24 hours).
 const AUTH_COOKIE_TTL_SECONDS: i64 = 24 * 60 * 60;
 
+/// Seconds a token is still accepted after its `exp`, for clock skew.
+pub const JWT_LEEWAY_SECONDS: u64 = 60;
+
 /// Initializes the JWT secret from the environment variable.
 ///
 /// This function must be called once at application startup before any
@@ -102,7 +104,7 @@ const AUTH_COOKIE_TTL_SECONDS: i64 = 24 * 60 * 60;
 ///
 /// # Example
 /// ```rust,no_run
-/// use linux_tutorial_cms::auth;
+/// use rust_blog_backend::security::auth;
 /// auth::init_jwt_secret().expect("Failed to initialize JWT secret");
 /// ```
 pub fn init_jwt_secret() -> Result<(), String> {
@@ -166,6 +168,7 @@ fn get_jwt_secret() -> &'static str {
 /// - `sub`: Subject (username) - identifies the user
 /// - `role`: User role (e.g., "admin", "user") - for authorization
 /// - `exp`: Expiration timestamp (Unix epoch) - prevents token reuse
+/// - `ver`: Token version of the user - revokes tokens on password change
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     /// Subject: the username of the authenticated user
@@ -176,6 +179,11 @@ pub struct Claims {
 
     /// Expiration time as Unix timestamp (seconds since epoch)
     pub exp: usize,
+
+    /// The user's `token_version` when the token was issued. Changing the
+    /// password bumps it, which revokes every older token of the user.
+    #[serde(default)]
+    pub ver: i64,
 }
 
 impl Claims {
@@ -184,13 +192,14 @@ impl Claims {
     /// # Arguments
     /// * `username` - The username to include in the token
     /// * `role` - The user's role for authorization
+    /// * `token_version` - The user's current token version
     ///
     /// # Returns
     /// A new Claims instance with expiration set to 24 hours from now
     ///
     /// # Panics
     /// Panics if the system time is severely misconfigured
-    pub fn new(username: String, role: String) -> Self {
+    pub fn new(username: String, role: String, token_version: i64) -> Self {
         // Calculate expiration time (24 hours from now)
         let expiration = Utc::now()
             .checked_add_signed(Duration::hours(24))
@@ -203,10 +212,28 @@ impl Claims {
             sub: username,
             role,
             exp: expiration,
+            ver: token_version,
         }
     }
 }
 
+/// Claims of `username` with `role` that never expire, for handler tests.
+#[cfg(test)]
+pub fn test_claims(username: &str, role: &str) -> Claims {
+    Claims {
+        sub: username.to_string(),
+        role: role.to_string(),
+        exp: usize::MAX,
+        ver: 0,
+    }
+}
+
+/// Claims of an admin named `admin`, for handler tests.
+#[cfg(test)]
+pub fn test_admin_claims() -> Claims {
+    test_claims("admin", "admin")
+}
+
 /// Creates a signed JWT token for a user.
 ///
 /// This generates a new JWT token with the user's identity and role,
@@ -215,6 +242,7 @@ impl Claims {
 /// # Arguments
 /// * `username` - The username to encode in the token
 /// * `role` - The user's role for authorization
+/// * `token_version` - The user's current token version
 ///
 /// # Returns
 /// - `Ok(String)` - The encoded JWT token
@@ -226,13 +254,17 @@ impl Claims {
 ///
 /// # Example
 /// ```rust,no_run
-/// use linux_tutorial_cms::auth;
-/// let token = auth::create_jwt("admin".to_string(), "admin".to_string())?;
+/// use rust_blog_backend::security::auth;
+/// let token = auth::create_jwt("admin".to_string(), "admin".to_string(), 0)?;
 /// # Ok::<(), jsonwebtoken::errors::Error>(())
 /// ```
-pub fn create_jwt(username: String, role: String) -> Result<String, jsonwebtoken::errors::Error> {
+pub fn create_jwt(
+    username: String,
+    role: String,
+    token_version: i64,
+) -> Result<String, jsonwebtoken::errors::Error> {
     // Create claims with 24-hour expiration
-    let claims = Claims::new(username, role);
+    let claims = Claims::new(username, role, token_version);
 
     // Get the initialized JWT secret
     let secret = get_jwt_secret();
@@ -273,7 +305,7 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
 
     // Configure validation rules
     let mut validation = Validation::default();
-    validation.leeway = 60; // Allow 60 seconds of clock skew
+    validation.leeway = JWT_LEEWAY_SECONDS; // Allow 60 seconds of clock skew
     validation.validate_exp = true; // Ensure token hasn't expired
 
     // Decode and validate the token
@@ -286,6 +318,23 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     Ok(token_data.claims)
 }
 
+/// Whether a verified token may no longer be used: it was logged out, or the
+/// user changed their password after it was issued.
+///
+/// Tokens naming a user without a row are only checked against the
+/// blacklist.
+pub async fn is_token_revoked(
+    pool: &DbPool,
+    token: &str,
+    claims: &Claims,
+) -> Result<bool, sqlx::Error> {
+    if crate::repositories::token_blacklist::is_token_blacklisted(pool, token).await? {
+        return Ok(true);
+    }
+    let version = crate::repositories::users::get_token_version(pool, &claims.sub).await?;
+    Ok(version.is_some_and(|version| version != claims.ver))
+}
+
 /// Builds a secure authentication cookie containing the JWT token.
 ///
 /// Creates an HttpOnly cookie with appropriate security flags for
@@ -388,20 +437,19 @@ where
         let claims = verify_jwt(&token)
             .map_err(|e| (StatusCode::UNAUTHORIZED, format!("Invalid token: {}", e)))?;
 
-        // Check if token is blacklisted
+        // Check if token is blacklisted or older than the last password change
         let pool = DbPool::from_ref(state);
-        let is_blacklisted =
-            crate::repositories::token_blacklist::is_token_blacklisted(&pool, &token)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Database error checking token blacklist: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Internal server error".to_string(),
-                    )
-                })?;
-
-        if is_blacklisted {
+        let is_revoked = is_token_revoked(&pool, &token, &claims)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error checking token revocation: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            })?;
+
+        if is_revoked {
             return Err((
                 StatusCode::UNAUTHORIZED,
                 "Token has been revoked".to_string(),
@@ -596,20 +644,19 @@ where
         let claims = verify_jwt(&token)
             .map_err(|e| (StatusCode::UNAUTHORIZED, format!("Invalid token: {}", e)))?;
 
-        // Check if token is blacklisted
+        // Check if token is blacklisted or older than the last password change
         let pool = DbPool::from_ref(state);
-        let is_blacklisted =
-            crate::repositories::token_blacklist::is_token_blacklisted(&pool, &token)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Database error checking token blacklist: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Internal server error".to_string(),
-                    )
-                })?;
-
-        if is_blacklisted {
+        let is_revoked = is_token_revoked(&pool, &token, &claims)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error checking token revocation: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            })?;
+
+        if is_revoked {
             return Err((
                 StatusCode::UNAUTHORIZED,
                 "Token has been revoked".to_string(),
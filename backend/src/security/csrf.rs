@@ -21,18 +21,22 @@
 //!
 //! ## Initialization
 //! ```rust,no_run
-//! use linux_tutorial_cms::csrf;
+//! use rust_blog_backend::security::csrf;
 //! csrf::init_csrf_secret().expect("Failed to initialize CSRF secret");
 //! ```
 //!
 //! ## Protection
 //! ```rust,no_run
 //! use axum::{Router, routing::post, middleware};
-//! use linux_tutorial_cms::csrf::CsrfGuard;
+//! use rust_blog_backend::security::csrf::CsrfGuard;
+//! # async fn handler() {}
+//! # fn build(pool: rust_blog_backend::db::DbPool) -> Router {
 //!
 //! let app = Router::new()
 //!     .route("/api/resource", post(handler))
-//!     .route_layer(middleware::from_extractor::<CsrfGuard>());
+//!     .route_layer(middleware::from_extractor_with_state::<CsrfGuard, _>(pool));
+//! # app
+//! # }
 //! ```
 
 use axum::{
@@ -103,7 +107,7 @@ static CSRF_SECRET: OnceLock<Vec<u8>> = OnceLock::new();
 ///
 /// # Example
 /// ```rust,no_run
-/// use linux_tutorial_cms::csrf;
+/// use rust_blog_backend::security::csrf;
 /// csrf::init_csrf_secret().expect("Failed to initialize CSRF secret");
 /// ```
 pub fn init_csrf_secret() -> Result<(), String> {
@@ -149,6 +153,12 @@ fn get_secret() -> &'static [u8] {
         .as_slice()
 }
 
+/// Fixed secret for tests that send CSRF-protected requests.
+#[cfg(test)]
+pub(crate) fn init_test_secret() {
+    CSRF_SECRET.get_or_init(|| b"csrf-test-secret-0123456789-ABCDEFGHIJ".to_vec());
+}
+
 /// Issues a new CSRF token for a user.
 ///
 /// Creates a cryptographically signed token bound to the user's identity.
@@ -451,11 +461,15 @@ fn build_csrf_removal() -> Cookie<'static> {
 /// # Usage
 /// ```rust,no_run
 /// use axum::{Router, routing::post, middleware};
-/// use linux_tutorial_cms::csrf::CsrfGuard;
+/// use rust_blog_backend::security::csrf::CsrfGuard;
+/// # async fn handler() {}
+/// # fn build(pool: rust_blog_backend::db::DbPool) -> Router {
 ///
 /// let app = Router::new()
 ///     .route("/api/resource", post(handler))
-///     .route_layer(middleware::from_extractor::<CsrfGuard>());
+///     .route_layer(middleware::from_extractor_with_state::<CsrfGuard, _>(pool));
+/// # app
+/// # }
 /// ```
 ///
 /// # Security
@@ -585,3 +599,17 @@ pub async fn enforce_csrf(
 ) -> axum::response::Response {
     next.run(req).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_are_bound_to_their_user() {
+        init_test_secret();
+        let token = issue_csrf_token("admin").unwrap();
+        assert!(validate_csrf_token(&token, "admin").is_ok());
+        assert!(validate_csrf_token(&token, "editor").is_err());
+        assert!(issue_csrf_token("").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Site Settings
+//!
+//! Runtime-tunable flags stored in the "settings" content section. Reads go
+//! through a process-wide cache with a short TTL; writes to the section call
+//! [`invalidate`] so admins see their change take effect immediately.
+
+use crate::db::DbPool;
+use crate::models::SiteSettings;
+use crate::repositories;
+use serde_json::{Map, Value};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// Content section holding the settings document.
+pub const SECTION: &str = "settings";
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+type Cached = Option<(Instant, SiteSettings)>;
+
+fn cache() -> &'static RwLock<Cached> {
+    static CACHE: OnceLock<RwLock<Cached>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// Current settings, served from the cache while it is fresh.
+pub async fn get(pool: &DbPool) -> SiteSettings {
+    if let Ok(guard) = cache().read() {
+        if let Some((loaded_at, settings)) = guard.as_ref() {
+            if loaded_at.elapsed() < CACHE_TTL {
+                return settings.clone();
+            }
+        }
+    }
+
+    let settings = load(pool).await;
+    if let Ok(mut guard) = cache().write() {
+        *guard = Some((Instant::now(), settings.clone()));
+    }
+    settings
+}
+
+/// Drops the cached settings so the next [`get`] reads the database.
+pub fn invalidate() {
+    if let Ok(mut guard) = cache().write() {
+        *guard = None;
+    }
+}
+
+/// Reads the settings section, bypassing the cache. Falls back to the
+/// defaults when the section is missing or unreadable.
+pub async fn load(pool: &DbPool) -> SiteSettings {
+    let record = match repositories::content::fetch_site_content_by_section(pool, SECTION).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return SiteSettings::default(),
+        Err(err) => {
+            tracing::error!("Failed to load site settings: {}", err);
+            return SiteSettings::default();
+        }
+    };
+
+    serde_json::from_str(&record.content_json).unwrap_or_else(|err| {
+        tracing::warn!("Stored site settings are invalid, using defaults: {}", err);
+        SiteSettings::default()
+    })
+}
+
+/// Checks a settings document before it is stored.
+///
+/// Known keys must have the type [`SiteSettings`] expects. Unknown keys are
+/// kept (the frontend may store its own flags) but logged, since they are
+/// often typos of a known key.
+pub fn validate(content: &Value) -> Result<(), String> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    let known = known_keys();
+
+    for (key, value) in obj {
+        if !known.contains_key(key) {
+            tracing::warn!("Unknown key '{}' in site settings", key);
+            continue;
+        }
+        let mut single = Map::new();
+        single.insert(key.clone(), value.clone());
+        serde_json::from_value::<SiteSettings>(Value::Object(single))
+            .map_err(|err| format!("Field '{key}': {err}"))?;
+    }
+
+    Ok(())
+}
+
+fn known_keys() -> Map<String, Value> {
+    match serde_json::to_value(SiteSettings::default()) {
+        Ok(Value::Object(keys)) => keys,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_defaults_when_section_missing() {
+        let pool = crate::db::pool::create_test_pool().await;
+        let settings = load(&pool).await;
+        assert_eq!(settings, SiteSettings::default());
+        assert!(settings.pdf_enabled);
+        assert!(settings.comment_blocklist.is_empty());
+    }
+
+    #[test]
+    fn test_validate_checks_known_keys() {
+        assert!(validate(&json!({
+            "pdfEnabled": false,
+            "allowedIcons": ["Rocket"],
+            "commentBlocklist": [],
+            "maintenanceMessage": null,
+            "indexingEnabled": false,
+            "customFlag": 1
+        }))
+        .is_ok());
+
+        let err = validate(&json!({ "pdfEnabled": "yes" })).unwrap_err();
+        assert!(err.starts_with("Field 'pdfEnabled'"), "{err}");
+        assert!(validate(&json!({ "commentBlocklist": "spam" })).is_err());
+        assert!(validate(&json!({ "allowedIcons": [1] })).is_err());
+        assert!(validate(&json!({ "indexingEnabled": "no" })).is_err());
+        assert!(validate(&json!([])).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use super::{check_key, upload_url, Storage};
+use crate::utils::content_refs::{PRIVATE_UPLOAD_DIR, UPLOAD_FILES_DIR};
+use async_trait::async_trait;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Keeps uploads as files in one directory.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> io::Result<String> {
+        let path = self.path(key)?;
+        fs::create_dir_all(path.parent().unwrap_or(&self.root)).await?;
+        fs::write(&path, bytes).await?;
+        Ok(upload_url(key))
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool> {
+        fs::try_exists(self.path(key)?).await
+    }
+
+    async fn open(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for dir in ["", UPLOAD_FILES_DIR, PRIVATE_UPLOAD_DIR] {
+            let mut entries = match fs::read_dir(self.root.join(dir)).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                if !entry.file_type().await?.is_file() {
+                    continue;
+                }
+                if let Some(name) = entry.file_name().to_str() {
+                    let key = format!("{dir}{name}");
+                    if key.starts_with(prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    fn local_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_round_trip_and_key_checks() {
+        let root = std::env::temp_dir().join(format!("storage-test-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(root.join("nested"));
+
+        assert_eq!(storage.list("").await.unwrap(), Vec::<String>::new());
+        let url = storage.put("a.png", b"png".to_vec(), "image/png").await.unwrap();
+        assert_eq!(url, "/uploads/a.png");
+        storage.put("a-thumb.webp", b"webp".to_vec(), "image/webp").await.unwrap();
+        storage.put("b.png", b"other".to_vec(), "image/png").await.unwrap();
+        storage.put("files/c.pdf", b"%PDF".to_vec(), "application/pdf").await.unwrap();
+        assert!(storage.exists("files/c.pdf").await.unwrap());
+        assert_eq!(storage.list("files/").await.unwrap(), vec!["files/c.pdf"]);
+
+        assert!(storage.exists("a.png").await.unwrap());
+        assert_eq!(storage.open("a.png").await.unwrap(), Some(b"png".to_vec()));
+        let mut keys = storage.list("a").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a-thumb.webp", "a.png"]);
+
+        storage.delete("a.png").await.unwrap();
+        storage.delete("a.png").await.unwrap();
+        assert!(!storage.exists("a.png").await.unwrap());
+        assert_eq!(storage.open("a.png").await.unwrap(), None);
+
+        for key in ["../escape.png", "dir/a.png", ""] {
+            let err = storage.put(key, Vec::new(), "image/png").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,111 @@
+//! Upload Storage
+//!
+//! Where uploaded files are kept. The local file system is the default; with
+//! the `s3` cargo feature and `STORAGE_BACKEND=s3`, files go to an
+//! S3-compatible bucket instead. Either way uploads keep their
+//! `/uploads/<key>` URLs, so stored content and the reference checks do not
+//! depend on the backend.
+//!
+//! Keys are upload names as produced by the upload handlers (flat, or below
+//! `files/` for downloads and `private/` for drafts); backends reject
+//! anything else.
+
+mod local;
+#[cfg(feature = "s3")]
+mod s3;
+
+pub use local::LocalStorage;
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3Storage};
+
+use crate::utils::content_refs::{is_plain_upload_name, UPLOADS_PREFIX};
+use async_trait::async_trait;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `bytes` under `key` and returns the URL the file is served at.
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> io::Result<String>;
+
+    /// Removes `key`. Deleting a missing file is not an error.
+    async fn delete(&self, key: &str) -> io::Result<()>;
+
+    async fn exists(&self, key: &str) -> io::Result<bool>;
+
+    /// Reads the whole file, or `None` when it does not exist.
+    async fn open(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Keys starting with `prefix`, in no particular order.
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>>;
+
+    /// Directory to serve `/uploads` from directly, for backends that keep
+    /// files on local disk.
+    fn local_root(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Public location to redirect `/uploads/<key>` requests to. Backends
+    /// without one have their files proxied through [`Storage::open`].
+    fn public_url(&self, _key: &str) -> Option<String> {
+        None
+    }
+}
+
+static STORAGE: OnceLock<Arc<dyn Storage>> = OnceLock::new();
+
+/// URL an upload is served at, independent of the backend.
+pub fn upload_url(key: &str) -> String {
+    format!("{UPLOADS_PREFIX}{key}")
+}
+
+pub(crate) fn check_key(key: &str) -> io::Result<()> {
+    if is_plain_upload_name(key) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid storage key '{key}'"),
+        ))
+    }
+}
+
+/// Builds the backend selected by `STORAGE_BACKEND` (`local` by default).
+pub fn from_env() -> Result<Arc<dyn Storage>, String> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+    match backend.trim().to_ascii_lowercase().as_str() {
+        "" | "local" => {
+            let dir = std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
+            Ok(Arc::new(LocalStorage::new(dir)))
+        }
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Arc::new(S3Storage::new(S3Config::from_env()?))),
+        #[cfg(not(feature = "s3"))]
+        "s3" => Err("STORAGE_BACKEND=s3 requires building with the `s3` feature".to_string()),
+        other => Err(format!("Unknown STORAGE_BACKEND '{other}'")),
+    }
+}
+
+/// Selects the storage backend for the process. Must be called before the
+/// first upload is handled; later calls are rejected.
+pub fn init() -> Result<Arc<dyn Storage>, String> {
+    let storage = from_env()?;
+    STORAGE
+        .set(storage.clone())
+        .map_err(|_| "Upload storage already initialized".to_string())?;
+    Ok(storage)
+}
+
+/// The process-wide backend. Falls back to the environment's backend when
+/// [`init`] was never called, e.g. in tools and tests.
+pub fn get() -> Arc<dyn Storage> {
+    STORAGE
+        .get_or_init(|| {
+            from_env().unwrap_or_else(|err| {
+                tracing::error!("{}; falling back to local upload storage", err);
+                Arc::new(LocalStorage::new("uploads"))
+            })
+        })
+        .clone()
+}
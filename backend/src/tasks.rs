@@ -0,0 +1,144 @@
+//! Heartbeats of background tasks, for the readiness probe.
+//!
+//! Each task registers itself when it starts and beats after every run. The
+//! returned [`Heartbeat`] marks the task as stopped when it is dropped, which
+//! also happens when the task panics, so a dead task shows up right away
+//! instead of only once its heartbeat has gone stale.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+/// State of one background task as reported by `/api/health/ready`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskHealth {
+    pub name: &'static str,
+    /// Readiness fails while a critical task is not running.
+    pub critical: bool,
+    pub running: bool,
+    /// RFC 3339 time of the last completed run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_heartbeat: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: &'static str,
+    critical: bool,
+    running: bool,
+    last_heartbeat: Option<DateTime<Utc>>,
+}
+
+/// Registry shared by the background tasks and the readiness handler.
+#[derive(Debug, Clone, Default)]
+pub struct TaskRegistry {
+    entries: Arc<RwLock<Vec<Entry>>>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `name` as running. Registering a name again replaces the
+    /// earlier entry, e.g. for a restarted task.
+    pub fn register(&self, name: &'static str, critical: bool) -> Heartbeat {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.retain(|entry| entry.name != name);
+            entries.push(Entry {
+                name,
+                critical,
+                running: true,
+                last_heartbeat: None,
+            });
+        }
+        Heartbeat {
+            registry: self.clone(),
+            name,
+        }
+    }
+
+    pub fn tasks(&self) -> Vec<TaskHealth> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        entries
+            .iter()
+            .map(|entry| TaskHealth {
+                name: entry.name,
+                critical: entry.critical,
+                running: entry.running,
+                last_heartbeat: entry
+                    .last_heartbeat
+                    .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })
+            .collect()
+    }
+
+    fn update(&self, name: &'static str, apply: impl FnOnce(&mut Entry)) {
+        if let Ok(mut entries) = self.entries.write() {
+            if let Some(entry) = entries.iter_mut().find(|entry| entry.name == name) {
+                apply(entry);
+            }
+        }
+    }
+}
+
+/// Held by a running task; see the module docs.
+#[derive(Debug)]
+#[must_use = "the task counts as stopped once its heartbeat is dropped"]
+pub struct Heartbeat {
+    registry: TaskRegistry,
+    name: &'static str,
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        self.registry
+            .update(self.name, |entry| entry.last_heartbeat = Some(Utc::now()));
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.registry
+            .update(self.name, |entry| entry.running = false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_heartbeats_and_dead_tasks() {
+        let registry = TaskRegistry::new();
+        let heartbeat = registry.register("cleanup", true);
+        assert_eq!(
+            registry.tasks(),
+            vec![TaskHealth {
+                name: "cleanup",
+                critical: true,
+                running: true,
+                last_heartbeat: None,
+            }]
+        );
+
+        heartbeat.beat();
+        assert!(registry.tasks()[0].last_heartbeat.is_some());
+
+        let task = tokio::spawn({
+            let registry = registry.clone();
+            async move {
+                let _heartbeat = registry.register("purge", false);
+                panic!("task failure");
+            }
+        });
+        assert!(task.await.is_err());
+        drop(heartbeat);
+
+        let tasks = registry.tasks();
+        assert!(tasks.iter().all(|task| !task.running));
+        assert_eq!(tasks.len(), 2);
+    }
+}
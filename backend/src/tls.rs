@@ -0,0 +1,489 @@
+//! Optional HTTPS termination for deployments without a reverse proxy.
+//!
+//! With `TLS_CERT_PATH` and `TLS_KEY_PATH` both set, the server speaks TLS on
+//! `PORT`. The PEM files are checked for changes every few seconds and
+//! reloaded without a restart; a broken replacement is logged and the
+//! previous certificate stays in use. `TLS_HTTP_REDIRECT_PORT` adds a plain
+//! HTTP listener that answers everything with a `301` to HTTPS, on
+//! `TLS_PUBLIC_PORT` when clients reach the server on another port than
+//! `PORT` (e.g. behind a container port mapping).
+
+use axum::{
+    http::{
+        header::{HOST, LOCATION},
+        StatusCode, Uri,
+    },
+    response::IntoResponse,
+    serve::Listener,
+    Router,
+};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+        ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+
+/// How often the certificate files are checked for changes.
+pub const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Clients that have not finished the handshake by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for the server to pick them up.
+const ACCEPT_BACKLOG: usize = 64;
+
+/// TLS settings, read from the environment once at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsSettings {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// Port of the plain HTTP listener redirecting to HTTPS.
+    pub redirect_port: Option<u16>,
+    /// Port named in redirects, when it differs from the one we listen on.
+    pub public_port: Option<u16>,
+}
+
+impl TlsSettings {
+    /// `None` when TLS is not configured.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::parse(|key| std::env::var(key).ok())
+    }
+
+    pub(crate) fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let value = |key: &str| {
+            lookup(key)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let port = |key: &str| -> Result<Option<u16>, String> {
+            value(key)
+                .map(|raw| match raw.parse::<u16>() {
+                    Ok(port) if port > 0 => Ok(port),
+                    _ => Err(format!("{} must be a port number, got '{}'", key, raw)),
+                })
+                .transpose()
+        };
+
+        let (cert_path, key_path) = match (value("TLS_CERT_PATH"), value("TLS_KEY_PATH")) {
+            (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+            (None, None) => return Ok(None),
+            _ => return Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string()),
+        };
+        Ok(Some(Self {
+            cert_path,
+            key_path,
+            redirect_port: port("TLS_HTTP_REDIRECT_PORT")?,
+            public_port: port("TLS_PUBLIC_PORT")?,
+        }))
+    }
+}
+
+/// Reads the certificate chain and private key and checks that they belong
+/// together.
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, String> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| {
+            format!(
+                "Failed to read TLS certificate '{}': {}",
+                cert_path.display(),
+                err
+            )
+        })?;
+    if certs.is_empty() {
+        return Err(format!(
+            "TLS certificate '{}' contains no certificates",
+            cert_path.display()
+        ));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|err| {
+        format!(
+            "Failed to read TLS private key '{}': {}",
+            key_path.display(),
+            err
+        )
+    })?;
+    CertifiedKey::from_der(certs, key, &ring::default_provider()).map_err(|err| {
+        format!(
+            "TLS private key '{}' does not match certificate '{}': {}",
+            key_path.display(),
+            cert_path.display(),
+            err
+        )
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// The certificate in use, swapped out when the files change.
+#[derive(Debug)]
+pub struct CertStore {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the files the current certificate came from.
+    loaded_from: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertStore {
+    pub fn load(settings: &TlsSettings) -> Result<Arc<Self>, String> {
+        let loaded_from = (modified(&settings.cert_path), modified(&settings.key_path));
+        let key = load_certified_key(&settings.cert_path, &settings.key_path)?;
+        Ok(Arc::new(Self {
+            cert_path: settings.cert_path.clone(),
+            key_path: settings.key_path.clone(),
+            current: RwLock::new(Arc::new(key)),
+            loaded_from: Mutex::new(loaded_from),
+        }))
+    }
+
+    /// Reloads the certificate if either file changed since the last
+    /// attempt. Returns whether a new certificate was put in place; on error
+    /// the old one stays.
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let files = (modified(&self.cert_path), modified(&self.key_path));
+        {
+            let mut loaded_from = self
+                .loaded_from
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if *loaded_from == files {
+                return Ok(false);
+            }
+            // Not retried until the files change again
+            *loaded_from = files;
+        }
+        let key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(key);
+        Ok(true)
+    }
+
+    pub fn server_config(self: &Arc<Self>) -> Result<Arc<ServerConfig>, String> {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|err| format!("Failed to configure TLS: {}", err))?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// Accepts TCP connections and hands them to the server once the TLS
+/// handshake is done. Handshakes run in their own tasks, so a slow client
+/// never holds up the others.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    handshaken: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(mut listener: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (sender, handshaken) = mpsc::channel(ACCEPT_BACKLOG);
+        tokio::spawn(async move {
+            loop {
+                // Stops accepting once the server dropped the listener
+                let (stream, remote_addr) = tokio::select! {
+                    accepted = Listener::accept(&mut listener) => accepted,
+                    _ = sender.closed() => break,
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, remote_addr)).await;
+                        }
+                        Ok(Err(err)) => {
+                            tracing::debug!(client_ip = %remote_addr.ip(), "TLS handshake failed: {}", err)
+                        }
+                        Err(_) => {
+                            tracing::debug!(client_ip = %remote_addr.ip(), "TLS handshake timed out")
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            local_addr,
+            handshaken,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaken.recv().await {
+            Some(accepted) => accepted,
+            // The accept loop only ends once we are gone
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Disables Nagle's algorithm on an accepted connection. Passed to
+/// `tap_io`, which also gives the TLS listener `ConnectInfo<SocketAddr>`.
+pub fn set_nodelay(stream: &mut TlsStream<TcpStream>) {
+    if let Err(err) = stream.get_ref().0.set_nodelay(true) {
+        tracing::trace!("Failed to set TCP_NODELAY: {}", err);
+    }
+}
+
+/// Request extension marking requests that arrived over our own TLS
+/// listener.
+#[derive(Debug, Clone, Copy)]
+pub struct Https;
+
+/// The HTTPS location of a request that came in over plain HTTP.
+fn https_location(host: &str, uri: &Uri, port: u16) -> Option<String> {
+    let authority: axum::http::uri::Authority = host.parse().ok()?;
+    let host = authority.host();
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    Some(if port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, port, path)
+    })
+}
+
+/// Answers every request with a `301` to the same URL over HTTPS on `port`.
+pub fn redirect_app(port: u16) -> Router {
+    Router::new().fallback(move |request: axum::extract::Request| async move {
+        let location = request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .and_then(|host| https_location(host, request.uri(), port));
+        match location {
+            Some(location) => {
+                (StatusCode::MOVED_PERMANENTLY, [(LOCATION, location)]).into_response()
+            }
+            None => (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::ConnectInfo, routing::get, serve::ListenerExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+    use tower::ServiceExt;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+    }
+
+    fn settings(cert: PathBuf, key: PathBuf) -> TlsSettings {
+        TlsSettings {
+            cert_path: cert,
+            key_path: key,
+            redirect_port: None,
+            public_port: None,
+        }
+    }
+
+    #[test]
+    fn test_settings_from_env() {
+        let parse = |vars: &[(&str, &str)]| {
+            TlsSettings::parse(|key| {
+                vars.iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(
+            parse(&[
+                ("TLS_CERT_PATH", "/certs/fullchain.pem"),
+                ("TLS_KEY_PATH", " /certs/privkey.pem "),
+                ("TLS_HTTP_REDIRECT_PORT", "8080"),
+            ])
+            .unwrap(),
+            Some(TlsSettings {
+                cert_path: "/certs/fullchain.pem".into(),
+                key_path: "/certs/privkey.pem".into(),
+                redirect_port: Some(8080),
+                public_port: None,
+            })
+        );
+        assert!(parse(&[("TLS_CERT_PATH", "/certs/fullchain.pem")]).is_err());
+        assert!(parse(&[
+            ("TLS_CERT_PATH", "/certs/fullchain.pem"),
+            ("TLS_KEY_PATH", "/certs/privkey.pem"),
+            ("TLS_HTTP_REDIRECT_PORT", "http"),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_unreadable_and_mismatched_files_are_rejected() {
+        let err = load_certified_key(&fixture("missing.pem"), &fixture("key.pem")).unwrap_err();
+        assert!(err.starts_with("Failed to read TLS certificate"), "{err}");
+
+        let err = load_certified_key(&fixture("cert.pem"), &fixture("cert.pem")).unwrap_err();
+        assert!(err.starts_with("Failed to read TLS private key"), "{err}");
+
+        let err = load_certified_key(&fixture("cert.pem"), &fixture("other_key.pem")).unwrap_err();
+        assert!(err.contains("does not match"), "{err}");
+
+        assert!(load_certified_key(&fixture("cert.pem"), &fixture("key.pem")).is_ok());
+    }
+
+    #[test]
+    fn test_certificate_reload() {
+        let dir = std::env::temp_dir().join(format!("tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+        std::fs::copy(fixture("cert.pem"), &cert).unwrap();
+        std::fs::copy(fixture("key.pem"), &key).unwrap();
+        let store = CertStore::load(&settings(cert.clone(), key.clone())).unwrap();
+        let initial = store.current.read().unwrap().clone();
+        assert_eq!(store.reload_if_changed(), Ok(false));
+
+        // Pushed into the future so the change shows on coarse clocks too
+        let replace = |path: &Path, from: &str, minutes: u64| {
+            std::fs::copy(fixture(from), path).unwrap();
+            let file = std::fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(60 * minutes))
+                .unwrap();
+        };
+
+        // A half-written renewal keeps the old certificate in place
+        replace(&key, "other_key.pem", 1);
+        assert!(store.reload_if_changed().is_err());
+        assert!(Arc::ptr_eq(&store.current.read().unwrap(), &initial));
+
+        replace(&key, "key.pem", 2);
+        assert_eq!(store.reload_if_changed(), Ok(true));
+        assert!(!Arc::ptr_eq(&store.current.read().unwrap(), &initial));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_with_connect_info() {
+        let store = CertStore::load(&settings(fixture("cert.pem"), fixture("key.pem"))).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = TlsListener::new(listener, store.server_config().unwrap())
+            .unwrap()
+            .tap_io(set_nodelay);
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/api/health",
+            get(|ConnectInfo(remote): ConnectInfo<SocketAddr>| async move {
+                format!("OK from {}", remote.ip())
+            }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async {
+            let _ = stopped.await;
+        });
+        let server = tokio::spawn(async move { server.await });
+
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(fixture("cert.pem")).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET /api/health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("OK from 127.0.0.1"), "{response}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_is_redirected() {
+        let send = |app: Router, host: Option<&'static str>| async move {
+            let mut request = axum::http::Request::builder().uri("/tutorials/bash?tab=2");
+            if let Some(host) = host {
+                request = request.header(HOST, host);
+            }
+            app.oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+        };
+
+        let response = send(redirect_app(443), Some("example.com:80")).await;
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://example.com/tutorials/bash?tab=2"
+        );
+
+        let response = send(redirect_app(8443), Some("[::1]:8080")).await;
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://[::1]:8443/tutorials/bash?tab=2"
+        );
+
+        let response = send(redirect_app(443), None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
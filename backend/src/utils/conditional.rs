@@ -0,0 +1,154 @@
+//! Conditional Requests
+//!
+//! Helpers for answering `If-None-Match` with `304 Not Modified`. Handlers
+//! build an ETag from whatever identifies the current representation and hand
+//! the body to [`respond`], which either serializes it or short-circuits with
+//! an empty 304.
+
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Cache policy for public, rarely changing API data.
+pub const PUBLIC_SHORT_CACHE: &str = "public, max-age=60, stale-while-revalidate=300";
+
+/// Cache policy for per-viewer data: browsers may keep it but must revalidate.
+pub const PRIVATE_REVALIDATE: &str = "private, no-cache";
+
+/// Cache policy for HTML: any cache may keep it but must revalidate.
+pub const REVALIDATE: &str = "no-cache";
+
+/// Cache policy for pages viewed anonymously: browsers revalidate, shared
+/// caches like a CDN may serve them for a few minutes.
+pub const EDGE_CACHE: &str = "public, max-age=0, s-maxage=300, stale-while-revalidate=600";
+
+/// Cache policy for responses to signed-in viewers: kept by no cache at all.
+pub const PRIVATE_NO_STORE: &str = "private, no-store";
+
+/// Builds a weak ETag from the given parts.
+///
+/// Parts are length-prefixed before hashing so `["ab", "c"]` and `["a", "bc"]`
+/// produce different tags.
+pub fn weak_etag<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let hex: String = digest[..16].iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("W/\"{hex}\"")
+}
+
+/// Whether the request's `If-None-Match` header matches `etag`.
+///
+/// Comparison is weak, as RFC 9110 requires for `If-None-Match`.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = strip(etag);
+
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == "*" || strip(candidate) == current)
+}
+
+/// Answers with `304 Not Modified` when the client already holds `etag`,
+/// otherwise with the JSON body. Both carry the ETag and cache policy.
+pub fn respond<T: Serialize>(
+    headers: &HeaderMap,
+    etag: &str,
+    cache_control: &'static str,
+    body: T,
+) -> Response {
+    with_validators(headers, etag, cache_control, || Json(body).into_response())
+}
+
+/// Like [`respond`], for an HTML page.
+pub fn respond_html(
+    headers: &HeaderMap,
+    etag: &str,
+    cache_control: &'static str,
+    html: String,
+) -> Response {
+    with_validators(headers, etag, cache_control, || Html(html).into_response())
+}
+
+fn with_validators(
+    headers: &HeaderMap,
+    etag: &str,
+    cache_control: &'static str,
+    body: impl FnOnce() -> Response,
+) -> Response {
+    let mut response = if if_none_match(headers, etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        body()
+    };
+
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(ETAG, value);
+    }
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_weak_etag_is_stable_and_unambiguous() {
+        assert_eq!(weak_etag(["a", "b"]), weak_etag(["a", "b"]));
+        assert_ne!(weak_etag(["ab", "c"]), weak_etag(["a", "bc"]));
+        assert!(weak_etag(["x"]).starts_with("W/\""));
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        let etag = weak_etag(["x"]);
+        let strong = etag.trim_start_matches("W/");
+
+        assert!(if_none_match(&request_with(&etag), &etag));
+        assert!(if_none_match(&request_with(strong), &etag));
+        assert!(if_none_match(
+            &request_with(&format!("\"other\", {etag}")),
+            &etag
+        ));
+        assert!(if_none_match(&request_with("*"), &etag));
+        assert!(!if_none_match(&request_with("\"other\""), &etag));
+        assert!(!if_none_match(&HeaderMap::new(), &etag));
+    }
+
+    #[test]
+    fn test_html_is_answered_with_not_modified() {
+        let html = "<html><title>Grundlagen</title></html>";
+        let etag = weak_etag([html]);
+
+        let response = respond_html(&HeaderMap::new(), &etag, REVALIDATE, html.to_string());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+        assert_eq!(response.headers()[CACHE_CONTROL], REVALIDATE);
+
+        let response = respond_html(&request_with(&etag), &etag, REVALIDATE, html.to_string());
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+    }
+}
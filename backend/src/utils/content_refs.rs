@@ -0,0 +1,328 @@
+//! Internal Content References
+//!
+//! Finds the places where stored content points at other parts of the site:
+//! link targets (`{ "type": "section" | "route" | "page", ... }`), `/uploads/`
+//! paths and internal markdown links. Callers resolve the references against
+//! the database and file system; this module only extracts and classifies
+//! them.
+
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// Anchors the home page can scroll to, including the German aliases the
+/// frontend maps onto the tutorial grid.
+pub const HOME_SECTIONS: &[&str] = &[
+    "home",
+    "tutorials",
+    "grundlagen",
+    "befehle",
+    "praxis",
+    "advanced",
+];
+
+/// Frontend routes that exist regardless of content.
+pub const STATIC_ROUTES: &[&str] = &["/", "/blog", "/login", "/admin"];
+
+/// Upload URLs are served below this prefix.
+pub const UPLOADS_PREFIX: &str = "/uploads/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceTarget {
+    /// Anchor on the home page.
+    Section(String),
+    /// Site-relative path, including `/uploads/` files.
+    Route(String),
+}
+
+/// Where a reference was found: a JSON Pointer for JSON documents, a line
+/// number for markdown.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceLocation {
+    Pointer(String),
+    Line(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundReference {
+    pub target: ReferenceTarget,
+    pub location: ReferenceLocation,
+}
+
+/// What a site-relative path points at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteKind<'a> {
+    Static,
+    /// API endpoints are not checked.
+    Api,
+    Upload(&'a str),
+    Page(&'a str),
+    Post { page: &'a str, post: &'a str },
+    Tutorial(&'a str),
+    Unknown,
+}
+
+/// Classifies a site-relative path. Query strings and fragments are ignored.
+pub fn classify_route(path: &str) -> RouteKind<'_> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let trimmed = if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    };
+
+    if STATIC_ROUTES.contains(&trimmed) {
+        return RouteKind::Static;
+    }
+    if trimmed == "/api" || trimmed.starts_with("/api/") {
+        return RouteKind::Api;
+    }
+    if let Some(file) = path.strip_prefix(UPLOADS_PREFIX) {
+        return RouteKind::Upload(file);
+    }
+    if let Some(id) = trimmed.strip_prefix("/tutorials/") {
+        if !id.is_empty() && !id.contains('/') {
+            return RouteKind::Tutorial(id);
+        }
+    }
+    if let Some(rest) = trimmed.strip_prefix("/pages/") {
+        let segments: Vec<&str> = rest.split('/').collect();
+        match segments.as_slice() {
+            [page] if !page.is_empty() => return RouteKind::Page(page),
+            [page, "posts", post] if !page.is_empty() && !post.is_empty() => {
+                return RouteKind::Post { page, post };
+            }
+            _ => {}
+        }
+    }
+    RouteKind::Unknown
+}
+
+/// Downloadable files live in this directory below `/uploads`.
+pub const UPLOAD_FILES_DIR: &str = "files/";
+
+/// Draft uploads live in this directory below `/uploads` and are only served
+/// to signed-in users.
+pub const PRIVATE_UPLOAD_DIR: &str = "private/";
+
+/// Upload names are flat, apart from downloads in [`UPLOAD_FILES_DIR`] and
+/// drafts in [`PRIVATE_UPLOAD_DIR`]; anything else with separators or dot
+/// segments cannot have come from the upload handlers.
+pub fn is_plain_upload_name(name: &str) -> bool {
+    let name = name
+        .strip_prefix(UPLOAD_FILES_DIR)
+        .or_else(|| name.strip_prefix(PRIVATE_UPLOAD_DIR))
+        .unwrap_or(name);
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.contains("..")
+}
+
+/// Collects the internal references in a JSON document.
+pub fn json_references(value: &Value) -> Vec<FoundReference> {
+    let mut found = Vec::new();
+    collect_json(value, String::new(), &mut found);
+    found
+}
+
+fn collect_json(value: &Value, pointer: String, found: &mut Vec<FoundReference>) {
+    match value {
+        Value::Object(obj) => {
+            let consumed = link_target(obj, &pointer, found);
+            for (key, child) in obj {
+                if Some(key.as_str()) == consumed {
+                    continue;
+                }
+                collect_json(child, child_pointer(&pointer, key), found);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_json(item, child_pointer(&pointer, &index.to_string()), found);
+            }
+        }
+        Value::String(text) if text.starts_with(UPLOADS_PREFIX) => found.push(FoundReference {
+            target: ReferenceTarget::Route(text.clone()),
+            location: ReferenceLocation::Pointer(pointer),
+        }),
+        _ => {}
+    }
+}
+
+/// Records the target of a typed link object and returns the key it was
+/// read from, so the caller does not report it a second time.
+fn link_target<'a>(
+    obj: &'a serde_json::Map<String, Value>,
+    pointer: &str,
+    found: &mut Vec<FoundReference>,
+) -> Option<&'a str> {
+    let kind = obj.get("type").and_then(Value::as_str)?;
+    let keys: &[&str] = match kind {
+        "section" => &["value", "id"],
+        "route" | "page" => &["value", "path", "slug"],
+        _ => return None,
+    };
+    let (key, raw) = keys.iter().find_map(|key| {
+        let (key, value) = obj.get_key_value(*key)?;
+        Some((key.as_str(), value.as_str()?.trim()))
+    })?;
+    if raw.is_empty() {
+        return None;
+    }
+
+    let target = match (kind, key) {
+        ("section", _) => ReferenceTarget::Section(raw.trim_start_matches('#').to_string()),
+        (_, "slug") => ReferenceTarget::Route(format!("/pages/{raw}")),
+        _ if raw.starts_with('/') => ReferenceTarget::Route(raw.to_string()),
+        _ => ReferenceTarget::Route(format!("/{raw}")),
+    };
+    found.push(FoundReference {
+        target,
+        location: ReferenceLocation::Pointer(child_pointer(pointer, key)),
+    });
+    Some(key)
+}
+
+fn child_pointer(parent: &str, segment: &str) -> String {
+    format!("{parent}/{}", segment.replace('~', "~0").replace('/', "~1"))
+}
+
+fn markdown_link_regex() -> &'static Regex {
+    static LINK_RE: OnceLock<Regex> = OnceLock::new();
+    LINK_RE.get_or_init(|| {
+        Regex::new(r#"\]\([ \t]*<?([^) \t>]+)|(?:src|href)[ \t]*=[ \t]*["']([^"']+)["']"#)
+            .expect("valid markdown link regex")
+    })
+}
+
+/// Collects site-relative link and image targets in markdown, skipping
+/// fenced code blocks.
+pub fn markdown_references(markdown: &str) -> Vec<FoundReference> {
+    let mut found = Vec::new();
+    let mut in_fence = false;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        for captures in markdown_link_regex().captures_iter(line) {
+            let Some(target) = captures.get(1).or_else(|| captures.get(2)) else {
+                continue;
+            };
+            let target = target.as_str();
+            if target.starts_with('/') && !target.starts_with("//") {
+                found.push(FoundReference {
+                    target: ReferenceTarget::Route(target.to_string()),
+                    location: ReferenceLocation::Line(index + 1),
+                });
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn route(path: &str, pointer: &str) -> FoundReference {
+        FoundReference {
+            target: ReferenceTarget::Route(path.to_string()),
+            location: ReferenceLocation::Pointer(pointer.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_classify_route() {
+        assert_eq!(classify_route("/blog/"), RouteKind::Static);
+        assert_eq!(classify_route("/?q=1"), RouteKind::Static);
+        assert_eq!(classify_route("/api/public/bootstrap"), RouteKind::Api);
+        assert_eq!(classify_route("/uploads/a.png"), RouteKind::Upload("a.png"));
+        assert_eq!(classify_route("/tutorials/42#top"), RouteKind::Tutorial("42"));
+        assert_eq!(classify_route("/pages/shell"), RouteKind::Page("shell"));
+        assert_eq!(
+            classify_route("/pages/shell/posts/pipes"),
+            RouteKind::Post {
+                page: "shell",
+                post: "pipes"
+            }
+        );
+        assert_eq!(classify_route("/grundlagen"), RouteKind::Unknown);
+        assert_eq!(classify_route("/pages/shell/extra"), RouteKind::Unknown);
+
+        assert!(is_plain_upload_name("a.png"));
+        assert!(is_plain_upload_name("files/cheatsheet.pdf"));
+        assert!(!is_plain_upload_name("files/"));
+        assert!(!is_plain_upload_name("files/../a.png"));
+        assert!(is_plain_upload_name("private/a.png"));
+        assert!(!is_plain_upload_name("private/../a.png"));
+        assert!(!is_plain_upload_name("private/files/a.png"));
+        assert!(!is_plain_upload_name("other/a.png"));
+    }
+
+    #[test]
+    fn test_json_references() {
+        let doc = json!({
+            "navItems": [
+                { "id": "home", "type": "section" },
+                { "id": "docs", "type": "route", "path": "/pages/docs" }
+            ],
+            "quickLinks": [
+                { "label": "Blog", "target": { "type": "route", "value": "/blog" } },
+                { "label": "Shell", "target": { "type": "page", "slug": "shell" } },
+                { "label": "Tut", "target": { "type": "section", "value": "#tutorials" } }
+            ],
+            "image": "/uploads/logo.png",
+            "external": { "type": "external", "value": "https://example.com" }
+        });
+
+        let found = json_references(&doc);
+        assert_eq!(
+            found,
+            vec![
+                route("/uploads/logo.png", "/image"),
+                FoundReference {
+                    target: ReferenceTarget::Section("home".to_string()),
+                    location: ReferenceLocation::Pointer("/navItems/0/id".to_string()),
+                },
+                route("/pages/docs", "/navItems/1/path"),
+                route("/blog", "/quickLinks/0/target/value"),
+                route("/pages/shell", "/quickLinks/1/target/slug"),
+                FoundReference {
+                    target: ReferenceTarget::Section("tutorials".to_string()),
+                    location: ReferenceLocation::Pointer("/quickLinks/2/target/value".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_references_skip_code_and_external_links() {
+        let markdown = "Intro [Docs](/pages/docs) and [ext](https://x.org)\n\
+                        ```\n[code](/pages/ignored)\n```\n\
+                        ![Bild](/uploads/a.png \"Titel\")\n\
+                        <img src=\"/uploads/b.png\"> [anchor](#top) [proto](//cdn.example)";
+
+        let found: Vec<(String, ReferenceLocation)> = markdown_references(markdown)
+            .into_iter()
+            .map(|found| match found.target {
+                ReferenceTarget::Route(path) => (path, found.location),
+                ReferenceTarget::Section(name) => (name, found.location),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/pages/docs".to_string(), ReferenceLocation::Line(1)),
+                ("/uploads/a.png".to_string(), ReferenceLocation::Line(5)),
+                ("/uploads/b.png".to_string(), ReferenceLocation::Line(6)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Image Metadata Stripping
+//!
+//! Removes EXIF, XMP, IPTC and comment data from uploaded images by
+//! rewriting their container structure. Pixel data is copied untouched, so
+//! nothing is re-encoded and dimensions and quality stay the same.
+//!
+//! JPEG orientation is the one EXIF field kept: phones store rotation there
+//! instead of in the pixels, and dropping it would turn photos sideways.
+
+/// Returns a copy of `data` without metadata, `Ok(None)` when the format is
+/// not one we rewrite, or an error when the file structure is malformed.
+pub fn strip_metadata(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg(data).map(Some)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        png(data).map(Some)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        webp(data).map(Some)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        gif(data).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn truncated() -> String {
+    "Image data is truncated".to_string()
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 2)?;
+    Some(usize::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+}
+
+fn jpeg(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut at = 2;
+    let mut orientation = None;
+
+    loop {
+        if *data.get(at).ok_or_else(truncated)? != 0xFF {
+            return Err("Invalid JPEG segment marker".to_string());
+        }
+        let marker = *data.get(at + 1).ok_or_else(truncated)?;
+        match marker {
+            // Fill byte before the actual marker.
+            0xFF => at += 1,
+            0x01 | 0xD0..=0xD7 => {
+                out.extend_from_slice(&data[at..at + 2]);
+                at += 2;
+            }
+            // Entropy-coded data follows; copy the rest verbatim.
+            0xDA | 0xD9 => {
+                if let Some(value) = orientation.take() {
+                    out.extend_from_slice(&orientation_segment(value));
+                }
+                out.extend_from_slice(&data[at..]);
+                return Ok(out);
+            }
+            _ => {
+                let len = be_u16(data, at + 2).ok_or_else(truncated)?;
+                if len < 2 {
+                    return Err("Invalid JPEG segment length".to_string());
+                }
+                let segment = data.get(at..at + 2 + len).ok_or_else(truncated)?;
+                match marker {
+                    // APP1 (EXIF, XMP): keep only the orientation.
+                    0xE1 => {
+                        if let Some(value) = exif_orientation(&segment[4..]) {
+                            orientation = Some(value);
+                        }
+                    }
+                    // APP13 (IPTC) and comments.
+                    0xED | 0xFE => {}
+                    _ => {
+                        // Re-insert the orientation before the next kept segment.
+                        if let Some(value) = orientation.take() {
+                            out.extend_from_slice(&orientation_segment(value));
+                        }
+                        out.extend_from_slice(segment);
+                    }
+                }
+                at += 2 + len;
+            }
+        }
+    }
+}
+
+/// Reads the orientation tag from IFD0 of an `Exif\0\0` APP1 payload.
+fn exif_orientation(payload: &[u8]) -> Option<u16> {
+    let tiff = payload.strip_prefix(b"Exif\0\0")?;
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| {
+        let bytes = tiff.get(at..at + 2)?;
+        Some(if little_endian {
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        } else {
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        })
+    };
+    let u32_at = |at: usize| {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    let ifd = usize::try_from(u32_at(4)?).ok()?;
+    let count = usize::from(u16_at(ifd)?);
+    (0..count).find_map(|index| {
+        let entry = ifd + 2 + index * 12;
+        (u16_at(entry)? == 0x0112)
+            .then(|| u16_at(entry + 8))
+            .flatten()
+            .filter(|value| (1..=8).contains(value))
+    })
+}
+
+/// A minimal big-endian EXIF APP1 segment holding only the orientation.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut payload = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+    payload.extend_from_slice(&orientation.to_be_bytes());
+    payload.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(&payload);
+    segment
+}
+
+fn png(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut at = PNG_SIGNATURE.len();
+
+    while at < data.len() {
+        let len_bytes: [u8; 4] = data
+            .get(at..at + 4)
+            .ok_or_else(truncated)?
+            .try_into()
+            .map_err(|_| truncated())?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        let kind = data.get(at + 4..at + 8).ok_or_else(truncated)?;
+        let chunk = data.get(at..at + 12 + len).ok_or_else(truncated)?;
+
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(chunk);
+        }
+        at += 12 + len;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+fn webp(data: &[u8]) -> Result<Vec<u8>, String> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    let mut out = data[..12].to_vec();
+    let mut at = 12;
+
+    while at < data.len() {
+        let kind = data.get(at..at + 4).ok_or_else(truncated)?;
+        let len_bytes: [u8; 4] = data
+            .get(at + 4..at + 8)
+            .ok_or_else(truncated)?
+            .try_into()
+            .map_err(|_| truncated())?;
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        // Chunks are padded to an even size; the last pad byte may be missing.
+        let end = (at + 8 + len + (len & 1)).min(data.len());
+        let chunk = data.get(at..end).filter(|chunk| chunk.len() >= 8 + len).ok_or_else(truncated)?;
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let flags_at = out.len() + 8;
+                out.extend_from_slice(chunk);
+                if let Some(flags) = out.get_mut(flags_at) {
+                    *flags &= !(EXIF_FLAG | XMP_FLAG);
+                }
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        at = end;
+    }
+
+    let riff_size = u32::try_from(out.len() - 8).map_err(|_| "WebP file too large".to_string())?;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(out)
+}
+
+fn gif(data: &[u8]) -> Result<Vec<u8>, String> {
+    /// Length of the sub-block chain starting at `at`, including the
+    /// terminating zero-length block.
+    fn sub_blocks(data: &[u8], mut at: usize) -> Result<usize, String> {
+        let start = at;
+        loop {
+            let size = usize::from(*data.get(at).ok_or_else(truncated)?);
+            at += 1 + size;
+            if size == 0 {
+                return Ok(at - start);
+            }
+        }
+    }
+
+    let flags = *data.get(10).ok_or_else(truncated)?;
+    let mut at = 13;
+    if flags & 0x80 != 0 {
+        at += 3 << ((flags & 0x07) + 1);
+    }
+    let mut out = data.get(..at).ok_or_else(truncated)?.to_vec();
+
+    loop {
+        match *data.get(at).ok_or_else(truncated)? {
+            // Trailer.
+            0x3B => {
+                out.push(0x3B);
+                return Ok(out);
+            }
+            // Image descriptor, optional local color table, LZW code size,
+            // then image data sub-blocks.
+            0x2C => {
+                let descriptor_flags = *data.get(at + 9).ok_or_else(truncated)?;
+                let mut end = at + 10;
+                if descriptor_flags & 0x80 != 0 {
+                    end += 3 << ((descriptor_flags & 0x07) + 1);
+                }
+                end += 1;
+                end += sub_blocks(data, end)?;
+                out.extend_from_slice(data.get(at..end).ok_or_else(truncated)?);
+                at = end;
+            }
+            0x21 => {
+                let label = *data.get(at + 1).ok_or_else(truncated)?;
+                let end = at + 2 + sub_blocks(data, at + 2)?;
+                let is_xmp = label == 0xFF && data.get(at + 3..at + 14) == Some(b"XMP DataXMP");
+                // Drop comments and XMP; keep graphic control, looping, etc.
+                if label != 0xFE && !is_xmp {
+                    out.extend_from_slice(data.get(at..end).ok_or_else(truncated)?);
+                }
+                at = end;
+            }
+            _ => return Err("Invalid GIF block".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::utils::image_size::dimensions;
+
+    /// JPEG with JFIF, an EXIF block holding GPS data and orientation 6, a
+    /// comment and a 300x200 frame header.
+    pub(crate) fn jpeg_with_gps() -> Vec<u8> {
+        let mut tiff = b"II\x2a\0\x08\0\0\0".to_vec();
+        // IFD0: orientation = 6, GPS IFD pointer.
+        tiff.extend_from_slice(&[2, 0]);
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        tiff.extend_from_slice(&[0x25, 0x88, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]);
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+        // GPS IFD with a latitude reference tag.
+        tiff.extend_from_slice(&[1, 0, 0x01, 0, 2, 0, 2, 0, 0, 0, b'N', 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend_from_slice(b"GPSLatitude 52.5200");
+
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(&tiff);
+
+        let mut data = vec![0xFF, 0xD8];
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&[0xFF, 0xFE, 0x00, 0x07, b'P', b'h', b'o', b'n', b'e']);
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0xC8, 0x01, 0x2C, 0x03]);
+        data.extend_from_slice(&[0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        data
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_jpeg_drops_gps_but_keeps_orientation() {
+        let original = jpeg_with_gps();
+        let stripped = strip_metadata(&original).unwrap().unwrap();
+
+        assert!(contains(&original, b"GPSLatitude"));
+        assert!(!contains(&stripped, b"GPSLatitude"));
+        assert!(!contains(&stripped, b"Phone"));
+        assert_eq!(dimensions(&stripped), Some((300, 200)));
+        assert!(stripped.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]));
+
+        let app1 = stripped.windows(2).position(|pair| pair == [0xFF, 0xE1]).unwrap();
+        assert_eq!(exif_orientation(&stripped[app1 + 4..]), Some(6));
+        assert!(stripped.len() < original.len());
+    }
+
+    #[test]
+    fn test_png_and_webp_chunks() {
+        let mut png_data = crate::utils::image_size::tests::png_header(8, 8);
+        png_data.extend_from_slice(b"\0\0\0\x08tEXtGPS=52.5\0\0\0\0");
+        png_data.extend_from_slice(b"\0\0\0\0IEND\xae\x42\x60\x82");
+        let stripped = strip_metadata(&png_data).unwrap().unwrap();
+        assert!(!contains(&stripped, b"GPS"));
+        assert!(stripped.ends_with(b"IEND\xae\x42\x60\x82"));
+        assert_eq!(dimensions(&stripped), Some((8, 8)));
+
+        let mut webp_data = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\x0c\0\0\0\x3F\x01\x00\xEF\x00\x00".to_vec();
+        webp_data.extend_from_slice(b"EXIF\x05\0\0\0GPS!!\0");
+        webp_data.extend_from_slice(b"VP8L\x02\0\0\0\x2f\0");
+        let stripped = strip_metadata(&webp_data).unwrap().unwrap();
+        assert!(!contains(&stripped, b"GPS"));
+        assert_eq!(stripped[20] & 0x0c, 0);
+        assert_eq!(u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize, stripped.len() - 8);
+        assert_eq!(dimensions(&stripped), Some((320, 240)));
+    }
+
+    #[test]
+    fn test_gif_drops_comments_and_rejects_garbage() {
+        let mut gif_data = b"GIF89a\x02\0\x02\0\0\0\0".to_vec();
+        gif_data.extend_from_slice(b"\x21\xFE\x05Phone\0");
+        gif_data.extend_from_slice(b"\x2C\0\0\0\0\x02\0\x02\0\0\x02\x02\x44\x01\0");
+        gif_data.push(0x3B);
+        let stripped = strip_metadata(&gif_data).unwrap().unwrap();
+        assert!(!contains(&stripped, b"Phone"));
+        assert_eq!(dimensions(&stripped), Some((2, 2)));
+
+        assert_eq!(strip_metadata(b"plain text").unwrap(), None);
+        assert!(strip_metadata(&[0xFF, 0xD8, 0xFF, 0xE1, 0x10]).is_err());
+    }
+}
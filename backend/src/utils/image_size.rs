@@ -0,0 +1,142 @@
+//! Image Dimensions
+//!
+//! Reads width and height from the headers of the image formats uploads
+//! accept (PNG, GIF, JPEG, WebP) without decoding any pixel data.
+
+/// Returns `(width, height)` for a supported image, or `None` when the data
+/// is not a recognised image or its header is truncated.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png(data)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        gif(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg(data)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        webp(data)
+    } else {
+        None
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u32::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u32::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16)
+}
+
+fn png(data: &[u8]) -> Option<(u32, u32)> {
+    // The IHDR chunk always comes first.
+    if data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    Some((be_u32(data, 16)?, be_u32(data, 20)?))
+}
+
+fn gif(data: &[u8]) -> Option<(u32, u32)> {
+    Some((le_u16(data, 6)?, le_u16(data, 8)?))
+}
+
+fn jpeg(data: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        // Skip fill bytes between segments.
+        while *data.get(at)? == 0xFF && *data.get(at + 1)? == 0xFF {
+            at += 1;
+        }
+        if *data.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        match marker {
+            // Start-of-frame markers (excluding DHT, JPG and DAC).
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((be_u16(data, at + 7)?, be_u16(data, at + 5)?));
+            }
+            // Standalone markers without a length.
+            0x01 | 0xD0..=0xD7 => at += 2,
+            0xD9 | 0xDA => return None,
+            _ => at += 2 + be_u16(data, at + 2)? as usize,
+        }
+    }
+}
+
+fn webp(data: &[u8]) -> Option<(u32, u32)> {
+    match data.get(12..16)? {
+        b"VP8X" => Some((le_u24(data, 24)? + 1, le_u24(data, 27)? + 1)),
+        b"VP8 " => Some((le_u16(data, 26)? & 0x3FFF, le_u16(data, 28)? & 0x3FFF)),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Smallest valid PNG header with the given size.
+    pub(crate) fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_png_and_gif() {
+        assert_eq!(dimensions(&png_header(640, 480)), Some((640, 480)));
+        assert_eq!(
+            dimensions(b"GIF89a\x20\x03\x58\x02\x00\x00"),
+            Some((800, 600))
+        );
+    }
+
+    #[test]
+    fn test_jpeg_skips_segments_before_frame() {
+        let mut data = vec![0xFF, 0xD8];
+        // APP0 segment with a 4-byte payload.
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        // SOF0: length, precision, height 200, width 300.
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0xC8, 0x01, 0x2C]);
+        assert_eq!(dimensions(&data), Some((300, 200)));
+    }
+
+    #[test]
+    fn test_webp_variants() {
+        let mut extended = b"RIFF\x00\x00\x00\x00WEBPVP8X".to_vec();
+        extended.extend_from_slice(&[0; 8]);
+        extended.extend_from_slice(&[0x3F, 0x01, 0x00, 0xEF, 0x00, 0x00]);
+        assert_eq!(dimensions(&extended), Some((320, 240)));
+
+        let mut lossless = b"RIFF\x00\x00\x00\x00WEBPVP8L".to_vec();
+        lossless.extend_from_slice(&[0, 0, 0, 0, 0x2F]);
+        let bits: u32 = 99 | (49 << 14);
+        lossless.extend_from_slice(&bits.to_le_bytes());
+        assert_eq!(dimensions(&lossless), Some((100, 50)));
+    }
+
+    #[test]
+    fn test_unknown_or_truncated() {
+        assert_eq!(dimensions(b"hello"), None);
+        assert_eq!(dimensions(&png_header(1, 1)[..18]), None);
+        assert_eq!(dimensions(&[0xFF, 0xD8, 0xFF]), None);
+    }
+}
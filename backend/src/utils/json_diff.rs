@@ -0,0 +1,268 @@
+//! JSON Diff
+//!
+//! Structural comparison of two JSON documents, used to preview site content
+//! changes before they are saved. Objects are compared key by key and arrays
+//! index by index. Every difference is reported with its JSON Pointer path.
+//! Equal subtrees are only counted, and large values are summarized, so the
+//! diff stays small even for big documents.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Values whose serialized form exceeds this size are summarized.
+pub const MAX_INLINE_VALUE_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Stand-in for a value too large to echo back.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueSummary {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Keys of an object or items of an array.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<usize>,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DiffValue {
+    Inline(Value),
+    Summary { summary: ValueSummary },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<DiffValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<DiffValue>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct JsonDiff {
+    pub changes: Vec<JsonChange>,
+    /// Number of leaf values that are identical on both sides.
+    pub unchanged: usize,
+}
+
+impl JsonDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Compares `old` against `new`.
+pub fn diff(old: &Value, new: &Value) -> JsonDiff {
+    let mut result = JsonDiff::default();
+    diff_at(String::new(), old, new, &mut result);
+    result
+}
+
+fn diff_at(path: String, old: &Value, new: &Value, result: &mut JsonDiff) {
+    if old == new {
+        result.unchanged += count_leaves(old);
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = child_path(&path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_at(child, old_value, new_value, result),
+                    None => result.changes.push(removed(child, old_value)),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    result.changes.push(added(child_path(&path, key), new_value));
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for (index, old_value) in old_items.iter().enumerate() {
+                let child = child_path(&path, &index.to_string());
+                match new_items.get(index) {
+                    Some(new_value) => diff_at(child, old_value, new_value, result),
+                    None => result.changes.push(removed(child, old_value)),
+                }
+            }
+            for (index, new_value) in new_items.iter().enumerate().skip(old_items.len()) {
+                result
+                    .changes
+                    .push(added(child_path(&path, &index.to_string()), new_value));
+            }
+        }
+        _ => result.changes.push(JsonChange {
+            path,
+            kind: ChangeKind::Changed,
+            old: Some(display_value(old)),
+            new: Some(display_value(new)),
+        }),
+    }
+}
+
+fn added(path: String, value: &Value) -> JsonChange {
+    JsonChange {
+        path,
+        kind: ChangeKind::Added,
+        old: None,
+        new: Some(display_value(value)),
+    }
+}
+
+fn removed(path: String, value: &Value) -> JsonChange {
+    JsonChange {
+        path,
+        kind: ChangeKind::Removed,
+        old: Some(display_value(value)),
+        new: None,
+    }
+}
+
+/// Appends a JSON Pointer segment, escaping `~` and `/` per RFC 6901.
+fn child_path(parent: &str, segment: &str) -> String {
+    format!("{parent}/{}", segment.replace('~', "~0").replace('/', "~1"))
+}
+
+fn count_leaves(value: &Value) -> usize {
+    match value {
+        Value::Object(map) if !map.is_empty() => map.values().map(count_leaves).sum(),
+        Value::Array(items) if !items.is_empty() => items.iter().map(count_leaves).sum(),
+        _ => 1,
+    }
+}
+
+fn display_value(value: &Value) -> DiffValue {
+    let bytes = serde_json::to_string(value).map(|s| s.len()).unwrap_or(0);
+    if bytes <= MAX_INLINE_VALUE_BYTES {
+        return DiffValue::Inline(value.clone());
+    }
+
+    let (kind, entries) = match value {
+        Value::Object(map) => ("object", Some(map.len())),
+        Value::Array(items) => ("array", Some(items.len())),
+        Value::String(_) => ("string", None),
+        Value::Number(_) => ("number", None),
+        Value::Bool(_) => ("boolean", None),
+        Value::Null => ("null", None),
+    };
+    DiffValue::Summary {
+        summary: ValueSummary {
+            kind,
+            entries,
+            bytes,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(diff: &JsonDiff) -> Vec<(&str, ChangeKind)> {
+        diff.changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_documents_have_no_changes() {
+        let doc = json!({ "a": [1, 2, { "b": "c" }], "d": {} });
+        let result = diff(&doc, &doc);
+        assert!(result.is_empty());
+        assert_eq!(result.unchanged, 4);
+    }
+
+    #[test]
+    fn test_nested_objects() {
+        let old = json!({
+            "brand": { "name": "Alt", "icon": "Terminal" },
+            "bottom": { "copyright": "2024" },
+            "legacy": true
+        });
+        let new = json!({
+            "brand": { "name": "Neu", "icon": "Terminal", "tagline": "Hallo" },
+            "bottom": { "copyright": "2024" }
+        });
+
+        let result = diff(&old, &new);
+        assert_eq!(
+            paths(&result),
+            vec![
+                ("/brand/name", ChangeKind::Changed),
+                ("/brand/tagline", ChangeKind::Added),
+                ("/legacy", ChangeKind::Removed),
+            ]
+        );
+        assert_eq!(result.changes[0].old, Some(DiffValue::Inline(json!("Alt"))));
+        assert_eq!(result.changes[0].new, Some(DiffValue::Inline(json!("Neu"))));
+        assert_eq!(result.changes[2].new, None);
+        assert_eq!(result.unchanged, 2);
+    }
+
+    #[test]
+    fn test_arrays_are_compared_by_index() {
+        let old = json!({ "items": ["a", "b", "c"] });
+        let shorter = json!({ "items": ["a", "x"] });
+        let longer = json!({ "items": ["a", "b", "c", { "id": 4 }] });
+
+        assert_eq!(
+            paths(&diff(&old, &shorter)),
+            vec![
+                ("/items/1", ChangeKind::Changed),
+                ("/items/2", ChangeKind::Removed),
+            ]
+        );
+        let grown = diff(&old, &longer);
+        assert_eq!(paths(&grown), vec![("/items/3", ChangeKind::Added)]);
+        assert_eq!(grown.changes[0].new, Some(DiffValue::Inline(json!({ "id": 4 }))));
+    }
+
+    #[test]
+    fn test_type_changes_replace_the_whole_value() {
+        let result = diff(
+            &json!({ "features": ["a"], "count": 1 }),
+            &json!({ "features": { "a": true }, "count": "1" }),
+        );
+        assert_eq!(
+            paths(&result),
+            vec![
+                ("/count", ChangeKind::Changed),
+                ("/features", ChangeKind::Changed),
+            ]
+        );
+        assert_eq!(result.changes[1].old, Some(DiffValue::Inline(json!(["a"]))));
+
+        let root = diff(&Value::Null, &json!({ "title": "x" }));
+        assert_eq!(paths(&root), vec![("", ChangeKind::Changed)]);
+    }
+
+    #[test]
+    fn test_large_values_are_summarized_and_paths_escaped() {
+        let big: Vec<String> = (0..200).map(|i| format!("item-{i}")).collect();
+        let result = diff(&json!({}), &json!({ "a/b~c": big }));
+
+        assert_eq!(result.changes[0].path, "/a~1b~0c");
+        match &result.changes[0].new {
+            Some(DiffValue::Summary { summary }) => {
+                assert_eq!(summary.kind, "array");
+                assert_eq!(summary.entries, Some(200));
+                assert!(summary.bytes > MAX_INLINE_VALUE_BYTES);
+            }
+            other => panic!("expected summary, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! JSON Schema Subset
+//!
+//! A small validator for the schemas attached to custom content sections. It
+//! understands the structural keywords admins actually need (`type`,
+//! `properties`, `required`, `additionalProperties`, `items`, `enum`, `const`,
+//! length/size/range bounds and `pattern`) and accepts the usual annotations
+//! (`title`, `description`, `default`, `examples`, `$schema`, `$id`,
+//! `$comment`). Anything else is rejected up front by [`check_schema`], so a
+//! schema never silently promises more than it enforces.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+const TYPES: &[&str] = &[
+    "object", "array", "string", "number", "integer", "boolean", "null",
+];
+
+const ANNOTATIONS: &[&str] = &[
+    "$schema", "$id", "$comment", "title", "description", "default", "examples",
+];
+
+/// Upper bound on schema nesting, to keep hostile schemas cheap.
+const MAX_SCHEMA_DEPTH: usize = 32;
+
+/// Checks that `schema` only uses supported keywords with well-formed values.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    check_schema_at(schema, "#", 0)
+}
+
+fn check_schema_at(schema: &Value, location: &str, depth: usize) -> Result<(), String> {
+    if depth > MAX_SCHEMA_DEPTH {
+        return Err(format!("{location}: schema is nested too deeply"));
+    }
+    let obj = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(obj) => obj,
+        _ => return Err(format!("{location}: a schema must be an object or a boolean")),
+    };
+
+    for (keyword, value) in obj {
+        let here = format!("{location}/{keyword}");
+        match keyword.as_str() {
+            "type" => check_type_keyword(value, &here)?,
+            "properties" => {
+                let properties = value
+                    .as_object()
+                    .ok_or_else(|| format!("{here}: must be an object"))?;
+                for (name, property) in properties {
+                    check_schema_at(property, &format!("{here}/{name}"), depth + 1)?;
+                }
+            }
+            "required" => {
+                let names = value
+                    .as_array()
+                    .ok_or_else(|| format!("{here}: must be an array of strings"))?;
+                if !names.iter().all(Value::is_string) {
+                    return Err(format!("{here}: must be an array of strings"));
+                }
+            }
+            "additionalProperties" | "items" => check_schema_at(value, &here, depth + 1)?,
+            "enum" => {
+                if !value.is_array() {
+                    return Err(format!("{here}: must be an array"));
+                }
+            }
+            "minLength" | "maxLength" | "minItems" | "maxItems" => {
+                if value.as_u64().is_none() {
+                    return Err(format!("{here}: must be a non-negative integer"));
+                }
+            }
+            "minimum" | "maximum" => {
+                if !value.is_number() {
+                    return Err(format!("{here}: must be a number"));
+                }
+            }
+            "pattern" => {
+                let pattern = value
+                    .as_str()
+                    .ok_or_else(|| format!("{here}: must be a string"))?;
+                Regex::new(pattern).map_err(|err| format!("{here}: invalid regex: {err}"))?;
+            }
+            "const" => {}
+            other if ANNOTATIONS.contains(&other) => {}
+            other => return Err(format!("{location}: unsupported keyword '{other}'")),
+        }
+    }
+
+    Ok(())
+}
+
+fn check_type_keyword(value: &Value, location: &str) -> Result<(), String> {
+    let names: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    for name in names {
+        match name.as_str() {
+            Some(name) if TYPES.contains(&name) => {}
+            _ => return Err(format!("{location}: unknown type {name}")),
+        }
+    }
+    Ok(())
+}
+
+/// Validates `instance` against a schema that passed [`check_schema`].
+///
+/// Returns the first violation, prefixed with the JSON Pointer of the
+/// offending value (`/` for the document root).
+pub fn validate(schema: &Value, instance: &Value) -> Result<(), String> {
+    validate_at(schema, instance, "").map_err(|(path, message)| {
+        let path = if path.is_empty() { "/".to_string() } else { path };
+        format!("{path}: {message}")
+    })
+}
+
+fn validate_at(schema: &Value, instance: &Value, path: &str) -> Result<(), (String, String)> {
+    let fail = |message: String| Err((path.to_string(), message));
+    let obj = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return fail("no value is allowed here".to_string()),
+        Value::Object(obj) => obj,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = obj.get("type") {
+        if !matches_type(expected, instance) {
+            return fail(format!("expected {}", describe_type(expected)));
+        }
+    }
+    if let Some(Value::Array(options)) = obj.get("enum") {
+        if !options.contains(instance) {
+            return fail("value is not one of the allowed options".to_string());
+        }
+    }
+    if let Some(expected) = obj.get("const") {
+        if expected != instance {
+            return fail(format!("must equal {expected}"));
+        }
+    }
+
+    match instance {
+        Value::String(text) => validate_string(obj, text).or_else(fail)?,
+        Value::Number(number) => {
+            let value = number.as_f64().unwrap_or_default();
+            if let Some(min) = obj.get("minimum").and_then(Value::as_f64) {
+                if value < min {
+                    return fail(format!("must be at least {min}"));
+                }
+            }
+            if let Some(max) = obj.get("maximum").and_then(Value::as_f64) {
+                if value > max {
+                    return fail(format!("must be at most {max}"));
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = obj.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    return fail(format!("must contain at least {min} items"));
+                }
+            }
+            if let Some(max) = obj.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    return fail(format!("must contain at most {max} items"));
+                }
+            }
+            if let Some(item_schema) = obj.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}/{index}"))?;
+                }
+            }
+        }
+        Value::Object(map) => validate_object(obj, map, path)?,
+        Value::Bool(_) | Value::Null => {}
+    }
+
+    Ok(())
+}
+
+fn validate_string(schema: &Map<String, Value>, text: &str) -> Result<(), String> {
+    let length = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            return Err(format!("must be at least {min} characters long"));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            return Err(format!("must be at most {max} characters long"));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        let regex = Regex::new(pattern).map_err(|err| format!("invalid pattern: {err}"))?;
+        if !regex.is_match(text) {
+            return Err(format!("does not match pattern '{pattern}'"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    map: &Map<String, Value>,
+    path: &str,
+) -> Result<(), (String, String)> {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(name) {
+                return Err((
+                    path.to_string(),
+                    format!("missing required property '{name}'"),
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (name, value) in map {
+        let child = format!("{path}/{}", name.replace('~', "~0").replace('/', "~1"));
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property) => validate_at(property, value, &child)?,
+            None => {
+                if let Some(additional) = schema.get("additionalProperties") {
+                    validate_at(additional, value, &child)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn matches_type(expected: &Value, instance: &Value) -> bool {
+    match expected {
+        Value::Array(options) => options.iter().any(|option| matches_type(option, instance)),
+        Value::String(name) => match name.as_str() {
+            "object" => instance.is_object(),
+            "array" => instance.is_array(),
+            "string" => instance.is_string(),
+            "number" => instance.is_number(),
+            "integer" => {
+                instance.is_i64()
+                    || instance.is_u64()
+                    || instance.as_f64().is_some_and(|value| value.fract() == 0.0)
+            }
+            "boolean" => instance.is_boolean(),
+            "null" => instance.is_null(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(options) => options
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        Value::String(name) => name.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn banner_schema() -> Value {
+        json!({
+            "title": "Banner",
+            "type": "object",
+            "required": ["text", "links"],
+            "additionalProperties": false,
+            "properties": {
+                "text": { "type": "string", "minLength": 1, "maxLength": 20 },
+                "level": { "enum": ["info", "warning"] },
+                "priority": { "type": "integer", "minimum": 0, "maximum": 10 },
+                "links": {
+                    "type": "array",
+                    "maxItems": 2,
+                    "items": {
+                        "type": "object",
+                        "required": ["href"],
+                        "properties": { "href": { "type": "string", "pattern": "^https://" } }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_check_schema_rejects_unsupported_keywords() {
+        assert!(check_schema(&banner_schema()).is_ok());
+        assert!(check_schema(&json!(true)).is_ok());
+
+        assert_eq!(
+            check_schema(&json!({ "oneOf": [] })).unwrap_err(),
+            "#: unsupported keyword 'oneOf'"
+        );
+        assert_eq!(
+            check_schema(&json!({ "properties": { "a": { "type": "text" } } })).unwrap_err(),
+            "#/properties/a/type: unknown type \"text\""
+        );
+        assert!(check_schema(&json!({ "pattern": "(" })).is_err());
+        assert!(check_schema(&json!({ "minLength": -1 })).is_err());
+        assert!(check_schema(&json!("object")).is_err());
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_documents() {
+        let schema = banner_schema();
+        let doc = json!({
+            "text": "Wartung",
+            "level": "info",
+            "priority": 3,
+            "links": [{ "href": "https://example.com" }]
+        });
+        assert!(validate(&schema, &doc).is_ok());
+        assert!(validate(&json!({ "type": "integer" }), &json!(4.0)).is_ok());
+        assert!(validate(&json!({ "type": ["string", "null"] }), &Value::Null).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_first_violation_with_path() {
+        let schema = banner_schema();
+        let cases = [
+            (json!([]), "/: expected object"),
+            (json!({ "links": [] }), "/: missing required property 'text'"),
+            (json!({ "text": "", "links": [] }), "/text: must be at least 1 characters long"),
+            (
+                json!({ "text": "x", "links": [], "extra": 1 }),
+                "/extra: no value is allowed here",
+            ),
+            (
+                json!({ "text": "x", "links": [], "level": "error" }),
+                "/level: value is not one of the allowed options",
+            ),
+            (
+                json!({ "text": "x", "links": [], "priority": 1.5 }),
+                "/priority: expected integer",
+            ),
+            (
+                json!({ "text": "x", "links": [], "priority": 11 }),
+                "/priority: must be at most 10",
+            ),
+            (
+                json!({ "text": "x", "links": [{ "href": "https://a" }, {}] }),
+                "/links/1: missing required property 'href'",
+            ),
+            (
+                json!({ "text": "x", "links": [{ "href": "http://a" }] }),
+                "/links/0/href: does not match pattern '^https://'",
+            ),
+            (
+                json!({ "text": "x", "links": [{ "href": "https://a" }, { "href": "https://b" }, { "href": "https://c" }] }),
+                "/links: must contain at most 2 items",
+            ),
+        ];
+
+        for (doc, expected) in cases {
+            assert_eq!(validate(&schema, &doc).unwrap_err(), expected);
+        }
+    }
+}
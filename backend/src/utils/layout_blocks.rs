@@ -0,0 +1,296 @@
+//! Layout Block References
+//!
+//! Page layouts may embed `{ "type": "ref", "block": "<id or name>" }` entries
+//! in `layout.blocks` that point at a shared block from the `layout_blocks`
+//! library. This module expands those references. Admin writes resolve in
+//! strict mode so broken references are rejected with a clear message, while
+//! public responses resolve leniently and drop anything that cannot be
+//! expanded instead of failing the whole page.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Block type used for library references inside `layout.blocks`.
+pub const REF_BLOCK_TYPE: &str = "ref";
+
+/// Upper bound on ref-to-ref chains, as a safety net on top of cycle detection.
+const MAX_REF_DEPTH: usize = 8;
+
+/// Why a block reference could not be expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefError {
+    Missing { pointer: String, reference: String },
+    Cycle { pointer: String, chain: Vec<String> },
+    TooDeep { pointer: String },
+}
+
+impl fmt::Display for RefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefError::Missing { pointer, reference } => {
+                write!(f, "{pointer}: references unknown block '{reference}'")
+            }
+            RefError::Cycle { pointer, chain } => {
+                write!(f, "{pointer}: block reference cycle {}", chain.join(" -> "))
+            }
+            RefError::TooDeep { pointer } => write!(
+                f,
+                "{pointer}: block references are nested deeper than {MAX_REF_DEPTH} levels"
+            ),
+        }
+    }
+}
+
+/// Library blocks addressable by id or by name.
+#[derive(Debug, Default)]
+pub struct BlockLibrary {
+    /// Blocks keyed by id.
+    by_id: HashMap<String, Value>,
+    /// Maps names to ids. Kept apart from `by_id` so a name can never shadow
+    /// another block's id.
+    by_name: HashMap<String, String>,
+}
+
+impl BlockLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, id: &str, name: &str, block: Value) {
+        self.by_name.insert(name.to_string(), id.to_string());
+        self.by_id.insert(id.to_string(), block);
+    }
+
+    /// Looks `reference` up as an id first, then as a name.
+    fn lookup(&self, reference: &str) -> Option<(&str, &Value)> {
+        let id = if self.by_id.contains_key(reference) {
+            reference
+        } else {
+            self.by_name.get(reference)?.as_str()
+        };
+        self.by_id.get_key_value(id).map(|(id, block)| (id.as_str(), block))
+    }
+}
+
+/// Returns the target of a `ref` block, or `None` for ordinary blocks.
+pub fn ref_target(block: &Value) -> Option<&str> {
+    if block.get("type").and_then(Value::as_str) != Some(REF_BLOCK_TYPE) {
+        return None;
+    }
+    block.get("block").and_then(Value::as_str)
+}
+
+/// Whether `layout.blocks` contains a direct reference to any of `keys`.
+pub fn layout_references(layout: &Value, keys: &[&str]) -> bool {
+    layout
+        .get("blocks")
+        .and_then(Value::as_array)
+        .is_some_and(|blocks| {
+            blocks
+                .iter()
+                .filter_map(ref_target)
+                .any(|target| keys.contains(&target))
+        })
+}
+
+/// Expands a single block, following ref chains until a concrete block is found.
+pub fn resolve_block(
+    block: &Value,
+    library: &BlockLibrary,
+    pointer: &str,
+) -> Result<Value, RefError> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut current = block;
+
+    while let Some(reference) = ref_target(current) {
+        if chain.len() >= MAX_REF_DEPTH {
+            return Err(RefError::TooDeep {
+                pointer: pointer.to_string(),
+            });
+        }
+
+        let (id, target) = library.lookup(reference).ok_or_else(|| RefError::Missing {
+            pointer: pointer.to_string(),
+            reference: reference.to_string(),
+        })?;
+
+        if chain.iter().any(|seen| seen == id) {
+            chain.push(id.to_string());
+            return Err(RefError::Cycle {
+                pointer: pointer.to_string(),
+                chain,
+            });
+        }
+
+        chain.push(id.to_string());
+        current = target;
+    }
+
+    Ok(current.clone())
+}
+
+/// Expands every ref in `layout.blocks`, failing on the first broken reference.
+pub fn resolve_layout_strict(layout: &Value, library: &BlockLibrary) -> Result<Value, RefError> {
+    resolve_layout_with(layout, |block, pointer| {
+        resolve_block(block, library, pointer).map(Some)
+    })
+}
+
+/// Expands every ref in `layout.blocks`, dropping references that cannot be
+/// resolved so a broken library entry never takes a public page down.
+pub fn resolve_layout_lenient(layout: &Value, library: &BlockLibrary) -> Value {
+    resolve_layout_with(layout, |block, pointer| {
+        Ok(match resolve_block(block, library, pointer) {
+            Ok(resolved) => Some(resolved),
+            Err(err) => {
+                tracing::warn!("Skipping layout block: {}", err);
+                None
+            }
+        })
+    })
+    .unwrap_or_else(|_| layout.clone())
+}
+
+fn resolve_layout_with<F>(layout: &Value, mut resolve: F) -> Result<Value, RefError>
+where
+    F: FnMut(&Value, &str) -> Result<Option<Value>, RefError>,
+{
+    let Some(blocks) = layout.get("blocks").and_then(Value::as_array) else {
+        return Ok(layout.clone());
+    };
+
+    let mut resolved = Vec::with_capacity(blocks.len());
+    for (index, block) in blocks.iter().enumerate() {
+        if let Some(block) = resolve(block, &format!("/blocks/{index}"))? {
+            resolved.push(block);
+        }
+    }
+
+    let mut layout = layout.clone();
+    layout["blocks"] = Value::Array(resolved);
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn library() -> BlockLibrary {
+        let mut library = BlockLibrary::new();
+        library.insert(
+            "id-hint",
+            "shell-hint",
+            json!({ "type": "infobox", "title": "Tipp", "content": "Nutze Tab" }),
+        );
+        library.insert(
+            "id-alias",
+            "hint-alias",
+            json!({ "type": "ref", "block": "shell-hint" }),
+        );
+        library.insert(
+            "id-a",
+            "loop-a",
+            json!({ "type": "ref", "block": "loop-b" }),
+        );
+        library.insert("id-b", "loop-b", json!({ "type": "ref", "block": "id-a" }));
+        library
+    }
+
+    #[test]
+    fn test_resolves_refs_by_name_id_and_through_aliases() {
+        let layout = json!({
+            "aboutSection": { "title": "Über" },
+            "blocks": [
+                { "type": "text", "content": "Intro" },
+                { "type": "ref", "block": "shell-hint" },
+                { "type": "ref", "block": "id-hint" },
+                { "type": "ref", "block": "hint-alias" }
+            ]
+        });
+
+        let resolved = resolve_layout_strict(&layout, &library()).expect("resolve");
+        let blocks = resolved["blocks"].as_array().expect("blocks");
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0]["type"], "text");
+        for block in &blocks[1..] {
+            assert_eq!(block["type"], "infobox");
+            assert_eq!(block["title"], "Tipp");
+        }
+        assert_eq!(resolved["aboutSection"]["title"], "Über");
+    }
+
+    #[test]
+    fn test_missing_ref_fails_strict_and_is_skipped_publicly() {
+        let layout = json!({
+            "blocks": [
+                { "type": "ref", "block": "gone" },
+                { "type": "text", "content": "Bleibt" }
+            ]
+        });
+
+        let err = resolve_layout_strict(&layout, &library()).unwrap_err();
+        assert_eq!(
+            err,
+            RefError::Missing {
+                pointer: "/blocks/0".to_string(),
+                reference: "gone".to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "/blocks/0: references unknown block 'gone'"
+        );
+
+        let public = resolve_layout_lenient(&layout, &library());
+        assert_eq!(
+            public["blocks"],
+            json!([{ "type": "text", "content": "Bleibt" }])
+        );
+    }
+
+    #[test]
+    fn test_names_never_shadow_ids() {
+        let mut library = library();
+        library.insert("id-impostor", "id-hint", json!({ "type": "text", "content": "Falsch" }));
+
+        let layout = json!({ "blocks": [{ "type": "ref", "block": "id-hint" }] });
+        let resolved = resolve_layout_strict(&layout, &library).expect("resolve");
+        assert_eq!(resolved["blocks"][0]["title"], "Tipp");
+    }
+
+    #[test]
+    fn test_cycles_are_detected() {
+        let layout = json!({ "blocks": [{ "type": "ref", "block": "loop-a" }] });
+
+        match resolve_layout_strict(&layout, &library()) {
+            Err(RefError::Cycle { pointer, chain }) => {
+                assert_eq!(pointer, "/blocks/0");
+                assert_eq!(chain, vec!["id-a", "id-b", "id-a"]);
+            }
+            other => panic!("expected cycle, got {other:?}"),
+        }
+
+        let public = resolve_layout_lenient(&layout, &library());
+        assert_eq!(public["blocks"], json!([]));
+    }
+
+    #[test]
+    fn test_layouts_without_blocks_are_untouched() {
+        assert_eq!(
+            resolve_layout_lenient(&Value::Null, &library()),
+            Value::Null
+        );
+        let layout = json!({ "postsSection": { "title": "Beiträge" } });
+        assert_eq!(resolve_layout_strict(&layout, &library()).unwrap(), layout);
+    }
+
+    #[test]
+    fn test_layout_references() {
+        let layout = json!({ "blocks": [{ "type": "ref", "block": "shell-hint" }] });
+        assert!(layout_references(&layout, &["id-hint", "shell-hint"]));
+        assert!(!layout_references(&layout, &["other"]));
+        assert!(!layout_references(&json!({}), &["shell-hint"]));
+    }
+}
@@ -0,0 +1,168 @@
+//! Markdown Text Helpers
+//!
+//! Lightweight helpers for turning post markdown into plain text, e.g. for
+//! excerpts and meta descriptions. This is intentionally not a full CommonMark
+//! parser: it removes the syntax that would otherwise leak into listings
+//! (fences, images, link targets, emphasis markers, headings, inline HTML).
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Maximum length (in characters) of an automatically derived excerpt.
+pub const AUTO_EXCERPT_MAX_CHARS: usize = 200;
+
+const ELLIPSIS: char = '…';
+
+fn image_regex() -> &'static Regex {
+    static IMAGE_RE: OnceLock<Regex> = OnceLock::new();
+    IMAGE_RE.get_or_init(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").expect("valid image regex"))
+}
+
+fn link_regex() -> &'static Regex {
+    static LINK_RE: OnceLock<Regex> = OnceLock::new();
+    LINK_RE.get_or_init(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").expect("valid link regex"))
+}
+
+fn html_tag_regex() -> &'static Regex {
+    static HTML_RE: OnceLock<Regex> = OnceLock::new();
+    HTML_RE.get_or_init(|| Regex::new(r"</?[A-Za-z][^>]*>").expect("valid html tag regex"))
+}
+
+fn list_marker_regex() -> &'static Regex {
+    static LIST_RE: OnceLock<Regex> = OnceLock::new();
+    LIST_RE.get_or_init(|| Regex::new(r"^(?:[-*+]|[0-9]+[.)])[ \t]+").expect("valid list regex"))
+}
+
+fn is_fence(line: &str) -> bool {
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+fn is_horizontal_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && (compact.chars().all(|c| c == '-')
+            || compact.chars().all(|c| c == '*')
+            || compact.chars().all(|c| c == '_'))
+}
+
+/// Converts markdown into a single line of plain text.
+///
+/// Fenced code blocks and images are dropped entirely, links keep only their
+/// label, and block/inline markers are removed. Whitespace is collapsed.
+pub fn strip_markdown(markdown: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut in_fence = false;
+
+    for raw_line in markdown.lines() {
+        let line = raw_line.trim();
+
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || line.is_empty() || is_horizontal_rule(line) {
+            continue;
+        }
+
+        let line = line.trim_start_matches('>').trim_start();
+        let line = line.trim_start_matches('#').trim_start();
+        let line = list_marker_regex().replace(line, "");
+
+        let line = image_regex().replace_all(&line, "");
+        let line = link_regex().replace_all(&line, "$1");
+        let line = html_tag_regex().replace_all(&line, "");
+
+        let cleaned = line
+            .replace("**", "")
+            .replace("__", "")
+            .replace("~~", "")
+            .replace(['*', '`'], "");
+
+        let cleaned = cleaned.trim();
+        if !cleaned.is_empty() {
+            parts.push(cleaned.to_string());
+        }
+    }
+
+    parts
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Truncates plain text to at most `max_chars` characters, cutting at the
+/// nearest word boundary and appending an ellipsis when shortened.
+pub fn truncate_at_word_boundary(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    // Reserve one character for the ellipsis.
+    let budget: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = match budget.rfind(char::is_whitespace) {
+        Some(idx) if idx > 0 => &budget[..idx],
+        _ => budget.as_str(),
+    };
+
+    let trimmed = cut.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '.' | '-'));
+    format!("{trimmed}{ELLIPSIS}")
+}
+
+/// Derives a post excerpt from markdown content.
+pub fn derive_excerpt(markdown: &str) -> String {
+    truncate_at_word_boundary(&strip_markdown(markdown), AUTO_EXCERPT_MAX_CHARS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_markdown_removes_code_fences() {
+        let md = "Intro text\n\n```bash\nls -la\nrm -rf /tmp/x\n```\n\nAfter the block";
+        assert_eq!(strip_markdown(md), "Intro text After the block");
+
+        let tilde = "Before\n~~~\ncode\n~~~\nAfter";
+        assert_eq!(strip_markdown(tilde), "Before After");
+    }
+
+    #[test]
+    fn test_strip_markdown_removes_images_and_keeps_link_labels() {
+        let md = "See ![Screenshot](/uploads/a.png) the [manual page](https://man7.org/ls) for details.";
+        assert_eq!(strip_markdown(md), "See the manual page for details.");
+    }
+
+    #[test]
+    fn test_strip_markdown_removes_block_and_inline_markers() {
+        let md = "# Heading\n\n> Quoted **bold** and `code`\n\n- first item\n2. second _item_\n\n---\n<br/>Done";
+        assert_eq!(
+            strip_markdown(md),
+            "Heading Quoted bold and code first item second _item_ Done"
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_word_boundary() {
+        assert_eq!(truncate_at_word_boundary("short text", 200), "short text");
+
+        let long = "word ".repeat(100);
+        let truncated = truncate_at_word_boundary(long.trim(), 200);
+        assert!(truncated.chars().count() <= 200);
+        assert!(truncated.ends_with("word…"));
+    }
+
+    #[test]
+    fn test_truncate_handles_multibyte_characters() {
+        let long = "Größenänderung ".repeat(30);
+        let truncated = truncate_at_word_boundary(long.trim(), 50);
+        assert!(truncated.chars().count() <= 50);
+        assert!(truncated.ends_with('…'));
+    }
+
+    #[test]
+    fn test_derive_excerpt_from_markdown() {
+        assert_eq!(derive_excerpt("## Title\n\nBody"), "Title Body");
+        assert_eq!(derive_excerpt(""), "");
+    }
+}
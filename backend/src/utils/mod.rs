@@ -0,0 +1,14 @@
+pub mod conditional;
+pub mod content_refs;
+pub mod image_metadata;
+pub mod image_size;
+pub mod json_diff;
+pub mod json_schema;
+pub mod layout_blocks;
+pub mod markdown;
+pub mod png_decode;
+pub mod public_url;
+pub mod svg_sanitize;
+pub mod textstats;
+pub mod timestamps;
+pub mod webp_encode;
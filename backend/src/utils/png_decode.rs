@@ -0,0 +1,569 @@
+//! PNG Decoding
+//!
+//! Decodes non-interlaced PNG files into ARGB pixels for re-encoding, with a
+//! small zlib inflater of its own. Interlaced images are reported as
+//! unsupported; callers keep the original file for those.
+
+/// Decoded pixels, one `0xAARRGGBB` value per pixel, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn malformed(what: &str) -> String {
+    format!("Malformed PNG: {what}")
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes `data`. Returns `Ok(None)` for valid PNGs we do not decode
+/// (interlaced ones).
+pub fn decode(data: &[u8]) -> Result<Option<Image>, String> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(malformed("missing signature"));
+    }
+
+    let mut header = None;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut transparent: Option<[u16; 3]> = None;
+    let mut compressed = Vec::new();
+    let mut at = PNG_SIGNATURE.len();
+
+    while at < data.len() {
+        let len = be_u32(data, at).ok_or_else(|| malformed("truncated chunk"))? as usize;
+        let kind = data.get(at + 4..at + 8).ok_or_else(|| malformed("truncated chunk"))?;
+        let body = data
+            .get(at + 8..at + 8 + len)
+            .ok_or_else(|| malformed("truncated chunk"))?;
+
+        match kind {
+            b"IHDR" => {
+                if body.len() != 13 {
+                    return Err(malformed("bad IHDR"));
+                }
+                header = Some(Header {
+                    width: be_u32(body, 0).unwrap_or(0),
+                    height: be_u32(body, 4).unwrap_or(0),
+                    bit_depth: body[8],
+                    color_type: body[9],
+                    interlaced: body[12] != 0,
+                });
+            }
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+                    .collect();
+            }
+            b"tRNS" => {
+                for (entry, alpha) in palette.iter_mut().zip(body) {
+                    entry[3] = *alpha;
+                }
+                let sample = |i: usize| {
+                    body.get(i * 2..i * 2 + 2)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                };
+                transparent = match body.len() {
+                    2 => sample(0).map(|gray| [gray; 3]),
+                    6 => Some([sample(0).unwrap_or(0), sample(1).unwrap_or(0), sample(2).unwrap_or(0)]),
+                    _ => None,
+                };
+            }
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + len;
+    }
+
+    let header = header.ok_or_else(|| malformed("missing IHDR"))?;
+    if header.interlaced {
+        return Ok(None);
+    }
+    let channels = header.channels()?;
+    if header.width == 0 || header.height == 0 {
+        return Err(malformed("empty image"));
+    }
+
+    let bits_per_pixel = channels * usize::from(header.bit_depth);
+    let stride = (header.width as usize * bits_per_pixel).div_ceil(8);
+    let expected = (stride + 1) * header.height as usize;
+    let raw = inflate_zlib(&compressed, expected)?;
+    if raw.len() < expected {
+        return Err(malformed("image data too short"));
+    }
+
+    let rows = unfilter(&raw, stride, header.height as usize, bits_per_pixel.div_ceil(8))?;
+    let pixels = to_argb(&header, &rows, stride, &palette, transparent)?;
+    Ok(Some(Image {
+        width: header.width,
+        height: header.height,
+        pixels,
+    }))
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> Result<usize, String> {
+        let channels = match self.color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => return Err(malformed("unknown color type")),
+        };
+        let depth_ok = match self.color_type {
+            0 => matches!(self.bit_depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(self.bit_depth, 1 | 2 | 4 | 8),
+            _ => matches!(self.bit_depth, 8 | 16),
+        };
+        if depth_ok {
+            Ok(channels)
+        } else {
+            Err(malformed("unsupported bit depth"))
+        }
+    }
+}
+
+fn unfilter(raw: &[u8], stride: usize, height: usize, bpp: usize) -> Result<Vec<u8>, String> {
+    let mut out = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, rest) = out.split_at_mut(y * stride);
+        let prior = if y == 0 { None } else { Some(&done[(y - 1) * stride..]) };
+        let current = &mut rest[..stride];
+
+        for x in 0..stride {
+            let left = if x >= bpp { current[x - bpp] } else { 0 };
+            let up = prior.map_or(0, |prior| prior[x]);
+            let up_left = match prior {
+                Some(prior) if x >= bpp => prior[x - bpp],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(malformed("unknown filter type")),
+            };
+            current[x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn to_argb(
+    header: &Header,
+    rows: &[u8],
+    stride: usize,
+    palette: &[[u8; 4]],
+    transparent: Option<[u16; 3]>,
+) -> Result<Vec<u32>, String> {
+    let (width, height) = (header.width as usize, header.height as usize);
+    let depth = usize::from(header.bit_depth);
+    let argb = |a: u8, r: u8, g: u8, b: u8| {
+        u32::from(a) << 24 | u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b)
+    };
+    let mut pixels = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        let row = &rows[y * stride..(y + 1) * stride];
+        // Sample `i` of the row at the image's bit depth
+        let sample = |i: usize| -> u16 {
+            match depth {
+                16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+                8 => u16::from(row[i]),
+                _ => {
+                    let bit = i * depth;
+                    let shift = 8 - depth - bit % 8;
+                    u16::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+                }
+            }
+        };
+        // Scales a sample to 8 bits
+        let scale = |value: u16| -> u8 {
+            match depth {
+                16 => (value >> 8) as u8,
+                8 => value as u8,
+                _ => (u32::from(value) * 255 / ((1 << depth) - 1)) as u8,
+            }
+        };
+
+        for x in 0..width {
+            let pixel = match header.color_type {
+                0 => {
+                    let gray = sample(x);
+                    let alpha = if transparent.is_some_and(|t| t[0] == gray) { 0 } else { 0xFF };
+                    let gray = scale(gray);
+                    argb(alpha, gray, gray, gray)
+                }
+                2 => {
+                    let (r, g, b) = (sample(x * 3), sample(x * 3 + 1), sample(x * 3 + 2));
+                    let alpha = if transparent == Some([r, g, b]) { 0 } else { 0xFF };
+                    argb(alpha, scale(r), scale(g), scale(b))
+                }
+                3 => {
+                    let [r, g, b, a] = *palette
+                        .get(usize::from(sample(x)))
+                        .ok_or_else(|| malformed("palette index out of range"))?;
+                    argb(a, r, g, b)
+                }
+                4 => {
+                    let gray = scale(sample(x * 2));
+                    argb(scale(sample(x * 2 + 1)), gray, gray, gray)
+                }
+                _ => argb(
+                    scale(sample(x * 4 + 3)),
+                    scale(sample(x * 4)),
+                    scale(sample(x * 4 + 1)),
+                    scale(sample(x * 4 + 2)),
+                ),
+            };
+            pixels.push(pixel);
+        }
+    }
+    Ok(pixels)
+}
+
+/// Reads bits least significant first, as deflate streams are packed.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| malformed("truncated deflate stream"))?;
+            value |= u32::from((byte >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Canonical Huffman decoding table: symbol counts per length and the
+/// symbols sorted by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.iter().filter(|&&len| len > 0).count()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len > 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(malformed("bad Huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Inflates a zlib stream, stopping once `limit` bytes were produced so a
+/// crafted stream cannot expand without bound.
+fn inflate_zlib(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    if data.len() < 2 || data[0] & 0x0F != 8 || (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 != 0 {
+        return Err(malformed("bad zlib header"));
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        pos: 0,
+        bit: 0,
+    };
+    let mut out = Vec::with_capacity(limit);
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let at = reader.pos;
+                let header = reader
+                    .data
+                    .get(at..at + 4)
+                    .ok_or_else(|| malformed("truncated stored block"))?;
+                let len = usize::from(u16::from_le_bytes([header[0], header[1]]));
+                let block = reader
+                    .data
+                    .get(at + 4..at + 4 + len)
+                    .ok_or_else(|| malformed("truncated stored block"))?;
+                out.extend_from_slice(block);
+                reader.pos = at + 4 + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &literals, &distances, &mut out, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, limit)?;
+            }
+            _ => return Err(malformed("bad deflate block type")),
+        }
+        if last || out.len() >= limit {
+            break;
+        }
+    }
+
+    out.truncate(limit);
+    Ok(out)
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    const ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &ORDER[..code_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or_else(|| malformed("repeat without length"))?,
+                3 + reader.bits(2)?,
+            ),
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err(malformed("code lengths overflow"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    out: &mut Vec<u8>,
+    limit: usize,
+) -> Result<(), String> {
+    loop {
+        let symbol = usize::from(literals.decode(reader)?);
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                let (base, extra) = LENGTH_BASE
+                    .get(index)
+                    .zip(LENGTH_EXTRA.get(index))
+                    .ok_or_else(|| malformed("bad length code"))?;
+                let len = usize::from(*base) + reader.bits(u32::from(*extra))? as usize;
+
+                let index = usize::from(distances.decode(reader)?);
+                let (base, extra) = DIST_BASE
+                    .get(index)
+                    .zip(DIST_EXTRA.get(index))
+                    .ok_or_else(|| malformed("bad distance code"))?;
+                let distance = usize::from(*base) + reader.bits(u32::from(*extra))? as usize;
+                if distance > out.len() {
+                    return Err(malformed("distance too far back"));
+                }
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+        if out.len() >= limit {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for &byte in data {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    /// Wraps raw scanlines into a PNG using stored deflate blocks.
+    pub(crate) fn png_from_scanlines(width: u32, height: u32, color_type: u8, bit_depth: u8, raw: &[u8]) -> Vec<u8> {
+        let chunk = |out: &mut Vec<u8>, kind: &[u8], body: &[u8]| {
+            out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            let mut crc_input = kind.to_vec();
+            crc_input.extend_from_slice(body);
+            out.extend_from_slice(&crc_input);
+            out.extend_from_slice(&crc32(&crc_input).to_be_bytes());
+        };
+
+        let mut zlib = vec![0x78, 0x01];
+        let blocks: Vec<&[u8]> = raw.chunks(65_535).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            zlib.push(u8::from(i + 1 == blocks.len()));
+            zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&[0, 0, 0, 1]); // Adler-32 is not checked
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+        let mut out = PNG_SIGNATURE.to_vec();
+        chunk(&mut out, b"IHDR", &ihdr);
+        chunk(&mut out, b"IDAT", &zlib);
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    /// A 2x2 RGBA image: red, green / blue, transparent.
+    const COMPRESSED_RGBA: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+        0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00,
+        0x00, 0x72, 0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x13, 0x49, 0x44, 0x41, 0x54, 0x78,
+        0xda, 0x63, 0xf8, 0xcf, 0xc0, 0xf0, 0x1f, 0x0c, 0x81, 0x34, 0x88, 0x60, 0x00, 0x00,
+        0x3f, 0xd2, 0x05, 0xfb, 0x7f, 0xe6, 0x6a, 0x2b, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45,
+        0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_decodes_compressed_rgba() {
+        let image = decode(COMPRESSED_RGBA).unwrap().unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, vec![0xFFFF0000, 0xFF00FF00, 0xFF0000FF, 0x00000000]);
+    }
+
+    #[test]
+    fn test_decodes_filters_palette_and_gray() {
+        // RGB with Sub and Up filters
+        let raw = [1, 10, 20, 30, 5, 5, 5, 2, 1, 1, 1, 0, 0, 0];
+        let image = decode(&png_from_scanlines(2, 2, 2, 8, &raw)).unwrap().unwrap();
+        assert_eq!(image.pixels, vec![0xFF0A141E, 0xFF0F1923, 0xFF0B151F, 0xFF0F1923]);
+
+        // 2-bit grayscale
+        let raw = [0, 0b0001_1011];
+        let image = decode(&png_from_scanlines(4, 1, 0, 2, &raw)).unwrap().unwrap();
+        assert_eq!(image.pixels, vec![0xFF000000, 0xFF555555, 0xFFAAAAAA, 0xFFFFFFFF]);
+
+        assert!(decode(b"not a png").is_err());
+        let mut truncated = png_from_scanlines(2, 2, 2, 8, &raw);
+        truncated.truncate(40);
+        assert!(decode(&truncated).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Public Base URL
+//!
+//! The address visitors reach the site at, from `PUBLIC_BASE_URL` (e.g.
+//! `https://linux-tutorial.example`). Absolute links the backend writes for
+//! crawlers, like canonical URLs, are built from it; without it they are left
+//! out, since the `Host` header of a request behind a proxy cannot be trusted.
+
+use std::sync::OnceLock;
+use url::Url;
+
+static PUBLIC_BASE_URL: OnceLock<Option<String>> = OnceLock::new();
+
+/// Validates `value` and drops the trailing slash, so paths can be appended.
+fn parse_base_url(value: Option<String>) -> Result<Option<String>, String> {
+    let Some(raw) = value.map(|raw| raw.trim().to_string()) else {
+        return Ok(None);
+    };
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let invalid = |reason: &str| format!("PUBLIC_BASE_URL {}, got '{}'", reason, raw);
+    let url = Url::parse(&raw).map_err(|_| invalid("must be an absolute URL"))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(invalid("must be an http or https URL"));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("must not have a query or fragment"));
+    }
+    Ok(Some(url.as_str().trim_end_matches('/').to_string()))
+}
+
+pub fn init_public_base_url() -> Result<(), String> {
+    PUBLIC_BASE_URL
+        .set(parse_base_url(std::env::var("PUBLIC_BASE_URL").ok())?)
+        .map_err(|_| "Public base URL already initialized".to_string())
+}
+
+/// The configured base URL without a trailing slash, if any.
+pub fn public_base_url() -> Option<&'static str> {
+    PUBLIC_BASE_URL
+        .get_or_init(|| {
+            parse_base_url(std::env::var("PUBLIC_BASE_URL").ok()).unwrap_or_else(|err| {
+                tracing::error!("{}; leaving out absolute URLs", err);
+                None
+            })
+        })
+        .as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Result<Option<String>, String> {
+        parse_base_url(Some(value.to_string()))
+    }
+
+    #[test]
+    fn test_base_url_is_normalized() {
+        assert_eq!(parse_base_url(None), Ok(None));
+        assert_eq!(parse("  "), Ok(None));
+        assert_eq!(
+            parse(" https://Linux.Example/ "),
+            Ok(Some("https://linux.example".to_string()))
+        );
+        assert_eq!(
+            parse("http://example.com:8080/cms/"),
+            Ok(Some("http://example.com:8080/cms".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_base_urls_are_rejected() {
+        for value in [
+            "linux.example",
+            "/cms",
+            "ftp://linux.example",
+            "https://linux.example/?lang=de",
+            "https://linux.example/#top",
+        ] {
+            assert!(parse(value).is_err(), "{value}");
+        }
+    }
+}
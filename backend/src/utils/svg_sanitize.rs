@@ -0,0 +1,455 @@
+//! SVG Sanitizer
+//!
+//! Uploaded SVGs are parsed with a small, strict XML tokenizer and rebuilt
+//! from an allowlist of drawing elements and attributes. Anything that can
+//! run script or fetch external resources is dropped: `<script>`,
+//! `<foreignObject>`, animation elements, event handlers, `javascript:` and
+//! external links, stylesheet processing instructions and DTDs. Entity
+//! references other than the XML predefined ones are rejected, so internal
+//! DTD subsets cannot be used for entity expansion or XXE.
+
+/// Drawing elements kept in sanitized output. Everything else is removed
+/// together with its content.
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "svg", "g", "defs", "symbol", "use", "title", "desc", "switch", "view",
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon",
+    "text", "tspan", "textPath", "marker", "pattern", "clipPath", "mask",
+    "linearGradient", "radialGradient", "stop", "image", "style", "a",
+    "filter", "feBlend", "feColorMatrix", "feComponentTransfer", "feComposite",
+    "feDropShadow", "feFlood", "feFuncA", "feFuncB", "feFuncG", "feFuncR",
+    "feGaussianBlur", "feMerge", "feMergeNode", "feMorphology", "feOffset",
+];
+
+/// Prefixed attributes that are part of plain SVG.
+const ALLOWED_PREFIXED_ATTRIBUTES: &[&str] = &["xlink:href", "xml:space", "xml:lang", "xmlns:xlink"];
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Raster images that may be embedded in `<image>` as data URLs.
+const EMBEDDED_IMAGE_PREFIXES: &[&str] = &[
+    "data:image/png;base64,",
+    "data:image/jpeg;base64,",
+    "data:image/gif;base64,",
+    "data:image/webp;base64,",
+];
+
+const MAX_DEPTH: usize = 256;
+
+enum Token<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        empty: bool,
+    },
+    End(&'a str),
+    Text(String),
+    /// Comments, processing instructions and the doctype.
+    Skipped,
+}
+
+struct Tokenizer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_past(&mut self, terminator: &str, what: &str) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let end = rest
+            .find(terminator)
+            .ok_or_else(|| format!("Unterminated {what}"))?;
+        self.pos += end + terminator.len();
+        Ok(&rest[..end])
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        let name = &rest[..len];
+        let valid = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '-' | '.'));
+        if name.is_empty() || !valid {
+            return Err("Invalid element or attribute name".to_string());
+        }
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, String> {
+        let rest = self.rest();
+        if rest.is_empty() {
+            return Ok(None);
+        }
+
+        if rest.starts_with("<!--") {
+            self.pos += 4;
+            self.skip_past("-->", "comment")?;
+            return Ok(Some(Token::Skipped));
+        }
+        if rest.starts_with("<![CDATA[") {
+            self.pos += 9;
+            let text = self.skip_past("]]>", "CDATA section")?;
+            return Ok(Some(Token::Text(text.to_string())));
+        }
+        if rest.starts_with("<!DOCTYPE") {
+            // Skip an internal subset in brackets, then the closing `>`.
+            let bracket = rest.find('[');
+            let close = rest.find('>').ok_or("Unterminated doctype")?;
+            if bracket.is_some_and(|bracket| bracket < close) {
+                self.skip_past("]", "doctype")?;
+            }
+            self.skip_past(">", "doctype")?;
+            return Ok(Some(Token::Skipped));
+        }
+        if rest.starts_with("<?") {
+            self.skip_past("?>", "processing instruction")?;
+            return Ok(Some(Token::Skipped));
+        }
+        if rest.starts_with("</") {
+            self.pos += 2;
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('>') {
+                return Err("Malformed closing tag".to_string());
+            }
+            self.pos += 1;
+            return Ok(Some(Token::End(name)));
+        }
+        if rest.starts_with('<') {
+            self.pos += 1;
+            let name = self.name()?;
+            let mut attributes: Vec<(&str, String)> = Vec::new();
+            loop {
+                self.skip_whitespace();
+                let rest = self.rest();
+                if rest.starts_with("/>") {
+                    self.pos += 2;
+                    return Ok(Some(Token::Start { name, attributes, empty: true }));
+                }
+                if rest.starts_with('>') {
+                    self.pos += 1;
+                    return Ok(Some(Token::Start { name, attributes, empty: false }));
+                }
+
+                let attribute = self.name()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with('=') {
+                    return Err(format!("Attribute '{attribute}' has no value"));
+                }
+                self.pos += 1;
+                self.skip_whitespace();
+                let quote = match self.rest().chars().next() {
+                    Some(quote @ ('"' | '\'')) => quote,
+                    _ => return Err(format!("Attribute '{attribute}' is not quoted")),
+                };
+                self.pos += 1;
+                let raw = self.skip_past(&quote.to_string(), "attribute value")?;
+                if raw.contains('<') {
+                    return Err(format!("Attribute '{attribute}' contains '<'"));
+                }
+                if attributes.iter().any(|(existing, _)| *existing == attribute) {
+                    return Err(format!("Duplicate attribute '{attribute}'"));
+                }
+                attributes.push((attribute, decode_entities(raw)?));
+            }
+        }
+
+        let len = rest.find('<').unwrap_or(rest.len());
+        self.pos += len;
+        Ok(Some(Token::Text(decode_entities(&rest[..len])?)))
+    }
+}
+
+/// Resolves the predefined XML entities and character references. Any other
+/// entity would need a DTD, which is never honoured.
+fn decode_entities(raw: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .ok_or("Unterminated entity reference")?;
+        let entity = &rest[start + 1..start + end];
+        let decoded = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    return Err(format!("Undefined entity '&{entity};'"));
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| format!("Invalid character reference '&{entity};'"))?
+            }
+        };
+        out.push(decoded);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Lowercased value without whitespace and control characters, the way
+/// browsers read URL schemes.
+fn normalized(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn has_script_scheme(value: &str) -> bool {
+    let value = normalized(value);
+    ["javascript:", "vbscript:", "data:text/html", "data:application"]
+        .iter()
+        .any(|scheme| value.contains(scheme))
+}
+
+/// CSS that imports stylesheets, uses legacy expressions or references
+/// anything but a fragment in the same document.
+fn is_unsafe_css(css: &str) -> bool {
+    let css = normalized(css);
+    if css.contains("@import") || css.contains("expression(") || css.contains("behavior:") {
+        return true;
+    }
+    css.match_indices("url(").any(|(at, _)| {
+        let target = css[at + 4..].trim_start_matches(['"', '\'']);
+        !target.starts_with('#')
+    })
+}
+
+fn keep_attribute(element: &str, name: &str, value: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    if lower.starts_with("on") || has_script_scheme(value) {
+        return false;
+    }
+    match lower.as_str() {
+        "xmlns" => value == SVG_NAMESPACE,
+        "xmlns:xlink" => value == XLINK_NAMESPACE,
+        "href" | "xlink:href" => {
+            let value = value.trim();
+            value.starts_with('#')
+                || (element == "image"
+                    && EMBEDDED_IMAGE_PREFIXES
+                        .iter()
+                        .any(|prefix| value.to_ascii_lowercase().starts_with(prefix)))
+        }
+        _ if lower.contains(':') && !ALLOWED_PREFIXED_ATTRIBUTES.contains(&lower.as_str()) => false,
+        _ => !is_unsafe_css(value),
+    }
+}
+
+/// Cheap check that `data` is an XML document with an `<svg>` root, used
+/// where magic-byte detection does not apply.
+pub fn looks_like_svg(data: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return false;
+    };
+    let mut tokenizer = Tokenizer {
+        src: text.trim_start_matches('\u{feff}'),
+        pos: 0,
+    };
+    loop {
+        match tokenizer.next() {
+            Ok(Some(Token::Skipped)) => continue,
+            Ok(Some(Token::Text(text))) if text.trim().is_empty() => continue,
+            Ok(Some(Token::Start { name, .. })) => return name == "svg",
+            _ => return false,
+        }
+    }
+}
+
+/// Parses `data` as SVG and returns a sanitized re-serialization. Fails on
+/// malformed XML, undefined entities or a root element other than `<svg>`.
+pub fn sanitize_svg(data: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "SVG must be UTF-8 encoded".to_string())?;
+    let mut tokenizer = Tokenizer {
+        src: text.trim_start_matches('\u{feff}'),
+        pos: 0,
+    };
+
+    let mut out = String::with_capacity(text.len());
+    // Open elements and whether each was kept.
+    let mut open: Vec<(&str, bool)> = Vec::new();
+    let mut seen_root = false;
+
+    while let Some(token) = tokenizer.next()? {
+        let inside_kept = open.iter().all(|(_, kept)| *kept);
+        match token {
+            Token::Skipped => {}
+            Token::Text(text) => {
+                if open.is_empty() {
+                    if !text.trim().is_empty() {
+                        return Err("Text outside the root element".to_string());
+                    }
+                    continue;
+                }
+                let in_style = open.last().is_some_and(|(name, _)| *name == "style");
+                if inside_kept && !(in_style && is_unsafe_css(&text)) {
+                    escape(&text, &mut out);
+                }
+            }
+            Token::Start {
+                name,
+                attributes,
+                empty,
+            } => {
+                if open.is_empty() {
+                    if seen_root {
+                        return Err("More than one root element".to_string());
+                    }
+                    if name != "svg" {
+                        return Err("Root element must be <svg>".to_string());
+                    }
+                    seen_root = true;
+                }
+                if open.len() >= MAX_DEPTH {
+                    return Err("SVG is nested too deeply".to_string());
+                }
+
+                let kept = inside_kept && ALLOWED_ELEMENTS.contains(&name);
+                if kept {
+                    out.push('<');
+                    out.push_str(name);
+                    for (attribute, value) in &attributes {
+                        if keep_attribute(name, attribute, value) {
+                            out.push(' ');
+                            out.push_str(attribute);
+                            out.push_str("=\"");
+                            escape(value, &mut out);
+                            out.push('"');
+                        }
+                    }
+                    out.push_str(if empty { "/>" } else { ">" });
+                }
+                if !empty {
+                    open.push((name, kept));
+                }
+            }
+            Token::End(name) => {
+                let (open_name, kept) = open.pop().ok_or("Unexpected closing tag")?;
+                if open_name != name {
+                    return Err(format!("Mismatched closing tag </{name}>"));
+                }
+                if kept {
+                    out.push_str("</");
+                    out.push_str(name);
+                    out.push('>');
+                }
+            }
+        }
+    }
+
+    if !open.is_empty() {
+        return Err("Unclosed element".to_string());
+    }
+    if !seen_root {
+        return Err("No <svg> element found".to_string());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Created with a diagram editor -->
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="120" height="40" viewBox="0 0 120 40">
+  <defs><linearGradient id="g"><stop offset="0" stop-color="#000"/></linearGradient></defs>
+  <style>.box { fill: url(#g); }</style>
+  <rect class="box" x="0" y="0" width="120" height="40"/>
+  <text x="4" y="24">ls -l &amp;&amp; pwd &lt;dir&gt;</text>
+  <use xlink:href="#g"/>
+</svg>"##;
+
+    #[test]
+    fn test_benign_diagram_survives() {
+        let clean = sanitize_svg(DIAGRAM.as_bytes()).unwrap();
+        assert!(clean.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(clean.contains("<linearGradient id=\"g\"><stop offset=\"0\" stop-color=\"#000\"/>"));
+        assert!(clean.contains("<style>.box { fill: url(#g); }</style>"));
+        assert!(clean.contains("ls -l &amp;&amp; pwd &lt;dir&gt;"));
+        assert!(clean.contains("<use xlink:href=\"#g\"/>"));
+        assert!(!clean.contains("<?xml") && !clean.contains("<!--"));
+        assert!(looks_like_svg(DIAGRAM.as_bytes()));
+        assert!(!looks_like_svg(b"<html><body></body></html>"));
+        assert!(!looks_like_svg(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn test_malicious_corpus_comes_out_inert() {
+        let corpus = [
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><script>alert(1)</script></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><SCRIPT><![CDATA[alert(1)]]></SCRIPT></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" onload="alert(1)"><rect ONCLICK="alert(1)"/></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><foreignObject><iframe src="javascript:alert(1)"/></foreignObject></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><a href="&#106;ava&#x09;script:alert(1)"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><a xlink:href="https://evil.example/"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><image href="https://evil.example/track.png"/></svg>"#,
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><a href="#x"><set attributeName="href" to="javascript:alert(1)"/></a></svg>"##,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><animate attributeName="href" values="javascript:alert(1)"/></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><style>@import url(https://evil.example/x.css);</style></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect style="fill: url('https://evil.example/p')"/></svg>"#,
+            r#"<?xml-stylesheet href="https://evil.example/x.css"?><svg xmlns="http://www.w3.org/2000/svg"/>"#,
+            r#"<!DOCTYPE svg [<!ENTITY ok "fine">]><svg xmlns="http://www.w3.org/2000/svg"><text>safe</text></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:ev="http://evil.example/ns"><ev:listener event="load"/></svg>"#,
+        ];
+
+        for input in corpus {
+            let clean = sanitize_svg(input.as_bytes()).unwrap_or_else(|err| panic!("{input}: {err}"));
+            let lower = clean.to_ascii_lowercase();
+            for needle in ["script", "alert", "onload", "onclick", "foreignobject", "evil.example", "<set", "<animate", "entity", "<!"] {
+                assert!(!lower.contains(needle), "{input} -> {clean} contains {needle}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_entities_and_malformed_documents() {
+        let rejected = [
+            r#"<!DOCTYPE svg [<!ENTITY xxe SYSTEM "file:///etc/passwd">]><svg xmlns="http://www.w3.org/2000/svg"><text>&xxe;</text></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect x=1/></svg>"#,
+            r#"<html><svg/></html>"#,
+            r#"<svg/><svg/>"#,
+            "not xml at all",
+        ];
+        for input in rejected {
+            assert!(sanitize_svg(input.as_bytes()).is_err(), "{input} was accepted");
+        }
+    }
+}
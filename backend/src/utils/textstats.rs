@@ -0,0 +1,72 @@
+//! Text Statistics
+//!
+//! Word counts and reading-time estimates for markdown content. Counting works
+//! on the plain text produced by [`strip_markdown`], so fenced code blocks,
+//! link targets and markup do not inflate the numbers.
+
+use crate::utils::markdown::strip_markdown;
+
+/// Average adult reading speed used for the estimate.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStats {
+    pub word_count: i64,
+    pub reading_time_minutes: i64,
+}
+
+/// Counts words and estimates reading time for markdown content.
+///
+/// Any non-empty text takes at least one minute; empty content reports zero.
+pub fn compute(markdown: &str) -> TextStats {
+    let word_count = strip_markdown(markdown)
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+
+    TextStats {
+        word_count: word_count as i64,
+        reading_time_minutes: word_count.div_ceil(WORDS_PER_MINUTE) as i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_content() {
+        assert_eq!(
+            compute(""),
+            TextStats {
+                word_count: 0,
+                reading_time_minutes: 0
+            }
+        );
+        assert_eq!(compute("---\n\n```\n```").word_count, 0);
+    }
+
+    #[test]
+    fn test_counts_prose_and_ignores_markup() {
+        let md = "# Dateien kopieren\n\nMit `cp` kopierst du **Dateien** - siehe [man cp](https://man7.org).";
+        let stats = compute(md);
+        assert_eq!(stats.word_count, 10);
+        assert_eq!(stats.reading_time_minutes, 1);
+    }
+
+    #[test]
+    fn test_large_code_block_is_not_counted() {
+        let code: String = (0..500).map(|i| format!("echo line {i}\n")).collect();
+        let md = format!("Intro with four words\n\n```bash\n{code}```\n\nOutro has three");
+        let stats = compute(&md);
+        assert_eq!(stats.word_count, 7);
+        assert_eq!(stats.reading_time_minutes, 1);
+    }
+
+    #[test]
+    fn test_reading_time_rounds_up() {
+        assert_eq!(compute(&"wort ".repeat(200)).reading_time_minutes, 1);
+        assert_eq!(compute(&"wort ".repeat(201)).reading_time_minutes, 2);
+        assert_eq!(compute(&"wort ".repeat(1000)).word_count, 1000);
+    }
+}
@@ -0,0 +1,59 @@
+//! Timestamps as the database holds them.
+//!
+//! Rows written by SQLite defaults (`CURRENT_TIMESTAMP`, `datetime('now')`)
+//! store `2024-05-01 10:00:00` in UTC, rows written by the application store
+//! RFC 3339. Models decode both into `DateTime<Utc>` through sqlx; this module
+//! covers the places that get a timestamp as text from elsewhere, such as
+//! query strings and imported bundles.
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+
+/// Parses RFC 3339 or SQLite's `YYYY-MM-DD HH:MM:SS[.fff]`, the latter as UTC.
+/// Whatever it accepts, the models decode as well.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+/// RFC 3339 in UTC, the same text responses serialize `DateTime<Utc>` as.
+pub fn format_timestamp(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parses_legacy_and_rfc3339_timestamps() {
+        let expected = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        for value in [
+            "2024-05-01 10:00:00",
+            "2024-05-01 10:00:00.000",
+            "2024-05-01T10:00:00Z",
+            "2024-05-01T12:00:00+02:00",
+            "2024-05-01T10:00:00.000+00:00",
+        ] {
+            assert_eq!(parse_timestamp(value), Some(expected), "{value}");
+        }
+
+        assert_eq!(parse_timestamp("2024-05-01"), None);
+        assert_eq!(parse_timestamp("gestern"), None);
+    }
+
+    #[test]
+    fn test_formats_like_serde() {
+        let value = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        assert_eq!(format_timestamp(&value), "2024-05-01T10:00:00Z");
+        assert_eq!(
+            serde_json::to_value(value).unwrap(),
+            serde_json::json!(format_timestamp(&value))
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! WebP Encoding
+//!
+//! Encodes ARGB pixels as WebP using the lossless VP8L bitstream: a subtract
+//! green transform, LZ77 backward references and per-channel prefix codes.
+//! Screenshots, with their flat areas and repeated widgets, shrink well this
+//! way. `quality` below 100 rounds off low color bits first ("near
+//! lossless"), which costs little visually and helps compression further.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Largest width or height the VP8L header can describe.
+pub const MAX_DIMENSION: u32 = 1 << 14;
+
+const NUM_LENGTH_CODES: usize = 24;
+const NUM_DISTANCE_CODES: usize = 40;
+const GREEN_ALPHABET: usize = 256 + NUM_LENGTH_CODES;
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+const CODE_LENGTH_ORDER: [usize; 19] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 4096;
+/// Distances are sent offset by the 120 short 2D neighbourhood codes.
+const PLANE_CODES: usize = 120;
+const MAX_DISTANCE: usize = (1 << 20) - PLANE_CODES;
+const HASH_BITS: u32 = 16;
+const MAX_CHAIN: usize = 32;
+
+/// Encodes `width` × `height` ARGB pixels (`0xAARRGGBB`, row by row).
+/// Returns `None` when the image is too large for WebP.
+pub fn encode(width: u32, height: u32, pixels: &[u32], quality: u8) -> Option<Vec<u8>> {
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return None;
+    }
+    if pixels.len() != width as usize * height as usize {
+        return None;
+    }
+
+    let mut argb = pixels.to_vec();
+    let dropped_bits = match quality {
+        90.. => 0,
+        70..=89 => 1,
+        50..=69 => 2,
+        _ => 3,
+    };
+    if dropped_bits > 0 {
+        for pixel in &mut argb {
+            *pixel = quantize(*pixel, dropped_bits);
+        }
+    }
+    let has_alpha = argb.iter().any(|pixel| pixel >> 24 != 0xFF);
+    for pixel in &mut argb {
+        *pixel = subtract_green(*pixel);
+    }
+
+    let mut writer = BitWriter::default();
+    writer.put(0x2F, 8);
+    writer.put(width - 1, 14);
+    writer.put(height - 1, 14);
+    writer.put(u32::from(has_alpha), 1);
+    writer.put(0, 3);
+
+    // One transform, subtract green, then the end of the transform list
+    writer.put(1, 1);
+    writer.put(2, 2);
+    writer.put(0, 1);
+
+    // No color cache and a single prefix code group
+    writer.put(0, 1);
+    writer.put(0, 1);
+    encode_pixels(&mut writer, &argb);
+
+    let data = writer.finish();
+    let padded = data.len() + data.len() % 2;
+    let mut out = Vec::with_capacity(20 + padded);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((12 + padded) as u32).to_le_bytes());
+    out.extend_from_slice(b"WEBPVP8L");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    Some(out)
+}
+
+/// Rounds the color channels to a multiple of `2^bits`; alpha is kept.
+fn quantize(pixel: u32, bits: u32) -> u32 {
+    let round = |value: u32| {
+        let half = 1 << (bits - 1);
+        ((value + half) >> bits << bits).min(0xFF)
+    };
+    let channel = |shift: u32| round((pixel >> shift) & 0xFF) << shift;
+    (pixel & 0xFF00_0000) | channel(16) | channel(8) | channel(0)
+}
+
+fn subtract_green(pixel: u32) -> u32 {
+    let green = (pixel >> 8) & 0xFF;
+    let red = (((pixel >> 16) & 0xFF).wrapping_sub(green)) & 0xFF;
+    let blue = ((pixel & 0xFF).wrapping_sub(green)) & 0xFF;
+    (pixel & 0xFF00_FF00) | red << 16 | blue
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    used: u32,
+}
+
+impl BitWriter {
+    /// Appends the low `count` bits of `value`, least significant first.
+    fn put(&mut self, value: u32, count: u32) {
+        if count == 0 {
+            return;
+        }
+        self.acc |= u64::from(value & ((1u64 << count) - 1) as u32) << self.used;
+        self.used += count;
+        while self.used >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.used -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+enum Token {
+    Literal(u32),
+    Copy { length: usize, distance: usize },
+}
+
+/// Splits a value of at least 1 into its prefix symbol, extra bit count and
+/// extra bits, as VP8L codes lengths and distances.
+fn prefix_encode(value: usize) -> (usize, u32, u32) {
+    let x = value - 1;
+    if x < 4 {
+        return (x, 0, 0);
+    }
+    let high = usize::BITS - 1 - x.leading_zeros();
+    let second = (x >> (high - 1)) & 1;
+    let extra_bits = high - 1;
+    (
+        2 * high as usize + second,
+        extra_bits,
+        (x & ((1 << extra_bits) - 1)) as u32,
+    )
+}
+
+fn hash(pixels: &[u32], at: usize) -> usize {
+    let mut h = pixels[at].wrapping_mul(0x9E37_79B1);
+    h ^= pixels[at + 1].wrapping_mul(0x85EB_CA6B);
+    h ^= pixels[at + 2].wrapping_mul(0xC2B2_AE35);
+    (h >> (32 - HASH_BITS)) as usize
+}
+
+/// Greedy LZ77 over whole pixels with hash chains.
+fn tokenize(pixels: &[u32]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; pixels.len()];
+    let insert = |at: usize, head: &mut Vec<usize>, prev: &mut Vec<usize>| {
+        if at + MIN_MATCH <= pixels.len() {
+            let h = hash(pixels, at);
+            prev[at] = head[h];
+            head[h] = at;
+        }
+    };
+
+    let mut at = 0;
+    while at < pixels.len() {
+        let mut best = (0, 0);
+        if at + MIN_MATCH <= pixels.len() {
+            let max_len = MAX_MATCH.min(pixels.len() - at);
+            // A run of the previous pixel is the most common match
+            if at > 0 {
+                let len = run_length(pixels, at - 1, at, max_len);
+                best = (len, 1);
+            }
+            let mut candidate = head[hash(pixels, at)];
+            let mut chain = 0;
+            while candidate != usize::MAX && chain < MAX_CHAIN && best.0 < max_len {
+                let distance = at - candidate;
+                if distance > MAX_DISTANCE {
+                    break;
+                }
+                let len = run_length(pixels, candidate, at, max_len);
+                if len > best.0 {
+                    best = (len, distance);
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best.0 >= MIN_MATCH {
+            tokens.push(Token::Copy {
+                length: best.0,
+                distance: best.1,
+            });
+            for i in at..at + best.0 {
+                insert(i, &mut head, &mut prev);
+            }
+            at += best.0;
+        } else {
+            tokens.push(Token::Literal(pixels[at]));
+            insert(at, &mut head, &mut prev);
+            at += 1;
+        }
+    }
+    tokens
+}
+
+fn run_length(pixels: &[u32], from: usize, at: usize, max_len: usize) -> usize {
+    let mut len = 0;
+    while len < max_len && pixels[from + len] == pixels[at + len] {
+        len += 1;
+    }
+    len
+}
+
+fn encode_pixels(writer: &mut BitWriter, pixels: &[u32]) {
+    let tokens = tokenize(pixels);
+
+    let mut green = vec![0u32; GREEN_ALPHABET];
+    let mut red = vec![0u32; 256];
+    let mut blue = vec![0u32; 256];
+    let mut alpha = vec![0u32; 256];
+    let mut distance = vec![0u32; NUM_DISTANCE_CODES];
+    for token in &tokens {
+        match *token {
+            Token::Literal(pixel) => {
+                green[((pixel >> 8) & 0xFF) as usize] += 1;
+                red[((pixel >> 16) & 0xFF) as usize] += 1;
+                blue[(pixel & 0xFF) as usize] += 1;
+                alpha[(pixel >> 24) as usize] += 1;
+            }
+            Token::Copy {
+                length,
+                distance: dist,
+            } => {
+                green[256 + prefix_encode(length).0] += 1;
+                distance[prefix_encode(dist + PLANE_CODES).0] += 1;
+            }
+        }
+    }
+
+    let codes: Vec<PrefixCode> = [green, red, blue, alpha, distance]
+        .iter()
+        .map(|histogram| PrefixCode::new(histogram, MAX_CODE_LENGTH))
+        .collect();
+    for code in &codes {
+        code.write_header(writer);
+    }
+
+    for token in &tokens {
+        match *token {
+            Token::Literal(pixel) => {
+                codes[0].write_symbol(writer, ((pixel >> 8) & 0xFF) as usize);
+                codes[1].write_symbol(writer, ((pixel >> 16) & 0xFF) as usize);
+                codes[2].write_symbol(writer, (pixel & 0xFF) as usize);
+                codes[3].write_symbol(writer, (pixel >> 24) as usize);
+            }
+            Token::Copy {
+                length,
+                distance: dist,
+            } => {
+                let (symbol, bits, extra) = prefix_encode(length);
+                codes[0].write_symbol(writer, 256 + symbol);
+                writer.put(extra, bits);
+                let (symbol, bits, extra) = prefix_encode(dist + PLANE_CODES);
+                codes[4].write_symbol(writer, symbol);
+                writer.put(extra, bits);
+            }
+        }
+    }
+}
+
+/// A canonical prefix code. `lengths` are the code lengths as declared in
+/// the bitstream; a code with a single symbol spends no bits on it.
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+    used: Vec<usize>,
+}
+
+impl PrefixCode {
+    fn new(histogram: &[u32], limit: u8) -> Self {
+        let lengths = code_lengths(histogram, limit);
+        let used: Vec<usize> = (0..lengths.len()).filter(|&s| lengths[s] > 0).collect();
+        let codes = canonical_codes(&lengths);
+        Self {
+            lengths,
+            codes,
+            used,
+        }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        if self.used.len() > 1 {
+            writer.put(u32::from(self.codes[symbol]), u32::from(self.lengths[symbol]));
+        }
+    }
+
+    fn write_header(&self, writer: &mut BitWriter) {
+        match self.used.as_slice() {
+            [] => {
+                // Simple code with the single symbol 0
+                writer.put(1, 1);
+                writer.put(0, 1);
+                writer.put(0, 1);
+                writer.put(0, 1);
+            }
+            symbols if symbols.len() <= 2 && symbols.iter().all(|&s| s < 256) => {
+                writer.put(1, 1);
+                writer.put(symbols.len() as u32 - 1, 1);
+                if symbols[0] < 2 {
+                    writer.put(0, 1);
+                    writer.put(symbols[0] as u32, 1);
+                } else {
+                    writer.put(1, 1);
+                    writer.put(symbols[0] as u32, 8);
+                }
+                if let Some(&second) = symbols.get(1) {
+                    writer.put(second as u32, 8);
+                }
+            }
+            _ => self.write_normal_header(writer),
+        }
+    }
+
+    fn write_normal_header(&self, writer: &mut BitWriter) {
+        // Code lengths as 0..=15 plus runs of zeros (17: 3-10, 18: 11-138)
+        let mut tokens: Vec<(usize, u32, u32)> = Vec::new();
+        let mut i = 0;
+        while i < self.lengths.len() {
+            let length = self.lengths[i];
+            let mut run = 1;
+            while i + run < self.lengths.len() && self.lengths[i + run] == length {
+                run += 1;
+            }
+            if length == 0 && run >= 3 {
+                let mut left = run;
+                while left >= 3 {
+                    if left >= 11 {
+                        let n = left.min(138);
+                        tokens.push((18, 7, (n - 11) as u32));
+                        left -= n;
+                    } else {
+                        let n = left.min(10);
+                        tokens.push((17, 3, (n - 3) as u32));
+                        left -= n;
+                    }
+                }
+                tokens.extend(std::iter::repeat_n((0, 0, 0), left));
+            } else {
+                tokens.extend(std::iter::repeat_n((usize::from(length), 0, 0), run));
+            }
+            i += run;
+        }
+
+        let mut histogram = [0u32; 19];
+        for &(symbol, _, _) in &tokens {
+            histogram[symbol] += 1;
+        }
+        let code = PrefixCode::new(&histogram, MAX_CODE_LENGTH_CODE_LENGTH);
+
+        let count = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code.lengths[symbol] > 0)
+            .map_or(0, |last| last + 1)
+            .max(4);
+        writer.put(0, 1);
+        writer.put(count as u32 - 4, 4);
+        for &symbol in &CODE_LENGTH_ORDER[..count] {
+            writer.put(u32::from(code.lengths[symbol]), 3);
+        }
+        // Lengths cover the whole alphabet
+        writer.put(0, 1);
+        for (symbol, bits, extra) in tokens {
+            code.write_symbol(writer, symbol);
+            writer.put(extra, bits);
+        }
+    }
+}
+
+/// Huffman code lengths for `histogram`, limited to `limit` bits. Unused
+/// symbols get length 0; a lone symbol gets length 1.
+fn code_lengths(histogram: &[u32], limit: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; histogram.len()];
+    let used: Vec<usize> = (0..histogram.len()).filter(|&s| histogram[s] > 0).collect();
+    match used.len() {
+        0 => return lengths,
+        1 => {
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    // Plain Huffman tree over the used symbols
+    let mut parent = vec![usize::MAX; used.len() * 2];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((u64::from(histogram[symbol]), node)))
+        .collect();
+    let mut next = used.len();
+    while heap.len() > 1 {
+        let Reverse((a, left)) = heap.pop().unwrap_or(Reverse((0, 0)));
+        let Reverse((b, right)) = heap.pop().unwrap_or(Reverse((0, 0)));
+        parent[left] = next;
+        parent[right] = next;
+        heap.push(Reverse((a + b, next)));
+        next += 1;
+    }
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while parent[node] != usize::MAX {
+            node = parent[node];
+            depth += 1;
+        }
+        depth
+    };
+
+    // Count codes per length, then move overlong codes up (JPEG K.3),
+    // which keeps the code complete
+    let mut per_length = vec![0usize; used.len() + 1];
+    for node in 0..used.len() {
+        per_length[depth(node)] += 1;
+    }
+    let limit = usize::from(limit);
+    for len in (limit + 1..per_length.len()).rev() {
+        while per_length[len] > 0 {
+            let mut shorter = len - 2;
+            while per_length[shorter] == 0 {
+                shorter -= 1;
+            }
+            per_length[len] -= 2;
+            per_length[len - 1] += 1;
+            per_length[shorter + 1] += 2;
+            per_length[shorter] -= 1;
+        }
+    }
+
+    // Most frequent symbols get the shortest codes
+    let mut by_frequency = used;
+    by_frequency.sort_by_key(|&symbol| (Reverse(histogram[symbol]), symbol));
+    let mut symbols = by_frequency.into_iter();
+    for (len, &count) in per_length.iter().enumerate().take(limit + 1) {
+        for symbol in symbols.by_ref().take(count) {
+            lengths[symbol] = len as u8;
+        }
+    }
+    lengths
+}
+
+/// Canonical codes for `lengths`, bit-reversed for LSB-first output.
+fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut per_length = [0u16; 16];
+    for &len in lengths {
+        per_length[usize::from(len)] += 1;
+    }
+    per_length[0] = 0;
+    let mut next = [0u16; 16];
+    let mut code = 0u16;
+    for len in 1..16 {
+        code = (code + per_length[len - 1]) << 1;
+        next[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next[usize::from(len)];
+            next[usize::from(len)] += 1;
+            code.reverse_bits() >> (16 - u32::from(len))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_encoding() {
+        assert_eq!(prefix_encode(1), (0, 0, 0));
+        assert_eq!(prefix_encode(4), (3, 0, 0));
+        assert_eq!(prefix_encode(5), (4, 1, 0));
+        assert_eq!(prefix_encode(6), (4, 1, 1));
+        assert_eq!(prefix_encode(7), (5, 1, 0));
+        assert_eq!(prefix_encode(4096), (23, 10, 1023));
+    }
+
+    #[test]
+    fn test_code_lengths_are_limited_and_complete() {
+        // Fibonacci weights produce a maximally deep tree
+        let mut histogram = vec![1u32, 1];
+        for i in 2..30 {
+            histogram.push(histogram[i - 1] + histogram[i - 2]);
+        }
+        let lengths = code_lengths(&histogram, 15);
+        assert!(lengths.iter().all(|&len| (1..=15).contains(&len)));
+        let kraft: f64 = lengths.iter().map(|&len| 0.5f64.powi(i32::from(len))).sum();
+        assert!((kraft - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_header_and_dimensions() {
+        let pixels = vec![0xFF20_4060; 300 * 20];
+        let webp = encode(300, 20, &pixels, 100).unwrap();
+        assert!(webp.starts_with(b"RIFF"));
+        assert_eq!(&webp[8..16], b"WEBPVP8L");
+        assert_eq!(webp.len() % 2, 0);
+        assert_eq!(
+            u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize,
+            webp.len() - 8
+        );
+        assert_eq!(crate::utils::image_size::dimensions(&webp), Some((300, 20)));
+        assert!(encode(0, 1, &[], 100).is_none());
+        assert!(encode(2, 2, &[0; 3], 100).is_none());
+    }
+}
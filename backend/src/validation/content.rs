@@ -0,0 +1,629 @@
+//! Site content sections: size, and the structure of the built-in ones.
+//!
+//! Custom sections registered in `content_sections` are checked against their
+//! own JSON schema, if they have one.
+
+use super::tutorials::validate_icon;
+use super::validate_slug;
+use crate::models::SITE_CONTENT_SECTIONS;
+use crate::settings;
+use crate::utils::json_schema;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+pub const MAX_CONTENT_BYTES: usize = 200_000;
+
+fn allowed_sections() -> &'static HashSet<&'static str> {
+    static ALLOWED: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    ALLOWED.get_or_init(|| SITE_CONTENT_SECTIONS.iter().copied().collect())
+}
+
+pub fn is_builtin_section(section: &str) -> bool {
+    allowed_sections().contains(section)
+}
+
+pub fn validate_content_size(content: &Value) -> Result<(), String> {
+    match serde_json::to_string(content) {
+        Ok(serialized) if serialized.len() <= MAX_CONTENT_BYTES => Ok(()),
+        Ok(_) => Err(format!("Content too large (max {MAX_CONTENT_BYTES} bytes)")),
+        Err(err) => Err(format!("Invalid JSON content: {err}")),
+    }
+}
+
+/// Checks a custom section against its registered schema.
+pub fn validate_custom_content(
+    section: &str,
+    schema: Option<&Value>,
+    content: &Value,
+) -> Result<(), String> {
+    match schema {
+        Some(schema) => json_schema::validate(schema, content)
+            .map_err(|err| format!("Invalid structure for section '{section}': {err}")),
+        None => Ok(()),
+    }
+}
+
+/// Checks a built-in section against the shape the frontend renders.
+pub fn validate_content_structure(section: &str, content: &Value) -> Result<(), String> {
+    let result: Result<(), String> = match section {
+        "hero" => validate_hero_structure(content).map_err(String::from),
+        "tutorial_section" => validate_tutorial_section_structure(content).map_err(String::from),
+        "header" => validate_header_structure(content),
+        "footer" => validate_footer_structure(content),
+        settings::SECTION => settings::validate(content),
+        "stats" => validate_stats_structure(content),
+        "cta_section" => validate_cta_section_structure(content),
+        "login" => validate_login_structure(content).map_err(String::from),
+        "grundlagen_page" => validate_grundlagen_page_structure(content).map_err(String::from),
+        _ => Ok(()),
+    };
+
+    result.map_err(|err| format!("Invalid structure for section '{section}': {err}"))
+}
+
+fn validate_hero_structure(content: &Value) -> Result<(), &'static str> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    if !obj.contains_key("title") || !obj.contains_key("features") {
+        return Err("Missing required fields 'title' or 'features'");
+    }
+    if !obj.get("features").map(|v| v.is_array()).unwrap_or(false) {
+        return Err("Field 'features' must be an array");
+    }
+    Ok(())
+}
+
+fn validate_tutorial_section_structure(content: &Value) -> Result<(), &'static str> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    if !obj.contains_key("title") || !obj.contains_key("description") {
+        return Err("Missing required fields 'title' or 'description'");
+    }
+    Ok(())
+}
+
+/// Checks the target fields of a header/footer link and reports whether the
+/// link has a usable target. Present targets must be non-blank strings:
+/// `href` must be http(s) or mailto, `path` must be absolute and `slug` must
+/// be a valid page slug.
+fn validate_link_targets(item: &Value, describe: &str) -> Result<bool, String> {
+    let mut has_target = false;
+
+    for key in ["slug", "href", "path", "value"] {
+        let Some(raw) = item.get(key) else {
+            continue;
+        };
+        let value = raw
+            .as_str()
+            .map(str::trim)
+            .ok_or_else(|| format!("{describe}: '{key}' must be a string"))?;
+        if value.is_empty() {
+            return Err(format!("{describe}: '{key}' must not be empty"));
+        }
+
+        match key {
+            "href" if !is_allowed_href(value) => {
+                return Err(format!(
+                    "{describe}: 'href' must start with http://, https:// or mailto:"
+                ));
+            }
+            "path" if !value.starts_with('/') => {
+                return Err(format!("{describe}: 'path' must start with '/'"));
+            }
+            "slug" => {
+                validate_slug(value)
+                    .map_err(|_| format!("{describe}: 'slug' is not a valid page slug"))?;
+            }
+            _ => {}
+        }
+        has_target = true;
+    }
+
+    if let Some(target) = item.get("target") {
+        let value = target.get("value").and_then(Value::as_str).map(str::trim);
+        if value.map(str::is_empty).unwrap_or(true) {
+            return Err(format!(
+                "{describe}: 'target.value' must be a non-empty string"
+            ));
+        }
+        has_target = true;
+    }
+
+    Ok(has_target || item.get("type").and_then(Value::as_str) == Some("section"))
+}
+
+fn is_allowed_href(href: &str) -> bool {
+    let lower = href.to_ascii_lowercase();
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme) && lower.len() > scheme.len())
+}
+
+fn validate_header_structure(content: &Value) -> Result<(), String> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    if !obj.contains_key("brand") || !obj.contains_key("navItems") {
+        return Err("Missing required fields 'brand' or 'navItems'".to_string());
+    }
+    let items = obj
+        .get("navItems")
+        .and_then(Value::as_array)
+        .ok_or("Field 'navItems' must be an array")?;
+
+    for (index, item) in items.iter().enumerate() {
+        let describe = format!("Navigation item {index}");
+        if item.get("id").is_none() || item.get("label").is_none() {
+            return Err(format!("{describe} must include 'id' and 'label'"));
+        }
+        if !validate_link_targets(item, &describe)? {
+            return Err(format!(
+                "{describe} must include a target ('slug', 'href', 'path', 'value', or type='section')"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_footer_structure(content: &Value) -> Result<(), String> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    if !obj.contains_key("brand") || !obj.contains_key("quickLinks") {
+        return Err("Missing required fields 'brand' or 'quickLinks'".to_string());
+    }
+
+    let quick_links = obj
+        .get("quickLinks")
+        .and_then(Value::as_array)
+        .ok_or("Field 'quickLinks' must be an array")?;
+    for (index, link) in quick_links.iter().enumerate() {
+        let describe = format!("Quick link {index}");
+        if !validate_link_targets(link, &describe)? {
+            return Err(format!(
+                "{describe} must include a target ('target', 'slug', 'href' or 'path')"
+            ));
+        }
+    }
+
+    if let Some(contact_links) = obj.get("contactLinks") {
+        let contact_links = contact_links
+            .as_array()
+            .ok_or("Field 'contactLinks' must be an array")?;
+        for (index, link) in contact_links.iter().enumerate() {
+            let href = link
+                .get("href")
+                .or_else(|| link.get("url"))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .unwrap_or_default();
+            if !is_allowed_href(href) {
+                return Err(format!(
+                    "Contact link {index}: 'href' must start with http://, https:// or mailto:"
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn non_empty_str<'a>(obj: &'a Value, key: &str) -> Option<&'a str> {
+    obj.get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn validate_stats_structure(content: &Value) -> Result<(), String> {
+    let items = content
+        .get("items")
+        .ok_or("Missing required field 'items'")?
+        .as_array()
+        .ok_or("Field 'items' must be an array")?;
+
+    for (index, item) in items.iter().enumerate() {
+        if !item.is_object() {
+            return Err(format!("Stat {index} must be an object"));
+        }
+        for key in ["value", "label"] {
+            if non_empty_str(item, key).is_none() {
+                return Err(format!("Stat {index}: '{key}' must be a non-empty string"));
+            }
+        }
+        if let Some(icon) = item.get("icon") {
+            let icon = icon
+                .as_str()
+                .ok_or_else(|| format!("Stat {index}: 'icon' must be a string"))?;
+            validate_icon(icon).map_err(|err| format!("Stat {index}: {err}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_cta_section_structure(content: &Value) -> Result<(), String> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    if non_empty_str(content, "title").is_none() {
+        return Err("Field 'title' must be a non-empty string".to_string());
+    }
+    if obj
+        .get("description")
+        .is_some_and(|value| !value.is_string())
+    {
+        return Err("Field 'description' must be a string".to_string());
+    }
+
+    let primary = obj
+        .get("primary")
+        .ok_or("Missing required field 'primary'")?;
+    validate_cta_link(primary, "primary")?;
+    if let Some(secondary) = obj.get("secondary") {
+        validate_cta_link(secondary, "secondary")?;
+    }
+
+    Ok(())
+}
+
+fn validate_cta_link(link: &Value, key: &str) -> Result<(), String> {
+    let describe = format!("CTA link '{key}'");
+    if !link.is_object() {
+        return Err(format!("{describe} must be an object"));
+    }
+    if non_empty_str(link, "label").is_none() {
+        return Err(format!("{describe}: 'label' must be a non-empty string"));
+    }
+    if !validate_link_targets(link, &describe)? {
+        return Err(format!(
+            "{describe} needs a 'target', 'path', 'href' or 'slug'"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_login_structure(content: &Value) -> Result<(), &'static str> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    // We can be lenient, but let's check for at least one expected field if we want strictness.
+    // For now, just ensuring it's an object is enough, or check for 'title'.
+    if !obj.contains_key("title") {
+        return Err("Missing required field 'title'");
+    }
+    Ok(())
+}
+
+fn validate_grundlagen_page_structure(content: &Value) -> Result<(), &'static str> {
+    let obj = content.as_object().ok_or("Expected JSON object")?;
+    for key in ["hero", "highlights", "modules", "cta"] {
+        if !obj.contains_key(key) {
+            return Err("Missing required fields 'hero', 'highlights', 'modules' or 'cta'");
+        }
+    }
+
+    let hero = obj["hero"]
+        .as_object()
+        .ok_or("Field 'hero' must be an object")?;
+    if !hero.get("title").map(Value::is_string).unwrap_or(false) {
+        return Err("Field 'hero.title' must be a string");
+    }
+
+    let highlights = obj["highlights"]
+        .as_array()
+        .ok_or("Field 'highlights' must be an array")?;
+    if !highlights
+        .iter()
+        .all(|item| item.get("title").map(Value::is_string).unwrap_or(false))
+    {
+        return Err("Each highlight must be an object with a 'title'");
+    }
+
+    let modules = obj["modules"]
+        .as_object()
+        .ok_or("Field 'modules' must be an object")?;
+    for key in ["items", "summary"] {
+        if let Some(list) = modules.get(key) {
+            if !list
+                .as_array()
+                .map(|items| items.iter().all(Value::is_string))
+                .unwrap_or(false)
+            {
+                return Err(
+                    "Fields 'modules.items' and 'modules.summary' must be arrays of strings",
+                );
+            }
+        }
+    }
+
+    let cta = obj["cta"]
+        .as_object()
+        .ok_or("Field 'cta' must be an object")?;
+    for key in ["primary", "secondary"] {
+        if let Some(link) = cta.get(key) {
+            if !link.get("label").map(Value::is_string).unwrap_or(false) {
+                return Err("CTA links must be objects with a 'label'");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_header_structure_relaxed() {
+        // Case 1: Standard link with path
+        let content_standard = json!({
+            "brand": { "name": "Test" },
+            "navItems": [
+                { "id": "1", "label": "Blog", "path": "/blog" }
+            ]
+        });
+        assert!(validate_header_structure(&content_standard).is_ok());
+
+        // Case 2: Section link with type="section" (no explicit target field)
+        let content_section = json!({
+            "brand": { "name": "Test" },
+            "navItems": [
+                { "id": "home", "label": "Home", "type": "section" }
+            ]
+        });
+        assert!(
+            validate_header_structure(&content_section).is_ok(),
+            "Should accept type='section' without other target fields"
+        );
+
+        // Case 3: Link with 'value' field (e.g. from some frontend logic)
+        let content_value = json!({
+            "brand": { "name": "Test" },
+            "navItems": [
+                { "id": "2", "label": "About", "value": "about-us" }
+            ]
+        });
+        assert!(
+            validate_header_structure(&content_value).is_ok(),
+            "Should accept 'value' field as target"
+        );
+
+        // Case 4: Invalid item (missing target)
+        let content_invalid = json!({
+            "brand": { "name": "Test" },
+            "navItems": [
+                { "id": "3", "label": "Invalid" }
+            ]
+        });
+        assert!(validate_header_structure(&content_invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_login_structure() {
+        // Case 1: Valid login content
+        let content_valid = json!({
+            "title": "Login",
+            "subtitle": "Welcome back"
+        });
+        assert!(validate_login_structure(&content_valid).is_ok());
+
+        // Case 2: Missing title
+        let content_invalid = json!({
+            "subtitle": "Welcome back"
+        });
+        assert!(validate_login_structure(&content_invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_header_structure_rejects_empty_target() {
+        for (target, expected) in [
+            (
+                json!({ "slug": "" }),
+                "Navigation item 1: 'slug' must not be empty",
+            ),
+            (
+                json!({ "slug": "   " }),
+                "Navigation item 1: 'slug' must not be empty",
+            ),
+            (
+                json!({ "href": " " }),
+                "Navigation item 1: 'href' must not be empty",
+            ),
+            (
+                json!({ "path": "" }),
+                "Navigation item 1: 'path' must not be empty",
+            ),
+            // An empty target is rejected even when another target is set.
+            (
+                json!({ "type": "section", "value": "" }),
+                "Navigation item 1: 'value' must not be empty",
+            ),
+        ] {
+            let mut item = json!({ "id": "2", "label": "Broken" });
+            item.as_object_mut()
+                .unwrap()
+                .extend(target.as_object().unwrap().clone());
+            let content = json!({
+                "brand": { "name": "Test" },
+                "navItems": [{ "id": "1", "label": "Ok", "path": "/" }, item]
+            });
+            assert_eq!(validate_header_structure(&content).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_validate_header_structure_checks_target_formats() {
+        let header = |item: Value| json!({ "brand": {}, "navItems": [item] });
+
+        assert!(validate_header_structure(&header(
+            json!({ "id": "a", "label": "A", "href": "https://example.com" })
+        ))
+        .is_ok());
+        assert!(validate_header_structure(&header(
+            json!({ "id": "a", "label": "A", "href": "mailto:info@example.com" })
+        ))
+        .is_ok());
+        assert!(validate_header_structure(&header(
+            json!({ "id": "a", "label": "A", "slug": "grundlagen" })
+        ))
+        .is_ok());
+
+        for item in [
+            json!({ "id": "a", "label": "A", "href": "javascript:alert(1)" }),
+            json!({ "id": "a", "label": "A", "href": "example.com" }),
+            json!({ "id": "a", "label": "A", "path": "blog" }),
+            json!({ "id": "a", "label": "A", "slug": "Not A Slug" }),
+            json!({ "id": "a", "label": "A", "slug": 5 }),
+        ] {
+            let err = validate_header_structure(&header(item.clone())).unwrap_err();
+            assert!(err.starts_with("Navigation item 0"), "{item}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_validate_footer_structure_checks_links() {
+        let (_, seeded) = crate::db::seed::default_site_content()
+            .into_iter()
+            .find(|(section, _)| *section == "footer")
+            .expect("seeded footer");
+        assert!(validate_footer_structure(&seeded).is_ok());
+
+        let mut empty_target = seeded.clone();
+        empty_target["quickLinks"][2] =
+            json!({ "label": "Praxis", "target": { "type": "section", "value": " " } });
+        assert_eq!(
+            validate_footer_structure(&empty_target).unwrap_err(),
+            "Quick link 2: 'target.value' must be a non-empty string"
+        );
+
+        let mut no_target = seeded.clone();
+        no_target["quickLinks"][0] = json!({ "label": "Nirgendwo" });
+        assert!(validate_footer_structure(&no_target)
+            .unwrap_err()
+            .starts_with("Quick link 0"));
+
+        let mut bad_contact = seeded;
+        bad_contact["contactLinks"][1]["href"] = json!("javascript:alert(1)");
+        assert_eq!(
+            validate_footer_structure(&bad_contact).unwrap_err(),
+            "Contact link 1: 'href' must start with http://, https:// or mailto:"
+        );
+    }
+
+    #[test]
+    fn test_validate_grundlagen_page_structure() {
+        let (_, seeded) = crate::db::seed::default_site_content()
+            .into_iter()
+            .find(|(section, _)| *section == "grundlagen_page")
+            .expect("seeded grundlagen_page");
+        assert!(validate_grundlagen_page_structure(&seeded).is_ok());
+
+        let mut missing_cta = seeded.clone();
+        missing_cta.as_object_mut().unwrap().remove("cta");
+        assert!(validate_grundlagen_page_structure(&missing_cta).is_err());
+
+        let mut bad_highlights = seeded.clone();
+        bad_highlights["highlights"] = json!({ "title": "x" });
+        assert!(validate_grundlagen_page_structure(&bad_highlights).is_err());
+
+        let mut bad_modules = seeded;
+        bad_modules["modules"]["items"] = json!([1, 2]);
+        assert!(validate_grundlagen_page_structure(&bad_modules).is_err());
+    }
+
+    fn seeded(section: &str) -> Value {
+        crate::db::seed::default_site_content()
+            .into_iter()
+            .find(|(name, _)| *name == section)
+            .map(|(_, content)| content)
+            .unwrap_or_else(|| panic!("seeded {section}"))
+    }
+
+    #[test]
+    fn test_validate_stats_structure() {
+        assert!(validate_stats_structure(&seeded("stats")).is_ok());
+        assert!(validate_stats_structure(&json!({
+            "items": [{ "value": "12", "label": "Kurse", "icon": "Terminal" }]
+        }))
+        .is_ok());
+
+        let cases = [
+            (json!("10k+"), "Missing required field 'items'"),
+            (
+                json!([{ "value": "1", "label": "x" }]),
+                "Missing required field 'items'",
+            ),
+            (json!({ "items": "x" }), "Field 'items' must be an array"),
+            (json!({ "items": ["x"] }), "Stat 0 must be an object"),
+            (
+                json!({ "items": [{ "value": "1", "label": "a" }, { "label": "b" }] }),
+                "Stat 1: 'value' must be a non-empty string",
+            ),
+            (
+                json!({ "items": [{ "value": 5, "label": "a" }] }),
+                "Stat 0: 'value' must be a non-empty string",
+            ),
+            (
+                json!({ "items": [{ "value": "1", "label": " " }] }),
+                "Stat 0: 'label' must be a non-empty string",
+            ),
+            (
+                json!({ "items": [{ "value": "1", "label": "a", "icon": 3 }] }),
+                "Stat 0: 'icon' must be a string",
+            ),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(validate_stats_structure(&content).unwrap_err(), expected);
+        }
+
+        let err = validate_stats_structure(&json!({
+            "items": [{ "value": "1", "label": "a", "icon": "Rocket" }]
+        }))
+        .unwrap_err();
+        assert!(err.starts_with("Stat 0: Invalid icon 'Rocket'"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_cta_section_structure() {
+        let valid = seeded("cta_section");
+        assert!(validate_cta_section_structure(&valid).is_ok());
+
+        let mut with_secondary = valid.clone();
+        with_secondary["secondary"] = json!({ "label": "Kontakt", "href": "mailto:a@b.de" });
+        assert!(validate_cta_section_structure(&with_secondary).is_ok());
+
+        let mut no_title = valid.clone();
+        no_title["title"] = json!("");
+        let mut bad_description = valid.clone();
+        bad_description["description"] = json!(["x"]);
+        let mut no_primary = valid.clone();
+        no_primary.as_object_mut().unwrap().remove("primary");
+        let mut primary_string = valid.clone();
+        primary_string["primary"] = json!("/blog");
+        let mut primary_without_label = valid.clone();
+        primary_without_label["primary"] = json!({ "path": "/blog" });
+        let mut primary_without_target = valid.clone();
+        primary_without_target["primary"] = json!({ "label": "Los" });
+        let mut bad_secondary = valid.clone();
+        bad_secondary["secondary"] = json!({ "label": "Los", "href": "javascript:alert(1)" });
+
+        let cases = [
+            (json!("Los"), "Expected JSON object"),
+            (no_title, "Field 'title' must be a non-empty string"),
+            (bad_description, "Field 'description' must be a string"),
+            (no_primary, "Missing required field 'primary'"),
+            (primary_string, "CTA link 'primary' must be an object"),
+            (
+                primary_without_label,
+                "CTA link 'primary': 'label' must be a non-empty string",
+            ),
+            (
+                primary_without_target,
+                "CTA link 'primary' needs a 'target', 'path', 'href' or 'slug'",
+            ),
+            (
+                bad_secondary,
+                "CTA link 'secondary': 'href' must start with http://, https:// or mailto:",
+            ),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(
+                validate_cta_section_structure(&content).unwrap_err(),
+                expected
+            );
+        }
+    }
+}
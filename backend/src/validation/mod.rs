@@ -0,0 +1,47 @@
+//! Field rules shared by the HTTP API and the content importer.
+//!
+//! Every check answers with the message the API sends back for a bad
+//! request, as a plain `String`, so code that has no request to answer (the
+//! `import_content` binary, seeding) applies exactly the rules the handlers
+//! do. Normalizers return the value as it is stored: trimmed, and lowercased
+//! where the API lowercases.
+//!
+//! [`request`] states which of these rules each request body obeys, so a
+//! handler refuses a body with every broken field at once.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+pub mod content;
+pub mod page_schema;
+pub mod pages;
+pub mod posts;
+pub mod request;
+pub mod tutorials;
+pub mod users;
+
+pub use request::{Validate, ValidatedJson, Violations};
+
+const MAX_SLUG_LENGTH: usize = 100;
+
+fn slug_regex() -> &'static Regex {
+    static SLUG_RE: OnceLock<Regex> = OnceLock::new();
+    SLUG_RE.get_or_init(|| Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)*$").expect("valid slug regex"))
+}
+
+/// Validates a slug for use in URLs.
+pub fn validate_slug(slug: &str) -> Result<(), String> {
+    if slug.len() > MAX_SLUG_LENGTH {
+        return Err(format!(
+            "Invalid slug. Maximum length is {MAX_SLUG_LENGTH} characters: '{slug}'"
+        ));
+    }
+
+    if slug_regex().is_match(slug) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid slug. Only lowercase letters, numbers and single hyphens allowed: '{slug}'"
+        ))
+    }
+}
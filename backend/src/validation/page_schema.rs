@@ -0,0 +1,499 @@
+//! Structural validation for page `hero` and `layout` payloads.
+//!
+//! Both fields are stored as free-form JSON, so a typo would otherwise only
+//! surface when the public site fails to render the page. The validators here
+//! describe the shapes the frontend understands and report the JSON pointer of
+//! the first violation. Unknown keys are logged but accepted so newer
+//! frontends can ship additional fields ahead of the backend.
+
+use crate::validation::tutorials::validate_icon;
+use serde_json::{Map, Value};
+use std::fmt;
+
+const HERO_TEXT_FIELDS: &[&str] = &[
+    "badge",
+    "badgeText",
+    "subtitle",
+    "description",
+    "backgroundGradient",
+    "gradient",
+];
+const HERO_CTA_FIELDS: &[&str] = &["primaryCta", "secondaryCta"];
+
+const ABOUT_SECTION_FIELDS: &[&str] = &["title"];
+const POSTS_SECTION_FIELDS: &[&str] = &[
+    "title",
+    "emptyTitle",
+    "emptyMessage",
+    "countLabelSingular",
+    "countLabelPlural",
+];
+
+const CTA_TARGET_TYPES: &[&str] = &["section", "route", "page", "url"];
+const INFOBOX_VARIANTS: &[&str] = &["info", "tip", "warning"];
+
+/// Block types allowed in `layout.blocks`, with their required and optional props.
+const BLOCK_TYPES: &[(&str, &[&str], &[&str])] = &[
+    ("text", &["content"], &["title"]),
+    ("cta", &["label", "target"], &["description"]),
+    ("infobox", &["title", "content"], &["variant"]),
+    ("image", &["src"], &["alt", "caption"]),
+    ("ref", &["block"], &[]),
+];
+
+/// First schema violation found in a payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    /// RFC 6901 JSON pointer to the offending value (`""` is the root).
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{pointer}: {}", self.message)
+    }
+}
+
+fn violation(pointer: &str, message: impl Into<String>) -> SchemaViolation {
+    SchemaViolation {
+        pointer: pointer.to_string(),
+        message: message.into(),
+    }
+}
+
+fn child_pointer(parent: &str, key: &str) -> String {
+    format!("{parent}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn warn_unknown_keys(obj: &Map<String, Value>, known: &[&str], pointer: &str, field: &str) {
+    for key in obj.keys().filter(|key| !known.contains(&key.as_str())) {
+        tracing::warn!(
+            "Ignoring unknown key '{}' in page {} at {}",
+            key,
+            field,
+            if pointer.is_empty() { "/" } else { pointer }
+        );
+    }
+}
+
+fn expect_object<'a>(
+    value: &'a Value,
+    pointer: &str,
+) -> Result<&'a Map<String, Value>, SchemaViolation> {
+    value
+        .as_object()
+        .ok_or_else(|| violation(pointer, "must be an object"))
+}
+
+fn optional_string(
+    obj: &Map<String, Value>,
+    key: &str,
+    pointer: &str,
+) -> Result<(), SchemaViolation> {
+    match obj.get(key) {
+        None | Some(Value::Null) | Some(Value::String(_)) => Ok(()),
+        Some(_) => Err(violation(&child_pointer(pointer, key), "must be a string")),
+    }
+}
+
+fn required_string(
+    obj: &Map<String, Value>,
+    key: &str,
+    pointer: &str,
+) -> Result<(), SchemaViolation> {
+    let field_pointer = child_pointer(pointer, key);
+    match obj.get(key) {
+        Some(Value::String(text)) if !text.trim().is_empty() => Ok(()),
+        Some(Value::String(_)) => Err(violation(&field_pointer, "must not be empty")),
+        Some(_) => Err(violation(&field_pointer, "must be a string")),
+        None => Err(violation(&field_pointer, "is required")),
+    }
+}
+
+fn validate_cta_target(value: &Value, pointer: &str) -> Result<(), SchemaViolation> {
+    let obj = expect_object(value, pointer)?;
+    required_string(obj, "type", pointer)?;
+    required_string(obj, "value", pointer)?;
+
+    let target_type = obj.get("type").and_then(Value::as_str).unwrap_or_default();
+    if !CTA_TARGET_TYPES.contains(&target_type) {
+        return Err(violation(
+            &child_pointer(pointer, "type"),
+            format!("must be one of {CTA_TARGET_TYPES:?}"),
+        ));
+    }
+
+    warn_unknown_keys(obj, &["type", "value"], pointer, "CTA target");
+    Ok(())
+}
+
+fn validate_cta(value: &Value, pointer: &str) -> Result<(), SchemaViolation> {
+    let obj = expect_object(value, pointer)?;
+    required_string(obj, "label", pointer)?;
+    let target = obj
+        .get("target")
+        .ok_or_else(|| violation(&child_pointer(pointer, "target"), "is required"))?;
+    validate_cta_target(target, &child_pointer(pointer, "target"))?;
+
+    warn_unknown_keys(obj, &["label", "target"], pointer, "hero CTA");
+    Ok(())
+}
+
+fn validate_hero_title(value: &Value, pointer: &str) -> Result<(), SchemaViolation> {
+    match value {
+        Value::Null | Value::String(_) => Ok(()),
+        Value::Object(obj) => {
+            required_string(obj, "line1", pointer)?;
+            optional_string(obj, "line2", pointer)?;
+            warn_unknown_keys(obj, &["line1", "line2"], pointer, "hero title");
+            Ok(())
+        }
+        _ => Err(violation(
+            pointer,
+            "must be a string or an object with 'line1'",
+        )),
+    }
+}
+
+/// Validates a page hero object. `null` is accepted as "no hero".
+pub fn validate_hero(value: &Value) -> Result<(), SchemaViolation> {
+    if value.is_null() {
+        return Ok(());
+    }
+    let obj = expect_object(value, "")?;
+
+    for key in HERO_TEXT_FIELDS {
+        optional_string(obj, key, "")?;
+    }
+
+    if let Some(title) = obj.get("title") {
+        validate_hero_title(title, "/title")?;
+    }
+
+    match obj.get("icon") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(icon)) => {
+            validate_icon(icon).map_err(|err| violation("/icon", err))?;
+        }
+        Some(_) => return Err(violation("/icon", "must be a string")),
+    }
+
+    for key in HERO_CTA_FIELDS {
+        match obj.get(*key) {
+            None | Some(Value::Null) => {}
+            Some(cta) => validate_cta(cta, &child_pointer("", key))?,
+        }
+    }
+
+    let known: Vec<&str> = HERO_TEXT_FIELDS
+        .iter()
+        .chain(HERO_CTA_FIELDS)
+        .chain(&["title", "icon"])
+        .copied()
+        .collect();
+    warn_unknown_keys(obj, &known, "", "hero");
+    Ok(())
+}
+
+fn validate_text_section(
+    value: &Value,
+    fields: &[&str],
+    pointer: &str,
+) -> Result<(), SchemaViolation> {
+    let obj = expect_object(value, pointer)?;
+    for key in fields {
+        optional_string(obj, key, pointer)?;
+    }
+    warn_unknown_keys(obj, fields, pointer, "layout section");
+    Ok(())
+}
+
+fn validate_block(value: &Value, pointer: &str) -> Result<(), SchemaViolation> {
+    let obj = expect_object(value, pointer)?;
+    required_string(obj, "type", pointer)?;
+
+    let block_type = obj.get("type").and_then(Value::as_str).unwrap_or_default();
+    let (_, required, optional) = BLOCK_TYPES
+        .iter()
+        .find(|(name, _, _)| *name == block_type)
+        .ok_or_else(|| {
+            let names: Vec<&str> = BLOCK_TYPES.iter().map(|(name, _, _)| *name).collect();
+            violation(
+                &child_pointer(pointer, "type"),
+                format!("unknown block type '{block_type}', expected one of {names:?}"),
+            )
+        })?;
+
+    for key in *required {
+        if *key == "target" {
+            let target = obj
+                .get("target")
+                .ok_or_else(|| violation(&child_pointer(pointer, "target"), "is required"))?;
+            validate_cta_target(target, &child_pointer(pointer, "target"))?;
+        } else {
+            required_string(obj, key, pointer)?;
+        }
+    }
+    for key in *optional {
+        optional_string(obj, key, pointer)?;
+    }
+
+    if block_type == "infobox" {
+        if let Some(variant) = obj.get("variant").and_then(Value::as_str) {
+            if !INFOBOX_VARIANTS.contains(&variant) {
+                return Err(violation(
+                    &child_pointer(pointer, "variant"),
+                    format!("must be one of {INFOBOX_VARIANTS:?}"),
+                ));
+            }
+        }
+    }
+
+    let known: Vec<&str> = required
+        .iter()
+        .chain(optional.iter())
+        .chain(&["type", "id"])
+        .copied()
+        .collect();
+    warn_unknown_keys(obj, &known, pointer, "layout block");
+    Ok(())
+}
+
+/// Validates a standalone block, such as an entry of the layout block library.
+pub fn validate_layout_block(value: &Value) -> Result<(), SchemaViolation> {
+    validate_block(value, "")
+}
+
+/// Validates a page layout object. `null` is accepted as "default layout".
+pub fn validate_layout(value: &Value) -> Result<(), SchemaViolation> {
+    if value.is_null() {
+        return Ok(());
+    }
+    let obj = expect_object(value, "")?;
+
+    if let Some(section) = obj.get("aboutSection").filter(|v| !v.is_null()) {
+        validate_text_section(section, ABOUT_SECTION_FIELDS, "/aboutSection")?;
+    }
+    if let Some(section) = obj.get("postsSection").filter(|v| !v.is_null()) {
+        validate_text_section(section, POSTS_SECTION_FIELDS, "/postsSection")?;
+    }
+
+    match obj.get("blocks") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(blocks)) => {
+            for (index, block) in blocks.iter().enumerate() {
+                validate_block(block, &format!("/blocks/{index}"))?;
+            }
+        }
+        Some(_) => return Err(violation("/blocks", "must be an array")),
+    }
+
+    warn_unknown_keys(
+        obj,
+        &["aboutSection", "postsSection", "blocks"],
+        "",
+        "layout",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hero_pointer(value: Value) -> String {
+        validate_hero(&value)
+            .expect_err("hero should be rejected")
+            .pointer
+    }
+
+    fn layout_pointer(value: Value) -> String {
+        validate_layout(&value)
+            .expect_err("layout should be rejected")
+            .pointer
+    }
+
+    #[test]
+    fn test_hero_accepts_frontend_default_and_null() {
+        assert!(validate_hero(&Value::Null).is_ok());
+        assert!(validate_hero(&json!({})).is_ok());
+        assert!(validate_hero(&json!({
+            "badge": "Neue Seite",
+            "title": "Titel der Seite",
+            "subtitle": "Kurzbeschreibung deiner Seite",
+            "backgroundGradient": "from-primary-600 to-primary-700"
+        }))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_hero_accepts_full_payload() {
+        let hero = json!({
+            "badge": "Kurs",
+            "title": { "line1": "Lerne Linux", "line2": "von Grund auf" },
+            "description": "Beschreibung",
+            "icon": "Terminal",
+            "primaryCta": { "label": "Los", "target": { "type": "section", "value": "tutorials" } },
+            "secondaryCta": { "label": "Mehr", "target": { "type": "url", "value": "https://example.com" } }
+        });
+        assert!(validate_hero(&hero).is_ok());
+    }
+
+    #[test]
+    fn test_hero_allows_unknown_keys() {
+        assert!(validate_hero(&json!({ "title": "x", "futureField": [1, 2, 3] })).is_ok());
+    }
+
+    #[test]
+    fn test_hero_rejects_non_object() {
+        assert_eq!(hero_pointer(json!("title")), "");
+        assert_eq!(hero_pointer(json!([1])), "");
+    }
+
+    #[test]
+    fn test_hero_rejects_wrong_field_types() {
+        assert_eq!(hero_pointer(json!({ "badge": 42 })), "/badge");
+        assert_eq!(
+            hero_pointer(json!({ "subtitle": { "text": "x" } })),
+            "/subtitle"
+        );
+        assert_eq!(hero_pointer(json!({ "title": 7 })), "/title");
+        assert_eq!(
+            hero_pointer(json!({ "title": { "line2": "x" } })),
+            "/title/line1"
+        );
+    }
+
+    #[test]
+    fn test_hero_rejects_unknown_icon() {
+        let err = validate_hero(&json!({ "icon": "NotAnIcon" })).unwrap_err();
+        assert_eq!(err.pointer, "/icon");
+        assert!(err.message.contains("NotAnIcon"));
+        assert_eq!(hero_pointer(json!({ "icon": true })), "/icon");
+    }
+
+    #[test]
+    fn test_hero_rejects_malformed_cta() {
+        assert_eq!(hero_pointer(json!({ "primaryCta": "Los" })), "/primaryCta");
+        assert_eq!(
+            hero_pointer(
+                json!({ "primaryCta": { "target": { "type": "section", "value": "x" } } })
+            ),
+            "/primaryCta/label"
+        );
+        assert_eq!(
+            hero_pointer(json!({ "primaryCta": { "label": "Los" } })),
+            "/primaryCta/target"
+        );
+        assert_eq!(
+            hero_pointer(
+                json!({ "secondaryCta": { "label": "Los", "target": { "type": "email", "value": "x" } } })
+            ),
+            "/secondaryCta/target/type"
+        );
+        assert_eq!(
+            hero_pointer(
+                json!({ "secondaryCta": { "label": "Los", "target": { "type": "route", "value": " " } } })
+            ),
+            "/secondaryCta/target/value"
+        );
+    }
+
+    #[test]
+    fn test_layout_accepts_frontend_default_and_null() {
+        assert!(validate_layout(&Value::Null).is_ok());
+        assert!(validate_layout(&json!({})).is_ok());
+        assert!(validate_layout(&json!({
+            "aboutSection": { "title": "Über diese Seite" },
+            "postsSection": {
+                "title": "Beiträge",
+                "emptyTitle": "Keine Beiträge vorhanden",
+                "emptyMessage": "Bald.",
+                "countLabelSingular": "{count} Beitrag",
+                "countLabelPlural": "{count} Beiträge"
+            }
+        }))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_layout_accepts_every_block_type() {
+        let layout = json!({
+            "blocks": [
+                { "type": "text", "content": "Hallo" },
+                { "type": "cta", "label": "Los", "target": { "type": "page", "value": "grundlagen" } },
+                { "type": "infobox", "title": "Hinweis", "content": "Achtung", "variant": "warning" },
+                { "type": "image", "src": "/uploads/a.png", "alt": "Bild", "futureProp": 1 },
+                { "type": "ref", "block": "shell-hint" }
+            ]
+        });
+        assert!(validate_layout(&layout).is_ok());
+    }
+
+    #[test]
+    fn test_layout_rejects_wrong_section_shapes() {
+        assert_eq!(layout_pointer(json!([])), "");
+        assert_eq!(
+            layout_pointer(json!({ "aboutSection": "x" })),
+            "/aboutSection"
+        );
+        assert_eq!(
+            layout_pointer(json!({ "postsSection": { "emptyMessage": 3 } })),
+            "/postsSection/emptyMessage"
+        );
+        assert_eq!(layout_pointer(json!({ "blocks": {} })), "/blocks");
+    }
+
+    #[test]
+    fn test_layout_rejects_invalid_blocks() {
+        assert_eq!(layout_pointer(json!({ "blocks": ["text"] })), "/blocks/0");
+        assert_eq!(
+            layout_pointer(json!({ "blocks": [{ "content": "x" }] })),
+            "/blocks/0/type"
+        );
+        assert_eq!(
+            layout_pointer(
+                json!({ "blocks": [{ "type": "text", "content": "ok" }, { "type": "video", "src": "x" }] })
+            ),
+            "/blocks/1/type"
+        );
+        assert_eq!(
+            layout_pointer(json!({ "blocks": [{ "type": "text" }] })),
+            "/blocks/0/content"
+        );
+        assert_eq!(
+            layout_pointer(json!({ "blocks": [{ "type": "cta", "label": "Los" }] })),
+            "/blocks/0/target"
+        );
+        assert_eq!(
+            layout_pointer(
+                json!({ "blocks": [{ "type": "infobox", "title": "a", "content": "b", "variant": "danger" }] })
+            ),
+            "/blocks/0/variant"
+        );
+        assert_eq!(
+            layout_pointer(json!({ "blocks": [{ "type": "image", "src": "/a.png", "alt": 5 }] })),
+            "/blocks/0/alt"
+        );
+        assert_eq!(
+            layout_pointer(json!({ "blocks": [{ "type": "ref", "block": "" }] })),
+            "/blocks/0/block"
+        );
+    }
+
+    #[test]
+    fn test_violation_display_and_pointer_escaping() {
+        assert_eq!(
+            violation("", "must be an object").to_string(),
+            "/: must be an object"
+        );
+        assert_eq!(child_pointer("/a", "b/c~d"), "/a/b~1c~0d");
+    }
+}
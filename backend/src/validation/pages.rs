@@ -0,0 +1,201 @@
+//! Page fields: slug, texts, visibility, SEO fields, and the `hero` and
+//! `layout` JSON.
+
+use crate::validation::page_schema;
+use crate::models::{PAGE_VISIBILITY_AUTHENTICATED, PAGE_VISIBILITY_PUBLIC};
+use serde_json::Value;
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const MAX_DESCRIPTION_LEN: usize = 1000;
+pub const MAX_NAV_LABEL_LEN: usize = 100;
+pub const MAX_JSON_BYTES: usize = 200_000;
+pub const MAX_META_TITLE_LEN: usize = 120;
+pub const MAX_META_DESCRIPTION_LEN: usize = 300;
+pub const MAX_OG_IMAGE_LEN: usize = 500;
+
+/// Trimmed and lowercased; the URL rules are checked when it is stored.
+pub fn normalize_slug(slug: &str) -> Result<String, String> {
+    let slug = slug.trim().to_lowercase();
+    if slug.is_empty() {
+        return Err("Slug cannot be empty".to_string());
+    }
+    Ok(slug)
+}
+
+pub fn normalize_title(title: &str) -> Result<String, String> {
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+    if title.len() > MAX_TITLE_LEN {
+        return Err(format!("Title too long (max {MAX_TITLE_LEN} characters)"));
+    }
+    Ok(title)
+}
+
+pub fn normalize_description(description: &str) -> Result<String, String> {
+    let description = description.trim().to_string();
+    if description.len() > MAX_DESCRIPTION_LEN {
+        return Err(format!(
+            "Description too long (max {MAX_DESCRIPTION_LEN} characters)"
+        ));
+    }
+    Ok(description)
+}
+
+/// Blank labels are stored as `NULL`, so the title is shown instead.
+pub fn normalize_nav_label(label: Option<String>) -> Result<Option<String>, String> {
+    let Some(label) = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+    else {
+        return Ok(None);
+    };
+    if label.len() > MAX_NAV_LABEL_LEN {
+        return Err(format!(
+            "Navigation label too long (max {MAX_NAV_LABEL_LEN} characters)"
+        ));
+    }
+    Ok(Some(label))
+}
+
+pub fn normalize_visibility(visibility: &str) -> Result<String, String> {
+    let normalized = visibility.trim().to_lowercase();
+    if normalized == PAGE_VISIBILITY_PUBLIC || normalized == PAGE_VISIBILITY_AUTHENTICATED {
+        Ok(normalized)
+    } else {
+        Err(format!(
+            "Visibility must be '{PAGE_VISIBILITY_PUBLIC}' or '{PAGE_VISIBILITY_AUTHENTICATED}'"
+        ))
+    }
+}
+
+/// Trims an optional SEO text field; blank values are stored as `NULL` so the
+/// global `site_meta` defaults apply.
+pub fn normalize_meta_text(
+    value: Option<String>,
+    label: &str,
+    max_len: usize,
+) -> Result<Option<String>, String> {
+    let Some(trimmed) = value
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    if trimmed.chars().count() > max_len {
+        return Err(format!("{label} too long (max {max_len} characters)"));
+    }
+
+    Ok(Some(trimmed))
+}
+
+pub fn normalize_meta_title(value: Option<String>) -> Result<Option<String>, String> {
+    normalize_meta_text(value, "Meta title", MAX_META_TITLE_LEN)
+}
+
+pub fn normalize_meta_description(value: Option<String>) -> Result<Option<String>, String> {
+    normalize_meta_text(value, "Meta description", MAX_META_DESCRIPTION_LEN)
+}
+
+/// Share images must be an uploaded file or an absolute https URL.
+pub fn normalize_og_image(value: Option<String>) -> Result<Option<String>, String> {
+    let Some(image) = normalize_meta_text(value, "Share image URL", MAX_OG_IMAGE_LEN)? else {
+        return Ok(None);
+    };
+
+    let allowed_prefix = image.starts_with("/uploads/") || image.starts_with("https://");
+    let has_unsafe_chars = image
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '<' | '>'));
+
+    if !allowed_prefix || has_unsafe_chars || image.contains("..") {
+        return Err("Share image must be an /uploads/ path or an https:// URL".to_string());
+    }
+
+    Ok(Some(image))
+}
+
+fn validate_json_size(value: &Value, field: &str) -> Result<(), String> {
+    match serde_json::to_string(value) {
+        Ok(serialized) if serialized.len() <= MAX_JSON_BYTES => Ok(()),
+        Ok(_) => Err(format!(
+            "{field} JSON exceeds maximum size of {MAX_JSON_BYTES} bytes"
+        )),
+        Err(err) => Err(format!("Invalid {field} JSON: {err}")),
+    }
+}
+
+fn validate_json(
+    value: &Value,
+    field: &str,
+    validate: fn(&Value) -> Result<(), page_schema::SchemaViolation>,
+) -> Result<(), String> {
+    validate_json_size(value, field)?;
+    validate(value).map_err(|violation| format!("Invalid {field} at {violation}"))
+}
+
+pub fn validate_hero(hero: &Value) -> Result<(), String> {
+    validate_json(hero, "hero", page_schema::validate_hero)
+}
+
+pub fn validate_layout(layout: &Value) -> Result<(), String> {
+    validate_json(layout, "layout", page_schema::validate_layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_seo_fields_are_validated() {
+        assert!(normalize_og_image(Some("https://cdn.example.com/a.png".to_string())).is_ok());
+        for invalid in [
+            "http://example.com/a.png",
+            "javascript:alert(1)",
+            "/uploads/../secret",
+            "/uploads/a b.png",
+            "/static/a.png",
+        ] {
+            assert!(
+                normalize_og_image(Some(invalid.to_string())).is_err(),
+                "{invalid}"
+            );
+        }
+
+        let long_title = "x".repeat(MAX_META_TITLE_LEN + 1);
+        assert!(normalize_meta_title(Some(long_title)).is_err());
+        assert_eq!(normalize_meta_description(Some("  ".to_string())), Ok(None));
+    }
+
+    #[test]
+    fn test_visibility_is_validated() {
+        assert_eq!(
+            normalize_visibility(" Authenticated ").unwrap(),
+            "authenticated"
+        );
+        assert_eq!(normalize_visibility("public").unwrap(), "public");
+        assert!(normalize_visibility("private").is_err());
+    }
+
+    #[test]
+    fn test_texts_are_trimmed_and_bounded() {
+        assert_eq!(normalize_slug(" Grundlagen ").unwrap(), "grundlagen");
+        assert!(normalize_slug("  ").is_err());
+        assert_eq!(normalize_title(" Titel ").unwrap(), "Titel");
+        assert!(normalize_title(&"x".repeat(MAX_TITLE_LEN + 1)).is_err());
+        assert_eq!(normalize_nav_label(Some(" ".to_string())), Ok(None));
+        assert!(normalize_nav_label(Some("x".repeat(MAX_NAV_LABEL_LEN + 1))).is_err());
+    }
+
+    #[test]
+    fn test_hero_and_layout_follow_the_schema() {
+        assert!(validate_hero(&json!({})).is_ok());
+        assert!(validate_layout(&json!({ "sections": [] })).is_ok());
+        assert!(validate_hero(&json!("nope"))
+            .unwrap_err()
+            .starts_with("Invalid hero at"));
+    }
+}
@@ -0,0 +1,49 @@
+//! Post fields: title, slug, excerpt and Markdown body.
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const MAX_SLUG_LEN: usize = 100;
+pub const MAX_EXCERPT_LEN: usize = 500;
+pub const MAX_CONTENT_LEN: usize = 100_000;
+
+pub fn sanitize_slug(slug: &str) -> String {
+    slug.trim().to_lowercase()
+}
+
+pub fn validate_post_fields(
+    title: &str,
+    slug: &str,
+    excerpt: Option<&str>,
+    content: &str,
+) -> Result<(), String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+    if title.len() > MAX_TITLE_LEN {
+        return Err(format!("Title too long (max {MAX_TITLE_LEN} characters)"));
+    }
+
+    let slug = sanitize_slug(slug);
+    if slug.is_empty() {
+        return Err("Slug cannot be empty".to_string());
+    }
+    if slug.len() > MAX_SLUG_LEN {
+        return Err(format!("Slug too long (max {MAX_SLUG_LEN} characters)"));
+    }
+
+    if let Some(excerpt) = excerpt {
+        if excerpt.len() > MAX_EXCERPT_LEN {
+            return Err(format!(
+                "Excerpt too long (max {MAX_EXCERPT_LEN} characters)"
+            ));
+        }
+    }
+
+    if content.len() > MAX_CONTENT_LEN {
+        return Err(format!(
+            "Content too long (max {MAX_CONTENT_LEN} characters)"
+        ));
+    }
+
+    Ok(())
+}
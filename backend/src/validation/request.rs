@@ -0,0 +1,381 @@
+//! Declarative checks for request bodies.
+//!
+//! A request type states its rules once, field by field, in an impl of
+//! [`Validate`]:
+//!
+//! ```ignore
+//! impl Validate for CreateTutorialRequest {
+//!     fn validate(&self, v: &mut Violations) {
+//!         v.field("title", "Title", &self.title).required().max_len(200);
+//!         v.field("icon", "Icon", &self.icon).rule(validate_icon);
+//!     }
+//! }
+//! ```
+//!
+//! Handlers take the body as [`ValidatedJson`], which runs every rule before
+//! the handler does and answers with one [`AppError::invalid_fields`] naming
+//! each broken field, not just the first. The rules of the sibling modules
+//! plug in with [`Field::rule`] and [`Violations::check`], so the importer and
+//! the API still share them.
+
+use crate::error::{AppError, FieldError};
+use axum::{
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+    Json,
+};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use std::ops::RangeInclusive;
+
+/// The rules of a request body.
+pub trait Validate {
+    /// Records every rule `self` breaks in `violations`.
+    fn validate(&self, violations: &mut Violations);
+
+    /// All broken rules as one error, if any.
+    fn check(&self) -> Result<(), AppError> {
+        let mut violations = Violations::default();
+        self.validate(&mut violations);
+        violations.into_result()
+    }
+}
+
+/// The broken rules of one request, in the order they were checked.
+#[derive(Debug, Default)]
+pub struct Violations {
+    errors: Vec<FieldError>,
+}
+
+impl Violations {
+    /// Starts the rules of a text field. `label` names it in messages.
+    pub fn field<'a>(
+        &'a mut self,
+        name: &'static str,
+        label: &'static str,
+        value: &'a str,
+    ) -> Field<'a> {
+        self.optional(name, label, Some(value))
+    }
+
+    /// Like [`Violations::field`], but a missing value passes every rule.
+    pub fn optional<'a>(
+        &'a mut self,
+        name: &'static str,
+        label: &'static str,
+        value: Option<&'a str>,
+    ) -> Field<'a> {
+        Field {
+            violations: self,
+            name,
+            label,
+            value,
+            broken: false,
+        }
+    }
+
+    /// Records the outcome of a rule that is not about one text, such as a
+    /// list or a JSON document.
+    pub fn check<T>(&mut self, name: &str, result: Result<T, String>) {
+        if let Err(message) = result {
+            self.add(name, message);
+        }
+    }
+
+    /// Requires a number within `range`, when there is one.
+    pub fn range(
+        &mut self,
+        name: &str,
+        label: &str,
+        value: Option<i64>,
+        range: RangeInclusive<i64>,
+    ) {
+        if let Some(value) = value.filter(|value| !range.contains(value)) {
+            self.add(
+                name,
+                format!(
+                    "{label} must be between {} and {} (got {value})",
+                    range.start(),
+                    range.end()
+                ),
+            );
+        }
+    }
+
+    pub fn add(&mut self, name: &str, message: impl Into<String>) {
+        self.errors.push(FieldError::new(name, message));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::invalid_fields(self.errors))
+        }
+    }
+}
+
+/// The rules of one text field. A field reports only its first broken rule,
+/// so each field appears at most once in the error.
+///
+/// Length rules look at the trimmed text, as handlers store it trimmed;
+/// [`Field::rule`] gets it as sent.
+pub struct Field<'a> {
+    violations: &'a mut Violations,
+    name: &'static str,
+    label: &'static str,
+    value: Option<&'a str>,
+    broken: bool,
+}
+
+impl Field<'_> {
+    fn test(mut self, rule: impl FnOnce(&str) -> Result<(), String>) -> Self {
+        if self.broken {
+            return self;
+        }
+        if let Some(value) = self.value {
+            if let Err(message) = rule(value) {
+                self.violations.add(self.name, message);
+                self.broken = true;
+            }
+        }
+        self
+    }
+
+    pub fn required(self) -> Self {
+        let label = self.label;
+        self.test(|value| {
+            if value.trim().is_empty() {
+                Err(format!("{label} cannot be empty"))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// At most `max` characters, so umlauts count once like any letter.
+    pub fn max_len(self, max: usize) -> Self {
+        let label = self.label;
+        self.test(|value| {
+            if value.trim().chars().count() > max {
+                Err(format!("{label} too long (max {max} characters)"))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// At most `max` bytes, for bodies where the limit guards storage size.
+    pub fn max_bytes(self, max: usize) -> Self {
+        let label = self.label;
+        self.test(|value| {
+            if value.trim().len() > max {
+                Err(format!("{label} too long (max {max} characters)"))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    pub fn matches(self, pattern: &Regex, message: &str) -> Self {
+        self.test(|value| {
+            if pattern.is_match(value) {
+                Ok(())
+            } else {
+                Err(message.to_string())
+            }
+        })
+    }
+
+    pub fn one_of(self, allowed: &[&str]) -> Self {
+        let label = self.label;
+        self.test(|value| {
+            if allowed.contains(&value) {
+                Ok(())
+            } else {
+                Err(format!("{label} must be one of: {}", allowed.join(", ")))
+            }
+        })
+    }
+
+    /// Applies a rule of the sibling modules, such as
+    /// [`super::validate_slug`]; normalizers fit too, their value is dropped.
+    pub fn rule<T>(self, rule: impl FnOnce(&str) -> Result<T, String>) -> Self {
+        self.test(|value| rule(value).map(drop))
+    }
+}
+
+/// A JSON body that passed its [`Validate`] rules.
+///
+/// Malformed JSON is refused as by [`Json`]; a body that breaks rules with a
+/// 400 listing each broken field.
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        value.check().map_err(IntoResponse::into_response)?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Note {
+        slug: String,
+        text: Option<String>,
+        kind: String,
+        rank: Option<i64>,
+    }
+
+    impl Validate for Note {
+        fn validate(&self, v: &mut Violations) {
+            v.field("slug", "Slug", &self.slug)
+                .required()
+                .rule(super::super::validate_slug);
+            v.optional("text", "Text", self.text.as_deref())
+                .required()
+                .max_len(5);
+            v.field("kind", "Kind", &self.kind)
+                .one_of(&["tip", "warning"]);
+            v.range("rank", "Rank", self.rank, 0..=9);
+        }
+    }
+
+    fn note(slug: &str, text: Option<&str>, kind: &str, rank: Option<i64>) -> Note {
+        Note {
+            slug: slug.to_string(),
+            text: text.map(str::to_string),
+            kind: kind.to_string(),
+            rank,
+        }
+    }
+
+    #[test]
+    fn test_each_broken_field_is_reported_once() {
+        let cases: &[(Note, &[(&str, &str)])] = &[
+            (note("grundlagen", Some("kurz"), "tip", Some(3)), &[]),
+            (note("grundlagen", None, "warning", None), &[]),
+            (
+                note("", Some("kurz"), "tip", None),
+                &[("slug", "Slug cannot be empty")],
+            ),
+            (
+                note("Gross", Some("  "), "tip", None),
+                &[
+                    (
+                        "slug",
+                        "Invalid slug. Only lowercase letters, numbers and single hyphens \
+                         allowed: 'Gross'",
+                    ),
+                    ("text", "Text cannot be empty"),
+                ],
+            ),
+            (
+                note("a", Some(" äöüäöü "), "hint", Some(10)),
+                &[
+                    ("text", "Text too long (max 5 characters)"),
+                    ("kind", "Kind must be one of: tip, warning"),
+                    ("rank", "Rank must be between 0 and 9 (got 10)"),
+                ],
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let errors = match input.check() {
+                Ok(()) => Vec::new(),
+                Err(err) => err.field_errors().to_vec(),
+            };
+            let expected: Vec<FieldError> = expected
+                .iter()
+                .map(|(field, message)| FieldError::new(field, *message))
+                .collect();
+            assert_eq!(errors, expected, "{}", input.slug);
+        }
+    }
+
+    #[test]
+    fn test_patterns_and_lengths() {
+        let digits = Regex::new(r"^[0-9]+$").unwrap();
+        let mut v = Violations::default();
+        v.field("code", "Code", "12a")
+            .matches(&digits, "Code must be digits");
+        // Six bytes, but three characters
+        v.field("short", "Short", " äöü ").max_len(3);
+        v.field("name", "Name", "Größe").max_len(4);
+        v.field("body", "Body", "Größe").max_bytes(5);
+        v.field("ok", "Ok", "123")
+            .matches(&digits, "unused")
+            .max_len(3);
+        assert_eq!(
+            v.into_result().unwrap_err().field_errors(),
+            [
+                FieldError::new("code", "Code must be digits"),
+                FieldError::new("name", "Name too long (max 4 characters)"),
+                FieldError::new("body", "Body too long (max 5 characters)"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extractor_refuses_broken_bodies() {
+        let app = Router::new().route(
+            "/",
+            post(|ValidatedJson(note): ValidatedJson<Note>| async move { note.slug }),
+        );
+        let send = |body: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, body)
+            }
+        };
+
+        let (status, body) = send(r#"{"slug": "ok", "kind": "tip"}"#).await;
+        assert_eq!((status, body.as_ref()), (StatusCode::OK, b"ok".as_ref()));
+
+        let (status, body) = send(r#"{"slug": "", "kind": "note", "rank": -1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let fields: Vec<&str> = json["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|err| err["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["slug", "kind", "rank"]);
+        assert_eq!(json["code"], "validation_failed");
+
+        let (status, _) = send(r#"{"slug": 1}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,158 @@
+//! Tutorial fields: id, texts, icon, gradient color and topics.
+
+use std::collections::HashSet;
+
+pub fn validate_tutorial_id(id: &str) -> Result<(), String> {
+    // Check length bounds to prevent buffer overflow attacks
+    if id.is_empty() || id.len() > 100 {
+        return Err("Invalid tutorial ID".to_string());
+    }
+
+    // Ensure only safe characters for database and URL usage
+    if !id
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err("Tutorial ID contains invalid characters".to_string());
+    }
+    Ok(())
+}
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const MAX_DESCRIPTION_LEN: usize = 1000;
+pub const MAX_CONTENT_LEN: usize = 100_000;
+
+pub fn validate_tutorial_data(title: &str, description: &str, content: &str) -> Result<(), String> {
+    let title_trimmed = title.trim();
+    if title_trimmed.is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+    if title_trimmed.chars().count() > MAX_TITLE_LEN {
+        return Err(format!("Title too long (max {MAX_TITLE_LEN} characters)"));
+    }
+    let description_trimmed = description.trim();
+    if description_trimmed.is_empty() {
+        return Err("Description cannot be empty".to_string());
+    }
+    if description_trimmed.chars().count() > MAX_DESCRIPTION_LEN {
+        return Err(format!(
+            "Description too long (max {MAX_DESCRIPTION_LEN} characters)"
+        ));
+    }
+    let content_trimmed = content.trim();
+    if content_trimmed.is_empty() {
+        return Err("Content cannot be empty".to_string());
+    }
+    if content_trimmed.len() > MAX_CONTENT_LEN {
+        return Err(format!(
+            "Content too long (max {MAX_CONTENT_LEN} characters)"
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_icon(icon: &str) -> Result<(), String> {
+    const ALLOWED_ICONS: &[&str] = &[
+        "Terminal",   // Command line and shell tutorials
+        "FolderTree", // File system and directory tutorials
+        "FileText",   // Text editing and file manipulation
+        "Settings",   // System configuration and settings
+        "Shield",     // Security and permissions
+        "Network",    // Networking and connectivity
+        "Database",   // Database and data management
+        "Server",     // Server administration and services
+    ];
+
+    if ALLOWED_ICONS.contains(&icon) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid icon '{}'. Must be one of: {:?}",
+            icon, ALLOWED_ICONS
+        ))
+    }
+}
+
+pub fn validate_color(color: &str) -> Result<(), String> {
+    const MAX_SEGMENT_LEN: usize = 32;
+
+    fn validate_segment(segment: &str, prefix: &str) -> bool {
+        if !segment.starts_with(prefix) {
+            return false;
+        }
+        let suffix = &segment[prefix.len()..];
+        !suffix.is_empty()
+            && suffix.len() <= MAX_SEGMENT_LEN
+            && suffix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+
+    let segments: Vec<&str> = color.split_whitespace().collect();
+    if !(segments.len() == 2 || segments.len() == 3) {
+        return Err(
+            "Invalid color gradient. Expected Tailwind style 'from-… [via-…] to-…' format."
+                .to_string(),
+        );
+    }
+
+    if !validate_segment(segments[0], "from-") {
+        return Err("Invalid color gradient: 'from-*' segment malformed or too long.".to_string());
+    }
+
+    if segments.len() == 3 {
+        if !validate_segment(segments[1], "via-") {
+            return Err(
+                "Invalid color gradient: 'via-*' segment malformed or too long.".to_string(),
+            );
+        }
+        if !validate_segment(segments[2], "to-") {
+            return Err(
+                "Invalid color gradient: 'to-*' segment malformed or too long.".to_string(),
+            );
+        }
+    } else if !validate_segment(segments[1], "to-") {
+        return Err("Invalid color gradient: 'to-*' segment malformed or too long.".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn sanitize_topics(topics: &[String]) -> Result<Vec<String>, String> {
+    if topics.len() > 20 {
+        return Err("Too many topics (max 20)".to_string());
+    }
+
+    let mut sanitized = Vec::with_capacity(topics.len());
+    let mut seen = HashSet::new();
+
+    for topic in topics {
+        let trimmed = topic.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let limited: String = if trimmed.len() > 100 {
+            trimmed.chars().take(100).collect()
+        } else {
+            trimmed.to_string()
+        };
+
+        let canonical = limited
+            .chars()
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>();
+
+        if !seen.insert(canonical) {
+            return Err("Duplicate topics are not allowed".to_string());
+        }
+
+        sanitized.push(limited);
+    }
+
+    if sanitized.is_empty() {
+        return Err("At least one topic is required".to_string());
+    }
+
+    Ok(sanitized)
+}
@@ -0,0 +1,106 @@
+//! Rules for user accounts.
+
+/// The roles the application knows; `admin` may manage content, `user` is
+/// the default of the `users` table.
+pub const ROLES: [&str; 2] = ["admin", "user"];
+
+/// Validates a username meets security and format requirements.
+///
+/// # Validation Rules
+/// - Not empty
+/// - Length ≤ 50 characters
+/// - Only alphanumeric, underscore, hyphen, and period allowed
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.is_empty() {
+        return Err("Username cannot be empty".to_string());
+    }
+    if username.len() > 50 {
+        return Err("Username too long".to_string());
+    }
+
+    if !username
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        return Err("Username contains invalid characters".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_role(role: &str) -> Result<(), String> {
+    if ROLES.contains(&role) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown role '{}', expected one of {}",
+            role,
+            ROLES.join(", ")
+        ))
+    }
+}
+
+/// Shortest password an account may be given (NIST recommendation).
+pub const MIN_PASSWORD_LENGTH: usize = 12;
+
+/// Validates a password chosen for an account, at `ADMIN_PASSWORD` or when
+/// changing it.
+///
+/// # Validation Rules
+/// - At least [`MIN_PASSWORD_LENGTH`] characters
+/// - At most 128 bytes, the most login accepts
+pub fn validate_new_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters long",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    if password.len() > 128 {
+        return Err("Password too long".to_string());
+    }
+    Ok(())
+}
+
+/// Accepts bcrypt hashes only, so a plaintext password never ends up in the
+/// `password_hash` column.
+pub fn validate_password_hash(hash: &str) -> Result<(), String> {
+    if hash.len() == 60 && hash.parse::<bcrypt::HashParts>().is_ok() {
+        Ok(())
+    } else {
+        Err("Password hash must be a bcrypt hash".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_and_hashes() {
+        assert!(validate_role("admin").is_ok());
+        assert!(validate_role("user").is_ok());
+        assert_eq!(
+            validate_role("root").unwrap_err(),
+            "Unknown role 'root', expected one of admin, user"
+        );
+
+        let hash = bcrypt::hash("correct horse battery", 4).unwrap();
+        assert!(validate_password_hash(&hash).is_ok());
+        assert!(validate_password_hash("correct horse battery").is_err());
+        assert!(validate_password_hash(&hash[..59]).is_err());
+        assert!(validate_password_hash(&format!("$1$${}", &hash[4..])).is_err());
+    }
+
+    #[test]
+    fn test_new_passwords() {
+        assert!(validate_new_password("correct horse").is_ok());
+        // Counted in characters, not bytes
+        assert!(validate_new_password("ääääääääääää").is_ok());
+        assert_eq!(
+            validate_new_password("kurz-123").unwrap_err(),
+            "Password must be at least 12 characters long"
+        );
+        assert!(validate_new_password("äääääää").is_err());
+        assert!(validate_new_password(&"x".repeat(129)).is_err());
+    }
+}
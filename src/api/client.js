@@ -22,6 +22,8 @@ export const getApiBaseUrl = () => {
   return typeof window !== 'undefined' ? '/api' : 'http://localhost:8489/api'
 }
 const API_BASE_URL = getApiBaseUrl()
+// Largest `limit` the list endpoints accept
+const MAX_PAGE_LIMIT = 100
 const isBinaryBody = (body) => {
   if (!body || typeof body !== 'object') {
     return false
@@ -221,6 +223,8 @@ class ApiClient {
           payload?.error || payload?.message || response.statusText || 'Request failed',
         )
         error.status = response.status
+        error.payload = payload
+        error.requestId = payload?.request_id || response.headers.get('x-request-id')
         throw error
       }
       cleanup()
@@ -261,8 +265,25 @@ class ApiClient {
   async me(options = {}) {
     return this.request('/auth/me', options)
   }
+  // Follows a paginated list endpoint until `has_more` is false.
+  async requestAll(endpoint, options = {}) {
+    const items = []
+    const separator = endpoint.includes('?') ? '&' : '?'
+    for (;;) {
+      const page = await this.request(
+        `${endpoint}${separator}limit=${MAX_PAGE_LIMIT}&offset=${items.length}`,
+        options,
+      )
+      const pageItems = Array.isArray(page?.items) ? page.items : []
+      items.push(...pageItems)
+      if (!page?.has_more || pageItems.length === 0) {
+        return { items, total: page?.total ?? items.length }
+      }
+    }
+  }
   async getTutorials(options = {}) {
-    return this.request('/tutorials', options)
+    const { items } = await this.requestAll('/tutorials', options)
+    return items
   }
   async getTutorial(id, options = {}) {
     return this.request(`/tutorials/${id}`, options)
@@ -287,7 +308,7 @@ class ApiClient {
       ...options,
     })
   }
-  async listTutorialComments(tutorialId, { limit, offset, ...options } = {}) {
+  async listTutorialComments(tutorialId, { limit, offset, sort, cursor, ...options } = {}) {
     if (!tutorialId) {
       throw new Error('tutorialId is required')
     }
@@ -295,6 +316,8 @@ class ApiClient {
     const params = new URLSearchParams()
     if (limit !== undefined) params.append('limit', limit)
     if (offset !== undefined) params.append('offset', offset)
+    if (sort !== undefined) params.append('sort', sort)
+    if (cursor !== undefined) params.append('cursor', cursor)
     const queryString = params.toString()
     const endpoint = `/tutorials/${encodedTutorialId}/comments${queryString ? `?${queryString}` : ''}`
     return this.request(endpoint, options)
@@ -352,7 +375,7 @@ class ApiClient {
     })
   }
   async listPages(options = {}) {
-    return this.request('/pages', options)
+    return this.requestAll('/pages', options)
   }
   async createPage(payload, options = {}) {
     return this.request('/pages', {
@@ -378,7 +401,7 @@ class ApiClient {
     })
   }
   async listPosts(pageId, options = {}) {
-    return this.request(`/pages/${pageId}/posts`, options)
+    return this.requestAll(`/pages/${pageId}/posts`, options)
   }
   async createPost(pageId, payload, options = {}) {
     return this.request(`/pages/${pageId}/posts`, {
@@ -412,14 +435,31 @@ class ApiClient {
   async listPublishedPages(options = {}) {
     return this.request('/public/published-pages', options)
   }
-  async uploadImage(file, options = {}) {
+  async uploadImages(files, options = {}) {
     const formData = new FormData()
-    formData.append('file', file)
-    return this.request('/upload', {
-      method: 'POST',
-      body: formData,
-      ...options,
-    })
+    for (const file of files) {
+      formData.append('file', file)
+    }
+    try {
+      return await this.request('/upload', {
+        method: 'POST',
+        body: formData,
+        ...options,
+      })
+    } catch (err) {
+      // A batch where every file failed still lists the per-file errors
+      if (Array.isArray(err.payload)) {
+        return err.payload
+      }
+      throw err
+    }
+  }
+  async uploadImage(file, options = {}) {
+    const [result] = await this.uploadImages([file], options)
+    if (!result?.url) {
+      throw new Error(result?.error || 'Upload failed')
+    }
+    return result
   }
 }
 export const api = new ApiClient()
@@ -17,6 +17,7 @@ const Comments = ({ tutorialId, postId }) => {
   const [loadingComments, setLoadingComments] = useState(false);
   const [loadError, setLoadError] = useState(null);
   const [offset, setOffset] = useState(0);
+  const [nextCursor, setNextCursor] = useState(null);
   const [hasMore, setHasMore] = useState(true);
   const [sortOrder, setSortOrder] = useState('newest'); // 'newest' or 'top'
   const [showEmojiPicker, setShowEmojiPicker] = useState(false);
@@ -65,9 +66,14 @@ const Comments = ({ tutorialId, postId }) => {
 
       const params = {
         limit: COMMENTS_PER_PAGE,
-        offset: currentOffset,
         sort: sortOrder
       };
+      // Newest-first pages continue by cursor so new comments do not shift them
+      if (!shouldReset && sortOrder === 'newest' && nextCursor) {
+        params.cursor = nextCursor;
+      } else {
+        params.offset = currentOffset;
+      }
 
       if (isPost) {
         data = await api.listPostComments(contextId, params);
@@ -75,11 +81,12 @@ const Comments = ({ tutorialId, postId }) => {
         data = await api.listTutorialComments(contextId, params);
       }
 
-      const newComments = Array.isArray(data) ? data : [];
+      const newComments = Array.isArray(data?.items) ? data.items : [];
 
       setComments(prev => shouldReset ? newComments : [...prev, ...newComments]);
       setOffset(prev => shouldReset ? newComments.length : prev + newComments.length);
-      setHasMore(newComments.length === COMMENTS_PER_PAGE);
+      setHasMore(Boolean(data?.has_more));
+      setNextCursor(data?.next_cursor ?? null);
 
     } catch (error) {
       console.error('Failed to load comments:', error);
@@ -88,7 +95,7 @@ const Comments = ({ tutorialId, postId }) => {
     } finally {
       setLoadingComments(false);
     }
-  }, [contextId, isPost, offset, sortOrder]);
+  }, [contextId, isPost, offset, nextCursor, sortOrder]);
 
   // Initial load and when sort changes
   useEffect(() => {
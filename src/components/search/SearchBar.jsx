@@ -3,6 +3,10 @@ import { Search, X, Tag } from 'lucide-react';
 import { useNavigate } from 'react-router-dom';
 import PropTypes from 'prop-types';
 import { api } from '../../api/client';
+
+// The dropdown shows the best matches only
+const SEARCH_RESULT_LIMIT = 20;
+
 const SearchBar = ({ onClose }) => {
   const [query, setQuery] = useState('');
   const [results, setResults] = useState([]);
@@ -78,7 +82,7 @@ const SearchBar = ({ onClose }) => {
     const timeoutId = setTimeout(async () => {
       setIsLoading(true);
       try {
-        const params = new URLSearchParams({ q: query });
+        const params = new URLSearchParams({ q: query, limit: SEARCH_RESULT_LIMIT });
         if (selectedTopic) {
           params.append('topic', selectedTopic);
         }
@@ -86,7 +90,7 @@ const SearchBar = ({ onClose }) => {
           cacheBust: false,
           signal: controller.signal
         })
-        setResults(Array.isArray(data) ? data : [])
+        setResults(Array.isArray(data?.items) ? data.items : [])
       } catch (error) {
         if (error.name !== 'AbortError') {
           console.error('Search failed:', error);